@@ -6,3 +6,4 @@ mkdir -p release
 ~/.cargo/bin/cross build --release --target x86_64-pc-windows-gnu
 mv target/x86_64-unknown-linux-gnu/release/fleet-renderer release
 mv target/x86_64-pc-windows-gnu/release/fleet-renderer.exe release
+cp -r models textures release
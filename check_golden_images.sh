@@ -0,0 +1,15 @@
+#!/bin/sh
+
+# Plays back each replay in golden_images and compares the last frame against the png next to it.
+# Pass --bless to overwrite the pngs after an intended change to the output.
+
+cargo build --release || exit 1
+
+status=0
+
+for replay in golden_images/*.ron
+do
+./target/release/fleet-project --replay $replay --golden-image ${replay%.ron}.png --lighting-preset deep-space --disable-audio "$@" || status=1
+done
+
+exit $status
@@ -1,16 +1,18 @@
 use components_and_resources::{gpu_structs::ColouredVertex, utils::uniform_sphere_distribution};
-use rand::rngs::ThreadRng;
 use rand::Rng;
 use spade::delaunay::FloatDelaunayTriangulation;
 use tint::Colour;
 use ultraviolet::{Rotor3, Vec2, Vec3};
 
 // https://www.redblobgames.com/x/1842-delaunay-voronoi-sphere/#delaunay
-pub fn make_background(rng: &mut ThreadRng) -> (Vec<ColouredVertex>, Vec3) {
+pub fn make_background<R: Rng>(
+    rng: &mut R,
+    nebula_brightness: std::ops::Range<f32>,
+) -> (Vec<ColouredVertex>, Vec3) {
     let nebula_colour = Colour::new(
         rng.gen_range(0.0..360.0),
         1.0,
-        rng.gen_range(0.5..1.0),
+        rng.gen_range(nebula_brightness) as f64,
         0.75,
     )
     .from_hsv();
@@ -66,6 +68,57 @@ pub fn make_background(rng: &mut ThreadRng) -> (Vec<ColouredVertex>, Vec3) {
     (vertices, ambient)
 }
 
+// Bake a low-res irradiance cubemap of the nebula, relative to the flat ambient light.
+// Each texel is the cosine-weighted average of the nebula around that direction, halved so
+// that tints of up to 2x fit in an `Rgba8Unorm` texture. Faces are in the standard
+// +X, -X, +Y, -Y, +Z, -Z order.
+pub fn irradiance_cubemap(
+    background: &[ColouredVertex],
+    ambient: Vec3,
+    face_size: u32,
+) -> Vec<[u8; 4]> {
+    let face_directions: [fn(f32, f32) -> Vec3; 6] = [
+        |s, t| Vec3::new(1.0, -t, -s),
+        |s, t| Vec3::new(-1.0, -t, s),
+        |s, t| Vec3::new(s, 1.0, t),
+        |s, t| Vec3::new(s, -1.0, -t),
+        |s, t| Vec3::new(s, -t, 1.0),
+        |s, t| Vec3::new(-s, -t, -1.0),
+    ];
+
+    let to_coord = |i: u32| (i as f32 + 0.5) / face_size as f32 * 2.0 - 1.0;
+
+    face_directions
+        .iter()
+        .flat_map(|face_direction| {
+            (0..face_size).flat_map(move |y| {
+                (0..face_size).map(move |x| face_direction(to_coord(x), to_coord(y)).normalized())
+            })
+        })
+        .map(|direction| {
+            let (sum, total_weight) =
+                background
+                    .iter()
+                    .fold((Vec3::zero(), 0.0), |(sum, total_weight), vertex| {
+                        let weight = direction.dot(vertex.position.normalized()).max(0.0);
+                        (sum + vertex.colour * weight, total_weight + weight)
+                    });
+
+            let irradiance =
+                sum / total_weight.max(f32::EPSILON) * 3.0 + Vec3::broadcast(1.0 / 10.0);
+
+            let tint = irradiance / ambient / 2.0;
+
+            [
+                (tint.x.min(1.0) * 255.0) as u8,
+                (tint.y.min(1.0) * 255.0) as u8,
+                (tint.z.min(1.0) * 255.0) as u8,
+                255,
+            ]
+        })
+        .collect()
+}
+
 #[derive(PartialEq, Debug, Clone, Copy)]
 struct ProjectedVertex {
     unit_pos: Vec3,
@@ -74,7 +127,7 @@ struct ProjectedVertex {
 }
 
 impl ProjectedVertex {
-    fn rand(rng: &mut ThreadRng, rotation: Rotor3, colour: Vec3) -> Self {
+    fn rand<R: Rng>(rng: &mut R, rotation: Rotor3, colour: Vec3) -> Self {
         use noise::{NoiseFn, Seedable};
 
         let unit_pos = uniform_sphere_distribution(rng);
@@ -120,8 +173,11 @@ impl spade::PointN for ProjectedVertex {
 
 impl spade::TwoDimensional for ProjectedVertex {}
 
-pub fn create_stars(rng: &mut ThreadRng) -> impl Iterator<Item = ColouredVertex> + '_ {
-    (0..2000).flat_map(move |_| {
+pub fn create_stars<R: Rng>(
+    rng: &mut R,
+    count: usize,
+) -> impl Iterator<Item = ColouredVertex> + '_ {
+    (0..count).flat_map(move |_| {
         let unit_pos = uniform_sphere_distribution(rng);
         star_points(unit_pos, 1.0, Vec3::one())
     })
@@ -0,0 +1,94 @@
+use crate::components::ModelId;
+use std::path::{Path, PathBuf};
+
+pub(crate) fn model_name(model_id: ModelId) -> &'static str {
+    match model_id {
+        ModelId::Carrier => "carrier",
+        ModelId::Fighter => "fighter",
+        ModelId::Miner => "miner",
+        ModelId::Explosion => "explosion",
+        ModelId::Asteroid => "asteroid",
+        ModelId::DefensePlatform => "defense_platform",
+        ModelId::Shipyard => "shipyard",
+        ModelId::Refinery => "refinery",
+        ModelId::Frigate => "frigate",
+        ModelId::Destroyer => "destroyer",
+        ModelId::Battleship => "battleship",
+        ModelId::TradingStation => "trading_station",
+        ModelId::Minelayer => "minelayer",
+        ModelId::RepairShip => "repair_ship",
+        ModelId::GasHarvester => "gas_harvester",
+        ModelId::ConstructionShip => "construction_ship",
+        ModelId::Freighter => "freighter",
+    }
+}
+
+// The directory that the game's models and textures are loaded from at startup, laid out like
+// the `models` and `textures` directories of the repository.
+pub struct Assets {
+    directory: PathBuf,
+}
+
+impl Assets {
+    pub fn new(directory: PathBuf) -> Self {
+        Self { directory }
+    }
+
+    pub fn model(&self, model_id: ModelId) -> PathBuf {
+        // Structures, warships and support ships reuse other models until they get models of
+        // their own. Trading stations are scaled-up carriers, as they're the closest thing to a
+        // space station, and support ships are converted miners.
+        let name = match model_id {
+            ModelId::DefensePlatform => "fighter",
+            ModelId::Shipyard | ModelId::TradingStation => "carrier",
+            ModelId::Refinery
+            | ModelId::Minelayer
+            | ModelId::RepairShip
+            | ModelId::GasHarvester
+            | ModelId::ConstructionShip
+            | ModelId::Freighter => "miner",
+            ModelId::Frigate | ModelId::Destroyer | ModelId::Battleship => "carrier",
+            _ => model_name(model_id),
+        };
+
+        self.directory.join("models").join(format!("{}.glb", name))
+    }
+
+    pub fn texture(&self, name: &str) -> PathBuf {
+        self.directory
+            .join("textures")
+            .join(format!("{}.png", name))
+    }
+}
+
+// Missing assets are the most likely thing to go wrong, so the error says which file it was.
+pub fn read(path: &Path) -> anyhow::Result<Vec<u8>> {
+    std::fs::read(path)
+        .map_err(|error| anyhow::anyhow!("Reading '{}' failed: {}", path.display(), error))
+}
+
+#[test]
+fn test_asset_paths() {
+    let assets = Assets::new(PathBuf::from("assets"));
+
+    assert_eq!(
+        assets.model(ModelId::Explosion),
+        Path::new("assets/models/explosion.glb")
+    );
+    assert_eq!(
+        assets.model(ModelId::Shipyard),
+        Path::new("assets/models/carrier.glb")
+    );
+    assert_eq!(
+        assets.model(ModelId::TradingStation),
+        Path::new("assets/models/carrier.glb")
+    );
+    assert_eq!(
+        assets.model(ModelId::Minelayer),
+        Path::new("assets/models/miner.glb")
+    );
+    assert_eq!(
+        assets.texture("mined_out_asteroid"),
+        Path::new("assets/textures/mined_out_asteroid.png")
+    );
+}
@@ -0,0 +1,202 @@
+use crate::components::ShipType;
+use std::fmt;
+
+const SHIP_TYPES: [ShipType; 12] = [
+    ShipType::Carrier,
+    ShipType::Fighter,
+    ShipType::Miner,
+    ShipType::Minelayer,
+    ShipType::RepairShip,
+    ShipType::GasHarvester,
+    ShipType::Freighter,
+    ShipType::ConstructionShip,
+    ShipType::Frigate,
+    ShipType::Destroyer,
+    ShipType::Battleship,
+    ShipType::Drone,
+];
+
+// Accepts names like `fighters`, `Fighter` or `repair_ships`.
+fn parse_ship_type(name: &str) -> anyhow::Result<ShipType> {
+    let name: String = name
+        .chars()
+        .filter(|c| c.is_alphanumeric())
+        .collect::<String>()
+        .to_lowercase();
+
+    SHIP_TYPES
+        .iter()
+        .copied()
+        .find(|ship_type| {
+            let type_name = format!("{:?}", ship_type).to_lowercase();
+            name == type_name || name == type_name + "s"
+        })
+        .ok_or_else(|| anyhow::anyhow!("'{}' isn't a type of ship", name))
+}
+
+pub type Fleet = Vec<(ShipType, u32)>;
+
+// The two fleets to pit against each other, given on the command line as something like
+// `fighters=20 vs carriers=2,frigates=4`.
+#[derive(Debug, PartialEq)]
+pub struct Matchup {
+    pub sides: [Fleet; 2],
+}
+
+impl Matchup {
+    pub fn parse(args: &[String]) -> anyhow::Result<Self> {
+        let joined = args.join(" ");
+        let mut sides = joined.split(" vs ");
+
+        let (first, second) = match (sides.next(), sides.next(), sides.next()) {
+            (Some(first), Some(second), None) => (first, second),
+            _ => return Err(anyhow::anyhow!("Expected two fleets separated by 'vs'")),
+        };
+
+        Ok(Self {
+            sides: [Self::parse_fleet(first)?, Self::parse_fleet(second)?],
+        })
+    }
+
+    fn parse_fleet(fleet: &str) -> anyhow::Result<Fleet> {
+        let fleet = fleet
+            .split(|c: char| c == ',' || c.is_whitespace())
+            .filter(|entry| !entry.is_empty())
+            .map(|entry| {
+                let mut parts = entry.split('=');
+
+                match (parts.next(), parts.next(), parts.next()) {
+                    (Some(name), Some(count), None) => Ok((parse_ship_type(name)?, count.parse()?)),
+                    _ => Err(anyhow::anyhow!("Expected 'ship=count', not '{}'", entry)),
+                }
+            })
+            .collect::<anyhow::Result<Fleet>>()?;
+
+        if fleet.iter().all(|&(_, count)| count == 0) {
+            return Err(anyhow::anyhow!("Each side needs at least one ship"));
+        }
+
+        Ok(fleet)
+    }
+}
+
+struct FleetDisplay<'a>(&'a Fleet);
+
+impl<'a> fmt::Display for FleetDisplay<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for (i, (ship_type, count)) in self.0.iter().enumerate() {
+            if i > 0 {
+                write!(f, ", ")?;
+            }
+            write!(f, "{} {:?}", count, ship_type)?;
+        }
+
+        Ok(())
+    }
+}
+
+// Win rates and losses over every run of a matchup. A run is a draw if both sides still have
+// ships when time runs out, or if they wipe each other out on the same tick.
+pub struct BalanceReport {
+    matchup: Matchup,
+    runs: u32,
+    wins: [u32; 2],
+    total_losses: [u32; 2],
+}
+
+impl BalanceReport {
+    pub fn new(matchup: Matchup) -> Self {
+        Self {
+            matchup,
+            runs: 0,
+            wins: [0; 2],
+            total_losses: [0; 2],
+        }
+    }
+
+    pub fn matchup(&self) -> &Matchup {
+        &self.matchup
+    }
+
+    pub fn record(&mut self, survivors: [u32; 2]) {
+        self.runs += 1;
+
+        for side in 0..2 {
+            let fleet_size: u32 = self.matchup.sides[side].iter().map(|&(_, n)| n).sum();
+            self.total_losses[side] += fleet_size - survivors[side];
+        }
+
+        match survivors {
+            [0, 0] => {}
+            [_, 0] => self.wins[0] += 1,
+            [0, _] => self.wins[1] += 1,
+            _ => {}
+        }
+    }
+
+    fn percentage(&self, count: u32) -> f32 {
+        count as f32 / self.runs.max(1) as f32 * 100.0
+    }
+}
+
+impl fmt::Display for BalanceReport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "{} runs", self.runs)?;
+
+        for side in 0..2 {
+            writeln!(
+                f,
+                "{}: won {:.1}%, lost {:.2} ships on average",
+                FleetDisplay(&self.matchup.sides[side]),
+                self.percentage(self.wins[side]),
+                self.total_losses[side] as f32 / self.runs.max(1) as f32,
+            )?;
+        }
+
+        write!(
+            f,
+            "Draws: {:.1}%",
+            self.percentage(self.runs - self.wins[0] - self.wins[1])
+        )
+    }
+}
+
+#[test]
+fn test_balance_report() {
+    let args = [
+        "fighters=20,",
+        "carrier=1",
+        "vs",
+        "Frigates=3",
+        "repair_ships=2",
+    ]
+    .iter()
+    .map(|arg| arg.to_string())
+    .collect::<Vec<_>>();
+
+    let matchup = Matchup::parse(&args).unwrap();
+    assert_eq!(
+        matchup.sides,
+        [
+            vec![(ShipType::Fighter, 20), (ShipType::Carrier, 1)],
+            vec![(ShipType::Frigate, 3), (ShipType::RepairShip, 2)],
+        ]
+    );
+
+    let invalid = |args: &[&str]| {
+        Matchup::parse(&args.iter().map(|arg| arg.to_string()).collect::<Vec<_>>()).is_err()
+    };
+    assert!(invalid(&["bombers=10", "vs", "fighters=10"]));
+    assert!(invalid(&["fighters=10"]));
+    assert!(invalid(&["fighters=0", "vs", "fighters=10"]));
+
+    let mut report = BalanceReport::new(matchup);
+    report.record([21, 0]);
+    report.record([15, 0]);
+    report.record([0, 1]);
+    report.record([1, 1]);
+
+    assert_eq!(report.wins, [2, 1]);
+    assert_eq!(report.total_losses, [6 + 21 + 20, 5 + 5 + 4 + 4]);
+    assert!(report.to_string().ends_with("Draws: 25.0%"));
+}
@@ -1,53 +1,61 @@
-use crate::resources::BoundingBox;
+use crate::resources::{AreaOfEffect, BoundingBox, Cooldown, SimClock, SmallRng, Timer};
 use crate::utils::uniform_sphere_distribution;
 use bevy_ecs::prelude::Bundle;
 use bevy_ecs::prelude::Entity;
 use rand::Rng;
+use serde::{Deserialize, Serialize};
 use std::collections::VecDeque;
 use std::f32::consts::TAU;
 use ultraviolet::{Mat3, Rotor3, Vec3};
 
 mod build_queue;
+mod flight_recorder;
 mod functions;
 mod people;
 
 pub use build_queue::*;
+pub use flight_recorder::*;
 pub use functions::*;
 pub use people::*;
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Position(pub Vec3);
+#[derive(Clone, Serialize, Deserialize)]
 pub struct Rotation(pub Rotor3);
 
-#[derive(Default, Debug)]
+#[derive(Default, Debug, Clone, Serialize, Deserialize)]
 pub struct RotationMatrix {
     pub matrix: Mat3,
     pub reversed: Mat3,
     pub rotated_model_bounding_box: BoundingBox,
 }
 
-impl RotationMatrix {
-    pub fn random_for_rendering_only(rng: &mut rand::rngs::SmallRng) -> Self {
-        let rotor = Rotor3::from_angle_plane(
+impl Rotation {
+    pub fn random_for_rendering_only(rng: &mut SmallRng) -> Self {
+        Self(Rotor3::from_angle_plane(
             rng.gen_range(0.0..TAU),
             ultraviolet::Bivec3::from_normalized_axis(uniform_sphere_distribution(rng)),
-        );
-
-        Self {
-            matrix: rotor.into_matrix(),
-            reversed: Default::default(),
-            rotated_model_bounding_box: Default::default(),
-        }
+        ))
     }
 }
 
+#[derive(Clone, Serialize, Deserialize)]
 pub struct Selected;
 
-#[derive(Debug, Clone, Copy, PartialEq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub enum ShipType {
     Carrier,
     Fighter,
     Miner,
+    Minelayer,
+    RepairShip,
+    GasHarvester,
+    Freighter,
+    ConstructionShip,
+    Frigate,
+    Destroyer,
+    Battleship,
+    Drone,
 }
 
 impl ShipType {
@@ -56,11 +64,34 @@ impl ShipType {
             Self::Carrier => 30.0,
             Self::Fighter => 5.0,
             Self::Miner => 7.5,
+            Self::Minelayer => 10.0,
+            Self::RepairShip => 12.5,
+            Self::GasHarvester => 9.0,
+            Self::Freighter => 8.0,
+            Self::ConstructionShip => 10.0,
+            Self::Frigate => 15.0,
+            Self::Destroyer => 22.5,
+            Self::Battleship => 45.0,
+            Self::Drone => 2.5,
         }
     }
 
-    pub fn build_cost(self) -> f32 {
-        self.build_time() * 5.0
+    pub fn build_cost(self) -> BuildCost {
+        // Only the more advanced ships need gas.
+        let gas = match self {
+            Self::Carrier => 50.0,
+            Self::Minelayer => 15.0,
+            Self::RepairShip => 20.0,
+            Self::Frigate => 10.0,
+            Self::Destroyer => 25.0,
+            Self::Battleship => 80.0,
+            _ => 0.0,
+        };
+
+        BuildCost {
+            minerals: self.build_time() * 5.0,
+            gas,
+        }
     }
 
     pub fn model_id(self) -> ModelId {
@@ -68,19 +99,160 @@ impl ShipType {
             Self::Carrier => ModelId::Carrier,
             Self::Fighter => ModelId::Fighter,
             Self::Miner => ModelId::Miner,
+            Self::Minelayer => ModelId::Minelayer,
+            Self::RepairShip => ModelId::RepairShip,
+            Self::GasHarvester => ModelId::GasHarvester,
+            Self::Freighter => ModelId::Freighter,
+            Self::ConstructionShip => ModelId::ConstructionShip,
+            Self::Frigate => ModelId::Frigate,
+            Self::Destroyer => ModelId::Destroyer,
+            Self::Battleship => ModelId::Battleship,
+            // Drones are shrunken fighters.
+            Self::Drone => ModelId::Fighter,
+        }
+    }
+}
+
+// Static structures that construction ships put together on site.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub enum StructureType {
+    DefensePlatform,
+    Shipyard,
+    Refinery,
+}
+
+impl StructureType {
+    pub fn build_time(self) -> f32 {
+        match self {
+            Self::DefensePlatform => 20.0,
+            Self::Shipyard => 40.0,
+            Self::Refinery => 30.0,
+        }
+    }
+
+    // Paid up front when the site is laid out.
+    pub fn cost(self) -> f32 {
+        match self {
+            Self::DefensePlatform => 100.0,
+            Self::Shipyard => 250.0,
+            Self::Refinery => 150.0,
+        }
+    }
+
+    pub fn model_id(self) -> ModelId {
+        match self {
+            Self::DefensePlatform => ModelId::DefensePlatform,
+            Self::Shipyard => ModelId::Shipyard,
+            Self::Refinery => ModelId::Refinery,
+        }
+    }
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct BuildCost {
+    pub minerals: f32,
+    pub gas: f32,
+}
+
+impl std::ops::Add for BuildCost {
+    type Output = Self;
+
+    fn add(self, other: Self) -> Self {
+        Self {
+            minerals: self.minerals + other.minerals,
+            gas: self.gas + other.gas,
         }
     }
 }
 
-#[derive(Debug, Copy, Clone, PartialEq)]
+impl std::iter::Sum for BuildCost {
+    fn sum<I: Iterator<Item = Self>>(iter: I) -> Self {
+        iter.fold(Self::default(), |a, b| a + b)
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Serialize, Deserialize)]
 pub enum ModelId {
     Carrier = 0,
     Fighter = 1,
     Miner = 2,
     Explosion = 3,
     Asteroid = 4,
+    DefensePlatform = 5,
+    Shipyard = 6,
+    Refinery = 7,
+    Frigate = 8,
+    Destroyer = 9,
+    Battleship = 10,
+    TradingStation = 11,
+    Minelayer = 12,
+    RepairShip = 13,
+    GasHarvester = 14,
+    ConstructionShip = 15,
+    Freighter = 16,
+}
+
+impl ModelId {
+    pub fn is_capital(self) -> bool {
+        matches!(
+            self,
+            Self::Carrier | Self::Frigate | Self::Destroyer | Self::Battleship
+        )
+    }
+
+    // Decides who comes off worse when ships ram each other.
+    pub fn mass(self) -> f32 {
+        match self {
+            Self::Fighter => 1.0,
+            Self::Miner
+            | Self::Minelayer
+            | Self::RepairShip
+            | Self::GasHarvester
+            | Self::ConstructionShip
+            | Self::Freighter => 3.0,
+            Self::Frigate => 10.0,
+            Self::Destroyer => 16.0,
+            Self::Carrier => 25.0,
+            Self::Battleship => 40.0,
+            Self::Explosion => 0.0,
+            Self::Asteroid
+            | Self::DefensePlatform
+            | Self::Shipyard
+            | Self::Refinery
+            | Self::TradingStation => 100.0,
+        }
+    }
+}
+
+// Two ships running into each other along the line between them. The lighter ship takes more
+// of the damage and gets knocked back further.
+pub struct Collision {
+    pub damage: [f32; 2],
+    // How much each ship's velocity changes, away from the other ship.
+    pub knockback: [f32; 2],
+}
+
+impl Collision {
+    const DAMAGE_PER_SPEED: f32 = 1.0;
+    // Overlapping ships are pushed apart at this speed, even if they weren't moving together.
+    const SEPARATION_SPEED: f32 = 2.0;
+
+    pub fn new(closing_speed: f32, masses: [f32; 2]) -> Self {
+        let total = masses[0] + masses[1];
+        let impact = closing_speed.max(0.0);
+        let change = impact + Self::SEPARATION_SPEED;
+
+        Self {
+            damage: [
+                impact * Self::DAMAGE_PER_SPEED * masses[1] / total,
+                impact * Self::DAMAGE_PER_SPEED * masses[0] / total,
+            ],
+            knockback: [change * masses[1] / total, change * masses[0] / total],
+        }
+    }
 }
 
+#[derive(Clone, Serialize, Deserialize)]
 pub struct Scale(pub f32);
 
 impl Scale {
@@ -88,16 +260,48 @@ impl Scale {
         let range = self.0 + 10.0;
         range * range
     }
+
+    // Miners wait this far out for a mining slot to become free.
+    pub fn queue_range_sq(&self) -> f32 {
+        self.range_sq() * 4.0
+    }
+
+    pub fn mining_slots(&self) -> usize {
+        (self.0 * 2.0) as usize + 2
+    }
+
+    pub fn mining_slot_offset(&self, index: usize) -> Vec3 {
+        let angle = index as f32 / self.mining_slots() as f32 * TAU;
+        Vec3::new(angle.cos(), 0.0, angle.sin()) * (self.0 + 8.0)
+    }
 }
 
+#[derive(Clone, Serialize, Deserialize)]
 pub struct Expands;
 
-pub struct AliveUntil(pub f32);
+#[derive(Clone, Serialize, Deserialize)]
+pub struct AliveUntil(pub Timer);
 
-#[derive(Default)]
+pub struct ImpactEffect {
+    pub ty: ImpactEffectType,
+    pub normal: Vec3,
+    pub spawned_at: f32,
+}
+
+impl ImpactEffect {
+    pub const DURATION: f32 = 0.4;
+}
+
+pub enum ImpactEffectType {
+    // Hull hits. Each spark flies off in its own direction.
+    Sparks { directions: [Vec3; 8] },
+    ShieldRipple,
+}
+
+#[derive(Default, Clone, Serialize, Deserialize)]
 pub struct WorldSpaceBoundingBox(pub BoundingBox);
 
-#[derive(Clone, Copy, Default)]
+#[derive(Clone, Copy, Default, Serialize, Deserialize)]
 pub struct MaxSpeed(pub f32);
 
 impl MaxSpeed {
@@ -106,6 +310,7 @@ impl MaxSpeed {
     }
 }
 
+#[derive(Clone, Serialize, Deserialize)]
 pub struct Spin {
     angle: f32,
     plane: ultraviolet::Bivec3,
@@ -128,59 +333,531 @@ impl Spin {
     }
 }
 
+// Circles around a vertical axis through `center`, like an asteroid belt around a sun.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Orbiting {
+    pub center: Vec3,
+    // In radians per second.
+    pub angular_velocity: f32,
+}
+
+impl Orbiting {
+    pub fn advance(&self, position: Vec3, delta_time: f32) -> Vec3 {
+        let rotation = Rotor3::from_rotation_xz(self.angular_velocity * delta_time);
+        self.center + rotation * (position - self.center)
+    }
+}
+
+// Moves in a straight line until the timer runs out. Used to bring new asteroids in from the edge
+// of the map.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Drifting {
+    pub velocity: Vec3,
+    pub until: Timer,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
 pub struct CameraFollowing;
 
-#[derive(Default)]
+#[derive(Default, Clone, Serialize, Deserialize)]
 pub struct Friendly;
-#[derive(Default)]
+#[derive(Default, Clone, Serialize, Deserialize)]
 pub struct Enemy;
 
+// The side markers as a value, for looking up what each side has.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Side {
+    Friendly,
+    Enemy,
+}
+
+impl Side {
+    pub fn of(friendly: Option<&Friendly>) -> Self {
+        if friendly.is_some() {
+            Self::Friendly
+        } else {
+            Self::Enemy
+        }
+    }
+}
+
+// `Friendly` and `Enemy` are the two sides of the game, whereas these are relative to the local
+// player, who might be on either side in multiplayer. Only input and the HUD should use them.
+#[derive(Clone)]
+pub struct Ours;
+#[derive(Clone)]
+pub struct Theirs;
+
+#[derive(Clone, Serialize, Deserialize)]
 pub struct Evading(pub Entity);
 
-#[derive(Clone, Copy, Default)]
+// How hard, and for how long, a ship throws itself around when it sees fire coming its way.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct EvasiveManeuvers {
+    pub strength: f32,
+    pub duration: f32,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Jinking {
+    pub direction: Vec3,
+    pub strength: f32,
+    pub until: Timer,
+}
+
+impl Jinking {
+    // How far ahead of a projectile to look for ships in its path.
+    pub const WARNING_TIME: f32 = 0.5;
+    // The threat cone widens by this much per unit of distance from the projectile.
+    pub const CONE_SLOPE: f32 = 0.1;
+    pub const CONE_BASE_RADIUS: f32 = 2.0;
+}
+
+#[derive(Clone, Copy, Default, Serialize, Deserialize)]
 pub struct Velocity(pub Vec3);
+#[derive(Clone, Serialize, Deserialize)]
 pub struct StagingPersuitForce(pub Vec3);
+#[derive(Clone, Serialize, Deserialize)]
 pub struct StagingEvasionForce(pub Vec3);
+#[derive(Clone, Serialize, Deserialize)]
 pub struct StagingAvoidanceForce(pub Vec3);
-pub struct RayCooldown(pub f32);
-
+#[derive(Clone, Serialize, Deserialize)]
 pub struct AgroRange(pub f32);
 
-#[derive(Default)]
+#[derive(Default, Clone, Serialize, Deserialize)]
 pub struct CommandQueue(pub VecDeque<Command>);
 
-#[derive(Clone, Copy)]
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
 pub enum Command {
     MoveTo {
         point: Vec3,
         ty: MoveType,
+        // Set when a group should arrive together instead of the fastest ships racing ahead.
+        #[serde(default)]
+        speed_cap: Option<f32>,
     },
     Interact {
         target: Entity,
         ty: InteractionType,
         range_sq: f32,
     },
+    // Hold position until it's time to carry on with the rest of the queue.
+    Wait(WaitFor),
+    // Turn in place until the bow points in this direction.
+    Face {
+        direction: Vec3,
+    },
+}
+
+// Followers in a formation steer towards this offset from the leader so that the group travels
+// together, rather than each ship heading straight for its own point.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct FormationSlot {
+    pub leader: Entity,
+    pub offset: Vec3,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub enum WaitFor {
+    Time(Timer),
+    Mark,
 }
 
-#[derive(Copy, Clone)]
+#[derive(Copy, Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub enum MoveType {
     Normal,
     Attack,
 }
 
-#[derive(Copy, Clone)]
+#[derive(Copy, Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum RulesOfEngagement {
+    HoldFire,
+    // Only pick fights after being hit.
+    ReturnFire,
+    WeaponsFree,
+}
+
+impl Default for RulesOfEngagement {
+    fn default() -> Self {
+        Self::WeaponsFree
+    }
+}
+
+impl RulesOfEngagement {
+    pub const RETURN_FIRE_DURATION: f32 = 5.0;
+
+    pub fn next(self) -> Self {
+        match self {
+            Self::HoldFire => Self::ReturnFire,
+            Self::ReturnFire => Self::WeaponsFree,
+            Self::WeaponsFree => Self::HoldFire,
+        }
+    }
+
+    pub fn to_str(self) -> &'static str {
+        match self {
+            Self::HoldFire => "Hold Fire",
+            Self::ReturnFire => "Return Fire",
+            Self::WeaponsFree => "Weapons Free",
+        }
+    }
+
+    // Ships that are free to fire try to keep their guns on target instead of dodging.
+    pub fn evasion_multiplier(self) -> f32 {
+        match self {
+            Self::HoldFire => 1.5,
+            Self::ReturnFire => 1.0,
+            Self::WeaponsFree => 0.75,
+        }
+    }
+
+    pub fn get(global: Self, rules_override: Option<&RulesOfEngagementOverride>) -> Self {
+        rules_override
+            .map(|rules_override| rules_override.0)
+            .unwrap_or(global)
+    }
+}
+
+// Makes a ship break off and dock at the closest carrier once its health drops below a fraction
+// of its maximum, then fly back to where it left once it's been repaired.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct RetreatPolicy {
+    pub health_threshold: f32,
+    // Where the ship broke off from, while it's retreating.
+    pub retreated_from: Option<Vec3>,
+}
+
+impl RetreatPolicy {
+    pub const DEFAULT_HEALTH_THRESHOLD: f32 = 0.25;
+
+    pub fn new(health_threshold: f32) -> Self {
+        Self {
+            health_threshold,
+            retreated_from: None,
+        }
+    }
+
+    pub fn should_retreat(&self, health: &Health) -> bool {
+        self.retreated_from.is_none() && health.current < health.max * self.health_threshold
+    }
+
+    pub fn ready_to_rejoin(&self, health: &Health) -> bool {
+        self.retreated_from.is_some() && health.current >= health.max
+    }
+}
+
+// How much a ship does without being given orders. Ships without this have local autonomy.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub enum Autonomy {
+    // Never acts on its own, apart from carrying out an attack-move.
+    FullManual,
+    Local,
+    // Chases targets further, finds new asteroids to mine while idle and docks when there's
+    // nothing left to do.
+    High,
+}
+
+impl Default for Autonomy {
+    fn default() -> Self {
+        Self::Local
+    }
+}
+
+impl Autonomy {
+    // How much further than its agro range a ship with high autonomy goes after targets.
+    pub const HIGH_PURSUIT_MULTIPLIER: f32 = 1.5;
+
+    pub fn next(self) -> Self {
+        match self {
+            Self::FullManual => Self::Local,
+            Self::Local => Self::High,
+            Self::High => Self::FullManual,
+        }
+    }
+
+    pub fn to_str(self) -> &'static str {
+        match self {
+            Self::FullManual => "Full Manual",
+            Self::Local => "Local Autonomy",
+            Self::High => "High Autonomy",
+        }
+    }
+
+    pub fn get(autonomy: Option<&Autonomy>) -> Self {
+        autonomy.copied().unwrap_or_default()
+    }
+
+    pub fn acts_alone(self) -> bool {
+        self != Self::FullManual
+    }
+
+    pub fn pursuit_range(self, agro_range: f32) -> f32 {
+        match self {
+            Self::High => agro_range * Self::HIGH_PURSUIT_MULTIPLIER,
+            _ => agro_range,
+        }
+    }
+}
+
+// How far a ship goes in looking for a fight. Ships without this are aggressive.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub enum Stance {
+    Aggressive,
+    // Fights anything that comes close to where it started, but lets targets go once they've
+    // left.
+    Defensive,
+    // Never opens fire unless ordered to attack something.
+    Passive,
+}
+
+impl Default for Stance {
+    fn default() -> Self {
+        Self::Aggressive
+    }
+}
+
+impl Stance {
+    pub const LEASH_RANGE: f32 = 150.0;
+
+    pub fn next(self) -> Self {
+        match self {
+            Self::Aggressive => Self::Defensive,
+            Self::Defensive => Self::Passive,
+            Self::Passive => Self::Aggressive,
+        }
+    }
+
+    pub fn to_str(self) -> &'static str {
+        match self {
+            Self::Aggressive => "Aggressive",
+            Self::Defensive => "Defensive",
+            Self::Passive => "Passive",
+        }
+    }
+
+    pub fn get(stance: Option<&Stance>) -> Self {
+        stance.copied().unwrap_or_default()
+    }
+
+    pub fn picks_targets(self) -> bool {
+        self != Self::Passive
+    }
+
+    // Whether a target is close enough to a defensive ship's starting point to go after.
+    pub fn within_leash(anchor: Vec3, target: Vec3) -> bool {
+        (target - anchor).mag_sq() < Self::LEASH_RANGE.powi(2)
+    }
+}
+
+// Where a defensive ship was when it first picked a fight, which it stays around until it's given
+// new orders.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Leash(pub Vec3);
+
+impl Leash {
+    // How close to its starting point a ship needs to be to count as back.
+    pub const RETURN_DISTANCE: f32 = 20.0;
+}
+
+// How much of its top speed a ship flies at. Ships without this go flat out.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub enum Throttle {
+    Quarter,
+    Half,
+    ThreeQuarters,
+    Full,
+}
+
+impl Default for Throttle {
+    fn default() -> Self {
+        Self::Full
+    }
+}
+
+impl Throttle {
+    pub fn next(self) -> Self {
+        match self {
+            Self::Quarter => Self::Half,
+            Self::Half => Self::ThreeQuarters,
+            Self::ThreeQuarters => Self::Full,
+            Self::Full => Self::Quarter,
+        }
+    }
+
+    pub fn to_str(self) -> &'static str {
+        match self {
+            Self::Quarter => "25%",
+            Self::Half => "50%",
+            Self::ThreeQuarters => "75%",
+            Self::Full => "100%",
+        }
+    }
+
+    pub fn fraction(self) -> f32 {
+        match self {
+            Self::Quarter => 0.25,
+            Self::Half => 0.5,
+            Self::ThreeQuarters => 0.75,
+            Self::Full => 1.0,
+        }
+    }
+
+    pub fn get(throttle: Option<&Throttle>) -> Self {
+        throttle.copied().unwrap_or_default()
+    }
+}
+
+// How a ship picks what to shoot at. Ships without this go for the nearest enemy.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub enum TargetingStance {
+    Nearest,
+    // Weighs up each target so that ships finish off damaged enemies, go after valuable ones and
+    // protect their allies instead of spreading their fire around.
+    FocusFire,
+}
+
+impl Default for TargetingStance {
+    fn default() -> Self {
+        Self::Nearest
+    }
+}
+
+// What's known about a potential target when scoring it.
+pub struct TargetInfo {
+    pub dist_sq: f32,
+    pub health_fraction: f32,
+    pub is_capital: bool,
+    pub attacking_ally: bool,
+}
+
+impl TargetingStance {
+    const CAPITAL_BONUS: f32 = 0.5;
+    const ATTACKING_ALLY_BONUS: f32 = 0.75;
+
+    pub fn next(self) -> Self {
+        match self {
+            Self::Nearest => Self::FocusFire,
+            Self::FocusFire => Self::Nearest,
+        }
+    }
+
+    pub fn to_str(self) -> &'static str {
+        match self {
+            Self::Nearest => "Nearest",
+            Self::FocusFire => "Focus Fire",
+        }
+    }
+
+    pub fn get(stance: Option<&TargetingStance>) -> Self {
+        stance.copied().unwrap_or_default()
+    }
+
+    // Lower scores are better. Focus fire still counts distance, scaled to the agro range, so that
+    // ships don't cross the whole fight to reach a slightly better target.
+    pub fn score(self, target: &TargetInfo, range_sq: f32) -> f32 {
+        match self {
+            Self::Nearest => target.dist_sq,
+            Self::FocusFire => {
+                let mut score = target.dist_sq / range_sq + target.health_fraction;
+
+                if target.is_capital {
+                    score -= Self::CAPITAL_BONUS;
+                }
+
+                if target.attacking_ally {
+                    score -= Self::ATTACKING_ALLY_BONUS;
+                }
+
+                score
+            }
+        }
+    }
+}
+
+// Keeps a ship where it is, only firing at enemies that come into range.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct HoldPosition;
+
+// Takes precedence over the global rules of engagement.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct RulesOfEngagementOverride(pub RulesOfEngagement);
+
+// A note the player has put on a ship to keep track of what it's for. Shown next to the ship and
+// in the list of selected ships.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub enum PlayerTag {
+    Scout,
+    Bait,
+    Reserve,
+    Flagship,
+}
+
+impl PlayerTag {
+    pub const COUNT: usize = 4;
+    pub const ARRAY: [Self; Self::COUNT] = [Self::Scout, Self::Bait, Self::Reserve, Self::Flagship];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Scout => "Scout",
+            Self::Bait => "Bait",
+            Self::Reserve => "Reserve",
+            Self::Flagship => "Flagship",
+        }
+    }
+
+    pub fn colour(self) -> [f32; 4] {
+        match self {
+            Self::Scout => [0.25, 1.0, 1.0, 1.0],
+            Self::Bait => [1.0, 0.5, 0.0, 1.0],
+            Self::Reserve => [0.6, 0.6, 0.6, 1.0],
+            Self::Flagship => [1.0, 0.85, 0.0, 1.0],
+        }
+    }
+
+    // Goes through each of the tags in turn and then back to having none.
+    pub fn next(tag: Option<Self>) -> Option<Self> {
+        match tag {
+            None => Some(Self::ARRAY[0]),
+            Some(tag) => Self::ARRAY.get(tag as usize + 1).copied(),
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub enum InteractionType {
     BeCarriedBy,
     Attack,
     Mine,
+    PickUp,
+    Repair,
+    Salvage,
+    Harvest,
+    Trade,
+    Construct,
 }
 
+#[derive(Clone, Serialize, Deserialize)]
 pub struct CanAttack;
 
-#[derive(Default)]
+// Patches up damaged ships on its own side with a repair beam.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct CanRepair {
+    pub rate: f32,
+    pub range: f32,
+}
+
+impl CanRepair {
+    // How far away an idle repair ship will go looking for something to fix.
+    pub const SEARCH_RANGE: f32 = 150.0;
+}
+
+#[derive(Default, Clone, Serialize, Deserialize)]
 pub struct Carrying(arrayvec::ArrayVec<(Entity, bool), 100>);
 
 impl Carrying {
+    // Ships are patched up in the hangar at this many hit points a second.
+    pub const REPAIR_RATE: f32 = 5.0;
+
     #[must_use]
     pub fn checked_push(&mut self, entity: Entity, priority: bool) -> bool {
         if self.is_full() {
@@ -201,10 +878,24 @@ impl Carrying {
         self.0.iter().map(|&(entity, _)| entity)
     }
 
+    pub fn iter_mut(&mut self) -> impl Iterator<Item = &mut Entity> + '_ {
+        self.0.iter_mut().map(|(entity, _)| entity)
+    }
+
     pub fn drain(&mut self) -> impl Iterator<Item = Entity> + '_ {
         self.0.drain(..).map(|(entity, _)| entity)
     }
 
+    pub fn remove(&mut self, entity: Entity) -> bool {
+        match self.0.iter().position(|&(carried, _)| carried == entity) {
+            Some(index) => {
+                self.0.remove(index);
+                true
+            }
+            None => false,
+        }
+    }
+
     pub fn is_full(&self) -> bool {
         self.0.is_full()
     }
@@ -222,26 +913,567 @@ impl Carrying {
     }
 }
 
+#[derive(Clone, Serialize, Deserialize)]
 pub struct CanBeCarried;
 
-pub struct Health {
-    pub current: f32,
-    pub max: f32,
+#[derive(Clone, Copy, Serialize, Deserialize)]
+pub struct WeaponArc {
+    // In model space.
+    pub direction: Vec3,
+    pub half_angle: f32,
+    pub range: f32,
+    pub cooldown: Cooldown,
 }
 
-impl Health {
-    pub fn new(max: f32) -> Self {
-        Self { max, current: max }
+impl WeaponArc {
+    pub const RELOAD_TIME: f32 = 2.0;
+
+    pub fn world_direction(&self, rotation: &RotationMatrix) -> Vec3 {
+        rotation.matrix * self.direction
+    }
+
+    pub fn contains(&self, rotation: &RotationMatrix, offset: Vec3) -> bool {
+        arc_contains(
+            self.world_direction(rotation),
+            self.half_angle,
+            self.range,
+            offset,
+        )
     }
 }
 
-pub struct Selectable;
+fn arc_contains(direction: Vec3, half_angle: f32, range: f32, offset: Vec3) -> bool {
+    let distance = offset.mag();
 
-#[derive(Debug)]
-pub struct OnBoard(pub Vec<Entity>);
+    distance > 0.0 && distance < range && (offset / distance).dot(direction) >= half_angle.cos()
+}
 
-pub struct CanMine;
-pub struct CanBeMined {
+#[derive(Clone, Serialize, Deserialize)]
+pub struct WeaponArcs(pub Vec<WeaponArc>);
+
+impl WeaponArcs {
+    // Port and starboard batteries that can only be brought to bear side-on.
+    pub fn broadsides(range: f32) -> Self {
+        let arc = |direction| WeaponArc {
+            direction,
+            half_angle: 45.0_f32.to_radians(),
+            range,
+            cooldown: Cooldown::default(),
+        };
+
+        Self(vec![arc(Vec3::unit_x()), arc(-Vec3::unit_x())])
+    }
+
+    // A single arc that covers every direction.
+    pub fn turret(range: f32) -> Self {
+        Self(vec![WeaponArc {
+            direction: Vec3::unit_x(),
+            half_angle: std::f32::consts::PI,
+            range,
+            cooldown: Cooldown::default(),
+        }])
+    }
+}
+
+// Forward-firing guns that shoot at whatever the ship has been ordered to attack.
+#[derive(Clone, Copy, Serialize, Deserialize)]
+pub struct Weapon {
+    pub projectile_speed: f32,
+    pub damage: f32,
+    pub damage_type: DamageType,
+    pub reload_time: f32,
+    pub range: f32,
+    pub cooldown: Cooldown,
+}
+
+impl Weapon {
+    pub const PROJECTILE_LIFETIME: f32 = 10.0;
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Weapons(pub Vec<Weapon>);
+
+// Firing builds up heat, which is slowly vented. A ship that maxes it out has to hold fire until
+// it's mostly cooled down again.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Heat {
+    pub current: f32,
+    pub max: f32,
+    pub overheated: bool,
+}
+
+impl Heat {
+    pub const PER_SHOT: f32 = 2.0;
+    // Beams run much hotter than anything that fires projectiles.
+    pub const BEAM_PER_SECOND: f32 = 3.0;
+    pub const VENT_RATE: f32 = 1.0;
+    // Weapons can be used again once it's vented down to this fraction.
+    const COOLED_FRACTION: f32 = 0.25;
+    // The sun is infinitely far away, so ships are treated as closer to it the further out they
+    // are in its direction. Past this distance, they start picking up heat from it.
+    pub const SUNWARD_DISTANCE: f32 = 250.0;
+    // For an ordinary sun. Lighting presets make theirs hotter or cooler.
+    pub const SUN_HEAT_PER_SECOND: f32 = 1.5;
+
+    pub fn new(max: f32) -> Self {
+        Self {
+            current: 0.0,
+            max,
+            overheated: false,
+        }
+    }
+
+    pub fn can_fire(heat: Option<&Self>) -> bool {
+        heat.map_or(true, |heat| !heat.overheated)
+    }
+
+    pub fn add(&mut self, amount: f32) {
+        self.current = (self.current + amount).min(self.max);
+
+        if self.current >= self.max {
+            self.overheated = true;
+        }
+    }
+
+    pub fn vent(&mut self, amount: f32) {
+        self.current = (self.current - amount).max(0.0);
+
+        if self.current <= self.max * Self::COOLED_FRACTION {
+            self.overheated = false;
+        }
+    }
+
+    pub fn fraction(&self) -> f32 {
+        self.current / self.max
+    }
+
+    // Ramps up over a couple of hundred units past `SUNWARD_DISTANCE`, to `heat_per_second`.
+    pub fn from_sun(position: Vec3, sun_dir: Vec3, heat_per_second: f32) -> f32 {
+        let sunward = position.dot(sun_dir) - Self::SUNWARD_DISTANCE;
+        (sunward / 200.0).max(0.0).min(1.0) * heat_per_second
+    }
+}
+
+// Instead of firing projectiles, a beam stays locked on to its target and damages it for as long
+// as the target stays in the arc and in sight, draining energy as it goes.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct BeamWeapon {
+    // In model space.
+    pub direction: Vec3,
+    pub half_angle: f32,
+    pub range: f32,
+    pub damage_per_second: f32,
+    pub damage_type: DamageType,
+    pub energy: f32,
+    pub max_energy: f32,
+    pub target: Option<Entity>,
+    // Set when the beam runs dry, until it's recharged enough to fire for a while again.
+    #[serde(default)]
+    pub recharging: bool,
+}
+
+impl BeamWeapon {
+    pub const DRAIN_RATE: f32 = 1.0;
+    pub const RECHARGE_RATE: f32 = 0.5;
+    // The fraction of `max_energy` that a beam that's run dry has to get back before firing.
+    pub const RESUME_FRACTION: f32 = 0.5;
+
+    // Fixed along the keel, so the whole ship has to be pointed at the target.
+    pub fn lance(range: f32, damage_per_second: f32, max_energy: f32) -> Self {
+        Self {
+            direction: Vec3::unit_z(),
+            half_angle: 30.0_f32.to_radians(),
+            range,
+            damage_per_second,
+            damage_type: DamageType::Energy,
+            energy: max_energy,
+            max_energy,
+            target: None,
+            recharging: false,
+        }
+    }
+
+    pub fn contains(&self, rotation: &RotationMatrix, offset: Vec3) -> bool {
+        arc_contains(
+            rotation.matrix * self.direction,
+            self.half_angle,
+            self.range,
+            offset,
+        )
+    }
+
+    // Returns whether the beam has the energy to keep firing. It recharges while it isn't, and
+    // once it's run dry it doesn't fire again until it's past `RESUME_FRACTION`, so that it
+    // doesn't flicker on and off with every tick's worth of energy.
+    pub fn drain(&mut self, firing: bool, delta: f32) -> bool {
+        if self.recharging && self.energy >= self.max_energy * Self::RESUME_FRACTION {
+            self.recharging = false;
+        }
+
+        if firing && !self.recharging {
+            self.energy = (self.energy - Self::DRAIN_RATE * delta).max(0.0);
+            self.recharging = self.energy == 0.0;
+            true
+        } else {
+            self.energy = (self.energy + Self::RECHARGE_RATE * delta).min(self.max_energy);
+            false
+        }
+    }
+}
+
+// How much damage a projectile does when it hits.
+#[derive(Clone, Copy, Serialize, Deserialize)]
+pub struct ProjectileDamage(pub f32);
+
+// The ship that fired a projectile, so that it can be credited with the kill.
+#[derive(Clone, Copy, Serialize, Deserialize)]
+pub struct FiredBy(pub Entity);
+
+// Ships that survive long enough to rack up kills get a little better at fighting.
+#[derive(Clone, Default, Serialize, Deserialize)]
+pub struct Veterancy {
+    pub kills: u32,
+}
+
+impl Veterancy {
+    // Kills needed for each rank.
+    const RANKS: [u32; 3] = [3, 8, 15];
+    const DAMAGE_BONUS_PER_RANK: f32 = 0.1;
+    pub const HEALTH_BONUS_PER_RANK: f32 = 0.1;
+
+    pub fn rank(&self) -> usize {
+        Self::RANKS
+            .iter()
+            .filter(|&&kills| self.kills >= kills)
+            .count()
+    }
+
+    // Returns whether the kill was enough for a promotion.
+    pub fn record_kill(&mut self) -> bool {
+        let rank = self.rank();
+        self.kills += 1;
+        self.rank() > rank
+    }
+
+    pub fn damage_multiplier(&self) -> f32 {
+        1.0 + self.rank() as f32 * Self::DAMAGE_BONUS_PER_RANK
+    }
+
+    // Veterans aim at their target instead of firing wherever they happen to be flying.
+    pub fn aims_directly(&self) -> bool {
+        self.rank() >= 2
+    }
+
+    pub fn chevrons(&self) -> &'static str {
+        &"^^^"[..self.rank()]
+    }
+}
+
+// A slower second weapon that fires homing missiles at the closest target in range.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct MissileLauncher {
+    pub range: f32,
+    pub cooldown: Cooldown,
+}
+
+impl MissileLauncher {
+    pub const RELOAD_TIME: f32 = 8.0;
+    pub const LAUNCH_SPEED: f32 = 20.0;
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Missile {
+    pub target: Entity,
+    // In radians per second.
+    pub turn_rate: f32,
+    pub acceleration: f32,
+    pub max_speed: f32,
+    pub trigger_radius: f32,
+    pub blast: AreaOfEffect,
+    #[serde(default = "one")]
+    pub damage_multiplier: f32,
+}
+
+// Missiles saved before weapon research applied to them.
+fn one() -> f32 {
+    1.0
+}
+
+impl Missile {
+    pub const BLAST_DAMAGE: f32 = 40.0;
+    pub const LIFETIME: f32 = 15.0;
+
+    pub fn new(target: Entity) -> Self {
+        Self {
+            target,
+            turn_rate: 2.0,
+            acceleration: 40.0,
+            max_speed: 80.0,
+            trigger_radius: 3.0,
+            blast: AreaOfEffect {
+                radius: 8.0,
+                falloff_bands: 2,
+            },
+            damage_multiplier: 1.0,
+        }
+    }
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Health {
+    pub current: f32,
+    pub max: f32,
+}
+
+impl Health {
+    pub fn new(max: f32) -> Self {
+        Self { max, current: max }
+    }
+}
+
+// Soaks up projectile damage before it reaches the hull. It only starts recharging once the ship
+// hasn't been hit for a while.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Shield {
+    pub current: f32,
+    pub max: f32,
+    pub recharge_rate: f32,
+}
+
+impl Shield {
+    pub const RECHARGE_DELAY: f32 = 3.0;
+    pub const IMPACT_FLASH_DURATION: f32 = 0.3;
+
+    pub fn new(max: f32, recharge_rate: f32) -> Self {
+        Self {
+            current: max,
+            max,
+            recharge_rate,
+        }
+    }
+
+    // Returns the damage that got through to the hull.
+    pub fn absorb(&mut self, damage: f32) -> f32 {
+        let absorbed = damage.min(self.current);
+        self.current -= absorbed;
+        damage - absorbed
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub enum DamageType {
+    Kinetic,
+    Energy,
+    Explosive,
+}
+
+// Projectiles saved before damage types existed were all kinetic.
+impl Default for DamageType {
+    fn default() -> Self {
+        Self::Kinetic
+    }
+}
+
+// The fraction of each type of damage that the hull shrugs off.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Armor {
+    pub kinetic: f32,
+    pub energy: f32,
+    pub explosive: f32,
+}
+
+impl Armor {
+    pub fn damage_multiplier(armor: Option<&Self>, ty: DamageType) -> f32 {
+        let resistance = armor.map_or(0.0, |armor| match ty {
+            DamageType::Kinetic => armor.kinetic,
+            DamageType::Energy => armor.energy,
+            DamageType::Explosive => armor.explosive,
+        });
+
+        1.0 - resistance
+    }
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+// The total time at which the entity was last hit by a projectile.
+pub struct LastHit(pub f32);
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Selectable;
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Scuttling {
+    pub detonates_at: Timer,
+    pub blast: Option<AreaOfEffect>,
+}
+
+impl Scuttling {
+    pub const COUNTDOWN: f32 = 5.0;
+    pub const BLAST_DAMAGE: f32 = 100.0;
+
+    pub fn new(model_id: ModelId, clock: &SimClock) -> Self {
+        // Fighters don't carry enough to make much of a bang.
+        let blast = match model_id {
+            ModelId::Carrier => Some(AreaOfEffect {
+                radius: 30.0,
+                falloff_bands: 3,
+            }),
+            ModelId::Miner
+            | ModelId::Minelayer
+            | ModelId::RepairShip
+            | ModelId::GasHarvester
+            | ModelId::ConstructionShip
+            | ModelId::Freighter => Some(AreaOfEffect {
+                radius: 10.0,
+                falloff_bands: 2,
+            }),
+            _ => None,
+        };
+
+        Self {
+            detonates_at: clock.timer(Self::COUNTDOWN),
+            blast,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OnBoard(pub Vec<Entity>);
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct CanMine;
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct CanHarvest;
+
+// What's left of a destroyed ship. Miners can strip it for minerals before it breaks up.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Wreck {
+    pub minerals: f32,
+}
+
+impl Wreck {
+    pub const LIFETIME: f32 = 120.0;
+    pub const SALVAGE_RANGE: f32 = 15.0;
+    pub const SALVAGE_RATE: f32 = 2.0;
+
+    pub fn new(model_id: ModelId) -> Self {
+        let minerals = match model_id {
+            ModelId::Carrier => 50.0,
+            ModelId::Fighter => 8.0,
+            _ => 12.0,
+        };
+
+        Self { minerals }
+    }
+}
+
+// Claimed by a miner on its way to an asteroid so that miners spread out around it.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct MiningSlot {
+    pub asteroid: Entity,
+    pub index: usize,
+}
+
+// Keeps station around a carrier whenever there's nothing else to do.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Escorting {
+    pub carrier: Entity,
+    pub offset: Vec3,
+}
+
+impl Escorting {
+    // Threats this close to the carrier get intercepted.
+    pub const INTERCEPT_RANGE: f32 = 100.0;
+}
+
+// Flies itself around the carrier that built it, attacking anything that comes close, and
+// docks whenever the carrier pulls back.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Drone {
+    pub mothership: Entity,
+}
+
+impl Drone {
+    pub const ORBIT_RADIUS: f32 = 25.0;
+    // In radians per second.
+    pub const ORBIT_SPEED: f32 = 0.5;
+    // Targets further than this from the mothership are given up on.
+    pub const LEASH_RANGE: f32 = 150.0;
+
+    // A slot at a random point around the mothership's orbit.
+    pub fn orbit_offset(rng: &mut SmallRng) -> Vec3 {
+        Rotor3::from_rotation_xz(rng.gen_range(0.0..TAU)) * Vec3::unit_x() * Self::ORBIT_RADIUS
+    }
+
+    // A carrier is retreating while it's moving without attacking along the way.
+    pub fn is_retreating(mothership_queue: &CommandQueue) -> bool {
+        matches!(
+            mothership_queue.0.front(),
+            Some(Command::MoveTo {
+                ty: MoveType::Normal,
+                ..
+            })
+        )
+    }
+}
+
+// How a carrier handles its fighters without being told. Aggressive carriers launch them once
+// enemies come within range, while passive ones keep them docked until they're unloaded by hand.
+// Either way, badly damaged fighters are called back in for repairs.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub enum CarrierDoctrine {
+    Aggressive,
+    Passive,
+}
+
+impl CarrierDoctrine {
+    // Fighters below this fraction of their health are recalled.
+    pub const RECALL_HEALTH: f32 = 0.35;
+    // Docked fighters are only launched again once they've been repaired to this fraction.
+    pub const LAUNCH_HEALTH: f32 = 0.9;
+
+    pub fn toggled(self) -> Self {
+        match self {
+            Self::Aggressive => Self::Passive,
+            Self::Passive => Self::Aggressive,
+        }
+    }
+
+    pub fn needs_repair(health: &Health) -> bool {
+        health.current < health.max * Self::RECALL_HEALTH
+    }
+
+    pub fn ready_to_launch(health: &Health) -> bool {
+        health.current >= health.max * Self::LAUNCH_HEALTH
+    }
+
+    // Only fighters that are idle or fighting on their own are recalled, so that orders from the
+    // player aren't overridden.
+    pub fn can_recall(queue: &CommandQueue) -> bool {
+        matches!(
+            queue.0.front(),
+            None | Some(Command::Interact {
+                ty: InteractionType::Attack,
+                ..
+            }) | Some(Command::MoveTo {
+                ty: MoveType::Attack,
+                ..
+            })
+        )
+    }
+}
+
+// Where a carrier sends newly built ships.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct RallyPoint(pub Vec3);
+
+// The carrier a miner always takes its minerals back to, instead of the closest one.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct DropOff(pub Entity);
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct CanBeMined {
     pub total: f32,
     pub minerals: f32,
 }
@@ -255,23 +1487,344 @@ impl CanBeMined {
     }
 }
 
+// How many miners are headed for or working an asteroid. Recounted every frame by
+// `count_mining_claims` so that miners can be spread out between asteroids.
+#[derive(Clone, Default, Serialize, Deserialize)]
+pub struct MiningClaims(pub usize);
+
+impl MiningClaims {
+    // Each claim makes the asteroid seem further away. Asteroids without a free mining slot are
+    // only picked when every other asteroid is full too.
+    pub fn priority(&self, distance_sq: f32, scale: &Scale) -> (bool, f32) {
+        let full = self.0 >= scale.mining_slots();
+        (full, distance_sq * ((self.0 + 1) * (self.0 + 1)) as f32)
+    }
+}
+
+// Follows a fixed elliptical orbit so that its position only depends on the time. Miners have to
+// chase it, but it's much richer than an asteroid, and anything it flies through gets hurt.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Comet {
+    pub center: Vec3,
+    pub major_axis: Vec3,
+    pub minor_axis: Vec3,
+    pub period: f32,
+    pub phase: f32,
+    pub radius: f32,
+    pub damage_per_second: f32,
+    // How many minerals each unit mined is worth.
+    pub richness: f32,
+}
+
+impl Comet {
+    pub fn position_at(&self, total_time: f32) -> Vec3 {
+        let angle = total_time / self.period * TAU + self.phase;
+        self.center + self.major_axis * angle.cos() + self.minor_axis * angle.sin()
+    }
+
+    pub fn velocity_at(&self, total_time: f32) -> Vec3 {
+        let angle = total_time / self.period * TAU + self.phase;
+        (self.minor_axis * angle.cos() - self.major_axis * angle.sin()) * (TAU / self.period)
+    }
+}
+
+#[derive(Clone, Serialize, Deserialize)]
 pub struct StoredMinerals {
     pub stored: f32,
     pub capacity: f32,
 }
 
+// A cloud of gas. Harvesters don't need to dock with it, anywhere inside it will do.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct CanBeHarvested {
+    pub total: f32,
+    pub gas: f32,
+}
+
+impl CanBeHarvested {
+    pub const RADIUS: f32 = 25.0;
+    pub const HARVEST_RATE: f32 = 1.5;
+
+    pub fn new(gas: f32) -> Self {
+        Self { total: gas, gas }
+    }
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct StoredGas {
+    pub stored: f32,
+    pub capacity: f32,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct CanTrade;
+
+// Minerals taken from the global pool to be sold at a trading station.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Cargo {
+    pub minerals: f32,
+    pub capacity: f32,
+}
+
+// A neutral station that buys minerals for credits. The price drifts around a base price and
+// drops as minerals are sold to it, so it pays to spread sales out between stations.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct TradingStation {
+    pub base_price: f32,
+    pub price: f32,
+}
+
+impl TradingStation {
+    pub const TRADE_RANGE: f32 = 20.0;
+    // The fraction of the price that's lost for each mineral sold.
+    const PRICE_IMPACT: f32 = 0.01;
+    // How quickly the price recovers towards the base price, per second.
+    const RECOVERY: f32 = 0.02;
+    const VOLATILITY: f32 = 0.05;
+
+    pub fn new(base_price: f32) -> Self {
+        Self {
+            base_price,
+            price: base_price,
+        }
+    }
+
+    pub fn sell(&mut self, minerals: f32) -> f32 {
+        let credits = minerals * self.price;
+        self.price = (self.price * (1.0 - minerals * Self::PRICE_IMPACT)).max(self.min_price());
+        credits
+    }
+
+    pub fn fluctuate<R: Rng>(&mut self, rng: &mut R, delta_time: f32) {
+        self.drift(rng.gen_range(-1.0..1.0), delta_time);
+    }
+
+    fn drift(&mut self, noise: f32, delta_time: f32) {
+        let drift = (self.base_price - self.price) * Self::RECOVERY
+            + noise * self.base_price * Self::VOLATILITY;
+        self.price = (self.price + drift * delta_time).max(self.min_price());
+    }
+
+    fn min_price(&self) -> f32 {
+        self.base_price * 0.25
+    }
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct CanConstruct;
+
+// A structure that's been laid out but not finished yet.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct UnderConstruction {
+    pub structure: StructureType,
+    pub progress: f32,
+}
+
+impl UnderConstruction {
+    pub const CONSTRUCTION_RANGE: f32 = 15.0;
+
+    pub fn new(structure: StructureType) -> Self {
+        Self {
+            structure,
+            progress: 0.0,
+        }
+    }
+
+    // Each construction ship working on the site adds its own progress.
+    pub fn advance(&mut self, delta_time: f32) -> bool {
+        self.progress = (self.progress + delta_time).min(self.structure.build_time());
+        self.is_complete()
+    }
+
+    pub fn is_complete(&self) -> bool {
+        self.progress >= self.structure.build_time()
+    }
+
+    pub fn fraction(&self) -> f32 {
+        self.progress / self.structure.build_time()
+    }
+}
+
+// Turns minerals into gas.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Refinery {
+    pub minerals_per_second: f32,
+    // How much gas each mineral is worth.
+    pub yield_ratio: f32,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
 pub struct Unloading {
-    pub until: f32,
+    pub until: Timer,
 }
 
 impl Unloading {
-    pub fn new(total_time: f32) -> Self {
+    pub const DURATION: f32 = 0.5;
+
+    pub fn new(clock: &SimClock) -> Self {
+        Self {
+            until: clock.timer(Self::DURATION),
+        }
+    }
+}
+
+// Stops ships with high autonomy from docking again as soon as they've been unloaded.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Launched {
+    pub until: Timer,
+}
+
+impl Launched {
+    pub const GRACE_PERIOD: f32 = 30.0;
+
+    pub fn new(clock: &SimClock) -> Self {
+        Self {
+            until: clock.timer(Self::GRACE_PERIOD),
+        }
+    }
+}
+
+// Lies dormant until a ship comes within `trigger_radius`.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct ProximityMine {
+    pub trigger_radius: f32,
+    pub blast: AreaOfEffect,
+}
+
+impl ProximityMine {
+    pub const BLAST_DAMAGE: f32 = 60.0;
+}
+
+impl Default for ProximityMine {
+    fn default() -> Self {
         Self {
-            until: total_time + 0.5,
+            trigger_radius: 8.0,
+            blast: AreaOfEffect {
+                radius: 15.0,
+                falloff_bands: 2,
+            },
+        }
+    }
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct MineLayer {
+    pub mines: u32,
+}
+
+// Lays a mine on reaching `point`, unless the ship is given other orders on the way.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct MinefieldDrop {
+    pub point: Vec3,
+}
+
+// Reveals the other side's mines within `range`.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Detector {
+    pub range: f32,
+}
+
+// Mines laid by either side are hidden from the other until a detector comes close.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Hidden;
+
+// Cloaked ships can't be targeted or hit until they run out of energy or open fire.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Cloaked;
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct CloakEnergy {
+    pub current: f32,
+    pub max: f32,
+}
+
+impl CloakEnergy {
+    pub const DRAIN_RATE: f32 = 1.0;
+    pub const RECHARGE_RATE: f32 = 0.25;
+
+    pub fn new(max: f32) -> Self {
+        Self { current: max, max }
+    }
+}
+
+// Something a ship can pick up and take with it. This is separate from `Carrying`, which is for
+// ships carrying other ships.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Carryable {
+    pub speed_multiplier: f32,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct HoldingItem(pub Entity);
+
+// Scores a point for whichever side gets it back to one of their carriers.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Relic;
+
+// Something large enough that ships should steer around it instead of through it.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Obstacle;
+
+// Extends the agro range of the first ship to fly close enough to pick it up.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct SensorRelay {
+    pub pickup_radius: f32,
+    pub range_bonus: f32,
+}
+
+// A crate floating in space, collected by the first ship to fly through it.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub enum Pickup {
+    Speed,
+    Damage,
+    Repair,
+    Minerals,
+}
+
+impl Pickup {
+    pub const ARRAY: [Self; 4] = [Self::Speed, Self::Damage, Self::Repair, Self::Minerals];
+    pub const RADIUS: f32 = 8.0;
+    pub const BOOST_DURATION: f32 = 30.0;
+    pub const MINERALS: f32 = 150.0;
+
+    pub fn random<R: Rng>(rng: &mut R) -> Self {
+        Self::ARRAY[rng.gen_range(0..Self::ARRAY.len())]
+    }
+
+    pub fn to_str(self) -> &'static str {
+        match self {
+            Self::Speed => "speed boost",
+            Self::Damage => "damage boost",
+            Self::Repair => "repair kit",
+            Self::Minerals => "crate of minerals",
+        }
+    }
+}
+
+// `MaxSpeed` has been multiplied by this for as long as the boost lasts.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct SpeedBoost(pub Timer);
+
+impl SpeedBoost {
+    pub const MULTIPLIER: f32 = 1.5;
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct DamageBoost(pub Timer);
+
+impl DamageBoost {
+    const MULTIPLIER: f32 = 1.5;
+
+    pub fn damage_multiplier(boost: Option<&Self>) -> f32 {
+        if boost.is_some() {
+            Self::MULTIPLIER
+        } else {
+            1.0
         }
     }
 }
 
+#[derive(Clone, Serialize, Deserialize)]
 pub struct DebugWatch;
 
 pub struct TlasIndex {
@@ -279,6 +1832,368 @@ pub struct TlasIndex {
     pub padded_bounding_box: BoundingBox,
 }
 
+#[derive(Clone, Serialize, Deserialize)]
 pub struct CarrierFull;
 
+#[derive(Clone, Serialize, Deserialize)]
 pub struct ResearchMultiplier(pub f32);
+
+#[test]
+fn test_orbiting() {
+    let orbiting = Orbiting {
+        center: Vec3::new(100.0, 0.0, 0.0),
+        angular_velocity: 0.1,
+    };
+
+    let start = Vec3::new(150.0, 10.0, 0.0);
+    let mut position = start;
+
+    for _ in 0..100 {
+        position = orbiting.advance(position, 0.5);
+    }
+
+    assert!(((position - orbiting.center).mag() - (start - orbiting.center).mag()).abs() < 0.01);
+    assert!((position.y - start.y).abs() < 0.001);
+    assert!((position - start).mag() > 1.0);
+}
+
+#[test]
+fn test_comet_orbit() {
+    let comet = Comet {
+        center: Vec3::new(250.0, 0.0, 0.0),
+        major_axis: Vec3::new(0.0, 0.0, 300.0),
+        minor_axis: Vec3::new(50.0, 0.0, 0.0),
+        period: 100.0,
+        phase: 0.0,
+        radius: 5.0,
+        damage_per_second: 10.0,
+        richness: 3.0,
+    };
+
+    assert_eq!(comet.position_at(0.0), Vec3::new(250.0, 0.0, 300.0));
+    assert!((comet.position_at(25.0) - Vec3::new(300.0, 0.0, 0.0)).mag() < 0.001);
+    assert!((comet.position_at(100.0) - comet.position_at(0.0)).mag() < 0.01);
+
+    let delta = 0.001;
+    let estimate = (comet.position_at(10.0 + delta) - comet.position_at(10.0)) / delta;
+    assert!((estimate - comet.velocity_at(10.0)).mag() < 0.1);
+}
+
+#[test]
+fn test_veterancy_ranks() {
+    let mut veterancy = Veterancy::default();
+    assert_eq!(veterancy.chevrons(), "");
+
+    let promotions = (0..15).filter(|_| veterancy.record_kill()).count();
+
+    assert_eq!(promotions, 3);
+    assert_eq!(veterancy.rank(), 3);
+    assert_eq!(veterancy.chevrons(), "^^^");
+    assert!(!veterancy.record_kill());
+}
+
+#[test]
+fn test_trading_station_prices() {
+    let mut station = TradingStation::new(2.0);
+
+    assert_eq!(station.sell(10.0), 20.0);
+    assert!(station.price < 2.0);
+
+    for _ in 0..10 {
+        station.sell(100.0);
+    }
+
+    assert_eq!(station.price, 0.5);
+
+    for _ in 0..1000 {
+        station.drift(0.0, 1.0);
+    }
+
+    assert!((station.price - station.base_price).abs() < 0.01);
+}
+
+#[test]
+fn test_cycling_player_tags() {
+    let mut tag = None;
+
+    for &expected in &PlayerTag::ARRAY {
+        tag = PlayerTag::next(tag);
+        assert_eq!(tag, Some(expected));
+    }
+
+    assert_eq!(PlayerTag::next(tag), None);
+}
+
+#[test]
+fn test_construction_progress() {
+    let mut site = UnderConstruction::new(StructureType::DefensePlatform);
+
+    assert!(!site.advance(5.0));
+    assert_eq!(site.fraction(), 0.25);
+
+    // Two ships working on it at once.
+    assert!(!site.advance(5.0));
+    assert!(site.advance(15.0));
+    assert_eq!(site.fraction(), 1.0);
+}
+
+#[test]
+fn test_mining_claims_priority() {
+    let scale = Scale(1.0);
+    let unclaimed = MiningClaims(0).priority(100.0, &scale);
+    let claimed = MiningClaims(1).priority(50.0, &scale);
+    let full = MiningClaims(scale.mining_slots()).priority(1.0, &scale);
+
+    assert!(claimed > unclaimed);
+    assert!(full > claimed);
+    assert!(MiningClaims(1).priority(20.0, &scale) < unclaimed);
+}
+
+#[test]
+fn test_beam_weapon() {
+    let mut beam = BeamWeapon::lance(100.0, 10.0, 1.0);
+    let rotation = RotationMatrix {
+        matrix: ultraviolet::Mat3::identity(),
+        ..Default::default()
+    };
+
+    assert!(beam.contains(&rotation, Vec3::new(0.0, 0.0, 50.0)));
+    assert!(!beam.contains(&rotation, Vec3::new(50.0, 0.0, 0.0)));
+    assert!(!beam.contains(&rotation, Vec3::new(0.0, 0.0, 150.0)));
+
+    // Runs dry after a second of firing, then has to recharge to half before it can fire again.
+    for _ in 0..4 {
+        assert!(beam.drain(true, 0.25));
+    }
+
+    assert_eq!(beam.energy, 0.0);
+
+    for _ in 0..4 {
+        assert!(!beam.drain(true, 0.25));
+    }
+
+    assert_eq!(beam.energy, 0.5);
+    assert!(beam.drain(true, 0.25));
+
+    // Stopping before running dry doesn't need a recharge.
+    assert!(!beam.drain(false, 0.25));
+    assert!(beam.drain(true, 0.25));
+}
+
+#[test]
+fn test_overheating() {
+    let mut heat = Heat::new(10.0);
+
+    for _ in 0..4 {
+        heat.add(Heat::PER_SHOT);
+    }
+
+    assert!(Heat::can_fire(Some(&heat)));
+
+    heat.add(Heat::PER_SHOT);
+    assert!(!Heat::can_fire(Some(&heat)));
+
+    // Has to cool down most of the way before it can fire again.
+    heat.vent(5.0);
+    assert!(!Heat::can_fire(Some(&heat)));
+    heat.vent(2.5);
+    assert!(Heat::can_fire(Some(&heat)));
+
+    let sun_dir = Vec3::unit_y();
+    assert_eq!(Heat::from_sun(Vec3::zero(), sun_dir, 1.5), 0.0);
+    assert_eq!(Heat::from_sun(sun_dir * 1000.0, sun_dir, 1.5), 1.5);
+}
+
+#[test]
+fn test_drone_docking() {
+    let mut queue = CommandQueue::default();
+    assert!(!Drone::is_retreating(&queue));
+
+    queue.0.push_back(Command::MoveTo {
+        point: Vec3::zero(),
+        ty: MoveType::Attack,
+        speed_cap: None,
+    });
+    assert!(!Drone::is_retreating(&queue));
+
+    queue.0.push_front(Command::MoveTo {
+        point: Vec3::zero(),
+        ty: MoveType::Normal,
+        speed_cap: None,
+    });
+    assert!(Drone::is_retreating(&queue));
+
+    let mut rng = rand::SeedableRng::seed_from_u64(0);
+    let offset = Drone::orbit_offset(&mut rng);
+    assert!((offset.mag() - Drone::ORBIT_RADIUS).abs() < 0.01);
+    assert_eq!(offset.y, 0.0);
+
+    let (a, b) = (Entity::new(0), Entity::new(1));
+    let mut carrying = Carrying::default();
+    assert!(carrying.checked_push(a, true));
+    assert!(carrying.checked_push(b, false));
+    assert!(carrying.remove(a));
+    assert!(!carrying.remove(a));
+    assert_eq!(carrying.iter().collect::<Vec<_>>(), vec![b]);
+}
+
+#[test]
+fn test_carrier_doctrine() {
+    assert_eq!(
+        CarrierDoctrine::Aggressive.toggled(),
+        CarrierDoctrine::Passive
+    );
+
+    let mut health = Health::new(100.0);
+    assert!(CarrierDoctrine::ready_to_launch(&health));
+    health.current = 50.0;
+    assert!(!CarrierDoctrine::needs_repair(&health));
+    assert!(!CarrierDoctrine::ready_to_launch(&health));
+    health.current = 20.0;
+    assert!(CarrierDoctrine::needs_repair(&health));
+
+    let mut queue = CommandQueue::default();
+    assert!(CarrierDoctrine::can_recall(&queue));
+
+    queue.0.push_back(Command::Interact {
+        target: Entity::new(0),
+        ty: InteractionType::Attack,
+        range_sq: 0.0,
+    });
+    assert!(CarrierDoctrine::can_recall(&queue));
+
+    queue.0.push_front(Command::MoveTo {
+        point: Vec3::zero(),
+        ty: MoveType::Normal,
+        speed_cap: None,
+    });
+    assert!(!CarrierDoctrine::can_recall(&queue));
+}
+
+#[test]
+fn test_autonomy() {
+    assert_eq!(Autonomy::get(None), Autonomy::Local);
+    assert!(!Autonomy::get(Some(&Autonomy::FullManual)).acts_alone());
+    assert_eq!(Autonomy::High.next(), Autonomy::FullManual);
+    assert_eq!(Autonomy::Local.pursuit_range(100.0), 100.0);
+    assert_eq!(Autonomy::High.pursuit_range(100.0), 150.0);
+}
+
+#[test]
+fn test_retreat_policy() {
+    let mut policy = RetreatPolicy::new(0.25);
+    let mut health = Health::new(100.0);
+    assert!(!policy.should_retreat(&health));
+    assert!(!policy.ready_to_rejoin(&health));
+
+    health.current = 20.0;
+    assert!(policy.should_retreat(&health));
+
+    policy.retreated_from = Some(Vec3::zero());
+    assert!(!policy.should_retreat(&health));
+    assert!(!policy.ready_to_rejoin(&health));
+
+    health.current = 100.0;
+    assert!(policy.ready_to_rejoin(&health));
+}
+
+#[test]
+fn test_targeting_stance() {
+    let target = |dist: f32, health_fraction: f32| TargetInfo {
+        dist_sq: dist * dist,
+        health_fraction,
+        is_capital: false,
+        attacking_ally: false,
+    };
+
+    let near = target(20.0, 1.0);
+    let damaged = target(80.0, 0.1);
+    let range_sq = 100.0 * 100.0;
+
+    let best = |stance: TargetingStance, targets: &[&TargetInfo]| {
+        targets
+            .iter()
+            .enumerate()
+            .min_by(|(_, a), (_, b)| {
+                crate::utils::compare_floats(stance.score(a, range_sq), stance.score(b, range_sq))
+            })
+            .map(|(i, _)| i)
+            .unwrap()
+    };
+
+    assert_eq!(best(TargetingStance::Nearest, &[&near, &damaged]), 0);
+    assert_eq!(best(TargetingStance::FocusFire, &[&near, &damaged]), 1);
+
+    let attacker = TargetInfo {
+        attacking_ally: true,
+        ..target(50.0, 1.0)
+    };
+    assert_eq!(best(TargetingStance::FocusFire, &[&near, &attacker]), 1);
+}
+
+#[test]
+fn test_pickups() {
+    use rand::SeedableRng;
+
+    let mut rng = rand::rngs::SmallRng::seed_from_u64(0);
+    let mut seen = [false; Pickup::ARRAY.len()];
+
+    for _ in 0..100 {
+        seen[Pickup::random(&mut rng) as usize] = true;
+    }
+
+    assert_eq!(seen, [true; Pickup::ARRAY.len()]);
+
+    let boost = DamageBoost(SimClock::new(0.0).timer(Pickup::BOOST_DURATION));
+    assert_eq!(DamageBoost::damage_multiplier(None), 1.0);
+    assert!(DamageBoost::damage_multiplier(Some(&boost)) > 1.0);
+}
+
+#[test]
+fn test_stance() {
+    assert_eq!(Stance::get(None), Stance::Aggressive);
+    assert!(!Stance::Passive.picks_targets());
+    assert!(Stance::Defensive.picks_targets());
+
+    let mut stance = Stance::Aggressive;
+    for _ in 0..3 {
+        stance = stance.next();
+    }
+    assert_eq!(stance, Stance::Aggressive);
+
+    let anchor = Vec3::new(10.0, 0.0, 0.0);
+    assert!(Stance::within_leash(
+        anchor,
+        anchor + Vec3::unit_z() * 100.0
+    ));
+    assert!(!Stance::within_leash(
+        anchor,
+        anchor + Vec3::unit_z() * 200.0
+    ));
+}
+
+#[test]
+fn test_throttle() {
+    assert_eq!(Throttle::get(None), Throttle::Full);
+    assert_eq!(Throttle::Full.next(), Throttle::Quarter);
+    assert_eq!(Throttle::Half.fraction(), 0.5);
+    assert_eq!(Throttle::ThreeQuarters.to_str(), "75%");
+}
+
+#[test]
+fn test_collision() {
+    let masses = [ModelId::Fighter.mass(), ModelId::Battleship.mass()];
+    let collision = Collision::new(15.0, masses);
+
+    assert!(collision.damage[0] > collision.damage[1] * 10.0);
+    assert!(collision.knockback[0] > collision.knockback[1]);
+    // Momentum is conserved.
+    assert!(
+        (collision.knockback[0] * masses[0] - collision.knockback[1] * masses[1]).abs() < 0.001
+    );
+
+    // Ships drifting apart aren't hurt, but are still separated.
+    let collision = Collision::new(-5.0, masses);
+    assert_eq!(collision.damage, [0.0; 2]);
+    assert!(collision.knockback[0] > 0.0);
+}
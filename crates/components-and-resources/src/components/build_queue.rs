@@ -1,20 +1,32 @@
 use super::*;
+use crate::resources::ShipCatalog;
 
-#[derive(Default)]
+#[derive(Default, Clone, Serialize, Deserialize)]
 pub struct BuildQueue {
     building: VecDeque<ShipType>,
-    time_of_next_pop: f32,
+    #[serde(alias = "time_of_next_pop")]
+    next_pop: Timer,
     pub stay_carried: bool,
+    // From research. Only affects ships that start building after it changes.
+    #[serde(default)]
+    pub speed_bonus: f32,
+    // From engineers on board.
+    #[serde(default)]
+    pub crew_speed_bonus: f32,
 }
 
 impl BuildQueue {
-    pub fn advance(&mut self, total_time: f32) -> Option<ShipType> {
+    fn build_time(&self, ship_type: ShipType, catalog: &ShipCatalog) -> f32 {
+        catalog.build_time(ship_type) / (1.0 + self.speed_bonus + self.crew_speed_bonus)
+    }
+
+    pub fn advance(&mut self, clock: &SimClock, catalog: &ShipCatalog) -> Option<ShipType> {
         if let Some(building) = self.building.front().copied() {
-            if total_time > self.time_of_next_pop {
+            if self.next_pop.finished(clock) {
                 self.building.pop_front();
 
                 if let Some(next) = self.building.front().copied() {
-                    self.time_of_next_pop = total_time + next.build_time();
+                    self.next_pop = clock.timer(self.build_time(next, catalog));
                 }
 
                 return Some(building);
@@ -24,34 +36,33 @@ impl BuildQueue {
         None
     }
 
-    pub fn progress_time(&self, total_time: f32) -> Option<f32> {
+    pub fn progress_time(&self, clock: &SimClock, catalog: &ShipCatalog) -> Option<f32> {
         if let Some(building) = self.building.front().copied() {
-            let remaining = self.time_of_next_pop - total_time;
-            Some(1.0 - (remaining / building.build_time()))
+            let remaining = self.next_pop.remaining(clock);
+            Some(1.0 - (remaining / self.build_time(building, catalog)))
         } else {
             None
         }
     }
 
-    pub fn push(&mut self, to_build: ShipType, total_time: f32) {
+    pub fn push(&mut self, to_build: ShipType, clock: &SimClock, catalog: &ShipCatalog) {
         if self.building.is_empty() {
-            self.time_of_next_pop = total_time + to_build.build_time();
+            self.next_pop = clock.timer(self.build_time(to_build, catalog));
         }
 
         self.building.push_back(to_build);
     }
 
-    pub fn queue_length(&self, total_time: f32) -> f32 {
+    pub fn queue_length(&self, clock: &SimClock, catalog: &ShipCatalog) -> f32 {
         let mut sum = self
             .building
             .iter()
             .skip(1)
-            .map(|model_id| model_id.build_time())
+            .map(|&model_id| self.build_time(model_id, catalog))
             .sum();
 
         if !self.building.is_empty() {
-            let remaining = self.time_of_next_pop - total_time;
-            sum += remaining;
+            sum += self.next_pop.remaining(clock);
         }
 
         sum
@@ -60,19 +71,134 @@ impl BuildQueue {
     pub fn num_in_queue(&self) -> usize {
         self.building.len()
     }
+
+    pub fn iter(&self) -> impl Iterator<Item = ShipType> + '_ {
+        self.building.iter().copied()
+    }
+
+    // Whatever ends up at the front of the queue starts building from scratch.
+    fn restart_front(&mut self, clock: &SimClock, catalog: &ShipCatalog) {
+        if let Some(front) = self.building.front().copied() {
+            self.next_pop = clock.timer(self.build_time(front, catalog));
+        }
+    }
+
+    // Returns what was spent on the cancelled ship.
+    pub fn cancel(
+        &mut self,
+        index: usize,
+        clock: &SimClock,
+        catalog: &ShipCatalog,
+    ) -> Option<BuildCost> {
+        let ship_type = self.building.remove(index)?;
+
+        if index == 0 {
+            self.restart_front(clock, catalog);
+        }
+
+        Some(catalog.build_cost(ship_type))
+    }
+
+    pub fn get(&self, index: usize) -> Option<ShipType> {
+        self.building.get(index).copied()
+    }
+
+    // Swaps the ship with the one ahead of it.
+    pub fn move_earlier(&mut self, index: usize, clock: &SimClock, catalog: &ShipCatalog) {
+        if index == 0 || index >= self.building.len() {
+            return;
+        }
+
+        self.building.swap(index - 1, index);
+
+        if index == 1 {
+            self.restart_front(clock, catalog);
+        }
+    }
+
+    // Returns what was spent on everything that was in the queue.
+    pub fn cancel_all(&mut self, catalog: &ShipCatalog) -> BuildCost {
+        self.building
+            .drain(..)
+            .map(|ship_type| catalog.build_cost(ship_type))
+            .sum()
+    }
 }
 
 #[test]
 fn test_build_queue() {
     let mut build_queue = BuildQueue::default();
+    let catalog = ShipCatalog::default();
+
+    let start = SimClock::new(0.0);
+    let halfway = SimClock::new(2.5);
 
-    build_queue.push(ShipType::Fighter, 0.0);
+    build_queue.push(ShipType::Fighter, &start, &catalog);
 
-    assert_eq!(build_queue.progress_time(0.0), Some(0.0));
-    assert_eq!(build_queue.progress_time(2.5), Some(0.5));
-    assert_eq!(build_queue.progress_time(5.0), Some(1.0));
+    assert_eq!(build_queue.progress_time(&start, &catalog), Some(0.0));
+    assert_eq!(build_queue.progress_time(&halfway, &catalog), Some(0.5));
+    assert_eq!(
+        build_queue.progress_time(&SimClock::new(5.0), &catalog),
+        Some(1.0)
+    );
 
-    build_queue.push(ShipType::Fighter, 0.0);
+    build_queue.push(ShipType::Fighter, &start, &catalog);
 
-    assert_eq!(build_queue.queue_length(2.5), 7.5);
+    assert_eq!(build_queue.queue_length(&halfway, &catalog), 7.5);
+}
+
+#[test]
+fn test_build_queue_cancel_all() {
+    let mut build_queue = BuildQueue::default();
+    let catalog = ShipCatalog::default();
+
+    let clock = SimClock::new(0.0);
+
+    build_queue.push(ShipType::Fighter, &clock, &catalog);
+    build_queue.push(ShipType::Carrier, &clock, &catalog);
+
+    assert_eq!(
+        build_queue.cancel_all(&catalog),
+        BuildCost {
+            minerals: ShipType::Fighter.build_cost().minerals
+                + ShipType::Carrier.build_cost().minerals,
+            gas: ShipType::Carrier.build_cost().gas,
+        }
+    );
+    assert_eq!(build_queue.num_in_queue(), 0);
+    assert_eq!(build_queue.progress_time(&clock, &catalog), None);
+}
+
+#[test]
+fn test_build_queue_reordering() {
+    let mut build_queue = BuildQueue::default();
+    let catalog = ShipCatalog::default();
+
+    let start = SimClock::new(0.0);
+    let later = SimClock::new(2.5);
+
+    build_queue.push(ShipType::Fighter, &start, &catalog);
+    build_queue.push(ShipType::Miner, &start, &catalog);
+    build_queue.push(ShipType::Carrier, &start, &catalog);
+
+    build_queue.move_earlier(2, &later, &catalog);
+    assert_eq!(
+        build_queue.iter().collect::<Vec<_>>(),
+        vec![ShipType::Fighter, ShipType::Carrier, ShipType::Miner]
+    );
+    // The fighter keeps its progress.
+    assert_eq!(build_queue.progress_time(&later, &catalog), Some(0.5));
+
+    build_queue.move_earlier(1, &later, &catalog);
+    assert_eq!(build_queue.get(0), Some(ShipType::Carrier));
+    assert_eq!(build_queue.get(3), None);
+    assert_eq!(build_queue.progress_time(&later, &catalog), Some(0.0));
+
+    assert_eq!(
+        build_queue.cancel(0, &later, &catalog),
+        Some(ShipType::Carrier.build_cost())
+    );
+    assert_eq!(build_queue.iter().next(), Some(ShipType::Fighter));
+    assert_eq!(build_queue.progress_time(&later, &catalog), Some(0.0));
+    assert_eq!(build_queue.cancel(5, &later, &catalog), None);
 }
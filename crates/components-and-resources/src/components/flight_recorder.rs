@@ -0,0 +1,93 @@
+use super::*;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum FlightEvent {
+    Position(Vec3),
+    Order(Option<Command>),
+    Damage(f32),
+}
+
+// Keeps the last few things that happened to a ship, so that it's possible to work out why it
+// ended up where it did without needing a full replay. Not saved, as it's only for debugging.
+pub struct FlightRecorder {
+    events: VecDeque<(f32, FlightEvent)>,
+    last_order: Option<Command>,
+    last_health: Option<f32>,
+    next_position_sample: f32,
+}
+
+impl Default for FlightRecorder {
+    fn default() -> Self {
+        Self {
+            events: VecDeque::with_capacity(Self::CAPACITY),
+            last_order: None,
+            last_health: None,
+            next_position_sample: 0.0,
+        }
+    }
+}
+
+impl FlightRecorder {
+    pub const CAPACITY: usize = 256;
+    pub const POSITION_SAMPLE_INTERVAL: f32 = 1.0;
+
+    fn push(&mut self, total_time: f32, event: FlightEvent) {
+        if self.events.len() == Self::CAPACITY {
+            self.events.pop_front();
+        }
+
+        self.events.push_back((total_time, event));
+    }
+
+    pub fn record(
+        &mut self,
+        total_time: f32,
+        position: Vec3,
+        order: Option<Command>,
+        health: Option<f32>,
+    ) {
+        if total_time >= self.next_position_sample {
+            self.push(total_time, FlightEvent::Position(position));
+            self.next_position_sample = total_time + Self::POSITION_SAMPLE_INTERVAL;
+        }
+
+        if order != self.last_order {
+            self.push(total_time, FlightEvent::Order(order));
+            self.last_order = order;
+        }
+
+        if let (Some(last_health), Some(health)) = (self.last_health, health) {
+            if health < last_health {
+                self.push(total_time, FlightEvent::Damage(last_health - health));
+            }
+        }
+
+        self.last_health = health;
+    }
+
+    pub fn events(&self) -> impl Iterator<Item = &(f32, FlightEvent)> {
+        self.events.iter()
+    }
+}
+
+#[test]
+fn test_flight_recorder() {
+    let mut recorder = FlightRecorder::default();
+
+    recorder.record(0.0, Vec3::zero(), None, Some(10.0));
+    recorder.record(0.5, Vec3::zero(), None, Some(5.0));
+
+    assert_eq!(
+        recorder.events().copied().collect::<Vec<_>>(),
+        vec![
+            (0.0, FlightEvent::Position(Vec3::zero())),
+            (0.5, FlightEvent::Damage(5.0)),
+        ]
+    );
+
+    for i in 0..FlightRecorder::CAPACITY * 2 {
+        recorder.record(i as f32 + 1.0, Vec3::one(), None, Some(5.0));
+    }
+
+    assert_eq!(recorder.events().count(), FlightRecorder::CAPACITY);
+}
@@ -15,9 +15,10 @@ pub fn base_ship_components(position: Vec3) -> impl Bundle {
     )
 }
 
-pub fn fighter_components(ray_cooldown: f32) -> impl Bundle {
+pub fn fighter_components(initial_cooldown: f32) -> impl Bundle {
     (
         ModelId::Fighter,
+        ShipType::Fighter,
         CanAttack,
         CanBeCarried,
         MaxSpeed(10.0),
@@ -25,14 +26,58 @@ pub fn fighter_components(ray_cooldown: f32) -> impl Bundle {
             current: 45.0,
             max: 50.0,
         },
-        RayCooldown(ray_cooldown),
+        Shield::new(15.0, 1.5),
+        // Broadside shells mostly miss something this small and fast.
+        Armor {
+            kinetic: 0.5,
+            energy: 0.0,
+            explosive: 0.0,
+        },
+        Weapons(vec![Weapon {
+            projectile_speed: 200.0,
+            damage: 10.0,
+            damage_type: DamageType::Energy,
+            reload_time: 1.0,
+            range: 200.0,
+            cooldown: Cooldown::new(initial_cooldown),
+        }]),
         AgroRange(200.0),
+        Heat::new(30.0),
+        CloakEnergy::new(10.0),
+        EvasiveManeuvers {
+            strength: 1.0,
+            duration: 0.75,
+        },
+        Veterancy::default(),
+    )
+}
+
+pub fn drone_components() -> impl Bundle {
+    (
+        ModelId::Fighter,
+        ShipType::Drone,
+        Scale(0.5),
+        CanAttack,
+        CanBeCarried,
+        MaxSpeed(12.0),
+        Health::new(20.0),
+        Weapons(vec![Weapon {
+            projectile_speed: 200.0,
+            damage: 4.0,
+            damage_type: DamageType::Energy,
+            reload_time: 1.0,
+            range: 120.0,
+            cooldown: Cooldown::default(),
+        }]),
+        AgroRange(120.0),
+        Heat::new(20.0),
     )
 }
 
 pub fn miner_components() -> impl Bundle {
     (
         ModelId::Miner,
+        ShipType::Miner,
         CanBeCarried,
         MaxSpeed(15.0),
         Health {
@@ -47,13 +92,388 @@ pub fn miner_components() -> impl Bundle {
     )
 }
 
+pub fn gas_harvester_components() -> impl Bundle {
+    (
+        ModelId::GasHarvester,
+        ShipType::GasHarvester,
+        CanBeCarried,
+        MaxSpeed(13.0),
+        Health::new(40.0),
+        CanHarvest,
+        StoredGas {
+            stored: 0.0,
+            capacity: 15.0,
+        },
+    )
+}
+
+pub fn minelayer_components() -> impl Bundle {
+    (
+        ModelId::Minelayer,
+        ShipType::Minelayer,
+        CanBeCarried,
+        MaxSpeed(12.0),
+        Health::new(60.0),
+        MineLayer { mines: 8 },
+        // Hardened against its own cargo going off.
+        Armor {
+            kinetic: 0.0,
+            energy: 0.0,
+            explosive: 0.5,
+        },
+        Detector { range: 80.0 },
+        CloakEnergy::new(20.0),
+        EvasiveManeuvers {
+            strength: 0.5,
+            duration: 1.0,
+        },
+    )
+}
+
+pub fn repair_ship_components() -> impl Bundle {
+    (
+        ModelId::RepairShip,
+        ShipType::RepairShip,
+        CanBeCarried,
+        MaxSpeed(12.0),
+        Health::new(60.0),
+        Shield::new(20.0, 2.0),
+        CanRepair {
+            rate: 5.0,
+            range: 20.0,
+        },
+        EvasiveManeuvers {
+            strength: 0.75,
+            duration: 1.0,
+        },
+    )
+}
+
 pub fn carrier_components(queue: BuildQueue, crew: Vec<Entity>) -> impl Bundle {
     (
         ModelId::Carrier,
+        ShipType::Carrier,
         OnBoard(crew),
         Carrying::default(),
         MaxSpeed(5.0),
         Health::new(250.0),
+        Shield::new(100.0, 4.0),
+        // Heavy plating that fighter lasers struggle to get through.
+        Armor {
+            kinetic: 0.0,
+            energy: 0.5,
+            explosive: 0.25,
+        },
         queue,
+        WeaponArcs::broadsides(150.0),
+        BeamWeapon::lance(120.0, 12.0, 6.0),
+        Heat::new(60.0),
+        MissileLauncher {
+            range: 250.0,
+            cooldown: Cooldown::default(),
+        },
+        Veterancy::default(),
+        CarrierDoctrine::Aggressive,
+    )
+}
+
+// Warships are scaled-down or scaled-up carriers until they get models of their own.
+pub fn frigate_components() -> impl Bundle {
+    (
+        ModelId::Frigate,
+        ShipType::Frigate,
+        CanAttack,
+        Scale(0.45),
+        MaxSpeed(8.0),
+        Health::new(120.0),
+        Shield::new(40.0, 2.0),
+        Weapons(vec![
+            Weapon {
+                projectile_speed: 200.0,
+                damage: 12.0,
+                damage_type: DamageType::Energy,
+                reload_time: 1.5,
+                range: 200.0,
+                cooldown: Cooldown::default(),
+            };
+            2
+        ]),
+        AgroRange(200.0),
+        Heat::new(40.0),
+        EvasiveManeuvers {
+            strength: 0.5,
+            duration: 1.0,
+        },
+        Veterancy::default(),
+    )
+}
+
+pub fn destroyer_components() -> impl Bundle {
+    (
+        ModelId::Destroyer,
+        ShipType::Destroyer,
+        CanAttack,
+        Scale(0.7),
+        MaxSpeed(6.0),
+        Health::new(200.0),
+        Shield::new(60.0, 3.0),
+        Armor {
+            kinetic: 0.25,
+            energy: 0.25,
+            explosive: 0.0,
+        },
+        Weapons(vec![
+            Weapon {
+                projectile_speed: 250.0,
+                damage: 15.0,
+                damage_type: DamageType::Kinetic,
+                reload_time: 2.0,
+                range: 225.0,
+                cooldown: Cooldown::default(),
+            };
+            4
+        ]),
+        WeaponArcs::broadsides(175.0),
+        AgroRange(225.0),
+        Heat::new(60.0),
+        Veterancy::default(),
+    )
+}
+
+pub fn battleship_components() -> impl Bundle {
+    let mut weapon_arcs = WeaponArcs::broadsides(250.0);
+    weapon_arcs.0.extend(WeaponArcs::turret(200.0).0);
+
+    (
+        ModelId::Battleship,
+        ShipType::Battleship,
+        CanAttack,
+        Scale(1.6),
+        MaxSpeed(3.5),
+        Health::new(600.0),
+        Shield::new(200.0, 5.0),
+        Armor {
+            kinetic: 0.5,
+            energy: 0.5,
+            explosive: 0.25,
+        },
+        weapon_arcs,
+        BeamWeapon::lance(150.0, 20.0, 8.0),
+        MissileLauncher {
+            range: 300.0,
+            cooldown: Cooldown::default(),
+        },
+        AgroRange(300.0),
+        Heat::new(100.0),
+        Veterancy::default(),
+    )
+}
+
+// Wrecked hulls left over from some earlier battle. They can be picked apart for minerals.
+pub fn derelict_components(position: Vec3, rotation: Rotor3, scale: f32) -> impl Bundle {
+    (
+        Position(position),
+        Rotation(rotation),
+        RotationMatrix::default(),
+        WorldSpaceBoundingBox::default(),
+        ModelId::Carrier,
+        Scale(scale),
+        Health::new(2000.0),
+        CanBeMined::new(300.0),
+        MiningClaims::default(),
+        Obstacle,
+        Selectable,
+    )
+}
+
+pub fn freighter_components() -> impl Bundle {
+    (
+        ModelId::Freighter,
+        ShipType::Freighter,
+        CanBeCarried,
+        MaxSpeed(12.0),
+        Health::new(60.0),
+        CanTrade,
+        Cargo {
+            minerals: 0.0,
+            capacity: 30.0,
+        },
+    )
+}
+
+pub fn construction_ship_components() -> impl Bundle {
+    (
+        ModelId::ConstructionShip,
+        ShipType::ConstructionShip,
+        CanBeCarried,
+        CanConstruct,
+        MaxSpeed(8.0),
+        Health::new(80.0),
+    )
+}
+
+// The structure's own components are added by `construct` once the site is finished.
+pub fn construction_site_components(position: Vec3, structure: StructureType) -> impl Bundle {
+    (
+        Position(position),
+        Rotation(Rotor3::identity()),
+        RotationMatrix::default(),
+        WorldSpaceBoundingBox::default(),
+        structure.model_id(),
+        Scale(2.0),
+        Selectable,
+        Health::new(100.0),
+        UnderConstruction::new(structure),
+    )
+}
+
+pub fn defense_platform_components() -> impl Bundle {
+    (
+        Health::new(400.0),
+        Shield::new(100.0, 5.0),
+        WeaponArcs::turret(175.0),
+        Heat::new(80.0),
+    )
+}
+
+pub fn shipyard_components() -> impl Bundle {
+    (
+        Health::new(600.0),
+        BuildQueue::default(),
+        Armor {
+            kinetic: 0.25,
+            energy: 0.25,
+            explosive: 0.0,
+        },
+    )
+}
+
+pub fn refinery_components() -> impl Bundle {
+    (
+        Health::new(300.0),
+        Refinery {
+            minerals_per_second: 2.0,
+            yield_ratio: 0.5,
+        },
+    )
+}
+
+pub fn comet_components(comet: Comet, spin_axis: Vec3) -> impl Bundle {
+    (
+        Position(comet.position_at(0.0)),
+        Rotation(Rotor3::identity()),
+        RotationMatrix::default(),
+        WorldSpaceBoundingBox::default(),
+        ModelId::Asteroid,
+        Scale(2.0),
+        Spin::new(spin_axis),
+        Health::new(1000.0),
+        Selectable,
+        CanBeMined::new(60.0),
+        MiningClaims::default(),
+        Obstacle,
+        comet,
+    )
+}
+
+// The asteroid model stands in for the dense core of the cloud, with the rest of it drawn by
+// `render_gas_clouds`.
+pub fn gas_cloud_components(position: Vec3, spin_axis: Vec3) -> impl Bundle {
+    (
+        Position(position),
+        Rotation(Rotor3::identity()),
+        RotationMatrix::default(),
+        WorldSpaceBoundingBox::default(),
+        ModelId::Asteroid,
+        Scale(1.5),
+        Spin::new(spin_axis),
+        Selectable,
+        CanBeHarvested::new(200.0),
+    )
+}
+
+// Stations use the carrier model until they get one of their own.
+pub fn trading_station_components(position: Vec3, base_price: f32) -> impl Bundle {
+    (
+        Position(position),
+        Rotation(Rotor3::identity()),
+        RotationMatrix::default(),
+        WorldSpaceBoundingBox::default(),
+        ModelId::TradingStation,
+        Scale(2.5),
+        Spin::new(Vec3::unit_y()),
+        Selectable,
+        TradingStation::new(base_price),
+    )
+}
+
+pub fn wreck_components(
+    position: Vec3,
+    rotation: Rotor3,
+    model_id: ModelId,
+    clock: &SimClock,
+) -> impl Bundle {
+    (
+        Position(position),
+        Rotation(rotation),
+        RotationMatrix::default(),
+        WorldSpaceBoundingBox::default(),
+        model_id,
+        Selectable,
+        Wreck::new(model_id),
+        AliveUntil(clock.timer(Wreck::LIFETIME)),
+    )
+}
+
+pub fn proximity_mine_components(position: Vec3, rotation: Rotor3) -> impl Bundle {
+    (
+        Position(position),
+        Rotation(rotation),
+        RotationMatrix::default(),
+        WorldSpaceBoundingBox::default(),
+        ModelId::Asteroid,
+        Scale(0.3),
+        Health::new(1.0),
+        ProximityMine::default(),
+    )
+}
+
+pub fn relic_components(position: Vec3, rotation: Rotor3) -> impl Bundle {
+    (
+        Position(position),
+        Rotation(rotation),
+        RotationMatrix::default(),
+        WorldSpaceBoundingBox::default(),
+        ModelId::Asteroid,
+        Scale(0.5),
+        Selectable,
+        Carryable {
+            speed_multiplier: 0.5,
+        },
+        Relic,
+    )
+}
+
+// Pickups use a tiny asteroid until they get a model of their own.
+pub fn pickup_components(position: Vec3, pickup: Pickup, spin_axis: Vec3) -> impl Bundle {
+    (
+        Position(position),
+        Rotation(Rotor3::identity()),
+        RotationMatrix::default(),
+        WorldSpaceBoundingBox::default(),
+        ModelId::Asteroid,
+        Scale(0.75),
+        Spin::new(spin_axis),
+        pickup,
+    )
+}
+
+pub fn sensor_relay_components(position: Vec3) -> impl Bundle {
+    (
+        Position(position),
+        SensorRelay {
+            pickup_radius: 10.0,
+            range_bonus: 50.0,
+        },
     )
 }
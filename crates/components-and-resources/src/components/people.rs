@@ -1,22 +1,94 @@
+use serde::{Deserialize, Serialize};
+
 #[derive(Clone, Copy, Debug)]
 pub enum PersonEnum {
     Civilian = 0,
     Engineer = 1,
     Researcher = 2,
+    Gunner = 3,
+    Pilot = 4,
 }
 
 impl PersonEnum {
-    pub const COUNT: usize = 3;
-    pub const ARRAY: [Self; Self::COUNT] = [Self::Civilian, Self::Engineer, Self::Researcher];
+    pub const COUNT: usize = 5;
+    pub const ARRAY: [Self; Self::COUNT] = [
+        Self::Civilian,
+        Self::Engineer,
+        Self::Researcher,
+        Self::Gunner,
+        Self::Pilot,
+    ];
 
-    pub fn new(engineer: bool, researcher: bool) -> Self {
-        match (engineer, researcher) {
-            (true, false) => Self::Engineer,
-            (false, true) => Self::Researcher,
+    pub fn new(engineer: bool, researcher: bool, gunner: bool, pilot: bool) -> Self {
+        match (engineer, researcher, gunner, pilot) {
+            (true, false, false, false) => Self::Engineer,
+            (false, true, false, false) => Self::Researcher,
+            (false, false, true, false) => Self::Gunner,
+            (false, false, false, true) => Self::Pilot,
             _ => Self::Civilian,
         }
     }
 }
 
+#[derive(Clone, Serialize, Deserialize)]
 pub struct Engineer;
+#[derive(Clone, Serialize, Deserialize)]
 pub struct Researcher;
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Gunner;
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Pilot;
+
+// What the crew on board a ship add up to. Kept up to date by `aggregate_crew`.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct CrewModifiers {
+    pub build_speed_bonus: f32,
+    #[serde(default)]
+    pub heat_vent_bonus: f32,
+    pub reload_multiplier: f32,
+    // `MaxSpeed` has already been multiplied by this, so it's kept to be divided out again when
+    // the crew changes.
+    pub speed_multiplier: f32,
+}
+
+impl Default for CrewModifiers {
+    fn default() -> Self {
+        Self::new(0, 0, 0)
+    }
+}
+
+impl CrewModifiers {
+    const BUILD_SPEED_PER_ENGINEER: f32 = 0.1;
+    const HEAT_VENTING_PER_ENGINEER: f32 = 0.25;
+    const RELOAD_SPEED_PER_GUNNER: f32 = 0.1;
+    const SPEED_PER_PILOT: f32 = 0.05;
+
+    pub fn new(engineers: usize, gunners: usize, pilots: usize) -> Self {
+        Self {
+            build_speed_bonus: engineers as f32 * Self::BUILD_SPEED_PER_ENGINEER,
+            heat_vent_bonus: engineers as f32 * Self::HEAT_VENTING_PER_ENGINEER,
+            reload_multiplier: 1.0 / (1.0 + gunners as f32 * Self::RELOAD_SPEED_PER_GUNNER),
+            speed_multiplier: 1.0 + pilots as f32 * Self::SPEED_PER_PILOT,
+        }
+    }
+
+    pub fn reload_multiplier(modifiers: Option<&Self>) -> f32 {
+        modifiers.map_or(1.0, |modifiers| modifiers.reload_multiplier)
+    }
+
+    pub fn heat_vent_multiplier(modifiers: Option<&Self>) -> f32 {
+        1.0 + modifiers.map_or(0.0, |modifiers| modifiers.heat_vent_bonus)
+    }
+}
+
+#[test]
+fn test_crew_modifiers() {
+    let modifiers = CrewModifiers::new(2, 5, 4);
+
+    assert!((modifiers.build_speed_bonus - 0.2).abs() < f32::EPSILON);
+    assert!((CrewModifiers::heat_vent_multiplier(Some(&modifiers)) - 1.5).abs() < f32::EPSILON);
+    assert!((modifiers.reload_multiplier - 1.0 / 1.5).abs() < f32::EPSILON);
+    assert!((modifiers.speed_multiplier - 1.2).abs() < f32::EPSILON);
+
+    assert_eq!(CrewModifiers::reload_multiplier(None), 1.0);
+}
@@ -18,6 +18,32 @@ impl FormationPosition {
     }
 }
 
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum FormationShape {
+    // A fighter screen for groups of only fighters, a sphere otherwise.
+    Auto,
+    Wedge,
+    Line,
+    Sphere,
+}
+
+impl Default for FormationShape {
+    fn default() -> Self {
+        Self::Auto
+    }
+}
+
+impl FormationShape {
+    pub fn next(self) -> Self {
+        match self {
+            Self::Auto => Self::Wedge,
+            Self::Wedge => Self::Line,
+            Self::Line => Self::Sphere,
+            Self::Sphere => Self::Auto,
+        }
+    }
+}
+
 #[derive(Debug)]
 pub struct Formation {
     positions: Vec<FormationPosition>,
@@ -88,9 +114,83 @@ impl Formation {
         }
     }
 
+    // A V shape with the tip at `position`, pointing along `facing`.
+    pub fn wedge(position: Vec3, facing: Vec3, count: usize, seperation: f32) -> Self {
+        let rotor = crate::utils::rotation_from_facing(facing);
+
+        let sideways = rotor * Vec3::unit_x() * seperation;
+        let backwards = rotor * -Vec3::unit_z() * seperation;
+
+        Self {
+            positions: (0..count)
+                .map(|i| {
+                    let row = ((i + 1) / 2) as f32;
+                    let side = if i % 2 == 0 { 1.0 } else { -1.0 };
+
+                    FormationPosition::new(position + backwards * row + sideways * row * side)
+                })
+                .collect(),
+        }
+    }
+
+    // A line abreast, perpendicular to `facing`.
+    pub fn line(position: Vec3, facing: Vec3, count: usize, seperation: f32) -> Self {
+        let rotor = crate::utils::rotation_from_facing(facing);
+
+        let sideways = rotor * Vec3::unit_x() * seperation;
+
+        let middle = (count as f32 - 1.0) / 2.0;
+
+        Self {
+            positions: (0..count)
+                .map(|i| FormationPosition::new(position + sideways * (i as f32 - middle)))
+                .collect(),
+        }
+    }
+
+    // Spreads the positions evenly over a sphere around `center`.
+    pub fn escort_screen(center: Vec3, count: usize, radius: f32) -> Self {
+        let golden_angle = PI * (3.0 - 5.0_f32.sqrt());
+
+        Self {
+            positions: (0..count)
+                .map(|i| {
+                    let y = 1.0 - (i as f32 + 0.5) / count as f32 * 2.0;
+                    let ring_radius = (1.0 - y * y).sqrt();
+                    let angle = golden_angle * i as f32;
+
+                    let offset = Vec3::new(angle.cos() * ring_radius, y, angle.sin() * ring_radius);
+
+                    FormationPosition::new(center + offset * radius)
+                })
+                .collect(),
+        }
+    }
+
     pub fn at_point(point: Vec3, count: usize) -> Self {
         Self {
             positions: vec![FormationPosition::new(point); count],
         }
     }
 }
+
+#[test]
+fn test_wedge() {
+    let mut wedge = Formation::wedge(Vec3::zero(), Vec3::unit_z(), 3, 1.0);
+
+    let tip = wedge.choose_position(Vec3::zero()).unwrap();
+    assert!(tip.mag() < 0.001);
+
+    let next = wedge.choose_position(Vec3::zero()).unwrap();
+    assert!((next.z + 1.0).abs() < 0.001);
+    assert!((next.x.abs() - 1.0).abs() < 0.001);
+}
+
+#[test]
+fn test_escort_screen() {
+    let mut screen = Formation::escort_screen(Vec3::unit_x(), 5, 20.0);
+
+    while let Some(position) = screen.choose_position(Vec3::zero()) {
+        assert!(((position - Vec3::unit_x()).mag() - 20.0).abs() < 0.001);
+    }
+}
@@ -0,0 +1,114 @@
+use std::path::Path;
+
+// Different drivers don't produce bit-identical output, so small differences are allowed.
+const CHANNEL_TOLERANCE: u8 = 8;
+const MAX_DIFFERING_FRACTION: f32 = 0.001;
+
+#[derive(Debug, PartialEq)]
+pub struct ImageDifference {
+    pub differing_pixels: usize,
+    pub total_pixels: usize,
+    pub max_channel_difference: u8,
+}
+
+impl ImageDifference {
+    pub fn within_tolerance(&self) -> bool {
+        self.differing_pixels as f32 <= self.total_pixels as f32 * MAX_DIFFERING_FRACTION
+    }
+}
+
+// Both images need to be tightly packed rgba8 of the same size.
+pub fn compare_images(a: &[u8], b: &[u8]) -> ImageDifference {
+    let mut difference = ImageDifference {
+        differing_pixels: 0,
+        total_pixels: a.len() / 4,
+        max_channel_difference: 0,
+    };
+
+    for (a, b) in a.chunks_exact(4).zip(b.chunks_exact(4)) {
+        let max = a
+            .iter()
+            .zip(b)
+            .map(|(&a, &b)| (a as i16 - b as i16).abs() as u8)
+            .max()
+            .unwrap_or(0);
+
+        difference.max_channel_difference = difference.max_channel_difference.max(max);
+        difference.differing_pixels += (max > CHANNEL_TOLERANCE) as usize;
+    }
+
+    difference
+}
+
+// Compares a captured frame against the golden image at `path`, or replaces the golden image with
+// it when blessing.
+pub fn check_against_golden_image(
+    pixels: &[u8],
+    width: u32,
+    height: u32,
+    path: &Path,
+    bless: bool,
+) -> anyhow::Result<()> {
+    if bless {
+        image::save_buffer(path, pixels, width, height, image::ColorType::Rgba8)?;
+        return Ok(());
+    }
+
+    // A new replay won't have a golden image until someone with a GPU blesses one.
+    if !path.exists() {
+        let actual_path = path.with_extension("actual.png");
+        image::save_buffer(&actual_path, pixels, width, height, image::ColorType::Rgba8)?;
+
+        return Err(anyhow::anyhow!(
+            "There's no golden image at '{}'. The frame was written to '{}', and running with --bless will keep it",
+            path.display(),
+            actual_path.display()
+        ));
+    }
+
+    let golden = image::open(path)?.to_rgba8();
+
+    if golden.dimensions() != (width, height) {
+        return Err(anyhow::anyhow!(
+            "The golden image is {:?} but the frame is {:?}",
+            golden.dimensions(),
+            (width, height)
+        ));
+    }
+
+    let difference = compare_images(pixels, golden.as_raw());
+
+    if !difference.within_tolerance() {
+        // Keep the frame around so that it can be compared by eye.
+        let actual_path = path.with_extension("actual.png");
+        image::save_buffer(&actual_path, pixels, width, height, image::ColorType::Rgba8)?;
+
+        return Err(anyhow::anyhow!(
+            "{} of {} pixels differ from the golden image by up to {}. The frame was written to '{}'",
+            difference.differing_pixels,
+            difference.total_pixels,
+            difference.max_channel_difference,
+            actual_path.display()
+        ));
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_image_comparison() {
+    let a = [10, 20, 30, 255].repeat(500);
+    let mut b = a.clone();
+
+    assert!(compare_images(&a, &b).within_tolerance());
+
+    // Driver noise.
+    b[0] += CHANNEL_TOLERANCE;
+    assert_eq!(compare_images(&a, &b).differing_pixels, 0);
+
+    b[4] += 100;
+    let difference = compare_images(&a, &b);
+    assert_eq!(difference.differing_pixels, 1);
+    assert_eq!(difference.max_channel_difference, 100);
+    assert!(!difference.within_tolerance());
+}
@@ -1,4 +1,4 @@
-use ultraviolet::{Mat3, Mat4, Vec2, Vec3, Vec4};
+use ultraviolet::{Mat4, Vec2, Vec3, Vec4};
 
 #[repr(C)]
 #[derive(Clone, Copy, bytemuck::Pod, bytemuck::Zeroable)]
@@ -7,17 +7,49 @@ pub struct PushConstants {
     pub light_dir: Vec3,
     pub padding: u32,
     pub ambient_light: Vec3,
+    pub padding_2: u32,
+    pub camera_position: Vec3,
+    pub time: f32,
+    pub second_light_dir: Vec3,
+    pub second_light_intensity: f32,
 }
 
+// Kept small as one of these is uploaded whenever a ship moves.
 #[repr(C)]
 #[derive(Default, Clone, Copy, bytemuck::Pod, bytemuck::Zeroable)]
 pub struct Instance {
-    pub rotation: Mat3,
+    // A quaternion, stored as `[vector, scalar]`.
+    pub rotation: Vec4,
     pub translation: Vec3,
-    pub colour: Vec3,
     pub scale: f32,
-    pub diffuse_texture: u32,
-    pub emissive_texture: u32,
+    // The alpha channel is 255 if the selection pulse should be shown.
+    pub colour: [u8; 4],
+    // Diffuse, emissive, normal and metallic-roughness.
+    pub textures: [u16; 4],
+    pub last_hit_time: f32,
+    // Used to sort the instances by model on the gpu.
+    pub model_id: u32,
+}
+
+impl Instance {
+    // Marks a free slot in the ship buffer.
+    pub fn unused() -> Self {
+        Self {
+            model_id: u32::MAX,
+            ..Default::default()
+        }
+    }
+
+    pub fn pack_colour(colour: Vec3, selection_pulse: bool) -> [u8; 4] {
+        let pack = |value: f32| (value.max(0.0).min(1.0) * 255.0).round() as u8;
+
+        [
+            pack(colour.x),
+            pack(colour.y),
+            pack(colour.z),
+            if selection_pulse { 255 } else { 0 },
+        ]
+    }
 }
 
 #[repr(C)]
@@ -26,6 +58,8 @@ pub struct ModelVertex {
     pub position: Vec3,
     pub normal: Vec3,
     pub uv: Vec2,
+    // The w component stores the handedness of the bitangent.
+    pub tangent: Vec4,
 }
 
 #[repr(C)]
@@ -37,9 +71,34 @@ pub struct ColouredVertex {
 
 #[repr(C)]
 #[derive(Default, Clone, Copy, bytemuck::Pod, bytemuck::Zeroable)]
-pub struct LaserVertex {
-    pub position: Vec3,
+pub struct LaserInstance {
+    pub start: Vec3,
+    pub end: Vec3,
     pub colour: Vec3,
+    pub width: f32,
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct BuildDrawsPushConstants {
+    pub step: u32,
+    pub num_slots: u32,
+    pub num_models: u32,
+}
+
+impl BuildDrawsPushConstants {
+    pub const COUNT_INSTANCES: u32 = 0;
+    pub const SUM_COUNTS: u32 = 1;
+    pub const SCATTER_INSTANCES: u32 = 2;
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct LaserPushConstants {
+    pub perspective_view: Mat4,
+    pub camera_position: Vec3,
+    // The distance over which lasers fade out as they approach the depth buffer.
+    pub depth_fade_distance: f32,
 }
 
 #[repr(C)]
@@ -68,6 +127,13 @@ pub struct CircleInstance {
     pub colour: Vec4,
 }
 
+#[repr(C)]
+#[derive(Clone, Copy, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct TranslucentVertex {
+    pub position: Vec3,
+    pub colour: Vec4,
+}
+
 #[repr(C)]
 #[derive(Clone, Copy, bytemuck::Pod, bytemuck::Zeroable)]
 pub struct RangeInstance {
@@ -97,3 +163,34 @@ pub struct DrawIndexedIndirect {
     // The instance ID of the first instance to draw.
     pub base_instance: u32,
 }
+
+#[test]
+fn test_instance_rotation_matches_rotor() {
+    // The same as `rotate` in `ship.vert`.
+    fn rotate(q: Vec4, v: Vec3) -> Vec3 {
+        let q_vector = q.truncated();
+        v + 2.0 * q_vector.cross(q_vector.cross(v) + q.w * v)
+    }
+
+    let rotor = ultraviolet::Rotor3::from_euler_angles(0.3, 1.2, -0.7);
+    let rotation = Vec4::from(rotor.into_quaternion_array());
+    let v = Vec3::new(1.0, 2.0, 3.0);
+
+    assert!((rotate(rotation, v) - rotor.into_matrix() * v).mag() < 0.0001);
+
+    assert_eq!(
+        Instance::pack_colour(Vec3::new(0.0, 0.5, 1.5), true),
+        [0, 128, 255, 255]
+    );
+}
+
+#[test]
+fn test_instance_layout() {
+    // `build_draws.comp` copies instances around word by word and reads the model id from the
+    // last one.
+    assert_eq!(std::mem::size_of::<Instance>(), 13 * 4);
+    assert_eq!(
+        bytemuck::cast::<_, [u32; 13]>(Instance::unused())[12],
+        u32::MAX
+    );
+}
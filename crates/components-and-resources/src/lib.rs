@@ -1,7 +1,15 @@
+pub mod assets;
+pub mod balance;
 pub mod components;
 pub mod formations;
+pub mod golden_image;
 pub mod gpu_structs;
+pub mod map_generation;
 pub mod model;
+pub mod mods;
 pub mod resources;
+pub mod save;
+pub mod spatial_hash;
 pub mod texture_manager;
 pub mod utils;
+pub mod world_hash;
@@ -0,0 +1,227 @@
+use crate::components::*;
+use crate::resources::SimClock;
+use crate::utils::{random_point_in_sphere, uniform_sphere_distribution};
+use bevy_ecs::prelude::{Bundle, World};
+use rand::Rng;
+use std::f32::consts::PI;
+use ultraviolet::{Rotor3, Vec3};
+
+// Friendly ships start around the origin and enemy ships this far along the x axis.
+pub const ENEMY_OFFSET: f32 = 500.0;
+
+// Reflects a point through the vertical axis halfway between the two fleets, so that whatever
+// is placed near one fleet has a twin in the same spot relative to the other.
+pub fn mirror(point: Vec3) -> Vec3 {
+    Vec3::new(ENEMY_OFFSET - point.x, point.y, -point.z)
+}
+
+// The asteroid belt is a flat slab around the friendly fleet.
+pub fn random_point_in_asteroid_belt<R: Rng>(rng: &mut R) -> Vec3 {
+    Vec3::new(
+        rng.gen_range(-400.0..400.0),
+        rng.gen_range(-50.0..=10.0),
+        rng.gen_range(-400.0..400.0),
+    )
+}
+
+pub fn asteroid_components<R: Rng>(position: Vec3, rng: &mut R) -> impl Bundle {
+    let facing = uniform_sphere_distribution(rng);
+
+    (
+        Position(position),
+        Rotation(Rotor3::from_rotation_between(Vec3::unit_y(), facing)),
+        RotationMatrix::default(),
+        ModelId::Asteroid,
+        WorldSpaceBoundingBox::default(),
+        Spin::new(uniform_sphere_distribution(rng)),
+        Scale(rng.gen_range(1.0..5.0)),
+        Health::new(1000.0),
+        Selectable,
+        CanBeMined::new(100.0),
+        MiningClaims::default(),
+        Obstacle,
+        // The belt slowly turns around the origin, taking about half an hour for a full
+        // revolution.
+        Orbiting {
+            center: Vec3::zero(),
+            angular_velocity: 0.0035,
+        },
+    )
+}
+
+pub const ASTEROID_DRIFT_TIME: f32 = 90.0;
+const MAP_EDGE: f32 = 900.0;
+
+// Picks a spot in the belt for a new cluster and starts it off past the edge of the map, so that
+// it drifts in over `ASTEROID_DRIFT_TIME`. Returns the asteroids' starting positions.
+pub fn drifting_asteroid_cluster<R: Rng>(rng: &mut R, clock: &SimClock) -> (Vec<Vec3>, Drifting) {
+    let destination = random_point_in_asteroid_belt(rng);
+    let angle = rng.gen_range(0.0..PI * 2.0);
+    let start = Vec3::new(
+        angle.cos() * MAP_EDGE,
+        destination.y,
+        angle.sin() * MAP_EDGE,
+    );
+
+    let positions = (0..rng.gen_range(5..9))
+        .map(|_| start + random_point_in_sphere(rng) * 40.0)
+        .collect();
+
+    let drifting = Drifting {
+        velocity: (destination - start) / ASTEROID_DRIFT_TIME,
+        until: clock.timer(ASTEROID_DRIFT_TIME),
+    };
+
+    (positions, drifting)
+}
+
+fn mirror_rotation(rotation: Rotor3) -> Rotor3 {
+    Rotor3::from_rotation_xz(PI) * rotation
+}
+
+fn random_point_on_friendly_side<R: Rng>(rng: &mut R) -> Vec3 {
+    Vec3::new(
+        rng.gen_range(50.0..200.0),
+        rng.gen_range(-50.0..50.0),
+        rng.gen_range(-300.0..300.0),
+    )
+}
+
+// Pickups come in mirrored pairs so that they're as easy for either side to reach.
+pub fn pickup_positions<R: Rng>(rng: &mut R) -> [Vec3; 2] {
+    let position = random_point_on_friendly_side(rng);
+    [position, mirror(position)]
+}
+
+// Everything is generated for the friendly half of the map and then mirrored, so neither side
+// gets a better start.
+pub fn spawn_skirmish_features<R: Rng>(world: &mut World, rng: &mut R) {
+    for _ in 0..2 {
+        let position = random_point_on_friendly_side(rng);
+        let rotation =
+            Rotor3::from_rotation_between(Vec3::unit_y(), uniform_sphere_distribution(rng));
+        let scale = rng.gen_range(4.0..7.0);
+
+        world
+            .spawn()
+            .insert_bundle(derelict_components(position, rotation, scale));
+        world.spawn().insert_bundle(derelict_components(
+            mirror(position),
+            mirror_rotation(rotation),
+            scale,
+        ));
+    }
+
+    for _ in 0..2 {
+        let center = random_point_on_friendly_side(rng);
+
+        for _ in 0..12 {
+            let position = center + random_point_in_sphere(rng) * 30.0;
+            let rotation =
+                Rotor3::from_rotation_between(Vec3::unit_y(), uniform_sphere_distribution(rng));
+
+            world
+                .spawn()
+                .insert_bundle(proximity_mine_components(position, rotation));
+            world.spawn().insert_bundle(proximity_mine_components(
+                mirror(position),
+                mirror_rotation(rotation),
+            ));
+        }
+    }
+
+    // One relic in the middle that both sides will race for, and one closer to each side.
+    world.spawn().insert_bundle(relic_components(
+        Vec3::new(ENEMY_OFFSET / 2.0, 0.0, 0.0),
+        Rotor3::identity(),
+    ));
+
+    let position = random_point_on_friendly_side(rng);
+
+    world
+        .spawn()
+        .insert_bundle(relic_components(position, Rotor3::identity()));
+    world
+        .spawn()
+        .insert_bundle(relic_components(mirror(position), Rotor3::identity()));
+
+    // A pair of comets on opposite sides of the same orbit around the middle of the map, so
+    // that each one is the mirror of the other.
+    let comet = Comet {
+        center: Vec3::new(ENEMY_OFFSET / 2.0, 0.0, 0.0),
+        major_axis: Vec3::new(0.0, 0.0, rng.gen_range(300.0..450.0)),
+        minor_axis: Vec3::new(rng.gen_range(100.0..200.0), 0.0, 0.0),
+        period: rng.gen_range(240.0..360.0),
+        phase: rng.gen_range(0.0..PI),
+        radius: 6.0,
+        damage_per_second: 20.0,
+        richness: 3.0,
+    };
+    let spin_axis = uniform_sphere_distribution(rng);
+
+    world.spawn().insert_bundle(comet_components(
+        Comet {
+            phase: comet.phase + PI,
+            ..comet.clone()
+        },
+        spin_axis,
+    ));
+    world
+        .spawn()
+        .insert_bundle(comet_components(comet, spin_axis));
+
+    for _ in 0..2 {
+        let position = random_point_on_friendly_side(rng);
+        let spin_axis = uniform_sphere_distribution(rng);
+
+        world
+            .spawn()
+            .insert_bundle(gas_cloud_components(position, spin_axis));
+        world
+            .spawn()
+            .insert_bundle(gas_cloud_components(mirror(position), spin_axis));
+    }
+
+    let position = random_point_on_friendly_side(rng);
+    let base_price = rng.gen_range(1.5..2.5);
+
+    world
+        .spawn()
+        .insert_bundle(trading_station_components(position, base_price));
+    world
+        .spawn()
+        .insert_bundle(trading_station_components(mirror(position), base_price));
+
+    for _ in 0..3 {
+        let position = random_point_on_friendly_side(rng);
+
+        world
+            .spawn()
+            .insert_bundle(sensor_relay_components(position));
+        world
+            .spawn()
+            .insert_bundle(sensor_relay_components(mirror(position)));
+    }
+}
+
+#[test]
+fn test_drifting_asteroid_cluster() {
+    use rand::SeedableRng;
+
+    let mut rng = rand::rngs::SmallRng::seed_from_u64(0);
+    let (positions, drifting) = drifting_asteroid_cluster(&mut rng, &SimClock::new(0.0));
+
+    for position in positions {
+        assert!(position.mag() > MAP_EDGE - 100.0);
+
+        let end = position + drifting.velocity * ASTEROID_DRIFT_TIME;
+        assert!(end.x.abs() < 450.0 && end.z.abs() < 450.0);
+    }
+}
+
+#[test]
+fn test_mirror() {
+    let point = Vec3::new(120.0, 15.0, -80.0);
+    assert_eq!(mirror(point), Vec3::new(380.0, 15.0, 80.0));
+    assert_eq!(mirror(mirror(point)), point);
+}
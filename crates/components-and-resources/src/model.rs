@@ -1,7 +1,7 @@
 use crate::gpu_structs::ModelVertex;
 use crate::texture_manager::TextureManager;
 use ray_collisions::{BoundingBox, DynamicBvh, Triangle};
-use ultraviolet::Vec3;
+use ultraviolet::{Vec3, Vec4};
 use wgpu::util::DeviceExt;
 
 pub struct Model {
@@ -10,6 +10,8 @@ pub struct Model {
     pub bounding_box: BoundingBox,
     pub diffuse_texture: u32,
     pub emissive_texture: u32,
+    pub normal_texture: u32,
+    pub metallic_roughness_texture: u32,
 }
 
 pub fn load_ship_model(
@@ -20,6 +22,80 @@ pub fn load_ship_model(
     merged_indices: &mut Vec<u16>,
     merged_bounding_boxes: &mut Vec<Vec3>,
     texture_manager: &mut TextureManager,
+) -> anyhow::Result<Model> {
+    let mut model = load_model_geometry(
+        bytes,
+        merged_vertices,
+        merged_indices,
+        merged_bounding_boxes,
+    )?;
+
+    let gltf = gltf::Gltf::from_slice(bytes)?;
+    let buffer_blob = gltf.blob.as_ref().unwrap();
+
+    let material = gltf.materials().next().unwrap();
+
+    let diffuse_texture = material
+        .pbr_metallic_roughness()
+        .base_color_texture()
+        .unwrap()
+        .texture();
+
+    let srgb = wgpu::TextureFormat::Rgba8UnormSrgb;
+
+    let diffuse_texture = load_image(&diffuse_texture.source(), buffer_blob, srgb, device, queue)?;
+    let emissive_texture = material.emissive_texture().unwrap().texture();
+    let emissive_texture =
+        load_image(&emissive_texture.source(), buffer_blob, srgb, device, queue)?;
+
+    model.diffuse_texture = texture_manager.add(diffuse_texture);
+    model.emissive_texture = texture_manager.add(emissive_texture);
+
+    model.normal_texture = match material.normal_texture() {
+        Some(normal_texture) => texture_manager.add(load_image(
+            &normal_texture.texture().source(),
+            buffer_blob,
+            wgpu::TextureFormat::Rgba8Unorm,
+            device,
+            queue,
+        )?),
+        None => texture_manager.flat_normal_map(device, queue),
+    };
+
+    let pbr = material.pbr_metallic_roughness();
+
+    model.metallic_roughness_texture = match pbr.metallic_roughness_texture() {
+        Some(metallic_roughness_texture) => texture_manager.add(load_image(
+            &metallic_roughness_texture.texture().source(),
+            buffer_blob,
+            wgpu::TextureFormat::Rgba8Unorm,
+            device,
+            queue,
+        )?),
+        // Bake the material factors into a texture so the shader doesn't need to care.
+        None => texture_manager.add_single_pixel_texture(
+            device,
+            queue,
+            "metallic roughness factors",
+            [
+                0,
+                (pbr.roughness_factor() * 255.0) as u8,
+                (pbr.metallic_factor() * 255.0) as u8,
+                255,
+            ],
+        ),
+    };
+
+    Ok(model)
+}
+
+// Everything about a model that the simulation needs, without touching the gpu. The textures are
+// left for `load_ship_model` to fill in.
+pub fn load_model_geometry(
+    bytes: &[u8],
+    merged_vertices: &mut Vec<ModelVertex>,
+    merged_indices: &mut Vec<u16>,
+    merged_bounding_boxes: &mut Vec<Vec3>,
 ) -> anyhow::Result<Model> {
     let gltf = gltf::Gltf::from_slice(bytes)?;
 
@@ -36,16 +112,14 @@ pub fn load_ship_model(
 
             let num_vertices = merged_vertices.len() as u16;
 
-            let read_indices = match reader.read_indices().unwrap() {
-                gltf::mesh::util::ReadIndices::U16(indices) => indices,
+            let read_indices: Vec<u16> = match reader.read_indices().unwrap() {
+                gltf::mesh::util::ReadIndices::U16(indices) => indices.collect(),
                 gltf::mesh::util::ReadIndices::U32(_) => {
                     return Err(anyhow::anyhow!("U32 indices not supported"))
                 }
                 _ => unreachable!(),
             };
 
-            indices.extend(read_indices.map(|index| index + num_vertices));
-
             let positions = reader.read_positions().unwrap();
             let normals = reader.read_normals().unwrap();
             let uvs = reader.read_tex_coords(0).unwrap().into_f32();
@@ -58,8 +132,13 @@ pub fn load_ship_model(
                         position: position.into(),
                         normal: normal.into(),
                         uv: uv.into(),
+                        tangent: Vec4::zero(),
                     });
-                })
+                });
+
+            compute_tangents(&mut merged_vertices[num_vertices as usize..], &read_indices);
+
+            indices.extend(read_indices.iter().map(|index| index + num_vertices));
         }
     }
 
@@ -90,21 +169,6 @@ pub fn load_ship_model(
 
     merged_indices.extend_from_slice(&indices);
 
-    let material = gltf.materials().next().unwrap();
-
-    let diffuse_texture = material
-        .pbr_metallic_roughness()
-        .base_color_texture()
-        .unwrap()
-        .texture();
-
-    let diffuse_texture = load_image(&diffuse_texture.source(), buffer_blob, device, queue)?;
-    let emissive_texture = material.emissive_texture().unwrap().texture();
-    let emissive_texture = load_image(&emissive_texture.source(), buffer_blob, device, queue)?;
-
-    let diffuse_texture = texture_manager.add(diffuse_texture);
-    let emissive_texture = texture_manager.add(emissive_texture);
-
     let bounding_box = BoundingBox::new(bounding_box.min.into(), bounding_box.max.into());
 
     merged_bounding_boxes.extend_from_slice(&bounding_box.corners());
@@ -113,14 +177,73 @@ pub fn load_ship_model(
         num_indices,
         acceleration_tree,
         bounding_box,
-        diffuse_texture,
-        emissive_texture,
+        diffuse_texture: 0,
+        emissive_texture: 0,
+        normal_texture: 0,
+        metallic_roughness_texture: 0,
     })
 }
 
+// Accumulate the per-triangle tangents and bitangents from the uv gradients, then
+// orthogonalise the tangents against the vertex normals.
+fn compute_tangents(vertices: &mut [ModelVertex], indices: &[u16]) {
+    let mut tangents = vec![Vec3::zero(); vertices.len()];
+    let mut bitangents = vec![Vec3::zero(); vertices.len()];
+
+    for triangle in indices.chunks(3) {
+        let a = vertices[triangle[0] as usize];
+        let b = vertices[triangle[1] as usize];
+        let c = vertices[triangle[2] as usize];
+
+        let edge_b_a = b.position - a.position;
+        let edge_c_a = c.position - a.position;
+        let uv_b_a = b.uv - a.uv;
+        let uv_c_a = c.uv - a.uv;
+
+        let determinant = uv_b_a.x * uv_c_a.y - uv_c_a.x * uv_b_a.y;
+
+        if determinant.abs() < std::f32::EPSILON {
+            continue;
+        }
+
+        let tangent = (edge_b_a * uv_c_a.y - edge_c_a * uv_b_a.y) / determinant;
+        let bitangent = (edge_c_a * uv_b_a.x - edge_b_a * uv_c_a.x) / determinant;
+
+        for &index in triangle {
+            tangents[index as usize] += tangent;
+            bitangents[index as usize] += bitangent;
+        }
+    }
+
+    for ((vertex, tangent), bitangent) in vertices.iter_mut().zip(tangents).zip(bitangents) {
+        let normal = vertex.normal;
+        let mut tangent = tangent - normal * normal.dot(tangent);
+
+        if tangent.mag_sq() < std::f32::EPSILON {
+            // Degenerate uvs, so any vector perpendicular to the normal will do.
+            tangent = normal.cross(if normal.x.abs() < 0.9 {
+                Vec3::unit_x()
+            } else {
+                Vec3::unit_y()
+            });
+        }
+
+        let tangent = tangent.normalized();
+
+        let handedness = if normal.cross(tangent).dot(bitangent) < 0.0 {
+            -1.0
+        } else {
+            1.0
+        };
+
+        vertex.tangent = Vec4::new(tangent.x, tangent.y, tangent.z, handedness);
+    }
+}
+
 fn load_image(
     image: &gltf::Image,
     buffer_blob: &[u8],
+    format: wgpu::TextureFormat,
     device: &wgpu::Device,
     queue: &wgpu::Queue,
 ) -> anyhow::Result<wgpu::TextureView> {
@@ -133,11 +256,12 @@ fn load_image(
     let image_end = image_start + image_view.length();
     let image_bytes = &buffer_blob[image_start..image_end];
 
-    load_image_from_bytes(image_bytes, device, queue)
+    load_image_from_bytes(image_bytes, format, device, queue)
 }
 
 pub fn load_image_from_bytes(
     image_bytes: &[u8],
+    format: wgpu::TextureFormat,
     device: &wgpu::Device,
     queue: &wgpu::Queue,
 ) -> anyhow::Result<wgpu::TextureView> {
@@ -145,26 +269,59 @@ pub fn load_image_from_bytes(
 
     let image = match image {
         image::DynamicImage::ImageRgba8(image) => image,
-        _ => panic!(),
+        // Textures from mods might not have an alpha channel.
+        image => image.to_rgba8(),
     };
 
+    let (width, height) = image.dimensions();
+    let (mip_level_count, data) = generate_mipmaps(image);
+
     Ok(device
         .create_texture_with_data(
             queue,
             &wgpu::TextureDescriptor {
                 label: None,
                 size: wgpu::Extent3d {
-                    width: image.width(),
-                    height: image.height(),
+                    width,
+                    height,
                     depth_or_array_layers: 1,
                 },
-                mip_level_count: 1,
+                mip_level_count,
                 sample_count: 1,
                 dimension: wgpu::TextureDimension::D2,
-                format: wgpu::TextureFormat::Rgba8UnormSrgb,
+                format,
                 usage: wgpu::TextureUsages::COPY_DST | wgpu::TextureUsages::TEXTURE_BINDING,
             },
-            &*image,
+            &data,
         )
         .create_view(&wgpu::TextureViewDescriptor::default()))
 }
+
+// Halves the image down to 1x1, with each level stored after the previous one as
+// `create_texture_with_data` expects.
+fn generate_mipmaps(image: image::RgbaImage) -> (u32, Vec<u8>) {
+    let mut data = image.to_vec();
+    let mut level = image;
+    let mut count = 1;
+
+    while level.width() > 1 || level.height() > 1 {
+        level = image::imageops::resize(
+            &level,
+            (level.width() / 2).max(1),
+            (level.height() / 2).max(1),
+            image::imageops::FilterType::Triangle,
+        );
+        data.extend_from_slice(&level);
+        count += 1;
+    }
+
+    (count, data)
+}
+
+#[test]
+fn test_generate_mipmaps() {
+    let (count, data) = generate_mipmaps(image::RgbaImage::new(8, 2));
+
+    assert_eq!(count, 4);
+    assert_eq!(data.len(), (8 * 2 + 4 + 2 + 1) * 4);
+}
@@ -0,0 +1,274 @@
+use crate::assets::{model_name, Assets};
+use crate::components::{ModelId, ShipType};
+use crate::model::{load_image_from_bytes, Model};
+use crate::resources::{Models, ShipCatalog, ShipStats};
+use crate::texture_manager::TextureManager;
+use std::collections::HashMap;
+use std::fmt::Debug;
+use std::path::{Path, PathBuf};
+
+// Textures that aren't part of a model but can still be replaced.
+const MISC_TEXTURES: [&str; 1] = ["mined_out_asteroid"];
+
+struct Entry<K, V> {
+    key: K,
+    value: V,
+    mod_name: String,
+}
+
+// Everything found in a directory of mods, each of which is a directory containing any of:
+// - `models/<model>.glb` to replace a model, such as `models/shipyard.glb`.
+// - `textures/<name>.png` to replace a texture, such as `textures/carrier_diffuse.png`.
+// - `ships.ron`, a map of ship types to their build times and costs.
+// Mods are applied in alphabetical order, so later mods win any conflicts.
+#[derive(Default)]
+pub struct ModFiles {
+    models: Vec<Entry<ModelId, PathBuf>>,
+    textures: Vec<Entry<String, PathBuf>>,
+    ships: Vec<Entry<ShipType, ShipStats>>,
+    // Conflicts between mods and files that weren't recognised, for logging.
+    pub warnings: Vec<String>,
+}
+
+impl ModFiles {
+    pub fn scan(directory: &Path) -> anyhow::Result<Self> {
+        let mut mod_files = Self::default();
+
+        let texture_names: Vec<String> = Models::ARRAY
+            .iter()
+            .flat_map(|&model_id| {
+                ["diffuse", "emissive", "normal", "metallic_roughness"]
+                    .iter()
+                    .map(move |slot| format!("{}_{}", model_name(model_id), slot))
+            })
+            .chain(MISC_TEXTURES.iter().map(|name| name.to_string()))
+            .collect();
+
+        for mod_directory in sorted_entries(directory)? {
+            if !mod_directory.is_dir() {
+                continue;
+            }
+
+            let mod_name = file_stem(&mod_directory);
+
+            for path in sorted_entries(&mod_directory.join("models"))? {
+                let name = file_stem(&path);
+
+                match Models::ARRAY.iter().find(|&&id| model_name(id) == name) {
+                    Some(&model_id) => merge(
+                        &mut mod_files.models,
+                        &mut mod_files.warnings,
+                        model_id,
+                        path,
+                        &mod_name,
+                    ),
+                    None => mod_files.warnings.push(format!(
+                        "'{}' has a model for '{}', which isn't in the game",
+                        mod_name, name
+                    )),
+                }
+            }
+
+            for path in sorted_entries(&mod_directory.join("textures"))? {
+                let name = file_stem(&path);
+
+                if texture_names.contains(&name) {
+                    merge(
+                        &mut mod_files.textures,
+                        &mut mod_files.warnings,
+                        name,
+                        path,
+                        &mod_name,
+                    );
+                } else {
+                    mod_files.warnings.push(format!(
+                        "'{}' has a texture for '{}', which isn't in the game",
+                        mod_name, name
+                    ));
+                }
+            }
+
+            let ships_path = mod_directory.join("ships.ron");
+
+            if ships_path.exists() {
+                let ships: HashMap<ShipType, ShipStats> =
+                    ron::de::from_str(&std::fs::read_to_string(&ships_path)?)?;
+
+                for (ship_type, stats) in ships {
+                    merge(
+                        &mut mod_files.ships,
+                        &mut mod_files.warnings,
+                        ship_type,
+                        stats,
+                        &mod_name,
+                    );
+                }
+            }
+        }
+
+        Ok(mod_files)
+    }
+
+    pub fn model(&self, model_id: ModelId) -> Option<&Path> {
+        self.models
+            .iter()
+            .find(|entry| entry.key == model_id)
+            .map(|entry| entry.value.as_path())
+    }
+
+    // A model from whichever mod replaces it, or from the game's own assets.
+    pub fn read_model(&self, model_id: ModelId, assets: &Assets) -> anyhow::Result<Vec<u8>> {
+        match self.model(model_id) {
+            Some(path) => crate::assets::read(path),
+            None => crate::assets::read(&assets.model(model_id)),
+        }
+    }
+
+    pub fn texture(&self, name: &str) -> Option<&Path> {
+        self.textures
+            .iter()
+            .find(|entry| entry.key == name)
+            .map(|entry| entry.value.as_path())
+    }
+
+    pub fn ship_catalog(&self) -> ShipCatalog {
+        let mut catalog = ShipCatalog::default();
+
+        for entry in &self.ships {
+            catalog.set(entry.key, entry.value);
+        }
+
+        catalog
+    }
+
+    // Swaps the textures of a freshly loaded model for any that mods replace.
+    pub fn apply_texture_overrides(
+        &self,
+        model_id: ModelId,
+        model: &mut Model,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        texture_manager: &mut TextureManager,
+    ) -> anyhow::Result<()> {
+        let srgb = wgpu::TextureFormat::Rgba8UnormSrgb;
+        let linear = wgpu::TextureFormat::Rgba8Unorm;
+
+        for (slot, index, format) in [
+            ("diffuse", &mut model.diffuse_texture, srgb),
+            ("emissive", &mut model.emissive_texture, srgb),
+            ("normal", &mut model.normal_texture, linear),
+            (
+                "metallic_roughness",
+                &mut model.metallic_roughness_texture,
+                linear,
+            ),
+        ]
+        .iter_mut()
+        {
+            let name = format!("{}_{}", model_name(model_id), slot);
+
+            if let Some(path) = self.texture(&name) {
+                let bytes = crate::assets::read(path)?;
+                **index =
+                    texture_manager.add(load_image_from_bytes(&bytes, *format, device, queue)?);
+            }
+        }
+
+        Ok(())
+    }
+}
+
+fn merge<K: PartialEq + Debug, V>(
+    entries: &mut Vec<Entry<K, V>>,
+    warnings: &mut Vec<String>,
+    key: K,
+    value: V,
+    mod_name: &str,
+) {
+    let new_entry = Entry {
+        key,
+        value,
+        mod_name: mod_name.to_string(),
+    };
+
+    match entries.iter_mut().find(|entry| entry.key == new_entry.key) {
+        Some(entry) => {
+            warnings.push(format!(
+                "'{}' overrides {:?} from '{}'",
+                mod_name, new_entry.key, entry.mod_name
+            ));
+            *entry = new_entry;
+        }
+        None => entries.push(new_entry),
+    }
+}
+
+// A missing directory just means there's nothing in it.
+fn sorted_entries(directory: &Path) -> anyhow::Result<Vec<PathBuf>> {
+    if !directory.is_dir() {
+        return Ok(Vec::new());
+    }
+
+    let mut entries = std::fs::read_dir(directory)?
+        .map(|entry| Ok(entry?.path()))
+        .collect::<anyhow::Result<Vec<_>>>()?;
+    entries.sort();
+    Ok(entries)
+}
+
+fn file_stem(path: &Path) -> String {
+    path.file_stem()
+        .map(|stem| stem.to_string_lossy().into_owned())
+        .unwrap_or_default()
+}
+
+#[test]
+fn test_mod_files() {
+    let directory = std::env::temp_dir().join(format!("fleet_mods_{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&directory);
+
+    let write = |path: &str, contents: &str| {
+        let path = directory.join(path);
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(path, contents).unwrap();
+    };
+
+    write("a_first/models/shipyard.glb", "");
+    write("a_first/textures/carrier_diffuse.png", "");
+    write(
+        "a_first/ships.ron",
+        "{Fighter: (build_time: 2.0, minerals: 10.0, gas: 0.0)}",
+    );
+    write("b_second/textures/carrier_diffuse.png", "");
+    write("b_second/models/titan.glb", "");
+
+    let mod_files = ModFiles::scan(&directory).unwrap();
+    std::fs::remove_dir_all(&directory).unwrap();
+
+    assert_eq!(
+        mod_files.model(ModelId::Shipyard),
+        Some(directory.join("a_first/models/shipyard.glb").as_path())
+    );
+    assert_eq!(mod_files.model(ModelId::Carrier), None);
+    // The later mod wins.
+    assert_eq!(
+        mod_files.texture("carrier_diffuse"),
+        Some(
+            directory
+                .join("b_second/textures/carrier_diffuse.png")
+                .as_path()
+        )
+    );
+    assert_eq!(mod_files.warnings.len(), 2);
+
+    let catalog = mod_files.ship_catalog();
+    assert_eq!(catalog.build_time(ShipType::Fighter), 2.0);
+    assert_eq!(catalog.build_cost(ShipType::Fighter).minerals, 10.0);
+    assert_eq!(
+        catalog.stats(ShipType::Carrier),
+        ShipStats::built_in(ShipType::Carrier)
+    );
+
+    // No mods at all is fine.
+    assert!(ModFiles::scan(&directory).unwrap().warnings.is_empty());
+}
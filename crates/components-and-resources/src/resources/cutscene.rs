@@ -0,0 +1,231 @@
+use std::ops::{Add, Mul, Sub};
+use ultraviolet::Vec3;
+
+#[derive(Clone, Copy)]
+pub struct CameraKeyframe {
+    pub time: f32,
+    pub center: Vec3,
+    pub longitude: f32,
+    pub latitude: f32,
+    pub distance: f32,
+}
+
+pub struct Subtitle {
+    pub start: f32,
+    pub end: f32,
+    pub text: String,
+}
+
+#[derive(Default)]
+pub struct Cutscene {
+    pub keyframes: Vec<CameraKeyframe>,
+    pub subtitles: Vec<Subtitle>,
+}
+
+impl Cutscene {
+    pub fn with_keyframe(mut self, keyframe: CameraKeyframe) -> Self {
+        self.keyframes.push(keyframe);
+        self
+    }
+
+    pub fn with_subtitle(mut self, start: f32, end: f32, text: &str) -> Self {
+        self.subtitles.push(Subtitle {
+            start,
+            end,
+            text: text.into(),
+        });
+        self
+    }
+
+    // Circles the camera around a point, for victories and other moments worth a look.
+    pub fn flyover(center: Vec3, subtitle: &str) -> Self {
+        const DURATION: f32 = 10.0;
+        const KEYFRAMES: usize = 5;
+
+        let keyframes = (0..KEYFRAMES).map(|i| {
+            let fraction = i as f32 / (KEYFRAMES - 1) as f32;
+
+            CameraKeyframe {
+                time: fraction * DURATION,
+                center,
+                longitude: fraction * std::f32::consts::PI * 1.5,
+                latitude: 0.6 - fraction * 0.3,
+                distance: 200.0 - fraction * 80.0,
+            }
+        });
+
+        let cutscene = Self {
+            keyframes: keyframes.collect(),
+            subtitles: Vec::new(),
+        };
+
+        if subtitle.is_empty() {
+            cutscene
+        } else {
+            cutscene.with_subtitle(1.0, DURATION - 1.0, subtitle)
+        }
+    }
+
+    pub fn duration(&self) -> f32 {
+        let keyframes_end = self.keyframes.last().map(|keyframe| keyframe.time);
+        let subtitles_end = self.subtitles.iter().map(|subtitle| subtitle.end);
+
+        keyframes_end
+            .into_iter()
+            .chain(subtitles_end)
+            .fold(0.0, f32::max)
+    }
+
+    // Interpolate between the keyframes with a catmull-rom spline so that the camera
+    // moves smoothly through them.
+    pub fn camera_at(&self, time: f32) -> Option<CameraKeyframe> {
+        let last = self.keyframes.len().checked_sub(1)?;
+
+        let index = self
+            .keyframes
+            .iter()
+            .rposition(|keyframe| keyframe.time <= time)
+            .unwrap_or(0);
+
+        if index == last {
+            return Some(self.keyframes[last]);
+        }
+
+        let p0 = self.keyframes[index.saturating_sub(1)];
+        let p1 = self.keyframes[index];
+        let p2 = self.keyframes[index + 1];
+        let p3 = self.keyframes[(index + 2).min(last)];
+
+        let t = ((time - p1.time) / (p2.time - p1.time)).max(0.0).min(1.0);
+
+        let spline = |get: fn(&CameraKeyframe) -> f32| {
+            catmull_rom(get(&p0), get(&p1), get(&p2), get(&p3), t)
+        };
+
+        Some(CameraKeyframe {
+            time,
+            center: catmull_rom(p0.center, p1.center, p2.center, p3.center, t),
+            longitude: spline(|keyframe| keyframe.longitude),
+            latitude: spline(|keyframe| keyframe.latitude),
+            distance: spline(|keyframe| keyframe.distance),
+        })
+    }
+
+    pub fn subtitle_at(&self, time: f32) -> Option<&str> {
+        self.subtitles
+            .iter()
+            .find(|subtitle| subtitle.start <= time && time < subtitle.end)
+            .map(|subtitle| &subtitle.text[..])
+    }
+}
+
+fn catmull_rom<T>(p0: T, p1: T, p2: T, p3: T, t: f32) -> T
+where
+    T: Copy + Add<Output = T> + Sub<Output = T> + Mul<f32, Output = T>,
+{
+    let t2 = t * t;
+    let t3 = t2 * t;
+
+    (p1 * 2.0
+        + (p2 - p0) * t
+        + (p0 * 2.0 - p1 * 5.0 + p2 * 4.0 - p3) * t2
+        + (p1 * 3.0 - p0 - p2 * 3.0 + p3) * t3)
+        * 0.5
+}
+
+#[derive(Default)]
+pub struct CutscenePlayer {
+    cutscene: Option<Cutscene>,
+    elapsed: f32,
+    letterbox: f32,
+}
+
+impl CutscenePlayer {
+    // How much of the screen height each of the letterbox bars takes up.
+    pub const LETTERBOX_HEIGHT: f32 = 0.12;
+
+    pub fn play(&mut self, cutscene: Cutscene) {
+        self.cutscene = Some(cutscene);
+        self.elapsed = 0.0;
+    }
+
+    pub fn skip(&mut self) {
+        self.cutscene = None;
+    }
+
+    pub fn is_playing(&self) -> bool {
+        self.cutscene.is_some()
+    }
+
+    pub fn current(&self) -> Option<(&Cutscene, f32)> {
+        self.cutscene
+            .as_ref()
+            .map(|cutscene| (cutscene, self.elapsed))
+    }
+
+    // Returns the fraction of the screen each letterbox bar should cover.
+    pub fn letterbox(&self) -> f32 {
+        self.letterbox * Self::LETTERBOX_HEIGHT
+    }
+
+    pub fn advance(&mut self, delta_time: f32) {
+        let target_letterbox = if self.is_playing() { 1.0 } else { 0.0 };
+        let letterbox_step = delta_time * 2.0;
+
+        self.letterbox = if self.letterbox < target_letterbox {
+            (self.letterbox + letterbox_step).min(target_letterbox)
+        } else {
+            (self.letterbox - letterbox_step).max(target_letterbox)
+        };
+
+        if let Some(cutscene) = &self.cutscene {
+            self.elapsed += delta_time;
+
+            if self.elapsed >= cutscene.duration() {
+                self.cutscene = None;
+            }
+        }
+    }
+}
+
+#[test]
+fn test_camera_path_passes_through_keyframes() {
+    let keyframe = |time, distance| CameraKeyframe {
+        time,
+        center: Vec3::broadcast(time),
+        longitude: 1.0,
+        latitude: 0.0,
+        distance,
+    };
+
+    let cutscene = Cutscene::default()
+        .with_keyframe(keyframe(0.0, 100.0))
+        .with_keyframe(keyframe(2.0, 50.0))
+        .with_keyframe(keyframe(4.0, 10.0));
+
+    assert_eq!(cutscene.duration(), 4.0);
+
+    for &(time, distance) in &[(0.0, 100.0), (2.0, 50.0), (4.0, 10.0), (10.0, 10.0)] {
+        let camera = cutscene.camera_at(time).unwrap();
+        assert!((camera.distance - distance).abs() < 0.001);
+    }
+
+    let halfway = cutscene.camera_at(1.0).unwrap();
+    assert!(halfway.distance < 100.0 && halfway.distance > 50.0);
+}
+
+#[test]
+fn test_flyover() {
+    let center = Vec3::new(100.0, 0.0, -50.0);
+    let cutscene = Cutscene::flyover(center, "Victory");
+
+    assert_eq!(cutscene.duration(), 10.0);
+    assert_eq!(cutscene.subtitle_at(5.0), Some("Victory"));
+
+    for time in 0..10 {
+        let camera = cutscene.camera_at(time as f32).unwrap();
+        assert!((camera.center - center).mag() < 0.001);
+    }
+
+    assert!(Cutscene::flyover(center, "").subtitles.is_empty());
+}
@@ -0,0 +1,184 @@
+use bevy_ecs::prelude::Entity;
+use std::collections::VecDeque;
+use ultraviolet::Vec3;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Severity {
+    Info,
+    Warning,
+    Danger,
+}
+
+impl Severity {
+    pub fn colour(self) -> [f32; 4] {
+        match self {
+            Self::Info => [0.75, 0.75, 0.75, 1.0],
+            Self::Warning => [1.0, 0.8, 0.2, 1.0],
+            Self::Danger => [1.0, 0.3, 0.3, 1.0],
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct GameEvent {
+    // In simulation time.
+    pub time: f32,
+    pub severity: Severity,
+    pub text: String,
+}
+
+// Everything that's happened over the game, shown in a panel that can be scrolled back through.
+// Unlike notifications, events stay around until there are too many of them.
+#[derive(Default, Clone)]
+pub struct GameEvents {
+    events: VecDeque<GameEvent>,
+    // How many lines back from the latest event the panel is scrolled.
+    scroll: usize,
+    next_attack_report: f32,
+    // Set while the cursor is over the panel, so that the mouse wheel scrolls it instead of
+    // zooming the camera.
+    pub hovered: bool,
+}
+
+impl GameEvents {
+    pub const MAX_EVENTS: usize = 200;
+    pub const LINES_SHOWN: usize = 8;
+    pub const WIDTH: f32 = 320.0;
+    // Attacks are only reported this often so that a long fight doesn't drown everything else out.
+    pub const ATTACK_REPORT_INTERVAL: f32 = 15.0;
+
+    pub fn push(&mut self, time: f32, severity: Severity, text: impl Into<String>) {
+        if self.events.len() == Self::MAX_EVENTS {
+            self.events.pop_front();
+        }
+
+        self.events.push_back(GameEvent {
+            time,
+            severity,
+            text: text.into(),
+        });
+
+        // Keep the same events in view while scrolled back.
+        if self.scroll > 0 {
+            self.scroll = (self.scroll + 1).min(self.max_scroll());
+        }
+    }
+
+    // Returns whether the attack was logged.
+    pub fn report_attack(&mut self, time: f32, text: impl Into<String>) -> bool {
+        if time < self.next_attack_report {
+            return false;
+        }
+
+        self.next_attack_report = time + Self::ATTACK_REPORT_INTERVAL;
+        self.push(time, Severity::Danger, text);
+        true
+    }
+
+    // Positive values go back in time.
+    pub fn scroll_by(&mut self, lines: i32) {
+        let scroll = self.scroll as i32 + lines;
+        self.scroll = (scroll.max(0) as usize).min(self.max_scroll());
+    }
+
+    pub fn scrolled_back(&self) -> usize {
+        self.scroll
+    }
+
+    fn max_scroll(&self) -> usize {
+        self.events.len().saturating_sub(Self::LINES_SHOWN)
+    }
+
+    // Oldest first.
+    pub fn visible(&self) -> impl Iterator<Item = &GameEvent> {
+        let end = self.events.len() - self.scroll;
+        let start = end.saturating_sub(Self::LINES_SHOWN);
+        self.events.range(start..end)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.events.is_empty()
+    }
+}
+
+pub struct DamageEvent {
+    pub target: Entity,
+    pub position: Vec3,
+}
+
+// Every hit on a ship this tick, for anything that wants to react to ships being damaged. Cleared
+// at the end of each tick.
+#[derive(Default)]
+pub struct DamageEvents(pub Vec<DamageEvent>);
+
+// Where a friendly ship was last hit somewhere the camera couldn't see.
+#[derive(Default, Clone)]
+pub struct AttackAlert {
+    pub location: Option<Vec3>,
+    // In real time.
+    shown_until: f32,
+}
+
+impl AttackAlert {
+    pub const DURATION: f32 = 5.0;
+
+    pub fn raise(&mut self, location: Vec3, real_time: f32) {
+        self.location = Some(location);
+        self.shown_until = real_time + Self::DURATION;
+    }
+
+    pub fn is_shown(&self, real_time: f32) -> bool {
+        self.location.is_some() && real_time < self.shown_until
+    }
+
+    pub fn dismiss(&mut self) {
+        self.shown_until = 0.0;
+    }
+}
+
+#[test]
+fn test_game_events() {
+    let mut events = GameEvents::default();
+
+    for i in 0..10 {
+        events.push(i as f32, Severity::Info, format!("Event {}", i));
+    }
+
+    let texts = |events: &GameEvents| -> Vec<String> {
+        events.visible().map(|event| event.text.clone()).collect()
+    };
+
+    assert_eq!(texts(&events).len(), GameEvents::LINES_SHOWN);
+    assert_eq!(texts(&events)[0], "Event 2");
+
+    // Can't scroll past the first event.
+    events.scroll_by(5);
+    assert_eq!(events.scrolled_back(), 2);
+    assert_eq!(texts(&events)[0], "Event 0");
+
+    // New events don't move the view while scrolled back.
+    events.push(10.0, Severity::Info, "Event 10");
+    assert_eq!(texts(&events)[0], "Event 0");
+
+    events.scroll_by(-10);
+    assert_eq!(texts(&events).last().unwrap(), "Event 10");
+
+    assert!(events.report_attack(20.0, "Under attack"));
+    assert!(!events.report_attack(25.0, "Under attack"));
+    assert!(events.report_attack(35.0, "Under attack"));
+}
+
+#[test]
+fn test_attack_alert() {
+    let mut alert = AttackAlert::default();
+    assert!(!alert.is_shown(0.0));
+
+    alert.raise(Vec3::unit_x(), 10.0);
+    assert!(alert.is_shown(12.0));
+    assert!(!alert.is_shown(10.0 + AttackAlert::DURATION));
+
+    // The location sticks around so that it can still be jumped to.
+    alert.dismiss();
+    assert!(!alert.is_shown(12.0));
+    assert_eq!(alert.location, Some(Vec3::unit_x()));
+}
@@ -1,5 +1,7 @@
+use super::Anchor;
 use ultraviolet::Vec2;
 use wgpu_glyph::ab_glyph::{FontRef, PxScale};
+use wgpu_glyph::{HorizontalAlign, Layout};
 
 pub struct GlyphLayoutCache {
     glyph_brush: wgpu_glyph::GlyphBrush<(), FontRef<'static>>,
@@ -20,6 +22,34 @@ impl GlyphLayoutCache {
 
     pub fn start_section(&mut self, position: Vec2, dpi_factor: f32) -> GlyphBrushSection {
         self.glyph_section.screen_position = position.into();
+        self.glyph_section.layout = Layout::default();
+
+        GlyphBrushSection {
+            inner: self,
+            scale: PxScale::from(16.0 * dpi_factor),
+        }
+    }
+
+    pub fn start_centered_section(&mut self, position: Vec2, dpi_factor: f32) -> GlyphBrushSection {
+        self.glyph_section.screen_position = position.into();
+        self.glyph_section.layout = Layout::default().h_align(HorizontalAlign::Center);
+
+        GlyphBrushSection {
+            inner: self,
+            scale: PxScale::from(16.0 * dpi_factor),
+        }
+    }
+
+    // For a block of text `height` pixels tall placed at a corner or edge of the screen.
+    pub fn start_anchored_section(
+        &mut self,
+        anchor: Anchor,
+        height: f32,
+        screen: Vec2,
+        dpi_factor: f32,
+    ) -> GlyphBrushSection {
+        self.glyph_section.screen_position = anchor.text_position(height, screen).into();
+        self.glyph_section.layout = Layout::default().h_align(anchor.h_align());
 
         GlyphBrushSection {
             inner: self,
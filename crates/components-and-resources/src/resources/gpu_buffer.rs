@@ -1,5 +1,7 @@
-use crate::gpu_structs::{DrawIndexedIndirect, Instance};
+use crate::gpu_structs::{DrawIndexedIndirect, Instance, Vertex2D};
 use crate::resources::Models;
+use bevy_ecs::prelude::Entity;
+use std::collections::HashMap;
 
 pub struct GpuBuffer<T> {
     staging: Vec<T>,
@@ -67,126 +69,303 @@ impl<T: Copy + bytemuck::Pod> GpuBuffer<T> {
     }
 }
 
+// 2D vertices drawn as filled triangles, where `GpuBuffer<Vertex2D>` is drawn as lines.
+pub struct Triangles2DBuffer(pub GpuBuffer<Vertex2D>);
+
+// Friendly cloaked ships are drawn separately so that they can be made see-through.
+pub struct CloakedShipBuffer(pub ShipBuffer);
+
+// Every entity that's drawn keeps the same slot in a persistent instance buffer for as long as
+// it's visible, so only the instances that changed need to be uploaded. A compute pass then
+// sorts the instances by model and builds the indirect draws from them.
 pub struct ShipBuffer {
-    staging: [Vec<Instance>; Models::COUNT],
-    buffer: wgpu::Buffer,
+    slots: HashMap<Entity, u32>,
+    instances: Vec<Instance>,
+    // Whether each slot was staged since the last upload. Slots that weren't are freed.
+    staged: Vec<bool>,
+    free_slots: Vec<u32>,
+    changed_slots: Vec<u32>,
+    model_counts: [u32; Models::COUNT],
+    capacity: usize,
+    instance_buffer: wgpu::Buffer,
+    sorted_instance_buffer: wgpu::Buffer,
     draw_indirect_buffer: wgpu::Buffer,
-    draw_indirect_count: u32,
-    capacity_in_bytes: usize,
+    scatter_offsets_buffer: wgpu::Buffer,
+    bind_group: wgpu::BindGroup,
+    label: &'static str,
 }
 
 impl ShipBuffer {
-    const LABEL: &'static str = "ship instance buffer";
-
-    pub fn new(device: &wgpu::Device) -> Self {
-        let capacity_in_bytes = std::mem::size_of::<Instance>() * Models::COUNT;
+    pub fn new(device: &wgpu::Device, label: &'static str) -> Self {
+        let capacity = Models::COUNT;
+        let (instance_buffer, sorted_instance_buffer) =
+            Self::create_instance_buffers(device, label, capacity);
+
+        let draw_indirect_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("draw indirect buffer"),
+            size: (std::mem::size_of::<DrawIndexedIndirect>() * Models::COUNT) as u64,
+            usage: wgpu::BufferUsages::COPY_DST
+                | wgpu::BufferUsages::INDIRECT
+                | wgpu::BufferUsages::STORAGE,
+            mapped_at_creation: false,
+        });
+
+        let scatter_offsets_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("scatter offsets buffer"),
+            size: (std::mem::size_of::<u32>() * Models::COUNT) as u64,
+            usage: wgpu::BufferUsages::STORAGE,
+            mapped_at_creation: false,
+        });
+
+        let bind_group = Self::create_bind_group(
+            device,
+            &instance_buffer,
+            &sorted_instance_buffer,
+            &draw_indirect_buffer,
+            &scatter_offsets_buffer,
+        );
 
         Self {
-            staging: Default::default(),
-            capacity_in_bytes,
-            buffer: device.create_buffer(&wgpu::BufferDescriptor {
-                label: Some(Self::LABEL),
-                size: capacity_in_bytes as u64,
-                usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::VERTEX,
+            slots: HashMap::new(),
+            instances: Vec::new(),
+            staged: Vec::new(),
+            free_slots: Vec::new(),
+            changed_slots: Vec::new(),
+            model_counts: [0; Models::COUNT],
+            capacity,
+            instance_buffer,
+            sorted_instance_buffer,
+            draw_indirect_buffer,
+            scatter_offsets_buffer,
+            bind_group,
+            label,
+        }
+    }
+
+    // Also used by the draw building pipeline. Identical layouts are compatible with each other.
+    pub fn create_bind_group_layout(device: &wgpu::Device) -> wgpu::BindGroupLayout {
+        let storage = |binding, read_only| wgpu::BindGroupLayoutEntry {
+            binding,
+            visibility: wgpu::ShaderStages::COMPUTE,
+            ty: wgpu::BindingType::Buffer {
+                ty: wgpu::BufferBindingType::Storage { read_only },
+                has_dynamic_offset: false,
+                min_binding_size: None,
+            },
+            count: None,
+        };
+
+        device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("ship buffer bind group layout"),
+            entries: &[
+                storage(0, true),
+                storage(1, false),
+                storage(2, false),
+                storage(3, false),
+            ],
+        })
+    }
+
+    fn create_instance_buffers(
+        device: &wgpu::Device,
+        label: &'static str,
+        capacity: usize,
+    ) -> (wgpu::Buffer, wgpu::Buffer) {
+        let size = (std::mem::size_of::<Instance>() * capacity) as u64;
+
+        (
+            device.create_buffer(&wgpu::BufferDescriptor {
+                label: Some(label),
+                size,
+                usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::STORAGE,
                 mapped_at_creation: false,
             }),
-            draw_indirect_buffer: device.create_buffer(&wgpu::BufferDescriptor {
-                label: Some("draw indirect buffer"),
-                size: (std::mem::size_of::<DrawIndexedIndirect>() * Models::COUNT) as u64,
-                usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::INDIRECT,
+            device.create_buffer(&wgpu::BufferDescriptor {
+                label: Some("sorted ship instance buffer"),
+                size,
+                usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::STORAGE,
                 mapped_at_creation: false,
             }),
-            draw_indirect_count: 0,
-        }
+        )
     }
 
-    pub fn clear(&mut self) {
-        for buffer in &mut self.staging {
-            buffer.clear();
-        }
+    fn create_bind_group(
+        device: &wgpu::Device,
+        instance_buffer: &wgpu::Buffer,
+        sorted_instance_buffer: &wgpu::Buffer,
+        draw_indirect_buffer: &wgpu::Buffer,
+        scatter_offsets_buffer: &wgpu::Buffer,
+    ) -> wgpu::BindGroup {
+        device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("ship buffer bind group"),
+            layout: &Self::create_bind_group_layout(device),
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: instance_buffer.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: sorted_instance_buffer.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: draw_indirect_buffer.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 3,
+                    resource: scatter_offsets_buffer.as_entire_binding(),
+                },
+            ],
+        })
     }
 
+    // The sorted instances, how many there are of each model, the indirect draws and how many
+    // of them to make.
     pub fn slice(&self) -> (wgpu::BufferSlice, [u32; Models::COUNT], &wgpu::Buffer, u32) {
-        let mut lengths = [0; Models::COUNT];
-        #[allow(clippy::needless_range_loop)]
-        for i in 0..Models::COUNT {
-            lengths[i] = self.staging[i].len() as u32;
-        }
+        let draw_indirect_count = if self.slots.is_empty() {
+            0
+        } else {
+            Models::COUNT as u32
+        };
 
         (
-            self.buffer.slice(..),
-            lengths,
+            self.sorted_instance_buffer.slice(..),
+            self.model_counts,
             &self.draw_indirect_buffer,
-            self.draw_indirect_count,
+            draw_indirect_count,
         )
     }
 
-    pub fn stage(&mut self, instance: Instance, ty: usize) {
-        self.staging[ty].push(instance);
+    // For the draw building compute pass. `None` if there's nothing to draw.
+    pub fn draw_building(&self) -> Option<(&wgpu::BindGroup, u32)> {
+        if self.slots.is_empty() {
+            None
+        } else {
+            Some((&self.bind_group, self.instances.len() as u32))
+        }
     }
 
-    pub fn upload(&mut self, device: &wgpu::Device, queue: &wgpu::Queue, models: &Models) {
-        let sum_length = self
-            .staging
-            .iter()
-            .map(|buffer| buffer.len())
-            .sum::<usize>()
-            * std::mem::size_of::<Instance>();
-
-        if sum_length == 0 {
-            return;
+    pub fn stage(&mut self, entity: Entity, instance: Instance) {
+        let instances = &mut self.instances;
+        let staged = &mut self.staged;
+        let free_slots = &mut self.free_slots;
+
+        let slot = *self.slots.entry(entity).or_insert_with(|| {
+            free_slots.pop().unwrap_or_else(|| {
+                instances.push(Instance::unused());
+                staged.push(false);
+                instances.len() as u32 - 1
+            })
+        });
+
+        let index = slot as usize;
+        self.staged[index] = true;
+
+        if bytemuck::bytes_of(&self.instances[index]) != bytemuck::bytes_of(&instance) {
+            self.replace(slot, instance);
         }
+    }
 
-        if sum_length > self.capacity_in_bytes {
-            self.capacity_in_bytes = sum_length.max(self.capacity_in_bytes * 2);
+    fn replace(&mut self, slot: u32, instance: Instance) {
+        let previous = std::mem::replace(&mut self.instances[slot as usize], instance);
 
-            self.buffer = device.create_buffer(&wgpu::BufferDescriptor {
-                label: Some(Self::LABEL),
-                size: self.capacity_in_bytes as u64,
-                usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::VERTEX,
-                mapped_at_creation: false,
-            });
+        if let Some(count) = self.model_counts.get_mut(previous.model_id as usize) {
+            *count -= 1;
         }
 
-        let mut offset = 0;
+        if let Some(count) = self.model_counts.get_mut(instance.model_id as usize) {
+            *count += 1;
+        }
 
-        let mut draw_indirect_array = [DrawIndexedIndirect::default(); Models::COUNT];
-        let mut draw_indirect_offset = 0;
-        let mut instance_offset = 0;
-        let mut index_offset = 0;
+        self.changed_slots.push(slot);
+    }
 
-        for i in 0..Models::COUNT {
-            let buffer = &self.staging[i];
+    pub fn upload(&mut self, device: &wgpu::Device, queue: &wgpu::Queue, models: &Models) {
+        // Free the slots of anything that wasn't drawn this frame.
+        let mut unstaged = Vec::new();
+        let staged = &mut self.staged;
 
-            let index_count = models.models[i].num_indices;
+        self.slots.retain(|_, &mut slot| {
+            let keep = std::mem::replace(&mut staged[slot as usize], false);
 
-            if !buffer.is_empty() {
-                let bytes = bytemuck::cast_slice(buffer);
-                queue.write_buffer(&self.buffer, offset, bytes);
-                offset += bytes.len() as u64;
+            if !keep {
+                unstaged.push(slot);
+            }
 
-                let instance_count = buffer.len() as u32;
+            keep
+        });
 
-                draw_indirect_array[draw_indirect_offset] = DrawIndexedIndirect {
-                    vertex_offset: 0,
-                    base_instance: instance_offset,
-                    instance_count,
-                    base_index: index_offset,
-                    index_count,
-                };
+        for slot in unstaged {
+            self.replace(slot, Instance::unused());
+            self.free_slots.push(slot);
+        }
+
+        if self.instances.len() > self.capacity {
+            self.capacity = self.instances.len().max(self.capacity * 2);
+
+            let (instance_buffer, sorted_instance_buffer) =
+                Self::create_instance_buffers(device, self.label, self.capacity);
+
+            self.bind_group = Self::create_bind_group(
+                device,
+                &instance_buffer,
+                &sorted_instance_buffer,
+                &self.draw_indirect_buffer,
+                &self.scatter_offsets_buffer,
+            );
+            self.instance_buffer = instance_buffer;
+            self.sorted_instance_buffer = sorted_instance_buffer;
+
+            // Everything needs to be written to the new buffer.
+            self.changed_slots.clear();
+            queue.write_buffer(
+                &self.instance_buffer,
+                0,
+                bytemuck::cast_slice(&self.instances),
+            );
+        }
+
+        self.changed_slots.sort_unstable();
+        self.changed_slots.dedup();
 
-                draw_indirect_offset += 1;
-                instance_offset += instance_count;
+        // Write runs of consecutive slots in one go.
+        let mut changed_slots = self.changed_slots.drain(..).peekable();
+
+        while let Some(first) = changed_slots.next() {
+            let mut last = first;
+
+            while changed_slots.peek() == Some(&(last + 1)) {
+                last = changed_slots.next().unwrap();
             }
 
+            queue.write_buffer(
+                &self.instance_buffer,
+                (first as usize * std::mem::size_of::<Instance>()) as u64,
+                bytemuck::cast_slice(&self.instances[first as usize..=last as usize]),
+            );
+        }
+
+        // The instance counts and base instances are filled in by the compute pass.
+        let mut draw_indirect_array = [DrawIndexedIndirect::default(); Models::COUNT];
+        let mut index_offset = 0;
+
+        for (i, draw_indirect) in draw_indirect_array.iter_mut().enumerate() {
+            let index_count = models.models[i].num_indices;
+
+            *draw_indirect = DrawIndexedIndirect {
+                index_count,
+                base_index: index_offset,
+                ..Default::default()
+            };
+
             index_offset += index_count;
         }
 
-        self.draw_indirect_count = draw_indirect_offset as u32;
         queue.write_buffer(
             &self.draw_indirect_buffer,
             0,
-            bytemuck::cast_slice(&draw_indirect_array[..draw_indirect_offset]),
+            bytemuck::cast_slice(&draw_indirect_array),
         );
     }
 }
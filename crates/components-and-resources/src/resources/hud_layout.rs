@@ -0,0 +1,167 @@
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use ultraviolet::Vec2;
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub enum Anchor {
+    TopLeft,
+    TopCenter,
+    TopRight,
+    BottomLeft,
+    BottomCenter,
+    BottomRight,
+}
+
+impl Anchor {
+    fn is_top(self) -> bool {
+        matches!(self, Self::TopLeft | Self::TopCenter | Self::TopRight)
+    }
+
+    // How far across the screen the anchor is, from 0 on the left to 1 on the right.
+    fn horizontal_fraction(self) -> f32 {
+        match self {
+            Self::TopLeft | Self::BottomLeft => 0.0,
+            Self::TopCenter | Self::BottomCenter => 0.5,
+            Self::TopRight | Self::BottomRight => 1.0,
+        }
+    }
+
+    // The top-left corner of a box of this size when it's placed at the anchor.
+    pub fn position(self, size: Vec2, screen: Vec2) -> Vec2 {
+        let x = (screen.x - size.x) * self.horizontal_fraction();
+        let y = if self.is_top() {
+            0.0
+        } else {
+            screen.y - size.y
+        };
+        Vec2::new(x, y)
+    }
+
+    // Text is aligned around a point on the edge of the screen instead of being given a box,
+    // as its width isn't known up front.
+    pub fn text_position(self, height: f32, screen: Vec2) -> Vec2 {
+        Vec2::new(
+            screen.x * self.horizontal_fraction(),
+            self.position(Vec2::new(0.0, height), screen).y,
+        )
+    }
+
+    pub(crate) fn h_align(self) -> wgpu_glyph::HorizontalAlign {
+        match self.horizontal_fraction() {
+            fraction if fraction < 0.5 => wgpu_glyph::HorizontalAlign::Left,
+            fraction if fraction > 0.5 => wgpu_glyph::HorizontalAlign::Right,
+            _ => wgpu_glyph::HorizontalAlign::Center,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+pub struct HudElement {
+    pub anchor: Anchor,
+    pub visible: bool,
+}
+
+impl HudElement {
+    fn at(anchor: Anchor) -> Self {
+        Self {
+            anchor,
+            visible: true,
+        }
+    }
+}
+
+// Where each part of the HUD goes and whether it's shown at all. Kept in a RON file per player
+// name so that each player can lay things out the way they like.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct HudLayout {
+    pub resource_bar: HudElement,
+    pub unit_counts: HudElement,
+    pub notifications: HudElement,
+    pub mission_clock: HudElement,
+    pub picture_in_picture: HudElement,
+    pub event_log: HudElement,
+    pub production_panel: HudElement,
+}
+
+impl Default for HudLayout {
+    fn default() -> Self {
+        Self {
+            resource_bar: HudElement::at(Anchor::TopLeft),
+            unit_counts: HudElement::at(Anchor::TopLeft),
+            notifications: HudElement::at(Anchor::BottomLeft),
+            mission_clock: HudElement::at(Anchor::TopCenter),
+            picture_in_picture: HudElement::at(Anchor::BottomRight),
+            event_log: HudElement::at(Anchor::TopRight),
+            production_panel: HudElement::at(Anchor::BottomCenter),
+        }
+    }
+}
+
+impl HudLayout {
+    // Minerals, gas, credits, research and idle ships.
+    pub const RESOURCE_BAR_LINES: usize = 5;
+
+    pub fn path_for_profile(player_name: &str) -> PathBuf {
+        let name: String = player_name
+            .chars()
+            .map(|c| if c.is_alphanumeric() { c } else { '_' })
+            .collect();
+
+        PathBuf::from(format!("hud_layout_{}.ron", name))
+    }
+
+    // Writes out the default layout if there isn't one yet, so that there's a file to edit.
+    pub fn load_or_create(path: &Path) -> anyhow::Result<Self> {
+        if path.exists() {
+            return Ok(ron::de::from_str(&std::fs::read_to_string(path)?)?);
+        }
+
+        let layout = Self::default();
+        let pretty = ron::ser::PrettyConfig::default();
+        std::fs::write(path, ron::ser::to_string_pretty(&layout, pretty)?)?;
+        Ok(layout)
+    }
+
+    // The unit counts go under the resource bar when they share a corner.
+    pub fn unit_counts_offset_lines(&self) -> usize {
+        if self.resource_bar.visible && self.resource_bar.anchor == self.unit_counts.anchor {
+            Self::RESOURCE_BAR_LINES
+        } else {
+            0
+        }
+    }
+}
+
+#[test]
+fn test_hud_layout() {
+    let screen = Vec2::new(800.0, 600.0);
+    let size = Vec2::new(200.0, 100.0);
+
+    assert_eq!(Anchor::TopLeft.position(size, screen), Vec2::zero());
+    assert_eq!(
+        Anchor::BottomCenter.position(size, screen),
+        Vec2::new(300.0, 500.0)
+    );
+    assert_eq!(
+        Anchor::BottomRight.text_position(100.0, screen),
+        Vec2::new(800.0, 500.0)
+    );
+
+    let mut layout: HudLayout =
+        ron::de::from_str("(unit_counts: (anchor: TopRight, visible: true))").unwrap();
+    // Anything left out keeps its default.
+    assert_eq!(layout.notifications.anchor, Anchor::BottomLeft);
+    assert_eq!(layout.unit_counts_offset_lines(), 0);
+
+    layout.unit_counts.anchor = Anchor::TopLeft;
+    assert_eq!(
+        layout.unit_counts_offset_lines(),
+        HudLayout::RESOURCE_BAR_LINES
+    );
+
+    assert_eq!(
+        HudLayout::path_for_profile("Ada L."),
+        PathBuf::from("hud_layout_Ada_L_.ron")
+    );
+}
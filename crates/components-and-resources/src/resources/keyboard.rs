@@ -19,7 +19,67 @@ pub struct KeyBindings {
     pub build_fighter: VirtualKeyCode,
     pub build_miner: VirtualKeyCode,
     pub build_carrier: VirtualKeyCode,
+    pub build_minelayer: VirtualKeyCode,
+    pub build_repair_ship: VirtualKeyCode,
+    pub build_gas_harvester: VirtualKeyCode,
+    pub build_freighter: VirtualKeyCode,
+    pub build_construction_ship: VirtualKeyCode,
+    pub build_frigate: VirtualKeyCode,
+    pub build_destroyer: VirtualKeyCode,
+    pub build_battleship: VirtualKeyCode,
+    pub build_drone: VirtualKeyCode,
+    pub place_defense_platform: VirtualKeyCode,
+    pub place_shipyard: VirtualKeyCode,
+    pub place_refinery: VirtualKeyCode,
+    pub hire_mercenaries: VirtualKeyCode,
+    pub buy_gas_shipment: VirtualKeyCode,
     pub toggle_fullscreen: VirtualKeyCode,
+    pub quick_save: VirtualKeyCode,
+    pub quick_load: VirtualKeyCode,
+    pub cancel_build_queues: VirtualKeyCode,
+    pub scuttle: VirtualKeyCode,
+    pub rules_of_engagement: VirtualKeyCode,
+    pub match_slowest: VirtualKeyCode,
+    pub order_timing: VirtualKeyCode,
+    pub mark: VirtualKeyCode,
+    pub cycle_formation: VirtualKeyCode,
+    pub set_drop_off: VirtualKeyCode,
+    pub hold_position: VirtualKeyCode,
+    pub toggle_carrier_doctrine: VirtualKeyCode,
+    pub cycle_autonomy: VirtualKeyCode,
+    pub toggle_retreat: VirtualKeyCode,
+    pub cycle_targeting_stance: VirtualKeyCode,
+    pub cycle_stance: VirtualKeyCode,
+    pub cycle_throttle: VirtualKeyCode,
+    pub slower_game_speed: VirtualKeyCode,
+    pub faster_game_speed: VirtualKeyCode,
+    pub lay_mine: VirtualKeyCode,
+    pub lay_minefield: VirtualKeyCode,
+    pub cloak: VirtualKeyCode,
+    pub dump_flight_recorder: VirtualKeyCode,
+    pub set_rally_point: VirtualKeyCode,
+    pub assign_escorts: VirtualKeyCode,
+    pub cycle_fleets: VirtualKeyCode,
+    pub jump_to_attack: VirtualKeyCode,
+    pub cycle_idle_ships: VirtualKeyCode,
+    pub cycle_tag: VirtualKeyCode,
+    pub watch_kill_cam: VirtualKeyCode,
+    pub research_panel: VirtualKeyCode,
+    pub strategic_map: VirtualKeyCode,
+    pub transfer_crew: VirtualKeyCode,
+    pub control: VirtualKeyCode,
+    pub control_groups: [VirtualKeyCode; 9],
+    pub read_status: VirtualKeyCode,
+    // Stand-ins for the mouse, for playing with just the keyboard.
+    pub cursor_up: VirtualKeyCode,
+    pub cursor_left: VirtualKeyCode,
+    pub cursor_down: VirtualKeyCode,
+    pub cursor_right: VirtualKeyCode,
+    pub left_click: VirtualKeyCode,
+    pub right_click: VirtualKeyCode,
+    pub middle_click: VirtualKeyCode,
+    pub zoom_in: VirtualKeyCode,
+    pub zoom_out: VirtualKeyCode,
 }
 
 impl Default for KeyBindings {
@@ -41,7 +101,76 @@ impl Default for KeyBindings {
             build_fighter: VirtualKeyCode::B,
             build_miner: VirtualKeyCode::N,
             build_carrier: VirtualKeyCode::M,
+            build_minelayer: VirtualKeyCode::Comma,
+            build_repair_ship: VirtualKeyCode::Period,
+            build_gas_harvester: VirtualKeyCode::Z,
+            build_freighter: VirtualKeyCode::Slash,
+            build_construction_ship: VirtualKeyCode::Backslash,
+            build_frigate: VirtualKeyCode::LBracket,
+            build_destroyer: VirtualKeyCode::RBracket,
+            build_battleship: VirtualKeyCode::Back,
+            build_drone: VirtualKeyCode::Equals,
+            place_defense_platform: VirtualKeyCode::F1,
+            place_shipyard: VirtualKeyCode::F2,
+            place_refinery: VirtualKeyCode::F3,
+            hire_mercenaries: VirtualKeyCode::Semicolon,
+            buy_gas_shipment: VirtualKeyCode::Apostrophe,
             toggle_fullscreen: VirtualKeyCode::F11,
+            quick_save: VirtualKeyCode::F5,
+            quick_load: VirtualKeyCode::F9,
+            cancel_build_queues: VirtualKeyCode::X,
+            scuttle: VirtualKeyCode::Delete,
+            rules_of_engagement: VirtualKeyCode::R,
+            match_slowest: VirtualKeyCode::V,
+            order_timing: VirtualKeyCode::T,
+            mark: VirtualKeyCode::G,
+            cycle_formation: VirtualKeyCode::Q,
+            set_drop_off: VirtualKeyCode::D,
+            hold_position: VirtualKeyCode::H,
+            toggle_carrier_doctrine: VirtualKeyCode::F8,
+            cycle_autonomy: VirtualKeyCode::Minus,
+            toggle_retreat: VirtualKeyCode::Key0,
+            cycle_targeting_stance: VirtualKeyCode::F10,
+            cycle_stance: VirtualKeyCode::Home,
+            cycle_throttle: VirtualKeyCode::End,
+            slower_game_speed: VirtualKeyCode::PageDown,
+            faster_game_speed: VirtualKeyCode::PageUp,
+            lay_mine: VirtualKeyCode::K,
+            lay_minefield: VirtualKeyCode::F12,
+            cloak: VirtualKeyCode::I,
+            dump_flight_recorder: VirtualKeyCode::J,
+            set_rally_point: VirtualKeyCode::Y,
+            assign_escorts: VirtualKeyCode::E,
+            cycle_fleets: VirtualKeyCode::Tab,
+            jump_to_attack: VirtualKeyCode::Space,
+            cycle_idle_ships: VirtualKeyCode::Insert,
+            cycle_tag: VirtualKeyCode::Grave,
+            watch_kill_cam: VirtualKeyCode::F4,
+            research_panel: VirtualKeyCode::O,
+            strategic_map: VirtualKeyCode::F6,
+            transfer_crew: VirtualKeyCode::W,
+            control: VirtualKeyCode::LControl,
+            control_groups: [
+                VirtualKeyCode::Key1,
+                VirtualKeyCode::Key2,
+                VirtualKeyCode::Key3,
+                VirtualKeyCode::Key4,
+                VirtualKeyCode::Key5,
+                VirtualKeyCode::Key6,
+                VirtualKeyCode::Key7,
+                VirtualKeyCode::Key8,
+                VirtualKeyCode::Key9,
+            ],
+            read_status: VirtualKeyCode::F7,
+            cursor_up: VirtualKeyCode::Numpad8,
+            cursor_left: VirtualKeyCode::Numpad4,
+            cursor_down: VirtualKeyCode::Numpad2,
+            cursor_right: VirtualKeyCode::Numpad6,
+            left_click: VirtualKeyCode::Numpad5,
+            right_click: VirtualKeyCode::Numpad0,
+            middle_click: VirtualKeyCode::NumpadDecimal,
+            zoom_in: VirtualKeyCode::NumpadAdd,
+            zoom_out: VirtualKeyCode::NumpadSubtract,
         }
     }
 }
@@ -55,6 +184,7 @@ pub struct KeyboardState {
     pub center_camera: Tapped,
     pub fire: bool,
     pub shift: bool,
+    pub control: bool,
     pub stop: Tapped,
     pub pause: Tapped,
     pub unload: Tapped,
@@ -64,6 +194,65 @@ pub struct KeyboardState {
     pub build_fighter: Tapped,
     pub build_miner: Tapped,
     pub build_carrier: Tapped,
+    pub build_minelayer: Tapped,
+    pub build_repair_ship: Tapped,
+    pub build_gas_harvester: Tapped,
+    pub build_freighter: Tapped,
+    pub build_construction_ship: Tapped,
+    pub build_frigate: Tapped,
+    pub build_destroyer: Tapped,
+    pub build_battleship: Tapped,
+    pub build_drone: Tapped,
+    pub place_defense_platform: Tapped,
+    pub place_shipyard: Tapped,
+    pub place_refinery: Tapped,
+    pub hire_mercenaries: Tapped,
+    pub buy_gas_shipment: Tapped,
+    pub quick_save: Tapped,
+    pub quick_load: Tapped,
+    pub rules_of_engagement: Tapped,
+    pub match_slowest: Tapped,
+    pub order_timing: Tapped,
+    pub mark: Tapped,
+    pub cycle_formation: Tapped,
+    pub set_drop_off: Tapped,
+    pub hold_position: Tapped,
+    pub toggle_carrier_doctrine: Tapped,
+    pub cycle_autonomy: Tapped,
+    pub toggle_retreat: Tapped,
+    pub cycle_targeting_stance: Tapped,
+    pub cycle_stance: Tapped,
+    pub cycle_throttle: Tapped,
+    pub slower_game_speed: Tapped,
+    pub faster_game_speed: Tapped,
+    pub lay_mine: Tapped,
+    pub lay_minefield: Tapped,
+    pub cloak: Tapped,
+    pub dump_flight_recorder: Tapped,
+    pub set_rally_point: Tapped,
+    pub assign_escorts: Tapped,
+    pub cycle_fleets: Tapped,
+    pub jump_to_attack: Tapped,
+    pub cycle_idle_ships: Tapped,
+    pub cycle_tag: Tapped,
+    pub watch_kill_cam: Tapped,
+    pub research_panel: Tapped,
+    pub strategic_map: Tapped,
+    pub transfer_crew: Tapped,
+    pub control_groups: [Tapped; 9],
+    pub read_status: Tapped,
+    // Destructive orders are held down until they're confirmed.
+    pub cancel_build_queues: bool,
+    pub scuttle: bool,
+    pub cursor_up: bool,
+    pub cursor_left: bool,
+    pub cursor_down: bool,
+    pub cursor_right: bool,
+    pub left_click: bool,
+    pub right_click: bool,
+    pub middle_click: bool,
+    pub zoom_in: bool,
+    pub zoom_out: bool,
 }
 
 #[derive(Default)]
@@ -81,9 +270,13 @@ impl Tapped {
 
 impl KeyboardState {
     #[rustfmt::skip]
-    pub fn handle(&mut self, key: VirtualKeyCode, pressed: bool, window: &Window) {
-        let bindings = KeyBindings::default();
-
+    pub fn handle(
+        &mut self,
+        key: VirtualKeyCode,
+        pressed: bool,
+        bindings: &KeyBindings,
+        window: &Window,
+    ) {
         if key == bindings.camera_forwards { self.camera_forwards = pressed; }
         if key == bindings.camera_left { self.camera_left = pressed; }
         if key == bindings.camera_back { self.camera_back = pressed; }
@@ -91,6 +284,7 @@ impl KeyboardState {
         if key == bindings.center_camera { self.center_camera.handle(pressed); }
         if key == bindings.fire { self.fire = pressed; }
         if key == bindings.shift { self.shift = pressed; }
+        if key == bindings.control { self.control = pressed; }
         if key == bindings.stop { self.stop.handle(pressed); }
         if key == bindings.pause { self.pause.handle(pressed); }
         if key == bindings.unload { self.unload.handle(pressed); }
@@ -100,6 +294,67 @@ impl KeyboardState {
         if key == bindings.build_fighter { self.build_fighter.handle(pressed); }
         if key == bindings.build_miner { self.build_miner.handle(pressed); }
         if key == bindings.build_carrier { self.build_carrier.handle(pressed); }
+        if key == bindings.build_minelayer { self.build_minelayer.handle(pressed); }
+        if key == bindings.build_repair_ship { self.build_repair_ship.handle(pressed); }
+        if key == bindings.build_gas_harvester { self.build_gas_harvester.handle(pressed); }
+        if key == bindings.build_freighter { self.build_freighter.handle(pressed); }
+        if key == bindings.build_construction_ship { self.build_construction_ship.handle(pressed); }
+        if key == bindings.build_frigate { self.build_frigate.handle(pressed); }
+        if key == bindings.build_destroyer { self.build_destroyer.handle(pressed); }
+        if key == bindings.build_battleship { self.build_battleship.handle(pressed); }
+        if key == bindings.build_drone { self.build_drone.handle(pressed); }
+        if key == bindings.place_defense_platform { self.place_defense_platform.handle(pressed); }
+        if key == bindings.place_shipyard { self.place_shipyard.handle(pressed); }
+        if key == bindings.place_refinery { self.place_refinery.handle(pressed); }
+        if key == bindings.hire_mercenaries { self.hire_mercenaries.handle(pressed); }
+        if key == bindings.buy_gas_shipment { self.buy_gas_shipment.handle(pressed); }
+        if key == bindings.quick_save { self.quick_save.handle(pressed); }
+        if key == bindings.quick_load { self.quick_load.handle(pressed); }
+        if key == bindings.rules_of_engagement { self.rules_of_engagement.handle(pressed); }
+        if key == bindings.match_slowest { self.match_slowest.handle(pressed); }
+        if key == bindings.order_timing { self.order_timing.handle(pressed); }
+        if key == bindings.mark { self.mark.handle(pressed); }
+        if key == bindings.cycle_formation { self.cycle_formation.handle(pressed); }
+        if key == bindings.set_drop_off { self.set_drop_off.handle(pressed); }
+        if key == bindings.hold_position { self.hold_position.handle(pressed); }
+        if key == bindings.toggle_carrier_doctrine { self.toggle_carrier_doctrine.handle(pressed); }
+        if key == bindings.cycle_autonomy { self.cycle_autonomy.handle(pressed); }
+        if key == bindings.toggle_retreat { self.toggle_retreat.handle(pressed); }
+        if key == bindings.cycle_targeting_stance { self.cycle_targeting_stance.handle(pressed); }
+        if key == bindings.cycle_stance { self.cycle_stance.handle(pressed); }
+        if key == bindings.cycle_throttle { self.cycle_throttle.handle(pressed); }
+        if key == bindings.slower_game_speed { self.slower_game_speed.handle(pressed); }
+        if key == bindings.faster_game_speed { self.faster_game_speed.handle(pressed); }
+        if key == bindings.lay_mine { self.lay_mine.handle(pressed); }
+        if key == bindings.lay_minefield { self.lay_minefield.handle(pressed); }
+        if key == bindings.cloak { self.cloak.handle(pressed); }
+        if key == bindings.dump_flight_recorder { self.dump_flight_recorder.handle(pressed); }
+        if key == bindings.set_rally_point { self.set_rally_point.handle(pressed); }
+        if key == bindings.assign_escorts { self.assign_escorts.handle(pressed); }
+        if key == bindings.cycle_fleets { self.cycle_fleets.handle(pressed); }
+        if key == bindings.jump_to_attack { self.jump_to_attack.handle(pressed); }
+        if key == bindings.cycle_idle_ships { self.cycle_idle_ships.handle(pressed); }
+        if key == bindings.cycle_tag { self.cycle_tag.handle(pressed); }
+        if key == bindings.watch_kill_cam { self.watch_kill_cam.handle(pressed); }
+        if key == bindings.research_panel { self.research_panel.handle(pressed); }
+        if key == bindings.strategic_map { self.strategic_map.handle(pressed); }
+        if key == bindings.transfer_crew { self.transfer_crew.handle(pressed); }
+        if key == bindings.cancel_build_queues { self.cancel_build_queues = pressed; }
+        if key == bindings.scuttle { self.scuttle = pressed; }
+        if key == bindings.read_status { self.read_status.handle(pressed); }
+        if key == bindings.cursor_up { self.cursor_up = pressed; }
+        if key == bindings.cursor_left { self.cursor_left = pressed; }
+        if key == bindings.cursor_down { self.cursor_down = pressed; }
+        if key == bindings.cursor_right { self.cursor_right = pressed; }
+        if key == bindings.left_click { self.left_click = pressed; }
+        if key == bindings.right_click { self.right_click = pressed; }
+        if key == bindings.middle_click { self.middle_click = pressed; }
+        if key == bindings.zoom_in { self.zoom_in = pressed; }
+        if key == bindings.zoom_out { self.zoom_out = pressed; }
+
+        for (binding, control_group) in bindings.control_groups.iter().zip(&mut self.control_groups) {
+            if key == *binding { control_group.handle(pressed); }
+        }
 
         if key == bindings.toggle_fullscreen && pressed {
             if window.fullscreen().is_some() {
@@ -110,6 +365,20 @@ impl KeyboardState {
         }
     }
 
+    // Only let key releases and escape through so that keys can't get stuck down and the
+    // cutscene can still be skipped.
+    pub fn handle_during_cutscene(
+        &mut self,
+        key: VirtualKeyCode,
+        pressed: bool,
+        bindings: &KeyBindings,
+        window: &Window,
+    ) {
+        if !pressed || key == bindings.escape {
+            self.handle(key, pressed, bindings, window);
+        }
+    }
+
     pub fn update(&mut self) {
         self.center_camera.reset();
         self.stop.reset();
@@ -122,5 +391,59 @@ impl KeyboardState {
         self.build_fighter.reset();
         self.build_miner.reset();
         self.build_carrier.reset();
+        self.build_minelayer.reset();
+        self.build_repair_ship.reset();
+        self.build_gas_harvester.reset();
+        self.build_freighter.reset();
+        self.build_construction_ship.reset();
+        self.build_frigate.reset();
+        self.build_destroyer.reset();
+        self.build_battleship.reset();
+        self.build_drone.reset();
+
+        self.place_defense_platform.reset();
+        self.place_shipyard.reset();
+        self.place_refinery.reset();
+
+        self.hire_mercenaries.reset();
+        self.buy_gas_shipment.reset();
+
+        self.quick_save.reset();
+        self.quick_load.reset();
+
+        self.rules_of_engagement.reset();
+        self.match_slowest.reset();
+        self.order_timing.reset();
+        self.mark.reset();
+        self.cycle_formation.reset();
+        self.set_drop_off.reset();
+        self.hold_position.reset();
+        self.toggle_carrier_doctrine.reset();
+        self.cycle_autonomy.reset();
+        self.toggle_retreat.reset();
+        self.cycle_targeting_stance.reset();
+        self.cycle_stance.reset();
+        self.cycle_throttle.reset();
+        self.slower_game_speed.reset();
+        self.faster_game_speed.reset();
+        self.lay_mine.reset();
+        self.lay_minefield.reset();
+        self.cloak.reset();
+        self.dump_flight_recorder.reset();
+        self.set_rally_point.reset();
+        self.assign_escorts.reset();
+        self.cycle_fleets.reset();
+        self.jump_to_attack.reset();
+        self.cycle_idle_ships.reset();
+        self.cycle_tag.reset();
+        self.watch_kill_cam.reset();
+        self.research_panel.reset();
+        self.strategic_map.reset();
+        self.transfer_crew.reset();
+        self.read_status.reset();
+
+        for control_group in &mut self.control_groups {
+            control_group.reset();
+        }
     }
 }
@@ -0,0 +1,212 @@
+use super::{CameraKeyframe, Cutscene, PlayerCommand, SimClock};
+use crate::components::{ModelId, Side};
+use std::collections::VecDeque;
+use ultraviolet::Vec3;
+
+// Keeps enough of the recent past to re-simulate it: snapshots of the world taken every few
+// seconds along with every command applied since the oldest one.
+#[derive(Default)]
+pub struct KillCam {
+    enabled: bool,
+    current_tick: u32,
+    snapshots: VecDeque<(u32, String)>,
+    commands: VecDeque<(u32, Vec<(Side, PlayerCommand)>)>,
+    offer: Option<KillCamOffer>,
+    requested: bool,
+}
+
+#[derive(Clone, Copy)]
+pub struct KillCamOffer {
+    pub position: Vec3,
+    pub tick: u32,
+    // What was destroyed.
+    pub model_id: ModelId,
+}
+
+impl KillCam {
+    const TICKS_PER_SECOND: u32 = 60;
+
+    pub const SNAPSHOT_INTERVAL: u32 = 5 * Self::TICKS_PER_SECOND;
+    // Enough to always have a snapshot from before the start of a replay that's offered.
+    const MAX_SNAPSHOTS: usize = 5;
+    // How long before the death the replay starts.
+    pub const LEAD_IN: u32 = 6 * Self::TICKS_PER_SECOND;
+    // How long the replay is offered for after the death.
+    pub const OFFER_DURATION: u32 = 8 * Self::TICKS_PER_SECOND;
+
+    // There's no way to re-simulate without holding up the other player in multiplayer.
+    pub fn new(enabled: bool) -> Self {
+        Self {
+            enabled,
+            ..Default::default()
+        }
+    }
+
+    pub fn should_snapshot(&self, tick: u32) -> bool {
+        self.enabled && tick % Self::SNAPSHOT_INTERVAL == 0
+    }
+
+    pub fn add_snapshot(&mut self, tick: u32, snapshot: String) {
+        self.snapshots.push_back((tick, snapshot));
+
+        if self.snapshots.len() > Self::MAX_SNAPSHOTS {
+            self.snapshots.pop_front();
+        }
+
+        if let Some(&(oldest, _)) = self.snapshots.front() {
+            while matches!(self.commands.front(), Some(&(tick, _)) if tick < oldest) {
+                self.commands.pop_front();
+            }
+        }
+    }
+
+    pub fn record(&mut self, tick: u32, commands: &[(Side, PlayerCommand)]) {
+        self.current_tick = tick;
+
+        if !self.enabled {
+            return;
+        }
+
+        if !commands.is_empty() {
+            self.commands.push_back((tick, commands.to_vec()));
+        }
+
+        if matches!(self.offer, Some(offer) if tick > offer.tick + Self::OFFER_DURATION) {
+            self.offer = None;
+        }
+    }
+
+    pub fn offer(&mut self, position: Vec3, model_id: ModelId) {
+        if self.enabled {
+            self.offer = Some(KillCamOffer {
+                position,
+                tick: self.current_tick,
+                model_id,
+            });
+        }
+    }
+
+    pub fn current_offer(&self) -> Option<KillCamOffer> {
+        self.offer
+    }
+
+    pub fn dismiss_offer(&mut self) {
+        self.offer = None;
+    }
+
+    pub fn request(&mut self) {
+        self.requested = self.offer.is_some();
+    }
+
+    // Everything from before a load is useless afterwards.
+    pub fn clear(&mut self) {
+        *self = Self::new(self.enabled);
+    }
+
+    // Returns what's needed to play back the time around the offered death, up until `tick`.
+    pub fn take_request(&mut self, tick: u32) -> Option<KillCamPlayback> {
+        if !std::mem::take(&mut self.requested) {
+            return None;
+        }
+
+        let offer = self.offer.take()?;
+        let start_tick = offer.tick.saturating_sub(Self::LEAD_IN);
+
+        let (snapshot_tick, snapshot) = self
+            .snapshots
+            .iter()
+            .rev()
+            .find(|&&(snapshot_tick, _)| snapshot_tick <= start_tick)
+            .or_else(|| self.snapshots.front())?
+            .clone();
+
+        Some(KillCamPlayback {
+            snapshot,
+            tick: snapshot_tick,
+            start_tick: start_tick.max(snapshot_tick),
+            end_tick: tick,
+            position: offer.position,
+            commands: self
+                .commands
+                .iter()
+                .filter(|&&(tick, _)| tick >= snapshot_tick)
+                .cloned()
+                .collect(),
+        })
+    }
+}
+
+pub struct KillCamPlayback {
+    pub snapshot: String,
+    // The next tick to be re-simulated.
+    pub tick: u32,
+    // Ticks before this are re-simulated without being shown.
+    pub start_tick: u32,
+    pub end_tick: u32,
+    pub position: Vec3,
+    commands: Vec<(u32, Vec<(Side, PlayerCommand)>)>,
+}
+
+impl KillCamPlayback {
+    pub fn commands_for_tick(&self, tick: u32) -> &[(Side, PlayerCommand)] {
+        match self.commands.binary_search_by_key(&tick, |&(tick, _)| tick) {
+            Ok(index) => &self.commands[index].1,
+            Err(_) => &[],
+        }
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.tick >= self.end_tick
+    }
+
+    // Slowly circles the death, pulling back as it goes.
+    pub fn cutscene(&self) -> Cutscene {
+        let duration = (self.end_tick - self.start_tick) as f32 * SimClock::TICK;
+
+        let keyframe = |time: f32, distance| CameraKeyframe {
+            time,
+            center: self.position,
+            longitude: 1.1,
+            latitude: time * 0.15,
+            distance,
+        };
+
+        Cutscene::default()
+            .with_keyframe(keyframe(0.0, 40.0))
+            .with_keyframe(keyframe(duration / 2.0, 55.0))
+            .with_keyframe(keyframe(duration, 70.0))
+            .with_subtitle(0.0, duration, "Replay - press escape to return")
+    }
+}
+
+#[test]
+fn test_kill_cam_playback() {
+    let mut kill_cam = KillCam::new(true);
+
+    for tick in 0..KillCam::SNAPSHOT_INTERVAL * 8 {
+        if kill_cam.should_snapshot(tick) {
+            kill_cam.add_snapshot(tick, tick.to_string());
+        }
+
+        kill_cam.record(tick, &[(Side::Friendly, PlayerCommand::Mark)]);
+
+        if tick == KillCam::SNAPSHOT_INTERVAL * 7 {
+            kill_cam.offer(Vec3::zero(), ModelId::Carrier);
+        }
+    }
+
+    assert_eq!(kill_cam.snapshots.len(), KillCam::MAX_SNAPSHOTS);
+
+    let end_tick = KillCam::SNAPSHOT_INTERVAL * 8;
+    kill_cam.request();
+    let playback = kill_cam.take_request(end_tick).unwrap();
+
+    // The newest snapshot from before the lead in.
+    let start_tick = KillCam::SNAPSHOT_INTERVAL * 7 - KillCam::LEAD_IN;
+    let snapshot_tick = start_tick / KillCam::SNAPSHOT_INTERVAL * KillCam::SNAPSHOT_INTERVAL;
+    assert_eq!(playback.snapshot, snapshot_tick.to_string());
+    assert_eq!(playback.start_tick, start_tick);
+    assert_eq!(playback.commands_for_tick(snapshot_tick).len(), 1);
+
+    assert!(kill_cam.take_request(end_tick).is_none());
+}
@@ -0,0 +1,310 @@
+use crate::components::Heat;
+use serde::{Deserialize, Serialize};
+use ultraviolet::{Lerp, Rotor3, Slerp, Vec3};
+
+#[derive(Clone, Copy)]
+pub struct LightingState {
+    pub sun_dir: Vec3,
+    pub sun_colour: Vec3,
+    pub ambient_light: Vec3,
+    // Black if there's only one sun.
+    pub second_sun_dir: Vec3,
+    pub second_sun_colour: Vec3,
+}
+
+impl LightingState {
+    // How strongly the second sun lights ships compared to the first.
+    pub fn second_sun_intensity(&self) -> f32 {
+        self.second_sun_colour.mag() / self.sun_colour.mag().max(f32::EPSILON)
+    }
+}
+
+struct Transition {
+    from: LightingState,
+    to: LightingState,
+    start: f32,
+    duration: f32,
+}
+
+pub struct Lighting {
+    pub current: LightingState,
+    transition: Option<Transition>,
+}
+
+impl Lighting {
+    pub fn new(current: LightingState) -> Self {
+        Self {
+            current,
+            transition: None,
+        }
+    }
+
+    // Smoothly move from the current lighting to the target over `duration` seconds.
+    pub fn transition_to(&mut self, to: LightingState, duration: f32, total_time: f32) {
+        self.transition = Some(Transition {
+            from: self.current,
+            to,
+            start: total_time,
+            duration,
+        });
+    }
+
+    // Where the lighting is heading, so that changes to one part of it can keep the rest.
+    pub fn target(&self) -> LightingState {
+        match &self.transition {
+            Some(transition) => transition.to,
+            None => self.current,
+        }
+    }
+
+    pub fn update(&mut self, total_time: f32) {
+        let transition = match &self.transition {
+            Some(transition) => transition,
+            None => return,
+        };
+
+        let t = if transition.duration > 0.0 {
+            ((total_time - transition.start) / transition.duration)
+                .max(0.0)
+                .min(1.0)
+        } else {
+            1.0
+        };
+
+        // Smoothstep so that changes ease in and out.
+        let eased = t * t * (3.0 - 2.0 * t);
+
+        let (from, to) = (transition.from, transition.to);
+
+        let rotate = |from: Vec3, to: Vec3| {
+            let rotation = Rotor3::identity().slerp(Rotor3::from_rotation_between(from, to), eased);
+            (rotation * from).normalized()
+        };
+
+        self.current = LightingState {
+            sun_dir: rotate(from.sun_dir, to.sun_dir),
+            sun_colour: from.sun_colour.lerp(to.sun_colour, eased),
+            ambient_light: from.ambient_light.lerp(to.ambient_light, eased),
+            second_sun_dir: rotate(from.second_sun_dir, to.second_sun_dir),
+            second_sun_colour: from.second_sun_colour.lerp(to.second_sun_colour, eased),
+        };
+
+        if t >= 1.0 {
+            self.current = to;
+            self.transition = None;
+        }
+    }
+}
+
+#[derive(Clone, Copy)]
+pub struct Godrays {
+    pub density: f32,
+    pub decay: f32,
+    pub weight: f32,
+}
+
+#[derive(Clone, Copy)]
+pub struct SecondSun {
+    pub colour: Vec3,
+    // How far around the sky from the first sun it is, in radians.
+    pub separation: f32,
+    pub heat: f32,
+}
+
+// Everything about the look of a map's surroundings, chosen as a bundle so that the sun,
+// nebula and godrays match each other.
+pub struct Environment {
+    // Also controls how bright the sun is.
+    pub sun_colour: Vec3,
+    // How much heat per second ships pick up deep in the sun's heat zone.
+    pub sun_heat: f32,
+    pub nebula_brightness: std::ops::Range<f32>,
+    pub ambient_scale: f32,
+    pub star_count: usize,
+    pub godrays: Godrays,
+    pub second_sun: Option<SecondSun>,
+    // Between 0 and 1.
+    pub dust_density: f32,
+}
+
+// Motes of dust that sit still in the world around the camera, so that they drift past and give
+// a sense of depth when it moves.
+pub struct AmbientDust {
+    // Offsets within a box around the camera, from 0 to 1 on each axis.
+    pub motes: Vec<Vec3>,
+    pub density: f32,
+}
+
+impl AmbientDust {
+    pub const MAX_MOTES: usize = 400;
+    pub const BOX_SIZE: f32 = 60.0;
+    // Comets shed dust, so it gets thicker within this distance of one.
+    pub const COMET_DUST_RANGE: f32 = 150.0;
+
+    pub fn new<R: rand::Rng>(rng: &mut R, density: f32) -> Self {
+        Self {
+            motes: (0..Self::MAX_MOTES)
+                .map(|_| Vec3::new(rng.gen(), rng.gen(), rng.gen()))
+                .collect(),
+            density,
+        }
+    }
+
+    pub fn density_at(&self, position: Vec3, comets: impl Iterator<Item = Vec3>) -> f32 {
+        let comet_dust = comets
+            .map(|comet| 1.0 - (comet - position).mag() / Self::COMET_DUST_RANGE)
+            .fold(0.0_f32, f32::max);
+
+        (self.density + comet_dust).min(1.0)
+    }
+
+    // Wraps each mote around so that it's always within half a box of the camera.
+    pub fn mote_positions(&self, camera: Vec3) -> impl Iterator<Item = Vec3> + '_ {
+        let size = Self::BOX_SIZE;
+        let wrap = move |offset: f32, camera: f32| {
+            camera + (offset * size - camera).rem_euclid(size) - size / 2.0
+        };
+
+        self.motes.iter().map(move |mote| {
+            Vec3::new(
+                wrap(mote.x, camera.x),
+                wrap(mote.y, camera.y),
+                wrap(mote.z, camera.z),
+            )
+        })
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub enum LightingPreset {
+    DeepSpace,
+    NebulaCore,
+    BinaryStar,
+    Eclipse,
+}
+
+impl LightingPreset {
+    const DEFAULT_GODRAYS: Godrays = Godrays {
+        density: 1.0,
+        decay: 0.98,
+        weight: 0.01,
+    };
+
+    // Used when no preset is given.
+    pub fn random_environment() -> Environment {
+        Environment {
+            sun_colour: Vec3::broadcast(2.0) * Vec3::new(1.0, 0.8, 1.0 / 3.0),
+            sun_heat: Heat::SUN_HEAT_PER_SECOND,
+            nebula_brightness: 0.5..1.0,
+            ambient_scale: 1.0,
+            star_count: 2000,
+            godrays: Self::DEFAULT_GODRAYS,
+            second_sun: None,
+            dust_density: 0.4,
+        }
+    }
+
+    pub fn environment(self) -> Environment {
+        match self {
+            // A faint nebula and lots of stars.
+            Self::DeepSpace => Environment {
+                sun_colour: Vec3::broadcast(2.0) * Vec3::new(1.0, 0.8, 1.0 / 3.0),
+                sun_heat: Heat::SUN_HEAT_PER_SECOND,
+                nebula_brightness: 0.1..0.3,
+                ambient_scale: 0.75,
+                star_count: 3000,
+                godrays: Self::DEFAULT_GODRAYS,
+                second_sun: None,
+                dust_density: 0.2,
+            },
+            // Bright, hazy and with the stars mostly hidden.
+            Self::NebulaCore => Environment {
+                sun_colour: Vec3::broadcast(1.5) * Vec3::new(1.0, 0.9, 0.8),
+                sun_heat: Heat::SUN_HEAT_PER_SECOND * 0.75,
+                nebula_brightness: 0.9..1.0,
+                ambient_scale: 1.5,
+                star_count: 500,
+                godrays: Godrays {
+                    density: 1.5,
+                    decay: 0.99,
+                    weight: 0.02,
+                },
+                second_sun: None,
+                dust_density: 1.0,
+            },
+            // A bright white star with a smaller orange companion.
+            Self::BinaryStar => Environment {
+                sun_colour: Vec3::broadcast(3.0) * Vec3::new(1.0, 0.95, 0.9),
+                sun_heat: Heat::SUN_HEAT_PER_SECOND * 1.25,
+                nebula_brightness: 0.4..0.6,
+                ambient_scale: 1.0,
+                star_count: 2000,
+                godrays: Godrays {
+                    density: 1.0,
+                    decay: 0.98,
+                    weight: 0.015,
+                },
+                second_sun: Some(SecondSun {
+                    colour: Vec3::broadcast(1.5) * Vec3::new(1.0, 0.6, 0.3),
+                    separation: 40.0_f32.to_radians(),
+                    heat: Heat::SUN_HEAT_PER_SECOND * 0.5,
+                }),
+                dust_density: 0.5,
+            },
+            // A dim sun with strong rays around its edges.
+            Self::Eclipse => Environment {
+                sun_colour: Vec3::broadcast(0.3) * Vec3::new(1.0, 0.5, 0.3),
+                sun_heat: Heat::SUN_HEAT_PER_SECOND * 0.25,
+                nebula_brightness: 0.2..0.4,
+                ambient_scale: 0.5,
+                star_count: 2500,
+                godrays: Godrays {
+                    density: 2.0,
+                    decay: 0.99,
+                    weight: 0.03,
+                },
+                second_sun: None,
+                dust_density: 0.3,
+            },
+        }
+    }
+}
+
+impl std::str::FromStr for LightingPreset {
+    type Err = String;
+
+    fn from_str(string: &str) -> Result<Self, Self::Err> {
+        match string {
+            "deep-space" => Ok(Self::DeepSpace),
+            "nebula-core" => Ok(Self::NebulaCore),
+            "binary-star" => Ok(Self::BinaryStar),
+            "eclipse" => Ok(Self::Eclipse),
+            _ => Err(format!(
+                "'{}' isn't one of deep-space, nebula-core, binary-star or eclipse",
+                string
+            )),
+        }
+    }
+}
+
+#[test]
+fn test_ambient_dust_wraps_around_camera() {
+    use rand::SeedableRng;
+
+    let mut rng = rand::rngs::SmallRng::seed_from_u64(0);
+    let dust = AmbientDust::new(&mut rng, 0.5);
+
+    for &camera in &[Vec3::zero(), Vec3::new(1000.0, -250.0, 37.5)] {
+        for position in dust.mote_positions(camera) {
+            let offset = position - camera;
+            let half = AmbientDust::BOX_SIZE / 2.0;
+            assert!(offset.x.abs() <= half && offset.y.abs() <= half && offset.z.abs() <= half);
+        }
+    }
+
+    assert_eq!(dust.density_at(Vec3::zero(), std::iter::empty()), 0.5);
+    assert_eq!(
+        dust.density_at(Vec3::zero(), std::iter::once(Vec3::zero())),
+        1.0
+    );
+}
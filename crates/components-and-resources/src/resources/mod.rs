@@ -1,52 +1,623 @@
+mod cutscene;
+mod game_events;
 mod glyph_layout_cache;
 mod gpu_buffer;
+mod hud_layout;
 mod keyboard;
+mod kill_cam;
+mod lighting;
 mod mouse;
+mod replay;
+mod research;
+mod screen_reader;
+mod scripting;
+mod ship_catalog;
+mod sim_clock;
+mod small_rng;
+mod strategic_map;
 
+pub use cutscene::{CameraKeyframe, Cutscene, CutscenePlayer, Subtitle};
+pub use game_events::{AttackAlert, DamageEvent, DamageEvents, GameEvent, GameEvents, Severity};
 pub use glyph_layout_cache::GlyphLayoutCache;
-pub use gpu_buffer::{GpuBuffer, ShipBuffer};
-pub use keyboard::KeyboardState;
+pub use gpu_buffer::{CloakedShipBuffer, GpuBuffer, ShipBuffer, Triangles2DBuffer};
+pub use hud_layout::{Anchor, HudElement, HudLayout};
+pub use keyboard::{KeyBindings, KeyboardState};
+pub use kill_cam::{KillCam, KillCamOffer, KillCamPlayback};
+pub use lighting::{
+    AmbientDust, Environment, Godrays, Lighting, LightingPreset, LightingState, SecondSun,
+};
 pub use mouse::{MouseButtonState, MouseState};
-pub use rand::rngs::SmallRng;
 pub use ray_collisions::{BoundingBox, DynamicBvh, Projectile, Ray, SelectionFrustum};
+pub use replay::InputReplay;
+pub use research::{ResearchPanel, Tech, TechStatus, TechTree};
+pub use screen_reader::{PrintBackend, ProgramBackend, ScreenReader, ScreenReaderBackend, Topic};
+pub use scripting::{Scenario, ScriptCommand, ScriptHost, Trigger, TriggerCondition};
+pub use ship_catalog::{ShipCatalog, ShipStats};
+pub use sim_clock::{Cooldown, GameSpeed, SimClock, Timer};
+pub use small_rng::SmallRng;
+pub use strategic_map::StrategicMap;
 pub use structopt::StructOpt;
 
-use crate::components::{ModelId, MoveType};
+use crate::components::{
+    Autonomy, CarrierDoctrine, Command, ModelId, MoveType, RulesOfEngagement, ShipType, Side,
+    Stance, StructureType, TargetingStance, Throttle, WaitFor,
+};
+use crate::formations::FormationShape;
 use crate::model::Model;
 use bevy_ecs::prelude::Entity;
-use ultraviolet::{Mat4, Vec2, Vec3};
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::convert::TryInto;
+use std::path::PathBuf;
+use ultraviolet::{Mat4, Vec2, Vec3, Vec4};
 
 #[derive(StructOpt)]
 pub struct Settings {
+    #[structopt(subcommand)]
+    pub subcommand: Option<Subcommand>,
     #[structopt(long)]
     pub disable_godrays: bool,
     #[structopt(long)]
     pub disable_bloom: bool,
     #[structopt(long)]
     pub enable_tlas_debug_drawing: bool,
+    // Show the number of draw calls and instances drawn per pipeline each frame.
+    #[structopt(long)]
+    pub enable_render_stats: bool,
+    // Replace the frame with a heatmap of how many times each pixel was drawn to.
+    #[structopt(long)]
+    pub visualise_overdraw: bool,
+    #[structopt(long)]
+    pub disable_audio: bool,
+    #[structopt(long)]
+    pub skip_intro: bool,
+    // Overrides the HUD layout.
+    #[structopt(long)]
+    pub hide_mission_clock: bool,
+    // Use this HUD layout file instead of the one for the player name.
+    #[structopt(long)]
+    pub hud_layout: Option<PathBuf>,
+    // Where to load the models and textures from.
+    #[structopt(long, default_value = ".")]
+    pub assets_directory: PathBuf,
+    // Where to look for mods. See `ModFiles` for what goes in them.
+    #[structopt(long, default_value = "mods")]
+    pub mods_directory: PathBuf,
+    #[structopt(long)]
+    pub disable_dust: bool,
+    // Occlude godrays using the depth buffer instead of the godray colour target.
+    #[structopt(long)]
+    pub depth_aware_godrays: bool,
+    // One of nearest, bilinear, trilinear or anisotropic. Nearest gives ships a pixel-art look.
+    #[structopt(long, default_value = "trilinear")]
+    pub texture_filtering: TextureFiltering,
+    // One of deep-space, nebula-core, binary-star or eclipse. Overrides the scenario's preset and
+    // is random if neither gives one.
+    #[structopt(long)]
+    pub lighting_preset: Option<LightingPreset>,
+    // These override the godray settings of the lighting preset.
+    #[structopt(long)]
+    pub godray_density: Option<f32>,
+    #[structopt(long)]
+    pub godray_decay: Option<f32>,
+    #[structopt(long)]
+    pub godray_weight: Option<f32>,
+    // Set to 0 to disable autosaving.
+    #[structopt(long, default_value = "5.0")]
+    pub autosave_interval_minutes: f32,
+    #[structopt(long, default_value = "3")]
+    pub autosave_slots: u32,
+    // Carry out destructive orders as soon as their key is pressed.
+    #[structopt(long)]
+    pub skip_order_confirmation: bool,
+    // How far from their carrier escorts keep station.
+    #[structopt(long, default_value = "20.0")]
+    pub escort_radius: f32,
+    // Wait for another player to connect on this address.
+    #[structopt(long)]
+    pub host: Option<String>,
+    // Connect to a player hosting on this address.
+    #[structopt(long, conflicts_with = "host")]
+    pub connect: Option<String>,
+    // Shown to the other player in multiplayer.
+    #[structopt(long, default_value = "Player")]
+    pub player_name: String,
+    // One of green, cyan, orange, purple, yellow or white. If the other player has already picked
+    // it, the next free one is used instead.
+    #[structopt(long, default_value = "green")]
+    pub team_colour: TeamColour,
+    // Use this seed instead of a random one. Ignored when connecting to another player.
+    #[structopt(long)]
+    pub seed: Option<u64>,
+    // Record the commands given during the game, to be written out when the window is closed.
+    #[structopt(long)]
+    pub record_replay: Option<PathBuf>,
+    // Play back a recorded game, using its seed.
+    #[structopt(long, conflicts_with_all = &["host", "connect"])]
+    pub replay: Option<PathBuf>,
+    // Write hashes of each part of the world every tick, to find where two runs diverge by
+    // diffing the logs.
+    #[structopt(long)]
+    pub hash_log: Option<PathBuf>,
+    // A RON file of triggers with scripts to run when they fire. Both players need the same one.
+    #[structopt(long)]
+    pub scenario: Option<PathBuf>,
+    // Once the replay has finished and this many more ticks have passed, render a single frame
+    // offscreen, compare it against the golden image and exit.
+    #[structopt(long)]
+    pub golden_image: Option<PathBuf>,
+    #[structopt(long, default_value = "120")]
+    pub golden_image_ticks: u32,
+    // Overwrite the golden image with the rendered frame instead of comparing against it.
+    #[structopt(long, requires = "golden-image")]
+    pub bless: bool,
+    // Read out the selection, notifications, minerals and objectives as they change. `print`
+    // writes them to stdout, anything else is run as a program with the text as its argument,
+    // such as `espeak`.
+    #[structopt(long)]
+    pub screen_reader: Option<String>,
+    // Drop crates around the map that give a boost to whichever ship flies through them first.
+    // Both players need the same setting.
+    #[structopt(long)]
+    pub pickups: bool,
+}
+
+#[derive(StructOpt)]
+pub enum Subcommand {
+    // Fight out a matchup such as `--matchup fighters=20 vs frigates=4` without a window, over
+    // a number of seeds, and print how each side fared.
+    Simulate {
+        #[structopt(long, required = true, min_values = 3)]
+        matchup: Vec<String>,
+        #[structopt(long, default_value = "100")]
+        runs: u32,
+        // Runs where both sides still have ships after this many seconds count as draws.
+        #[structopt(long, default_value = "300")]
+        time_limit: f32,
+    },
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum TextureFiltering {
+    Nearest,
+    Bilinear,
+    Trilinear,
+    Anisotropic,
+}
+
+impl TextureFiltering {
+    pub fn sampler_descriptor(self) -> wgpu::SamplerDescriptor<'static> {
+        let (filter, mipmap_filter) = match self {
+            Self::Nearest => (wgpu::FilterMode::Nearest, wgpu::FilterMode::Nearest),
+            Self::Bilinear => (wgpu::FilterMode::Linear, wgpu::FilterMode::Nearest),
+            Self::Trilinear | Self::Anisotropic => {
+                (wgpu::FilterMode::Linear, wgpu::FilterMode::Linear)
+            }
+        };
+
+        wgpu::SamplerDescriptor {
+            label: Some("ship texture sampler"),
+            mag_filter: filter,
+            min_filter: filter,
+            mipmap_filter,
+            anisotropy_clamp: match self {
+                Self::Anisotropic => std::num::NonZeroU8::new(16),
+                _ => None,
+            },
+            ..Default::default()
+        }
+    }
+}
+
+impl std::str::FromStr for TextureFiltering {
+    type Err = String;
+
+    fn from_str(string: &str) -> Result<Self, Self::Err> {
+        match string {
+            "nearest" => Ok(Self::Nearest),
+            "bilinear" => Ok(Self::Bilinear),
+            "trilinear" => Ok(Self::Trilinear),
+            "anisotropic" => Ok(Self::Anisotropic),
+            _ => Err(format!(
+                "'{}' isn't one of nearest, bilinear, trilinear or anisotropic",
+                string
+            )),
+        }
+    }
+}
+
+// Red and blue are left out as they're used for enemy and neutral ships.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum TeamColour {
+    Green,
+    Cyan,
+    Orange,
+    Purple,
+    Yellow,
+    White,
+}
+
+impl TeamColour {
+    pub const ARRAY: [Self; 6] = [
+        Self::Green,
+        Self::Cyan,
+        Self::Orange,
+        Self::Purple,
+        Self::Yellow,
+        Self::White,
+    ];
+
+    pub fn rgb(self) -> Vec3 {
+        match self {
+            Self::Green => Vec3::new(0.25, 1.0, 0.25),
+            Self::Cyan => Vec3::new(0.25, 1.0, 1.0),
+            Self::Orange => Vec3::new(1.0, 0.6, 0.1),
+            Self::Purple => Vec3::new(0.7, 0.3, 1.0),
+            Self::Yellow => Vec3::new(1.0, 1.0, 0.25),
+            Self::White => Vec3::one(),
+        }
+    }
+
+    pub fn rgba(self) -> [f32; 4] {
+        let rgb = self.rgb();
+        [rgb.x, rgb.y, rgb.z, 1.0]
+    }
+
+    // Unknown indices, e.g. from a newer version of the game, wrap around.
+    pub fn from_index(index: u8) -> Self {
+        Self::ARRAY[index as usize % Self::ARRAY.len()]
+    }
+}
+
+impl std::str::FromStr for TeamColour {
+    type Err = String;
+
+    fn from_str(string: &str) -> Result<Self, Self::Err> {
+        match string {
+            "green" => Ok(Self::Green),
+            "cyan" => Ok(Self::Cyan),
+            "orange" => Ok(Self::Orange),
+            "purple" => Ok(Self::Purple),
+            "yellow" => Ok(Self::Yellow),
+            "white" => Ok(Self::White),
+            _ => Err(format!(
+                "'{}' isn't one of green, cyan, orange, purple, yellow or white",
+                string
+            )),
+        }
+    }
+}
+
+// The name and fleet colour of everyone in the game, in player order.
+pub struct Players {
+    pub players: Vec<(String, TeamColour)>,
+    pub local: usize,
+}
+
+impl Players {
+    // Players keep their preferred colour unless someone earlier in the player order already has
+    // it, in which case they get the next free one. Both sides resolve this the same way, so
+    // they agree on who has which colour without any more messages.
+    pub fn new(preferences: Vec<(String, TeamColour)>, local: usize) -> Self {
+        let mut players: Vec<(String, TeamColour)> = Vec::with_capacity(preferences.len());
+
+        for (name, preferred) in preferences {
+            let start = TeamColour::ARRAY
+                .iter()
+                .position(|&colour| colour == preferred)
+                .unwrap_or(0);
+
+            let colour = (0..TeamColour::ARRAY.len())
+                .map(|offset| TeamColour::ARRAY[(start + offset) % TeamColour::ARRAY.len()])
+                .find(|&colour| players.iter().all(|(_, taken)| *taken != colour))
+                .unwrap_or(preferred);
+
+            players.push((name, colour));
+        }
+
+        Self { players, local }
+    }
+
+    pub fn local_colour(&self) -> TeamColour {
+        self.players[self.local].1
+    }
+
+    // `None` against the AI, which isn't in the player list.
+    pub fn opponent_colour(&self) -> Option<TeamColour> {
+        self.players
+            .iter()
+            .enumerate()
+            .find(|&(index, _)| index != self.local)
+            .map(|(_, &(_, colour))| colour)
+    }
+
+    // The host plays the `Friendly` side and whoever connects plays the `Enemy` side.
+    pub fn local_is_friendly(&self) -> bool {
+        self.local == 0
+    }
+
+    pub fn local_side(&self) -> Side {
+        if self.local_is_friendly() {
+            Side::Friendly
+        } else {
+            Side::Enemy
+        }
+    }
 }
 
 pub struct DpiFactor(pub f32);
 
+// Filled in by the renderer, so it's always a frame behind when displayed.
+#[derive(Default)]
+pub struct RenderStats {
+    pub draw_calls: u32,
+    pub instances_per_pipeline: Vec<(&'static str, u32)>,
+}
+
+impl RenderStats {
+    pub fn record(&mut self, pipeline: &'static str, draw_calls: u32, instances: u32) {
+        self.draw_calls += draw_calls;
+
+        match self
+            .instances_per_pipeline
+            .iter_mut()
+            .find(|(name, _)| *name == pipeline)
+        {
+            Some((_, total)) => *total += instances,
+            None => self.instances_per_pipeline.push((pipeline, instances)),
+        }
+    }
+}
+
+#[derive(Default)]
+pub struct SelectedFormationShape(pub FormationShape);
+
+// Whether ships ordered to move together all fly at the speed of the slowest of them.
+#[derive(Default)]
+pub struct GroupSpeed(pub bool);
+
+#[derive(Default)]
+pub struct ControlGroups {
+    pub groups: [Vec<Entity>; 9],
+    // A carrier whose newly built ships replace any losses in the group.
+    pub reinforcement_pools: [Option<Entity>; 9],
+    lost_since_recall: [usize; 9],
+    awaiting_reinforcements: [usize; 9],
+    last_recall: Option<(usize, std::time::Instant)>,
+}
+
+impl ControlGroups {
+    const DOUBLE_TAP_TIME: f32 = 0.3;
+
+    pub fn assign(&mut self, group: usize, entities: Vec<Entity>) {
+        self.groups[group] = entities;
+        self.lost_since_recall[group] = 0;
+        self.awaiting_reinforcements[group] = 0;
+    }
+
+    // Drops any ships that no longer exist from the groups, keeping track of how many were lost.
+    pub fn remove_dead(&mut self, is_alive: impl Fn(Entity) -> bool) {
+        for i in 0..self.groups.len() {
+            let before = self.groups[i].len();
+            self.groups[i].retain(|&entity| is_alive(entity));
+            let lost = before - self.groups[i].len();
+
+            self.lost_since_recall[i] += lost;
+
+            if self.reinforcement_pools[i].is_some() {
+                self.awaiting_reinforcements[i] += lost;
+            }
+
+            if matches!(self.reinforcement_pools[i], Some(pool) if !is_alive(pool)) {
+                self.reinforcement_pools[i] = None;
+                self.awaiting_reinforcements[i] = 0;
+            }
+        }
+    }
+
+    pub fn take_losses(&mut self, group: usize) -> usize {
+        std::mem::take(&mut self.lost_since_recall[group])
+    }
+
+    // Adds a freshly built ship to the first group that is waiting on reinforcements from its
+    // carrier, returning that group.
+    pub fn backfill(&mut self, carrier: Entity, ship: Entity) -> Option<usize> {
+        let group = (0..self.groups.len()).find(|&i| {
+            self.reinforcement_pools[i] == Some(carrier) && self.awaiting_reinforcements[i] > 0
+        })?;
+
+        self.groups[group].push(ship);
+        self.awaiting_reinforcements[group] -= 1;
+
+        Some(group)
+    }
+
+    // Returns true if the same group was recalled just before, meaning that the camera should
+    // jump to it.
+    pub fn recall(&mut self, group: usize) -> bool {
+        let now = std::time::Instant::now();
+
+        let double_tapped = matches!(
+            self.last_recall,
+            Some((last_group, last_time))
+                if last_group == group
+                    && (now - last_time).as_secs_f32() < Self::DOUBLE_TAP_TIME
+        );
+
+        self.last_recall = Some((group, now));
+
+        double_tapped
+    }
+
+    pub fn groups_containing(&self, entity: Entity) -> impl Iterator<Item = usize> + '_ {
+        (0..self.groups.len()).filter(move |&i| self.groups[i].contains(&entity))
+    }
+}
+
+// Which cluster of friendly ships the camera last jumped to.
+#[derive(Default)]
+pub struct FleetCycle(pub Option<usize>);
+
+impl FleetCycle {
+    // Ships further apart than this are treated as being in different fleets.
+    pub const CLUSTER_SIZE: f32 = 50.0;
+}
+
+// Friendly ships that have nothing to do, counted for the resource bar.
+#[derive(Default)]
+pub struct IdleShips {
+    pub miners: usize,
+    pub combat: usize,
+    // The idle ship that was last selected.
+    last: Option<Entity>,
+}
+
+impl IdleShips {
+    // Goes on to the next idle ship after the last one, so that repeated presses visit them all.
+    pub fn next(&mut self, mut idle: Vec<Entity>) -> Option<Entity> {
+        idle.sort_unstable();
+
+        let next = match self.last {
+            Some(last) => idle
+                .iter()
+                .find(|&&entity| entity > last)
+                .or_else(|| idle.first()),
+            None => idle.first(),
+        };
+
+        self.last = next.copied();
+        self.last
+    }
+}
+
+// The enemy ship that the cursor was last moved to.
+#[derive(Default)]
+pub struct TargetCycle(pub Option<Entity>);
+
+#[derive(Default)]
+pub struct GlobalRulesOfEngagement(pub RulesOfEngagement);
+
 #[derive(Default)]
 pub struct GlobalResearch(pub f32);
 
 #[derive(Default)]
 pub struct GlobalMinerals(pub f32);
 
+#[derive(Default)]
+pub struct GlobalGas(pub f32);
+
+// Earned by selling minerals to trading stations.
+#[derive(Default)]
+pub struct GlobalCredits(pub f32);
+
+// A resource that each side has their own of, such as `PerSide<GlobalMinerals>`.
+#[derive(Default, Clone)]
+pub struct PerSide<T> {
+    pub friendly: T,
+    pub enemy: T,
+}
+
+impl<T> std::ops::Index<Side> for PerSide<T> {
+    type Output = T;
+
+    fn index(&self, side: Side) -> &T {
+        match side {
+            Side::Friendly => &self.friendly,
+            Side::Enemy => &self.enemy,
+        }
+    }
+}
+
+impl<T> std::ops::IndexMut<Side> for PerSide<T> {
+    fn index_mut(&mut self, side: Side) -> &mut T {
+        match side {
+            Side::Friendly => &mut self.friendly,
+            Side::Enemy => &mut self.enemy,
+        }
+    }
+}
+
+// How many relics each side has delivered.
+#[derive(Default, Clone, Copy, Serialize, Deserialize)]
+pub struct ObjectiveScore {
+    pub friendly: u32,
+    pub enemy: u32,
+}
+
+// Once the belt has been mostly mined out, a new cluster of asteroids is brought in every so
+// often.
+#[derive(Default, Clone, Copy, Serialize, Deserialize)]
+pub struct AsteroidRespawns {
+    pub next_check: Timer,
+}
+
+impl AsteroidRespawns {
+    pub const INTERVAL: f32 = 180.0;
+    // The fraction of the belt that can still be mined below which clusters start coming in.
+    pub const THRESHOLD: f32 = 0.25;
+
+    pub fn belt_depleted(minable: usize, total: usize) -> bool {
+        (minable as f32) < total as f32 * Self::THRESHOLD
+    }
+}
+
+// Drops pairs of pickups onto the map every so often, if they've been turned on for the match.
+#[derive(Default, Clone, Copy, Serialize, Deserialize)]
+pub struct PickupSpawner {
+    pub enabled: bool,
+    pub next_spawn: Timer,
+}
+
+impl PickupSpawner {
+    pub const INTERVAL: f32 = 45.0;
+    // Spawning stops while there are this many pickups waiting to be collected.
+    pub const MAX_PICKUPS: usize = 8;
+
+    pub fn new(enabled: bool) -> Self {
+        Self {
+            enabled,
+            next_spawn: Timer {
+                ends_at: Self::INTERVAL,
+            },
+        }
+    }
+}
+
 pub type TopLevelAccelerationStructure = DynamicBvh<Entity>;
 
+pub struct EngineHum {
+    pub volume: f32,
+    pub pitch: f32,
+}
+
+impl Default for EngineHum {
+    fn default() -> Self {
+        Self {
+            volume: 0.0,
+            pitch: 1.0,
+        }
+    }
+}
+
 pub struct MiscTextures {
     pub mined_out_asteroid: u32,
 }
 
 #[derive(Default)]
-pub struct UnitButtons(pub Vec<(ModelId, UnitStatus)>);
+pub struct UnitButtons {
+    pub buttons: Vec<(ModelId, UnitStatus)>,
+    // The top-left corner of the first button, in pixels. Moves with the HUD layout.
+    pub origin: Vec2,
+    // The throttle line sits just above the first button and cycles the throttle when clicked.
+    pub throttle_shown: bool,
+    pub throttle_hovered: bool,
+}
 
 impl UnitButtons {
     pub const LINE_HEIGHT: f32 = 18.0;
     pub const BUTTON_WIDTH: f32 = 130.0;
-    pub const UI_LINES: isize = 2;
+    // Rules of engagement, formation, stance, relics and throttle.
+    pub const UI_LINES: usize = 5;
 }
 
 #[derive(Default)]
@@ -78,19 +649,478 @@ impl UnitStatus {
     }
 }
 
+// A tactical pause. Ships can still be selected and given orders, which are carried out once the
+// game resumes.
 pub struct Paused(pub bool);
 
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum SaveLoad {
+    Save,
+    Load,
+    Autosave { slot: u32 },
+}
+
+// Handled outside of the schedule as saving and loading need access to the whole world.
+#[derive(Default)]
+pub struct SaveLoadRequest(pub Option<SaveLoad>);
+
+// Orders from a player. Input handling pushes these instead of changing command queues directly
+// so that in multiplayer they can be sent to the other player and applied on the same tick.
+#[derive(Clone, Serialize, Deserialize)]
+pub enum PlayerCommand {
+    Orders {
+        orders: Vec<(Entity, Command)>,
+        queued: bool,
+        timing: Option<OrderTiming>,
+    },
+    Stop {
+        ships: Vec<Entity>,
+    },
+    // Releases every ship waiting for the mark.
+    Mark,
+    // Sets up the followers of a formation after they've been given their orders.
+    Formation {
+        leader: Entity,
+        followers: Vec<(Entity, Vec3)>,
+    },
+    HoldPosition {
+        ships: Vec<Entity>,
+        hold: bool,
+    },
+    // `None` goes back to using the closest carrier.
+    SetDropOff {
+        miners: Vec<Entity>,
+        carrier: Option<Entity>,
+    },
+    LayMine {
+        ships: Vec<Entity>,
+    },
+    LayMinefield {
+        ships: Vec<Entity>,
+        point: Vec3,
+    },
+    Cloak {
+        ships: Vec<Entity>,
+        cloak: bool,
+    },
+    SetCarrierDoctrine {
+        carriers: Vec<Entity>,
+        doctrine: CarrierDoctrine,
+    },
+    SetAutonomy {
+        ships: Vec<Entity>,
+        autonomy: Autonomy,
+    },
+    SetRetreat {
+        ships: Vec<Entity>,
+        retreat: bool,
+    },
+    SetTargetingStance {
+        ships: Vec<Entity>,
+        stance: TargetingStance,
+    },
+    SetStance {
+        ships: Vec<Entity>,
+        stance: Stance,
+    },
+    SetThrottle {
+        ships: Vec<Entity>,
+        throttle: Throttle,
+    },
+    SetGameSpeed(GameSpeed),
+    SetPaused(bool),
+    SetRallyPoint {
+        carriers: Vec<Entity>,
+        point: Vec3,
+    },
+    Escort {
+        carrier: Entity,
+        escorts: Vec<(Entity, Vec3)>,
+    },
+    Research(Tech),
+    TransferCrew {
+        person: Entity,
+        from: Entity,
+        to: Entity,
+    },
+    Purchase {
+        purchase: SpecialPurchase,
+        carrier: Entity,
+    },
+    Construct {
+        ships: Vec<Entity>,
+        structure: StructureType,
+        point: Vec3,
+    },
+    // Goes to whichever of the carriers will finish it first.
+    Build {
+        carriers: Vec<Entity>,
+        ship: ShipType,
+    },
+    CancelBuildQueues {
+        carriers: Vec<Entity>,
+    },
+    // Refunds the ship at `index` in the carrier's build queue. `ship` is what was there when the
+    // order was given, as the queue may have moved on by the time it's carried out.
+    CancelBuild {
+        carrier: Entity,
+        index: usize,
+        ship: ShipType,
+    },
+    // Moves the ship at `index` one place up the queue, or down if `earlier` is false.
+    ReorderBuild {
+        carrier: Entity,
+        index: usize,
+        ship: ShipType,
+        earlier: bool,
+    },
+    // `None` unloads everything.
+    Unload {
+        carriers: Vec<Entity>,
+        model: Option<ModelId>,
+    },
+    // Whether newly built ships stay on board instead of being launched.
+    SetStayCarried {
+        carriers: Vec<Entity>,
+        stay_carried: bool,
+    },
+    Scuttle {
+        ships: Vec<Entity>,
+        scuttle: bool,
+    },
+    SetRulesOfEngagement(RulesOfEngagement),
+    // `None` goes back to the global rules.
+    SetRulesOfEngagementOverride {
+        ships: Vec<Entity>,
+        rules: Option<RulesOfEngagement>,
+    },
+    // Both players load their quicksave on the same tick.
+    QuickLoad,
+}
+
+// Things that can only be bought with credits. They're delivered to a carrier.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub enum SpecialPurchase {
+    Mercenaries,
+    GasShipment,
+}
+
+impl SpecialPurchase {
+    pub const MERCENARY_FIGHTERS: usize = 4;
+    pub const GAS_SHIPMENT: f32 = 40.0;
+
+    pub fn cost(self) -> f32 {
+        match self {
+            Self::Mercenaries => 150.0,
+            Self::GasShipment => 60.0,
+        }
+    }
+}
+
+// Lists the crew of two ships next to each other. Clicking on someone moves them to the other ship.
+#[derive(Default)]
+pub struct CrewTransferPanel {
+    pub ships: Option<(Entity, Entity)>,
+    pub anchor: Vec2,
+    pub hovered: Option<Entity>,
+}
+
+impl CrewTransferPanel {
+    pub const WIDTH: f32 = 200.0;
+    // People can only be moved between ships that are close together.
+    pub const RANGE: f32 = 50.0;
+}
+
+// The build queue of the selected carrier, when only one is selected.
+#[derive(Default)]
+pub struct ProductionPanel {
+    pub carrier: Option<Entity>,
+    // An index into the queue.
+    pub hovered: Option<usize>,
+}
+
+impl ProductionPanel {
+    pub const WIDTH: f32 = 420.0;
+
+    // A heading and then a line for each queued ship.
+    pub fn size(queue_length: usize, dpi_factor: f32) -> Vec2 {
+        Vec2::new(
+            Self::WIDTH,
+            (1 + queue_length) as f32 * UnitButtons::LINE_HEIGHT,
+        ) * dpi_factor
+    }
+
+    pub fn item_at(offset: Vec2, queue_length: usize, dpi_factor: f32) -> Option<usize> {
+        let size = Self::size(queue_length, dpi_factor);
+
+        if offset.x < 0.0 || offset.y < 0.0 || offset.x >= size.x || offset.y >= size.y {
+            return None;
+        }
+
+        let line = (offset.y / (UnitButtons::LINE_HEIGHT * dpi_factor)) as usize;
+        line.checked_sub(1)
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub enum OrderTiming {
+    Delay(f32),
+    OnMark,
+}
+
+// Applied to the next order that's given.
+#[derive(Default)]
+pub struct PendingOrderTiming(pub Option<OrderTiming>);
+
+impl PendingOrderTiming {
+    pub fn cycle(&mut self) {
+        self.0 = match self.0 {
+            None => Some(OrderTiming::Delay(10.0)),
+            Some(OrderTiming::Delay(delay)) if delay < 30.0 => {
+                Some(OrderTiming::Delay(delay + 10.0))
+            }
+            Some(OrderTiming::Delay(_)) => Some(OrderTiming::OnMark),
+            Some(OrderTiming::OnMark) => None,
+        };
+    }
+}
+
+impl OrderTiming {
+    pub fn wait_for(self, clock: &SimClock) -> WaitFor {
+        match self {
+            Self::Delay(delay) => WaitFor::Time(clock.timer(delay)),
+            Self::OnMark => WaitFor::Mark,
+        }
+    }
+}
+
+#[derive(Default)]
+pub struct PlayerCommands {
+    // Issued this frame.
+    pub local: Vec<PlayerCommand>,
+    // Ready to be applied, from all players, along with the side that gave them.
+    pub to_apply: Vec<(Side, PlayerCommand)>,
+    // Held back while paused, to be applied when the game resumes.
+    pub deferred: Vec<(Side, PlayerCommand)>,
+}
+
+pub struct AutosaveTimer {
+    pub interval: f32,
+    pub slots: u32,
+    pub next_autosave: f32,
+    pub next_slot: u32,
+}
+
+impl AutosaveTimer {
+    pub fn new(settings: &Settings, total_time: f32) -> Self {
+        let mut timer = Self {
+            interval: settings.autosave_interval_minutes * 60.0,
+            slots: settings.autosave_slots.max(1),
+            next_autosave: 0.0,
+            next_slot: 0,
+        };
+
+        timer.reschedule(total_time);
+        timer
+    }
+
+    pub fn reschedule(&mut self, total_time: f32) {
+        self.next_autosave = total_time + self.interval;
+    }
+
+    pub fn enabled(&self) -> bool {
+        self.interval > 0.0
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum DestructiveOrder {
+    CancelBuildQueues,
+    Scuttle,
+}
+
+#[derive(Default)]
+pub struct OrderConfirmation {
+    holding: Option<(DestructiveOrder, f32)>,
+    // Set for the single frame on which an order is confirmed.
+    pub confirmed: Option<DestructiveOrder>,
+    // Stops an order from repeating while its key is still held after being confirmed.
+    waiting_for_release: bool,
+}
+
+impl OrderConfirmation {
+    pub const HOLD_DURATION: f32 = 0.75;
+
+    pub fn update(&mut self, held: Option<DestructiveOrder>, delta_time: f32, skip: bool) {
+        self.confirmed = None;
+
+        let held = match held {
+            Some(held) => held,
+            None => {
+                self.holding = None;
+                self.waiting_for_release = false;
+                return;
+            }
+        };
+
+        if self.waiting_for_release {
+            return;
+        }
+
+        let held_for = match self.holding {
+            Some((order, held_for)) if order == held => held_for + delta_time,
+            _ => 0.0,
+        };
+
+        if skip || held_for >= Self::HOLD_DURATION {
+            self.confirmed = Some(held);
+            self.holding = None;
+            self.waiting_for_release = true;
+        } else {
+            self.holding = Some((held, held_for));
+        }
+    }
+
+    pub fn progress(&self) -> Option<f32> {
+        self.holding
+            .map(|(_, held_for)| held_for / Self::HOLD_DURATION)
+    }
+}
+
+// Explains why an order couldn't be given.
+#[derive(Default)]
+pub struct OrderFeedback {
+    // Set by whatever rejected the order, and picked up by `report_rejected_orders`.
+    pub rejected: Option<&'static str>,
+    // Where the cursor was, and when.
+    pub flash: Option<(Vec2, f32)>,
+    pub play_error_blip: bool,
+}
+
+impl OrderFeedback {
+    pub const FLASH_DURATION: f32 = 0.5;
+}
+
+// Messages shown in the bottom left corner for a few seconds each.
+#[derive(Default, Clone)]
+pub struct Notifications {
+    shown: VecDeque<(String, f32)>,
+    total_pushed: usize,
+}
+
+impl Notifications {
+    pub const DURATION: f32 = 4.0;
+    const MAX_SHOWN: usize = 5;
+
+    pub fn push(&mut self, message: impl Into<String>, total_time: f32) {
+        if self.shown.len() == Self::MAX_SHOWN {
+            self.shown.pop_front();
+        }
+
+        self.shown.push_back((message.into(), total_time));
+        self.total_pushed += 1;
+    }
+
+    pub fn total_pushed(&self) -> usize {
+        self.total_pushed
+    }
+
+    // Puts back the messages from an earlier copy, but keeps counting up from here so that what's
+    // been read out isn't read out again.
+    pub fn restore(&mut self, earlier: Self) {
+        self.shown = earlier.shown;
+    }
+
+    // The most recent notifications that are still shown, oldest first.
+    pub fn latest(&self, count: usize) -> impl Iterator<Item = &str> {
+        self.iter().skip(self.len().saturating_sub(count))
+    }
+
+    pub fn remove_expired(&mut self, total_time: f32) {
+        while let Some(&(_, pushed_at)) = self.shown.front() {
+            if total_time - pushed_at < Self::DURATION {
+                break;
+            }
+
+            self.shown.pop_front();
+        }
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &str> {
+        self.shown.iter().map(|(message, _)| message.as_str())
+    }
+
+    pub fn len(&self) -> usize {
+        self.shown.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.shown.is_empty()
+    }
+}
+
 pub enum MouseMode {
     Normal,
-    Movement { point_on_plane: Vec3, ty: MoveType },
+    Movement {
+        point_on_plane: Vec3,
+        ty: MoveType,
+        match_slowest: bool,
+        // Set by dragging out the right click that gives the order.
+        facing: Option<Vec3>,
+    },
+    AreaTargeting {
+        area: AreaOfEffect,
+        point: Vec3,
+    },
+    // The next right click sets where the selected carriers send the ships they build.
+    SetRallyPoint {
+        point_on_plane: Vec3,
+    },
+    // The next right click on a nearby friendly ship opens the crew transfer panel.
+    TransferCrew {
+        source: Entity,
+    },
+    // The next right click lays out a construction site for the selected construction ships.
+    PlaceStructure {
+        structure: StructureType,
+        point_on_plane: Vec3,
+    },
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct AreaOfEffect {
+    pub radius: f32,
+    // The effect drops off in this many equal steps between the center and the edge.
+    pub falloff_bands: u32,
+}
+
+impl AreaOfEffect {
+    pub fn falloff_at(&self, distance: f32) -> f32 {
+        if distance >= self.radius {
+            return 0.0;
+        }
+
+        let bands = self.falloff_bands.max(1);
+        let band = (distance / self.radius * bands as f32) as u32;
+        (bands - band) as f32 / bands as f32
+    }
+
+    pub fn band_radii(&self) -> impl Iterator<Item = f32> + '_ {
+        let bands = self.falloff_bands.max(1);
+        (1..=bands).map(move |band| self.radius * band as f32 / bands as f32)
+    }
 }
 
 #[derive(Default)]
 pub struct AverageSelectedPosition(pub Option<Vec3>);
 
-pub struct TotalTime(pub f32);
+// Keeps going while paused, for UI animations.
+pub struct RealTime(pub f32);
 
-pub struct DeltaTime(pub f32);
+// Each sun's direction and how hot its heat zone gets, for the simulation to use. They come from
+// the map seed and scenario rather than the player's lighting preset, so they're the same for
+// both players.
+pub struct HeatZones(pub Vec<(Vec3, f32)>);
 
 pub struct GpuInterface {
     pub device: wgpu::Device,
@@ -100,24 +1130,48 @@ pub struct GpuInterface {
 #[derive(Default)]
 pub struct ShipUnderCursor(pub Option<Entity>);
 
+// Kept apart from `ModelBuffers` so that the simulation can run without a gpu.
 pub struct Models {
+    pub models: [Model; Self::COUNT],
+}
+
+pub struct ModelBuffers {
     pub vertices: wgpu::Buffer,
     pub indices: wgpu::Buffer,
     pub bounding_boxes: wgpu::Buffer,
     pub bind_group: wgpu::BindGroup,
-    pub models: [Model; Self::COUNT],
 }
 
 impl Models {
-    pub const COUNT: usize = 5;
+    pub const COUNT: usize = 17;
     pub const ARRAY: [ModelId; Self::COUNT] = [
         ModelId::Carrier,
         ModelId::Fighter,
         ModelId::Miner,
         ModelId::Explosion,
         ModelId::Asteroid,
+        ModelId::DefensePlatform,
+        ModelId::Shipyard,
+        ModelId::Refinery,
+        ModelId::Frigate,
+        ModelId::Destroyer,
+        ModelId::Battleship,
+        ModelId::TradingStation,
+        ModelId::Minelayer,
+        ModelId::RepairShip,
+        ModelId::GasHarvester,
+        ModelId::ConstructionShip,
+        ModelId::Freighter,
     ];
 
+    // Takes one model per id, in the same order as `ARRAY`.
+    pub fn new(models: Vec<Model>) -> Self {
+        match models.try_into() {
+            Ok(models) => Self { models },
+            Err(models) => panic!("Expected {} models, got {}", Self::COUNT, models.len()),
+        }
+    }
+
     pub const MINER_LASER_OFFSET: Vec3 = Vec3::new(0.0, 1.89621, 0.87578);
 
     pub fn get(&self, id: ModelId) -> &Model {
@@ -163,6 +1217,7 @@ impl Dimensions {
     }
 }
 
+#[derive(Clone)]
 pub struct Orbit {
     pub longitude: f32,
     pub latitude: f32,
@@ -182,6 +1237,10 @@ impl Orbit {
             .min(PI - epsilon);
     }
 
+    pub fn set_distance(&mut self, distance: f32) {
+        self.distance = distance.max(1.0).min(250.0);
+    }
+
     pub fn zoom(&mut self, delta: f32) {
         self.distance = (self.distance * (1.0 + delta * 0.1)).max(1.0).min(250.0);
     }
@@ -264,4 +1323,169 @@ impl PerspectiveView {
         self.view_without_movement = Mat4::look_at(Vec3::zero(), -orbit, Vec3::unit_y());
         self.recalculate();
     }
+
+    pub fn is_on_screen(&self, point: Vec3) -> bool {
+        let projected = self.perspective_view * Vec4::new(point.x, point.y, point.z, 1.0);
+
+        if projected.z < 0.0 {
+            return false;
+        }
+
+        let screen_space = Vec2::new(projected.x, projected.y) / projected.w;
+
+        screen_space.x.abs() <= 1.0 && screen_space.y.abs() <= 1.0
+    }
+
+    // Returns where on the screen the point ends up in pixels, unless it's behind the camera.
+    pub fn project_to_pixels(&self, point: Vec3, dimensions: &Dimensions) -> Option<Vec2> {
+        let projected = self.perspective_view * Vec4::new(point.x, point.y, point.z, 1.0);
+
+        if projected.z < 0.0 {
+            return None;
+        }
+
+        let screen_space = Vec2::new(projected.x, projected.y) / projected.w;
+
+        let uv_space = Vec2::new((screen_space.x + 1.0) / 2.0, (1.0 - screen_space.y) / 2.0);
+
+        Some(uv_space * dimensions.to_vec())
+    }
+}
+
+pub struct PictureInPicture {
+    // The unit that was most recently followed by the main camera.
+    pub target: Option<Entity>,
+    pub visible: bool,
+    pub camera_position: Vec3,
+    pub perspective_view: PerspectiveView,
+    pub orbit: Orbit,
+}
+
+impl PictureInPicture {
+    // The size of the view as a fraction of the window size.
+    pub const SCALE: f32 = 0.25;
+
+    pub fn new(perspective_view: PerspectiveView) -> Self {
+        let mut orbit = Orbit::default();
+        orbit.set_distance(25.0);
+
+        Self {
+            target: None,
+            visible: false,
+            camera_position: Vec3::zero(),
+            perspective_view,
+            orbit,
+        }
+    }
+}
+
+#[test]
+fn test_control_group_losses() {
+    let carrier = Entity::new(0);
+    let ships = [Entity::new(1), Entity::new(2), Entity::new(3)];
+
+    let mut control_groups = ControlGroups::default();
+    control_groups.assign(1, ships.to_vec());
+    control_groups.reinforcement_pools[1] = Some(carrier);
+
+    control_groups.remove_dead(|entity| entity != ships[0] && entity != ships[1]);
+    assert_eq!(control_groups.groups[1], vec![ships[2]]);
+    assert_eq!(control_groups.take_losses(1), 2);
+    assert_eq!(control_groups.take_losses(1), 0);
+
+    assert_eq!(control_groups.backfill(carrier, Entity::new(4)), Some(1));
+    assert_eq!(control_groups.backfill(carrier, Entity::new(5)), Some(1));
+    assert_eq!(control_groups.backfill(carrier, Entity::new(6)), None);
+    assert_eq!(control_groups.groups[1].len(), 3);
+}
+
+#[test]
+fn test_idle_ships() {
+    let ships = vec![Entity::new(3), Entity::new(1), Entity::new(2)];
+
+    let mut idle_ships = IdleShips::default();
+    assert_eq!(idle_ships.next(ships.clone()), Some(Entity::new(1)));
+    assert_eq!(idle_ships.next(ships.clone()), Some(Entity::new(2)));
+    assert_eq!(idle_ships.next(ships.clone()), Some(Entity::new(3)));
+    assert_eq!(idle_ships.next(ships), Some(Entity::new(1)));
+
+    // The last ship found something to do in the meantime.
+    assert_eq!(idle_ships.next(vec![Entity::new(3)]), Some(Entity::new(3)));
+    assert_eq!(idle_ships.next(Vec::new()), None);
+}
+
+#[test]
+fn test_production_panel_items() {
+    let line = UnitButtons::LINE_HEIGHT * 2.0;
+
+    assert_eq!(
+        ProductionPanel::item_at(Vec2::new(10.0, line * 0.5), 3, 2.0),
+        None
+    );
+    assert_eq!(
+        ProductionPanel::item_at(Vec2::new(10.0, line * 1.5), 3, 2.0),
+        Some(0)
+    );
+    assert_eq!(
+        ProductionPanel::item_at(Vec2::new(10.0, line * 3.5), 3, 2.0),
+        Some(2)
+    );
+    assert_eq!(
+        ProductionPanel::item_at(Vec2::new(10.0, line * 4.5), 3, 2.0),
+        None
+    );
+    assert_eq!(
+        ProductionPanel::item_at(Vec2::new(-1.0, line * 1.5), 3, 2.0),
+        None
+    );
+}
+
+#[test]
+fn test_team_colour_conflicts() {
+    let players = Players::new(
+        vec![
+            ("host".into(), TeamColour::Green),
+            ("guest".into(), TeamColour::Green),
+        ],
+        1,
+    );
+
+    assert_eq!(players.players[0].1, TeamColour::Green);
+    assert_eq!(players.local_colour(), TeamColour::Cyan);
+    assert_eq!(players.opponent_colour(), Some(TeamColour::Green));
+
+    let players = Players::new(
+        vec![
+            ("host".into(), TeamColour::White),
+            ("guest".into(), TeamColour::White),
+        ],
+        0,
+    );
+
+    // Wraps around to the start of the palette.
+    assert_eq!(players.players[1].1, TeamColour::Green);
+
+    let players = Players::new(vec![("solo".into(), TeamColour::Orange)], 0);
+    assert_eq!(players.opponent_colour(), None);
+}
+
+#[test]
+fn test_models_array_is_indexed_by_id() {
+    // Models, instance counts and indirect draws are all looked up by `id as usize`.
+    for (i, &model_id) in Models::ARRAY.iter().enumerate() {
+        assert_eq!(model_id as usize, i);
+    }
+}
+
+#[test]
+fn test_restoring_notifications() {
+    let mut notifications = Notifications::default();
+    notifications.push("Before", 0.0);
+    let earlier = notifications.clone();
+
+    notifications.push("During", 1.0);
+    notifications.restore(earlier);
+
+    assert_eq!(notifications.iter().collect::<Vec<_>>(), ["Before"]);
+    assert_eq!(notifications.total_pushed(), 2);
 }
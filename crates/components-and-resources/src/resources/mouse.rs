@@ -6,28 +6,38 @@ pub struct MouseState {
     pub left_state: MouseButtonState,
     pub right_state: MouseButtonState,
     pub middle_state: MouseButtonState,
+    // Set when the cursor is moved with the keyboard, so that the window's cursor can follow it.
+    pub moved_by_keyboard: bool,
 }
 
 #[derive(Debug, Clone)]
 pub enum MouseButtonState {
     Dragging(Vec2),
     Dragged(Vec2),
-    Up,
+    // Holds the time since the last click.
+    Up(f32),
     Clicked,
-    Down(f32, Vec2),
+    DoubleClicked,
+    // Holds the time the button has been down for, where it was pressed and the time between
+    // the last click and the press.
+    Down(f32, Vec2, f32),
 }
 
 impl Default for MouseButtonState {
     fn default() -> Self {
-        Self::Up
+        Self::Up(f32::INFINITY)
     }
 }
 
 impl MouseButtonState {
+    const DOUBLE_CLICK_TIME: f32 = 0.4;
+
     pub fn update(&mut self, delta_time: f32, drag_threshold: f32) {
         match *self {
-            Self::Clicked => *self = Self::Up,
-            Self::Down(ref mut time_down, start) => {
+            Self::Clicked => *self = Self::Up(0.0),
+            // Don't let a third click count as another double click.
+            Self::DoubleClicked => *self = Self::default(),
+            Self::Down(ref mut time_down, start, _) => {
                 let drag = *time_down >= drag_threshold;
 
                 if drag {
@@ -36,8 +46,9 @@ impl MouseButtonState {
                     *time_down += delta_time;
                 }
             }
-            Self::Dragged(_) => *self = Self::Up,
-            Self::Up | Self::Dragging(_) => {}
+            Self::Dragged(_) => *self = Self::default(),
+            Self::Up(ref mut since_click) => *since_click += delta_time,
+            Self::Dragging(_) => {}
         }
     }
 
@@ -50,19 +61,34 @@ impl MouseButtonState {
     }
 
     fn handle_down(&mut self, mouse: Vec2) {
-        *self = Self::Down(0.0, mouse)
+        let since_click = match *self {
+            Self::Up(since_click) => since_click,
+            _ => f32::INFINITY,
+        };
+
+        *self = Self::Down(0.0, mouse, since_click)
     }
 
     fn handle_up(&mut self) {
         match *self {
-            Self::Down(_, _) => *self = Self::Clicked,
+            Self::Down(time_down, _, since_click)
+                if since_click + time_down < Self::DOUBLE_CLICK_TIME =>
+            {
+                *self = Self::DoubleClicked
+            }
+            Self::Down(..) => *self = Self::Clicked,
             Self::Dragging(start) => *self = Self::Dragged(start),
-            _ => *self = Self::Up,
+            _ => *self = Self::default(),
         }
     }
 
+    // Double clicks also count as clicks.
     pub fn was_clicked(&self) -> bool {
-        matches!(self, Self::Clicked)
+        matches!(self, Self::Clicked | Self::DoubleClicked)
+    }
+
+    pub fn was_double_clicked(&self) -> bool {
+        matches!(self, Self::DoubleClicked)
     }
 
     pub fn is_being_dragged(&self) -> Option<Vec2> {
@@ -81,3 +107,24 @@ impl MouseButtonState {
         }
     }
 }
+
+#[test]
+fn test_double_click() {
+    let mut state = MouseButtonState::default();
+
+    state.handle(Vec2::zero(), true);
+    state.handle(Vec2::zero(), false);
+    assert!(state.was_clicked() && !state.was_double_clicked());
+
+    state.update(0.1, 0.1);
+    state.handle(Vec2::zero(), true);
+    state.update(0.05, 0.1);
+    state.handle(Vec2::zero(), false);
+    assert!(state.was_double_clicked());
+
+    // A third click shouldn't be another double click.
+    state.update(0.1, 0.1);
+    state.handle(Vec2::zero(), true);
+    state.handle(Vec2::zero(), false);
+    assert!(state.was_clicked() && !state.was_double_clicked());
+}
@@ -0,0 +1,89 @@
+use super::PlayerCommand;
+use crate::components::Side;
+use crate::world_hash::WorldHashes;
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+// The player commands applied on each tick. The simulation is deterministic for a given seed, so
+// that's all that's needed to play a game back.
+#[derive(Default, Serialize, Deserialize)]
+pub struct InputReplay {
+    pub seed: u64,
+    // Sorted by tick, with only the ticks that had commands.
+    ticks: Vec<(u32, Vec<(Side, PlayerCommand)>)>,
+    // Hashes of the world after some ticks, to check that playing back gives the same game.
+    #[serde(default)]
+    hashes: Vec<(u32, WorldHashes)>,
+}
+
+impl InputReplay {
+    pub fn new(seed: u64) -> Self {
+        Self {
+            seed,
+            ticks: Vec::new(),
+            hashes: Vec::new(),
+        }
+    }
+
+    pub fn load(path: &Path) -> anyhow::Result<Self> {
+        Ok(ron::de::from_str(&std::fs::read_to_string(path)?)?)
+    }
+
+    pub fn save(&self, path: &Path) -> anyhow::Result<()> {
+        let string = ron::ser::to_string(self)?;
+        std::fs::write(path, string)?;
+        Ok(())
+    }
+
+    pub fn record(&mut self, tick: u32, commands: &[(Side, PlayerCommand)]) {
+        if !commands.is_empty() {
+            self.ticks.push((tick, commands.to_vec()));
+        }
+    }
+
+    pub fn commands_for_tick(&self, tick: u32) -> &[(Side, PlayerCommand)] {
+        match self.ticks.binary_search_by_key(&tick, |&(tick, _)| tick) {
+            Ok(index) => &self.ticks[index].1,
+            Err(_) => &[],
+        }
+    }
+
+    pub fn record_hashes(&mut self, tick: u32, hashes: WorldHashes) {
+        self.hashes.push((tick, hashes));
+    }
+
+    pub fn hashes_for_tick(&self, tick: u32) -> Option<&WorldHashes> {
+        self.hashes
+            .binary_search_by_key(&tick, |&(tick, _)| tick)
+            .ok()
+            .map(|index| &self.hashes[index].1)
+    }
+
+    pub fn last_tick(&self) -> u32 {
+        self.ticks.last().map(|&(tick, _)| tick).unwrap_or(0)
+    }
+}
+
+#[test]
+fn test_input_replay() {
+    let mut replay = InputReplay::new(5);
+
+    replay.record(3, &[(Side::Friendly, PlayerCommand::Mark)]);
+    replay.record(4, &[]);
+    replay.record(
+        10,
+        &[
+            (Side::Friendly, PlayerCommand::Mark),
+            (Side::Enemy, PlayerCommand::Mark),
+        ],
+    );
+
+    assert_eq!(replay.commands_for_tick(3).len(), 1);
+    assert!(replay.commands_for_tick(4).is_empty());
+    assert_eq!(replay.commands_for_tick(10).len(), 2);
+    assert_eq!(replay.last_tick(), 10);
+
+    replay.record_hashes(60, WorldHashes(vec![1, 2]));
+    assert_eq!(replay.hashes_for_tick(60), Some(&WorldHashes(vec![1, 2])));
+    assert!(replay.hashes_for_tick(10).is_none());
+}
@@ -0,0 +1,167 @@
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Tech {
+    ImprovedConstruction,
+    ImprovedWeapons,
+    AdvancedWeapons,
+    ImprovedMining,
+    ResearchLabs,
+}
+
+impl Tech {
+    pub const COUNT: usize = 5;
+    pub const ARRAY: [Self; Self::COUNT] = [
+        Self::ImprovedConstruction,
+        Self::ImprovedWeapons,
+        Self::AdvancedWeapons,
+        Self::ImprovedMining,
+        Self::ResearchLabs,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::ImprovedConstruction => "Improved Construction",
+            Self::ImprovedWeapons => "Improved Weapons",
+            Self::AdvancedWeapons => "Advanced Weapons",
+            Self::ImprovedMining => "Improved Mining",
+            Self::ResearchLabs => "Research Labs",
+        }
+    }
+
+    pub fn description(self) -> &'static str {
+        match self {
+            Self::ImprovedConstruction => "+50% build speed",
+            Self::ImprovedWeapons | Self::AdvancedWeapons => "+25% weapon damage",
+            Self::ImprovedMining => "+50% mining rate",
+            Self::ResearchLabs => "+50% research on carriers",
+        }
+    }
+
+    pub fn cost(self) -> f32 {
+        match self {
+            Self::ImprovedConstruction => 10.0,
+            Self::ImprovedWeapons => 15.0,
+            Self::AdvancedWeapons => 40.0,
+            Self::ImprovedMining => 10.0,
+            Self::ResearchLabs => 20.0,
+        }
+    }
+
+    pub fn prerequisites(self) -> &'static [Self] {
+        match self {
+            Self::AdvancedWeapons => &[Self::ImprovedWeapons],
+            Self::ResearchLabs => &[Self::ImprovedConstruction],
+            _ => &[],
+        }
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub enum TechStatus {
+    Unlocked,
+    Researching,
+    Available,
+    Locked,
+}
+
+// One side's research. Their `GlobalResearch` is spent on whichever tech is being researched.
+#[derive(Default, Clone, Serialize, Deserialize)]
+pub struct TechTree {
+    unlocked: [bool; Tech::COUNT],
+    researching: Option<Tech>,
+}
+
+impl TechTree {
+    pub fn is_unlocked(&self, tech: Tech) -> bool {
+        self.unlocked[tech as usize]
+    }
+
+    pub fn status(&self, tech: Tech) -> TechStatus {
+        if self.is_unlocked(tech) {
+            TechStatus::Unlocked
+        } else if self.researching == Some(tech) {
+            TechStatus::Researching
+        } else if tech
+            .prerequisites()
+            .iter()
+            .all(|&prerequisite| self.is_unlocked(prerequisite))
+        {
+            TechStatus::Available
+        } else {
+            TechStatus::Locked
+        }
+    }
+
+    pub fn research(&mut self, tech: Tech) {
+        if self.status(tech) == TechStatus::Available {
+            self.researching = Some(tech);
+        }
+    }
+
+    // Unlocks the tech being researched once enough research has built up, returning it.
+    pub fn advance(&mut self, global_research: &mut f32) -> Option<Tech> {
+        let tech = self.researching?;
+
+        if *global_research < tech.cost() {
+            return None;
+        }
+
+        *global_research -= tech.cost();
+        self.unlocked[tech as usize] = true;
+        self.researching = None;
+
+        Some(tech)
+    }
+
+    pub fn build_speed_bonus(&self) -> f32 {
+        if self.is_unlocked(Tech::ImprovedConstruction) {
+            0.5
+        } else {
+            0.0
+        }
+    }
+
+    pub fn weapon_damage_multiplier(&self) -> f32 {
+        let upgrades = self.is_unlocked(Tech::ImprovedWeapons) as u8
+            + self.is_unlocked(Tech::AdvancedWeapons) as u8;
+
+        1.0 + upgrades as f32 * 0.25
+    }
+
+    pub fn mining_rate_multiplier(&self) -> f32 {
+        if self.is_unlocked(Tech::ImprovedMining) {
+            1.5
+        } else {
+            1.0
+        }
+    }
+}
+
+// Toggled with a key. Techs are chosen by clicking on them.
+#[derive(Default)]
+pub struct ResearchPanel {
+    pub open: bool,
+    pub hovered: Option<Tech>,
+}
+
+impl ResearchPanel {
+    pub const WIDTH: f32 = 300.0;
+}
+
+#[test]
+fn test_tech_tree() {
+    let mut tree = TechTree::default();
+    let mut research = 100.0;
+
+    assert_eq!(tree.status(Tech::AdvancedWeapons), TechStatus::Locked);
+
+    tree.research(Tech::AdvancedWeapons);
+    assert_eq!(tree.advance(&mut research), None);
+
+    tree.research(Tech::ImprovedWeapons);
+    assert_eq!(tree.advance(&mut research), Some(Tech::ImprovedWeapons));
+    assert_eq!(research, 85.0);
+    assert_eq!(tree.status(Tech::AdvancedWeapons), TechStatus::Available);
+    assert_eq!(tree.weapon_damage_multiplier(), 1.25);
+}
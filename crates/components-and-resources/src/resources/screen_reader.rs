@@ -0,0 +1,254 @@
+use crate::components::ModelId;
+use std::collections::VecDeque;
+use std::process::Child;
+
+// Somewhere to send descriptions of what's going on, such as a text-to-speech program.
+pub trait ScreenReaderBackend: Send + Sync {
+    fn speak(&mut self, text: &str) -> anyhow::Result<()>;
+
+    // Called every frame, for backends that have to keep an eye on something after speaking.
+    fn poll(&mut self) -> anyhow::Result<()> {
+        Ok(())
+    }
+}
+
+pub struct PrintBackend;
+
+impl ScreenReaderBackend for PrintBackend {
+    fn speak(&mut self, text: &str) -> anyhow::Result<()> {
+        println!("{}", text);
+        Ok(())
+    }
+}
+
+// Runs a program with each piece of text as its only argument, such as `espeak`. Only one copy
+// runs at a time so that lines are read out in order instead of over each other, and each one is
+// waited on once it's done so that finished copies don't pile up.
+pub struct ProgramBackend {
+    program: String,
+    running: Option<Child>,
+    queue: VecDeque<String>,
+}
+
+impl ProgramBackend {
+    // Older lines are dropped past this, as they'd be out of date by the time they're read out.
+    const MAX_QUEUED: usize = 8;
+
+    pub fn new(program: impl Into<String>) -> Self {
+        Self {
+            program: program.into(),
+            running: None,
+            queue: VecDeque::new(),
+        }
+    }
+}
+
+impl ScreenReaderBackend for ProgramBackend {
+    fn speak(&mut self, text: &str) -> anyhow::Result<()> {
+        if self.queue.len() == Self::MAX_QUEUED {
+            self.queue.pop_front();
+        }
+
+        self.queue.push_back(text.to_string());
+        self.poll()
+    }
+
+    fn poll(&mut self) -> anyhow::Result<()> {
+        if let Some(child) = &mut self.running {
+            if child.try_wait()?.is_none() {
+                return Ok(());
+            }
+
+            self.running = None;
+        }
+
+        if let Some(text) = self.queue.pop_front() {
+            self.running = Some(
+                std::process::Command::new(&self.program)
+                    .arg(text)
+                    .spawn()?,
+            );
+        }
+
+        Ok(())
+    }
+}
+
+impl Drop for ProgramBackend {
+    fn drop(&mut self) {
+        if let Some(mut child) = self.running.take() {
+            let _ = child.kill();
+            let _ = child.wait();
+        }
+    }
+}
+
+#[derive(Clone, Copy)]
+pub enum Topic {
+    Selection,
+    Minerals,
+    Objectives,
+}
+
+impl Topic {
+    const COUNT: usize = 3;
+}
+
+// Reads out the state of the game as it changes, so that it can be followed without looking at
+// the screen. Does nothing unless a backend is set.
+#[derive(Default)]
+pub struct ScreenReader {
+    backend: Option<Box<dyn ScreenReaderBackend>>,
+    // The last thing said about each topic, so that only changes get read out.
+    said: [String; Topic::COUNT],
+    notifications_read: usize,
+    pending: Vec<String>,
+}
+
+impl ScreenReader {
+    // Minerals are read out in steps so that mining doesn't drown everything else out.
+    pub const MINERALS_STEP: f32 = 100.0;
+
+    // `print` writes to stdout, anything else is run as a program.
+    pub fn new(backend: &str) -> Self {
+        let backend: Box<dyn ScreenReaderBackend> = match backend {
+            "print" => Box::new(PrintBackend),
+            program => Box::new(ProgramBackend::new(program)),
+        };
+
+        Self::with_backend(backend)
+    }
+
+    pub fn with_backend(backend: Box<dyn ScreenReaderBackend>) -> Self {
+        Self {
+            backend: Some(backend),
+            ..Default::default()
+        }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.backend.is_some()
+    }
+
+    pub fn announce(&mut self, text: impl Into<String>) {
+        if self.is_enabled() {
+            self.pending.push(text.into());
+        }
+    }
+
+    // Only announces the text if it's different to what was last said about the topic.
+    pub fn report(&mut self, topic: Topic, text: String) {
+        let said = &mut self.said[topic as usize];
+
+        if *said != text {
+            *said = text.clone();
+            self.announce(text);
+        }
+    }
+
+    // Takes the total number of notifications ever pushed, and returns how many of them haven't
+    // been read out yet.
+    pub fn unread_notifications(&mut self, total_pushed: usize) -> usize {
+        let unread = total_pushed - self.notifications_read;
+        self.notifications_read = total_pushed;
+        unread
+    }
+
+    pub fn flush(&mut self) -> anyhow::Result<()> {
+        let backend = match &mut self.backend {
+            Some(backend) => backend,
+            None => return Ok(()),
+        };
+
+        for text in self.pending.drain(..) {
+            backend.speak(&text)?;
+        }
+
+        backend.poll()
+    }
+
+    pub fn selection_summary(counts: &[u32; crate::resources::Models::COUNT]) -> String {
+        let parts: Vec<String> = crate::resources::Models::ARRAY
+            .iter()
+            .filter(|&&model_id| counts[model_id as usize] > 0)
+            .map(|&model_id| {
+                let count = counts[model_id as usize];
+                let plural = if count == 1 { "" } else { "s" };
+                format!("{} {}{}", count, model_name(model_id), plural)
+            })
+            .collect();
+
+        if parts.is_empty() {
+            "Nothing selected".to_string()
+        } else {
+            format!("Selected {}", parts.join(", "))
+        }
+    }
+
+    pub fn minerals_summary(minerals: f32) -> String {
+        let rounded = (minerals / Self::MINERALS_STEP).floor() * Self::MINERALS_STEP;
+        format!("{} minerals", rounded)
+    }
+
+    pub fn objectives_summary(score: &crate::resources::ObjectiveScore) -> String {
+        format!(
+            "Relics delivered: {} to the enemy's {}",
+            score.friendly, score.enemy
+        )
+    }
+
+    pub fn target_summary(model_id: ModelId, index: usize, count: usize) -> String {
+        format!("Enemy {}, {} of {}", model_name(model_id), index + 1, count)
+    }
+}
+
+fn model_name(model_id: ModelId) -> String {
+    crate::assets::model_name(model_id).replace('_', " ")
+}
+
+#[test]
+fn test_screen_reader() {
+    use std::sync::{Arc, Mutex};
+
+    struct Recorder(Arc<Mutex<Vec<String>>>);
+
+    impl ScreenReaderBackend for Recorder {
+        fn speak(&mut self, text: &str) -> anyhow::Result<()> {
+            self.0.lock().unwrap().push(text.to_string());
+            Ok(())
+        }
+    }
+
+    let spoken = Arc::new(Mutex::new(Vec::new()));
+    let mut reader = ScreenReader::with_backend(Box::new(Recorder(spoken.clone())));
+
+    let mut counts = [0; crate::resources::Models::COUNT];
+    reader.report(Topic::Selection, ScreenReader::selection_summary(&counts));
+    counts[ModelId::Fighter as usize] = 3;
+    counts[ModelId::Carrier as usize] = 1;
+    reader.report(Topic::Selection, ScreenReader::selection_summary(&counts));
+    // Nothing has changed.
+    reader.report(Topic::Selection, ScreenReader::selection_summary(&counts));
+    reader.report(Topic::Minerals, ScreenReader::minerals_summary(250.0));
+    reader.report(Topic::Minerals, ScreenReader::minerals_summary(299.0));
+    reader.announce(ScreenReader::target_summary(ModelId::Frigate, 0, 2));
+    reader.flush().unwrap();
+
+    assert_eq!(
+        *spoken.lock().unwrap(),
+        vec![
+            "Nothing selected",
+            "Selected 1 carrier, 3 fighters",
+            "200 minerals",
+            "Enemy frigate, 1 of 2"
+        ]
+    );
+
+    assert_eq!(reader.unread_notifications(3), 3);
+    assert_eq!(reader.unread_notifications(5), 2);
+
+    // Without a backend nothing piles up.
+    let mut disabled = ScreenReader::default();
+    disabled.announce("Hello");
+    assert!(disabled.pending.is_empty());
+}
@@ -0,0 +1,353 @@
+use super::LightingPreset;
+use crate::components::{ModelId, ShipType};
+use rhai::{Engine, EvalAltResult, AST, FLOAT, INT};
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use ultraviolet::Vec3;
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub enum TriggerCondition {
+    // Fires once this many seconds into the game.
+    Timer(f32),
+    // Fires every tick that a ship of this model on the given side is destroyed.
+    UnitDestroyed { model: ModelId, enemy: bool },
+    // Fires once, when a friendly ship first comes within `radius` of `center`.
+    AreaEntered { center: Vec3, radius: f32 },
+}
+
+impl TriggerCondition {
+    fn fires_once(&self) -> bool {
+        !matches!(self, Self::UnitDestroyed { .. })
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Trigger {
+    pub on: TriggerCondition,
+    pub script: String,
+}
+
+// A RON file of triggers, each with a script that's run when the trigger fires.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct Scenario {
+    pub triggers: Vec<Trigger>,
+    // Used unless one is given on the command line.
+    #[serde(default)]
+    pub lighting_preset: Option<LightingPreset>,
+}
+
+impl Scenario {
+    pub fn load(path: &Path) -> anyhow::Result<Self> {
+        Ok(ron::de::from_str(&std::fs::read_to_string(path)?)?)
+    }
+}
+
+// Scripts never get at the world directly. All they can do is queue these up, to be carried out by
+// `run_mission_scripts`.
+#[derive(Clone, Debug, PartialEq)]
+pub enum ScriptCommand {
+    SpawnWave {
+        ship: ShipType,
+        count: u32,
+        position: Vec3,
+    },
+    GrantMinerals(f32),
+    Message(String),
+    // Lighting changes are eased in over `duration` seconds.
+    SetSun {
+        direction: Vec3,
+        colour: Vec3,
+        duration: f32,
+    },
+    SetAmbient {
+        colour: Vec3,
+        duration: f32,
+    },
+    Flyover {
+        center: Vec3,
+        subtitle: String,
+    },
+}
+
+pub struct ScriptHost {
+    engine: Engine,
+    triggers: Vec<(Trigger, AST)>,
+    fired: Vec<bool>,
+    queued: Arc<Mutex<Vec<ScriptCommand>>>,
+    destroyed: Vec<(ModelId, bool)>,
+}
+
+impl Default for ScriptHost {
+    fn default() -> Self {
+        Self::new(Scenario::default()).unwrap()
+    }
+}
+
+impl ScriptHost {
+    // Keeps a script stuck in a loop from hanging the game.
+    const MAX_OPERATIONS: u64 = 100_000;
+    // A typo in a wave size shouldn't be able to spawn millions of ships.
+    const MAX_WAVE_SIZE: INT = 100;
+
+    pub fn new(scenario: Scenario) -> anyhow::Result<Self> {
+        let queued = Arc::new(Mutex::new(Vec::new()));
+
+        let mut engine = Engine::new();
+        engine.set_max_operations(Self::MAX_OPERATIONS);
+
+        let queue = queued.clone();
+        engine.register_fn(
+            "spawn_wave",
+            move |ship: &str, count: INT, x: FLOAT, y: FLOAT, z: FLOAT| {
+                let ship: ShipType = ron::de::from_str(ship)
+                    .map_err(|_| format!("'{}' isn't a type of ship", ship))?;
+
+                queue.lock().unwrap().push(ScriptCommand::SpawnWave {
+                    ship,
+                    count: count.max(0).min(Self::MAX_WAVE_SIZE) as u32,
+                    position: Vec3::new(x as f32, y as f32, z as f32),
+                });
+
+                Ok::<_, Box<EvalAltResult>>(())
+            },
+        );
+
+        let queue = queued.clone();
+        engine.register_fn("grant_minerals", move |amount: FLOAT| {
+            queue
+                .lock()
+                .unwrap()
+                .push(ScriptCommand::GrantMinerals(amount as f32));
+        });
+
+        let queue = queued.clone();
+        engine.register_fn("message", move |message: &str| {
+            queue
+                .lock()
+                .unwrap()
+                .push(ScriptCommand::Message(message.to_string()));
+        });
+
+        let queue = queued.clone();
+        engine.register_fn(
+            "set_sun",
+            move |x: FLOAT, y: FLOAT, z: FLOAT, r: FLOAT, g: FLOAT, b: FLOAT, duration: FLOAT| {
+                let direction = Vec3::new(x as f32, y as f32, z as f32);
+
+                if direction.mag_sq() == 0.0 {
+                    return Err("The sun needs a direction".into());
+                }
+
+                queue.lock().unwrap().push(ScriptCommand::SetSun {
+                    direction: direction.normalized(),
+                    colour: Vec3::new(r as f32, g as f32, b as f32),
+                    duration: duration as f32,
+                });
+
+                Ok::<_, Box<EvalAltResult>>(())
+            },
+        );
+
+        let queue = queued.clone();
+        engine.register_fn(
+            "set_ambient",
+            move |r: FLOAT, g: FLOAT, b: FLOAT, duration: FLOAT| {
+                queue.lock().unwrap().push(ScriptCommand::SetAmbient {
+                    colour: Vec3::new(r as f32, g as f32, b as f32),
+                    duration: duration as f32,
+                });
+            },
+        );
+
+        let queue = queued.clone();
+        engine.register_fn(
+            "flyover",
+            move |x: FLOAT, y: FLOAT, z: FLOAT, subtitle: &str| {
+                queue.lock().unwrap().push(ScriptCommand::Flyover {
+                    center: Vec3::new(x as f32, y as f32, z as f32),
+                    subtitle: subtitle.to_string(),
+                });
+            },
+        );
+
+        let triggers = scenario
+            .triggers
+            .into_iter()
+            .map(|trigger| {
+                let ast = engine.compile(&trigger.script)?;
+                Ok((trigger, ast))
+            })
+            .collect::<anyhow::Result<Vec<_>>>()?;
+
+        Ok(Self {
+            engine,
+            fired: vec![false; triggers.len()],
+            triggers,
+            queued,
+            destroyed: Vec::new(),
+        })
+    }
+
+    pub fn unit_destroyed(&mut self, model: ModelId, enemy: bool) {
+        if !self.triggers.is_empty() {
+            self.destroyed.push((model, enemy));
+        }
+    }
+
+    pub fn take_destroyed(&mut self) -> Vec<(ModelId, bool)> {
+        std::mem::take(&mut self.destroyed)
+    }
+
+    // The indices of the triggers that have fired and won't fire again, for saving.
+    pub fn fired(&self) -> Vec<usize> {
+        (0..self.fired.len()).filter(|&i| self.fired[i]).collect()
+    }
+
+    pub fn restore_fired(&mut self, fired: &[usize]) {
+        self.destroyed.clear();
+
+        for (i, has_fired) in self.fired.iter_mut().enumerate() {
+            *has_fired = fired.contains(&i);
+        }
+    }
+
+    // Runs the scripts of the triggers whose conditions are met and returns what they asked for.
+    // Errors in scripts are passed on as messages so that whoever's writing the scenario sees them.
+    pub fn fire(
+        &mut self,
+        mut condition_met: impl FnMut(&TriggerCondition) -> bool,
+    ) -> Vec<ScriptCommand> {
+        for (i, (trigger, ast)) in self.triggers.iter().enumerate() {
+            if self.fired[i] || !condition_met(&trigger.on) {
+                continue;
+            }
+
+            self.fired[i] = trigger.on.fires_once();
+
+            if let Err(error) = self.engine.run_ast(ast) {
+                self.queued
+                    .lock()
+                    .unwrap()
+                    .push(ScriptCommand::Message(format!(
+                        "Error in the script for trigger {}: {}",
+                        i, error
+                    )));
+            }
+        }
+
+        std::mem::take(&mut *self.queued.lock().unwrap())
+    }
+}
+
+#[test]
+fn test_script_host() {
+    let scenario: Scenario = ron::de::from_str(
+        r#"(
+            triggers: [
+                (on: Timer(10.0), script: "grant_minerals(50.0); message(\"Reinforcements\");"),
+                (
+                    on: UnitDestroyed(model: Carrier, enemy: true),
+                    script: "spawn_wave(\"Fighter\", 3, 0.0, 0.0, 0.0);",
+                ),
+                (on: Timer(20.0), script: "spawn_wave(\"Dreadnought\", 1, 0.0, 0.0, 0.0);"),
+            ],
+        )"#,
+    )
+    .unwrap();
+
+    let mut host = ScriptHost::new(scenario).unwrap();
+
+    let timer_reached = |condition: &TriggerCondition| match condition {
+        TriggerCondition::Timer(time) => *time <= 10.0,
+        _ => false,
+    };
+
+    assert_eq!(
+        host.fire(timer_reached),
+        vec![
+            ScriptCommand::GrantMinerals(50.0),
+            ScriptCommand::Message("Reinforcements".to_string())
+        ]
+    );
+    // Timers only fire once.
+    assert!(host.fire(timer_reached).is_empty());
+    assert_eq!(host.fired(), vec![0]);
+
+    // Unit destroyed triggers fire every time.
+    for _ in 0..2 {
+        assert_eq!(
+            host.fire(|condition| matches!(condition, TriggerCondition::UnitDestroyed { .. })),
+            vec![ScriptCommand::SpawnWave {
+                ship: ShipType::Fighter,
+                count: 3,
+                position: Vec3::zero(),
+            }]
+        );
+    }
+
+    let errors = host.fire(|condition| matches!(condition, TriggerCondition::Timer(_)));
+    assert!(matches!(&errors[..], [ScriptCommand::Message(_)]));
+}
+
+#[test]
+fn test_scenario_lighting_preset() {
+    let scenario: Scenario =
+        ron::de::from_str("(triggers: [], lighting_preset: Some(Eclipse))").unwrap();
+    assert_eq!(scenario.lighting_preset, Some(LightingPreset::Eclipse));
+
+    let scenario: Scenario = ron::de::from_str("(triggers: [])").unwrap();
+    assert_eq!(scenario.lighting_preset, None);
+}
+
+#[test]
+fn test_lighting_scripts() {
+    let scenario: Scenario = ron::de::from_str(
+        r#"(
+            triggers: [
+                (on: Timer(0.0), script: "set_sun(0.0, 2.0, 0.0, 1.0, 0.2, 0.1, 30.0); set_ambient(0.1, 0.0, 0.0, 30.0);"),
+                (on: Timer(0.0), script: "set_sun(0.0, 0.0, 0.0, 1.0, 1.0, 1.0, 0.0);"),
+            ],
+        )"#,
+    )
+    .unwrap();
+
+    let commands = ScriptHost::new(scenario).unwrap().fire(|_| true);
+
+    assert_eq!(
+        &commands[..2],
+        &[
+            ScriptCommand::SetSun {
+                direction: Vec3::unit_y(),
+                colour: Vec3::new(1.0, 0.2, 0.1),
+                duration: 30.0,
+            },
+            ScriptCommand::SetAmbient {
+                colour: Vec3::new(0.1, 0.0, 0.0),
+                duration: 30.0,
+            },
+        ]
+    );
+    // A sun without a direction is an error.
+    assert!(matches!(&commands[2..], [ScriptCommand::Message(_)]));
+}
+
+#[test]
+fn test_flyover_script() {
+    let scenario: Scenario = ron::de::from_str(
+        r#"(
+            triggers: [
+                (on: UnitDestroyed(model: Carrier, enemy: true), script: "flyover(10.0, 0.0, 20.0, \"Victory!\");"),
+            ],
+        )"#,
+    )
+    .unwrap();
+
+    assert_eq!(
+        ScriptHost::new(scenario).unwrap().fire(|_| true),
+        vec![ScriptCommand::Flyover {
+            center: Vec3::new(10.0, 0.0, 20.0),
+            subtitle: "Victory!".to_string(),
+        }]
+    );
+}
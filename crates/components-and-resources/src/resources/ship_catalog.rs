@@ -0,0 +1,56 @@
+use crate::components::{BuildCost, ShipType};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ShipStats {
+    pub build_time: f32,
+    pub minerals: f32,
+    pub gas: f32,
+}
+
+impl ShipStats {
+    pub fn built_in(ship_type: ShipType) -> Self {
+        let cost = ship_type.build_cost();
+
+        Self {
+            build_time: ship_type.build_time(),
+            minerals: cost.minerals,
+            gas: cost.gas,
+        }
+    }
+}
+
+// How long each type of ship takes to build and what it costs. Starts out with the built-in
+// values, which mods can replace. These affect the simulation, so both players of a networked
+// game need the same mods.
+#[derive(Clone, Default)]
+pub struct ShipCatalog {
+    overrides: HashMap<ShipType, ShipStats>,
+}
+
+impl ShipCatalog {
+    pub fn set(&mut self, ship_type: ShipType, stats: ShipStats) {
+        self.overrides.insert(ship_type, stats);
+    }
+
+    pub fn stats(&self, ship_type: ShipType) -> ShipStats {
+        self.overrides
+            .get(&ship_type)
+            .copied()
+            .unwrap_or_else(|| ShipStats::built_in(ship_type))
+    }
+
+    pub fn build_time(&self, ship_type: ShipType) -> f32 {
+        self.stats(ship_type).build_time
+    }
+
+    pub fn build_cost(&self, ship_type: ShipType) -> BuildCost {
+        let stats = self.stats(ship_type);
+
+        BuildCost {
+            minerals: stats.minerals,
+            gas: stats.gas,
+        }
+    }
+}
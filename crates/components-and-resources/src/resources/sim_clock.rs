@@ -0,0 +1,180 @@
+use serde::{Deserialize, Serialize};
+
+// The simulation's own clock. It steps forward by a fixed amount each tick that the simulation
+// runs, so anything timed against it takes the same number of ticks regardless of frame rate,
+// and stays in step between players.
+pub struct SimClock {
+    pub time: f32,
+    pub delta: f32,
+    speed: GameSpeed,
+}
+
+impl SimClock {
+    pub const TICK: f32 = 1.0 / 60.0;
+
+    pub fn new(time: f32) -> Self {
+        Self {
+            time,
+            delta: Self::TICK,
+            speed: GameSpeed::Normal,
+        }
+    }
+
+    pub fn speed(&self) -> GameSpeed {
+        self.speed
+    }
+
+    pub fn set_speed(&mut self, speed: GameSpeed) {
+        self.speed = speed;
+        self.delta = Self::TICK * speed.multiplier();
+    }
+
+    pub fn advance(&mut self) {
+        self.time += self.delta;
+    }
+
+    pub fn timer(&self, duration: f32) -> Timer {
+        Timer {
+            ends_at: self.time + duration,
+        }
+    }
+}
+
+// There's still one tick per frame at every speed, each tick just covers more or less time.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub enum GameSpeed {
+    Half,
+    Normal,
+    Double,
+    Quadruple,
+}
+
+impl Default for GameSpeed {
+    fn default() -> Self {
+        Self::Normal
+    }
+}
+
+impl GameSpeed {
+    pub fn multiplier(self) -> f32 {
+        match self {
+            Self::Half => 0.5,
+            Self::Normal => 1.0,
+            Self::Double => 2.0,
+            Self::Quadruple => 4.0,
+        }
+    }
+
+    pub fn to_str(self) -> &'static str {
+        match self {
+            Self::Half => "0.5x",
+            Self::Normal => "1x",
+            Self::Double => "2x",
+            Self::Quadruple => "4x",
+        }
+    }
+
+    pub fn faster(self) -> Self {
+        match self {
+            Self::Half => Self::Normal,
+            Self::Normal => Self::Double,
+            Self::Double | Self::Quadruple => Self::Quadruple,
+        }
+    }
+
+    pub fn slower(self) -> Self {
+        match self {
+            Self::Half | Self::Normal => Self::Half,
+            Self::Double => Self::Normal,
+            Self::Quadruple => Self::Double,
+        }
+    }
+}
+
+// Finishes at a set point in simulation time. Saved as that point.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct Timer {
+    pub ends_at: f32,
+}
+
+impl Timer {
+    pub fn finished(&self, clock: &SimClock) -> bool {
+        clock.time >= self.ends_at
+    }
+
+    pub fn remaining(&self, clock: &SimClock) -> f32 {
+        (self.ends_at - clock.time).max(0.0)
+    }
+}
+
+// Counts down by the simulation's delta each tick it's ticked. Saved as the time remaining.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct Cooldown {
+    remaining: f32,
+}
+
+impl Cooldown {
+    pub fn new(remaining: f32) -> Self {
+        Self { remaining }
+    }
+
+    pub fn tick(&mut self, clock: &SimClock) {
+        self.remaining = (self.remaining - clock.delta).max(0.0);
+    }
+
+    pub fn is_ready(&self) -> bool {
+        self.remaining == 0.0
+    }
+
+    pub fn start(&mut self, duration: f32) {
+        self.remaining = duration;
+    }
+
+    pub fn remaining(&self) -> f32 {
+        self.remaining
+    }
+}
+
+#[test]
+fn test_timers_and_cooldowns() {
+    let mut clock = SimClock::new(0.0);
+    let timer = clock.timer(0.5);
+    let mut cooldown = Cooldown::default();
+    cooldown.start(0.5);
+
+    // Both take the same number of ticks, whatever the tick length.
+    for _ in 0..29 {
+        clock.advance();
+        cooldown.tick(&clock);
+    }
+
+    assert!(!timer.finished(&clock));
+    assert!(!cooldown.is_ready());
+
+    clock.advance();
+    cooldown.tick(&clock);
+
+    assert!(timer.finished(&clock));
+    assert!(cooldown.is_ready());
+    assert_eq!(timer.remaining(&clock), 0.0);
+}
+
+#[test]
+fn test_game_speed() {
+    let mut clock = SimClock::new(0.0);
+    let timer = clock.timer(1.0);
+    clock.set_speed(GameSpeed::Normal.faster().faster());
+
+    assert_eq!(clock.speed(), GameSpeed::Quadruple);
+    assert_eq!(clock.speed().faster(), GameSpeed::Quadruple);
+    assert_eq!(GameSpeed::Half.slower(), GameSpeed::Half);
+
+    // A second of simulated time goes by four times as quickly.
+    for _ in 0..15 {
+        clock.advance();
+    }
+
+    assert!(timer.finished(&clock));
+}
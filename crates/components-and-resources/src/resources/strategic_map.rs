@@ -0,0 +1,115 @@
+use crate::map_generation::ENEMY_OFFSET;
+use ultraviolet::{Vec2, Vec3};
+
+// A top-down schematic of the whole sector that covers the screen while it's open. Toggled with a
+// key, with orders given by clicking on it.
+#[derive(Default)]
+pub struct StrategicMap {
+    pub open: bool,
+}
+
+impl StrategicMap {
+    // The map is a square centered between the two fleets, looking down the y axis.
+    const CENTER_X: f32 = ENEMY_OFFSET / 2.0;
+    const HALF_WIDTH: f32 = 800.0;
+    // How much of the screen's height the map takes up, leaving room for the heading.
+    const SCALE: f32 = 0.9;
+    // Control is worked out per cell of a grid laid over the map.
+    pub const CONTROL_CELLS: usize = 16;
+    // How close to an icon a click has to be to pick it, in pixels.
+    pub const CLICK_RADIUS: f32 = 8.0;
+
+    // Converts a point in the world to wgpu coordinates, keeping the map square whatever the
+    // aspect ratio of the window. Height is ignored.
+    pub fn to_wgpu(point: Vec3, aspect_ratio: f32) -> Vec2 {
+        let offset = Vec2::new(point.x - Self::CENTER_X, point.z) / Self::HALF_WIDTH * Self::SCALE;
+        Vec2::new(offset.x / aspect_ratio, -offset.y)
+    }
+
+    pub fn to_world(point: Vec2, aspect_ratio: f32, height: f32) -> Vec3 {
+        let offset = Vec2::new(point.x * aspect_ratio, -point.y) / Self::SCALE * Self::HALF_WIDTH;
+        Vec3::new(offset.x + Self::CENTER_X, height, offset.y)
+    }
+
+    pub fn contains(point: Vec2, aspect_ratio: f32) -> bool {
+        (point.x * aspect_ratio).abs() <= Self::SCALE && point.y.abs() <= Self::SCALE
+    }
+
+    // The bottom-left and top-right corners of the map, in wgpu coordinates.
+    pub fn bounds(aspect_ratio: f32) -> (Vec2, Vec2) {
+        let corner = Vec2::new(Self::SCALE / aspect_ratio, Self::SCALE);
+        (-corner, corner)
+    }
+
+    fn control_cell(point: Vec3) -> Option<usize> {
+        let cell_width = Self::HALF_WIDTH * 2.0 / Self::CONTROL_CELLS as f32;
+        let x = (point.x - Self::CENTER_X + Self::HALF_WIDTH) / cell_width;
+        let z = (point.z + Self::HALF_WIDTH) / cell_width;
+
+        let in_range = |value: f32| value >= 0.0 && value < Self::CONTROL_CELLS as f32;
+
+        if in_range(x) && in_range(z) {
+            Some(z as usize * Self::CONTROL_CELLS + x as usize)
+        } else {
+            None
+        }
+    }
+
+    // For each cell of the grid, the number of friendly ships in it minus the number of enemies.
+    pub fn control_zones(
+        friendly: impl Iterator<Item = Vec3>,
+        enemy: impl Iterator<Item = Vec3>,
+    ) -> Vec<i32> {
+        let mut zones = vec![0; Self::CONTROL_CELLS * Self::CONTROL_CELLS];
+
+        for (point, weight) in friendly.map(|p| (p, 1)).chain(enemy.map(|p| (p, -1))) {
+            if let Some(cell) = Self::control_cell(point) {
+                zones[cell] += weight;
+            }
+        }
+
+        zones
+    }
+
+    // The corners of a cell of the grid, in the same order as `bounds`.
+    pub fn cell_bounds(cell: usize, aspect_ratio: f32) -> (Vec2, Vec2) {
+        let (min, max) = Self::bounds(aspect_ratio);
+        let size = (max - min) / Self::CONTROL_CELLS as f32;
+
+        let x = (cell % Self::CONTROL_CELLS) as f32;
+        // Rows go down the screen as z increases.
+        let y = (Self::CONTROL_CELLS - 1 - cell / Self::CONTROL_CELLS) as f32;
+
+        let start = min + Vec2::new(x, y) * size;
+        (start, start + size)
+    }
+}
+
+#[test]
+fn test_strategic_map_coordinates() {
+    let aspect_ratio = 16.0 / 9.0;
+    let point = Vec3::new(100.0, 20.0, -300.0);
+
+    let on_map = StrategicMap::to_wgpu(point, aspect_ratio);
+    assert!(StrategicMap::contains(on_map, aspect_ratio));
+    assert!((StrategicMap::to_world(on_map, aspect_ratio, 20.0) - point).mag() < 0.01);
+
+    assert!(!StrategicMap::contains(Vec2::new(0.0, 1.0), aspect_ratio));
+
+    let zones = StrategicMap::control_zones(
+        vec![point, point, Vec3::new(ENEMY_OFFSET, 0.0, 0.0)].into_iter(),
+        vec![
+            point,
+            Vec3::new(ENEMY_OFFSET, 0.0, 0.0),
+            Vec3::new(5000.0, 0.0, 0.0),
+        ]
+        .into_iter(),
+    );
+    let cell = StrategicMap::control_cell(point).unwrap();
+    assert_eq!(zones[cell], 1);
+    assert_eq!(zones.iter().sum::<i32>(), 1);
+
+    // The point's cell on screen surrounds the point.
+    let (min, max) = StrategicMap::cell_bounds(cell, aspect_ratio);
+    assert!(on_map.x > min.x && on_map.x < max.x && on_map.y > min.y && on_map.y < max.y);
+}
@@ -0,0 +1,677 @@
+use crate::components::*;
+use crate::resources::{
+    AsteroidRespawns, AutosaveTimer, ControlGroups, CrewTransferPanel, GameSpeed, GlobalCredits,
+    GlobalGas, GlobalMinerals, GlobalResearch, GlobalRulesOfEngagement, ObjectiveScore, PerSide,
+    PickupSpawner, PictureInPicture, Projectile, ScriptHost, ShipUnderCursor, SimClock, SmallRng,
+    TechTree, TopLevelAccelerationStructure,
+};
+use bevy_ecs::component::Component;
+use bevy_ecs::entity::EntityMap;
+use bevy_ecs::prelude::{Entity, World};
+use rand::SeedableRng;
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use std::path::Path;
+
+// Bump this whenever a saved component or resource changes shape in a way that `serde(default)`
+// can't cover.
+const SAVE_VERSION: u32 = 3;
+
+// Read on its own first, as saves from other versions may not parse as a `SaveFile` at all.
+#[derive(Deserialize)]
+struct SaveFileVersion {
+    version: u32,
+}
+
+fn check_save_version(string: &str) -> anyhow::Result<()> {
+    let SaveFileVersion { version } = ron::de::from_str(string)?;
+
+    match version.cmp(&SAVE_VERSION) {
+        std::cmp::Ordering::Less => Err(anyhow::anyhow!(
+            "The save is from an older version of the game (save version {}, this version reads {}) and can't be loaded",
+            version,
+            SAVE_VERSION
+        )),
+        std::cmp::Ordering::Greater => Err(anyhow::anyhow!(
+            "The save is from a newer version of the game (save version {}, this version reads {}). Update the game to load it",
+            version,
+            SAVE_VERSION
+        )),
+        std::cmp::Ordering::Equal => Ok(()),
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct SaveFile {
+    version: u32,
+    total_time: f32,
+    global_minerals: f32,
+    #[serde(default)]
+    global_gas: f32,
+    #[serde(default)]
+    global_credits: f32,
+    // The `global_` fields are the friendly side's. Older saves don't have the enemy's.
+    #[serde(default)]
+    enemy_minerals: f32,
+    #[serde(default)]
+    enemy_gas: f32,
+    #[serde(default)]
+    enemy_credits: f32,
+    global_research: f32,
+    #[serde(default)]
+    enemy_research: f32,
+    #[serde(default)]
+    rng: Option<SmallRng>,
+    // Older saves only have a seed to start a new rng from.
+    #[serde(default, skip_serializing)]
+    rng_seed: u64,
+    #[serde(default)]
+    rules_of_engagement: RulesOfEngagement,
+    #[serde(default)]
+    enemy_rules_of_engagement: RulesOfEngagement,
+    #[serde(default)]
+    objective_score: ObjectiveScore,
+    #[serde(default)]
+    tech_tree: TechTree,
+    #[serde(default)]
+    enemy_tech_tree: TechTree,
+    #[serde(default)]
+    asteroid_respawns: AsteroidRespawns,
+    #[serde(default)]
+    pickup_spawner: PickupSpawner,
+    #[serde(default)]
+    game_speed: GameSpeed,
+    // Which of the scenario's triggers have already fired.
+    #[serde(default)]
+    fired_triggers: Vec<usize>,
+    entities: Vec<SavedEntity>,
+}
+
+// A component in its serialized form, under the name it was registered with.
+type SavedComponent = (String, String);
+
+#[derive(Serialize, Deserialize)]
+struct SavedEntity {
+    entity: Entity,
+    components: Vec<SavedComponent>,
+}
+
+// Components from a save that nothing is registered under the name of, such as those from a mod
+// that isn't loaded. They're kept as they are and written back out on the next save. Any entities
+// they refer to aren't remapped.
+#[derive(Clone, Default)]
+pub struct UnknownComponents(pub Vec<SavedComponent>);
+
+type Snapshot = fn(&World, Entity) -> anyhow::Result<Option<String>>;
+type Restore = fn(&mut World, Entity, &str) -> anyhow::Result<()>;
+type Remap = fn(&mut World, Entity, &EntityMap);
+
+#[derive(Clone)]
+struct Registration {
+    name: &'static str,
+    snapshot: Snapshot,
+    restore: Restore,
+    remap: Option<Remap>,
+}
+
+fn snapshot_component<T: Component + Serialize>(
+    world: &World,
+    entity: Entity,
+) -> anyhow::Result<Option<String>> {
+    Ok(match world.get::<T>(entity) {
+        Some(component) => Some(ron::ser::to_string(component)?),
+        None => None,
+    })
+}
+
+fn restore_component<T: Component + DeserializeOwned>(
+    world: &mut World,
+    entity: Entity,
+    data: &str,
+) -> anyhow::Result<()> {
+    let component: T = ron::de::from_str(data)?;
+    world.entity_mut(entity).insert(component);
+    Ok(())
+}
+
+// Every component that gets saved, under a name that has to stay the same between versions as
+// it's what identifies the component in save files. Mods register their own components on the
+// `ComponentRegistry` resource before anything is saved or loaded.
+#[derive(Clone)]
+pub struct ComponentRegistry {
+    registrations: Vec<Registration>,
+}
+
+impl ComponentRegistry {
+    pub fn register<T: Component + Serialize + DeserializeOwned>(&mut self, name: &'static str) {
+        self.add(Registration {
+            name,
+            snapshot: snapshot_component::<T>,
+            restore: restore_component::<T>,
+            remap: None,
+        });
+    }
+
+    // For components that refer to other entities, which need to be remapped after loading.
+    pub fn register_with_entities<T: Component + Serialize + DeserializeOwned + RemapEntities>(
+        &mut self,
+        name: &'static str,
+    ) {
+        self.add(Registration {
+            name,
+            snapshot: snapshot_component::<T>,
+            restore: restore_component::<T>,
+            remap: Some(remap_component_entities::<T>),
+        });
+    }
+
+    fn add(&mut self, registration: Registration) {
+        assert!(
+            !self.contains(registration.name),
+            "'{}' is already registered",
+            registration.name
+        );
+        self.registrations.push(registration);
+    }
+
+    pub fn contains(&self, name: &str) -> bool {
+        self.registrations
+            .iter()
+            .any(|registration| registration.name == name)
+    }
+
+    // The registered components that `entity` has, in their serialized form.
+    pub(crate) fn serialized_components<'a>(
+        &'a self,
+        world: &'a World,
+        entity: Entity,
+    ) -> impl Iterator<Item = (&'static str, String)> + 'a {
+        self.registrations.iter().filter_map(move |registration| {
+            match (registration.snapshot)(world, entity) {
+                Ok(Some(data)) => Some((registration.name, data)),
+                _ => None,
+            }
+        })
+    }
+
+    fn snapshot(&self, world: &World, entity: Entity) -> anyhow::Result<SavedEntity> {
+        let mut components = Vec::new();
+
+        for registration in &self.registrations {
+            if let Some(data) = (registration.snapshot)(world, entity)? {
+                components.push((registration.name.to_string(), data));
+            }
+        }
+
+        if let Some(unknown) = world.get::<UnknownComponents>(entity) {
+            components.extend(unknown.0.iter().cloned());
+        }
+
+        Ok(SavedEntity { entity, components })
+    }
+
+    fn restore(
+        &self,
+        world: &mut World,
+        entity: Entity,
+        components: Vec<SavedComponent>,
+    ) -> anyhow::Result<()> {
+        let mut unknown = Vec::new();
+
+        for (name, data) in components {
+            match self.registrations.iter().find(|r| r.name == name) {
+                Some(registration) => (registration.restore)(world, entity, &data)
+                    .map_err(|error| anyhow::anyhow!("Loading '{}' failed: {}", name, error))?,
+                None => unknown.push((name, data)),
+            }
+        }
+
+        if !unknown.is_empty() {
+            world.entity_mut(entity).insert(UnknownComponents(unknown));
+        }
+
+        Ok(())
+    }
+
+    fn remap(&self, world: &mut World, entity: Entity, entity_map: &EntityMap) {
+        for registration in &self.registrations {
+            if let Some(remap) = registration.remap {
+                remap(world, entity, entity_map);
+            }
+        }
+    }
+}
+
+macro_rules! register_components {
+    ($registry:ident, $($name:ident: $component:ty,)*) => {
+        $($registry.register::<$component>(stringify!($name));)*
+    };
+}
+
+// `TlasIndex` isn't saved as the TLAS is rebuilt after loading, `ImpactEffect`s are
+// purely cosmetic, `FlightRecorder`s are only for debugging and `Ours` and `Theirs` depend on
+// who's playing.
+impl Default for ComponentRegistry {
+    fn default() -> Self {
+        let mut registry = Self {
+            registrations: Vec::new(),
+        };
+
+        register_components! {
+            registry,
+            position: Position,
+            rotation: Rotation,
+            rotation_matrix: RotationMatrix,
+            selected: Selected,
+            model_id: ModelId,
+            ship_type: ShipType,
+            scale: Scale,
+            expands: Expands,
+            alive_until: AliveUntil,
+            world_space_bounding_box: WorldSpaceBoundingBox,
+            max_speed: MaxSpeed,
+            spin: Spin,
+            orbiting: Orbiting,
+            drifting: Drifting,
+            camera_following: CameraFollowing,
+            friendly: Friendly,
+            enemy: Enemy,
+            evasive_maneuvers: EvasiveManeuvers,
+            jinking: Jinking,
+            velocity: Velocity,
+            staging_persuit_force: StagingPersuitForce,
+            staging_evasion_force: StagingEvasionForce,
+            staging_avoidance_force: StagingAvoidanceForce,
+            weapons: Weapons,
+            agro_range: AgroRange,
+            can_attack: CanAttack,
+            can_repair: CanRepair,
+            can_be_carried: CanBeCarried,
+            weapon_arcs: WeaponArcs,
+            missile_launcher: MissileLauncher,
+            heat: Heat,
+            health: Health,
+            shield: Shield,
+            armor: Armor,
+            damage_type: DamageType,
+            projectile_damage: ProjectileDamage,
+            veterancy: Veterancy,
+            last_hit: LastHit,
+            selectable: Selectable,
+            scuttling: Scuttling,
+            rules_of_engagement_override: RulesOfEngagementOverride,
+            player_tag: PlayerTag,
+            hold_position: HoldPosition,
+            carrier_doctrine: CarrierDoctrine,
+            autonomy: Autonomy,
+            retreat_policy: RetreatPolicy,
+            targeting_stance: TargetingStance,
+            stance: Stance,
+            throttle: Throttle,
+            leash: Leash,
+            can_mine: CanMine,
+            rally_point: RallyPoint,
+            can_be_mined: CanBeMined,
+            mining_claims: MiningClaims,
+            can_harvest: CanHarvest,
+            stored_gas: StoredGas,
+            can_be_harvested: CanBeHarvested,
+            can_trade: CanTrade,
+            cargo: Cargo,
+            trading_station: TradingStation,
+            can_construct: CanConstruct,
+            under_construction: UnderConstruction,
+            refinery: Refinery,
+            wreck: Wreck,
+            comet: Comet,
+            stored_minerals: StoredMinerals,
+            unloading: Unloading,
+            launched: Launched,
+            debug_watch: DebugWatch,
+            proximity_mine: ProximityMine,
+            mine_layer: MineLayer,
+            minefield_drop: MinefieldDrop,
+            detector: Detector,
+            hidden: Hidden,
+            cloaked: Cloaked,
+            cloak_energy: CloakEnergy,
+            obstacle: Obstacle,
+            sensor_relay: SensorRelay,
+            pickup: Pickup,
+            speed_boost: SpeedBoost,
+            damage_boost: DamageBoost,
+            carryable: Carryable,
+            relic: Relic,
+            carrier_full: CarrierFull,
+            research_multiplier: ResearchMultiplier,
+            build_queue: BuildQueue,
+            engineer: Engineer,
+            researcher: Researcher,
+            gunner: Gunner,
+            pilot: Pilot,
+            crew_modifiers: CrewModifiers,
+            projectile: Projectile,
+        }
+
+        registry.register_with_entities::<Evading>("evading");
+        registry.register_with_entities::<CommandQueue>("command_queue");
+        registry.register_with_entities::<Carrying>("carrying");
+        registry.register_with_entities::<BeamWeapon>("beam_weapon");
+        registry.register_with_entities::<Missile>("missile");
+        registry.register_with_entities::<FiredBy>("fired_by");
+        registry.register_with_entities::<FormationSlot>("formation_slot");
+        registry.register_with_entities::<Escorting>("escorting");
+        registry.register_with_entities::<Drone>("drone");
+        registry.register_with_entities::<OnBoard>("on_board");
+        registry.register_with_entities::<DropOff>("drop_off");
+        registry.register_with_entities::<MiningSlot>("mining_slot");
+        registry.register_with_entities::<HoldingItem>("holding_item");
+
+        registry
+    }
+}
+
+pub fn save_world(world: &mut World, path: &Path) -> anyhow::Result<()> {
+    std::fs::write(path, save_world_to_string(world)?)?;
+    Ok(())
+}
+
+pub fn load_world(world: &mut World, path: &Path) -> anyhow::Result<()> {
+    load_world_from_str(world, &std::fs::read_to_string(path)?)
+}
+
+// Falls back to the built-in components for worlds that weren't given a registry.
+pub(crate) fn component_registry(world: &World) -> ComponentRegistry {
+    world
+        .get_resource::<ComponentRegistry>()
+        .cloned()
+        .unwrap_or_default()
+}
+
+pub fn save_world_to_string(world: &mut World) -> anyhow::Result<String> {
+    let registry = component_registry(world);
+
+    let entities = world
+        .query::<Entity>()
+        .iter(world)
+        .collect::<Vec<_>>()
+        .into_iter()
+        .map(|entity| registry.snapshot(world, entity))
+        .collect::<anyhow::Result<_>>()?;
+
+    let minerals = world.get_resource::<PerSide<GlobalMinerals>>().unwrap();
+    let gas = world.get_resource::<PerSide<GlobalGas>>().unwrap();
+    let credits = world.get_resource::<PerSide<GlobalCredits>>().unwrap();
+    let research = world.get_resource::<PerSide<GlobalResearch>>().unwrap();
+    let rules = world
+        .get_resource::<PerSide<GlobalRulesOfEngagement>>()
+        .unwrap();
+    let tech_tree = world.get_resource::<PerSide<TechTree>>().unwrap();
+
+    let save_file = SaveFile {
+        version: SAVE_VERSION,
+        total_time: world.get_resource::<SimClock>().unwrap().time,
+        game_speed: world.get_resource::<SimClock>().unwrap().speed(),
+        global_minerals: minerals.friendly.0,
+        global_gas: gas.friendly.0,
+        global_credits: credits.friendly.0,
+        enemy_minerals: minerals.enemy.0,
+        enemy_gas: gas.enemy.0,
+        enemy_credits: credits.enemy.0,
+        global_research: research.friendly.0,
+        enemy_research: research.enemy.0,
+        rng: Some(world.get_resource::<SmallRng>().unwrap().clone()),
+        rng_seed: 0,
+        rules_of_engagement: rules.friendly.0,
+        enemy_rules_of_engagement: rules.enemy.0,
+        objective_score: *world.get_resource::<ObjectiveScore>().unwrap(),
+        tech_tree: tech_tree.friendly.clone(),
+        enemy_tech_tree: tech_tree.enemy.clone(),
+        asteroid_respawns: *world.get_resource::<AsteroidRespawns>().unwrap(),
+        pickup_spawner: *world.get_resource::<PickupSpawner>().unwrap(),
+        fired_triggers: world.get_resource::<ScriptHost>().unwrap().fired(),
+        entities,
+    };
+
+    Ok(ron::ser::to_string(&save_file)?)
+}
+
+pub fn load_world_from_str(world: &mut World, string: &str) -> anyhow::Result<()> {
+    check_save_version(string)?;
+    let save_file: SaveFile = ron::de::from_str(string)?;
+
+    let existing = world.query::<Entity>().iter(world).collect::<Vec<_>>();
+
+    for entity in existing {
+        world.despawn(entity);
+    }
+
+    let mut entity_map = EntityMap::default();
+
+    for saved in &save_file.entities {
+        entity_map.insert(saved.entity, world.spawn().id());
+    }
+
+    let registry = component_registry(world);
+
+    for saved in save_file.entities {
+        let entity = entity_map.get(saved.entity)?;
+        registry.restore(world, entity, saved.components)?;
+        registry.remap(world, entity, &entity_map);
+
+        // Asteroids from older saves don't keep track of who's mining them yet.
+        if world.get::<CanBeMined>(entity).is_some() && world.get::<MiningClaims>(entity).is_none()
+        {
+            world.entity_mut(entity).insert(MiningClaims::default());
+        }
+    }
+
+    let mut clock = SimClock::new(save_file.total_time);
+    clock.set_speed(save_file.game_speed);
+    world.insert_resource(clock);
+    world.insert_resource(PerSide {
+        friendly: GlobalMinerals(save_file.global_minerals),
+        enemy: GlobalMinerals(save_file.enemy_minerals),
+    });
+    world.insert_resource(PerSide {
+        friendly: GlobalGas(save_file.global_gas),
+        enemy: GlobalGas(save_file.enemy_gas),
+    });
+    world.insert_resource(PerSide {
+        friendly: GlobalCredits(save_file.global_credits),
+        enemy: GlobalCredits(save_file.enemy_credits),
+    });
+    world.insert_resource(PerSide {
+        friendly: GlobalResearch(save_file.global_research),
+        enemy: GlobalResearch(save_file.enemy_research),
+    });
+    let rng_seed = save_file.rng_seed;
+    world.insert_resource(
+        save_file
+            .rng
+            .unwrap_or_else(|| SmallRng::seed_from_u64(rng_seed)),
+    );
+    world.insert_resource(PerSide {
+        friendly: GlobalRulesOfEngagement(save_file.rules_of_engagement),
+        enemy: GlobalRulesOfEngagement(save_file.enemy_rules_of_engagement),
+    });
+    world.insert_resource(save_file.objective_score);
+    world.insert_resource(PerSide {
+        friendly: save_file.tech_tree,
+        enemy: save_file.enemy_tech_tree,
+    });
+    world.insert_resource(save_file.asteroid_respawns);
+    world.insert_resource(save_file.pickup_spawner);
+    world
+        .get_resource_mut::<ScriptHost>()
+        .unwrap()
+        .restore_fired(&save_file.fired_triggers);
+
+    // Reset anything that refers to entities from before the load.
+    world.insert_resource(TopLevelAccelerationStructure::default());
+    world.insert_resource(ShipUnderCursor(None));
+    world.insert_resource(ControlGroups::default());
+    world.insert_resource(CrewTransferPanel::default());
+    world.get_resource_mut::<PictureInPicture>().unwrap().target = None;
+
+    if let Some(mut autosave_timer) = world.get_resource_mut::<AutosaveTimer>() {
+        autosave_timer.reschedule(save_file.total_time);
+    }
+
+    Ok(())
+}
+
+fn remap_component_entities<T: RemapEntities + Send + Sync + 'static>(
+    world: &mut World,
+    entity: Entity,
+    entity_map: &EntityMap,
+) {
+    if let Some(mut component) = world.get_mut::<T>(entity) {
+        component.remap_entities(entity_map);
+    }
+}
+
+// Components can refer to entities that had already been despawned when the game was saved.
+// These are mapped to an id that will never be allocated so that they can't alias a newly
+// spawned entity.
+pub fn remap_entity(entity: &mut Entity, entity_map: &EntityMap) {
+    *entity = entity_map
+        .get(*entity)
+        .unwrap_or_else(|_| Entity::new(u32::MAX));
+}
+
+pub trait RemapEntities {
+    fn remap_entities(&mut self, entity_map: &EntityMap);
+}
+
+impl RemapEntities for Evading {
+    fn remap_entities(&mut self, entity_map: &EntityMap) {
+        remap_entity(&mut self.0, entity_map);
+    }
+}
+
+impl RemapEntities for CommandQueue {
+    fn remap_entities(&mut self, entity_map: &EntityMap) {
+        for command in self.0.iter_mut() {
+            if let Command::Interact { target, .. } = command {
+                remap_entity(target, entity_map);
+            }
+        }
+    }
+}
+
+impl RemapEntities for Carrying {
+    fn remap_entities(&mut self, entity_map: &EntityMap) {
+        for entity in self.iter_mut() {
+            remap_entity(entity, entity_map);
+        }
+    }
+}
+
+impl RemapEntities for FormationSlot {
+    fn remap_entities(&mut self, entity_map: &EntityMap) {
+        remap_entity(&mut self.leader, entity_map);
+    }
+}
+
+impl RemapEntities for Escorting {
+    fn remap_entities(&mut self, entity_map: &EntityMap) {
+        remap_entity(&mut self.carrier, entity_map);
+    }
+}
+
+impl RemapEntities for Drone {
+    fn remap_entities(&mut self, entity_map: &EntityMap) {
+        remap_entity(&mut self.mothership, entity_map);
+    }
+}
+
+impl RemapEntities for DropOff {
+    fn remap_entities(&mut self, entity_map: &EntityMap) {
+        remap_entity(&mut self.0, entity_map);
+    }
+}
+
+impl RemapEntities for MiningSlot {
+    fn remap_entities(&mut self, entity_map: &EntityMap) {
+        remap_entity(&mut self.asteroid, entity_map);
+    }
+}
+
+impl RemapEntities for HoldingItem {
+    fn remap_entities(&mut self, entity_map: &EntityMap) {
+        remap_entity(&mut self.0, entity_map);
+    }
+}
+
+impl RemapEntities for Missile {
+    fn remap_entities(&mut self, entity_map: &EntityMap) {
+        remap_entity(&mut self.target, entity_map);
+    }
+}
+
+impl RemapEntities for FiredBy {
+    fn remap_entities(&mut self, entity_map: &EntityMap) {
+        remap_entity(&mut self.0, entity_map);
+    }
+}
+
+impl RemapEntities for OnBoard {
+    fn remap_entities(&mut self, entity_map: &EntityMap) {
+        for entity in &mut self.0 {
+            remap_entity(entity, entity_map);
+        }
+    }
+}
+
+impl RemapEntities for BeamWeapon {
+    fn remap_entities(&mut self, entity_map: &EntityMap) {
+        if let Some(target) = &mut self.target {
+            remap_entity(target, entity_map);
+        }
+    }
+}
+
+#[test]
+fn test_unknown_components_are_kept() {
+    let registry = ComponentRegistry::default();
+    assert!(registry.contains("position"));
+
+    let mut world = World::default();
+    let entity = world.spawn().id();
+
+    let components = vec![
+        ("position".to_string(), "((x:1,y:2,z:3))".to_string()),
+        ("from_a_mod".to_string(), "(fuel: 5.0)".to_string()),
+    ];
+    registry
+        .restore(&mut world, entity, components.clone())
+        .unwrap();
+
+    assert_eq!(
+        world.get::<Position>(entity).unwrap().0,
+        ultraviolet::Vec3::new(1.0, 2.0, 3.0)
+    );
+    assert_eq!(
+        registry.snapshot(&world, entity).unwrap().components,
+        components
+    );
+
+    // Components that are registered but don't parse are still an error.
+    let broken = vec![("health".to_string(), "()".to_string())];
+    assert!(registry.restore(&mut world, entity, broken).is_err());
+}
+
+#[test]
+fn test_save_versions() {
+    // Entities were saved as structs before the registry.
+    let old = "(version:2,total_time:0.0,global_minerals:0.0,global_research:0.0,entities:[(entity:0,position:Some(((x:1,y:2,z:3))))])";
+    let error = check_save_version(old).unwrap_err().to_string();
+    assert!(error.contains("older version"), "{}", error);
+
+    let new = format!("(version:{},entities:[])", SAVE_VERSION + 1);
+    let error = check_save_version(&new).unwrap_err().to_string();
+    assert!(error.contains("newer version"), "{}", error);
+
+    let current = format!("(version:{},total_time:0.0,entities:[])", SAVE_VERSION);
+    assert!(check_save_version(&current).is_ok());
+}
@@ -0,0 +1,91 @@
+use std::collections::HashMap;
+use ultraviolet::Vec3;
+
+// Buckets items into a uniform grid so that nearby items can be found without comparing every
+// pair.
+pub struct SpatialHash<T> {
+    cell_size: f32,
+    cells: HashMap<[i32; 3], Vec<(Vec3, T)>>,
+}
+
+impl<T> SpatialHash<T> {
+    pub fn new(cell_size: f32) -> Self {
+        Self {
+            cell_size,
+            cells: HashMap::new(),
+        }
+    }
+
+    fn cell(&self, position: Vec3) -> [i32; 3] {
+        let cell = position / self.cell_size;
+        [
+            cell.x.floor() as i32,
+            cell.y.floor() as i32,
+            cell.z.floor() as i32,
+        ]
+    }
+
+    pub fn insert(&mut self, position: Vec3, item: T) {
+        let cell = self.cell(position);
+        self.cells.entry(cell).or_default().push((position, item));
+    }
+
+    // Groups items in touching cells together. The order of the clusters is arbitrary.
+    pub fn clusters(&self) -> Vec<Vec<&(Vec3, T)>> {
+        let mut visited = std::collections::HashSet::new();
+        let mut clusters = Vec::new();
+
+        for &start in self.cells.keys() {
+            if !visited.insert(start) {
+                continue;
+            }
+
+            let mut cluster = Vec::new();
+            let mut stack = vec![start];
+
+            while let Some(cell) = stack.pop() {
+                cluster.extend(self.cells[&cell].iter());
+
+                for x in -1..=1 {
+                    for y in -1..=1 {
+                        for z in -1..=1 {
+                            let neighbour = [cell[0] + x, cell[1] + y, cell[2] + z];
+
+                            if self.cells.contains_key(&neighbour) && visited.insert(neighbour) {
+                                stack.push(neighbour);
+                            }
+                        }
+                    }
+                }
+            }
+
+            clusters.push(cluster);
+        }
+
+        clusters
+    }
+}
+
+#[test]
+fn test_spatial_hash_clusters() {
+    let mut hash = SpatialHash::new(10.0);
+
+    hash.insert(Vec3::new(0.0, 0.0, 0.0), 0);
+    hash.insert(Vec3::new(15.0, 0.0, 0.0), 1);
+    hash.insert(Vec3::new(25.0, 0.0, 0.0), 2);
+    hash.insert(Vec3::new(100.0, 0.0, 0.0), 3);
+
+    let mut clusters = hash
+        .clusters()
+        .into_iter()
+        .map(|cluster| {
+            let mut items = cluster.iter().map(|&&(_, item)| item).collect::<Vec<_>>();
+            items.sort_unstable();
+            items
+        })
+        .collect::<Vec<_>>();
+
+    clusters.sort();
+
+    assert_eq!(clusters, vec![vec![0, 1, 2], vec![3]]);
+}
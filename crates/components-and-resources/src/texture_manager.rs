@@ -1,6 +1,9 @@
+use wgpu::util::DeviceExt;
+
 #[derive(Default)]
 pub struct TextureManager {
     texture_views: Vec<wgpu::TextureView>,
+    flat_normal_map: Option<u32>,
 }
 
 impl TextureManager {
@@ -10,6 +13,46 @@ impl TextureManager {
         index
     }
 
+    // Shared by all the models that don't come with a normal map.
+    pub fn flat_normal_map(&mut self, device: &wgpu::Device, queue: &wgpu::Queue) -> u32 {
+        if let Some(index) = self.flat_normal_map {
+            return index;
+        }
+
+        let index =
+            self.add_single_pixel_texture(device, queue, "flat normal map", [128, 128, 255, 255]);
+        self.flat_normal_map = Some(index);
+        index
+    }
+
+    pub fn add_single_pixel_texture(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        label: &str,
+        pixel: [u8; 4],
+    ) -> u32 {
+        let texture = device.create_texture_with_data(
+            queue,
+            &wgpu::TextureDescriptor {
+                label: Some(label),
+                size: wgpu::Extent3d {
+                    width: 1,
+                    height: 1,
+                    depth_or_array_layers: 1,
+                },
+                mip_level_count: 1,
+                sample_count: 1,
+                dimension: wgpu::TextureDimension::D2,
+                format: wgpu::TextureFormat::Rgba8Unorm,
+                usage: wgpu::TextureUsages::COPY_DST | wgpu::TextureUsages::TEXTURE_BINDING,
+            },
+            &pixel,
+        );
+
+        self.add(texture.create_view(&wgpu::TextureViewDescriptor::default()))
+    }
+
     pub fn into_bind_group(
         self,
         device: &wgpu::Device,
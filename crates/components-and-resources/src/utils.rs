@@ -37,3 +37,57 @@ pub fn random_point_in_sphere<R: Rng>(rng: &mut R) -> Vec3 {
 pub fn compare_floats(a: f32, b: f32) -> std::cmp::Ordering {
     a.partial_cmp(&b).unwrap_or(std::cmp::Ordering::Equal)
 }
+
+// How long it'll take a group of ships, given as positions and top speeds, to all reach a point.
+// Matching the slowest ship holds everyone to its speed.
+pub fn estimated_arrival(
+    ships: &[(Vec3, f32)],
+    destination: Vec3,
+    match_slowest: bool,
+) -> Option<f32> {
+    let slowest = ships
+        .iter()
+        .map(|&(_, speed)| speed)
+        .min_by(|&a, &b| compare_floats(a, b))?;
+
+    ships
+        .iter()
+        .map(|&(position, speed)| {
+            let speed = if match_slowest { slowest } else { speed };
+            (destination - position).mag() / speed.max(f32::EPSILON)
+        })
+        .max_by(|&a, &b| compare_floats(a, b))
+}
+
+// Formats simulation time as a mission clock, e.g. `12:34` or `1:02:03`.
+pub struct MissionTime(pub f32);
+
+impl std::fmt::Display for MissionTime {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        let seconds = self.0.max(0.0) as u32;
+        let (hours, minutes, seconds) = (seconds / 3600, (seconds / 60) % 60, seconds % 60);
+
+        if hours > 0 {
+            write!(f, "{}:{:02}:{:02}", hours, minutes, seconds)
+        } else {
+            write!(f, "{:02}:{:02}", minutes, seconds)
+        }
+    }
+}
+
+#[test]
+fn test_mission_time() {
+    assert_eq!(MissionTime(0.0).to_string(), "00:00");
+    assert_eq!(MissionTime(754.9).to_string(), "12:34");
+    assert_eq!(MissionTime(3723.0).to_string(), "1:02:03");
+}
+
+#[test]
+fn test_estimated_arrival() {
+    let ships = [(Vec3::zero(), 10.0), (Vec3::new(50.0, 0.0, 0.0), 5.0)];
+    let destination = Vec3::new(100.0, 0.0, 0.0);
+
+    assert_eq!(estimated_arrival(&ships, destination, false), Some(10.0));
+    assert_eq!(estimated_arrival(&ships, destination, true), Some(20.0));
+    assert_eq!(estimated_arrival(&[], destination, false), None);
+}
@@ -0,0 +1,187 @@
+use crate::components::Side;
+use crate::resources::{
+    GlobalCredits, GlobalGas, GlobalMinerals, GlobalResearch, PerSide, SimClock, SmallRng,
+};
+use crate::save::component_registry;
+use bevy_ecs::prelude::{Entity, World};
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+
+// `DefaultHasher::new` always uses the same keys, so hashes match between two copies of the
+// same build.
+fn hash_one<T: Hash>(value: T) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    value.hash(&mut hasher);
+    hasher.finish()
+}
+
+// Components that only reflect what one player is doing, so they can differ between players
+// without the game having diverged.
+const LOCAL_ONLY: [&str; 3] = ["selected", "camera_following", "debug_watch"];
+
+// Which subsystem registered components are counted under. Everything else, including components
+// registered by mods, is counted under "other components".
+const COMPONENT_SUBSYSTEMS: [(&str, usize); 13] = [
+    ("position", 0),
+    ("velocity", 1),
+    ("health", 2),
+    ("shield", 2),
+    ("command_queue", 3),
+    ("weapons", 4),
+    ("weapon_arcs", 4),
+    ("missile_launcher", 4),
+    ("beam_weapon", 4),
+    ("heat", 4),
+    ("can_be_mined", 5),
+    ("stored_minerals", 5),
+    ("carrying", 5),
+];
+
+const OTHER_COMPONENTS: usize = 6;
+const RESOURCES: usize = 7;
+const RNG: usize = 8;
+
+pub const SUBSYSTEMS: [&str; 9] = [
+    "positions",
+    "velocities",
+    "health",
+    "command queues",
+    "weapons",
+    "mining",
+    "other components",
+    "resources",
+    "rng",
+];
+
+// Hashes every component in the `ComponentRegistry` through its serialized form, which covers
+// every field without needing `Hash` impls for floats. The per-entity hashes are summed so that
+// the order entities are visited in doesn't matter.
+fn hash_components(world: &mut World, hashes: &mut [u64]) {
+    let registry = component_registry(world);
+    let entities = world.query::<Entity>().iter(world).collect::<Vec<_>>();
+
+    for entity in entities {
+        for (name, data) in registry.serialized_components(world, entity) {
+            if LOCAL_ONLY.contains(&name) {
+                continue;
+            }
+
+            let subsystem = COMPONENT_SUBSYSTEMS
+                .iter()
+                .find(|&&(component, _)| component == name)
+                .map_or(OTHER_COMPONENTS, |&(_, subsystem)| subsystem);
+
+            hashes[subsystem] =
+                hashes[subsystem].wrapping_add(hash_one((entity.to_bits(), name, data)));
+        }
+    }
+}
+
+fn hash_resources(world: &World) -> u64 {
+    let minerals = world.get_resource::<PerSide<GlobalMinerals>>().unwrap();
+    let gas = world.get_resource::<PerSide<GlobalGas>>().unwrap();
+    let credits = world.get_resource::<PerSide<GlobalCredits>>().unwrap();
+    let research = world.get_resource::<PerSide<GlobalResearch>>().unwrap();
+
+    let side = |side: Side| {
+        (
+            minerals[side].0.to_bits(),
+            gas[side].0.to_bits(),
+            credits[side].0.to_bits(),
+            research[side].0.to_bits(),
+        )
+    };
+
+    hash_one((
+        world.get_resource::<SimClock>().unwrap().time.to_bits(),
+        side(Side::Friendly),
+        side(Side::Enemy),
+    ))
+}
+
+// Draws from a copy so the simulation's rng isn't advanced.
+fn hash_rng(world: &World) -> u64 {
+    world.get_resource::<SmallRng>().unwrap().clone().gen()
+}
+
+// How often hashes are compared between players, or against a replay.
+pub const CHECK_INTERVAL: u32 = 60;
+
+// One hash per entry in `SUBSYSTEMS`, so that when two simulations drift apart it's clear which
+// part of the world went first.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct WorldHashes(pub Vec<u64>);
+
+impl WorldHashes {
+    pub fn compute(world: &mut World) -> Self {
+        let mut hashes = vec![0; SUBSYSTEMS.len()];
+        hash_components(world, &mut hashes);
+        hashes[RESOURCES] = hash_resources(world);
+        hashes[RNG] = hash_rng(world);
+        Self(hashes)
+    }
+
+    pub fn diverged_subsystems(&self, other: &Self) -> Vec<&'static str> {
+        SUBSYSTEMS
+            .iter()
+            .zip(self.0.iter().zip(&other.0))
+            .filter(|(_, (a, b))| a != b)
+            .map(|(name, _)| *name)
+            .collect()
+    }
+}
+
+impl std::fmt::Display for WorldHashes {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        for (i, (name, hash)) in SUBSYSTEMS.iter().zip(&self.0).enumerate() {
+            if i > 0 {
+                write!(f, " ")?;
+            }
+
+            write!(f, "{}={:016x}", name.replace(' ', "_"), hash)?;
+        }
+
+        Ok(())
+    }
+}
+
+#[test]
+fn test_world_hashes() {
+    use crate::components::{Health, Position, Scale, Selected};
+    use rand::SeedableRng;
+    use ultraviolet::Vec3;
+
+    let mut world = World::default();
+    world.insert_resource(SimClock::new(0.0));
+    world.insert_resource(PerSide::<GlobalMinerals>::default());
+    world.insert_resource(PerSide::<GlobalGas>::default());
+    world.insert_resource(PerSide::<GlobalCredits>::default());
+    world.insert_resource(PerSide::<GlobalResearch>::default());
+    world.insert_resource(SmallRng::seed_from_u64(0));
+
+    let entity = world
+        .spawn()
+        .insert_bundle((Position(Vec3::zero()), Health::new(100.0)))
+        .id();
+
+    let before = WorldHashes::compute(&mut world);
+    assert_eq!(before, WorldHashes::compute(&mut world));
+
+    world.get_mut::<Health>(entity).unwrap().current -= 1.0;
+    let after = WorldHashes::compute(&mut world);
+
+    assert_eq!(before.diverged_subsystems(&after), vec!["health"]);
+
+    // Components without a subsystem of their own are still covered, but what's selected isn't.
+    world
+        .entity_mut(entity)
+        .insert_bundle((Scale(2.0), Selected));
+    let scaled = WorldHashes::compute(&mut world);
+
+    assert_eq!(after.diverged_subsystems(&scaled), vec!["other components"]);
+
+    world.entity_mut(entity).remove::<Selected>();
+    assert_eq!(scaled, WorldHashes::compute(&mut world));
+}
@@ -0,0 +1,282 @@
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::io::{ErrorKind, Read, Write};
+use std::net::{TcpListener, TcpStream, ToSocketAddrs};
+
+// Commands are scheduled this many ticks ahead so that they have time to reach the other player
+// before either simulation needs them.
+pub const INPUT_DELAY: u32 = 4;
+
+// Exchanged when the game starts so that each player knows who they're playing with.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct PlayerProfile {
+    pub name: String,
+    // An index into the game's palette of team colours.
+    pub colour: u8,
+}
+
+// Everything about a match that both simulations need to agree on from the start. The host
+// decides, and whoever connects uses the host's choices.
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+pub struct MatchSettings {
+    pub seed: u64,
+    pub pickups: bool,
+}
+
+// Generic over the command container so that commands can be sent by reference and received
+// into a `Vec`.
+#[derive(Serialize, Deserialize)]
+enum Packet<C> {
+    Handshake {
+        settings: MatchSettings,
+        profile: PlayerProfile,
+    },
+    Profile(PlayerProfile),
+    Tick {
+        tick: u32,
+        commands: C,
+    },
+    Hashes {
+        tick: u32,
+        hashes: Vec<u64>,
+    },
+}
+
+// Keeps two identical simulations in step by only advancing a tick once the commands of both
+// players for that tick are known.
+pub struct Lockstep<T> {
+    stream: TcpStream,
+    local_player: usize,
+    profiles: [PlayerProfile; 2],
+    tick: u32,
+    commands: [BTreeMap<u32, Vec<T>>; 2],
+    // Hashes of each simulation, kept until the other player's hashes for the same tick arrive.
+    hashes: [BTreeMap<u32, Vec<u64>>; 2],
+    read_buffer: Vec<u8>,
+}
+
+impl<T: Serialize + DeserializeOwned> Lockstep<T> {
+    // Waits for the other player to connect, sends them the settings for the match and then
+    // waits for their profile.
+    pub fn host<A: ToSocketAddrs>(
+        address: A,
+        settings: MatchSettings,
+        profile: PlayerProfile,
+    ) -> anyhow::Result<Self> {
+        let listener = TcpListener::bind(address)?;
+        log::info!(
+            "Waiting for a player to connect on {}",
+            listener.local_addr()?
+        );
+
+        let (stream, peer) = listener.accept()?;
+        log::info!("{} connected", peer);
+
+        let mut lockstep = Self::new(stream, 0, profile.clone())?;
+        lockstep.send(&Packet::<&[T]>::Handshake { settings, profile })?;
+
+        match lockstep.wait_for_packet()? {
+            Packet::Profile(profile) => lockstep.profiles[1] = profile,
+            _ => return Err(anyhow::anyhow!("Expected the other player's profile")),
+        }
+
+        Ok(lockstep)
+    }
+
+    // Returns the host's settings for the match along with the connection.
+    pub fn connect<A: ToSocketAddrs>(
+        address: A,
+        profile: PlayerProfile,
+    ) -> anyhow::Result<(Self, MatchSettings)> {
+        let stream = TcpStream::connect(address)?;
+
+        let mut lockstep = Self::new(stream, 1, profile.clone())?;
+
+        let settings = match lockstep.wait_for_packet()? {
+            Packet::Handshake {
+                settings,
+                profile: host_profile,
+            } => {
+                lockstep.profiles[0] = host_profile;
+                settings
+            }
+            _ => return Err(anyhow::anyhow!("Expected a handshake")),
+        };
+
+        lockstep.send(&Packet::<&[T]>::Profile(profile))?;
+
+        Ok((lockstep, settings))
+    }
+
+    fn new(stream: TcpStream, local_player: usize, profile: PlayerProfile) -> anyhow::Result<Self> {
+        stream.set_nodelay(true)?;
+        stream.set_nonblocking(true)?;
+
+        let mut commands = [BTreeMap::new(), BTreeMap::new()];
+
+        // Nobody can have issued commands for the first few ticks.
+        for tick in 0..INPUT_DELAY {
+            for player_commands in &mut commands {
+                player_commands.insert(tick, Vec::new());
+            }
+        }
+
+        Ok(Self {
+            stream,
+            local_player,
+            // The other player's profile is filled in during the handshake.
+            profiles: [profile.clone(), profile],
+            tick: 0,
+            commands,
+            hashes: [BTreeMap::new(), BTreeMap::new()],
+            read_buffer: Vec::new(),
+        })
+    }
+
+    pub fn local_player(&self) -> usize {
+        self.local_player
+    }
+
+    // In player order.
+    pub fn profiles(&self) -> &[PlayerProfile; 2] {
+        &self.profiles
+    }
+
+    // Returns the commands of both players, in player order, for the next tick if they've
+    // arrived. `local_commands` are sent off to be run `INPUT_DELAY` ticks later.
+    pub fn advance(&mut self, local_commands: &mut Vec<T>) -> anyhow::Result<Option<[Vec<T>; 2]>> {
+        while let Some(packet) = self.receive()? {
+            match packet {
+                Packet::Tick { tick, commands } => {
+                    self.commands[1 - self.local_player].insert(tick, commands);
+                }
+                Packet::Hashes { tick, hashes } => {
+                    self.hashes[1 - self.local_player].insert(tick, hashes);
+                }
+                Packet::Handshake { .. } | Packet::Profile(_) => {
+                    return Err(anyhow::anyhow!("Received a handshake mid-game"))
+                }
+            }
+        }
+
+        if !self.commands[1 - self.local_player].contains_key(&self.tick) {
+            return Ok(None);
+        }
+
+        let scheduled_for = self.tick + INPUT_DELAY;
+        let local_commands = std::mem::take(local_commands);
+
+        self.send(&Packet::Tick {
+            tick: scheduled_for,
+            commands: &local_commands[..],
+        })?;
+        self.commands[self.local_player].insert(scheduled_for, local_commands);
+
+        let tick = self.tick;
+        self.tick += 1;
+
+        let mut take = |player: usize| self.commands[player].remove(&tick).unwrap_or_default();
+
+        Ok(Some([take(0), take(1)]))
+    }
+
+    // Sends hashes of the local simulation after `tick` to be compared against the other
+    // player's.
+    pub fn send_hashes(&mut self, tick: u32, hashes: Vec<u64>) -> anyhow::Result<()> {
+        self.send(&Packet::<&[T]>::Hashes {
+            tick,
+            hashes: hashes.clone(),
+        })?;
+        self.hashes[self.local_player].insert(tick, hashes);
+        Ok(())
+    }
+
+    // Returns each tick that both players have sent hashes for where they differ, along with
+    // the local and remote hashes.
+    pub fn take_mismatched_hashes(&mut self) -> Vec<(u32, Vec<u64>, Vec<u64>)> {
+        let local_player = self.local_player;
+        let [first, second] = &mut self.hashes;
+        let (local, remote) = if local_player == 0 {
+            (first, second)
+        } else {
+            (second, first)
+        };
+
+        let mut mismatched = Vec::new();
+
+        local.retain(|tick, local_hashes| match remote.remove(tick) {
+            Some(remote_hashes) => {
+                if *local_hashes != remote_hashes {
+                    mismatched.push((*tick, local_hashes.clone(), remote_hashes));
+                }
+
+                false
+            }
+            None => true,
+        });
+
+        mismatched
+    }
+
+    fn wait_for_packet(&mut self) -> anyhow::Result<Packet<Vec<T>>> {
+        loop {
+            match self.receive()? {
+                Some(packet) => return Ok(packet),
+                None => std::thread::yield_now(),
+            }
+        }
+    }
+
+    fn send<C: Serialize>(&mut self, packet: &Packet<C>) -> anyhow::Result<()> {
+        let bytes = bincode::serialize(packet)?;
+
+        let mut framed = Vec::with_capacity(4 + bytes.len());
+        framed.extend_from_slice(&(bytes.len() as u32).to_le_bytes());
+        framed.extend_from_slice(&bytes);
+
+        let mut written = 0;
+
+        // The stream is non-blocking, so keep trying until everything has been written.
+        while written < framed.len() {
+            match self.stream.write(&framed[written..]) {
+                Ok(0) => return Err(anyhow::anyhow!("Connection closed")),
+                Ok(bytes) => written += bytes,
+                Err(error) if error.kind() == ErrorKind::WouldBlock => std::thread::yield_now(),
+                Err(error) => return Err(error.into()),
+            }
+        }
+
+        Ok(())
+    }
+
+    fn receive(&mut self) -> anyhow::Result<Option<Packet<Vec<T>>>> {
+        let mut buffer = [0; 1024];
+
+        loop {
+            match self.stream.read(&mut buffer) {
+                Ok(0) => return Err(anyhow::anyhow!("Connection closed")),
+                Ok(bytes) => self.read_buffer.extend_from_slice(&buffer[..bytes]),
+                Err(error) if error.kind() == ErrorKind::WouldBlock => break,
+                Err(error) => return Err(error.into()),
+            }
+        }
+
+        if self.read_buffer.len() < 4 {
+            return Ok(None);
+        }
+
+        let mut length = [0; 4];
+        length.copy_from_slice(&self.read_buffer[..4]);
+        let length = u32::from_le_bytes(length) as usize;
+
+        if self.read_buffer.len() < 4 + length {
+            return Ok(None);
+        }
+
+        let packet = bincode::deserialize(&self.read_buffer[4..4 + length])?;
+        self.read_buffer.drain(..4 + length);
+
+        Ok(Some(packet))
+    }
+}
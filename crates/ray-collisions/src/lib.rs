@@ -1,10 +1,11 @@
+use serde::{Deserialize, Serialize};
 use ultraviolet::{Mat3, Mat4, Vec2, Vec3, Vec4};
 
 mod dynamic_bvh;
 
 pub use dynamic_bvh::DynamicBvh;
 
-#[derive(Debug, Default, Clone, Copy)]
+#[derive(Debug, Default, Clone, Copy, Serialize, Deserialize)]
 pub struct Ray {
     pub origin: Vec3,
     pub direction: Vec3,
@@ -142,6 +143,7 @@ impl std::ops::Neg for &Ray {
     }
 }
 
+#[derive(Clone, Serialize, Deserialize)]
 pub struct Projectile {
     flipped_ray: Ray,
     velocity: f32,
@@ -190,6 +192,10 @@ impl Projectile {
     pub fn get_intersection_point(&self, t: f32) -> Vec3 {
         self.flipped_ray.get_intersection_point(t)
     }
+
+    pub fn direction(&self) -> Vec3 {
+        -self.flipped_ray.direction
+    }
 }
 
 #[derive(Clone, Copy)]
@@ -231,7 +237,7 @@ impl LimitedRay {
     }
 }
 
-#[derive(Copy, Clone, Default, Debug, PartialEq)]
+#[derive(Copy, Clone, Default, Debug, PartialEq, Serialize, Deserialize)]
 pub struct BoundingBox {
     min: Vec3,
     max: Vec3,
@@ -324,6 +330,16 @@ impl BoundingBox {
         )
     }
 
+    #[inline]
+    pub fn center(self) -> Vec3 {
+        (self.min + self.max) / 2.0
+    }
+
+    #[inline]
+    pub fn size(self) -> Vec3 {
+        self.max - self.min
+    }
+
     #[inline]
     pub fn contains(self, inner: BoundingBox) -> bool {
         self.union_with(inner) == self
@@ -373,6 +389,10 @@ impl Triangle {
 
         BoundingBox::new(min, max)
     }
+
+    pub fn normal(&self) -> Vec3 {
+        self.edge_b_a.cross(self.edge_c_a).normalized()
+    }
 }
 
 fn to_wgpu_coords(point: Vec2, dimensions: Vec2) -> Vec2 {
@@ -2,12 +2,10 @@
 
 layout(location = 0) in vec3 position;
 
-layout(location = 1) in vec3 rotation_1;
-layout(location = 2) in vec3 rotation_2;
-layout(location = 3) in vec3 rotation_3;
-layout(location = 4) in vec3 translation;
-layout(location = 5) in vec3 colour;
-layout(location = 6) in float scale;
+layout(location = 1) in vec4 rotation;
+layout(location = 2) in vec3 translation;
+layout(location = 3) in float scale;
+layout(location = 4) in vec4 colour;
 
 layout(push_constant) uniform PushConstants {
     mat4 perspective_view;
@@ -16,15 +14,17 @@ layout(push_constant) uniform PushConstants {
 
 layout(location = 0) out vec4 out_colour;
 
-void main() {
-    mat3 rotation = mat3(rotation_1, rotation_2, rotation_3);
+vec3 rotate(vec4 q, vec3 v) {
+    return v + 2.0 * cross(q.xyz, cross(q.xyz, v) + q.w * v);
+}
 
-    vec3 transformed_position = rotation * position * scale + translation;
+void main() {
+    vec3 transformed_position = rotate(rotation, position) * scale + translation;
     gl_Position = perspective_view * vec4(transformed_position, 1.0);
 
-    if (colour == vec3(0.0)) {
+    if (colour.rgb == vec3(0.0)) {
         gl_Position = vec4(0.0);
     }
 
-    out_colour = vec4(colour, 1.0);
+    out_colour = vec4(colour.rgb, 1.0);
 }
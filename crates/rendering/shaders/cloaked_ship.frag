@@ -0,0 +1,33 @@
+#version 450
+
+layout(location = 0) in vec3 in_normal;
+layout(location = 7) in vec3 in_world_position;
+layout(location = 8) flat in vec3 in_colour;
+
+layout(push_constant) uniform PushConstants {
+    mat4 perspective_view;
+    vec3 light_dir;
+    vec3 ambient_light;
+    vec3 camera_position;
+    float time;
+};
+
+layout(location = 0) out vec4 colour;
+
+const vec3 SHIMMER_COLOUR = vec3(0.4, 0.7, 1.0);
+const float SHIMMER_SPEED = 3.0;
+const float SHIMMER_FREQUENCY = 0.5;
+
+// Only the edges of a cloaked ship are visible, with bands rippling across them.
+void main() {
+    vec3 normal = normalize(in_normal);
+    vec3 view_dir = normalize(camera_position - in_world_position);
+
+    float fresnel = pow(1.0 - max(dot(normal, view_dir), 0.0), 3.0);
+
+    float shimmer = sin(dot(in_world_position, vec3(1.0)) * SHIMMER_FREQUENCY + time * SHIMMER_SPEED) * 0.5 + 0.5;
+
+    float alpha = clamp(fresnel * (0.5 + shimmer * 0.5) + 0.05, 0.0, 1.0);
+
+    colour = vec4(SHIMMER_COLOUR + in_colour, alpha);
+}
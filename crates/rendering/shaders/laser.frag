@@ -0,0 +1,42 @@
+#version 450
+
+layout(location = 0) in vec3 colour;
+layout(location = 1) in float across;
+
+layout(push_constant) uniform PushConstants {
+    mat4 perspective_view;
+    vec3 camera_position;
+    float depth_fade_distance;
+};
+
+layout(set = 0, binding = 0) uniform sampler u_sampler;
+layout(set = 0, binding = 1) uniform texture2D u_depth;
+
+layout(location = 0) out vec4 out_colour;
+layout(location = 1) out vec4 bloom_colour;
+
+// Must match the near plane in the perspective matrix.
+const float Z_NEAR = 0.1;
+
+float linearize_depth(float depth) {
+    return Z_NEAR / (1.0 - depth);
+}
+
+void main() {
+    ivec2 depth_size = textureSize(sampler2D(u_depth, u_sampler), 0);
+    float scene_depth = texture(sampler2D(u_depth, u_sampler), gl_FragCoord.xy / vec2(depth_size)).r;
+
+    float distance_to_geometry = linearize_depth(scene_depth) - linearize_depth(gl_FragCoord.z);
+
+    if (distance_to_geometry < 0.0) {
+        discard;
+    }
+
+    float depth_fade = clamp(distance_to_geometry / depth_fade_distance, 0.0, 1.0);
+    float soft_edge = 1.0 - smoothstep(0.25, 1.0, abs(across));
+
+    vec3 faded = colour * soft_edge * depth_fade;
+
+    out_colour = vec4(faded, 1.0);
+    bloom_colour = vec4(faded, 1.0);
+}
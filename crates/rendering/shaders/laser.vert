@@ -0,0 +1,41 @@
+#version 450
+
+layout(location = 0) in vec3 start;
+layout(location = 1) in vec3 end;
+layout(location = 2) in vec3 colour;
+layout(location = 3) in float width;
+
+layout(push_constant) uniform PushConstants {
+    mat4 perspective_view;
+    vec3 camera_position;
+    float depth_fade_distance;
+};
+
+layout(location = 0) out vec3 out_colour;
+// -1 to 1 across the width of the quad.
+layout(location = 1) out float out_across;
+
+const vec2 CORNERS[6] = vec2[6](
+    vec2(0.0, -1.0), vec2(1.0, -1.0), vec2(1.0, 1.0),
+    vec2(0.0, -1.0), vec2(1.0, 1.0), vec2(0.0, 1.0)
+);
+
+void main() {
+    vec2 corner = CORNERS[gl_VertexIndex];
+
+    vec3 position = mix(start, end, corner.x);
+    vec3 to_camera = camera_position - position;
+    vec3 side = cross(end - start, to_camera);
+
+    // Avoid a NaN when looking straight down the laser.
+    if (dot(side, side) > 0.0) {
+        side = normalize(side);
+    }
+
+    position += side * corner.y * width * 0.5;
+
+    gl_Position = perspective_view * vec4(position, 1.0);
+
+    out_colour = colour;
+    out_across = corner.y;
+}
@@ -0,0 +1,8 @@
+#version 450
+
+layout(location = 0) out float out_count;
+
+// Blended additively, so the total is the number of times each pixel was drawn to.
+void main() {
+    out_count = 1.0;
+}
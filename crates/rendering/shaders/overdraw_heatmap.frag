@@ -0,0 +1,31 @@
+#version 450
+
+layout (location = 0) in vec2 uv;
+
+layout(set = 0, binding = 0) uniform sampler u_sampler;
+layout(set = 0, binding = 1) uniform texture2D u_texture;
+
+layout (location = 0) out vec4 out_colour;
+
+// Drawn to once, twice, 4 times, 8 times and 16 or more times.
+const vec3 HEAT[5] = vec3[5](
+    vec3(0.0, 0.0, 0.4),
+    vec3(0.0, 0.4, 1.0),
+    vec3(0.0, 1.0, 0.0),
+    vec3(1.0, 1.0, 0.0),
+    vec3(1.0, 0.0, 0.0)
+);
+
+void main() {
+    float count = texelFetch(sampler2D(u_texture, u_sampler), ivec2(gl_FragCoord.xy), 0).r;
+
+    if (count < 0.5) {
+        out_colour = vec4(0.0, 0.0, 0.0, 1.0);
+        return;
+    }
+
+    float heat = clamp(log2(count), 0.0, 4.0);
+    int index = min(int(heat), 3);
+
+    out_colour = vec4(mix(HEAT[index], HEAT[index + 1], heat - float(index)), 1.0);
+}
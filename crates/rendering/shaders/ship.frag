@@ -5,32 +5,117 @@ layout(location = 0) in vec3 in_normal;
 layout(location = 1) in vec2 in_uv;
 layout(location = 2) flat in uint in_diffuse_texture;
 layout(location = 3) flat in uint in_emissive_texture;
+layout(location = 4) in vec4 in_tangent;
+layout(location = 5) flat in uint in_normal_texture;
+layout(location = 6) flat in uint in_metallic_roughness_texture;
+layout(location = 7) in vec3 in_world_position;
+layout(location = 8) flat in vec3 in_colour;
+layout(location = 9) flat in float in_last_hit_time;
+layout(location = 10) flat in float in_selection_pulse;
+layout(location = 11) flat in vec3 in_ambient_tint;
 
 layout(set = 0, binding = 0) uniform sampler u_sampler;
-layout(set = 0, binding = 1) uniform texture2D u_textures[10];
+layout(set = 0, binding = 1) uniform texture2D u_textures[];
 
 layout(push_constant) uniform PushConstants {
     mat4 perspective_view;
     vec3 light_dir;
     vec3 ambient_light;
+    vec3 camera_position;
+    float time;
+    vec3 second_light_dir;
+    float second_light_intensity;
 };
 
 layout(location = 0) out vec4 colour;
 layout(location = 1) out vec4 bloom;
 
-float ambient_factor = 1.0 / 3.0;
+const float PI = 3.14159265359;
 
-void main() {
+// Chosen so that a fully lit, non-metallic surface is roughly as bright as it was
+// with the old lambertian shading.
+const float SUN_INTENSITY = PI;
+
+const float HIT_FLASH_DURATION = 0.15;
+const float SELECTION_PULSE_SPEED = 4.0;
+
+vec3 sample_normal() {
     vec3 normal = normalize(in_normal);
+    vec3 tangent = normalize(in_tangent.xyz - normal * dot(normal, in_tangent.xyz));
+    vec3 bitangent = cross(normal, tangent) * in_tangent.w;
+
+    vec3 tangent_space_normal = texture(sampler2D(u_textures[in_normal_texture], u_sampler), in_uv).xyz * 2.0 - 1.0;
+
+    return normalize(mat3(tangent, bitangent, normal) * tangent_space_normal);
+}
+
+float distribution_ggx(float n_dot_h, float roughness) {
+    float alpha = roughness * roughness;
+    float alpha_sq = alpha * alpha;
+    float denominator = n_dot_h * n_dot_h * (alpha_sq - 1.0) + 1.0;
+    return alpha_sq / (PI * denominator * denominator);
+}
+
+float geometry_schlick_ggx(float n_dot_x, float roughness) {
+    float k = (roughness + 1.0) * (roughness + 1.0) / 8.0;
+    return n_dot_x / (n_dot_x * (1.0 - k) + k);
+}
+
+vec3 fresnel_schlick(float cos_theta, vec3 f0) {
+    return f0 + (1.0 - f0) * pow(1.0 - cos_theta, 5.0);
+}
+
+// Cook-torrance lighting from a single directional light.
+vec3 direct_light(vec3 light_dir, vec3 normal, vec3 view_dir, vec3 base_colour, float metallic, float roughness) {
+    vec3 halfway = normalize(view_dir + light_dir);
 
-    float diffuse_factor = max(dot(normal, light_dir), 0.0);
+    float n_dot_l = max(dot(normal, light_dir), 0.0);
+    float n_dot_v = max(dot(normal, view_dir), 0.0001);
+    float n_dot_h = max(dot(normal, halfway), 0.0);
 
-    vec3 diffuse = texture(sampler2D(u_textures[in_diffuse_texture], u_sampler), in_uv).rgb;
+    vec3 f0 = mix(vec3(0.04), base_colour, metallic);
+    vec3 fresnel = fresnel_schlick(max(dot(halfway, view_dir), 0.0), f0);
+
+    float geometry = geometry_schlick_ggx(n_dot_v, roughness) * geometry_schlick_ggx(n_dot_l, roughness);
+
+    vec3 specular = distribution_ggx(n_dot_h, roughness) * geometry * fresnel / (4.0 * n_dot_v * n_dot_l + 0.0001);
+    vec3 diffuse = (1.0 - fresnel) * (1.0 - metallic) * base_colour / PI;
+
+    return (diffuse + specular) * n_dot_l;
+}
+
+void main() {
+    vec3 normal = sample_normal();
+    vec3 view_dir = normalize(camera_position - in_world_position);
+
+    vec3 base_colour = texture(sampler2D(u_textures[in_diffuse_texture], u_sampler), in_uv).rgb;
 
     float emissive_factor = texture(sampler2D(u_textures[in_emissive_texture], u_sampler), in_uv).r;
 
-    float colour_factor = max(diffuse_factor, emissive_factor);
+    // Roughness is stored in the green channel and metallic in the blue, as per the gltf spec.
+    vec2 metallic_roughness = texture(sampler2D(u_textures[in_metallic_roughness_texture], u_sampler), in_uv).bg;
+    float metallic = metallic_roughness.x;
+    float roughness = max(metallic_roughness.y, 0.04);
+
+    float n_dot_v = max(dot(normal, view_dir), 0.0001);
+
+    vec3 sun = direct_light(light_dir, normal, view_dir, base_colour, metallic, roughness) * SUN_INTENSITY;
+
+    if (second_light_intensity > 0.0) {
+        sun += direct_light(second_light_dir, normal, view_dir, base_colour, metallic, roughness)
+            * SUN_INTENSITY * second_light_intensity;
+    }
+    vec3 ambient = ambient_light * in_ambient_tint * base_colour;
+    vec3 emissive = emissive_factor * base_colour;
+
+    float rim = pow(1.0 - n_dot_v, 3.0);
+    float pulse = (0.5 + 0.5 * sin(time * SELECTION_PULSE_SPEED)) * in_selection_pulse;
+    vec3 rim_tint = in_colour * rim * pulse;
+
+    float hit_flash = clamp(1.0 - (time - in_last_hit_time) / HIT_FLASH_DURATION, 0.0, 1.0);
+
+    vec3 lit = sun + ambient + emissive + rim_tint;
 
-    colour = vec4((vec3(colour_factor) + ambient_light) * diffuse, 1.0);
-    bloom = vec4(emissive_factor * diffuse, 1.0);
+    colour = vec4(mix(lit, vec3(1.0), hit_flash * 0.75), 1.0);
+    bloom = vec4(emissive + vec3(hit_flash * 0.5), 1.0);
 }
@@ -3,36 +3,63 @@
 layout(location = 0) in vec3 position;
 layout(location = 1) in vec3 normal;
 layout(location = 2) in vec2 uv;
+layout(location = 3) in vec4 tangent;
 
-layout(location = 3) in vec3 rotation_1;
-layout(location = 4) in vec3 rotation_2;
-layout(location = 5) in vec3 rotation_3;
-layout(location = 6) in vec3 translation;
-layout(location = 7) in vec3 colour;
-layout(location = 8) in float scale;
-layout(location = 9) in uint diffuse_texture;
-layout(location = 10) in uint emissive_texture;
+layout(location = 4) in vec4 rotation;
+layout(location = 5) in vec3 translation;
+layout(location = 6) in float scale;
+// The alpha channel is the selection pulse.
+layout(location = 7) in vec4 colour;
+// Diffuse, emissive, normal and metallic-roughness.
+layout(location = 8) in uvec4 textures;
+layout(location = 9) in float last_hit_time;
 
 layout(push_constant) uniform PushConstants {
     mat4 perspective_view;
     vec3 light_dir;
     vec3 ambient_light;
+    vec3 camera_position;
+    float time;
 };
 
+layout(set = 1, binding = 0) uniform sampler u_irradiance_sampler;
+layout(set = 1, binding = 1) uniform textureCube u_irradiance;
+
 layout(location = 0) out vec3 out_normal;
 layout(location = 1) out vec2 out_uv;
 layout(location = 2) out uint out_diffuse_texture;
 layout(location = 3) out uint out_emissive_texture;
+layout(location = 4) out vec4 out_tangent;
+layout(location = 5) out uint out_normal_texture;
+layout(location = 6) out uint out_metallic_roughness_texture;
+layout(location = 7) out vec3 out_world_position;
+layout(location = 8) out vec3 out_colour;
+layout(location = 9) out float out_last_hit_time;
+layout(location = 10) out float out_selection_pulse;
+layout(location = 11) out vec3 out_ambient_tint;
 
+// Rotates a vector by a quaternion.
+vec3 rotate(vec4 q, vec3 v) {
+    return v + 2.0 * cross(q.xyz, cross(q.xyz, v) + q.w * v);
+}
 
 void main() {
-    mat3 rotation = mat3(rotation_1, rotation_2, rotation_3);
-
-    vec3 transformed_position = rotation * position * scale + translation;
+    vec3 transformed_position = rotate(rotation, position) * scale + translation;
     gl_Position = perspective_view * vec4(transformed_position, 1.0);
 
-    out_normal = rotation * normal;
+    out_normal = rotate(rotation, normal);
     out_uv = uv;
-    out_diffuse_texture = diffuse_texture;
-    out_emissive_texture = emissive_texture;
+    out_diffuse_texture = textures.x;
+    out_emissive_texture = textures.y;
+    out_tangent = vec4(rotate(rotation, tangent.xyz), tangent.w);
+    out_normal_texture = textures.z;
+    out_metallic_roughness_texture = textures.w;
+    out_world_position = transformed_position;
+    out_colour = colour.rgb;
+    out_last_hit_time = last_hit_time;
+    out_selection_pulse = colour.a;
+
+    // Tint the ambient light by the nebula in the direction of the ship.
+    vec3 region_dir = length(translation) > 0.0 ? translation : vec3(0.0, 1.0, 0.0);
+    out_ambient_tint = textureLod(samplerCube(u_irradiance, u_irradiance_sampler), region_dir, 0).rgb * 2.0;
 }
@@ -1,11 +1,14 @@
 pub mod passes;
 
 use components_and_resources::gpu_structs::*;
+use components_and_resources::resources::{ShipBuffer, TextureFiltering};
 use ultraviolet::{Mat4, Vec2, Vec3};
+use wgpu::util::DeviceExt;
 
 const DEPTH_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Depth32Float;
 const HDR_FRAMEBUFFER_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Rgba32Float;
 const EFFECT_BUFFER_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Rgba32Float;
+const OVERDRAW_BUFFER_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::R16Float;
 
 pub struct Resizables {
     hdr_framebuffer: wgpu::TextureView,
@@ -17,6 +20,68 @@ pub struct Resizables {
     second_bloom_blur_pass: wgpu::BindGroup,
     godray_buffer: wgpu::TextureView,
     godray_bind_group: wgpu::BindGroup,
+    depth_bind_group: wgpu::BindGroup,
+    overdraw_buffer: wgpu::TextureView,
+    overdraw_pass: wgpu::BindGroup,
+    picture_in_picture: PictureInPictureTargets,
+}
+
+// A smaller set of render targets for the picture-in-picture view. Bloom and godrays aren't
+// applied to it, but the ship and background pipelines still need somewhere to write them.
+struct PictureInPictureTargets {
+    hdr_framebuffer: wgpu::TextureView,
+    bloom_buffer: wgpu::TextureView,
+    godray_buffer: wgpu::TextureView,
+    depth_buffer: wgpu::TextureView,
+    hdr_pass: wgpu::BindGroup,
+}
+
+impl PictureInPictureTargets {
+    fn new(width: u32, height: u32, device: &wgpu::Device, resources: &Resources) -> Self {
+        let scale = components_and_resources::resources::PictureInPicture::SCALE;
+        let width = ((width as f32 * scale) as u32).max(1);
+        let height = ((height as f32 * scale) as u32).max(1);
+
+        let effect_target = |label| {
+            create_texture(
+                device,
+                label,
+                width,
+                height,
+                EFFECT_BUFFER_FORMAT,
+                wgpu::TextureUsages::RENDER_ATTACHMENT,
+            )
+        };
+
+        let hdr_framebuffer = create_texture(
+            device,
+            "picture in picture hdr framebuffer",
+            width,
+            height,
+            HDR_FRAMEBUFFER_FORMAT,
+            wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::TEXTURE_BINDING,
+        );
+
+        Self {
+            hdr_pass: make_effect_bind_group(
+                device,
+                resources,
+                &hdr_framebuffer,
+                "picture in picture hdr pass",
+            ),
+            hdr_framebuffer,
+            bloom_buffer: effect_target("picture in picture bloom buffer"),
+            godray_buffer: effect_target("picture in picture godray buffer"),
+            depth_buffer: create_texture(
+                device,
+                "picture in picture depth buffer",
+                width,
+                height,
+                DEPTH_FORMAT,
+                wgpu::TextureUsages::RENDER_ATTACHMENT,
+            ),
+        }
+    }
 }
 
 impl Resizables {
@@ -75,17 +140,43 @@ impl Resizables {
             },
         );
 
+        let depth_buffer = create_texture(
+            device,
+            "depth buffer",
+            width,
+            height,
+            DEPTH_FORMAT,
+            wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::TEXTURE_BINDING,
+        );
+
+        let overdraw_buffer = create_texture(
+            device,
+            "overdraw buffer",
+            width,
+            height,
+            OVERDRAW_BUFFER_FORMAT,
+            wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::TEXTURE_BINDING,
+        );
+
         Self {
             hdr_pass: make_effect_bind_group(device, resources, &hdr_framebuffer, "hdr pass"),
             hdr_framebuffer,
-            depth_buffer: create_texture(
-                device,
-                "depth buffer",
-                width,
-                height,
-                DEPTH_FORMAT,
-                wgpu::TextureUsages::RENDER_ATTACHMENT,
-            ),
+            depth_bind_group: device.create_bind_group(&wgpu::BindGroupDescriptor {
+                label: Some("depth bind group"),
+                layout: &resources.depth_bgl,
+                entries: &[
+                    wgpu::BindGroupEntry {
+                        binding: 0,
+                        resource: wgpu::BindingResource::Sampler(&resources.nearest_sampler),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 1,
+                        resource: wgpu::BindingResource::TextureView(&depth_buffer),
+                    },
+                ],
+            }),
+            depth_buffer,
+            picture_in_picture: PictureInPictureTargets::new(width, height, device, resources),
             first_bloom_blur_pass: make_effect_bind_group(
                 device,
                 resources,
@@ -107,6 +198,13 @@ impl Resizables {
                 "godray blur bind group",
             ),
             godray_buffer,
+            overdraw_pass: make_effect_bind_group(
+                device,
+                resources,
+                &overdraw_buffer,
+                "overdraw pass bind group",
+            ),
+            overdraw_buffer,
         }
     }
 }
@@ -136,12 +234,19 @@ fn make_effect_bind_group(
 pub struct Resources {
     pub merged_textures_bgl: wgpu::BindGroupLayout,
     effect_bgl: wgpu::BindGroupLayout,
+    depth_bgl: wgpu::BindGroupLayout,
+    irradiance_bgl: wgpu::BindGroupLayout,
     pub nearest_sampler: wgpu::Sampler,
     linear_sampler: wgpu::Sampler,
+    pub texture_sampler: wgpu::Sampler,
 }
 
 impl Resources {
-    pub fn new(device: &wgpu::Device, num_merged_textures: u32) -> Self {
+    pub fn new(
+        device: &wgpu::Device,
+        num_merged_textures: u32,
+        texture_filtering: TextureFiltering,
+    ) -> Self {
         let texture = |binding, shader_stage| wgpu::BindGroupLayoutEntry {
             binding,
             visibility: shader_stage,
@@ -168,12 +273,12 @@ impl Resources {
                 &wgpu::BindGroupLayoutDescriptor {
                     label: Some("merged textures bind group layout"),
                     entries: &[
-                        sampler(0, wgpu::ShaderStages::FRAGMENT, false),
+                        sampler(0, wgpu::ShaderStages::FRAGMENT, true),
                         wgpu::BindGroupLayoutEntry {
                             binding: 1,
                             visibility: wgpu::ShaderStages::FRAGMENT,
                             ty: wgpu::BindingType::Texture {
-                                sample_type: wgpu::TextureSampleType::Float { filterable: false },
+                                sample_type: wgpu::TextureSampleType::Float { filterable: true },
                                 view_dimension: wgpu::TextureViewDimension::D2,
                                 multisampled: false,
                             },
@@ -189,6 +294,38 @@ impl Resources {
                     texture(1, wgpu::ShaderStages::FRAGMENT),
                 ],
             }),
+            depth_bgl: device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                label: Some("depth bind group layout"),
+                entries: &[
+                    sampler(0, wgpu::ShaderStages::FRAGMENT, false),
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 1,
+                        visibility: wgpu::ShaderStages::FRAGMENT,
+                        ty: wgpu::BindingType::Texture {
+                            sample_type: wgpu::TextureSampleType::Depth,
+                            view_dimension: wgpu::TextureViewDimension::D2,
+                            multisampled: false,
+                        },
+                        count: None,
+                    },
+                ],
+            }),
+            irradiance_bgl: device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                label: Some("irradiance bind group layout"),
+                entries: &[
+                    sampler(0, wgpu::ShaderStages::VERTEX, true),
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 1,
+                        visibility: wgpu::ShaderStages::VERTEX,
+                        ty: wgpu::BindingType::Texture {
+                            sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                            view_dimension: wgpu::TextureViewDimension::Cube,
+                            multisampled: false,
+                        },
+                        count: None,
+                    },
+                ],
+            }),
             nearest_sampler: device.create_sampler(&wgpu::SamplerDescriptor {
                 label: Some("nearest sampler"),
                 ..Default::default()
@@ -199,10 +336,58 @@ impl Resources {
                 min_filter: wgpu::FilterMode::Linear,
                 ..Default::default()
             }),
+            texture_sampler: device.create_sampler(&texture_filtering.sampler_descriptor()),
         }
     }
 }
 
+pub fn create_irradiance_bind_group(
+    device: &wgpu::Device,
+    queue: &wgpu::Queue,
+    resources: &Resources,
+    face_size: u32,
+    texels: &[[u8; 4]],
+) -> wgpu::BindGroup {
+    let texture = device.create_texture_with_data(
+        queue,
+        &wgpu::TextureDescriptor {
+            label: Some("irradiance cubemap"),
+            size: wgpu::Extent3d {
+                width: face_size,
+                height: face_size,
+                depth_or_array_layers: 6,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: wgpu::TextureFormat::Rgba8Unorm,
+            usage: wgpu::TextureUsages::COPY_DST | wgpu::TextureUsages::TEXTURE_BINDING,
+        },
+        bytemuck::cast_slice(texels),
+    );
+
+    let view = texture.create_view(&wgpu::TextureViewDescriptor {
+        label: Some("irradiance cubemap view"),
+        dimension: Some(wgpu::TextureViewDimension::Cube),
+        ..Default::default()
+    });
+
+    device.create_bind_group(&wgpu::BindGroupDescriptor {
+        label: Some("irradiance bind group"),
+        layout: &resources.irradiance_bgl,
+        entries: &[
+            wgpu::BindGroupEntry {
+                binding: 0,
+                resource: wgpu::BindingResource::Sampler(&resources.linear_sampler),
+            },
+            wgpu::BindGroupEntry {
+                binding: 1,
+                resource: wgpu::BindingResource::TextureView(&view),
+            },
+        ],
+    })
+}
+
 fn create_texture(
     device: &wgpu::Device,
     label: &str,
@@ -230,18 +415,26 @@ fn create_texture(
 
 pub struct Pipelines {
     ship: wgpu::RenderPipeline,
+    cloaked_ship: wgpu::RenderPipeline,
     background: wgpu::RenderPipeline,
     first_bloom_blur: wgpu::RenderPipeline,
     second_bloom_blur: wgpu::RenderPipeline,
     godray_blur: wgpu::RenderPipeline,
+    depth_aware_godray_blur: wgpu::RenderPipeline,
     lines: wgpu::RenderPipeline,
     bounding_boxes: wgpu::RenderPipeline,
     tonemapper: wgpu::RenderPipeline,
     circle: wgpu::RenderPipeline,
     circle_outline: wgpu::RenderPipeline,
+    translucent_triangles: wgpu::RenderPipeline,
     z_facing_circle_outline: wgpu::RenderPipeline,
     lines_2d: wgpu::RenderPipeline,
+    triangles_2d: wgpu::RenderPipeline,
     lasers: wgpu::RenderPipeline,
+    ship_overdraw: wgpu::RenderPipeline,
+    laser_overdraw: wgpu::RenderPipeline,
+    overdraw_heatmap: wgpu::RenderPipeline,
+    build_draws: wgpu::ComputePipeline,
 }
 
 impl Pipelines {
@@ -256,7 +449,7 @@ impl Pipelines {
         let ship_bgl_pipeline_layout =
             device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
                 label: Some("ship bgl pipeline layout"),
-                bind_group_layouts: &[&resources.merged_textures_bgl],
+                bind_group_layouts: &[&resources.merged_textures_bgl, &resources.irradiance_bgl],
                 push_constant_ranges: &[wgpu::PushConstantRange {
                     stages: wgpu::ShaderStages::VERTEX | wgpu::ShaderStages::FRAGMENT,
                     range: 0..std::mem::size_of::<PushConstants>() as u32,
@@ -273,13 +466,13 @@ impl Pipelines {
         let model_vertex_buffer_layout = wgpu::VertexBufferLayout {
             array_stride: std::mem::size_of::<ModelVertex>() as u64,
             step_mode: wgpu::VertexStepMode::Vertex,
-            attributes: &wgpu::vertex_attr_array![0 => Float32x3, 1 => Float32x3, 2 => Float32x2],
+            attributes: &wgpu::vertex_attr_array![0 => Float32x3, 1 => Float32x3, 2 => Float32x2, 3 => Float32x4],
         };
 
         let instance_buffer_layout = wgpu::VertexBufferLayout {
             array_stride: std::mem::size_of::<Instance>() as u64,
             step_mode: wgpu::VertexStepMode::Instance,
-            attributes: &wgpu::vertex_attr_array![3 => Float32x3, 4 => Float32x3, 5 => Float32x3, 6 => Float32x3, 7 => Float32x3, 8 => Float32, 9 => Uint32, 10 => Uint32],
+            attributes: &wgpu::vertex_attr_array![4 => Float32x4, 5 => Float32x3, 6 => Float32, 7 => Unorm8x4, 8 => Uint16x4, 9 => Float32],
         };
 
         let vertex_2d_buffer_layout = wgpu::VertexBufferLayout {
@@ -427,12 +620,36 @@ impl Pipelines {
             "../shaders/compiled/circle.vert.spv"
         ));
 
+        let vs_ship = device.create_shader_module_spirv(&wgpu::include_spirv_raw!(
+            "../shaders/compiled/ship.vert.spv"
+        ));
+
+        let lasers_pipeline_layout =
+            device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                label: Some("lasers pipeline layout"),
+                bind_group_layouts: &[&resources.depth_bgl],
+                push_constant_ranges: &[wgpu::PushConstantRange {
+                    stages: wgpu::ShaderStages::VERTEX | wgpu::ShaderStages::FRAGMENT,
+                    range: 0..std::mem::size_of::<LaserPushConstants>() as u32,
+                }],
+            });
+
+        let vs_laser = device.create_shader_module_spirv(&wgpu::include_spirv_raw!(
+            "../shaders/compiled/laser.vert.spv"
+        ));
+
+        let laser_instance_buffer_layout = wgpu::VertexBufferLayout {
+            array_stride: std::mem::size_of::<LaserInstance>() as u64,
+            step_mode: wgpu::VertexStepMode::Instance,
+            attributes: &wgpu::vertex_attr_array![0 => Float32x3, 1 => Float32x3, 2 => Float32x3, 3 => Float32],
+        };
+
+        let fs_overdraw = device.create_shader_module_spirv(&wgpu::include_spirv_raw!(
+            "../shaders/compiled/overdraw.frag.spv"
+        ));
+
         Self {
             ship: {
-                let vs_ship = device.create_shader_module_spirv(&wgpu::include_spirv_raw!(
-                    "../shaders/compiled/ship.vert.spv"
-                ));
-
                 let fs_ship = device.create_shader_module_spirv(&wgpu::include_spirv_raw!(
                     "../shaders/compiled/ship.frag.spv"
                 ));
@@ -462,6 +679,36 @@ impl Pipelines {
                     multisample: wgpu::MultisampleState::default(),
                 })
             },
+            cloaked_ship: {
+                let fs_cloaked_ship = device.create_shader_module_spirv(&wgpu::include_spirv_raw!(
+                    "../shaders/compiled/cloaked_ship.frag.spv"
+                ));
+
+                device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+                    label: Some("cloaked ship pipeline"),
+                    layout: Some(&ship_bgl_pipeline_layout),
+                    vertex: wgpu::VertexState {
+                        module: &vs_ship,
+                        entry_point: "main",
+                        buffers: &[
+                            model_vertex_buffer_layout.clone(),
+                            instance_buffer_layout.clone(),
+                        ],
+                    },
+                    fragment: Some(wgpu::FragmentState {
+                        module: &fs_cloaked_ship,
+                        entry_point: "main",
+                        targets: &[
+                            alpha_blend(HDR_FRAMEBUFFER_FORMAT),
+                            ignore_colour_state(EFFECT_BUFFER_FORMAT),
+                            ignore_colour_state(EFFECT_BUFFER_FORMAT),
+                        ],
+                    }),
+                    primitive: backface_culling,
+                    depth_stencil: Some(depth_read.clone()),
+                    multisample: wgpu::MultisampleState::default(),
+                })
+            },
             background: {
                 let fs_background = device.create_shader_module_spirv(&wgpu::include_spirv_raw!(
                     "../shaders/compiled/background.frag.spv"
@@ -548,33 +795,60 @@ impl Pipelines {
                     multisample: wgpu::MultisampleState::default(),
                 })
             },
+            depth_aware_godray_blur: {
+                let pipeline_layout =
+                    device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                        label: Some("depth aware godray blur pipeline layout"),
+                        bind_group_layouts: &[&resources.effect_bgl, &resources.depth_bgl],
+                        push_constant_ranges: &[wgpu::PushConstantRange {
+                            stages: wgpu::ShaderStages::FRAGMENT,
+                            range: 0..std::mem::size_of::<GodraySettings>() as u32,
+                        }],
+                    });
+
+                let fs_depth_aware_godray_blur =
+                    device.create_shader_module_spirv(&wgpu::include_spirv_raw!(
+                        "../shaders/compiled/depth_aware_godray_blur.frag.spv"
+                    ));
+
+                device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+                    label: Some("depth aware godray blur pipeline"),
+                    layout: Some(&pipeline_layout),
+                    vertex: fullscreen_tri_vertex.clone(),
+                    fragment: Some(wgpu::FragmentState {
+                        module: &fs_depth_aware_godray_blur,
+                        entry_point: "main",
+                        targets: &[additive_colour_state(HDR_FRAMEBUFFER_FORMAT)],
+                    }),
+                    primitive: wgpu::PrimitiveState::default(),
+                    depth_stencil: None,
+                    multisample: wgpu::MultisampleState::default(),
+                })
+            },
             lasers: {
-                let fs_flat_colour_bloom = device.create_shader_module_spirv(
-                    &wgpu::include_spirv_raw!("../shaders/compiled/flat_colour_bloom.frag.spv"),
-                );
+                let fs_laser = device.create_shader_module_spirv(&wgpu::include_spirv_raw!(
+                    "../shaders/compiled/laser.frag.spv"
+                ));
 
                 device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
                     label: Some("lasers pipeline"),
-                    layout: Some(&perspective_view_pipeline_layout),
+                    layout: Some(&lasers_pipeline_layout),
                     vertex: wgpu::VertexState {
-                        module: &vs_flat_colour,
+                        module: &vs_laser,
                         entry_point: "main",
-                        buffers: &[background_vertex_buffer_layout.clone()],
+                        buffers: &[laser_instance_buffer_layout.clone()],
                     },
                     fragment: Some(wgpu::FragmentState {
-                        module: &fs_flat_colour_bloom,
+                        module: &fs_laser,
                         entry_point: "main",
                         targets: &[
-                            HDR_FRAMEBUFFER_FORMAT.into(),
-                            EFFECT_BUFFER_FORMAT.into(),
-                            ignore_colour_state(EFFECT_BUFFER_FORMAT),
+                            additive_colour_state(HDR_FRAMEBUFFER_FORMAT),
+                            additive_colour_state(EFFECT_BUFFER_FORMAT),
                         ],
                     }),
-                    primitive: wgpu::PrimitiveState {
-                        topology: wgpu::PrimitiveTopology::LineList,
-                        ..Default::default()
-                    },
-                    depth_stencil: Some(depth_write.clone()),
+                    primitive: wgpu::PrimitiveState::default(),
+                    // The depth buffer is sampled in the fragment shader instead.
+                    depth_stencil: None,
                     multisample: wgpu::MultisampleState::default(),
                 })
             },
@@ -608,7 +882,7 @@ impl Pipelines {
                 let instance_buffer_layout = wgpu::VertexBufferLayout {
                     array_stride: std::mem::size_of::<Instance>() as u64,
                     step_mode: wgpu::VertexStepMode::Instance,
-                    attributes: &wgpu::vertex_attr_array![1 => Float32x3, 2 => Float32x3, 3 => Float32x3, 4 => Float32x3, 5 => Float32x3, 6 => Float32],
+                    attributes: &wgpu::vertex_attr_array![1 => Float32x4, 2 => Float32x3, 3 => Float32, 4 => Unorm8x4],
                 };
 
                 device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
@@ -635,6 +909,73 @@ impl Pipelines {
                     multisample: wgpu::MultisampleState::default(),
                 })
             },
+            ship_overdraw: {
+                device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+                    label: Some("ship overdraw pipeline"),
+                    layout: Some(&ship_bgl_pipeline_layout),
+                    vertex: wgpu::VertexState {
+                        module: &vs_ship,
+                        entry_point: "main",
+                        buffers: &[
+                            model_vertex_buffer_layout.clone(),
+                            instance_buffer_layout.clone(),
+                        ],
+                    },
+                    fragment: Some(wgpu::FragmentState {
+                        module: &fs_overdraw,
+                        entry_point: "main",
+                        targets: &[additive_colour_state(OVERDRAW_BUFFER_FORMAT)],
+                    }),
+                    primitive: backface_culling,
+                    depth_stencil: None,
+                    multisample: wgpu::MultisampleState::default(),
+                })
+            },
+            laser_overdraw: {
+                device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+                    label: Some("laser overdraw pipeline"),
+                    layout: Some(&lasers_pipeline_layout),
+                    vertex: wgpu::VertexState {
+                        module: &vs_laser,
+                        entry_point: "main",
+                        buffers: &[laser_instance_buffer_layout.clone()],
+                    },
+                    fragment: Some(wgpu::FragmentState {
+                        module: &fs_overdraw,
+                        entry_point: "main",
+                        targets: &[additive_colour_state(OVERDRAW_BUFFER_FORMAT)],
+                    }),
+                    primitive: wgpu::PrimitiveState::default(),
+                    depth_stencil: None,
+                    multisample: wgpu::MultisampleState::default(),
+                })
+            },
+            overdraw_heatmap: {
+                let pipeline_layout =
+                    device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                        label: Some("overdraw heatmap pipeline layout"),
+                        bind_group_layouts: &[&resources.effect_bgl],
+                        push_constant_ranges: &[],
+                    });
+
+                let fs_overdraw_heatmap = device.create_shader_module_spirv(
+                    &wgpu::include_spirv_raw!("../shaders/compiled/overdraw_heatmap.frag.spv"),
+                );
+
+                device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+                    label: Some("overdraw heatmap pipeline"),
+                    layout: Some(&pipeline_layout),
+                    vertex: fullscreen_tri_vertex.clone(),
+                    fragment: Some(wgpu::FragmentState {
+                        module: &fs_overdraw_heatmap,
+                        entry_point: "main",
+                        targets: &[display_format.into()],
+                    }),
+                    primitive: wgpu::PrimitiveState::default(),
+                    depth_stencil: Some(depth_ignore.clone()),
+                    multisample: wgpu::MultisampleState::default(),
+                })
+            },
             tonemapper: {
                 let pipeline_layout =
                     device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
@@ -662,7 +1003,7 @@ impl Pipelines {
                         targets: &[display_format.into()],
                     }),
                     primitive: wgpu::PrimitiveState::default(),
-                    depth_stencil: Some(depth_ignore),
+                    depth_stencil: Some(depth_ignore.clone()),
                     multisample: wgpu::MultisampleState::default(),
                 })
             },
@@ -716,6 +1057,36 @@ impl Pipelines {
                     multisample: wgpu::MultisampleState::default(),
                 })
             },
+            translucent_triangles: {
+                let vs_translucent = device.create_shader_module_spirv(&wgpu::include_spirv_raw!(
+                    "../shaders/compiled/translucent.vert.spv"
+                ));
+
+                device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+                    label: Some("translucent triangles pipeline"),
+                    layout: Some(&perspective_view_pipeline_layout),
+                    vertex: wgpu::VertexState {
+                        module: &vs_translucent,
+                        entry_point: "main",
+                        buffers: &[wgpu::VertexBufferLayout {
+                            array_stride: std::mem::size_of::<TranslucentVertex>() as u64,
+                            step_mode: wgpu::VertexStepMode::Vertex,
+                            attributes: &wgpu::vertex_attr_array![0 => Float32x3, 1 => Float32x4],
+                        }],
+                    },
+                    fragment: Some(wgpu::FragmentState {
+                        module: &fs_flat_colour,
+                        entry_point: "main",
+                        targets: &[alpha_blend(display_format)],
+                    }),
+                    primitive: wgpu::PrimitiveState {
+                        topology: wgpu::PrimitiveTopology::TriangleList,
+                        ..Default::default()
+                    },
+                    depth_stencil: Some(depth_read.clone()),
+                    multisample: wgpu::MultisampleState::default(),
+                })
+            },
             z_facing_circle_outline: {
                 let vs_z_facing = device.create_shader_module_spirv(&wgpu::include_spirv_raw!(
                     "../shaders/compiled/z_facing.vert.spv"
@@ -771,6 +1142,54 @@ impl Pipelines {
                     multisample: wgpu::MultisampleState::default(),
                 })
             },
+            triangles_2d: {
+                let vs_2d = device.create_shader_module_spirv(&wgpu::include_spirv_raw!(
+                    "../shaders/compiled/2d.vert.spv"
+                ));
+
+                device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+                    label: Some("triangles 2d pipeline"),
+                    layout: Some(&empty_pipeline_layout),
+                    vertex: wgpu::VertexState {
+                        module: &vs_2d,
+                        entry_point: "main",
+                        buffers: &[vertex_2d_buffer_layout.clone()],
+                    },
+                    fragment: Some(wgpu::FragmentState {
+                        module: &fs_flat_colour,
+                        entry_point: "main",
+                        targets: &[display_format.into()],
+                    }),
+                    primitive: wgpu::PrimitiveState {
+                        topology: wgpu::PrimitiveTopology::TriangleList,
+                        ..Default::default()
+                    },
+                    depth_stencil: Some(depth_ignore),
+                    multisample: wgpu::MultisampleState::default(),
+                })
+            },
+            build_draws: {
+                let cs_build_draws = device.create_shader_module_spirv(&wgpu::include_spirv_raw!(
+                    "../shaders/compiled/build_draws.comp.spv"
+                ));
+
+                let pipeline_layout =
+                    device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                        label: Some("build draws pipeline layout"),
+                        bind_group_layouts: &[&ShipBuffer::create_bind_group_layout(device)],
+                        push_constant_ranges: &[wgpu::PushConstantRange {
+                            stages: wgpu::ShaderStages::COMPUTE,
+                            range: 0..std::mem::size_of::<BuildDrawsPushConstants>() as u32,
+                        }],
+                    });
+
+                device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+                    label: Some("build draws pipeline"),
+                    layout: Some(&pipeline_layout),
+                    module: &cs_build_draws,
+                    entry_point: "main",
+                })
+            },
         }
     }
 }
@@ -1,17 +1,18 @@
 use crate::{Pipelines, Resizables};
 use components_and_resources::components::ModelId;
 use components_and_resources::gpu_structs::{
-    BlurSettings, CircleInstance, ColouredVertex, GodraySettings, LaserVertex, PushConstants,
-    RangeInstance, Vertex2D,
+    BlurSettings, BuildDrawsPushConstants, CircleInstance, ColouredVertex, GodraySettings,
+    LaserInstance, LaserPushConstants, PushConstants, RangeInstance, TranslucentVertex, Vertex2D,
 };
 use components_and_resources::resources;
-use ultraviolet::{Vec2, Vec3, Vec4};
+use ultraviolet::{Mat4, Vec2, Vec3, Vec4};
 
 pub struct StarSystem {
-    pub sun_dir: Vec3,
     pub background_vertices: wgpu::Buffer,
     pub num_background_vertices: u32,
-    pub ambient_light: Vec3,
+    // Kept separate from the rest of the background as the sun can move.
+    pub sun_vertices: wgpu::Buffer,
+    pub irradiance_bind_group: wgpu::BindGroup,
 }
 
 pub struct Constants {
@@ -30,16 +31,29 @@ pub fn run_render_passes(
     star_system: &StarSystem,
     tonemapper: &colstodian::tonemap::BakedLottesTonemapperParams,
     constants: &Constants,
-) {
+) -> resources::RenderStats {
+    let mut stats = resources::RenderStats::default();
+
     let ship_buffer = world.get_resource::<resources::ShipBuffer>().unwrap();
-    let models = world.get_resource::<resources::Models>().unwrap();
+    let cloaked_ship_buffer = world
+        .get_resource::<resources::CloakedShipBuffer>()
+        .unwrap();
+    let models = world.get_resource::<resources::ModelBuffers>().unwrap();
     let perspective_view = world.get_resource::<resources::PerspectiveView>().unwrap();
     let settings = world.get_resource::<resources::Settings>().unwrap();
+    let godrays = world.get_resource::<resources::Godrays>().unwrap();
+    let camera = world.get_resource::<resources::Camera>().unwrap();
+    let orbit = world.get_resource::<resources::Orbit>().unwrap();
+    let clock = world.get_resource::<resources::SimClock>().unwrap();
+    let lighting = &world.get_resource::<resources::Lighting>().unwrap().current;
 
     let laser_buffer = world
-        .get_resource::<resources::GpuBuffer<LaserVertex>>()
+        .get_resource::<resources::GpuBuffer<LaserInstance>>()
         .unwrap();
 
+    build_ship_draws(encoder, pipelines, ship_buffer);
+    build_ship_draws(encoder, pipelines, &cloaked_ship_buffer.0);
+
     let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
         label: Some("main render pass"),
         color_attachments: &[
@@ -78,50 +92,161 @@ pub fn run_render_passes(
         }),
     });
 
-    let (instance_buffer, num_instances, draw_indirect_buffer, draw_indirect_count) =
-        ship_buffer.slice();
+    let camera_position = camera.center + orbit.as_vector();
+
+    let push_constants = PushConstants {
+        perspective_view: perspective_view.perspective_view,
+        light_dir: lighting.sun_dir,
+        padding: 0,
+        ambient_light: lighting.ambient_light,
+        padding_2: 0,
+        camera_position,
+        time: clock.time,
+        second_light_dir: lighting.second_sun_dir,
+        second_light_intensity: lighting.second_sun_intensity(),
+    };
+
+    draw_ships_and_background(
+        &mut render_pass,
+        pipelines,
+        models,
+        ship_buffer,
+        star_system,
+        &push_constants,
+        perspective_view.perspective_view_without_movement,
+        &mut stats,
+    );
 
-    render_pass.set_pipeline(&pipelines.ship);
-    render_pass.set_push_constants(
-        wgpu::ShaderStages::VERTEX | wgpu::ShaderStages::FRAGMENT,
-        0,
-        bytemuck::bytes_of(&PushConstants {
-            perspective_view: perspective_view.perspective_view,
-            light_dir: star_system.sun_dir,
-            padding: 0,
-            ambient_light: star_system.ambient_light,
-        }),
+    // Drawn after the background so that it shows through them.
+    draw_cloaked_ships(
+        &mut render_pass,
+        pipelines,
+        models,
+        &cloaked_ship_buffer.0,
+        star_system,
+        &push_constants,
+        &mut stats,
     );
-    render_pass.set_vertex_buffer(0, models.vertices.slice(..));
-    render_pass.set_vertex_buffer(1, instance_buffer);
-    render_pass.set_index_buffer(models.indices.slice(..), wgpu::IndexFormat::Uint16);
-    render_pass.set_bind_group(0, &models.bind_group, &[]);
 
-    render_pass.multi_draw_indexed_indirect(draw_indirect_buffer, 0, draw_indirect_count);
+    drop(render_pass);
+
+    let laser_push_constants = LaserPushConstants {
+        perspective_view: perspective_view.perspective_view,
+        camera_position,
+        depth_fade_distance: 0.5,
+    };
 
-    let (laser_buffer, num_laser_vertices) = laser_buffer.slice();
+    let (laser_buffer, num_lasers) = laser_buffer.slice();
+
+    if num_lasers > 0 {
+        let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("laser render pass"),
+            color_attachments: &[
+                wgpu::RenderPassColorAttachment {
+                    view: &resizables.hdr_framebuffer,
+                    resolve_target: None,
+                    ops: wgpu::Operations {
+                        load: wgpu::LoadOp::Load,
+                        store: true,
+                    },
+                },
+                wgpu::RenderPassColorAttachment {
+                    view: &resizables.bloom_buffer,
+                    resolve_target: None,
+                    ops: wgpu::Operations {
+                        load: wgpu::LoadOp::Load,
+                        store: true,
+                    },
+                },
+            ],
+            depth_stencil_attachment: None,
+        });
 
-    if num_laser_vertices > 0 {
         render_pass.set_pipeline(&pipelines.lasers);
+        render_pass.set_bind_group(0, &resizables.depth_bind_group, &[]);
         render_pass.set_vertex_buffer(0, laser_buffer);
         render_pass.set_push_constants(
-            wgpu::ShaderStages::VERTEX,
+            wgpu::ShaderStages::VERTEX | wgpu::ShaderStages::FRAGMENT,
             0,
-            bytemuck::bytes_of(&perspective_view.perspective_view),
+            bytemuck::bytes_of(&laser_push_constants),
         );
-        render_pass.draw(0..num_laser_vertices, 0..1);
+        render_pass.draw(0..6, 0..num_lasers);
+        stats.record("lasers", 1, num_lasers);
     }
 
-    render_pass.set_pipeline(&pipelines.background);
-    render_pass.set_vertex_buffer(0, star_system.background_vertices.slice(..));
-    render_pass.set_push_constants(
-        wgpu::ShaderStages::VERTEX,
-        0,
-        bytemuck::bytes_of(&perspective_view.perspective_view_without_movement),
-    );
-    render_pass.draw(0..star_system.num_background_vertices, 0..1);
+    let picture_in_picture = world.get_resource::<resources::PictureInPicture>().unwrap();
+    let cutscene_player = world.get_resource::<resources::CutscenePlayer>().unwrap();
 
-    drop(render_pass);
+    let hud_layout = world.get_resource::<resources::HudLayout>().unwrap();
+
+    let show_picture_in_picture = picture_in_picture.visible
+        && hud_layout.picture_in_picture.visible
+        && !cutscene_player.is_playing();
+
+    if show_picture_in_picture {
+        let targets = &resizables.picture_in_picture;
+
+        let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("picture in picture render pass"),
+            color_attachments: &[
+                wgpu::RenderPassColorAttachment {
+                    view: &targets.hdr_framebuffer,
+                    resolve_target: None,
+                    ops: wgpu::Operations {
+                        load: wgpu::LoadOp::Clear(wgpu::Color::BLACK),
+                        store: true,
+                    },
+                },
+                wgpu::RenderPassColorAttachment {
+                    view: &targets.bloom_buffer,
+                    resolve_target: None,
+                    ops: wgpu::Operations {
+                        load: wgpu::LoadOp::Clear(wgpu::Color::BLACK),
+                        store: false,
+                    },
+                },
+                wgpu::RenderPassColorAttachment {
+                    view: &targets.godray_buffer,
+                    resolve_target: None,
+                    ops: wgpu::Operations {
+                        load: wgpu::LoadOp::Clear(wgpu::Color::BLACK),
+                        store: false,
+                    },
+                },
+            ],
+            depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
+                view: &targets.depth_buffer,
+                depth_ops: Some(wgpu::Operations {
+                    load: wgpu::LoadOp::Clear(1.0),
+                    store: false,
+                }),
+                stencil_ops: None,
+            }),
+        });
+
+        draw_ships_and_background(
+            &mut render_pass,
+            pipelines,
+            models,
+            ship_buffer,
+            star_system,
+            &PushConstants {
+                perspective_view: picture_in_picture.perspective_view.perspective_view,
+                light_dir: lighting.sun_dir,
+                padding: 0,
+                ambient_light: lighting.ambient_light,
+                padding_2: 0,
+                camera_position: picture_in_picture.camera_position,
+                time: clock.time,
+                second_light_dir: lighting.second_sun_dir,
+                second_light_intensity: lighting.second_sun_intensity(),
+            },
+            picture_in_picture
+                .perspective_view
+                .perspective_view_without_movement,
+            &mut stats,
+        );
+    }
 
     if !settings.disable_bloom {
         let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
@@ -149,6 +274,7 @@ pub fn run_render_passes(
             }),
         );
         render_pass.draw(0..3, 0..1);
+        stats.record("bloom blur", 1, 1);
 
         drop(render_pass);
 
@@ -177,11 +303,10 @@ pub fn run_render_passes(
             }),
         );
         render_pass.draw(0..3, 0..1);
+        stats.record("bloom blur", 1, 1);
     }
 
     if !settings.disable_godrays {
-        let uv_space_light_pos = uv_space_light_pos(perspective_view, star_system.sun_dir);
-
         let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
             label: Some("god ray render pass"),
             color_attachments: &[wgpu::RenderPassColorAttachment {
@@ -195,20 +320,56 @@ pub fn run_render_passes(
             depth_stencil_attachment: None,
         });
 
-        render_pass.set_pipeline(&pipelines.godray_blur);
+        if settings.depth_aware_godrays {
+            render_pass.set_pipeline(&pipelines.depth_aware_godray_blur);
+            render_pass.set_bind_group(1, &resizables.depth_bind_group, &[]);
+        } else {
+            render_pass.set_pipeline(&pipelines.godray_blur);
+        }
+
+        let num_samples = 100;
+
         render_pass.set_bind_group(0, &resizables.godray_bind_group, &[]);
-        render_pass.set_push_constants(
-            wgpu::ShaderStages::FRAGMENT,
-            0,
-            bytemuck::bytes_of(&GodraySettings {
-                density_div_num_samples: 1.0 / 100.0,
-                decay: 0.98,
-                weight: 0.01,
-                num_samples: 100,
-                uv_space_light_pos,
-            }),
+
+        // Rays are blurred out from each sun in turn and added together.
+        let lights = [
+            (lighting.sun_dir, 1.0),
+            (lighting.second_sun_dir, lighting.second_sun_intensity()),
+        ];
+
+        for &(light_dir, intensity) in &lights {
+            if intensity == 0.0 {
+                continue;
+            }
+
+            render_pass.set_push_constants(
+                wgpu::ShaderStages::FRAGMENT,
+                0,
+                bytemuck::bytes_of(&GodraySettings {
+                    density_div_num_samples: godrays.density / num_samples as f32,
+                    decay: godrays.decay,
+                    weight: godrays.weight * intensity,
+                    num_samples,
+                    uv_space_light_pos: uv_space_light_pos(perspective_view, light_dir),
+                }),
+            );
+            render_pass.draw(0..3, 0..1);
+            stats.record("godray blur", 1, 1);
+        }
+    }
+
+    if settings.visualise_overdraw {
+        draw_overdraw(
+            encoder,
+            resizables,
+            pipelines,
+            models,
+            &[ship_buffer, &cloaked_ship_buffer.0],
+            (laser_buffer, num_lasers),
+            star_system,
+            &push_constants,
+            &laser_push_constants,
         );
-        render_pass.draw(0..3, 0..1);
     }
 
     let circle_instances_buffer = world
@@ -219,6 +380,15 @@ pub fn run_render_passes(
         .get_resource::<resources::GpuBuffer<RangeInstance>>()
         .unwrap();
 
+    let translucent_buffer = world
+        .get_resource::<resources::GpuBuffer<TranslucentVertex>>()
+        .unwrap();
+
+    let triangles_2d_buffer = &world
+        .get_resource::<resources::Triangles2DBuffer>()
+        .unwrap()
+        .0;
+
     let lines_2d_buffer = world
         .get_resource::<resources::GpuBuffer<Vertex2D>>()
         .unwrap();
@@ -247,14 +417,34 @@ pub fn run_render_passes(
         }),
     });
 
-    render_pass.set_pipeline(&pipelines.tonemapper);
-    render_pass.set_bind_group(0, &resizables.hdr_pass, &[]);
-    render_pass.set_push_constants(
-        wgpu::ShaderStages::FRAGMENT,
-        0,
-        bytemuck::bytes_of(tonemapper),
-    );
+    let dimensions = world.get_resource::<resources::Dimensions>().unwrap();
+
+    // Leave the letterbox bars as the black clear colour.
+    let letterbox_height = (dimensions.height as f32 * cutscene_player.letterbox()) as u32;
+
+    if letterbox_height > 0 {
+        render_pass.set_scissor_rect(
+            0,
+            letterbox_height,
+            dimensions.width,
+            dimensions.height - letterbox_height * 2,
+        );
+    }
+
+    if settings.visualise_overdraw {
+        render_pass.set_pipeline(&pipelines.overdraw_heatmap);
+        render_pass.set_bind_group(0, &resizables.overdraw_pass, &[]);
+    } else {
+        render_pass.set_pipeline(&pipelines.tonemapper);
+        render_pass.set_bind_group(0, &resizables.hdr_pass, &[]);
+        render_pass.set_push_constants(
+            wgpu::ShaderStages::FRAGMENT,
+            0,
+            bytemuck::bytes_of(tonemapper),
+        );
+    }
     render_pass.draw(0..3, 0..1);
+    stats.record("tonemapper", 1, 1);
 
     let (line_buffer, num_line_vertices) = line_buffer.slice();
 
@@ -267,9 +457,12 @@ pub fn run_render_passes(
             bytemuck::bytes_of(&perspective_view.perspective_view),
         );
         render_pass.draw(0..num_line_vertices, 0..1);
+        stats.record("lines", 1, 1);
     }
 
     {
+        let (instance_buffer, num_instances, ..) = ship_buffer.slice();
+
         render_pass.set_pipeline(&pipelines.bounding_boxes);
         render_pass.set_push_constants(
             wgpu::ShaderStages::VERTEX,
@@ -292,6 +485,7 @@ pub fn run_render_passes(
             if num_instances > 0 {
                 if i != ModelId::Explosion as usize {
                     render_pass.draw_indexed(0..24, vertex_offset, offset..offset + num_instances);
+                    stats.record("bounding boxes", 1, num_instances);
                 }
 
                 offset += num_instances;
@@ -312,6 +506,7 @@ pub fn run_render_passes(
         );
         render_pass.set_vertex_buffer(1, circle_instances_buffer);
         render_pass.draw_indexed(0..((64 - 2) * 3), 0, 0..num_circle_instances);
+        stats.record("circles", 1, num_circle_instances);
 
         render_pass.set_pipeline(&pipelines.circle_outline);
         render_pass.set_index_buffer(
@@ -319,6 +514,21 @@ pub fn run_render_passes(
             wgpu::IndexFormat::Uint16,
         );
         render_pass.draw_indexed(0..(64 * 2), 0, 0..num_circle_instances);
+        stats.record("circle outlines", 1, num_circle_instances);
+    }
+
+    let (translucent_buffer, num_translucent_vertices) = translucent_buffer.slice();
+
+    if num_translucent_vertices > 0 {
+        render_pass.set_pipeline(&pipelines.translucent_triangles);
+        render_pass.set_push_constants(
+            wgpu::ShaderStages::VERTEX,
+            0,
+            bytemuck::bytes_of(&perspective_view.perspective_view),
+        );
+        render_pass.set_vertex_buffer(0, translucent_buffer);
+        render_pass.draw(0..num_translucent_vertices, 0..1);
+        stats.record("translucent triangles", 1, 1);
     }
 
     let (range_instances_buffer, num_range_instances) = range_instances_buffer.slice();
@@ -337,6 +547,16 @@ pub fn run_render_passes(
             wgpu::IndexFormat::Uint16,
         );
         render_pass.draw_indexed(0..(64 * 2), 0, 0..num_range_instances);
+        stats.record("range circles", 1, num_range_instances);
+    }
+
+    let (triangles_2d_buffer, num_triangles_2d) = triangles_2d_buffer.slice();
+
+    if num_triangles_2d > 0 {
+        render_pass.set_pipeline(&pipelines.triangles_2d);
+        render_pass.set_vertex_buffer(0, triangles_2d_buffer);
+        render_pass.draw(0..num_triangles_2d, 0..1);
+        stats.record("2d triangles", 1, 1);
     }
 
     let (lines_2d_buffer, num_lines_2d) = lines_2d_buffer.slice();
@@ -345,13 +565,36 @@ pub fn run_render_passes(
         render_pass.set_pipeline(&pipelines.lines_2d);
         render_pass.set_vertex_buffer(0, lines_2d_buffer);
         render_pass.draw(0..num_lines_2d, 0..1);
+        stats.record("2d lines", 1, 1);
+    }
+
+    if show_picture_in_picture {
+        let scale = resources::PictureInPicture::SCALE;
+        let margin = Vec2::broadcast(10.0);
+        let size = dimensions.to_vec() * scale;
+        let position = hud_layout
+            .picture_in_picture
+            .anchor
+            .position(size + margin * 2.0, dimensions.to_vec())
+            + margin;
+
+        // The fullscreen triangle fills the viewport.
+        render_pass.set_viewport(position.x, position.y, size.x, size.y, 0.0, 1.0);
+        render_pass.set_pipeline(&pipelines.tonemapper);
+        render_pass.set_bind_group(0, &resizables.picture_in_picture.hdr_pass, &[]);
+        render_pass.set_push_constants(
+            wgpu::ShaderStages::FRAGMENT,
+            0,
+            bytemuck::bytes_of(tonemapper),
+        );
+        render_pass.draw(0..3, 0..1);
+        stats.record("tonemapper", 1, 1);
     }
 
     drop(render_pass);
 
     let mut staging_belt = wgpu::util::StagingBelt::new(100);
 
-    let dimensions = world.get_resource::<resources::Dimensions>().unwrap();
     let gpu_interface = world.get_resource::<resources::GpuInterface>().unwrap();
     let (width, height) = (dimensions.width, dimensions.height);
 
@@ -369,6 +612,183 @@ pub fn run_render_passes(
             height,
         )
         .unwrap();
+
+    stats
+}
+
+fn build_ship_draws(
+    encoder: &mut wgpu::CommandEncoder,
+    pipelines: &Pipelines,
+    ship_buffer: &resources::ShipBuffer,
+) {
+    const WORKGROUP_SIZE: u32 = 64;
+
+    let (bind_group, num_slots) = match ship_buffer.draw_building() {
+        Some(draw_building) => draw_building,
+        None => return,
+    };
+
+    let workgroups = (num_slots + WORKGROUP_SIZE - 1) / WORKGROUP_SIZE;
+
+    let mut compute_pass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
+        label: Some("build draws compute pass"),
+    });
+
+    compute_pass.set_pipeline(&pipelines.build_draws);
+    compute_pass.set_bind_group(0, bind_group, &[]);
+
+    for &(step, workgroups) in &[
+        (BuildDrawsPushConstants::COUNT_INSTANCES, workgroups),
+        (BuildDrawsPushConstants::SUM_COUNTS, 1),
+        (BuildDrawsPushConstants::SCATTER_INSTANCES, workgroups),
+    ] {
+        compute_pass.set_push_constants(
+            0,
+            bytemuck::bytes_of(&BuildDrawsPushConstants {
+                step,
+                num_slots,
+                num_models: resources::Models::COUNT as u32,
+            }),
+        );
+        compute_pass.dispatch(workgroups, 1, 1);
+    }
+}
+
+fn draw_ships_and_background<'a>(
+    render_pass: &mut wgpu::RenderPass<'a>,
+    pipelines: &'a Pipelines,
+    models: &'a resources::ModelBuffers,
+    ship_buffer: &'a resources::ShipBuffer,
+    star_system: &'a StarSystem,
+    push_constants: &PushConstants,
+    perspective_view_without_movement: Mat4,
+    stats: &mut resources::RenderStats,
+) {
+    let (instance_buffer, num_instances, draw_indirect_buffer, draw_indirect_count) =
+        ship_buffer.slice();
+
+    render_pass.set_pipeline(&pipelines.ship);
+    render_pass.set_push_constants(
+        wgpu::ShaderStages::VERTEX | wgpu::ShaderStages::FRAGMENT,
+        0,
+        bytemuck::bytes_of(push_constants),
+    );
+    render_pass.set_vertex_buffer(0, models.vertices.slice(..));
+    render_pass.set_vertex_buffer(1, instance_buffer);
+    render_pass.set_index_buffer(models.indices.slice(..), wgpu::IndexFormat::Uint16);
+    render_pass.set_bind_group(0, &models.bind_group, &[]);
+    render_pass.set_bind_group(1, &star_system.irradiance_bind_group, &[]);
+
+    render_pass.multi_draw_indexed_indirect(draw_indirect_buffer, 0, draw_indirect_count);
+    stats.record("ships", draw_indirect_count, num_instances.iter().sum());
+
+    render_pass.set_pipeline(&pipelines.background);
+    render_pass.set_vertex_buffer(0, star_system.background_vertices.slice(..));
+    render_pass.set_push_constants(
+        wgpu::ShaderStages::VERTEX,
+        0,
+        bytemuck::bytes_of(&perspective_view_without_movement),
+    );
+    render_pass.draw(0..star_system.num_background_vertices, 0..1);
+    render_pass.set_vertex_buffer(0, star_system.sun_vertices.slice(..));
+    render_pass.draw(0..12, 0..1);
+    stats.record("background", 2, 2);
+}
+
+fn draw_cloaked_ships<'a>(
+    render_pass: &mut wgpu::RenderPass<'a>,
+    pipelines: &'a Pipelines,
+    models: &'a resources::ModelBuffers,
+    ship_buffer: &'a resources::ShipBuffer,
+    star_system: &'a StarSystem,
+    push_constants: &PushConstants,
+    stats: &mut resources::RenderStats,
+) {
+    let (instance_buffer, num_instances, draw_indirect_buffer, draw_indirect_count) =
+        ship_buffer.slice();
+
+    if draw_indirect_count == 0 {
+        return;
+    }
+
+    render_pass.set_pipeline(&pipelines.cloaked_ship);
+    render_pass.set_push_constants(
+        wgpu::ShaderStages::VERTEX | wgpu::ShaderStages::FRAGMENT,
+        0,
+        bytemuck::bytes_of(push_constants),
+    );
+    render_pass.set_vertex_buffer(0, models.vertices.slice(..));
+    render_pass.set_vertex_buffer(1, instance_buffer);
+    render_pass.set_index_buffer(models.indices.slice(..), wgpu::IndexFormat::Uint16);
+    render_pass.set_bind_group(0, &models.bind_group, &[]);
+    render_pass.set_bind_group(1, &star_system.irradiance_bind_group, &[]);
+
+    render_pass.multi_draw_indexed_indirect(draw_indirect_buffer, 0, draw_indirect_count);
+    stats.record(
+        "cloaked ships",
+        draw_indirect_count,
+        num_instances.iter().sum(),
+    );
+}
+
+// Redraws the ships and lasers, counting how many times each pixel is written to. Depth testing
+// is off so that hidden surfaces are counted too.
+#[allow(clippy::too_many_arguments)]
+fn draw_overdraw(
+    encoder: &mut wgpu::CommandEncoder,
+    resizables: &Resizables,
+    pipelines: &Pipelines,
+    models: &resources::ModelBuffers,
+    ship_buffers: &[&resources::ShipBuffer],
+    (laser_buffer, num_lasers): (wgpu::BufferSlice, u32),
+    star_system: &StarSystem,
+    push_constants: &PushConstants,
+    laser_push_constants: &LaserPushConstants,
+) {
+    let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+        label: Some("overdraw render pass"),
+        color_attachments: &[wgpu::RenderPassColorAttachment {
+            view: &resizables.overdraw_buffer,
+            resolve_target: None,
+            ops: wgpu::Operations {
+                load: wgpu::LoadOp::Clear(wgpu::Color::BLACK),
+                store: true,
+            },
+        }],
+        depth_stencil_attachment: None,
+    });
+
+    render_pass.set_pipeline(&pipelines.ship_overdraw);
+    render_pass.set_push_constants(
+        wgpu::ShaderStages::VERTEX | wgpu::ShaderStages::FRAGMENT,
+        0,
+        bytemuck::bytes_of(push_constants),
+    );
+    render_pass.set_vertex_buffer(0, models.vertices.slice(..));
+    render_pass.set_index_buffer(models.indices.slice(..), wgpu::IndexFormat::Uint16);
+    render_pass.set_bind_group(0, &models.bind_group, &[]);
+    render_pass.set_bind_group(1, &star_system.irradiance_bind_group, &[]);
+
+    for ship_buffer in ship_buffers {
+        let (instance_buffer, _, draw_indirect_buffer, draw_indirect_count) = ship_buffer.slice();
+
+        if draw_indirect_count > 0 {
+            render_pass.set_vertex_buffer(1, instance_buffer);
+            render_pass.multi_draw_indexed_indirect(draw_indirect_buffer, 0, draw_indirect_count);
+        }
+    }
+
+    if num_lasers > 0 {
+        render_pass.set_pipeline(&pipelines.laser_overdraw);
+        render_pass.set_bind_group(0, &resizables.depth_bind_group, &[]);
+        render_pass.set_vertex_buffer(0, laser_buffer);
+        render_pass.set_push_constants(
+            wgpu::ShaderStages::VERTEX | wgpu::ShaderStages::FRAGMENT,
+            0,
+            bytemuck::bytes_of(laser_push_constants),
+        );
+        render_pass.draw(0..6, 0..num_lasers);
+    }
 }
 
 fn uv_space_light_pos(perspective_view: &resources::PerspectiveView, sun_dir: Vec3) -> Vec2 {
@@ -0,0 +1,185 @@
+use crate::SelectedFriendly;
+use bevy_ecs::prelude::*;
+use components_and_resources::components::*;
+use components_and_resources::resources::*;
+use ultraviolet::Vec2;
+
+// How many pixels a second the cursor moves when nudged with the keyboard.
+const CURSOR_SPEED: f32 = 500.0;
+// How much the camera zooms each tick that a zoom key is held, in mouse wheel lines.
+const ZOOM_SPEED: f32 = 0.1;
+
+pub fn read_out_game_state(
+    mut screen_reader: ResMut<ScreenReader>,
+    keyboard_state: Res<KeyboardState>,
+    notifications: Res<Notifications>,
+    minerals: Res<PerSide<GlobalMinerals>>,
+    players: Res<Players>,
+    objective_score: Res<ObjectiveScore>,
+    selected: Query<&ModelId, SelectedFriendly>,
+) {
+    if !screen_reader.is_enabled() {
+        return;
+    }
+
+    let mut counts = [0; Models::COUNT];
+
+    selected.for_each(|&model_id| {
+        counts[model_id as usize] += 1;
+    });
+
+    let selection = ScreenReader::selection_summary(&counts);
+    let minerals = ScreenReader::minerals_summary(minerals[players.local_side()].0);
+    let objectives = ScreenReader::objectives_summary(&objective_score);
+
+    // Reads everything out again, even if it hasn't changed.
+    if keyboard_state.read_status.0 {
+        screen_reader.announce(selection.clone());
+        screen_reader.announce(minerals.clone());
+        screen_reader.announce(objectives.clone());
+    }
+
+    screen_reader.report(Topic::Selection, selection);
+    screen_reader.report(Topic::Minerals, minerals);
+    screen_reader.report(Topic::Objectives, objectives);
+
+    let unread = screen_reader.unread_notifications(notifications.total_pushed());
+
+    for notification in notifications.latest(unread) {
+        screen_reader.announce(notification);
+    }
+
+    if let Err(error) = screen_reader.flush() {
+        log::warn!("Reading out the game state failed: {}", error);
+    }
+}
+
+// Lets the numpad stand in for the mouse. Runs after the mouse state has been updated for the
+// tick, so that presses are seen by the next tick's systems just like real mouse buttons.
+pub fn control_cursor_with_keys(
+    keyboard_state: Res<KeyboardState>,
+    mut mouse_state: ResMut<MouseState>,
+    mut orbit: ResMut<Orbit>,
+    mut mouse_mode: ResMut<MouseMode>,
+    dimensions: Res<Dimensions>,
+    dpi_factor: Res<DpiFactor>,
+    // Whether each of the left, right and middle keys were held last tick.
+    mut buttons_held: Local<[bool; 3]>,
+) {
+    let buttons = [
+        keyboard_state.left_click,
+        keyboard_state.right_click,
+        keyboard_state.middle_click,
+    ];
+
+    for (i, &pressed) in buttons.iter().enumerate() {
+        if pressed == buttons_held[i] {
+            continue;
+        }
+
+        buttons_held[i] = pressed;
+
+        let position = mouse_state.position;
+
+        match i {
+            0 => mouse_state.left_state.handle(position, pressed),
+            1 => mouse_state.right_state.handle(position, pressed),
+            _ => mouse_state.middle_state.handle(position, pressed),
+        }
+    }
+
+    let direction = Vec2::new(
+        keyboard_state.cursor_right as u8 as f32 - keyboard_state.cursor_left as u8 as f32,
+        keyboard_state.cursor_down as u8 as f32 - keyboard_state.cursor_up as u8 as f32,
+    );
+
+    if direction != Vec2::zero() {
+        let delta = direction * CURSOR_SPEED * dpi_factor.0 * SimClock::TICK;
+
+        // Same as moving the mouse: the middle button orbits the camera and shift moves the
+        // order plane up and down.
+        if mouse_state.middle_state.is_being_dragged().is_some() {
+            orbit.rotate(delta);
+        } else {
+            if keyboard_state.shift {
+                if let MouseMode::Movement { point_on_plane, .. } = &mut *mouse_mode {
+                    point_on_plane.y -= delta.y / 10.0;
+                }
+            }
+
+            let max = dimensions.to_vec() - Vec2::one();
+            mouse_state.position = (mouse_state.position + delta).clamped(Vec2::zero(), max);
+            mouse_state.moved_by_keyboard = true;
+        }
+    }
+
+    if keyboard_state.zoom_in {
+        orbit.zoom(-ZOOM_SPEED);
+    }
+
+    if keyboard_state.zoom_out {
+        orbit.zoom(ZOOM_SPEED);
+    }
+}
+
+// Ctrl+Tab moves the cursor onto each enemy ship on screen in turn, so that they can be targeted
+// without the mouse. Shift goes backwards.
+pub fn cycle_targets(
+    keyboard_state: Res<KeyboardState>,
+    mut target_cycle: ResMut<TargetCycle>,
+    mut mouse_state: ResMut<MouseState>,
+    mut screen_reader: ResMut<ScreenReader>,
+    perspective_view: Res<PerspectiveView>,
+    dimensions: Res<Dimensions>,
+    enemies: Query<
+        (Entity, &Position, &ModelId),
+        (With<Theirs>, With<Selectable>, Without<Cloaked>),
+    >,
+) {
+    if !(keyboard_state.cycle_fleets.0 && keyboard_state.control) {
+        return;
+    }
+
+    let screen = dimensions.to_vec();
+
+    let mut targets: Vec<_> = enemies
+        .iter()
+        .filter_map(|(entity, position, &model_id)| {
+            let pixel = perspective_view.project_to_pixels(position.0, &dimensions)?;
+            let on_screen =
+                pixel.x >= 0.0 && pixel.y >= 0.0 && pixel.x < screen.x && pixel.y < screen.y;
+            if on_screen {
+                Some((entity, pixel, model_id))
+            } else {
+                None
+            }
+        })
+        .collect();
+
+    if targets.is_empty() {
+        target_cycle.0 = None;
+        screen_reader.announce("No enemies on screen");
+        return;
+    }
+
+    targets.sort_unstable_by_key(|&(entity, ..)| entity);
+
+    let count = targets.len();
+    let current = target_cycle
+        .0
+        .and_then(|target| targets.iter().position(|&(entity, ..)| entity == target));
+
+    let index = match (current, keyboard_state.shift) {
+        (None, false) => 0,
+        (None, true) => count - 1,
+        (Some(index), false) => (index + 1) % count,
+        (Some(index), true) => (index + count - 1) % count,
+    };
+
+    let (entity, pixel, model_id) = targets[index];
+
+    target_cycle.0 = Some(entity);
+    mouse_state.position = pixel;
+    mouse_state.moved_by_keyboard = true;
+    screen_reader.announce(ScreenReader::target_summary(model_id, index, count));
+}
@@ -0,0 +1,58 @@
+use bevy_ecs::prelude::*;
+use components_and_resources::components::*;
+use components_and_resources::map_generation;
+use components_and_resources::resources::*;
+
+pub fn drift(
+    mut query: Query<(Entity, &Drifting, &mut Position)>,
+    clock: Res<SimClock>,
+    mut commands: Commands,
+) {
+    query.for_each_mut(|(entity, drifting, mut position)| {
+        if drifting.until.finished(&clock) {
+            commands.entity(entity).remove::<Drifting>();
+        } else {
+            position.0 += drifting.velocity * clock.delta;
+        }
+    });
+}
+
+// Only the belt's asteroids orbit, so comets and derelicts aren't counted.
+pub fn respawn_asteroids(
+    asteroids: Query<Option<&CanBeMined>, With<Orbiting>>,
+    clock: Res<SimClock>,
+    real_time: Res<RealTime>,
+    mut respawns: ResMut<AsteroidRespawns>,
+    mut rng: ResMut<SmallRng>,
+    mut notifications: ResMut<Notifications>,
+    mut commands: Commands,
+) {
+    if !respawns.next_check.finished(&clock) {
+        return;
+    }
+
+    respawns.next_check = clock.timer(AsteroidRespawns::INTERVAL);
+
+    let total = asteroids.iter().count();
+    let minable = asteroids
+        .iter()
+        .filter(|can_be_mined| can_be_mined.is_some())
+        .count();
+
+    if !AsteroidRespawns::belt_depleted(minable, total) {
+        return;
+    }
+
+    let (positions, drifting) = map_generation::drifting_asteroid_cluster(&mut *rng, &clock);
+
+    for position in positions {
+        commands
+            .spawn_bundle(map_generation::asteroid_components(position, &mut *rng))
+            .insert(drifting.clone());
+    }
+
+    notifications.push(
+        "A new asteroid cluster is drifting in from the edge of the map",
+        real_time.0,
+    );
+}
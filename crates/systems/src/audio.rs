@@ -0,0 +1,52 @@
+use bevy_ecs::prelude::*;
+use components_and_resources::components::*;
+use components_and_resources::resources::*;
+use ultraviolet::Vec3;
+
+pub fn update_engine_hum(
+    ships: Query<(&Position, &ShipType)>,
+    tlas: Res<TopLevelAccelerationStructure>,
+    camera: Res<Camera>,
+    orbit: Res<Orbit>,
+    mut engine_hum: ResMut<EngineHum>,
+) {
+    // Zooming out lets you hear more of the fleet.
+    let radius = orbit.as_vector().mag() * 2.0 + 25.0;
+
+    let bbox = BoundingBox::new(-Vec3::broadcast(radius), Vec3::broadcast(radius)) + camera.center;
+
+    let mut find_stack = Vec::with_capacity(10);
+
+    let mut total_mass = 0.0;
+    let mut weighted_build_time = 0.0;
+
+    for entity in tlas.find(
+        |bounding_box| bbox.intersects(bounding_box),
+        &mut find_stack,
+    ) {
+        if let Ok((position, ship_type)) = ships.get(*entity) {
+            let falloff = 1.0 - ((position.0 - camera.center).mag() / radius).min(1.0);
+
+            // Bigger ships take longer to build, so use that as a stand-in for their mass.
+            let mass = ship_type.build_time() * falloff;
+
+            total_mass += mass;
+            weighted_build_time += ship_type.build_time() * mass;
+        }
+    }
+
+    let target_volume = 1.0 - (-total_mass / 150.0).exp();
+
+    let target_pitch = if total_mass > 0.0 {
+        let average_build_time = weighted_build_time / total_mass;
+        let carrier_build_time = ShipType::Carrier.build_time();
+        1.5 - average_build_time / carrier_build_time
+    } else {
+        1.0
+    };
+
+    // Ease towards the targets so that panning the camera doesn't cause jumps.
+    let t = (SimClock::TICK * 2.0).min(1.0);
+    engine_hum.volume += (target_volume - engine_hum.volume) * t;
+    engine_hum.pitch += (target_pitch - engine_hum.pitch) * t;
+}
@@ -0,0 +1,199 @@
+use crate::find_functions::find_next_carrier;
+use bevy_ecs::prelude::*;
+use components_and_resources::components::*;
+use components_and_resources::resources::*;
+
+// Aggressive carriers send out their repaired fighters as soon as an enemy comes within the
+// fighters' agro range.
+pub fn launch_fighters<SideA, SideB>(
+    mut carriers: Query<(Entity, &Position, &CarrierDoctrine, &mut Carrying), With<SideA>>,
+    fighters: Query<
+        (
+            &Health,
+            &AgroRange,
+            Option<&Autonomy>,
+            Option<&RetreatPolicy>,
+        ),
+        (With<CanAttack>, Without<Drone>),
+    >,
+    mut movement: Query<(&mut Velocity, &mut CommandQueue)>,
+    enemies: Query<
+        &Position,
+        (
+            With<SideB>,
+            Without<Hidden>,
+            Without<Cloaked>,
+            Without<Missile>,
+        ),
+    >,
+    clock: Res<SimClock>,
+    mut rng: ResMut<SmallRng>,
+    mut commands: Commands,
+) where
+    SideA: Send + Sync + 'static,
+    SideB: Send + Sync + 'static,
+{
+    carriers.for_each_mut(|(carrier, position, doctrine, mut carrying)| {
+        if *doctrine != CarrierDoctrine::Aggressive {
+            return;
+        }
+
+        let mut range: f32 = 0.0;
+
+        let ready: Vec<Entity> = carrying
+            .iter()
+            .filter(|&entity| match fighters.get(entity) {
+                // Retreating ships wait until they're fully repaired.
+                Ok((health, agro_range, autonomy, retreat_policy))
+                    if CarrierDoctrine::ready_to_launch(health)
+                        && Autonomy::get(autonomy).acts_alone()
+                        && retreat_policy
+                            .map_or(true, |policy| policy.retreated_from.is_none()) =>
+                {
+                    range = range.max(agro_range.0);
+                    true
+                }
+                _ => false,
+            })
+            .collect();
+
+        if ready.is_empty() {
+            return;
+        }
+
+        let enemy_in_range = enemies
+            .iter()
+            .any(|enemy| (enemy.0 - position.0).mag_sq() < range.powi(2));
+
+        if !enemy_in_range {
+            return;
+        }
+
+        for entity in ready {
+            carrying.remove(entity);
+
+            crate::unload_single(
+                position.0,
+                entity,
+                &mut rng,
+                &clock,
+                movement.get_mut(entity).ok(),
+                &mut commands,
+                false,
+            );
+        }
+
+        commands.entity(carrier).remove::<CarrierFull>();
+    });
+}
+
+// Badly damaged fighters fly back to the closest carrier with room for them.
+pub fn recall_damaged_fighters<Side: Send + Sync + 'static>(
+    mut fighters: Query<
+        (&Position, &Health, &mut CommandQueue, Option<&Autonomy>),
+        (
+            With<Side>,
+            With<CanAttack>,
+            With<CanBeCarried>,
+            Without<Drone>,
+            // Ships that retreat on their own terms are left to it.
+            Without<RetreatPolicy>,
+        ),
+    >,
+    carriers: Query<(Entity, &Position), (With<Side>, With<CarrierDoctrine>, Without<CarrierFull>)>,
+) {
+    fighters.for_each_mut(|(position, health, mut queue, autonomy)| {
+        if !Autonomy::get(autonomy).acts_alone() {
+            return;
+        }
+
+        if CarrierDoctrine::needs_repair(health) && CarrierDoctrine::can_recall(&queue) {
+            let mut docking = CommandQueue::default();
+            find_next_carrier(position.0, &mut docking, carriers.iter());
+
+            // Ships only dock for good when there's nothing else in their queue, but keep their
+            // orders if there's nowhere to dock.
+            if !docking.0.is_empty() {
+                *queue = docking;
+            }
+        }
+    });
+}
+
+pub fn repair_carried_ships(
+    carriers: Query<&Carrying>,
+    mut health: Query<&mut Health>,
+    clock: Res<SimClock>,
+) {
+    carriers.for_each(|carrying| {
+        for entity in carrying.iter() {
+            if let Ok(mut health) = health.get_mut(entity) {
+                health.current =
+                    (health.current + Carrying::REPAIR_RATE * clock.delta).min(health.max);
+            }
+        }
+    });
+}
+
+// Ships with high autonomy go back to the closest carrier once they've run out of orders and
+// there's nothing left nearby to fight or mine, but not straight after being unloaded.
+pub fn dock_idle_ships<SideA, SideB>(
+    mut ships: Query<
+        (
+            &Position,
+            &Autonomy,
+            &mut CommandQueue,
+            Option<&AgroRange>,
+            Option<&CanMine>,
+        ),
+        (
+            With<SideA>,
+            With<CanBeCarried>,
+            Without<Unloading>,
+            Without<Launched>,
+            Without<HoldPosition>,
+            Without<Escorting>,
+            Without<FormationSlot>,
+            Without<Drone>,
+        ),
+    >,
+    carriers: Query<(Entity, &Position), (With<SideA>, With<Carrying>, Without<CarrierFull>)>,
+    enemies: Query<
+        &Position,
+        (
+            With<SideB>,
+            Without<Hidden>,
+            Without<Cloaked>,
+            Without<Missile>,
+        ),
+    >,
+    asteroids: Query<Entity, With<CanBeMined>>,
+) where
+    SideA: Send + Sync + 'static,
+    SideB: Send + Sync + 'static,
+{
+    let asteroids_left = asteroids.iter().next().is_some();
+
+    ships.for_each_mut(|(position, &autonomy, mut queue, agro_range, can_mine)| {
+        if autonomy != Autonomy::High || !queue.0.is_empty() {
+            return;
+        }
+
+        if can_mine.is_some() && asteroids_left {
+            return;
+        }
+
+        if let Some(agro_range) = agro_range {
+            let range_sq = autonomy.pursuit_range(agro_range.0).powi(2);
+
+            if enemies
+                .iter()
+                .any(|enemy| (enemy.0 - position.0).mag_sq() < range_sq)
+            {
+                return;
+            }
+        }
+
+        find_next_carrier(position.0, &mut queue, carriers.iter());
+    });
+}
@@ -1,126 +1,340 @@
-use super::{get_scale, spawn_explosion};
+use super::{get_scale, spawn_explosion, spawn_impact_effect};
 use bevy_ecs::prelude::*;
 use components_and_resources::components::*;
 use components_and_resources::resources::*;
-use components_and_resources::utils::compare_floats;
+use components_and_resources::utils::{compare_floats, uniform_sphere_distribution};
+use ultraviolet::Vec3;
 
 #[profiling::function]
 pub fn collide_projectiles<Side>(
-    projectiles: Query<(Entity, &Projectile), With<Side>>,
-    ships: Query<(&Position, &RotationMatrix, &ModelId, Option<&Scale>), Without<Side>>,
+    projectiles: Query<
+        (
+            Entity,
+            &Projectile,
+            Option<&DamageType>,
+            Option<&ProjectileDamage>,
+            Option<&FiredBy>,
+        ),
+        With<Side>,
+    >,
+    ships: Query<
+        (&Position, &RotationMatrix, &ModelId, Option<&Scale>),
+        (Without<Side>, Without<Cloaked>),
+    >,
     models: Res<Models>,
-    delta_time: Res<DeltaTime>,
-    total_time: Res<TotalTime>,
+    clock: Res<SimClock>,
     commands: Commands,
-    health: Query<&mut Health>,
+    health: Query<(&mut Health, Option<&mut Shield>, Option<&Armor>)>,
+    veterancy: Query<&mut Veterancy>,
     task_pool: Res<bevy_tasks::TaskPool>,
     rng: ResMut<SmallRng>,
     bvh: Res<TopLevelAccelerationStructure>,
+    damage_events: ResMut<DamageEvents>,
 ) where
     Side: Send + Sync + 'static,
 {
-    let on_hit_resources = parking_lot::Mutex::new((commands, health, rng));
+    let on_hit_resources =
+        parking_lot::Mutex::new((commands, health, veterancy, rng, damage_events));
 
-    projectiles.par_for_each(&task_pool, 16, |(entity, projectile)| {
-        let bounding_box = projectile.bounding_box(delta_time.0);
+    projectiles.par_for_each(
+        &task_pool,
+        16,
+        |(entity, projectile, damage_type, damage, fired_by)| {
+            let bounding_box = projectile.bounding_box(clock.delta);
 
-        let mut find_stack = Vec::with_capacity(10);
+            let mut find_stack = Vec::with_capacity(10);
 
-        let first_hit = bvh
-            .find(
-                |ship_bounding_box| bounding_box.intersects(ship_bounding_box),
-                &mut find_stack,
-            )
-            .filter_map(|&entity| {
-                ships
-                    .get(entity)
-                    .ok()
-                    .map(|components| (entity, components))
-            })
-            .flat_map(|(ship_entity, (position, rotation, model_id, scale))| {
-                let scale = get_scale(scale);
-
-                let ray = projectile
-                    .as_limited_ray(delta_time.0)
-                    .centered_around_transform(position.0, rotation.reversed, scale);
-
-                models
-                    .get(*model_id)
-                    .acceleration_tree
-                    .find_with_owned_stack(
-                        move |bbox| ray.bounding_box_intersection(bbox),
-                        Vec::with_capacity(10),
-                    )
-                    .filter_map(move |triangle| ray.triangle_intersection(triangle))
-                    .map(move |scaled_t| (ship_entity, scaled_t))
-            })
-            .max_by(|&(_, a, ..), &(_, b, ..)| compare_floats(a, b));
+            let first_hit = bvh
+                .find(
+                    |ship_bounding_box| bounding_box.intersects(ship_bounding_box),
+                    &mut find_stack,
+                )
+                .filter_map(|&entity| {
+                    ships
+                        .get(entity)
+                        .ok()
+                        .map(|components| (entity, components))
+                })
+                .flat_map(|(ship_entity, (position, rotation, model_id, scale))| {
+                    let scale = get_scale(scale);
+
+                    let ray = projectile
+                        .as_limited_ray(clock.delta)
+                        .centered_around_transform(position.0, rotation.reversed, scale);
 
-        if let Some((ship_entity, t)) = first_hit {
-            let position = projectile.get_intersection_point(t);
+                    models
+                        .get(*model_id)
+                        .acceleration_tree
+                        .find_with_owned_stack(
+                            move |bbox| ray.bounding_box_intersection(bbox),
+                            Vec::with_capacity(10),
+                        )
+                        .filter_map(move |triangle| {
+                            ray.triangle_intersection(triangle)
+                                .map(|scaled_t| (triangle, scaled_t))
+                        })
+                        .map(move |(triangle, scaled_t)| {
+                            (ship_entity, scaled_t, rotation.matrix * triangle.normal())
+                        })
+                })
+                .max_by(|&(_, a, ..), &(_, b, ..)| compare_floats(a, b));
+
+            if let Some((ship_entity, t, normal)) = first_hit {
+                let position = projectile.get_intersection_point(t);
+
+                // Make sure the normal faces back towards where the projectile came from.
+                let normal = if normal.dot(projectile.direction()) > 0.0 {
+                    -normal
+                } else {
+                    normal
+                };
+
+                let mut lock_guard = on_hit_resources.lock();
+                let (
+                    ref mut commands,
+                    ref mut health,
+                    ref mut veterancy,
+                    ref mut rng,
+                    ref mut damage_events,
+                ) = &mut *lock_guard;
+
+                commands.entity(entity).despawn();
+
+                let mut hit_shield = false;
+                let mut killed = false;
+
+                if let Ok((mut health, shield, armor)) = health.get_mut(ship_entity) {
+                    let damage = damage.map_or(10.0, |damage| damage.0);
+
+                    let damage = match shield {
+                        Some(mut shield) => {
+                            hit_shield = shield.current > 0.0;
+                            shield.absorb(damage)
+                        }
+                        None => damage,
+                    };
+
+                    let damage_type = damage_type.copied().unwrap_or_default();
+
+                    let was_alive = health.current > 0.0;
+                    health.current -= damage * Armor::damage_multiplier(armor, damage_type);
+                    killed = was_alive && health.current <= 0.0;
+
+                    damage_events.0.push(DamageEvent {
+                        target: ship_entity,
+                        position,
+                    });
+                }
 
-            let mut lock_guard = on_hit_resources.lock();
-            let (ref mut commands, ref mut health, ref mut rng) = &mut *lock_guard;
+                if let (true, Some(fired_by)) = (killed, fired_by) {
+                    let promoted = veterancy
+                        .get_mut(fired_by.0)
+                        .map_or(false, |mut veterancy| veterancy.record_kill());
 
-            commands.entity(entity).despawn();
-            if let Ok(mut health) = health.get_mut(ship_entity) {
-                health.current -= 10.0;
+                    if promoted {
+                        if let Ok((mut health, ..)) = health.get_mut(fired_by.0) {
+                            let bonus = health.max * Veterancy::HEALTH_BONUS_PER_RANK;
+                            health.max += bonus;
+                            health.current += bonus;
+                        }
+                    }
+                }
+
+                commands.entity(ship_entity).insert(LastHit(clock.time));
+
+                if !hit_shield {
+                    spawn_explosion(position, &clock, &mut *rng, commands);
+                }
+
+                spawn_impact_effect(position, normal, hit_shield, &clock, &mut *rng, commands);
             }
-            spawn_explosion(position, total_time.0, &mut *rng, commands);
-        }
-    });
+        },
+    );
 }
 
 #[profiling::function]
 pub fn choose_enemy_target<SideA, SideB>(
     mut query: Query<
-        (Entity, &Position, &AgroRange, &mut CommandQueue),
+        (
+            Entity,
+            &Position,
+            &AgroRange,
+            &mut CommandQueue,
+            Option<&LastHit>,
+            Option<&RulesOfEngagementOverride>,
+            Option<&Friendly>,
+            Option<&Autonomy>,
+            Option<&TargetingStance>,
+            Option<&Stance>,
+            Option<&Leash>,
+        ),
         (With<SideA>, With<CanAttack>),
     >,
-    candidates: Query<(Entity, &Position), With<SideB>>,
+    candidates: Query<
+        (&Position, Option<&Health>, Option<&ModelId>),
+        (
+            With<SideB>,
+            Without<Hidden>,
+            Without<Cloaked>,
+            Without<Missile>,
+        ),
+    >,
+    allies_under_attack: Query<&Evading, With<SideA>>,
     commands: Commands,
     task_pool: Res<bevy_tasks::TaskPool>,
+    global_rules: Res<PerSide<GlobalRulesOfEngagement>>,
+    clock: Res<SimClock>,
+    tlas: Res<TopLevelAccelerationStructure>,
 ) where
     SideA: Send + Sync + 'static,
     SideB: Send + Sync + 'static,
 {
     let commands = parking_lot::Mutex::new(commands);
 
-    query.par_for_each_mut(&task_pool, 8, |(entity, pos, agro_range, mut queue)| {
-        match queue.0.front() {
-            None
-            | Some(Command::MoveTo {
-                ty: MoveType::Attack,
-                ..
-            }) => {}
-            _ => return,
-        };
+    let attacking_allies: std::collections::HashSet<Entity> = allies_under_attack
+        .iter()
+        .map(|evading| evading.0)
+        .collect();
 
-        let agro_range_sq = agro_range.0 * agro_range.0;
+    query.par_for_each_mut(
+        &task_pool,
+        8,
+        |(
+            entity,
+            pos,
+            agro_range,
+            mut queue,
+            last_hit,
+            rules_override,
+            friendly,
+            autonomy,
+            targeting_stance,
+            stance,
+            leash,
+        )| {
+            let stance = Stance::get(stance);
 
-        let target = candidates
-            .iter()
-            .filter_map(|(target_entity, target_pos)| {
-                let dist_sq = (target_pos.0 - pos.0).mag_sq();
+            if !stance.picks_targets() {
+                return;
+            }
 
-                if dist_sq < agro_range_sq {
-                    Some((target_entity, dist_sq))
-                } else {
+            let rules = rules_of_engagement(&global_rules, rules_override, friendly);
+
+            let recently_hit = last_hit.map_or(false, |last_hit| {
+                clock.time - last_hit.0 < RulesOfEngagement::RETURN_FIRE_DURATION
+            });
+
+            let autonomy = Autonomy::get(autonomy);
+
+            match (queue.0.front(), rules) {
+                (_, RulesOfEngagement::HoldFire) => return,
+                (None, _) if !autonomy.acts_alone() => return,
+                (None, RulesOfEngagement::ReturnFire) if !recently_hit => return,
+                (
                     None
+                    | Some(Command::MoveTo {
+                        ty: MoveType::Attack,
+                        ..
+                    }),
+                    _,
+                ) => {}
+                _ => return,
+            };
+
+            let range = autonomy.pursuit_range(agro_range.0);
+            let range_sq = range.powi(2);
+            let targeting_stance = TargetingStance::get(targeting_stance);
+
+            let anchor = leash.map_or(pos.0, |leash| leash.0);
+            let within_leash = |target_pos: Vec3| {
+                stance != Stance::Defensive || Stance::within_leash(anchor, target_pos)
+            };
+
+            let mut find_stack = Vec::with_capacity(10);
+            let agro_box =
+                BoundingBox::new(-Vec3::broadcast(range), Vec3::broadcast(range)) + pos.0;
+
+            let target = tlas
+                .find(
+                    |bounding_box| agro_box.intersects(bounding_box),
+                    &mut find_stack,
+                )
+                .filter_map(|&target_entity| {
+                    let (target_pos, health, model_id) = candidates.get(target_entity).ok()?;
+                    let dist_sq = (target_pos.0 - pos.0).mag_sq();
+
+                    if dist_sq >= range_sq || !within_leash(target_pos.0) {
+                        return None;
+                    }
+
+                    let info = TargetInfo {
+                        dist_sq,
+                        health_fraction: health.map_or(1.0, |health| health.current / health.max),
+                        is_capital: model_id.map_or(false, |model_id| model_id.is_capital()),
+                        attacking_ally: attacking_allies.contains(&target_entity),
+                    };
+
+                    Some((target_entity, targeting_stance.score(&info, range_sq)))
+                })
+                .min_by(|&(_, a), &(_, b)| compare_floats(a, b));
+
+            if let Some((target_entity, _)) = target {
+                queue.0.push_front(Command::Interact {
+                    target: target_entity,
+                    ty: InteractionType::Attack,
+                    range_sq: 0.0,
+                });
+
+                let mut commands = commands.lock();
+                commands.entity(target_entity).insert(Evading(entity));
+
+                if stance == Stance::Defensive && leash.is_none() {
+                    commands.entity(entity).insert(Leash(anchor));
                 }
-            })
-            .min_by(|&(_, a), &(_, b)| compare_floats(a, b));
+            }
+        },
+    );
+}
+
+// Defensive ships let targets go once they've left the area, and then head back to where they
+// started.
+pub fn pull_back_leashed_ships(
+    mut ships: Query<(Entity, &Position, &Leash, &mut CommandQueue)>,
+    positions: Query<&Position>,
+    mut commands: Commands,
+) {
+    ships.for_each_mut(|(entity, position, leash, mut queue)| {
+        let anchor = leash.0;
 
-        if let Some((target_entity, _)) = target {
-            queue.0.push_front(Command::Interact {
-                target: target_entity,
+        match queue.0.front() {
+            Some(&Command::Interact {
+                target,
                 ty: InteractionType::Attack,
-                range_sq: 0.0,
+                ..
+            }) => {
+                let target_left = positions.get(target).map_or(false, |target_pos| {
+                    !Stance::within_leash(anchor, target_pos.0)
+                });
+
+                if target_left {
+                    queue.0.pop_front();
+                }
+            }
+            Some(&Command::MoveTo { point, .. }) if point == anchor => {}
+            None => {}
+            // Anything else was queued up by the player.
+            Some(_) => {
+                commands.entity(entity).remove::<Leash>();
+                return;
+            }
+        }
+
+        if queue.0.is_empty() && (position.0 - anchor).mag_sq() > Leash::RETURN_DISTANCE.powi(2) {
+            queue.0.push_back(Command::MoveTo {
+                point: anchor,
+                ty: MoveType::Attack,
+                speed_cap: None,
             });
-            commands
-                .lock()
-                .entity(target_entity)
-                .insert(Evading(entity));
         }
     });
 }
@@ -128,54 +342,1069 @@ pub fn choose_enemy_target<SideA, SideB>(
 pub fn spawn_projectile_from_ships<Side: Send + Sync + Default + 'static>(
     mut query: Query<
         (
+            Entity,
             &Position,
             &Velocity,
-            &mut RayCooldown,
+            &mut Weapons,
             &CommandQueue,
-            &AgroRange,
+            Option<&RulesOfEngagementOverride>,
+            Option<&Friendly>,
+            Option<&HoldPosition>,
+            Option<&Cloaked>,
+            Option<&Veterancy>,
+            Option<&CrewModifiers>,
+            Option<&mut Heat>,
+            Option<&DamageBoost>,
         ),
         With<Side>,
     >,
     positions: Query<&Position>,
-    delta_time: Res<DeltaTime>,
-    total_time: Res<TotalTime>,
+    clock: Res<SimClock>,
     mut commands: Commands,
+    global_rules: Res<PerSide<GlobalRulesOfEngagement>>,
+    tech_tree: Res<PerSide<TechTree>>,
 ) {
-    query.for_each_mut(|(pos, vel, mut ray_cooldown, queue, agro_range)| {
-        ray_cooldown.0 = (ray_cooldown.0 - delta_time.0).max(0.0);
+    query.for_each_mut(
+        |(
+            entity,
+            pos,
+            vel,
+            mut weapons,
+            queue,
+            rules_override,
+            friendly,
+            hold_position,
+            cloaked,
+            veterancy,
+            crew_modifiers,
+            mut heat,
+            damage_boost,
+        )| {
+            for weapon in &mut weapons.0 {
+                weapon.cooldown.tick(&clock);
+            }
 
-        if ray_cooldown.0 != 0.0 {
-            return;
-        }
+            if rules_of_engagement(&global_rules, rules_override, friendly)
+                == RulesOfEngagement::HoldFire
+            {
+                return;
+            }
 
-        let attack_target = match queue.0.front() {
+            let attack_target = match queue.0.front() {
+                Some(Command::Interact {
+                    ty: InteractionType::Attack,
+                    target,
+                    ..
+                }) => target,
+                _ => return,
+            };
+
+            let target_pos = match positions.get(*attack_target) {
+                Ok(target_pos) => target_pos.0,
+                _ => return,
+            };
+
+            let distance_sq = (pos.0 - target_pos).mag_sq();
+
+            // Ships holding position aren't flying towards their target, so aim at it directly.
+            let direction =
+                if hold_position.is_some() || veterancy.map_or(false, Veterancy::aims_directly) {
+                    target_pos - pos.0
+                } else {
+                    vel.0
+                };
+
+            let ray = Ray::new(pos.0, direction.normalized());
+
+            let damage_multiplier = research_multiplier(&tech_tree, friendly)
+                * veterancy.map_or(1.0, Veterancy::damage_multiplier)
+                * DamageBoost::damage_multiplier(damage_boost);
+
+            let mut fired = false;
+
+            for weapon in &mut weapons.0 {
+                if !weapon.cooldown.is_ready()
+                    || distance_sq > weapon.range * weapon.range
+                    || !Heat::can_fire(heat.as_deref())
+                {
+                    continue;
+                }
+
+                if let Some(heat) = &mut heat {
+                    heat.add(Heat::PER_SHOT);
+                }
+
+                weapon
+                    .cooldown
+                    .start(weapon.reload_time * CrewModifiers::reload_multiplier(crew_modifiers));
+                fired = true;
+
+                commands.spawn_bundle((
+                    Projectile::new(&ray, weapon.projectile_speed),
+                    AliveUntil(clock.timer(Weapon::PROJECTILE_LIFETIME)),
+                    weapon.damage_type,
+                    ProjectileDamage(weapon.damage * damage_multiplier),
+                    FiredBy(entity),
+                    Side::default(),
+                ));
+            }
+
+            if fired && cloaked.is_some() {
+                commands.entity(entity).remove::<Cloaked>();
+            }
+        },
+    )
+}
+
+// Whether a ship that isn't under direct orders to attack anything may open fire.
+fn weapons_free(
+    global_rules: &PerSide<GlobalRulesOfEngagement>,
+    rules_override: Option<&RulesOfEngagementOverride>,
+    friendly: Option<&Friendly>,
+    last_hit: Option<&LastHit>,
+    total_time: f32,
+    stance: Option<&Stance>,
+    queue: Option<&CommandQueue>,
+) -> bool {
+    // Passive ships only ever attack on orders.
+    if !Stance::get(stance).picks_targets() {
+        return matches!(
+            queue.and_then(|queue| queue.0.front()),
             Some(Command::Interact {
                 ty: InteractionType::Attack,
-                target,
                 ..
-            }) => target,
-            _ => return,
+            })
+        );
+    }
+
+    let rules = rules_of_engagement(global_rules, rules_override, friendly);
+
+    let recently_hit = last_hit.map_or(false, |last_hit| {
+        total_time - last_hit.0 < RulesOfEngagement::RETURN_FIRE_DURATION
+    });
+
+    match rules {
+        RulesOfEngagement::HoldFire => false,
+        RulesOfEngagement::ReturnFire => recently_hit,
+        RulesOfEngagement::WeaponsFree => true,
+    }
+}
+
+// Each side follows their own rules, unless a ship has been given its own.
+fn rules_of_engagement(
+    global_rules: &PerSide<GlobalRulesOfEngagement>,
+    rules_override: Option<&RulesOfEngagementOverride>,
+    friendly: Option<&Friendly>,
+) -> RulesOfEngagement {
+    RulesOfEngagement::get(global_rules[Side::of(friendly)].0, rules_override)
+}
+
+// Weapon research only benefits the side that researched it.
+fn research_multiplier(tech_tree: &PerSide<TechTree>, friendly: Option<&Friendly>) -> f32 {
+    tech_tree[Side::of(friendly)].weapon_damage_multiplier()
+}
+
+// Broadsides fire at the closest target in each arc rather than at the ship's target, so that
+// capital ships can keep shooting while manoeuvring.
+pub fn fire_broadsides<SideA, SideB>(
+    mut query: Query<
+        (
+            Entity,
+            &Position,
+            &RotationMatrix,
+            &mut WeaponArcs,
+            Option<&LastHit>,
+            Option<&RulesOfEngagementOverride>,
+            Option<&Friendly>,
+            Option<&Veterancy>,
+            Option<&CrewModifiers>,
+            Option<&mut Heat>,
+            Option<&DamageBoost>,
+            Option<&Stance>,
+            Option<&CommandQueue>,
+        ),
+        With<SideA>,
+    >,
+    candidates: Query<&Position, (With<SideB>, With<CommandQueue>, Without<Cloaked>)>,
+    clock: Res<SimClock>,
+    mut commands: Commands,
+    global_rules: Res<PerSide<GlobalRulesOfEngagement>>,
+    tech_tree: Res<PerSide<TechTree>>,
+) where
+    SideA: Send + Sync + Default + 'static,
+    SideB: Send + Sync + 'static,
+{
+    query.for_each_mut(
+        |(
+            entity,
+            pos,
+            rotation,
+            mut weapon_arcs,
+            last_hit,
+            rules_override,
+            friendly,
+            veterancy,
+            crew_modifiers,
+            mut heat,
+            damage_boost,
+            stance,
+            queue,
+        )| {
+            let weapons_free = weapons_free(
+                &global_rules,
+                rules_override,
+                friendly,
+                last_hit,
+                clock.time,
+                stance,
+                queue,
+            );
+
+            for arc in &mut weapon_arcs.0 {
+                arc.cooldown.tick(&clock);
+
+                if !arc.cooldown.is_ready() || !weapons_free || !Heat::can_fire(heat.as_deref()) {
+                    continue;
+                }
+
+                let target = candidates
+                    .iter()
+                    .map(|target_pos| target_pos.0 - pos.0)
+                    .filter(|&offset| arc.contains(rotation, offset))
+                    .min_by(|a, b| compare_floats(a.mag_sq(), b.mag_sq()));
+
+                if let Some(offset) = target {
+                    arc.cooldown.start(
+                        WeaponArc::RELOAD_TIME * CrewModifiers::reload_multiplier(crew_modifiers),
+                    );
+
+                    if let Some(heat) = &mut heat {
+                        heat.add(Heat::PER_SHOT);
+                    }
+
+                    let ray = Ray::new(pos.0, offset.normalized());
+
+                    commands.spawn_bundle((
+                        Projectile::new(&ray, 200.0),
+                        AliveUntil(clock.timer(10.0)),
+                        DamageType::Kinetic,
+                        ProjectileDamage(
+                            10.0 * research_multiplier(&tech_tree, friendly)
+                                * veterancy.map_or(1.0, Veterancy::damage_multiplier)
+                                * DamageBoost::damage_multiplier(damage_boost),
+                        ),
+                        FiredBy(entity),
+                        SideA::default(),
+                    ));
+                }
+            }
+        },
+    )
+}
+
+// Beams hold on to their target until it leaves the arc or something gets in the way, and then
+// lock on to the closest target that they can see.
+pub fn fire_beams<SideA, SideB>(
+    mut query: Query<
+        (
+            Entity,
+            &Position,
+            &RotationMatrix,
+            &mut BeamWeapon,
+            Option<&mut Veterancy>,
+            Option<&LastHit>,
+            Option<&RulesOfEngagementOverride>,
+            Option<&Friendly>,
+            Option<&Cloaked>,
+            Option<&mut Heat>,
+            Option<&Stance>,
+            Option<&CommandQueue>,
+        ),
+        With<SideA>,
+    >,
+    candidates: Query<(Entity, &Position), (With<SideB>, With<CommandQueue>, Without<Cloaked>)>,
+    mut health: Query<(&mut Health, Option<&mut Shield>, Option<&Armor>)>,
+    bounding_boxes: Query<&WorldSpaceBoundingBox>,
+    tlas: Res<TopLevelAccelerationStructure>,
+    clock: Res<SimClock>,
+    mut commands: Commands,
+    global_rules: Res<PerSide<GlobalRulesOfEngagement>>,
+    mut damage_events: ResMut<DamageEvents>,
+    tech_tree: Res<PerSide<TechTree>>,
+) where
+    SideA: Send + Sync + 'static,
+    SideB: Send + Sync + 'static,
+{
+    query.for_each_mut(
+        |(
+            entity,
+            pos,
+            rotation,
+            mut beam,
+            veterancy,
+            last_hit,
+            rules_override,
+            friendly,
+            cloaked,
+            mut heat,
+            stance,
+            queue,
+        )| {
+            let weapons_free = weapons_free(
+                &global_rules,
+                rules_override,
+                friendly,
+                last_hit,
+                clock.time,
+                stance,
+                queue,
+            );
+
+            let target = if weapons_free && Heat::can_fire(heat.as_deref()) {
+                let can_fire_at = |target: Entity, target_pos: Vec3| {
+                    beam.contains(rotation, target_pos - pos.0)
+                        && !line_of_sight_blocked(
+                            &tlas,
+                            &bounding_boxes,
+                            (entity, pos.0),
+                            (target, target_pos),
+                        )
+                };
+
+                beam.target
+                    .filter(|&target| match candidates.get(target) {
+                        Ok((_, target_pos)) => can_fire_at(target, target_pos.0),
+                        Err(_) => false,
+                    })
+                    .or_else(|| {
+                        candidates
+                            .iter()
+                            .filter(|&(target, target_pos)| can_fire_at(target, target_pos.0))
+                            .min_by(|(_, a), (_, b)| {
+                                compare_floats((a.0 - pos.0).mag_sq(), (b.0 - pos.0).mag_sq())
+                            })
+                            .map(|(target, _)| target)
+                    })
+            } else {
+                None
+            };
+
+            let firing = beam.drain(target.is_some(), clock.delta);
+            beam.target = if firing { target } else { None };
+
+            let target = match beam.target {
+                Some(target) => target,
+                None => return,
+            };
+
+            if cloaked.is_some() {
+                commands.entity(entity).remove::<Cloaked>();
+            }
+
+            if let Some(heat) = &mut heat {
+                heat.add(Heat::BEAM_PER_SECOND * clock.delta);
+            }
+
+            let damage = beam.damage_per_second
+                * clock.delta
+                * research_multiplier(&tech_tree, friendly)
+                * veterancy
+                    .as_deref()
+                    .map_or(1.0, Veterancy::damage_multiplier);
+
+            let mut killed = false;
+
+            if let Ok((mut health, shield, armor)) = health.get_mut(target) {
+                let damage = match shield {
+                    Some(mut shield) => shield.absorb(damage),
+                    None => damage,
+                };
+
+                let was_alive = health.current > 0.0;
+                health.current -= damage * Armor::damage_multiplier(armor, beam.damage_type);
+                killed = was_alive && health.current <= 0.0;
+
+                if let Ok((_, target_pos)) = candidates.get(target) {
+                    damage_events.0.push(DamageEvent {
+                        target,
+                        position: target_pos.0,
+                    });
+                }
+            }
+
+            commands.entity(target).insert(LastHit(clock.time));
+
+            let promoted =
+                killed && veterancy.map_or(false, |mut veterancy| veterancy.record_kill());
+
+            if promoted {
+                if let Ok((mut health, ..)) = health.get_mut(entity) {
+                    let bonus = health.max * Veterancy::HEALTH_BONUS_PER_RANK;
+                    health.max += bonus;
+                    health.current += bonus;
+                }
+            }
+        },
+    )
+}
+
+// Anything else in the TLAS between the two ends blocks the line of sight. Bounding boxes are
+// close enough for this, even if they mean the odd gap that could have been fired through isn't.
+fn line_of_sight_blocked(
+    tlas: &TopLevelAccelerationStructure,
+    bounding_boxes: &Query<&WorldSpaceBoundingBox>,
+    (from, from_pos): (Entity, Vec3),
+    (to, to_pos): (Entity, Vec3),
+) -> bool {
+    let offset = to_pos - from_pos;
+    let distance = offset.mag();
+    let ray = Ray::new(from_pos, offset / distance);
+
+    let mut find_stack = Vec::with_capacity(10);
+
+    let blocked = tlas
+        .find(
+            |bounding_box| {
+                ray.bounding_box_intersection(bounding_box)
+                    .map_or(false, |t| t < distance)
+            },
+            &mut find_stack,
+        )
+        .filter(|&&entity| entity != from && entity != to)
+        .any(|&entity| {
+            bounding_boxes
+                .get(entity)
+                .ok()
+                .and_then(|bounding_box| ray.bounding_box_intersection(bounding_box.0))
+                .map_or(false, |t| t > 0.0 && t < distance)
+        });
+
+    blocked
+}
+
+pub fn detonate_scuttled_ships(
+    scuttling: Query<(Entity, &Position, &Scuttling)>,
+    mut health: Query<(&Position, &mut Health, Option<&Armor>)>,
+    clock: Res<SimClock>,
+) {
+    let mut detonating = Vec::new();
+
+    scuttling.for_each(|(entity, position, scuttling)| {
+        if scuttling.detonates_at.finished(&clock) {
+            detonating.push((entity, position.0, scuttling.blast));
+        }
+    });
+
+    for (entity, center, blast) in detonating {
+        // Setting health to 0 lets `handle_destruction` take care of unloading and cleanup.
+        if let Ok((_, mut health, _)) = health.get_mut(entity) {
+            health.current = 0.0;
+        }
+
+        if let Some(blast) = blast {
+            health.for_each_mut(|(position, mut health, armor)| {
+                let falloff = blast.falloff_at((position.0 - center).mag());
+                health.current -= Scuttling::BLAST_DAMAGE
+                    * falloff
+                    * Armor::damage_multiplier(armor, DamageType::Explosive);
+            });
+        }
+    }
+}
+
+// Only ships on opposing sides ram each other. Ships on the same side already keep their distance,
+// and would get in the way of docking otherwise.
+pub fn ram_ships(
+    ships: Query<
+        (
+            Entity,
+            &Position,
+            &WorldSpaceBoundingBox,
+            &ModelId,
+            Option<&Friendly>,
+            Option<&Enemy>,
+        ),
+        (With<MaxSpeed>, Without<Missile>),
+    >,
+    mut physics: Query<(&mut Velocity, &mut Health, Option<&Armor>)>,
+    tlas: Res<TopLevelAccelerationStructure>,
+) {
+    let mut collisions = Vec::new();
+
+    ships.for_each(|(entity, position, bbox, model_id, friendly, enemy)| {
+        let mut find_stack = Vec::with_capacity(10);
+
+        for &other in tlas.find(
+            |bounding_box| bbox.0.intersects(bounding_box),
+            &mut find_stack,
+        ) {
+            // Each pair is only handled once.
+            if other <= entity {
+                continue;
+            }
+
+            if let Ok((
+                _,
+                other_position,
+                other_bbox,
+                other_model_id,
+                other_friendly,
+                other_enemy,
+            )) = ships.get(other)
+            {
+                let overlapping = bbox.0.intersects(other_bbox.0);
+                let hostile = is_hostile((friendly, enemy), (other_friendly, other_enemy));
+                let normal = (other_position.0 - position.0).normalized();
+
+                // Ships in exactly the same place have no way to be pushed apart.
+                if overlapping && hostile && normal.mag_sq() > 0.5 {
+                    let masses = [model_id.mass(), other_model_id.mass()];
+                    collisions.push(([entity, other], normal, masses));
+                }
+            }
+        }
+    });
+
+    for ([a, b], normal, masses) in collisions {
+        let mut velocity = |entity| {
+            physics
+                .get_mut(entity)
+                .ok()
+                .map(|(velocity, ..)| velocity.0)
+        };
+
+        let velocities = match (velocity(a), velocity(b)) {
+            (Some(velocity_a), Some(velocity_b)) => [velocity_a, velocity_b],
+            _ => continue,
         };
 
-        let agro_range_sq = agro_range.0 * agro_range.0;
+        let closing_speed = (velocities[0] - velocities[1]).dot(normal);
+        let collision = Collision::new(closing_speed, masses);
+
+        for (i, (entity, direction)) in [(a, -normal), (b, normal)].iter().enumerate() {
+            if let Ok((mut velocity, mut health, armor)) = physics.get_mut(*entity) {
+                velocity.0 += *direction * collision.knockback[i];
+                health.current -=
+                    collision.damage[i] * Armor::damage_multiplier(armor, DamageType::Kinetic);
+            }
+        }
+    }
+}
+
+// Mines that aren't on either side go off for anyone.
+fn is_hostile(
+    (friendly_a, enemy_a): (Option<&Friendly>, Option<&Enemy>),
+    (friendly_b, enemy_b): (Option<&Friendly>, Option<&Enemy>),
+) -> bool {
+    match (friendly_a.is_some(), enemy_a.is_some()) {
+        (true, _) => enemy_b.is_some(),
+        (_, true) => friendly_b.is_some(),
+        _ => true,
+    }
+}
+
+pub fn detonate_proximity_mines(
+    mines: Query<(
+        Entity,
+        &Position,
+        &ProximityMine,
+        Option<&Friendly>,
+        Option<&Enemy>,
+    )>,
+    ships: Query<(&Position, Option<&Friendly>, Option<&Enemy>), With<MaxSpeed>>,
+    mut health: Query<(&Position, &mut Health, Option<&Armor>)>,
+    tlas: Res<TopLevelAccelerationStructure>,
+) {
+    let mut detonating = Vec::new();
 
-        let in_range = match positions.get(*attack_target) {
-            Ok(target_pos) => (pos.0 - target_pos.0).mag_sq() < agro_range_sq,
-            _ => false,
+    mines.for_each(|(entity, position, mine, friendly, enemy)| {
+        let mut find_stack = Vec::with_capacity(10);
+        let radius = mine.trigger_radius;
+        let trigger_box =
+            BoundingBox::new(-Vec3::broadcast(radius), Vec3::broadcast(radius)) + position.0;
+
+        let triggered = tlas
+            .find(
+                |bounding_box| trigger_box.intersects(bounding_box),
+                &mut find_stack,
+            )
+            .filter_map(|&entity| ships.get(entity).ok())
+            .filter(|&(_, ship_friendly, ship_enemy)| {
+                is_hostile((friendly, enemy), (ship_friendly, ship_enemy))
+            })
+            .any(|(ship_position, ..)| (ship_position.0 - position.0).mag_sq() < radius.powi(2));
+
+        if triggered {
+            detonating.push((entity, position.0, mine.blast));
+        }
+    });
+
+    for (entity, center, blast) in detonating {
+        if let Ok((_, mut health, _)) = health.get_mut(entity) {
+            health.current = 0.0;
+        }
+
+        health.for_each_mut(|(position, mut health, armor)| {
+            let falloff = blast.falloff_at((position.0 - center).mag());
+            health.current -= ProximityMine::BLAST_DAMAGE
+                * falloff
+                * Armor::damage_multiplier(armor, DamageType::Explosive);
+        });
+    }
+}
+
+pub fn detect_mines(
+    mines: Query<
+        (
+            Entity,
+            &Position,
+            Option<&Friendly>,
+            Option<&Enemy>,
+            Option<&Hidden>,
+        ),
+        With<ProximityMine>,
+    >,
+    detectors: Query<(&Position, &Detector, Option<&Friendly>, Option<&Enemy>)>,
+    mut commands: Commands,
+) {
+    mines.for_each(|(entity, position, friendly, enemy, hidden)| {
+        if friendly.is_none() && enemy.is_none() {
+            return;
+        }
+
+        let detected = detectors
+            .iter()
+            .filter(|&(.., detector_friendly, detector_enemy)| {
+                is_hostile((friendly, enemy), (detector_friendly, detector_enemy))
+            })
+            .any(|(detector_position, detector, ..)| {
+                (detector_position.0 - position.0).mag_sq() < detector.range.powi(2)
+            });
+
+        match (detected, hidden.is_some()) {
+            (true, true) => {
+                commands.entity(entity).remove::<Hidden>();
+            }
+            (false, false) => {
+                commands.entity(entity).insert(Hidden);
+            }
+            _ => {}
+        }
+    });
+}
+
+pub fn intercept_threats<SideA, SideB>(
+    mut escorts: Query<
+        (
+            Entity,
+            &Escorting,
+            &mut CommandQueue,
+            Option<&RulesOfEngagementOverride>,
+            Option<&Friendly>,
+        ),
+        With<SideA>,
+    >,
+    carriers: Query<&Position>,
+    threats: Query<(Entity, &Position), (With<SideB>, With<MaxSpeed>, Without<Cloaked>)>,
+    global_rules: Res<PerSide<GlobalRulesOfEngagement>>,
+    mut commands: Commands,
+) where
+    SideA: Send + Sync + 'static,
+    SideB: Send + Sync + 'static,
+{
+    escorts.for_each_mut(|(entity, escorting, mut queue, rules_override, friendly)| {
+        let carrier_position = match carriers.get(escorting.carrier) {
+            Ok(position) => position.0,
+            Err(_) => {
+                commands.entity(entity).remove::<Escorting>();
+                return;
+            }
         };
 
-        if !in_range {
+        if !queue.0.is_empty() {
             return;
         }
 
-        ray_cooldown.0 = 1.0;
+        if rules_of_engagement(&global_rules, rules_override, friendly)
+            == RulesOfEngagement::HoldFire
+        {
+            return;
+        }
+
+        let threat = threats
+            .iter()
+            .map(|(threat, position)| (threat, (position.0 - carrier_position).mag_sq()))
+            .filter(|&(_, dist_sq)| dist_sq < Escorting::INTERCEPT_RANGE.powi(2))
+            .min_by(|&(_, a), &(_, b)| compare_floats(a, b));
+
+        if let Some((threat, _)) = threat {
+            queue.0.push_back(Command::Interact {
+                target: threat,
+                ty: InteractionType::Attack,
+                range_sq: 0.0,
+            });
+            commands.entity(threat).insert(Evading(entity));
+        }
+    });
+}
+
+pub fn drain_cloak_energy(
+    mut ships: Query<(Entity, &mut CloakEnergy, Option<&Cloaked>)>,
+    clock: Res<SimClock>,
+    mut commands: Commands,
+) {
+    ships.for_each_mut(|(entity, mut energy, cloaked)| {
+        if cloaked.is_some() {
+            energy.current = (energy.current - CloakEnergy::DRAIN_RATE * clock.delta).max(0.0);
+
+            if energy.current == 0.0 {
+                commands.entity(entity).remove::<Cloaked>();
+            }
+        } else {
+            energy.current =
+                (energy.current + CloakEnergy::RECHARGE_RATE * clock.delta).min(energy.max);
+        }
+    });
+}
+
+// Ships that were already attacking a ship when it cloaked lose track of it.
+pub fn lose_track_of_cloaked_ships(
+    mut queues: Query<&mut CommandQueue>,
+    cloaked: Query<(), With<Cloaked>>,
+) {
+    queues.for_each_mut(|mut queue| {
+        if let Some(Command::Interact {
+            target,
+            ty: InteractionType::Attack,
+            ..
+        }) = queue.0.front()
+        {
+            if cloaked.get(*target).is_ok() {
+                queue.0.pop_front();
+            }
+        }
+    });
+}
+
+// Ships in the path of an incoming projectile jink off to one side. There's no acceleration
+// structure for projectiles, so the ship TLAS is searched along each projectile's path instead.
+pub fn detect_incoming_fire<Side>(
+    projectiles: Query<&Projectile, With<Side>>,
+    ships: Query<
+        (
+            &Position,
+            &EvasiveManeuvers,
+            Option<&Jinking>,
+            Option<&HoldPosition>,
+            Option<&RulesOfEngagementOverride>,
+            Option<&Friendly>,
+        ),
+        Without<Side>,
+    >,
+    bvh: Res<TopLevelAccelerationStructure>,
+    clock: Res<SimClock>,
+    global_rules: Res<PerSide<GlobalRulesOfEngagement>>,
+    mut rng: ResMut<SmallRng>,
+    mut commands: Commands,
+) where
+    Side: Send + Sync + 'static,
+{
+    projectiles.for_each(|projectile| {
+        let mut find_stack = Vec::with_capacity(10);
+
+        let origin = projectile.get_intersection_point(0.0);
+        let direction = projectile.direction();
+        let range = projectile.max_t(Jinking::WARNING_TIME);
+
+        let end = origin + direction * range;
+
+        let path = BoundingBox::new(origin.min_by_component(end), origin.max_by_component(end))
+            .expand(Jinking::CONE_BASE_RADIUS + range * Jinking::CONE_SLOPE);
+
+        for &entity in bvh.find(
+            |bounding_box| path.intersects(bounding_box),
+            &mut find_stack,
+        ) {
+            let (position, maneuvers, jinking, hold_position, rules_override, friendly) =
+                match ships.get(entity) {
+                    Ok(components) => components,
+                    Err(_) => continue,
+                };
+
+            if hold_position.is_some() || jinking.map_or(false, |j| !j.until.finished(&clock)) {
+                continue;
+            }
+
+            let offset = position.0 - origin;
+            let along = offset.dot(direction);
+
+            if along < 0.0 || along > range {
+                continue;
+            }
+
+            let cone_radius = Jinking::CONE_BASE_RADIUS + along * Jinking::CONE_SLOPE;
+
+            if offset.mag_sq() - along * along > cone_radius * cone_radius {
+                continue;
+            }
+
+            let rules = rules_of_engagement(&global_rules, rules_override, friendly);
+
+            let random = uniform_sphere_distribution(&mut *rng);
+            let sideways = random - direction * random.dot(direction);
+
+            if sideways == Vec3::zero() {
+                continue;
+            }
+
+            commands.entity(entity).insert(Jinking {
+                direction: sideways.normalized(),
+                strength: maneuvers.strength * rules.evasion_multiplier(),
+                until: clock.timer(maneuvers.duration),
+            });
+        }
+    });
+}
+
+// Ships out in the sun keep heating up, so they can overheat without firing a shot. In binary
+// systems, each sun adds its own heat.
+pub fn vent_heat(
+    mut query: Query<(&Position, &mut Heat, Option<&CrewModifiers>)>,
+    heat_zones: Res<HeatZones>,
+    clock: Res<SimClock>,
+) {
+    query.for_each_mut(|(pos, mut heat, crew_modifiers)| {
+        let from_suns: f32 = heat_zones
+            .0
+            .iter()
+            .map(|&(sun_dir, heat_per_second)| Heat::from_sun(pos.0, sun_dir, heat_per_second))
+            .sum();
+
+        heat.add(from_suns * clock.delta);
+        heat.vent(
+            Heat::VENT_RATE * CrewModifiers::heat_vent_multiplier(crew_modifiers) * clock.delta,
+        );
+    });
+}
 
-        let ray = Ray::new(pos.0, vel.0.normalized());
+pub fn recharge_shields(mut query: Query<(&mut Shield, Option<&LastHit>)>, clock: Res<SimClock>) {
+    query.for_each_mut(|(mut shield, last_hit)| {
+        let recently_hit = last_hit.map_or(false, |last_hit| {
+            clock.time - last_hit.0 < Shield::RECHARGE_DELAY
+        });
 
-        commands.spawn_bundle((
-            Projectile::new(&ray, 200.0),
-            AliveUntil(total_time.0 + 10.0),
-            Side::default(),
-        ));
+        if !recently_hit {
+            shield.current = (shield.current + shield.recharge_rate * clock.delta).min(shield.max);
+        }
+    });
+}
+
+pub fn launch_missiles<SideA, SideB>(
+    mut query: Query<
+        (
+            &Position,
+            &mut MissileLauncher,
+            Option<&LastHit>,
+            Option<&RulesOfEngagementOverride>,
+            Option<&Friendly>,
+            Option<&CrewModifiers>,
+            Option<&Stance>,
+            Option<&CommandQueue>,
+        ),
+        With<SideA>,
+    >,
+    candidates: Query<(Entity, &Position), (With<SideB>, With<CommandQueue>, Without<Cloaked>)>,
+    clock: Res<SimClock>,
+    mut commands: Commands,
+    global_rules: Res<PerSide<GlobalRulesOfEngagement>>,
+    tech_tree: Res<PerSide<TechTree>>,
+) where
+    SideA: Send + Sync + Default + 'static,
+    SideB: Send + Sync + 'static,
+{
+    query.for_each_mut(
+        |(pos, mut launcher, last_hit, rules_override, friendly, crew_modifiers, stance, queue)| {
+            launcher.cooldown.tick(&clock);
+
+            if !launcher.cooldown.is_ready()
+                || !weapons_free(
+                    &global_rules,
+                    rules_override,
+                    friendly,
+                    last_hit,
+                    clock.time,
+                    stance,
+                    queue,
+                )
+            {
+                return;
+            }
+
+            let target = candidates
+                .iter()
+                .map(|(entity, target_pos)| (entity, target_pos.0 - pos.0))
+                .filter(|(_, offset)| offset.mag_sq() < launcher.range.powi(2))
+                .min_by(|(_, a), (_, b)| compare_floats(a.mag_sq(), b.mag_sq()));
+
+            if let Some((target, offset)) = target {
+                launcher.cooldown.start(
+                    MissileLauncher::RELOAD_TIME * CrewModifiers::reload_multiplier(crew_modifiers),
+                );
+
+                commands.spawn_bundle((
+                    Position(pos.0),
+                    Velocity(offset.normalized() * MissileLauncher::LAUNCH_SPEED),
+                    Missile {
+                        damage_multiplier: research_multiplier(&tech_tree, friendly),
+                        ..Missile::new(target)
+                    },
+                    AliveUntil(clock.timer(Missile::LIFETIME)),
+                    SideA::default(),
+                ));
+            }
+        },
+    )
+}
+
+// Missiles that lose their target, either because it was destroyed or cloaked, carry on in a
+// straight line until they hit something or run out of fuel.
+pub fn guide_missiles(
+    mut missiles: Query<(&Position, &mut Velocity, &Missile)>,
+    targets: Query<&Position, Without<Cloaked>>,
+    clock: Res<SimClock>,
+) {
+    missiles.for_each_mut(|(position, mut velocity, missile)| {
+        let speed = (velocity.0.mag() + missile.acceleration * clock.delta).min(missile.max_speed);
+        let direction = velocity.0.normalized();
+
+        let direction = match targets.get(missile.target) {
+            Ok(target) => {
+                let desired = (target.0 - position.0).normalized();
+                let max_turn = missile.turn_rate * clock.delta;
+                let angle = direction.dot(desired).max(-1.0).min(1.0).acos();
+
+                if angle <= max_turn {
+                    desired
+                } else {
+                    let mut perpendicular = desired - direction * direction.dot(desired);
+
+                    // With the target directly behind, any way round will do.
+                    if perpendicular.mag_sq() < 0.0001 {
+                        perpendicular = direction.cross(Vec3::unit_y());
+
+                        if perpendicular.mag_sq() < 0.0001 {
+                            perpendicular = Vec3::unit_x();
+                        }
+                    }
+
+                    let perpendicular = perpendicular.normalized();
+                    direction * max_turn.cos() + perpendicular * max_turn.sin()
+                }
+            }
+            Err(_) => direction,
+        };
+
+        velocity.0 = direction * speed;
     })
 }
+
+pub fn detonate_missiles<Side>(
+    missiles: Query<(Entity, &Position, &Missile), With<Side>>,
+    ships: Query<&Position, (Without<Side>, With<MaxSpeed>)>,
+    mut health: Query<(&Position, &mut Health, Option<&Armor>)>,
+    tlas: Res<TopLevelAccelerationStructure>,
+    clock: Res<SimClock>,
+    mut rng: ResMut<SmallRng>,
+    mut commands: Commands,
+) where
+    Side: Send + Sync + 'static,
+{
+    for (entity, position, missile) in missiles.iter() {
+        let mut find_stack = Vec::with_capacity(10);
+        let radius = missile.trigger_radius;
+        let trigger_box =
+            BoundingBox::new(-Vec3::broadcast(radius), Vec3::broadcast(radius)) + position.0;
+
+        let triggered = tlas
+            .find(
+                |bounding_box| trigger_box.intersects(bounding_box),
+                &mut find_stack,
+            )
+            .filter_map(|&entity| ships.get(entity).ok())
+            .any(|ship_position| (ship_position.0 - position.0).mag_sq() < radius.powi(2));
+
+        if !triggered {
+            continue;
+        }
+
+        let blast = missile.blast;
+        let blast_box = BoundingBox::new(
+            -Vec3::broadcast(blast.radius),
+            Vec3::broadcast(blast.radius),
+        ) + position.0;
+
+        let mut blast_find_stack = Vec::with_capacity(10);
+
+        for &ship in tlas.find(
+            |bounding_box| blast_box.intersects(bounding_box),
+            &mut blast_find_stack,
+        ) {
+            if let Ok((ship_position, mut health, armor)) = health.get_mut(ship) {
+                let falloff = blast.falloff_at((ship_position.0 - position.0).mag());
+                health.current -= Missile::BLAST_DAMAGE
+                    * missile.damage_multiplier
+                    * falloff
+                    * Armor::damage_multiplier(armor, DamageType::Explosive);
+            }
+        }
+
+        commands.entity(entity).despawn();
+        spawn_explosion(position.0, &clock, &mut *rng, &mut commands);
+    }
+}
+
+#[test]
+fn test_warships_pick_targets() {
+    use crate::spawn_ship;
+
+    for &ship in &[ShipType::Frigate, ShipType::Destroyer, ShipType::Battleship] {
+        let mut world = World::default();
+        world.insert_resource(bevy_tasks::TaskPool::new());
+        world.insert_resource(PerSide::<GlobalRulesOfEngagement>::default());
+        world.insert_resource(SimClock::new(0.0));
+
+        let mut command_queue = bevy_ecs::system::CommandQueue::default();
+        let warship = spawn_ship::<Friendly>(
+            ship,
+            Vec3::zero(),
+            &mut Commands::new(&mut command_queue, &world),
+        );
+        command_queue.apply(&mut world);
+
+        let target_pos = Vec3::new(0.0, 0.0, 50.0);
+        let target = world
+            .spawn()
+            .insert_bundle((Position(target_pos), Enemy))
+            .id();
+
+        let mut tlas = TopLevelAccelerationStructure::default();
+        tlas.insert(
+            target,
+            BoundingBox::new(target_pos - Vec3::one(), target_pos + Vec3::one()),
+        );
+        world.insert_resource(tlas);
+
+        let mut stage = SystemStage::parallel();
+        stage.add_system(choose_enemy_target::<Friendly, Enemy>.system());
+        stage.run(&mut world);
+
+        assert!(
+            matches!(
+                world.get::<CommandQueue>(warship).unwrap().0.front(),
+                Some(&Command::Interact { target: attacking, ty: InteractionType::Attack, .. }) if attacking == target
+            ),
+            "{:?} didn't pick a target",
+            ship
+        );
+    }
+}
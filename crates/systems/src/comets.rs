@@ -0,0 +1,84 @@
+use bevy_ecs::prelude::*;
+use components_and_resources::components::*;
+use components_and_resources::gpu_structs::LaserInstance;
+use components_and_resources::resources::*;
+use ultraviolet::Vec3;
+
+pub fn move_comets(mut comets: Query<(&Comet, &mut Position)>, clock: Res<SimClock>) {
+    comets.for_each_mut(|(comet, mut position)| {
+        position.0 = comet.position_at(clock.time);
+    });
+}
+
+pub fn collide_comets(
+    comets: Query<(Entity, &Position, &Comet)>,
+    mut ships: Query<(&Position, &mut Health), With<MaxSpeed>>,
+    clock: Res<SimClock>,
+    tlas: Res<TopLevelAccelerationStructure>,
+) {
+    let mut hit = Vec::new();
+
+    comets.for_each(|(comet_entity, position, comet)| {
+        let mut find_stack = Vec::with_capacity(10);
+        let radius = comet.radius;
+        let comet_box =
+            BoundingBox::new(-Vec3::broadcast(radius), Vec3::broadcast(radius)) + position.0;
+
+        for &entity in tlas.find(
+            |bounding_box| comet_box.intersects(bounding_box),
+            &mut find_stack,
+        ) {
+            if entity != comet_entity {
+                hit.push((entity, position.0, comet.radius, comet.damage_per_second));
+            }
+        }
+    });
+
+    for (entity, center, radius, damage_per_second) in hit {
+        if let Ok((position, mut health)) = ships.get_mut(entity) {
+            if (position.0 - center).mag_sq() < radius * radius {
+                health.current -= damage_per_second * clock.delta;
+            }
+        }
+    }
+}
+
+// The tail streams away from the sun, and is made up of specks that drift down it and fade out.
+pub fn render_comet_tails(
+    comets: Query<(&Position, &Comet)>,
+    lighting: Res<Lighting>,
+    clock: Res<SimClock>,
+    mut lasers: ResMut<GpuBuffer<LaserInstance>>,
+) {
+    const PARTICLES: usize = 24;
+    const TAIL_LENGTH: f32 = 40.0;
+
+    let away_from_sun = -lighting.current.sun_dir.normalized();
+
+    comets.for_each(|(position, comet)| {
+        lasers.stage(&[LaserInstance {
+            start: position.0,
+            end: position.0 + away_from_sun * TAIL_LENGTH * 0.5,
+            colour: Vec3::new(0.6, 0.8, 1.0) * 2.0,
+            width: comet.radius * 0.5,
+        }]);
+
+        for i in 0..PARTICLES {
+            // Spread the particles out along the tail with the golden ratio so they don't bunch up.
+            let progress = (i as f32 * 0.618 + clock.time * 0.25).fract();
+            let angle = i as f32 * 2.4;
+            let spread =
+                Vec3::new(angle.cos(), (angle * 1.7).sin(), angle.sin()) * progress * comet.radius;
+
+            let start = position.0 + away_from_sun * progress * TAIL_LENGTH + spread;
+            let fade = 1.0 - progress;
+
+            lasers.stage(&[LaserInstance {
+                start,
+                end: start + away_from_sun * 2.0,
+                colour: Vec3::new(0.5, 0.7, 1.0) * fade * 1.5,
+                width: 0.3 * fade + 0.1,
+            }]);
+        }
+    });
+}
@@ -0,0 +1,150 @@
+use crate::{average, SelectedFriendly};
+use bevy_ecs::prelude::*;
+use components_and_resources::components::*;
+use components_and_resources::resources::*;
+
+pub fn enter_structure_placement_mode(
+    keyboard_state: Res<KeyboardState>,
+    builders: Query<&Position, (SelectedFriendly, With<CanConstruct>)>,
+    global_minerals: Res<PerSide<GlobalMinerals>>,
+    players: Res<Players>,
+    mut mouse_mode: ResMut<MouseMode>,
+    mut order_feedback: ResMut<OrderFeedback>,
+) {
+    let structure = if keyboard_state.place_defense_platform.0 {
+        StructureType::DefensePlatform
+    } else if keyboard_state.place_shipyard.0 {
+        StructureType::Shipyard
+    } else if keyboard_state.place_refinery.0 {
+        StructureType::Refinery
+    } else {
+        return;
+    };
+
+    let avg = match average(builders.iter().map(|position| position.0)) {
+        Some(avg) => avg,
+        None => {
+            order_feedback.rejected = Some("Select a construction ship to build with");
+            return;
+        }
+    };
+
+    if global_minerals[players.local_side()].0 < structure.cost() {
+        order_feedback.rejected = Some("Not enough minerals");
+        return;
+    }
+
+    *mouse_mode = MouseMode::PlaceStructure {
+        structure,
+        point_on_plane: avg,
+    };
+}
+
+pub fn update_structure_placement_point(ray: Res<Ray>, mut mouse_mode: ResMut<MouseMode>) {
+    if let MouseMode::PlaceStructure {
+        ref mut point_on_plane,
+        ..
+    } = &mut *mouse_mode
+    {
+        if let Some(t) = ray.y_plane_intersection(point_on_plane.y) {
+            *point_on_plane = ray.get_intersection_point(t);
+        }
+    }
+}
+
+pub fn place_structure(
+    mouse_button: Res<MouseState>,
+    builders: Query<Entity, (SelectedFriendly, With<CanConstruct>, With<CommandQueue>)>,
+    mut mouse_mode: ResMut<MouseMode>,
+    mut player_commands: ResMut<PlayerCommands>,
+) {
+    if !mouse_button.right_state.was_clicked() {
+        return;
+    }
+
+    if let MouseMode::PlaceStructure {
+        structure,
+        point_on_plane,
+    } = *mouse_mode
+    {
+        player_commands.local.push(PlayerCommand::Construct {
+            ships: builders.iter().collect(),
+            structure,
+            point: point_on_plane,
+        });
+
+        *mouse_mode = MouseMode::Normal;
+    }
+}
+
+// Construction ships in range of their site work on it together. Once it's done, the site is
+// turned into the finished structure.
+pub fn construct(
+    mut builders: Query<(&Position, &MaxSpeed, &mut CommandQueue), With<CanConstruct>>,
+    mut sites: Query<(&Position, &mut UnderConstruction)>,
+    clock: Res<SimClock>,
+    mut commands: Commands,
+) {
+    builders.for_each_mut(|(pos, max_speed, mut queue)| {
+        let (target, range_sq) = match queue.0.front() {
+            Some(&Command::Interact {
+                target,
+                ty: InteractionType::Construct,
+                range_sq,
+            }) => (target, range_sq),
+            _ => return,
+        };
+
+        let (site_pos, mut site) = match sites.get_mut(target) {
+            Ok(site) if !site.1.is_complete() => site,
+            // Either finished or destroyed.
+            _ => {
+                queue.0.pop_front();
+                return;
+            }
+        };
+
+        if (site_pos.0 - pos.0).mag_sq() > range_sq + max_speed.max_force() {
+            return;
+        }
+
+        if !site.advance(clock.delta) {
+            return;
+        }
+
+        queue.0.pop_front();
+
+        let mut entity = commands.entity(target);
+        entity.remove::<UnderConstruction>();
+
+        match site.structure {
+            StructureType::DefensePlatform => {
+                entity.insert_bundle(defense_platform_components());
+            }
+            StructureType::Shipyard => {
+                entity.insert_bundle(shipyard_components());
+            }
+            StructureType::Refinery => {
+                entity.insert_bundle(refinery_components());
+            }
+        }
+    });
+}
+
+pub fn refine(
+    refineries: Query<(&Refinery, Option<&Friendly>), Or<(With<Friendly>, With<Enemy>)>>,
+    clock: Res<SimClock>,
+    mut global_minerals: ResMut<PerSide<GlobalMinerals>>,
+    mut global_gas: ResMut<PerSide<GlobalGas>>,
+) {
+    refineries.for_each(|(refinery, friendly)| {
+        let side = Side::of(friendly);
+        let global_minerals = &mut global_minerals[side];
+        let minerals = (refinery.minerals_per_second * clock.delta).min(global_minerals.0);
+
+        if minerals > 0.0 {
+            global_minerals.0 -= minerals;
+            global_gas[side].0 += minerals * refinery.yield_ratio;
+        }
+    });
+}
@@ -2,10 +2,11 @@ use crate::find_functions::find_next_carrier;
 use crate::{average, get_scale, unload, unload_of_type, SelectedFriendly, UnloadParams};
 use bevy_ecs::prelude::*;
 use components_and_resources::components::*;
-use components_and_resources::formations::Formation;
+use components_and_resources::formations::{Formation, FormationShape};
 use components_and_resources::resources::*;
-use components_and_resources::utils::compare_floats;
-use ultraviolet::Vec3;
+use components_and_resources::spatial_hash::SpatialHash;
+use components_and_resources::utils::{compare_floats, uniform_sphere_distribution};
+use ultraviolet::{Rotor3, Vec3};
 
 pub fn find_ship_under_cursor(
     query: Query<
@@ -71,38 +72,40 @@ pub fn handle_left_click(
     ship_under_cursor: Res<ShipUnderCursor>,
     mut mouse_mode: ResMut<MouseMode>,
     keyboard_state: Res<KeyboardState>,
-    unit_buttons: Res<UnitButtons>,
-    selected_button: Res<SelectedButton>,
-    button_selection: Query<(Entity, &ModelId, Option<&Friendly>, Option<&Enemy>)>,
-    mut carrying: Query<(Entity, &Position, &mut Carrying), SelectedFriendly>,
-    mut movement: Query<(&mut Velocity, &mut CommandQueue)>,
-    models: Query<&ModelId>,
-    mut rng: ResMut<SmallRng>,
-    total_time: Res<TotalTime>,
+    // Grouped to stay within the limit on the number of system parameters.
+    (unit_buttons, selected_button, research_panel, crew_transfer_panel, production_panel): (
+        Res<UnitButtons>,
+        Res<SelectedButton>,
+        Res<ResearchPanel>,
+        Res<CrewTransferPanel>,
+        Res<ProductionPanel>,
+    ),
+    button_selection: Query<(Entity, &ModelId, Option<&Ours>, Option<&Theirs>)>,
+    carriers: Query<Entity, (SelectedFriendly, With<Carrying>)>,
+    mut player_commands: ResMut<PlayerCommands>,
+    on_screen: Query<
+        (Entity, &ModelId, &Position, Option<&Ours>, Option<&Theirs>),
+        With<Selectable>,
+    >,
+    perspective_view: Res<PerspectiveView>,
 ) {
-    if !mouse_button.left_state.was_clicked() {
+    // Handled by `cycle_throttle`.
+    if !mouse_button.left_state.was_clicked()
+        || unit_buttons.throttle_hovered
+        || research_panel.hovered.is_some()
+        || crew_transfer_panel.hovered.is_some()
+        || production_panel.hovered.is_some()
+    {
         return;
     }
 
     if let Some(button_index) = selected_button.0 {
-        if let Some((button_model, button_status)) = unit_buttons.0.get(button_index) {
+        if let Some((button_model, button_status)) = unit_buttons.buttons.get(button_index) {
             let is_being_carried = matches!(button_status, UnitStatus::Friendly { carried: true });
             if is_being_carried {
-                carrying.for_each_mut(|(entity, pos, mut carrying)| {
-                    unload_of_type(
-                        UnloadParams {
-                            entity,
-                            pos: pos.0,
-                            carrying: &mut carrying,
-                            rng: &mut rng,
-                            total_time: total_time.0,
-                            commands: &mut commands,
-                            movement: &mut movement,
-                            selected: true,
-                        },
-                        &models,
-                        *button_model,
-                    );
+                player_commands.local.push(PlayerCommand::Unload {
+                    carriers: carriers.iter().collect(),
+                    model: Some(*button_model),
                 });
                 return;
             }
@@ -119,11 +122,41 @@ pub fn handle_left_click(
         return;
     }
 
-    if matches!(*mouse_mode, MouseMode::Movement { .. }) {
+    if matches!(
+        *mouse_mode,
+        MouseMode::Movement { .. }
+            | MouseMode::AreaTargeting { .. }
+            | MouseMode::SetRallyPoint { .. }
+            | MouseMode::TransferCrew { .. }
+            | MouseMode::PlaceStructure { .. }
+    ) {
         *mouse_mode = MouseMode::Normal;
         return;
     }
 
+    // The first click has already taken care of the existing selection, so just add everything
+    // on screen that matches the ship that was double clicked.
+    let double_clicked = ship_under_cursor
+        .0
+        .filter(|_| mouse_button.left_state.was_double_clicked())
+        .and_then(|entity| button_selection.get(entity).ok());
+
+    if let Some((_, clicked_model, friendly, enemy)) = double_clicked {
+        let clicked_status = UnitStatus::from_bools(friendly.is_some(), enemy.is_some(), false);
+
+        on_screen.for_each(|(entity, model_id, position, friendly, enemy)| {
+            let matches = model_id == clicked_model
+                && clicked_status
+                    == UnitStatus::from_bools(friendly.is_some(), enemy.is_some(), false);
+
+            if matches && perspective_view.is_on_screen(position.0) {
+                commands.entity(entity).insert(Selected);
+            }
+        });
+
+        return;
+    }
+
     if !keyboard_state.shift {
         selected.for_each(|entity| {
             commands.entity(entity).remove::<Selected>();
@@ -175,61 +208,202 @@ pub fn handle_left_drag(
 }
 
 pub fn handle_right_clicks(
-    mut query_set: QuerySet<(
-        Query<(&Position, &mut CommandQueue), SelectedFriendly>,
-        Query<&mut CommandQueue, (SelectedFriendly, With<CanAttack>)>,
-        Query<&mut CommandQueue, (SelectedFriendly, With<CanBeCarried>)>,
-        Query<&mut CommandQueue, (SelectedFriendly, With<CanMine>)>,
+    selected: Query<
+        (
+            Entity,
+            &Position,
+            &ModelId,
+            Option<&MaxSpeed>,
+            Option<&Throttle>,
+        ),
+        (SelectedFriendly, With<CommandQueue>),
+    >,
+    can_attack: Query<Entity, (SelectedFriendly, With<CommandQueue>, With<CanAttack>)>,
+    can_be_carried: Query<Entity, (SelectedFriendly, With<CommandQueue>, With<CanBeCarried>)>,
+    // Grouped to stay within the limit on the number of system parameters.
+    (can_mine, can_harvest, can_trade): (
+        Query<Entity, (SelectedFriendly, With<CommandQueue>, With<CanMine>)>,
+        Query<Entity, (SelectedFriendly, With<CommandQueue>, With<CanHarvest>)>,
+        Query<Entity, (SelectedFriendly, With<CommandQueue>, With<CanTrade>)>,
+    ),
+    can_repair: Query<(Entity, &CanRepair), (SelectedFriendly, With<CommandQueue>)>,
+    targets: Query<(
+        Option<&Theirs>,
+        Option<&Carrying>,
+        Option<&Carryable>,
+        Option<&Ours>,
+        Option<&Health>,
+        Option<&Wreck>,
+        Option<&CanBeHarvested>,
+        Option<&TradingStation>,
     )>,
-    selected_models: Query<&ModelId, (SelectedFriendly, With<Position>, With<CommandQueue>)>,
-    enemies: Query<&Enemy>,
     mouse_button: Res<MouseState>,
     average_selected_position: Res<AverageSelectedPosition>,
     mut mouse_mode: ResMut<MouseMode>,
     ship_under_cursor: Res<ShipUnderCursor>,
-    can_carry: Query<&Carrying>,
     can_be_mined: Query<&Scale, With<CanBeMined>>,
     keyboard_state: Res<KeyboardState>,
+    mut player_commands: ResMut<PlayerCommands>,
+    mut pending_timing: ResMut<PendingOrderTiming>,
+    (formation_shape, group_speed): (Res<SelectedFormationShape>, Res<GroupSpeed>),
+    mut order_feedback: ResMut<OrderFeedback>,
 ) {
-    if !mouse_button.right_state.was_clicked() {
+    let dragged_out_movement = mouse_button.right_state.was_dragged().is_some()
+        && matches!(*mouse_mode, MouseMode::Movement { .. });
+
+    if !mouse_button.right_state.was_clicked() && !dragged_out_movement {
         return;
     }
 
-    match ship_under_cursor.0 {
+    // Handled by `place_rally_point`, `open_crew_transfer_panel`, `place_structure` and
+    // `place_minefield`.
+    if matches!(
+        *mouse_mode,
+        MouseMode::AreaTargeting { .. }
+            | MouseMode::SetRallyPoint { .. }
+            | MouseMode::TransferCrew { .. }
+            | MouseMode::PlaceStructure { .. }
+    ) {
+        return;
+    }
+
+    // The cursor ends up wherever the facing was dragged to, so ignore any ship under it.
+    match ship_under_cursor.0.filter(|_| !dragged_out_movement) {
         Some(target_entity) => {
-            if enemies.get(target_entity).is_ok() {
-                query_set.q1_mut().for_each_mut(|mut queue| {
-                    if !keyboard_state.shift {
-                        queue.0.clear();
-                    }
-                    queue.0.push_back(Command::Interact {
-                        target: target_entity,
-                        ty: InteractionType::Attack,
-                        range_sq: 0.0,
-                    });
-                });
-            } else if can_carry.get(target_entity).is_ok() {
-                query_set.q2_mut().for_each_mut(|mut queue| {
-                    if !keyboard_state.shift {
-                        queue.0.clear();
-                    }
-                    queue.0.push_back(Command::Interact {
-                        target: target_entity,
-                        ty: InteractionType::BeCarriedBy,
-                        range_sq: 0.0,
-                    });
-                });
+            let interact =
+                |ships: &mut dyn Iterator<Item = Entity>, ty, range_sq| PlayerCommand::Orders {
+                    orders: ships
+                        .map(|entity| {
+                            (
+                                entity,
+                                Command::Interact {
+                                    target: target_entity,
+                                    ty,
+                                    range_sq,
+                                },
+                            )
+                        })
+                        .collect(),
+                    queued: keyboard_state.shift,
+                    timing: pending_timing.0,
+                };
+
+            let (
+                enemy,
+                carrying,
+                carryable,
+                friendly,
+                health,
+                wreck,
+                can_be_harvested,
+                trading_station,
+            ) = targets
+                .get(target_entity)
+                .unwrap_or((None, None, None, None, None, None, None, None));
+
+            let damaged_friendly =
+                friendly.is_some() && health.map_or(false, |health| health.current < health.max);
+
+            // Clicking on something with nothing selected isn't an order, so it's not an error.
+            let anything_selected = selected.iter().next().is_some();
+
+            let command = if enemy.is_some() {
+                if can_attack.iter().next().is_some() {
+                    Ok(interact(
+                        &mut can_attack.iter(),
+                        InteractionType::Attack,
+                        0.0,
+                    ))
+                } else {
+                    Err("None of the selected ships can attack")
+                }
+            } else if damaged_friendly && can_repair.iter().next().is_some() {
+                // Only the repair ships get the order, the rest of the selection stays put.
+                Ok(PlayerCommand::Orders {
+                    orders: can_repair
+                        .iter()
+                        .filter(|&(entity, _)| entity != target_entity)
+                        .map(|(entity, can_repair)| {
+                            (
+                                entity,
+                                Command::Interact {
+                                    target: target_entity,
+                                    ty: InteractionType::Repair,
+                                    range_sq: can_repair.range * can_repair.range,
+                                },
+                            )
+                        })
+                        .collect(),
+                    queued: keyboard_state.shift,
+                    timing: pending_timing.0,
+                })
+            } else if carrying.is_some() {
+                if can_be_carried.iter().any(|entity| entity != target_entity) {
+                    Ok(interact(
+                        &mut can_be_carried.iter(),
+                        InteractionType::BeCarriedBy,
+                        0.0,
+                    ))
+                } else {
+                    Err("None of the selected ships can dock with a carrier")
+                }
+            } else if carryable.is_some() {
+                Ok(interact(
+                    &mut selected.iter().map(|(entity, ..)| entity),
+                    InteractionType::PickUp,
+                    4.0,
+                ))
+            } else if wreck.is_some() {
+                if can_mine.iter().next().is_some() {
+                    Ok(interact(
+                        &mut can_mine.iter(),
+                        InteractionType::Salvage,
+                        Wreck::SALVAGE_RANGE * Wreck::SALVAGE_RANGE,
+                    ))
+                } else {
+                    Err("None of the selected ships can salvage")
+                }
+            } else if can_be_harvested.is_some() {
+                if can_harvest.iter().next().is_some() {
+                    Ok(interact(
+                        &mut can_harvest.iter(),
+                        InteractionType::Harvest,
+                        CanBeHarvested::RADIUS * CanBeHarvested::RADIUS,
+                    ))
+                } else {
+                    Err("None of the selected ships can harvest gas")
+                }
+            } else if trading_station.is_some() {
+                if can_trade.iter().next().is_some() {
+                    Ok(interact(
+                        &mut can_trade.iter(),
+                        InteractionType::Trade,
+                        TradingStation::TRADE_RANGE * TradingStation::TRADE_RANGE,
+                    ))
+                } else {
+                    Err("None of the selected ships can trade")
+                }
             } else if let Ok(scale) = can_be_mined.get(target_entity) {
-                query_set.q3_mut().for_each_mut(|mut queue| {
-                    if !keyboard_state.shift {
-                        queue.0.clear();
-                    }
-                    queue.0.push_back(Command::Interact {
-                        target: target_entity,
-                        ty: InteractionType::Mine,
-                        range_sq: scale.range_sq(),
-                    });
-                });
+                if can_mine.iter().next().is_some() {
+                    Ok(interact(
+                        &mut can_mine.iter(),
+                        InteractionType::Mine,
+                        scale.range_sq(),
+                    ))
+                } else {
+                    Err("None of the selected ships can mine")
+                }
+            } else {
+                Err("The selected ships can't do anything with that")
+            };
+
+            match command {
+                Ok(command) => {
+                    pending_timing.0 = None;
+                    player_commands.local.push(command);
+                }
+                Err(reason) if anything_selected => order_feedback.rejected = Some(reason),
+                Err(_) => {}
             }
 
             *mouse_mode = MouseMode::Normal
@@ -240,68 +414,655 @@ pub fn handle_right_clicks(
                     Some(avg) => MouseMode::Movement {
                         point_on_plane: Vec3::new(0.0, avg.y, 0.0),
                         ty: MoveType::Normal,
+                        match_slowest: group_speed.0,
+                        facing: None,
                     },
                     _ => MouseMode::Normal,
                 },
-                MouseMode::Movement { ty, point_on_plane } => {
+                MouseMode::Movement {
+                    ty,
+                    point_on_plane,
+                    match_slowest,
+                    facing: hull_facing,
+                } => {
                     if let Some(avg) = average_selected_position.0 {
                         let mut count = 0;
                         let mut all_fighters = true;
+                        // The slowest ship leads the formation so that the others can keep up.
+                        let mut slowest: Option<(Entity, f32)> = None;
 
-                        selected_models.for_each(|&model_id| {
+                        selected.for_each(|(entity, _, &model_id, max_speed, throttle)| {
                             count += 1;
                             all_fighters &= model_id == ModelId::Fighter;
+
+                            if let Some(max_speed) = max_speed {
+                                // Throttled ships are only as fast as their throttle lets them be.
+                                let speed = max_speed.0 * Throttle::get(throttle).fraction();
+
+                                if slowest.map_or(true, |(_, slowest)| speed < slowest) {
+                                    slowest = Some((entity, speed));
+                                }
+                            }
                         });
 
-                        let mut formation = if count == 1 {
-                            Formation::at_point(point_on_plane, count)
-                        } else if all_fighters {
-                            Formation::fighter_screen(
-                                point_on_plane,
-                                (point_on_plane - avg).normalized(),
-                                count,
-                                5.0,
-                            )
-                        } else {
-                            Formation::in_sphere(point_on_plane, count)
+                        let speed_cap = slowest
+                            .map(|(_, slowest)| slowest)
+                            .filter(|_| match_slowest);
+
+                        let facing = (point_on_plane - avg).normalized();
+
+                        let mut formation = match formation_shape.0 {
+                            _ if count == 1 => Formation::at_point(point_on_plane, count),
+                            FormationShape::Auto if all_fighters => {
+                                Formation::fighter_screen(point_on_plane, facing, count, 5.0)
+                            }
+                            FormationShape::Auto | FormationShape::Sphere => {
+                                Formation::in_sphere(point_on_plane, count)
+                            }
+                            FormationShape::Wedge => {
+                                Formation::wedge(point_on_plane, facing, count, 10.0)
+                            }
+                            FormationShape::Line => {
+                                Formation::line(point_on_plane, facing, count, 10.0)
+                            }
                         };
 
-                        query_set.q0_mut().for_each_mut(|(pos, mut queue)| {
-                            queue.0.clear();
-                            if let Some(point) = formation.choose_position(pos.0) {
-                                queue.0.push_back(Command::MoveTo { point, ty });
+                        let mut orders = Vec::new();
+                        let mut facing_orders = Vec::new();
+                        let mut without_a_position = Vec::new();
+
+                        selected.for_each(|(entity, pos, &model_id, ..)| {
+                            match formation.choose_position(pos.0) {
+                                Some(point) => {
+                                    orders.push((
+                                        entity,
+                                        Command::MoveTo {
+                                            point,
+                                            ty,
+                                            speed_cap,
+                                        },
+                                    ));
+
+                                    if let Some(direction) =
+                                        hull_facing.filter(|_| model_id.is_capital())
+                                    {
+                                        facing_orders.push((entity, Command::Face { direction }));
+                                    }
+                                }
+                                None => without_a_position.push(entity),
                             }
                         });
+
+                        let leader = slowest.and_then(|(leader, _)| {
+                            orders.iter().find_map(|&(entity, command)| match command {
+                                Command::MoveTo { point, .. } if entity == leader => {
+                                    Some((leader, point))
+                                }
+                                _ => None,
+                            })
+                        });
+
+                        let formation_slots =
+                            leader.filter(|_| count > 1).map(|(leader, leader_point)| {
+                                PlayerCommand::Formation {
+                                    leader,
+                                    followers: orders
+                                        .iter()
+                                        .filter_map(|&(entity, command)| match command {
+                                            Command::MoveTo { point, .. } if entity != leader => {
+                                                Some((entity, point - leader_point))
+                                            }
+                                            _ => None,
+                                        })
+                                        .collect(),
+                                }
+                            });
+
+                        orders.extend(facing_orders);
+
+                        player_commands.local.push(PlayerCommand::Orders {
+                            orders,
+                            queued: false,
+                            timing: pending_timing.0.take(),
+                        });
+                        player_commands.local.push(PlayerCommand::Stop {
+                            ships: without_a_position,
+                        });
+                        player_commands.local.extend(formation_slots);
                     }
 
                     MouseMode::Normal
                 }
+                MouseMode::AreaTargeting { .. }
+                | MouseMode::SetRallyPoint { .. }
+                | MouseMode::TransferCrew { .. }
+                | MouseMode::PlaceStructure { .. } => MouseMode::Normal,
             };
         }
     }
 }
 
-pub fn update_mouse_state(mut mouse_state: ResMut<MouseState>, delta_time: Res<DeltaTime>) {
-    mouse_state.left_state.update(delta_time.0, 0.1);
-    mouse_state.right_state.update(delta_time.0, 0.1);
-    mouse_state.middle_state.update(delta_time.0, 0.0);
+pub fn apply_player_commands(
+    mut player_commands: ResMut<PlayerCommands>,
+    mut movement: Query<(&mut Velocity, &mut CommandQueue)>,
+    mut mine_layers: Query<(&Position, &mut MineLayer, Option<&Friendly>, Option<&Enemy>)>,
+    cloak_energy: Query<&CloakEnergy>,
+    mut tech_tree: ResMut<PerSide<TechTree>>,
+    mut on_board: Query<&mut OnBoard>,
+    carriers: Query<(&Position, Option<&Friendly>, Option<&Enemy>), With<Carrying>>,
+    builders: Query<(Option<&Friendly>, Option<&Enemy>), With<CanConstruct>>,
+    // Grouped to stay within the limit on the number of system parameters.
+    (
+        mut global_credits,
+        mut global_gas,
+        mut global_minerals,
+        ship_catalog,
+        mut global_rules,
+        mut save_load_request,
+    ): (
+        ResMut<PerSide<GlobalCredits>>,
+        ResMut<PerSide<GlobalGas>>,
+        ResMut<PerSide<GlobalMinerals>>,
+        Res<ShipCatalog>,
+        ResMut<PerSide<GlobalRulesOfEngagement>>,
+        ResMut<SaveLoadRequest>,
+    ),
+    (mut carrying, mut build_queues, models): (
+        Query<(&Position, &mut Carrying, Option<&Selected>)>,
+        Query<&mut BuildQueue>,
+        Query<&ModelId>,
+    ),
+    mut clock: ResMut<SimClock>,
+    mut paused: ResMut<Paused>,
+    mut notifications: ResMut<Notifications>,
+    real_time: Res<RealTime>,
+    mut rng: ResMut<SmallRng>,
+    mut commands: Commands,
+) {
+    let mut pending: std::collections::VecDeque<_> = player_commands.to_apply.drain(..).collect();
+
+    while let Some((side, player_command)) = pending.pop_front() {
+        if paused.0 && !matches!(player_command, PlayerCommand::SetPaused(_)) {
+            player_commands.deferred.push((side, player_command));
+            continue;
+        }
+
+        let global_credits = &mut global_credits[side];
+        let global_gas = &mut global_gas[side];
+        let global_minerals = &mut global_minerals[side];
+
+        match player_command {
+            PlayerCommand::Orders {
+                orders,
+                queued,
+                timing,
+            } => {
+                if !queued {
+                    for &(entity, _) in &orders {
+                        if let Ok((_, mut queue)) = movement.get_mut(entity) {
+                            queue.0.clear();
+                            commands
+                                .entity(entity)
+                                .remove::<FormationSlot>()
+                                .remove::<HoldPosition>()
+                                .remove::<Escorting>()
+                                .remove::<Leash>();
+                        }
+                    }
+                }
+
+                let mut waiting = std::collections::HashSet::new();
+
+                for (entity, command) in orders {
+                    if let Ok((_, mut queue)) = movement.get_mut(entity) {
+                        // A ship holding position would otherwise never get round to queued
+                        // orders.
+                        if queued {
+                            commands.entity(entity).remove::<HoldPosition>();
+                        }
+
+                        if let Some(timing) = timing {
+                            // Only wait before the first of each ship's orders.
+                            if waiting.insert(entity) {
+                                queue.0.push_back(Command::Wait(timing.wait_for(&clock)));
+                            }
+                        }
+
+                        queue.0.push_back(command);
+                    }
+                }
+            }
+            PlayerCommand::Stop { ships } => {
+                for entity in ships {
+                    if let Ok((_, mut queue)) = movement.get_mut(entity) {
+                        queue.0.clear();
+                        commands
+                            .entity(entity)
+                            .remove::<FormationSlot>()
+                            .remove::<Escorting>()
+                            .remove::<Leash>();
+                    }
+                }
+            }
+            PlayerCommand::Formation { leader, followers } => {
+                for (entity, offset) in followers {
+                    commands
+                        .entity(entity)
+                        .insert(FormationSlot { leader, offset });
+                }
+            }
+            PlayerCommand::HoldPosition { ships, hold } => {
+                for entity in ships {
+                    if let Ok((_, mut queue)) = movement.get_mut(entity) {
+                        if hold {
+                            queue.0.clear();
+                            commands
+                                .entity(entity)
+                                .insert(HoldPosition)
+                                .remove::<FormationSlot>()
+                                .remove::<Escorting>();
+                        } else {
+                            commands.entity(entity).remove::<HoldPosition>();
+                        }
+                    }
+                }
+            }
+            PlayerCommand::SetDropOff { miners, carrier } => {
+                for entity in miners {
+                    match carrier {
+                        Some(carrier) => commands.entity(entity).insert(DropOff(carrier)),
+                        None => commands.entity(entity).remove::<DropOff>(),
+                    };
+                }
+            }
+            PlayerCommand::LayMine { ships } => {
+                for entity in ships {
+                    if let Ok((position, mut mine_layer, friendly, enemy)) =
+                        mine_layers.get_mut(entity)
+                    {
+                        if mine_layer.mines == 0 {
+                            continue;
+                        }
+
+                        mine_layer.mines -= 1;
+                        spawn_mine(&mut commands, position.0, &mut rng, friendly, enemy);
+                    }
+                }
+            }
+            PlayerCommand::LayMinefield { ships, point } => {
+                for entity in ships {
+                    if let Ok((_, mut queue)) = movement.get_mut(entity) {
+                        queue.0.clear();
+                        queue.0.push_back(Command::MoveTo {
+                            point,
+                            ty: MoveType::Normal,
+                            speed_cap: None,
+                        });
+                        commands
+                            .entity(entity)
+                            .remove::<FormationSlot>()
+                            .remove::<HoldPosition>()
+                            .remove::<Escorting>()
+                            .remove::<Leash>()
+                            .insert(MinefieldDrop { point });
+                    }
+                }
+            }
+            PlayerCommand::Cloak { ships, cloak } => {
+                for entity in ships {
+                    match cloak_energy.get(entity) {
+                        Ok(energy) if cloak && energy.current > 0.0 => {
+                            commands.entity(entity).insert(Cloaked);
+                        }
+                        _ => {
+                            commands.entity(entity).remove::<Cloaked>();
+                        }
+                    }
+                }
+            }
+            PlayerCommand::SetCarrierDoctrine { carriers, doctrine } => {
+                for entity in carriers {
+                    commands.entity(entity).insert(doctrine);
+                }
+            }
+            PlayerCommand::SetAutonomy { ships, autonomy } => {
+                for entity in ships {
+                    commands.entity(entity).insert(autonomy);
+                }
+            }
+            PlayerCommand::SetRetreat { ships, retreat } => {
+                for entity in ships {
+                    if retreat {
+                        commands
+                            .entity(entity)
+                            .insert(RetreatPolicy::new(RetreatPolicy::DEFAULT_HEALTH_THRESHOLD));
+                    } else {
+                        commands.entity(entity).remove::<RetreatPolicy>();
+                    }
+                }
+            }
+            PlayerCommand::SetStance { ships, stance } => {
+                for entity in ships {
+                    commands.entity(entity).insert(stance).remove::<Leash>();
+                }
+            }
+            PlayerCommand::SetThrottle { ships, throttle } => {
+                for entity in ships {
+                    commands.entity(entity).insert(throttle);
+                }
+            }
+            PlayerCommand::SetGameSpeed(speed) => clock.set_speed(speed),
+            PlayerCommand::SetPaused(pause) => {
+                if pause != paused.0 {
+                    paused.0 = pause;
+                    let message = if pause { "Game paused" } else { "Game resumed" };
+                    notifications.push(message, real_time.0);
+
+                    // Carry out the orders given while paused before any that came after.
+                    if !pause {
+                        for deferred in player_commands.deferred.drain(..).rev() {
+                            pending.push_front(deferred);
+                        }
+                    }
+                }
+            }
+            PlayerCommand::SetTargetingStance { ships, stance } => {
+                for entity in ships {
+                    commands.entity(entity).insert(stance);
+                }
+            }
+            PlayerCommand::SetRallyPoint { carriers, point } => {
+                for entity in carriers {
+                    commands.entity(entity).insert(RallyPoint(point));
+                }
+            }
+            PlayerCommand::Escort { carrier, escorts } => {
+                for (entity, offset) in escorts {
+                    if let Ok((_, mut queue)) = movement.get_mut(entity) {
+                        queue.0.clear();
+                        commands
+                            .entity(entity)
+                            .insert(Escorting { carrier, offset })
+                            .remove::<FormationSlot>()
+                            .remove::<HoldPosition>();
+                    }
+                }
+            }
+            PlayerCommand::Research(tech) => tech_tree[side].research(tech),
+            PlayerCommand::TransferCrew { person, from, to } => {
+                // Either ship could have been destroyed since the command was given.
+                if on_board.get_mut(to).is_err() {
+                    continue;
+                }
+
+                let removed = on_board.get_mut(from).ok().and_then(|mut on_board| {
+                    let index = on_board.0.iter().position(|&entity| entity == person)?;
+                    Some(on_board.0.remove(index))
+                });
+
+                if let (Some(person), Ok(mut on_board)) = (removed, on_board.get_mut(to)) {
+                    on_board.0.push(person);
+                }
+            }
+            PlayerCommand::Purchase { purchase, carrier } => {
+                let (position, friendly, enemy) = match carriers.get(carrier) {
+                    Ok(carrier) => carrier,
+                    Err(_) => continue,
+                };
+
+                // Checked again here as credits may have been spent since the order was given.
+                if global_credits.0 < purchase.cost() {
+                    continue;
+                }
+
+                global_credits.0 -= purchase.cost();
+
+                match purchase {
+                    SpecialPurchase::Mercenaries => {
+                        for _ in 0..SpecialPurchase::MERCENARY_FIGHTERS {
+                            let offset = uniform_sphere_distribution(&mut *rng) * 10.0;
+
+                            let mut spawner = commands.spawn();
+                            spawner
+                                .insert_bundle(base_ship_components(position.0 + offset))
+                                .insert_bundle(fighter_components(0.0));
+
+                            if friendly.is_some() {
+                                spawner.insert(Friendly);
+                            }
+
+                            if enemy.is_some() {
+                                spawner.insert(Enemy);
+                            }
+                        }
+                    }
+                    SpecialPurchase::GasShipment => global_gas.0 += SpecialPurchase::GAS_SHIPMENT,
+                }
+            }
+            PlayerCommand::Construct {
+                ships,
+                structure,
+                point,
+            } => {
+                // The builders could have been destroyed since the order was given.
+                let (friendly, enemy) = match ships.iter().find_map(|&ship| builders.get(ship).ok())
+                {
+                    Some(builder) => builder,
+                    None => continue,
+                };
+
+                if global_minerals.0 < structure.cost() {
+                    continue;
+                }
+
+                global_minerals.0 -= structure.cost();
+
+                let mut spawner = commands.spawn();
+                spawner.insert_bundle(construction_site_components(point, structure));
+
+                if friendly.is_some() {
+                    spawner.insert(Friendly);
+                }
+
+                if enemy.is_some() {
+                    spawner.insert(Enemy);
+                }
+
+                let site = spawner.id();
+
+                for entity in ships {
+                    if let Ok((_, mut queue)) = movement.get_mut(entity) {
+                        queue.0.clear();
+                        queue.0.push_back(Command::Interact {
+                            target: site,
+                            ty: InteractionType::Construct,
+                            range_sq: UnderConstruction::CONSTRUCTION_RANGE.powi(2),
+                        });
+                        commands
+                            .entity(entity)
+                            .remove::<FormationSlot>()
+                            .remove::<HoldPosition>()
+                            .remove::<Escorting>();
+                    }
+                }
+            }
+            PlayerCommand::Build { carriers, ship } => {
+                let cost = ship_catalog.build_cost(ship);
+
+                // Checked again here as minerals and gas may have been spent since the order was
+                // given.
+                if cost.minerals > global_minerals.0 || cost.gas > global_gas.0 {
+                    continue;
+                }
+
+                let best_carrier = carriers
+                    .into_iter()
+                    .filter_map(|entity| {
+                        let queue = build_queues.get_mut(entity).ok()?;
+                        Some((queue.queue_length(&clock, &ship_catalog), entity))
+                    })
+                    .min_by(|&(a, _), &(b, _)| compare_floats(a, b));
+
+                if let Some((_, entity)) = best_carrier {
+                    if let Ok(mut queue) = build_queues.get_mut(entity) {
+                        global_minerals.0 -= cost.minerals;
+                        global_gas.0 -= cost.gas;
+                        queue.push(ship, &clock, &ship_catalog);
+                    }
+                }
+            }
+            PlayerCommand::CancelBuildQueues { carriers } => {
+                for entity in carriers {
+                    if let Ok(mut queue) = build_queues.get_mut(entity) {
+                        let refund = queue.cancel_all(&ship_catalog);
+                        global_minerals.0 += refund.minerals;
+                        global_gas.0 += refund.gas;
+                    }
+                }
+            }
+            PlayerCommand::CancelBuild {
+                carrier,
+                index,
+                ship,
+            } => {
+                if let Ok(mut queue) = build_queues.get_mut(carrier) {
+                    if queue.get(index) != Some(ship) {
+                        continue;
+                    }
+
+                    if let Some(refund) = queue.cancel(index, &clock, &ship_catalog) {
+                        global_minerals.0 += refund.minerals;
+                        global_gas.0 += refund.gas;
+                    }
+                }
+            }
+            PlayerCommand::ReorderBuild {
+                carrier,
+                index,
+                ship,
+                earlier,
+            } => {
+                if let Ok(mut queue) = build_queues.get_mut(carrier) {
+                    if queue.get(index) != Some(ship) {
+                        continue;
+                    }
+
+                    // Moving a ship down is the same as moving the one behind it up.
+                    let index = if earlier { index } else { index + 1 };
+                    queue.move_earlier(index, &clock, &ship_catalog);
+                }
+            }
+            PlayerCommand::Unload { carriers, model } => {
+                for entity in carriers {
+                    if let Ok((position, mut carrying, selected)) = carrying.get_mut(entity) {
+                        // Selections aren't shared, so the other player's unloaded ships don't
+                        // get selected here.
+                        let params = UnloadParams {
+                            entity,
+                            pos: position.0,
+                            carrying: &mut carrying,
+                            rng: &mut rng,
+                            clock: &clock,
+                            commands: &mut commands,
+                            movement: &mut movement,
+                            selected: selected.is_some(),
+                        };
+
+                        match model {
+                            Some(model) => unload_of_type(params, &models, model),
+                            None => unload(params),
+                        }
+                    }
+                }
+            }
+            PlayerCommand::SetStayCarried {
+                carriers,
+                stay_carried,
+            } => {
+                for entity in carriers {
+                    if let Ok(mut queue) = build_queues.get_mut(entity) {
+                        queue.stay_carried = stay_carried;
+                    }
+                }
+            }
+            PlayerCommand::Scuttle { ships, scuttle } => {
+                for entity in ships {
+                    match models.get(entity) {
+                        Ok(model_id) if scuttle => {
+                            commands
+                                .entity(entity)
+                                .insert(Scuttling::new(*model_id, &clock));
+                        }
+                        _ => {
+                            commands.entity(entity).remove::<Scuttling>();
+                        }
+                    }
+                }
+            }
+            PlayerCommand::SetRulesOfEngagement(rules) => global_rules[side].0 = rules,
+            PlayerCommand::SetRulesOfEngagementOverride { ships, rules } => {
+                for entity in ships {
+                    match rules {
+                        Some(rules) => {
+                            commands
+                                .entity(entity)
+                                .insert(RulesOfEngagementOverride(rules));
+                        }
+                        None => {
+                            commands
+                                .entity(entity)
+                                .remove::<RulesOfEngagementOverride>();
+                        }
+                    }
+                }
+            }
+            PlayerCommand::QuickLoad => save_load_request.0 = Some(SaveLoad::Load),
+            PlayerCommand::Mark => {
+                movement.for_each_mut(|(_, mut queue)| {
+                    if let Some(Command::Wait(WaitFor::Mark)) = queue.0.front() {
+                        queue.0.pop_front();
+                    }
+                });
+            }
+        }
+    }
+}
+
+// Input is timed in real time so that it doesn't change with the game speed.
+pub fn update_mouse_state(mut mouse_state: ResMut<MouseState>) {
+    mouse_state.left_state.update(SimClock::TICK, 0.1);
+    mouse_state.right_state.update(SimClock::TICK, 0.1);
+    mouse_state.middle_state.update(SimClock::TICK, 0.0);
 }
 
 pub fn update_ray_plane_point(
     ray: Res<Ray>,
     mut mouse_mode: ResMut<MouseMode>,
     keyboard_state: Res<KeyboardState>,
+    mouse_state: Res<MouseState>,
 ) {
     if let MouseMode::Movement {
         ref mut point_on_plane,
+        ref mut facing,
         ..
     } = &mut *mouse_mode
     {
-        if !keyboard_state.shift {
-            if let Some(point) = ray
-                .y_plane_intersection(point_on_plane.y)
-                .map(|t| ray.get_intersection_point(t))
-            {
+        let point = ray
+            .y_plane_intersection(point_on_plane.y)
+            .map(|t| ray.get_intersection_point(t));
+
+        if mouse_state.right_state.is_being_dragged().is_some() {
+            // Keep the destination where it is and point the ships towards the cursor instead.
+            if let Some(point) = point {
+                let direction =
+                    Vec3::new(point.x - point_on_plane.x, 0.0, point.z - point_on_plane.z);
+
+                if direction.mag_sq() > 0.0 {
+                    *facing = Some(direction.normalized());
+                }
+            }
+        } else if !keyboard_state.shift {
+            if let Some(point) = point {
                 point_on_plane.x = point.x;
                 point_on_plane.z = point.z;
             }
@@ -309,6 +1070,59 @@ pub fn update_ray_plane_point(
     }
 }
 
+pub fn update_rally_point_on_plane(ray: Res<Ray>, mut mouse_mode: ResMut<MouseMode>) {
+    if let MouseMode::SetRallyPoint {
+        ref mut point_on_plane,
+    } = &mut *mouse_mode
+    {
+        if let Some(t) = ray.y_plane_intersection(point_on_plane.y) {
+            *point_on_plane = ray.get_intersection_point(t);
+        }
+    }
+}
+
+pub fn update_area_targeting_point(
+    ray: Res<Ray>,
+    mut mouse_mode: ResMut<MouseMode>,
+    tlas: Res<TopLevelAccelerationStructure>,
+    ships: Query<(&Position, &RotationMatrix, &ModelId, Option<&Scale>)>,
+    models: Res<Models>,
+) {
+    if let MouseMode::AreaTargeting { ref mut point, .. } = &mut *mouse_mode {
+        let mut stack = Vec::with_capacity(10);
+
+        let first_hit = tlas
+            .find(
+                |bounding_box| ray.bounding_box_intersection(bounding_box).is_some(),
+                &mut stack,
+            )
+            .filter_map(|&entity| ships.get(entity).ok())
+            .flat_map(|(position, rotation, model_id, scale)| {
+                let scale = get_scale(scale);
+
+                let ray = ray.centered_around_transform(position.0, rotation.reversed, scale);
+
+                models
+                    .get(*model_id)
+                    .acceleration_tree
+                    .find_with_owned_stack(
+                        move |bbox| ray.bounding_box_intersection(bbox).is_some(),
+                        Vec::with_capacity(10),
+                    )
+                    .filter_map(move |triangle| ray.triangle_intersection(triangle))
+                    .map(move |t| t * scale)
+            })
+            .min_by(|&a, &b| compare_floats(a, b));
+
+        // Without anything under the cursor, keep the preview at the same height.
+        let t = first_hit.or_else(|| ray.y_plane_intersection(point.y));
+
+        if let Some(t) = t {
+            *point = ray.get_intersection_point(t);
+        }
+    }
+}
+
 pub fn move_camera(
     kbd: Res<KeyboardState>,
     orbit: Res<Orbit>,
@@ -342,50 +1156,47 @@ pub fn move_camera(
 }
 
 pub fn handle_keys(
-    mut query_set: QuerySet<(
-        Query<&mut CommandQueue, SelectedFriendly>,
-        Query<(&mut Velocity, &mut CommandQueue)>,
-        Query<(&Position, &mut CommandQueue), (SelectedFriendly, With<CanBeCarried>)>,
-    )>,
-    mut commands: Commands,
+    selected: Query<Entity, (SelectedFriendly, With<CommandQueue>)>,
+    to_load: Query<(Entity, &Position), (SelectedFriendly, With<CommandQueue>, With<CanBeCarried>)>,
     keyboard_state: Res<KeyboardState>,
-    mut paused: ResMut<Paused>,
-    mut carrying: Query<(Entity, &Position, &mut Carrying), SelectedFriendly>,
-    mut rng: ResMut<SmallRng>,
+    paused: Res<Paused>,
+    selected_carriers: Query<Entity, (SelectedFriendly, With<Carrying>)>,
     average_selected_position: Res<AverageSelectedPosition>,
     mut mouse_mode: ResMut<MouseMode>,
-    total_time: Res<TotalTime>,
     carriers: Query<(Entity, &Position), (With<Carrying>, Without<CarrierFull>)>,
-    mut build_queues: Query<&mut BuildQueue, SelectedFriendly>,
-    mut global_minerals: ResMut<GlobalMinerals>,
+    build_queues: Query<Entity, (SelectedFriendly, With<BuildQueue>)>,
+    mut group_speed: ResMut<GroupSpeed>,
+    scuttling: Query<Entity, (SelectedFriendly, With<Scuttling>)>,
+    mut player_commands: ResMut<PlayerCommands>,
 ) {
     if keyboard_state.stop.0 {
-        query_set.q0_mut().for_each_mut(|mut queue| {
-            queue.0.clear();
+        player_commands.local.push(PlayerCommand::Stop {
+            ships: selected.iter().collect(),
+        });
+
+        player_commands.local.push(PlayerCommand::Scuttle {
+            ships: scuttling.iter().collect(),
+            scuttle: false,
         });
     }
 
+    // Goes through the other player like any other order so that both games stop on the same tick.
     if keyboard_state.pause.0 {
-        paused.0 = !paused.0;
+        player_commands
+            .local
+            .push(PlayerCommand::SetPaused(!paused.0));
     }
 
     if keyboard_state.unload.0 {
-        carrying.for_each_mut(|(entity, pos, mut carrying)| {
-            unload(UnloadParams {
-                entity,
-                pos: pos.0,
-                carrying: &mut carrying,
-                rng: &mut *rng,
-                total_time: total_time.0,
-                commands: &mut commands,
-                movement: &mut query_set.q1_mut(),
-                selected: true,
-            });
+        player_commands.local.push(PlayerCommand::Unload {
+            carriers: selected_carriers.iter().collect(),
+            model: None,
         });
 
-        build_queues.for_each_mut(|mut queue| {
-            queue.stay_carried = false;
-        })
+        player_commands.local.push(PlayerCommand::SetStayCarried {
+            carriers: build_queues.iter().collect(),
+            stay_carried: false,
+        });
     }
 
     if keyboard_state.escape.0 {
@@ -400,21 +1211,46 @@ pub fn handle_keys(
                     *mouse_mode = MouseMode::Movement {
                         point_on_plane: Vec3::new(0.0, avg.y, 0.0),
                         ty: MoveType::Attack,
+                        match_slowest: group_speed.0,
+                        facing: None,
                     };
                 }
             }
         }
     }
 
+    // Sticks for later orders as well as the one being given.
+    if keyboard_state.match_slowest.0 {
+        group_speed.0 = !group_speed.0;
+
+        if let MouseMode::Movement {
+            ref mut match_slowest,
+            ..
+        } = *mouse_mode
+        {
+            *match_slowest = group_speed.0;
+        }
+    }
+
     if keyboard_state.load.0 {
-        query_set.q2_mut().for_each_mut(|(pos, mut command_queue)| {
-            command_queue.0.clear();
-            find_next_carrier(pos.0, &mut command_queue, carriers.iter())
+        let mut orders = Vec::new();
+
+        to_load.for_each(|(entity, pos)| {
+            let mut command_queue = CommandQueue::default();
+            find_next_carrier(pos.0, &mut command_queue, carriers.iter());
+            orders.extend(command_queue.0.into_iter().map(|command| (entity, command)));
         });
 
-        build_queues.for_each_mut(|mut queue| {
-            queue.stay_carried = true;
-        })
+        player_commands.local.push(PlayerCommand::Orders {
+            orders,
+            queued: false,
+            timing: None,
+        });
+
+        player_commands.local.push(PlayerCommand::SetStayCarried {
+            carriers: build_queues.iter().collect(),
+            stay_carried: true,
+        });
     }
 
     let build_ship_type = if keyboard_state.build_fighter.0 {
@@ -423,27 +1259,715 @@ pub fn handle_keys(
         Some(ShipType::Miner)
     } else if keyboard_state.build_carrier.0 {
         Some(ShipType::Carrier)
+    } else if keyboard_state.build_minelayer.0 {
+        Some(ShipType::Minelayer)
+    } else if keyboard_state.build_repair_ship.0 {
+        Some(ShipType::RepairShip)
+    } else if keyboard_state.build_gas_harvester.0 {
+        Some(ShipType::GasHarvester)
+    } else if keyboard_state.build_freighter.0 {
+        Some(ShipType::Freighter)
+    } else if keyboard_state.build_construction_ship.0 {
+        Some(ShipType::ConstructionShip)
+    } else if keyboard_state.build_frigate.0 {
+        Some(ShipType::Frigate)
+    } else if keyboard_state.build_destroyer.0 {
+        Some(ShipType::Destroyer)
+    } else if keyboard_state.build_battleship.0 {
+        Some(ShipType::Battleship)
+    } else if keyboard_state.build_drone.0 {
+        Some(ShipType::Drone)
     } else {
         None
     };
 
-    if let Some(build_ship_type) = build_ship_type {
-        let cost = build_ship_type.build_cost();
-        if cost <= global_minerals.0 {
-            global_minerals.0 -= cost;
+    if let Some(ship) = build_ship_type {
+        player_commands.local.push(PlayerCommand::Build {
+            carriers: build_queues.iter().collect(),
+            ship,
+        });
+    }
+}
+
+pub fn handle_order_timing_keys(
+    keyboard_state: Res<KeyboardState>,
+    mut pending_timing: ResMut<PendingOrderTiming>,
+    mut player_commands: ResMut<PlayerCommands>,
+) {
+    if keyboard_state.order_timing.0 {
+        pending_timing.cycle();
+    }
+
+    if keyboard_state.mark.0 {
+        player_commands.local.push(PlayerCommand::Mark);
+    }
+}
+
+// Goes through the other player in multiplayer so that both games speed up on the same tick.
+pub fn change_game_speed(
+    keyboard_state: Res<KeyboardState>,
+    clock: Res<SimClock>,
+    mut player_commands: ResMut<PlayerCommands>,
+) {
+    let speed = clock.speed();
+
+    let new_speed = if keyboard_state.faster_game_speed.0 {
+        speed.faster()
+    } else if keyboard_state.slower_game_speed.0 {
+        speed.slower()
+    } else {
+        return;
+    };
+
+    if new_speed != speed {
+        player_commands
+            .local
+            .push(PlayerCommand::SetGameSpeed(new_speed));
+    }
+}
+
+pub fn cycle_formation_shape(
+    keyboard_state: Res<KeyboardState>,
+    mut formation_shape: ResMut<SelectedFormationShape>,
+) {
+    if keyboard_state.cycle_formation.0 {
+        formation_shape.0 = formation_shape.0.next();
+    }
+}
+
+pub fn handle_control_group_keys(
+    keyboard_state: Res<KeyboardState>,
+    mut control_groups: ResMut<ControlGroups>,
+    selected: Query<Entity, With<Selected>>,
+    selected_friendly: Query<Entity, SelectedFriendly>,
+    selected_carriers: Query<Entity, (SelectedFriendly, With<BuildQueue>)>,
+    // Carried ships stay in their groups but can't be selected until they're unloaded.
+    ships: Query<Option<&Position>, With<Selectable>>,
+    currently_following: Query<Entity, With<CameraFollowing>>,
+    mut notifications: ResMut<Notifications>,
+    real_time: Res<RealTime>,
+    mut commands: Commands,
+) {
+    for (i, tapped) in keyboard_state.control_groups.iter().enumerate() {
+        if !tapped.0 {
+            continue;
+        }
+
+        if keyboard_state.control && keyboard_state.shift {
+            let pool = selected_carriers.iter().next();
+            control_groups.reinforcement_pools[i] = pool;
+
+            let message = match pool {
+                Some(_) => format!("Group {}: reinforcing from the selected carrier", i + 1),
+                None => format!("Group {}: no longer being reinforced", i + 1),
+            };
+            notifications.push(message, real_time.0);
+            continue;
+        }
+
+        if keyboard_state.control {
+            control_groups.assign(i, selected_friendly.iter().collect());
+            continue;
+        }
+
+        let double_tapped = control_groups.recall(i);
+
+        let lost = control_groups.take_losses(i);
+
+        if lost > 0 {
+            notifications.push(
+                format!(
+                    "Group {}: {} ship{} lost since last recall",
+                    i + 1,
+                    lost,
+                    if lost == 1 { "" } else { "s" }
+                ),
+                real_time.0,
+            );
+        }
 
-            let best_queue = build_queues
-                .iter_mut()
-                .map(|queue| (queue.queue_length(total_time.0), queue))
-                .min_by(|&(a, _), &(b, _)| compare_floats(a, b));
+        let group = &control_groups.groups[i];
 
-            if let Some((_, mut queue)) = best_queue {
-                queue.push(build_ship_type, total_time.0);
+        if group.is_empty() {
+            continue;
+        }
+
+        if !keyboard_state.shift {
+            selected.for_each(|entity| {
+                commands.entity(entity).remove::<Selected>();
+            });
+        }
+
+        for &entity in group.iter() {
+            if let Ok(Some(_)) = ships.get(entity) {
+                commands.entity(entity).insert(Selected);
+            }
+        }
+
+        if double_tapped {
+            currently_following.for_each(|entity| {
+                commands.entity(entity).remove::<CameraFollowing>();
+            });
+
+            for &entity in group.iter() {
+                if let Ok(Some(_)) = ships.get(entity) {
+                    commands.entity(entity).insert(CameraFollowing);
+                }
             }
         }
     }
 }
 
+// Done every frame rather than just on recall so that reinforcements are built as soon as ships are
+// lost.
+pub fn track_control_group_losses(
+    mut control_groups: ResMut<ControlGroups>,
+    ships: Query<(), With<Selectable>>,
+) {
+    control_groups.remove_dead(|entity| ships.get(entity).is_ok());
+}
+
+// Saving doesn't change the game, so only loading has to go through the other player.
+pub fn handle_save_load_keys(
+    keyboard_state: Res<KeyboardState>,
+    mut save_load_request: ResMut<SaveLoadRequest>,
+    mut player_commands: ResMut<PlayerCommands>,
+) {
+    if keyboard_state.quick_save.0 {
+        save_load_request.0 = Some(SaveLoad::Save);
+    }
+
+    if keyboard_state.quick_load.0 {
+        player_commands.local.push(PlayerCommand::QuickLoad);
+    }
+}
+
+// Toggles holding position, unless only some of the selected ships are already holding, in which
+// case they all start holding.
+pub fn toggle_hold_position(
+    keyboard_state: Res<KeyboardState>,
+    selected: Query<(Entity, Option<&HoldPosition>), (SelectedFriendly, With<CommandQueue>)>,
+    mut player_commands: ResMut<PlayerCommands>,
+) {
+    if !keyboard_state.hold_position.0 {
+        return;
+    }
+
+    let mut all_holding = true;
+
+    let ships = selected
+        .iter()
+        .map(|(entity, hold_position)| {
+            all_holding &= hold_position.is_some();
+            entity
+        })
+        .collect();
+
+    player_commands.local.push(PlayerCommand::HoldPosition {
+        ships,
+        hold: !all_holding,
+    });
+}
+
+// Works the same way as holding position.
+pub fn toggle_cloak(
+    keyboard_state: Res<KeyboardState>,
+    selected: Query<(Entity, Option<&Cloaked>), (SelectedFriendly, With<CloakEnergy>)>,
+    mut player_commands: ResMut<PlayerCommands>,
+) {
+    if !keyboard_state.cloak.0 {
+        return;
+    }
+
+    let mut all_cloaked = true;
+
+    let ships = selected
+        .iter()
+        .map(|(entity, cloaked)| {
+            all_cloaked &= cloaked.is_some();
+            entity
+        })
+        .collect();
+
+    player_commands.local.push(PlayerCommand::Cloak {
+        ships,
+        cloak: !all_cloaked,
+    });
+}
+
+// Switches the selected carriers between aggressive and passive, unless they're mixed, in which
+// case they all become aggressive.
+pub fn toggle_carrier_doctrine(
+    keyboard_state: Res<KeyboardState>,
+    selected: Query<(Entity, &CarrierDoctrine), SelectedFriendly>,
+    mut player_commands: ResMut<PlayerCommands>,
+) {
+    if !keyboard_state.toggle_carrier_doctrine.0 {
+        return;
+    }
+
+    let mut all_aggressive = true;
+
+    let carriers: Vec<_> = selected
+        .iter()
+        .map(|(entity, &doctrine)| {
+            all_aggressive &= doctrine == CarrierDoctrine::Aggressive;
+            entity
+        })
+        .collect();
+
+    if carriers.is_empty() {
+        return;
+    }
+
+    let doctrine = if all_aggressive {
+        CarrierDoctrine::Passive
+    } else {
+        CarrierDoctrine::Aggressive
+    };
+
+    player_commands
+        .local
+        .push(PlayerCommand::SetCarrierDoctrine { carriers, doctrine });
+}
+
+// Moves the selected ships on to the next autonomy level. If they're at different levels, they're
+// all set to the level after the lowest.
+pub fn cycle_autonomy(
+    keyboard_state: Res<KeyboardState>,
+    selected: Query<(Entity, Option<&Autonomy>), (SelectedFriendly, With<CommandQueue>)>,
+    mut player_commands: ResMut<PlayerCommands>,
+) {
+    if !keyboard_state.cycle_autonomy.0 {
+        return;
+    }
+
+    let lowest = selected
+        .iter()
+        .map(|(_, autonomy)| Autonomy::get(autonomy))
+        .min();
+
+    if let Some(lowest) = lowest {
+        player_commands.local.push(PlayerCommand::SetAutonomy {
+            ships: selected.iter().map(|(entity, _)| entity).collect(),
+            autonomy: lowest.next(),
+        });
+    }
+}
+
+// Works the same way as holding position. Only ships that can dock can retreat.
+pub fn toggle_retreat(
+    keyboard_state: Res<KeyboardState>,
+    selected: Query<(Entity, Option<&RetreatPolicy>), (SelectedFriendly, With<CanBeCarried>)>,
+    mut player_commands: ResMut<PlayerCommands>,
+) {
+    if !keyboard_state.toggle_retreat.0 {
+        return;
+    }
+
+    let mut all_retreating = true;
+
+    let ships = selected
+        .iter()
+        .map(|(entity, policy)| {
+            all_retreating &= policy.is_some();
+            entity
+        })
+        .collect();
+
+    player_commands.local.push(PlayerCommand::SetRetreat {
+        ships,
+        retreat: !all_retreating,
+    });
+}
+
+pub fn cycle_stance(
+    keyboard_state: Res<KeyboardState>,
+    selected: Query<(Entity, Option<&Stance>), (SelectedFriendly, With<CanAttack>)>,
+    mut player_commands: ResMut<PlayerCommands>,
+) {
+    if !keyboard_state.cycle_stance.0 {
+        return;
+    }
+
+    let first = match selected.iter().next() {
+        Some((_, stance)) => Stance::get(stance),
+        None => return,
+    };
+
+    // A mixed selection is brought into line first.
+    let stance = if selected
+        .iter()
+        .all(|(_, stance)| Stance::get(stance) == first)
+    {
+        first.next()
+    } else {
+        first
+    };
+
+    player_commands.local.push(PlayerCommand::SetStance {
+        ships: selected.iter().map(|(entity, _)| entity).collect(),
+        stance,
+    });
+}
+
+pub fn cycle_throttle(
+    keyboard_state: Res<KeyboardState>,
+    buttons: Res<UnitButtons>,
+    mouse_state: Res<MouseState>,
+    selected: Query<(Entity, Option<&Throttle>), (SelectedFriendly, With<MaxSpeed>)>,
+    mut player_commands: ResMut<PlayerCommands>,
+) {
+    let clicked = mouse_state.left_state.was_clicked() && buttons.throttle_hovered;
+
+    if !keyboard_state.cycle_throttle.0 && !clicked {
+        return;
+    }
+
+    let first = match selected.iter().next() {
+        Some((_, throttle)) => Throttle::get(throttle),
+        None => return,
+    };
+
+    // A mixed selection is brought into line first.
+    let throttle = if selected
+        .iter()
+        .all(|(_, throttle)| Throttle::get(throttle) == first)
+    {
+        first.next()
+    } else {
+        first
+    };
+
+    player_commands.local.push(PlayerCommand::SetThrottle {
+        ships: selected.iter().map(|(entity, _)| entity).collect(),
+        throttle,
+    });
+}
+
+// Switches the whole selection to focus fire unless it's already using it.
+pub fn cycle_targeting_stance(
+    keyboard_state: Res<KeyboardState>,
+    selected: Query<(Entity, Option<&TargetingStance>), (SelectedFriendly, With<CanAttack>)>,
+    mut player_commands: ResMut<PlayerCommands>,
+) {
+    if !keyboard_state.cycle_targeting_stance.0 {
+        return;
+    }
+
+    let first = match selected.iter().next() {
+        Some((_, stance)) => TargetingStance::get(stance),
+        None => return,
+    };
+
+    let all_same = selected
+        .iter()
+        .all(|(_, stance)| TargetingStance::get(stance) == first);
+
+    let stance = if all_same {
+        first.next()
+    } else {
+        TargetingStance::FocusFire
+    };
+
+    player_commands
+        .local
+        .push(PlayerCommand::SetTargetingStance {
+            ships: selected.iter().map(|(entity, _)| entity).collect(),
+            stance,
+        });
+}
+
+pub fn enter_rally_point_mode(
+    keyboard_state: Res<KeyboardState>,
+    carriers: Query<&Position, (SelectedFriendly, With<BuildQueue>)>,
+    mut mouse_mode: ResMut<MouseMode>,
+) {
+    if !keyboard_state.set_rally_point.0 {
+        return;
+    }
+
+    let positions = carriers.iter().map(|position| position.0);
+
+    if let Some(avg) = average(positions) {
+        *mouse_mode = MouseMode::SetRallyPoint {
+            point_on_plane: avg,
+        };
+    }
+}
+
+pub fn place_rally_point(
+    mouse_button: Res<MouseState>,
+    carriers: Query<Entity, (SelectedFriendly, With<BuildQueue>)>,
+    mut mouse_mode: ResMut<MouseMode>,
+    mut player_commands: ResMut<PlayerCommands>,
+) {
+    if !mouse_button.right_state.was_clicked() {
+        return;
+    }
+
+    if let MouseMode::SetRallyPoint { point_on_plane } = *mouse_mode {
+        player_commands.local.push(PlayerCommand::SetRallyPoint {
+            carriers: carriers.iter().collect(),
+            point: point_on_plane,
+        });
+
+        *mouse_mode = MouseMode::Normal;
+    }
+}
+
+// Escorts the carrier under the cursor, or otherwise the first selected carrier, with the selected
+// fighters.
+pub fn assign_escorts(
+    keyboard_state: Res<KeyboardState>,
+    ship_under_cursor: Res<ShipUnderCursor>,
+    fighters: Query<(Entity, &Position), (SelectedFriendly, With<CanAttack>, With<CommandQueue>)>,
+    selected_carriers: Query<Entity, (SelectedFriendly, With<Carrying>)>,
+    carriers: Query<&Position, (With<Ours>, With<Carrying>)>,
+    settings: Res<Settings>,
+    mut player_commands: ResMut<PlayerCommands>,
+) {
+    if !keyboard_state.assign_escorts.0 {
+        return;
+    }
+
+    let carrier = ship_under_cursor
+        .0
+        .filter(|&entity| carriers.get(entity).is_ok())
+        .or_else(|| selected_carriers.iter().next());
+
+    let (carrier, carrier_position) =
+        match carrier.and_then(|carrier| Some((carrier, carriers.get(carrier).ok()?))) {
+            Some(carrier) => carrier,
+            None => return,
+        };
+
+    let count = fighters.iter().count();
+    let mut screen = Formation::escort_screen(Vec3::zero(), count, settings.escort_radius);
+
+    let escorts = fighters
+        .iter()
+        .filter_map(|(entity, position)| {
+            screen
+                .choose_position(position.0 - carrier_position.0)
+                .map(|offset| (entity, offset))
+        })
+        .collect();
+
+    player_commands
+        .local
+        .push(PlayerCommand::Escort { carrier, escorts });
+}
+
+pub fn lay_mine(
+    keyboard_state: Res<KeyboardState>,
+    mine_layers: Query<Entity, (SelectedFriendly, With<MineLayer>)>,
+    mut player_commands: ResMut<PlayerCommands>,
+) {
+    if !keyboard_state.lay_mine.0 {
+        return;
+    }
+
+    player_commands.local.push(PlayerCommand::LayMine {
+        ships: mine_layers.iter().collect(),
+    });
+}
+
+fn spawn_mine(
+    commands: &mut Commands,
+    position: Vec3,
+    rng: &mut SmallRng,
+    friendly: Option<&Friendly>,
+    enemy: Option<&Enemy>,
+) {
+    let rotation = Rotor3::from_rotation_between(Vec3::unit_y(), uniform_sphere_distribution(rng));
+
+    let mut spawner = commands.spawn();
+    spawner
+        .insert_bundle(proximity_mine_components(position, rotation))
+        .insert(Hidden);
+
+    if friendly.is_some() {
+        spawner.insert(Friendly);
+    }
+
+    if enemy.is_some() {
+        spawner.insert(Enemy);
+    }
+}
+
+pub fn enter_minefield_targeting_mode(
+    keyboard_state: Res<KeyboardState>,
+    mine_layers: Query<&Position, (SelectedFriendly, With<MineLayer>)>,
+    mut mouse_mode: ResMut<MouseMode>,
+) {
+    if !keyboard_state.lay_minefield.0 {
+        return;
+    }
+
+    if let Some(avg) = average(mine_layers.iter().map(|position| position.0)) {
+        *mouse_mode = MouseMode::AreaTargeting {
+            area: ProximityMine::default().blast,
+            point: avg,
+        };
+    }
+}
+
+pub fn place_minefield(
+    mouse_button: Res<MouseState>,
+    mine_layers: Query<Entity, (SelectedFriendly, With<MineLayer>, With<CommandQueue>)>,
+    mut mouse_mode: ResMut<MouseMode>,
+    mut player_commands: ResMut<PlayerCommands>,
+) {
+    if !mouse_button.right_state.was_clicked() {
+        return;
+    }
+
+    if let MouseMode::AreaTargeting { point, .. } = *mouse_mode {
+        player_commands.local.push(PlayerCommand::LayMinefield {
+            ships: mine_layers.iter().collect(),
+            point,
+        });
+
+        *mouse_mode = MouseMode::Normal;
+    }
+}
+
+pub fn drop_minefields(
+    mut mine_layers: Query<(
+        Entity,
+        &Position,
+        &CommandQueue,
+        &MinefieldDrop,
+        &mut MineLayer,
+        Option<&Friendly>,
+        Option<&Enemy>,
+    )>,
+    mut rng: ResMut<SmallRng>,
+    mut commands: Commands,
+) {
+    mine_layers.for_each_mut(
+        |(entity, position, queue, drop, mut mine_layer, friendly, enemy)| {
+            if let Some(&Command::MoveTo { point, .. }) = queue.0.front() {
+                if point == drop.point {
+                    return;
+                }
+            }
+
+            // Anywhere short of the point means the ship was sent elsewhere.
+            let arrived = (position.0 - drop.point).mag_sq()
+                < ProximityMine::default().trigger_radius.powi(2);
+
+            if arrived && mine_layer.mines > 0 {
+                mine_layer.mines -= 1;
+                spawn_mine(&mut commands, position.0, &mut rng, friendly, enemy);
+            }
+
+            commands.entity(entity).remove::<MinefieldDrop>();
+        },
+    );
+}
+
+// Hovering over a friendly carrier makes it the drop-off for the selected miners, and hovering
+// over anything else clears it.
+pub fn set_drop_off(
+    keyboard_state: Res<KeyboardState>,
+    ship_under_cursor: Res<ShipUnderCursor>,
+    miners: Query<Entity, (SelectedFriendly, With<CanMine>)>,
+    carriers: Query<Entity, (With<Carrying>, With<Ours>)>,
+    mut player_commands: ResMut<PlayerCommands>,
+) {
+    if !keyboard_state.set_drop_off.0 {
+        return;
+    }
+
+    let carrier = ship_under_cursor
+        .0
+        .filter(|&entity| carriers.get(entity).is_ok());
+
+    player_commands.local.push(PlayerCommand::SetDropOff {
+        miners: miners.iter().collect(),
+        carrier,
+    });
+}
+
+pub fn set_rules_of_engagement(
+    keyboard_state: Res<KeyboardState>,
+    global_rules: Res<PerSide<GlobalRulesOfEngagement>>,
+    players: Res<Players>,
+    selected: Query<(Entity, Option<&RulesOfEngagementOverride>), SelectedFriendly>,
+    mut player_commands: ResMut<PlayerCommands>,
+) {
+    if !keyboard_state.rules_of_engagement.0 {
+        return;
+    }
+
+    if !keyboard_state.shift {
+        player_commands
+            .local
+            .push(PlayerCommand::SetRulesOfEngagement(
+                global_rules[players.local_side()].0.next(),
+            ));
+        return;
+    }
+
+    // Cycle through the overrides for the selected ships before going back to the global rules.
+    selected.for_each(|(entity, rules_override)| {
+        let rules = match rules_override.map(|rules_override| rules_override.0) {
+            None => Some(RulesOfEngagement::HoldFire),
+            Some(RulesOfEngagement::WeaponsFree) => None,
+            Some(rules) => Some(rules.next()),
+        };
+
+        player_commands
+            .local
+            .push(PlayerCommand::SetRulesOfEngagementOverride {
+                ships: vec![entity],
+                rules,
+            });
+    });
+}
+
+pub fn confirm_destructive_orders(
+    keyboard_state: Res<KeyboardState>,
+    settings: Res<Settings>,
+    mut order_confirmation: ResMut<OrderConfirmation>,
+) {
+    let held = if keyboard_state.cancel_build_queues {
+        Some(DestructiveOrder::CancelBuildQueues)
+    } else if keyboard_state.scuttle {
+        Some(DestructiveOrder::Scuttle)
+    } else {
+        None
+    };
+
+    order_confirmation.update(held, SimClock::TICK, settings.skip_order_confirmation);
+}
+
+pub fn carry_out_destructive_orders(
+    order_confirmation: Res<OrderConfirmation>,
+    build_queues: Query<Entity, (With<BuildQueue>, With<Ours>)>,
+    to_scuttle: Query<Entity, (SelectedFriendly, With<Health>, Without<Scuttling>)>,
+    mut player_commands: ResMut<PlayerCommands>,
+) {
+    match order_confirmation.confirmed {
+        Some(DestructiveOrder::CancelBuildQueues) => {
+            player_commands
+                .local
+                .push(PlayerCommand::CancelBuildQueues {
+                    carriers: build_queues.iter().collect(),
+                });
+        }
+        Some(DestructiveOrder::Scuttle) => {
+            player_commands.local.push(PlayerCommand::Scuttle {
+                ships: to_scuttle.iter().collect(),
+                scuttle: true,
+            });
+        }
+        None => {}
+    }
+}
+
 pub fn update_keyboard_state(mut keyboard_state: ResMut<KeyboardState>) {
     keyboard_state.update();
 }
@@ -469,12 +1993,157 @@ pub fn set_camera_following(
     }
 }
 
+// Tags are only notes for the player, so they're applied directly instead of going through
+// `PlayerCommands`. Ships with different tags all get the first one.
+pub fn cycle_player_tags(
+    keyboard_state: Res<KeyboardState>,
+    selected: Query<(Entity, Option<&PlayerTag>), SelectedFriendly>,
+    mut commands: Commands,
+) {
+    if !keyboard_state.cycle_tag.0 {
+        return;
+    }
+
+    let mut tags = selected.iter().map(|(_, tag)| tag.copied());
+
+    let current = match tags.next() {
+        Some(first) if tags.all(|tag| tag == first) => first,
+        Some(_) => None,
+        None => return,
+    };
+
+    let next = PlayerTag::next(current);
+
+    selected.for_each(|(entity, _)| match next {
+        Some(tag) => {
+            commands.entity(entity).insert(tag);
+        }
+        None => {
+            commands.entity(entity).remove::<PlayerTag>();
+        }
+    });
+}
+
+pub fn cycle_fleets(
+    keyboard_state: Res<KeyboardState>,
+    mut fleet_cycle: ResMut<FleetCycle>,
+    ships: Query<(Entity, &Position), (With<Ours>, With<Selectable>)>,
+    currently_following: Query<Entity, With<CameraFollowing>>,
+    mut commands: Commands,
+) {
+    // Ctrl+Tab cycles through enemy targets instead.
+    if !keyboard_state.cycle_fleets.0 || keyboard_state.control {
+        return;
+    }
+
+    let mut spatial_hash = SpatialHash::new(FleetCycle::CLUSTER_SIZE);
+
+    ships.for_each(|(entity, position)| {
+        spatial_hash.insert(position.0, entity);
+    });
+
+    let mut clusters = spatial_hash.clusters();
+
+    if clusters.is_empty() {
+        return;
+    }
+
+    // Biggest fleets first, with the lowest entity id breaking ties so that the order is stable.
+    for cluster in &mut clusters {
+        cluster.sort_unstable_by_key(|&&(_, entity)| entity);
+    }
+
+    clusters.sort_unstable_by(|a, b| b.len().cmp(&a.len()).then(a[0].1.cmp(&b[0].1)));
+
+    let count = clusters.len();
+
+    let index = match (fleet_cycle.0, keyboard_state.shift) {
+        (None, false) => 0,
+        (None, true) => count - 1,
+        (Some(index), false) => (index + 1) % count,
+        (Some(index), true) => (index + count - 1) % count,
+    };
+
+    fleet_cycle.0 = Some(index);
+
+    currently_following.for_each(|entity| {
+        commands.entity(entity).remove::<CameraFollowing>();
+    });
+
+    for &&(_, entity) in &clusters[index] {
+        commands.entity(entity).insert(CameraFollowing);
+    }
+}
+
+// Escorts and formation followers have empty queues but are still busy keeping station.
+type IdleFilter = (
+    With<Ours>,
+    With<Selectable>,
+    With<Position>,
+    Without<Drone>,
+    Without<Escorting>,
+    Without<FormationSlot>,
+);
+
+// Insert goes through idle miners and Ctrl+Insert through idle combat ships.
+pub fn cycle_idle_ships(
+    keyboard_state: Res<KeyboardState>,
+    mut idle_ships: ResMut<IdleShips>,
+    miners: Query<(Entity, &CommandQueue), (IdleFilter, Or<(With<CanMine>, With<CanHarvest>)>)>,
+    combat: Query<(Entity, &CommandQueue), (IdleFilter, With<AgroRange>)>,
+    selected: Query<Entity, With<Selected>>,
+    currently_following: Query<Entity, With<CameraFollowing>>,
+    mut commands: Commands,
+) {
+    let idle = |(entity, queue): (Entity, &CommandQueue)| {
+        if queue.0.is_empty() {
+            Some(entity)
+        } else {
+            None
+        }
+    };
+
+    let idle_miners: Vec<Entity> = miners.iter().filter_map(idle).collect();
+    let idle_combat: Vec<Entity> = combat.iter().filter_map(idle).collect();
+
+    idle_ships.miners = idle_miners.len();
+    idle_ships.combat = idle_combat.len();
+
+    if !keyboard_state.cycle_idle_ships.0 {
+        return;
+    }
+
+    let next = idle_ships.next(if keyboard_state.control {
+        idle_combat
+    } else {
+        idle_miners
+    });
+
+    let next = match next {
+        Some(next) => next,
+        None => return,
+    };
+
+    selected.for_each(|entity| {
+        commands.entity(entity).remove::<Selected>();
+    });
+
+    currently_following.for_each(|entity| {
+        commands.entity(entity).remove::<CameraFollowing>();
+    });
+
+    commands
+        .entity(next)
+        .insert(Selected)
+        .insert(CameraFollowing);
+}
+
 pub fn move_camera_around_following(
     mut camera: ResMut<Camera>,
     mut perspective_view: ResMut<PerspectiveView>,
     orbit: Res<Orbit>,
     following: Query<&Position, With<CameraFollowing>>,
-    friendly_following: Query<&Position, (With<CameraFollowing>, With<Friendly>)>,
+    friendly_following: Query<&Position, (With<CameraFollowing>, With<Ours>)>,
 ) {
     // If any friendly units are being followed, follow only friendly units.
     // This prevents problems where a whole bunch of units and a single asteroid
@@ -492,17 +2161,125 @@ pub fn move_camera_around_following(
     perspective_view.set_view(orbit.as_vector(), camera.center);
 }
 
+pub fn update_picture_in_picture(
+    mut picture_in_picture: ResMut<PictureInPicture>,
+    following: Query<Entity, With<CameraFollowing>>,
+    positions: Query<&Position>,
+) {
+    let picture_in_picture = &mut *picture_in_picture;
+
+    if let Some(entity) = following.iter().next() {
+        picture_in_picture.target = Some(entity);
+        // No need to show the unit twice.
+        picture_in_picture.visible = false;
+        return;
+    }
+
+    let position = match picture_in_picture
+        .target
+        .and_then(|target| positions.get(target).ok())
+    {
+        Some(position) => position.0,
+        None => {
+            picture_in_picture.target = None;
+            picture_in_picture.visible = false;
+            return;
+        }
+    };
+
+    picture_in_picture.orbit.latitude += SimClock::TICK * 0.2;
+
+    let orbit = picture_in_picture.orbit.as_vector();
+
+    picture_in_picture.camera_position = position + orbit;
+    picture_in_picture
+        .perspective_view
+        .set_view(orbit, position);
+    picture_in_picture.visible = true;
+}
+
 pub fn spawn_projectiles(
     ray: Res<Ray>,
     keyboard_state: Res<KeyboardState>,
-    total_time: Res<TotalTime>,
+    clock: Res<SimClock>,
     mut commands: Commands,
 ) {
     if keyboard_state.fire {
         commands.spawn_bundle((
             Projectile::new(&ray, 10.0),
-            AliveUntil(total_time.0 + 30.0),
+            AliveUntil(clock.timer(30.0)),
             Friendly,
         ));
     }
 }
+
+pub fn report_rejected_orders(
+    mut order_feedback: ResMut<OrderFeedback>,
+    mut notifications: ResMut<Notifications>,
+    mouse_state: Res<MouseState>,
+    real_time: Res<RealTime>,
+) {
+    if let Some(reason) = order_feedback.rejected.take() {
+        order_feedback.flash = Some((mouse_state.position, real_time.0));
+        order_feedback.play_error_blip = true;
+        notifications.push(reason, real_time.0);
+    }
+}
+
+#[test]
+fn test_orders_wait_for_unpause() {
+    use rand::SeedableRng;
+
+    let mut world = World::default();
+    world.insert_resource(bevy_tasks::TaskPool::new());
+    world.insert_resource(PlayerCommands::default());
+    world.insert_resource(PerSide::<TechTree>::default());
+    world.insert_resource(PerSide::<GlobalCredits>::default());
+    world.insert_resource(PerSide::<GlobalGas>::default());
+    world.insert_resource(PerSide::<GlobalMinerals>::default());
+    world.insert_resource(ShipCatalog::default());
+    world.insert_resource(PerSide::<GlobalRulesOfEngagement>::default());
+    world.insert_resource(SaveLoadRequest::default());
+    world.insert_resource(SimClock::new(0.0));
+    world.insert_resource(Paused(true));
+    world.insert_resource(Notifications::default());
+    world.insert_resource(RealTime(0.0));
+    world.insert_resource(SmallRng::seed_from_u64(0));
+
+    let fighter = world
+        .spawn()
+        .insert_bundle((Velocity(Vec3::zero()), CommandQueue::default()))
+        .id();
+    let mut carrying = Carrying::default();
+    assert!(carrying.checked_push(fighter, false));
+    let carrier = world
+        .spawn()
+        .insert_bundle((Position(Vec3::zero()), carrying))
+        .id();
+
+    let mut stage = SystemStage::parallel();
+    stage.add_system(apply_player_commands.system());
+
+    world
+        .get_resource_mut::<PlayerCommands>()
+        .unwrap()
+        .to_apply
+        .push((
+            Side::Friendly,
+            PlayerCommand::Unload {
+                carriers: vec![carrier],
+                model: None,
+            },
+        ));
+    stage.run(&mut world);
+    stage.run(&mut world);
+    assert_eq!(world.get::<Carrying>(carrier).unwrap().len(), 1);
+
+    world
+        .get_resource_mut::<PlayerCommands>()
+        .unwrap()
+        .to_apply
+        .push((Side::Friendly, PlayerCommand::SetPaused(false)));
+    stage.run(&mut world);
+    assert!(world.get::<Carrying>(carrier).unwrap().is_empty());
+}
@@ -0,0 +1,202 @@
+use crate::SelectedFriendly;
+use bevy_ecs::prelude::*;
+use components_and_resources::components::*;
+use components_and_resources::resources::*;
+
+pub fn enter_crew_transfer_mode(
+    keyboard_state: Res<KeyboardState>,
+    ships: Query<Entity, (SelectedFriendly, With<OnBoard>)>,
+    mut mouse_mode: ResMut<MouseMode>,
+    mut crew_transfer_panel: ResMut<CrewTransferPanel>,
+) {
+    if keyboard_state.escape.0 {
+        crew_transfer_panel.ships = None;
+    }
+
+    if !keyboard_state.transfer_crew.0 {
+        return;
+    }
+
+    if crew_transfer_panel.ships.take().is_some() {
+        return;
+    }
+
+    let mut selected = ships.iter();
+
+    if let (Some(source), None) = (selected.next(), selected.next()) {
+        *mouse_mode = MouseMode::TransferCrew { source };
+    }
+}
+
+pub fn open_crew_transfer_panel(
+    mouse_state: Res<MouseState>,
+    mut mouse_mode: ResMut<MouseMode>,
+    ship_under_cursor: Res<ShipUnderCursor>,
+    ships: Query<&Position, (With<Ours>, With<OnBoard>)>,
+    mut crew_transfer_panel: ResMut<CrewTransferPanel>,
+    mut order_feedback: ResMut<OrderFeedback>,
+) {
+    if !mouse_state.right_state.was_clicked() {
+        return;
+    }
+
+    if let MouseMode::TransferCrew { source } = *mouse_mode {
+        *mouse_mode = MouseMode::Normal;
+
+        let in_range = |target| match (ships.get(source), ships.get(target)) {
+            (Ok(source), Ok(target)) => {
+                (source.0 - target.0).mag_sq() <= CrewTransferPanel::RANGE.powi(2)
+            }
+            _ => false,
+        };
+
+        match ship_under_cursor.0.filter(|&target| target != source) {
+            Some(target) if in_range(target) => {
+                crew_transfer_panel.ships = Some((source, target));
+                crew_transfer_panel.anchor = mouse_state.position;
+            }
+            _ => order_feedback.rejected = Some("Crew can only move to a nearby friendly ship"),
+        }
+    }
+}
+
+// The heading and each ship's name take up a line before their crew.
+fn crew_member_at_line(source: &OnBoard, target: &OnBoard, line: usize) -> Option<Entity> {
+    let index = line.checked_sub(2)?;
+
+    match source.0.get(index) {
+        Some(&person) => Some(person),
+        None => index
+            .checked_sub(source.0.len() + 1)
+            .and_then(|index| target.0.get(index))
+            .copied(),
+    }
+}
+
+pub fn set_hovered_crew_member(
+    mut crew_transfer_panel: ResMut<CrewTransferPanel>,
+    mouse_state: Res<MouseState>,
+    dpi_factor: Res<DpiFactor>,
+    ships: Query<(&Position, &OnBoard)>,
+) {
+    crew_transfer_panel.hovered = None;
+
+    let (source, target) = match crew_transfer_panel.ships {
+        Some(ships) => ships,
+        None => return,
+    };
+
+    let (source, target) = match (ships.get(source), ships.get(target)) {
+        (Ok(source), Ok(target)) => (source, target),
+        _ => {
+            crew_transfer_panel.ships = None;
+            return;
+        }
+    };
+
+    // Close the panel if the ships drift apart.
+    if (source.0 .0 - target.0 .0).mag_sq() > CrewTransferPanel::RANGE.powi(2) {
+        crew_transfer_panel.ships = None;
+        return;
+    }
+
+    let offset = mouse_state.position - crew_transfer_panel.anchor;
+
+    if offset.x < 0.0 || offset.y < 0.0 || offset.x > CrewTransferPanel::WIDTH * dpi_factor.0 {
+        return;
+    }
+
+    let line = (offset.y / (UnitButtons::LINE_HEIGHT * dpi_factor.0)) as usize;
+    crew_transfer_panel.hovered = crew_member_at_line(source.1, target.1, line);
+}
+
+pub fn choose_crew_member(
+    crew_transfer_panel: Res<CrewTransferPanel>,
+    mouse_state: Res<MouseState>,
+    on_board: Query<&OnBoard>,
+    mut player_commands: ResMut<PlayerCommands>,
+) {
+    if !mouse_state.left_state.was_clicked() {
+        return;
+    }
+
+    if let (Some(person), Some((source, target))) =
+        (crew_transfer_panel.hovered, crew_transfer_panel.ships)
+    {
+        let on_source = on_board
+            .get(source)
+            .map(|on_board| on_board.0.contains(&person))
+            .unwrap_or(false);
+
+        let (from, to) = if on_source {
+            (source, target)
+        } else {
+            (target, source)
+        };
+
+        player_commands
+            .local
+            .push(PlayerCommand::TransferCrew { person, from, to });
+    }
+}
+
+pub fn render_crew_transfer_panel(
+    crew_transfer_panel: Res<CrewTransferPanel>,
+    ships: Query<(&ModelId, &OnBoard)>,
+    people: Query<(
+        Option<&Engineer>,
+        Option<&Researcher>,
+        Option<&Gunner>,
+        Option<&Pilot>,
+    )>,
+    mut glyph_layout_cache: ResMut<GlyphLayoutCache>,
+    dpi_factor: Res<DpiFactor>,
+    key_bindings: Res<KeyBindings>,
+) {
+    let (source, target) = match crew_transfer_panel.ships {
+        Some(ships) => ships,
+        None => return,
+    };
+
+    let (source, target) = match (ships.get(source), ships.get(target)) {
+        (Ok(source), Ok(target)) => (source, target),
+        _ => return,
+    };
+
+    let mut section = glyph_layout_cache.start_section(crew_transfer_panel.anchor, dpi_factor.0);
+
+    section.push(
+        format_args!(
+            "Crew transfer ({:?} to close)\n",
+            key_bindings.transfer_crew
+        ),
+        [1.0; 4],
+    );
+
+    for &((model_id, on_board), arrow) in &[(source, ">>"), (target, "<<")] {
+        section.push(format_args!("{:?}:\n", model_id), [0.75, 0.75, 0.75, 1.0]);
+
+        for &person in &on_board.0 {
+            // Each person needs a line to keep the hovered lines lined up.
+            let person_enum = people
+                .get(person)
+                .map(|(engineer, researcher, gunner, pilot)| {
+                    PersonEnum::new(
+                        engineer.is_some(),
+                        researcher.is_some(),
+                        gunner.is_some(),
+                        pilot.is_some(),
+                    )
+                })
+                .unwrap_or(PersonEnum::Civilian);
+
+            let colour = if crew_transfer_panel.hovered == Some(person) {
+                [0.5, 0.75, 1.0, 1.0]
+            } else {
+                [1.0; 4]
+            };
+
+            section.push(format_args!("  {:?} {}\n", person_enum, arrow), colour);
+        }
+    }
+}
@@ -0,0 +1,91 @@
+use bevy_ecs::prelude::*;
+use components_and_resources::components::*;
+use components_and_resources::resources::*;
+use ultraviolet::Vec2;
+
+pub fn run_cutscene(
+    mut cutscene_player: ResMut<CutscenePlayer>,
+    mut camera: ResMut<Camera>,
+    mut orbit: ResMut<Orbit>,
+    keyboard_state: Res<KeyboardState>,
+    currently_following: Query<Entity, With<CameraFollowing>>,
+    mut commands: Commands,
+) {
+    if keyboard_state.escape.0 {
+        cutscene_player.skip();
+    }
+
+    // Cutscenes play at the same pace whatever the game speed.
+    cutscene_player.advance(SimClock::TICK);
+
+    let keyframe = match cutscene_player.current() {
+        Some((cutscene, elapsed)) => cutscene.camera_at(elapsed),
+        None => return,
+    };
+
+    if let Some(keyframe) = keyframe {
+        currently_following.for_each(|entity| {
+            commands.entity(entity).remove::<CameraFollowing>();
+        });
+
+        camera.center = keyframe.center;
+        orbit.longitude = keyframe.longitude;
+        orbit.latitude = keyframe.latitude;
+        orbit.set_distance(keyframe.distance);
+    }
+}
+
+pub fn render_subtitles(
+    cutscene_player: Res<CutscenePlayer>,
+    mut glyph_layout_cache: ResMut<GlyphLayoutCache>,
+    dimensions: Res<Dimensions>,
+    dpi_factor: Res<DpiFactor>,
+) {
+    let subtitle = cutscene_player
+        .current()
+        .and_then(|(cutscene, elapsed)| cutscene.subtitle_at(elapsed));
+
+    if let Some(subtitle) = subtitle {
+        // Place the text in the middle of the bottom letterbox bar.
+        let bar_height = dimensions.height as f32 * cutscene_player.letterbox();
+        let position = Vec2::new(
+            dimensions.width as f32 / 2.0,
+            dimensions.height as f32 - (bar_height + 16.0 * dpi_factor.0) / 2.0,
+        );
+
+        let mut section = glyph_layout_cache.start_centered_section(position, dpi_factor.0);
+        section.push(format_args!("{}", subtitle), [1.0; 4]);
+    }
+}
+
+pub fn request_kill_cam(keyboard_state: Res<KeyboardState>, mut kill_cam: ResMut<KillCam>) {
+    if keyboard_state.watch_kill_cam.0 {
+        kill_cam.request();
+    }
+}
+
+pub fn render_kill_cam_prompt(
+    kill_cam: Res<KillCam>,
+    cutscene_player: Res<CutscenePlayer>,
+    mut glyph_layout_cache: ResMut<GlyphLayoutCache>,
+    dimensions: Res<Dimensions>,
+    dpi_factor: Res<DpiFactor>,
+) {
+    let offer = match kill_cam.current_offer() {
+        Some(offer) if !cutscene_player.is_playing() => offer,
+        _ => return,
+    };
+
+    let position = Vec2::new(
+        dimensions.width as f32 / 2.0,
+        dimensions.height as f32 * 0.8,
+    );
+    let mut section = glyph_layout_cache.start_centered_section(position, dpi_factor.0);
+    section.push(
+        format_args!(
+            "{:?} destroyed - press F4 to watch the replay",
+            offer.model_id
+        ),
+        [1.0, 0.5, 0.5, 1.0],
+    );
+}
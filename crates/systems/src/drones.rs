@@ -0,0 +1,109 @@
+use bevy_ecs::prelude::*;
+use components_and_resources::components::*;
+use components_and_resources::resources::*;
+use ultraviolet::Rotor3;
+
+// Drones circle their mothership by swinging their escort slot around it.
+pub fn orbit_motherships(
+    mut drones: Query<(&mut Escorting, &mut CommandQueue), With<Drone>>,
+    positions: Query<&Position>,
+    clock: Res<SimClock>,
+) {
+    let rotation = Rotor3::from_rotation_xz(Drone::ORBIT_SPEED * clock.delta);
+
+    drones.for_each_mut(|(mut escorting, mut queue)| {
+        escorting.offset = rotation * escorting.offset;
+
+        let mothership = match positions.get(escorting.carrier) {
+            Ok(position) => position.0,
+            Err(_) => return,
+        };
+
+        // Don't get drawn away chasing something.
+        if let Some(Command::Interact {
+            target,
+            ty: InteractionType::Attack,
+            ..
+        }) = queue.0.front()
+        {
+            let strayed = positions.get(*target).map_or(true, |target| {
+                (target.0 - mothership).mag_sq() > Drone::LEASH_RANGE.powi(2)
+            });
+
+            if strayed {
+                queue.0.pop_front();
+            }
+        }
+    });
+}
+
+// Drones fly back into their mothership while it retreats and launch again once it stops.
+pub fn dock_drones(
+    mut drones: Query<(
+        Entity,
+        &Drone,
+        &mut CommandQueue,
+        &mut Velocity,
+        Option<&Position>,
+        Option<&Escorting>,
+    )>,
+    mut motherships: Query<(&Position, &CommandQueue, &mut Carrying), Without<Drone>>,
+    clock: Res<SimClock>,
+    mut rng: ResMut<SmallRng>,
+    mut commands: Commands,
+) {
+    drones.for_each_mut(
+        |(entity, drone, mut queue, velocity, position, escorting)| {
+            // Drones that outlive their mothership just carry on as they are.
+            let (mothership_position, mothership_queue, mut carrying) =
+                match motherships.get_mut(drone.mothership) {
+                    Ok(mothership) => mothership,
+                    Err(_) => return,
+                };
+
+            let retreating = Drone::is_retreating(mothership_queue);
+
+            match (position, retreating) {
+                (Some(_), true) if escorting.is_some() => {
+                    queue.0.clear();
+                    queue.0.push_back(Command::Interact {
+                        target: drone.mothership,
+                        ty: InteractionType::BeCarriedBy,
+                        range_sq: 0.0,
+                    });
+                    commands.entity(entity).remove::<Escorting>();
+                }
+                // Either the retreat ended before the drone got back or the carrier was unloaded.
+                (Some(_), false) if escorting.is_none() => {
+                    queue.0.clear();
+                    commands.entity(entity).insert(Escorting {
+                        carrier: drone.mothership,
+                        offset: Drone::orbit_offset(&mut rng),
+                    });
+                }
+                (None, false) => {
+                    if !carrying.remove(entity) {
+                        return;
+                    }
+
+                    crate::unload_single(
+                        mothership_position.0,
+                        entity,
+                        &mut rng,
+                        &clock,
+                        Some((velocity, queue)),
+                        &mut commands,
+                        false,
+                    );
+
+                    commands.entity(entity).insert(Escorting {
+                        carrier: drone.mothership,
+                        offset: Drone::orbit_offset(&mut rng),
+                    });
+                    commands.entity(drone.mothership).remove::<CarrierFull>();
+                }
+                _ => {}
+            }
+        },
+    );
+}
@@ -0,0 +1,110 @@
+use bevy_ecs::prelude::*;
+use components_and_resources::components::*;
+use components_and_resources::resources::*;
+use components_and_resources::utils::MissionTime;
+use ultraviolet::Vec2;
+
+pub fn clear_damage_events(mut damage_events: ResMut<DamageEvents>) {
+    damage_events.0.clear();
+}
+
+// Runs after the hits for the tick have been applied.
+pub fn report_attacks(
+    damage_events: Res<DamageEvents>,
+    friendly: Query<(&ModelId, Option<&ShipType>), With<Ours>>,
+    clock: Res<SimClock>,
+    real_time: Res<RealTime>,
+    perspective_view: Res<PerspectiveView>,
+    dimensions: Res<Dimensions>,
+    mut game_events: ResMut<GameEvents>,
+    mut attack_alert: ResMut<AttackAlert>,
+) {
+    let screen = dimensions.to_vec();
+
+    for event in &damage_events.0 {
+        let name = match friendly.get(event.target) {
+            Ok((&model_id, ship_type)) => crate::unit_name(model_id, ship_type),
+            Err(_) => continue,
+        };
+
+        game_events.report_attack(clock.time, format!("{} under attack", name));
+
+        let on_screen = perspective_view
+            .project_to_pixels(event.position, &dimensions)
+            .map_or(false, |pixel| {
+                pixel.x >= 0.0 && pixel.y >= 0.0 && pixel.x < screen.x && pixel.y < screen.y
+            });
+
+        if !on_screen {
+            attack_alert.raise(event.position, real_time.0);
+        }
+    }
+}
+
+pub fn jump_to_attack(
+    keyboard_state: Res<KeyboardState>,
+    mut attack_alert: ResMut<AttackAlert>,
+    mut camera: ResMut<Camera>,
+    currently_following: Query<Entity, With<CameraFollowing>>,
+    mut commands: Commands,
+) {
+    if !keyboard_state.jump_to_attack.0 {
+        return;
+    }
+
+    let location = match attack_alert.location {
+        Some(location) => location,
+        None => return,
+    };
+
+    currently_following.for_each(|entity| {
+        commands.entity(entity).remove::<CameraFollowing>();
+    });
+
+    camera.center = location;
+    attack_alert.dismiss();
+}
+
+pub fn render_event_log(
+    mut game_events: ResMut<GameEvents>,
+    hud_layout: Res<HudLayout>,
+    research_panel: Res<ResearchPanel>,
+    mouse_state: Res<MouseState>,
+    mut glyph_layout_cache: ResMut<GlyphLayoutCache>,
+    dimensions: Res<Dimensions>,
+    dpi_factor: Res<DpiFactor>,
+) {
+    game_events.hovered = false;
+
+    // The research panel covers the right of the screen while it's open.
+    if !hud_layout.event_log.visible || research_panel.open || game_events.is_empty() {
+        return;
+    }
+
+    let screen = dimensions.to_vec();
+    let anchor = hud_layout.event_log.anchor;
+    let width = GameEvents::WIDTH * dpi_factor.0;
+    // A heading and then the events.
+    let lines = 1 + game_events.visible().count();
+    let height = lines as f32 * UnitButtons::LINE_HEIGHT * dpi_factor.0;
+
+    let relative = mouse_state.position - anchor.position(Vec2::new(width, height), screen);
+    game_events.hovered =
+        relative.x >= 0.0 && relative.y >= 0.0 && relative.x < width && relative.y < height;
+
+    let mut section =
+        glyph_layout_cache.start_anchored_section(anchor, height, screen, dpi_factor.0);
+
+    match game_events.scrolled_back() {
+        0 => section.push(format_args!("Event Log\n"), [1.0; 4]),
+        newer => section.push(format_args!("Event Log ({} newer)\n", newer), [1.0; 4]),
+    }
+
+    for event in game_events.visible() {
+        section.push(
+            format_args!("{} ", MissionTime(event.time)),
+            [0.5, 0.5, 0.5, 1.0],
+        );
+        section.push(format_args!("{}\n", event.text), event.severity.colour());
+    }
+}
@@ -26,17 +26,22 @@ pub fn find_next_carrier<'a>(
 pub fn find_next_asteroid(
     pos: Vec3,
     queue: &mut CommandQueue,
-    new_targets: &Query<(Entity, &Position, &Scale), With<CanBeMined>>,
+    new_targets: &mut Query<(Entity, &Position, &Scale, &mut MiningClaims), With<CanBeMined>>,
 ) {
     let new_target = new_targets
-        .iter()
-        .map(|(entity, new_pos, scale)| {
-            let dist_sq = (pos - new_pos.0).mag_sq();
-            (entity, dist_sq, scale)
+        .iter_mut()
+        .map(|(entity, new_pos, scale, claims)| {
+            let priority = claims.priority((pos - new_pos.0).mag_sq(), scale);
+            (entity, priority, scale, claims)
         })
-        .min_by(|&(_, a, _), &(_, b, _)| compare_floats(a, b));
+        .min_by(|&(_, (a_full, a), ..), &(_, (b_full, b), ..)| {
+            a_full.cmp(&b_full).then(compare_floats(a, b))
+        });
+
+    if let Some((entity, _, scale, mut claims)) = new_target {
+        // Count the claim straight away so that other miners given orders this frame see it.
+        claims.0 += 1;
 
-    if let Some((entity, _, scale)) = new_target {
         queue.0.push_back(Command::Interact {
             target: entity,
             ty: InteractionType::Mine,
@@ -44,3 +49,22 @@ pub fn find_next_asteroid(
         });
     }
 }
+
+pub fn find_next_gas_cloud(
+    pos: Vec3,
+    queue: &mut CommandQueue,
+    new_targets: &Query<(Entity, &Position), With<CanBeHarvested>>,
+) {
+    let new_target = new_targets
+        .iter()
+        .map(|(entity, new_pos)| (entity, (pos - new_pos.0).mag_sq()))
+        .min_by(|&(_, a), &(_, b)| compare_floats(a, b));
+
+    if let Some((entity, _)) = new_target {
+        queue.0.push_back(Command::Interact {
+            target: entity,
+            ty: InteractionType::Harvest,
+            range_sq: CanBeHarvested::RADIUS * CanBeHarvested::RADIUS,
+        });
+    }
+}
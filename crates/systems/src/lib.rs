@@ -3,6 +3,7 @@
 #![allow(clippy::too_many_arguments)]
 
 use bevy_ecs::prelude::*;
+use bevy_ecs::schedule::ShouldRun;
 use components_and_resources::components::*;
 use components_and_resources::resources::*;
 use components_and_resources::utils::*;
@@ -10,22 +11,68 @@ use std::array::IntoIter;
 use std::ops::{Deref, DerefMut};
 use ultraviolet::{Vec2, Vec3};
 
+mod accessibility;
+mod asteroid_respawns;
+mod audio;
+mod carrier_doctrine;
 mod combat;
+mod comets;
+mod construction;
 mod controls;
+mod crew_transfer;
+mod cutscene;
+mod drones;
+mod event_log;
 mod find_functions;
 mod people;
+mod pickups;
+mod production_panel;
 mod rendering;
+mod research;
 mod resource_management;
+mod scripting;
 mod steering;
+mod strategic_map;
+mod trading;
 
+pub use accessibility::*;
+pub use asteroid_respawns::*;
+pub use audio::*;
+pub use carrier_doctrine::*;
 pub use combat::*;
+pub use comets::*;
+pub use construction::*;
 pub use controls::*;
+pub use crew_transfer::*;
+pub use cutscene::*;
+pub use drones::*;
+pub use event_log::*;
 pub use people::*;
+pub use pickups::*;
+pub use production_panel::*;
 pub use rendering::*;
+pub use research::*;
 pub use resource_management::*;
+pub use scripting::*;
 pub use steering::*;
+pub use strategic_map::*;
+pub use trading::*;
 
-type SelectedFriendly = (With<Selected>, With<Friendly>);
+type SelectedFriendly = (With<Selected>, With<Ours>);
+
+pub fn mark_local_side(
+    sides: Query<(Entity, Option<&Friendly>), Or<(Added<Friendly>, Added<Enemy>)>>,
+    players: Res<Players>,
+    mut commands: Commands,
+) {
+    sides.for_each(|(entity, friendly)| {
+        if friendly.is_some() == players.local_is_friendly() {
+            commands.entity(entity).insert(Ours);
+        } else {
+            commands.entity(entity).insert(Theirs);
+        }
+    });
+}
 
 pub fn update_ship_rotation_matrix(
     mut query: Query<(&Rotation, &mut RotationMatrix, &ModelId), Changed<Rotation>>,
@@ -55,16 +102,27 @@ pub fn upload_buffer<T: bytemuck::Pod + Send + Sync + 'static>(
     buffer.upload(&gpu_interface.device, &gpu_interface.queue);
 }
 
-pub fn clear_ship_buffer(mut buffer: ResMut<ShipBuffer>) {
-    buffer.clear();
+pub fn clear_triangles_2d_buffer(mut buffer: ResMut<Triangles2DBuffer>) {
+    buffer.0.clear();
+}
+
+pub fn upload_triangles_2d_buffer(
+    mut buffer: ResMut<Triangles2DBuffer>,
+    gpu_interface: Res<GpuInterface>,
+) {
+    buffer.0.upload(&gpu_interface.device, &gpu_interface.queue);
 }
 
 pub fn upload_ship_buffer(
     mut buffer: ResMut<ShipBuffer>,
+    mut cloaked_buffer: ResMut<CloakedShipBuffer>,
     gpu_interface: Res<GpuInterface>,
     models: Res<Models>,
 ) {
     buffer.upload(&gpu_interface.device, &gpu_interface.queue, &models);
+    cloaked_buffer
+        .0
+        .upload(&gpu_interface.device, &gpu_interface.queue, &models);
 }
 
 #[profiling::function]
@@ -85,15 +143,34 @@ pub fn handle_destruction(
         Option<&OnBoard>,
         Option<&TlasIndex>,
         Option<&Selected>,
+        Option<&HoldingItem>,
+        Option<(&Rotation, &ModelId, &MaxSpeed)>,
+        Option<&Enemy>,
+        (Option<&Ours>, Option<&Theirs>, Option<&ShipType>),
     )>,
     mut rng: ResMut<SmallRng>,
     mut commands: Commands,
-    total_time: Res<TotalTime>,
+    clock: Res<SimClock>,
     mut movement: Query<(&mut Velocity, &mut CommandQueue)>,
     mut tlas: ResMut<TopLevelAccelerationStructure>,
+    mut kill_cam: ResMut<KillCam>,
+    mut script_host: ResMut<ScriptHost>,
+    mut game_events: ResMut<GameEvents>,
 ) {
     query.for_each_mut(
-        |(entity, pos, health, carrying, on_board, tlas_index, selected)| {
+        |(
+            entity,
+            pos,
+            health,
+            carrying,
+            on_board,
+            tlas_index,
+            selected,
+            holding_item,
+            ship,
+            enemy,
+            (ours, theirs, ship_type),
+        )| {
             if health.current > 0.0 {
                 return;
             }
@@ -104,7 +181,7 @@ pub fn handle_destruction(
                     pos: pos.0,
                     carrying: &mut carrying,
                     rng: &mut *rng,
-                    total_time: total_time.0,
+                    clock: &clock,
                     commands: &mut commands,
                     movement: &mut movement,
                     selected: selected.is_some(),
@@ -123,28 +200,95 @@ pub fn handle_destruction(
                 tlas.remove(tlas_index.index);
             }
 
-            spawn_explosion(pos.0, total_time.0, &mut *rng, &mut commands);
+            // Drop whatever the ship was holding where it was destroyed.
+            if let Some(holding_item) = holding_item {
+                commands.entity(holding_item.0).insert(Position(pos.0));
+            }
+
+            // Only ships leave wrecks behind, not asteroids or mines.
+            if let Some((rotation, &model_id, _)) = ship {
+                commands.spawn_bundle(wreck_components(pos.0, rotation.0, model_id, &clock));
+
+                if model_id.is_capital() {
+                    kill_cam.offer(pos.0, model_id);
+                }
+
+                script_host.unit_destroyed(model_id, enemy.is_some());
+
+                if ours.is_some() {
+                    let severity = if model_id.is_capital() {
+                        Severity::Danger
+                    } else {
+                        Severity::Warning
+                    };
+                    game_events.push(
+                        clock.time,
+                        severity,
+                        format!("{} lost", unit_name(model_id, ship_type)),
+                    );
+                } else if theirs.is_some() {
+                    game_events.push(
+                        clock.time,
+                        Severity::Info,
+                        format!("Enemy {} destroyed", unit_name(model_id, ship_type)),
+                    );
+                }
+            }
+
+            spawn_explosion(pos.0, &clock, &mut *rng, &mut commands);
         },
     )
 }
 
-fn spawn_explosion(pos: Vec3, total_time: f32, rng: &mut SmallRng, commands: &mut Commands) {
+fn spawn_explosion(pos: Vec3, clock: &SimClock, rng: &mut SmallRng, commands: &mut Commands) {
     commands.spawn_bundle((
         Position(pos),
-        RotationMatrix::random_for_rendering_only(rng),
+        Rotation::random_for_rendering_only(rng),
+        RotationMatrix::default(),
         ModelId::Explosion,
         Scale(0.0),
-        AliveUntil(total_time + 2.5),
+        AliveUntil(clock.timer(2.5)),
         Expands,
     ));
 }
 
+fn spawn_impact_effect(
+    pos: Vec3,
+    normal: Vec3,
+    hit_shield: bool,
+    clock: &SimClock,
+    rng: &mut SmallRng,
+    commands: &mut Commands,
+) {
+    let ty = if hit_shield {
+        ImpactEffectType::ShieldRipple
+    } else {
+        let mut directions = [Vec3::zero(); 8];
+
+        for direction in &mut directions {
+            *direction = (normal + uniform_sphere_distribution(rng) * 0.75).normalized();
+        }
+
+        ImpactEffectType::Sparks { directions }
+    };
+
+    commands.spawn_bundle((
+        Position(pos),
+        ImpactEffect {
+            ty,
+            normal,
+            spawned_at: clock.time,
+        },
+        AliveUntil(clock.timer(ImpactEffect::DURATION)),
+    ));
+}
+
 struct UnloadParams<'caller, 'q, 'cm, 'v, 'cq> {
     entity: Entity,
     pos: Vec3,
     carrying: &'caller mut Carrying,
     rng: &'caller mut SmallRng,
-    total_time: f32,
+    clock: &'caller SimClock,
     commands: &'caller mut Commands<'cm>,
     movement: &'caller mut Query<'q, (&'v mut Velocity, &'cq mut CommandQueue)>,
     selected: bool,
@@ -156,7 +300,7 @@ fn unload(params: UnloadParams) {
         pos,
         carrying,
         rng,
-        total_time,
+        clock,
         commands,
         movement,
         selected,
@@ -167,7 +311,7 @@ fn unload(params: UnloadParams) {
             pos,
             entity,
             rng,
-            total_time,
+            clock,
             movement.get_mut(entity).ok(),
             commands,
             selected,
@@ -183,7 +327,7 @@ fn unload_of_type(params: UnloadParams, models: &Query<&ModelId>, ty: ModelId) {
         pos,
         carrying,
         rng,
-        total_time,
+        clock,
         commands,
         movement,
         selected,
@@ -199,7 +343,7 @@ fn unload_of_type(params: UnloadParams, models: &Query<&ModelId>, ty: ModelId) {
                 pos,
                 entity,
                 rng,
-                total_time,
+                clock,
                 movement.get_mut(entity).ok(),
                 commands,
                 selected,
@@ -217,7 +361,7 @@ fn unload_single<V, M>(
     pos: Vec3,
     entity: Entity,
     rng: &mut SmallRng,
-    total_time: f32,
+    clock: &SimClock,
     movement: Option<(V, M)>,
     commands: &mut Commands,
     select: bool,
@@ -229,7 +373,8 @@ fn unload_single<V, M>(
 
     entity_commands
         .insert(Position(pos))
-        .insert(Unloading::new(total_time));
+        .insert(Unloading::new(clock))
+        .insert(Launched::new(clock));
 
     if select {
         entity_commands.insert(Selected);
@@ -241,36 +386,78 @@ fn unload_single<V, M>(
         queue.0.push_front(Command::MoveTo {
             point: pos + uniform_sphere_distribution(rng) * 5.0,
             ty: MoveType::Attack,
+            speed_cap: None,
         })
     }
 }
 
-pub fn update_projectiles(mut query: Query<&mut Projectile>, delta_time: Res<DeltaTime>) {
+pub fn update_projectiles(mut query: Query<&mut Projectile>, clock: Res<SimClock>) {
     query.for_each_mut(|mut projectile| {
-        projectile.update(delta_time.0);
+        projectile.update(clock.delta);
     })
 }
 
-pub fn expand_explosions(mut query: Query<&mut Scale, With<Expands>>, delta_time: Res<DeltaTime>) {
+pub fn expand_explosions(mut query: Query<&mut Scale, With<Expands>>, clock: Res<SimClock>) {
     query.for_each_mut(|mut scale| {
-        scale.0 += delta_time.0 * 1.5;
+        scale.0 += clock.delta * 1.5;
     });
 }
 
 pub fn kill_temporary(
-    query: Query<(Entity, &AliveUntil)>,
-    total_time: Res<TotalTime>,
+    query: Query<(Entity, &AliveUntil, Option<&TlasIndex>)>,
+    clock: Res<SimClock>,
     mut commands: Commands,
+    mut tlas: ResMut<TopLevelAccelerationStructure>,
 ) {
-    query.for_each(|(entity, alive_until)| {
-        if total_time.0 > alive_until.0 {
+    query.for_each(|(entity, alive_until, tlas_index)| {
+        if alive_until.0.finished(&clock) {
             commands.entity(entity).despawn();
+
+            // Wrecks are big enough to be in the TLAS, unlike explosions and projectiles.
+            if let Some(tlas_index) = tlas_index {
+                tlas.remove(tlas_index.index);
+            }
         }
     })
 }
 
-pub fn increase_total_time(mut total_time: ResMut<TotalTime>, delta_time: Res<DeltaTime>) {
-    total_time.0 += delta_time.0;
+pub fn advance_sim_clock(mut clock: ResMut<SimClock>) {
+    clock.advance();
+}
+
+// Real time moves on by a tick each frame whether or not the simulation does.
+pub fn increase_real_time(mut real_time: ResMut<RealTime>) {
+    real_time.0 += SimClock::TICK;
+}
+
+// Run criteria for everything that advances the game itself. Input, the camera, selection and
+// rendering are left outside of this so that the game can still be looked at while paused.
+pub fn simulation_running(paused: Res<Paused>) -> ShouldRun {
+    if paused.0 {
+        ShouldRun::No
+    } else {
+        ShouldRun::Yes
+    }
+}
+
+pub fn autosave(
+    clock: Res<SimClock>,
+    mut autosave_timer: ResMut<AutosaveTimer>,
+    mut save_load_request: ResMut<SaveLoadRequest>,
+) {
+    if !autosave_timer.enabled() || clock.time < autosave_timer.next_autosave {
+        return;
+    }
+
+    // Don't clobber a quicksave or quickload requested on the same frame.
+    if save_load_request.0.is_none() {
+        save_load_request.0 = Some(SaveLoad::Autosave {
+            slot: autosave_timer.next_slot,
+        });
+        autosave_timer.next_slot = (autosave_timer.next_slot + 1) % autosave_timer.slots;
+    }
+
+    autosave_timer.reschedule(clock.time);
 }
 
 // We cache these because it's 6 f32 adds and that adds time to bounding box checks
@@ -294,17 +481,31 @@ pub fn set_world_space_bounding_box(
     });
 }
 
-pub fn spin(mut query: Query<(&mut Spin, &mut Rotation)>, delta_time: Res<DeltaTime>) {
+pub fn spin(mut query: Query<(&mut Spin, &mut Rotation)>, clock: Res<SimClock>) {
     query.for_each_mut(|(mut spin, mut rotation)| {
-        spin.update_angle(delta_time.0);
+        spin.update_angle(clock.delta);
         rotation.0 = spin.as_rotor();
     });
 }
 
+pub fn orbit(mut query: Query<(&Orbiting, &mut Position)>, clock: Res<SimClock>) {
+    query.for_each_mut(|(orbiting, mut position)| {
+        position.0 = orbiting.advance(position.0, clock.delta);
+    });
+}
+
 fn get_scale(scale: Option<&Scale>) -> f32 {
     scale.map(|scale| scale.0).unwrap_or(1.0)
 }
 
+// Several ship types share a model, so the ship type is the better name where there is one.
+fn unit_name(model_id: ModelId, ship_type: Option<&ShipType>) -> String {
+    match ship_type {
+        Some(ship_type) => format!("{:?}", ship_type),
+        None => format!("{:?}", model_id),
+    }
+}
+
 pub fn calculate_average_selected_position(
     mut average_selected_position: ResMut<AverageSelectedPosition>,
     selected_positions: Query<&Position, SelectedFriendly>,
@@ -328,88 +529,270 @@ fn average(positions: impl Iterator<Item = Vec3>) -> Option<Vec3> {
     }
 }
 
-pub fn apply_velocity(
-    mut query: Query<(&mut Position, &Velocity)>,
-    delta_time: Res<DeltaTime>,
-    paused: Res<Paused>,
-) {
-    if paused.0 {
-        return;
-    }
+pub fn apply_velocity(mut query: Query<(&mut Position, &Velocity)>, clock: Res<SimClock>) {
     query.for_each_mut(|(mut position, velocity)| {
-        position.0 += velocity.0 * delta_time.0;
+        position.0 += velocity.0 * clock.delta;
     });
 }
 
 type SelectedUncarried = (With<Selected>, With<Position>);
 
 pub fn count_selected(
-    friendly: Query<&ModelId, (SelectedUncarried, With<Friendly>)>,
-    neutral: Query<&ModelId, (SelectedUncarried, Without<Friendly>, Without<Enemy>)>,
-    enemy: Query<&ModelId, (SelectedUncarried, With<Enemy>)>,
+    friendly: Query<
+        (
+            Entity,
+            &ModelId,
+            Option<&PlayerTag>,
+            Option<&Stance>,
+            Option<&Throttle>,
+        ),
+        (SelectedUncarried, With<Ours>),
+    >,
+    neutral: Query<&ModelId, (SelectedUncarried, Without<Ours>, Without<Theirs>)>,
+    enemy: Query<&ModelId, (SelectedUncarried, With<Theirs>)>,
     mut glyph_layout_cache: ResMut<GlyphLayoutCache>,
-    friendly_carrying: Query<&Carrying, (SelectedUncarried, With<Friendly>)>,
+    friendly_carrying: Query<&Carrying, (SelectedUncarried, With<Ours>)>,
     all_models: Query<&ModelId>,
     mut buttons: ResMut<UnitButtons>,
-    global_minerals: Res<GlobalMinerals>,
-    global_research: Res<GlobalResearch>,
+    // Grouped to stay within the limit on the number of system parameters.
+    (global_minerals, global_gas, global_credits, global_research, idle_ships): (
+        Res<PerSide<GlobalMinerals>>,
+        Res<PerSide<GlobalGas>>,
+        Res<PerSide<GlobalCredits>>,
+        Res<PerSide<GlobalResearch>>,
+        Res<IdleShips>,
+    ),
+    players: Res<Players>,
+    global_rules: Res<PerSide<GlobalRulesOfEngagement>>,
+    formation_shape: Res<SelectedFormationShape>,
+    control_groups: Res<ControlGroups>,
+    objective_score: Res<ObjectiveScore>,
     dpi_factor: Res<DpiFactor>,
+    dimensions: Res<Dimensions>,
+    hud_layout: Res<HudLayout>,
 ) {
-    buttons.0.clear();
+    buttons.buttons.clear();
+    buttons.throttle_shown = false;
 
-    let mut section = glyph_layout_cache.start_section(Vec2::zero(), dpi_factor.0);
+    let side = players.local_side();
+
+    let screen = dimensions.to_vec();
+    let line_height = UnitButtons::LINE_HEIGHT * dpi_factor.0;
+
+    if hud_layout.resource_bar.visible {
+        let mut section = glyph_layout_cache.start_anchored_section(
+            hud_layout.resource_bar.anchor,
+            HudLayout::RESOURCE_BAR_LINES as f32 * line_height,
+            screen,
+            dpi_factor.0,
+        );
+
+        section.push(
+            format_args!("Global Minerals: {}\n", global_minerals[side].0),
+            [1.0; 4],
+        );
+
+        section.push(
+            format_args!("Global Gas: {:.0}\n", global_gas[side].0),
+            [1.0; 4],
+        );
+
+        section.push(
+            format_args!("Credits: {:.0}\n", global_credits[side].0),
+            [1.0; 4],
+        );
+
+        section.push(
+            format_args!("Global Research: {:.2}\n", global_research[side].0),
+            [1.0; 4],
+        );
+
+        let idle_colour = if idle_ships.miners + idle_ships.combat > 0 {
+            [1.0, 0.8, 0.2, 1.0]
+        } else {
+            [1.0; 4]
+        };
+
+        section.push(
+            format_args!(
+                "Idle: {} miners, {} combat ships\n",
+                idle_ships.miners, idle_ships.combat
+            ),
+            idle_colour,
+        );
+    }
+
+    if !hud_layout.unit_counts.visible {
+        return;
+    }
+
+    // Which control groups the selected ships of each model are in, and which tags they have.
+    let mut group_membership = [[false; 9]; Models::COUNT];
+    let mut tags = [[false; PlayerTag::COUNT]; Models::COUNT];
+
+    friendly.for_each(|(entity, &model_id, player_tag, ..)| {
+        for group in control_groups.groups_containing(entity) {
+            group_membership[model_id as usize][group] = true;
+        }
+
+        if let Some(&player_tag) = player_tag {
+            tags[model_id as usize][player_tag as usize] = true;
+        }
+    });
+
+    let friendly_counts = count(friendly.iter().map(|(_, model_id, ..)| model_id));
+    let carried_counts = count(
+        friendly_carrying
+            .iter()
+            .flat_map(|carrying| carrying.iter())
+            .filter_map(|entity| all_models.get(entity).ok()),
+    );
+    let neutral_counts = count(neutral.iter());
+    let enemy_counts = count(enemy.iter());
+
+    // The height is needed up front to place the counts at the bottom of the screen.
+    let button_lines = IntoIter::new([
+        friendly_counts,
+        carried_counts,
+        neutral_counts,
+        enemy_counts,
+    ])
+    .flat_map(IntoIter::new)
+    .filter(|&count| count > 0)
+    .count();
+    let offset_lines = hud_layout.unit_counts_offset_lines();
+    let lines = offset_lines + UnitButtons::UI_LINES + button_lines;
+    let anchor = hud_layout.unit_counts.anchor;
+
+    let top_left = anchor.position(
+        Vec2::new(
+            UnitButtons::BUTTON_WIDTH * dpi_factor.0,
+            lines as f32 * line_height,
+        ),
+        screen,
+    );
+    buttons.origin = top_left
+        + Vec2::new(
+            0.0,
+            (offset_lines + UnitButtons::UI_LINES) as f32 * line_height,
+        );
+
+    let mut section = glyph_layout_cache.start_anchored_section(
+        anchor,
+        lines as f32 * line_height,
+        screen,
+        dpi_factor.0,
+    );
+
+    // Leave space for the resource bar.
+    for _ in 0..offset_lines {
+        section.push(format_args!("\n"), [1.0; 4]);
+    }
 
     section.push(
-        format_args!("Global Minerals: {}\n", global_minerals.0),
+        format_args!("Rules of Engagement: {}\n", global_rules[side].0.to_str()),
         [1.0; 4],
     );
 
     section.push(
-        format_args!("Global Research: {:.2}\n", global_research.0),
+        format_args!("Formation: {:?}\n", formation_shape.0),
         [1.0; 4],
     );
 
-    let mut print = |status: UnitStatus, colour, counts: [u32; Models::COUNT]| {
+    let mut stances = friendly
+        .iter()
+        .map(|(.., stance, _)| Stance::get(stance).to_str());
+    let stance = match stances.next() {
+        Some(first) if stances.all(|stance| stance == first) => first,
+        Some(_) => "Mixed",
+        None => "-",
+    };
+
+    section.push(format_args!("Stance: {}\n", stance), [1.0; 4]);
+
+    section.push(
+        format_args!(
+            "Relics: {} - {}\n",
+            objective_score.friendly, objective_score.enemy
+        ),
+        [1.0; 4],
+    );
+
+    let mut throttles = friendly
+        .iter()
+        .map(|(.., throttle)| Throttle::get(throttle).to_str());
+    let throttle = match throttles.next() {
+        Some(first) if throttles.all(|throttle| throttle == first) => first,
+        Some(_) => "Mixed",
+        None => "-",
+    };
+
+    section.push(format_args!("Throttle: {}\n", throttle), [1.0; 4]);
+    buttons.throttle_shown = true;
+
+    let mut print = |status: UnitStatus,
+                     colour,
+                     counts: [u32; Models::COUNT],
+                     groups: [[bool; 9]; Models::COUNT],
+                     tags: [[bool; PlayerTag::COUNT]; Models::COUNT]| {
         for model_id in IntoIter::new(Models::ARRAY) {
             let i = model_id as usize;
             let count = counts[i];
 
             if count > 0 {
-                buttons.0.push((model_id, status));
+                buttons.buttons.push((model_id, status));
                 section.push(format_args!("{}", status.to_str()), colour);
 
                 section.push(
-                    format_args!(" {:?}s: {}\n", Models::ARRAY[i], count),
+                    format_args!(" {:?}s: {}", Models::ARRAY[i], count),
                     [1.0; 4],
                 );
+
+                for (group, _) in groups[i].iter().enumerate().filter(|(_, &member)| member) {
+                    section.push(format_args!(" [{}]", group + 1), [1.0, 1.0, 0.0, 1.0]);
+                }
+
+                for (tag, _) in IntoIter::new(PlayerTag::ARRAY)
+                    .zip(&tags[i])
+                    .filter(|(_, &has_tag)| has_tag)
+                {
+                    section.push(format_args!(" {}", tag.name()), tag.colour());
+                }
+
+                section.push(format_args!("\n"), [1.0; 4]);
             }
         }
     };
 
+    let friendly_colour = players.local_colour().rgba();
+
     print(
         UnitStatus::Friendly { carried: false },
-        [0.25, 1.0, 0.25, 1.0],
-        count(friendly.iter()),
+        friendly_colour,
+        friendly_counts,
+        group_membership,
+        tags,
     );
     print(
         UnitStatus::Friendly { carried: true },
-        [0.25, 1.0, 0.25, 1.0],
-        count(
-            friendly_carrying
-                .iter()
-                .flat_map(|carrying| carrying.iter())
-                .filter_map(|entity| all_models.get(entity).ok()),
-        ),
+        friendly_colour,
+        carried_counts,
+        Default::default(),
+        Default::default(),
     );
     print(
         UnitStatus::Neutral,
         [0.25, 0.25, 1.0, 1.0],
-        count(neutral.iter()),
+        neutral_counts,
+        Default::default(),
+        Default::default(),
     );
     print(
         UnitStatus::Enemy,
         [1.0, 0.25, 0.25, 1.0],
-        count(enemy.iter()),
+        enemy_counts,
+        Default::default(),
+        Default::default(),
     );
 }
 
@@ -424,21 +807,24 @@ fn count<'a>(iter: impl Iterator<Item = &'a ModelId>) -> [u32; Models::COUNT] {
 }
 
 pub fn set_selected_button(
-    buttons: Res<UnitButtons>,
+    mut buttons: ResMut<UnitButtons>,
     mut selected_button: ResMut<SelectedButton>,
     mouse_state: Res<MouseState>,
     dpi_factor: Res<DpiFactor>,
 ) {
-    if mouse_state.position.x > (UnitButtons::BUTTON_WIDTH * dpi_factor.0) {
+    let relative = mouse_state.position - buttons.origin;
+
+    if relative.x < 0.0 || relative.x > (UnitButtons::BUTTON_WIDTH * dpi_factor.0) {
         selected_button.0 = None;
+        buttons.throttle_hovered = false;
         return;
     }
 
-    let index = mouse_state.position.y / (UnitButtons::LINE_HEIGHT * dpi_factor.0);
+    let index = (relative.y / (UnitButtons::LINE_HEIGHT * dpi_factor.0)).floor();
 
-    let index = index as isize - UnitButtons::UI_LINES;
+    buttons.throttle_hovered = buttons.throttle_shown && index == -1.0;
 
-    selected_button.0 = if index < buttons.0.len() as isize && index >= 0 {
+    selected_button.0 = if index < buttons.buttons.len() as f32 && index >= 0.0 {
         Some(index as usize)
     } else {
         None
@@ -474,16 +860,72 @@ pub fn update_tlas(
     });
 }
 
+pub fn update_lighting(mut lighting: ResMut<Lighting>, clock: Res<SimClock>) {
+    lighting.update(clock.time);
+}
+
 pub fn remove_unloading(
     query: Query<(Entity, &Unloading)>,
-    total_time: Res<TotalTime>,
+    launched: Query<(Entity, &Launched)>,
+    clock: Res<SimClock>,
     mut commands: Commands,
 ) {
     query.for_each(|(entity, unloading)| {
-        if unloading.until <= total_time.0 {
+        if unloading.until.finished(&clock) {
             commands.entity(entity).remove::<Unloading>();
         }
-    })
+    });
+
+    launched.for_each(|(entity, launched)| {
+        if launched.until.finished(&clock) {
+            commands.entity(entity).remove::<Launched>();
+        }
+    });
+}
+
+// Ships get a recorder the first time they're seen, so that ones spawned or loaded from a save
+// don't need to have it added explicitly.
+pub fn record_flights(
+    mut query: Query<(
+        Entity,
+        &Position,
+        &CommandQueue,
+        Option<&Health>,
+        Option<&mut FlightRecorder>,
+    )>,
+    clock: Res<SimClock>,
+    mut commands: Commands,
+) {
+    query.for_each_mut(|(entity, position, queue, health, recorder)| {
+        let order = queue.0.front().copied();
+        let health = health.map(|health| health.current);
+
+        match recorder {
+            Some(mut recorder) => recorder.record(clock.time, position.0, order, health),
+            None => {
+                let mut recorder = FlightRecorder::default();
+                recorder.record(clock.time, position.0, order, health);
+                commands.entity(entity).insert(recorder);
+            }
+        }
+    });
+}
+
+pub fn dump_flight_recorders(
+    keyboard_state: Res<KeyboardState>,
+    query: Query<(Entity, &FlightRecorder), With<Selected>>,
+) {
+    if !keyboard_state.dump_flight_recorder.0 {
+        return;
+    }
+
+    query.for_each(|(entity, recorder)| {
+        log::info!("Flight recorder for {:?}:", entity);
+
+        for (time, event) in recorder.events() {
+            log::info!("  {}: {:?}", MissionTime(*time), event);
+        }
+    });
 }
 
 pub fn debug_watch(
@@ -1,12 +1,15 @@
 use bevy_ecs::prelude::*;
 use components_and_resources::components::*;
+use components_and_resources::gpu_structs::LaserInstance;
 use components_and_resources::resources::*;
+use components_and_resources::utils::compare_floats;
+use ultraviolet::Vec3;
 
 pub fn repair_ships(
     mut query: Query<(Entity, &OnBoard, Option<&Carrying>)>,
     mut health: Query<&mut Health>,
     engineers: Query<&Engineer>,
-    delta_time: Res<DeltaTime>,
+    clock: Res<SimClock>,
 ) {
     query.for_each_mut(|(entity, on_board, carrying)| {
         let mut health_increase_pool = on_board
@@ -14,7 +17,7 @@ pub fn repair_ships(
             .iter()
             .filter(|&&person_entity| engineers.get(person_entity).is_ok())
             .count() as f32
-            * delta_time.0;
+            * clock.delta;
 
         if let Ok(mut health) = health.get_mut(entity) {
             let health_increase = health_increase_pool.min(health.max - health.current);
@@ -40,24 +43,134 @@ pub fn repair_ships(
     })
 }
 
+// Idle repair ships go looking for the closest damaged ship on their side by themselves.
+pub fn repair_with_beams<Side: Send + Sync + 'static>(
+    mut repairers: Query<(Entity, &Position, &CanRepair, &mut CommandQueue), With<Side>>,
+    mut patients: Query<(Entity, &Position, &mut Health), (With<Side>, With<MaxSpeed>)>,
+    clock: Res<SimClock>,
+    mut lasers: ResMut<GpuBuffer<LaserInstance>>,
+) {
+    repairers.for_each_mut(|(entity, position, can_repair, mut queue)| {
+        if queue.0.is_empty() {
+            let closest = patients
+                .iter_mut()
+                .filter(|(patient, _, health)| *patient != entity && health.current < health.max)
+                .map(|(patient, patient_pos, _)| (patient, (patient_pos.0 - position.0).mag_sq()))
+                .filter(|&(_, distance_sq)| distance_sq < CanRepair::SEARCH_RANGE.powi(2))
+                .min_by(|&(_, a), &(_, b)| compare_floats(a, b));
+
+            if let Some((patient, _)) = closest {
+                queue.0.push_back(Command::Interact {
+                    target: patient,
+                    ty: InteractionType::Repair,
+                    range_sq: can_repair.range * can_repair.range,
+                });
+            }
+        }
+
+        let (target, range_sq) = match queue.0.front() {
+            Some(&Command::Interact {
+                target,
+                ty: InteractionType::Repair,
+                range_sq,
+            }) => (target, range_sq),
+            _ => return,
+        };
+
+        let (target_pos, mut health) = match patients.get_mut(target) {
+            Ok((_, target_pos, health)) if health.current < health.max => (target_pos.0, health),
+            // Either fully repaired or gone.
+            _ => {
+                queue.0.pop_front();
+                return;
+            }
+        };
+
+        if (target_pos - position.0).mag_sq() > range_sq {
+            return;
+        }
+
+        health.current = (health.current + can_repair.rate * clock.delta).min(health.max);
+
+        lasers.stage(&[LaserInstance {
+            start: position.0,
+            end: target_pos,
+            colour: Vec3::new(0.1, 1.0, 0.3),
+            width: 0.2,
+        }]);
+    })
+}
+
+// Ships without a `CrewModifiers` yet are treated as having had no crew.
+pub fn aggregate_crew(
+    mut query: Query<(
+        Entity,
+        &OnBoard,
+        Option<&mut CrewModifiers>,
+        Option<&mut MaxSpeed>,
+        Option<&mut BuildQueue>,
+    )>,
+    people: Query<(Option<&Engineer>, Option<&Gunner>, Option<&Pilot>)>,
+    mut commands: Commands,
+) {
+    query.for_each_mut(|(entity, on_board, modifiers, max_speed, build_queue)| {
+        let (mut engineers, mut gunners, mut pilots) = (0, 0, 0);
+
+        for &person in on_board.0.iter() {
+            if let Ok((engineer, gunner, pilot)) = people.get(person) {
+                engineers += engineer.is_some() as usize;
+                gunners += gunner.is_some() as usize;
+                pilots += pilot.is_some() as usize;
+            }
+        }
+
+        let new_modifiers = CrewModifiers::new(engineers, gunners, pilots);
+
+        let previous_speed_multiplier = match modifiers {
+            Some(mut modifiers) => {
+                let previous = modifiers.speed_multiplier;
+                *modifiers = new_modifiers.clone();
+                previous
+            }
+            None => {
+                commands.entity(entity).insert(new_modifiers.clone());
+                1.0
+            }
+        };
+
+        if let Some(mut max_speed) = max_speed {
+            if new_modifiers.speed_multiplier != previous_speed_multiplier {
+                max_speed.0 *= new_modifiers.speed_multiplier / previous_speed_multiplier;
+            }
+        }
+
+        if let Some(mut build_queue) = build_queue {
+            build_queue.crew_speed_bonus = new_modifiers.build_speed_bonus;
+        }
+    })
+}
+
 pub fn perform_research(
-    on_board: Query<(&OnBoard, Option<&ResearchMultiplier>), With<Friendly>>,
+    on_board: Query<
+        (&OnBoard, Option<&ResearchMultiplier>, Option<&Friendly>),
+        Or<(With<Friendly>, With<Enemy>)>,
+    >,
     researchers: Query<&Researcher>,
-    delta_time: Res<DeltaTime>,
-    mut global_research: ResMut<GlobalResearch>,
+    clock: Res<SimClock>,
+    mut global_research: ResMut<PerSide<GlobalResearch>>,
 ) {
     const BASE_RESEARCH_SPEED: f32 = 0.1;
 
-    on_board.for_each(|(on_board, research_multiplier)| {
+    on_board.for_each(|(on_board, research_multiplier, friendly)| {
         let research_increase = on_board
             .0
             .iter()
             .filter(|&&person_entity| researchers.get(person_entity).is_ok())
             .count() as f32
-            * delta_time.0
+            * clock.delta
             * research_multiplier.map(|mul| mul.0).unwrap_or(1.0)
             * BASE_RESEARCH_SPEED;
 
-        global_research.0 += research_increase;
+        global_research[Side::of(friendly)].0 += research_increase;
     })
 }
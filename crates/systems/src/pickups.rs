@@ -0,0 +1,163 @@
+use bevy_ecs::prelude::*;
+use components_and_resources::components::*;
+use components_and_resources::gpu_structs::LaserInstance;
+use components_and_resources::map_generation;
+use components_and_resources::resources::*;
+use components_and_resources::utils::uniform_sphere_distribution;
+use ultraviolet::Vec3;
+
+pub fn spawn_pickups(
+    pickups: Query<&Pickup>,
+    clock: Res<SimClock>,
+    mut spawner: ResMut<PickupSpawner>,
+    mut rng: ResMut<SmallRng>,
+    mut commands: Commands,
+) {
+    if !spawner.enabled || !spawner.next_spawn.finished(&clock) {
+        return;
+    }
+
+    spawner.next_spawn = clock.timer(PickupSpawner::INTERVAL);
+
+    if pickups.iter().count() + 2 > PickupSpawner::MAX_PICKUPS {
+        return;
+    }
+
+    let pickup = Pickup::random(&mut *rng);
+    let spin_axis = uniform_sphere_distribution(&mut *rng);
+
+    for &position in &map_generation::pickup_positions(&mut *rng) {
+        commands.spawn_bundle(pickup_components(position, pickup, spin_axis));
+    }
+}
+
+pub fn collect_pickups(
+    pickups: Query<(Entity, &Position, &Pickup)>,
+    ship_positions: Query<&Position, (With<MaxSpeed>, Without<Missile>)>,
+    mut ships: Query<(
+        &mut Health,
+        &mut MaxSpeed,
+        Option<&SpeedBoost>,
+        Option<&Friendly>,
+        Option<&Ours>,
+    )>,
+    clock: Res<SimClock>,
+    real_time: Res<RealTime>,
+    tlas: Res<TopLevelAccelerationStructure>,
+    mut global_minerals: ResMut<PerSide<GlobalMinerals>>,
+    mut notifications: ResMut<Notifications>,
+    mut commands: Commands,
+) {
+    // Boosts aren't inserted until the commands are applied, so a ship flying through two speed
+    // boosts on the same tick would otherwise be sped up twice.
+    let mut sped_up = Vec::new();
+
+    pickups.for_each(|(pickup_entity, position, &pickup)| {
+        let mut find_stack = Vec::with_capacity(10);
+        let radius = Pickup::RADIUS;
+        let pickup_box =
+            BoundingBox::new(-Vec3::broadcast(radius), Vec3::broadcast(radius)) + position.0;
+
+        let collector = tlas
+            .find(
+                |bounding_box| pickup_box.intersects(bounding_box),
+                &mut find_stack,
+            )
+            .copied()
+            .find(|&entity| match ship_positions.get(entity) {
+                Ok(ship_position) => (ship_position.0 - position.0).mag_sq() < radius * radius,
+                Err(_) => false,
+            });
+
+        let entity = match collector {
+            Some(entity) => entity,
+            None => return,
+        };
+
+        let (mut health, mut max_speed, speed_boost, friendly, ours) = match ships.get_mut(entity) {
+            Ok(ship) => ship,
+            Err(_) => return,
+        };
+
+        let boost = clock.timer(Pickup::BOOST_DURATION);
+
+        match pickup {
+            Pickup::Speed => {
+                // Picking up another boost only resets the timer.
+                if speed_boost.is_none() && !sped_up.contains(&entity) {
+                    max_speed.0 *= SpeedBoost::MULTIPLIER;
+                    sped_up.push(entity);
+                }
+
+                commands.entity(entity).insert(SpeedBoost(boost));
+            }
+            Pickup::Damage => {
+                commands.entity(entity).insert(DamageBoost(boost));
+            }
+            Pickup::Repair => health.current = health.max,
+            Pickup::Minerals => global_minerals[Side::of(friendly)].0 += Pickup::MINERALS,
+        }
+
+        if ours.is_some() {
+            notifications.push(format!("Picked up a {}", pickup.to_str()), real_time.0);
+        }
+
+        commands.entity(pickup_entity).despawn();
+    });
+}
+
+pub fn expire_boosts(
+    mut speed_boosted: Query<(Entity, &SpeedBoost, &mut MaxSpeed)>,
+    damage_boosted: Query<(Entity, &DamageBoost)>,
+    clock: Res<SimClock>,
+    mut commands: Commands,
+) {
+    speed_boosted.for_each_mut(|(entity, boost, mut max_speed)| {
+        if boost.0.finished(&clock) {
+            max_speed.0 /= SpeedBoost::MULTIPLIER;
+            commands.entity(entity).remove::<SpeedBoost>();
+        }
+    });
+
+    damage_boosted.for_each(|(entity, boost)| {
+        if boost.0.finished(&clock) {
+            commands.entity(entity).remove::<DamageBoost>();
+        }
+    });
+}
+
+// Each crate has a pulsing beam of light above it so that it can be spotted from across the map.
+pub fn render_pickups(
+    pickups: Query<(&Position, &Pickup)>,
+    clock: Res<SimClock>,
+    mut lasers: ResMut<GpuBuffer<LaserInstance>>,
+) {
+    const BEACON_HEIGHT: f32 = 60.0;
+
+    let pulse = 1.5 + (clock.time * 3.0).sin() * 0.5;
+
+    pickups.for_each(|(position, &pickup)| {
+        let colour = match pickup {
+            Pickup::Speed => Vec3::new(0.2, 0.6, 1.0),
+            Pickup::Damage => Vec3::new(1.0, 0.3, 0.2),
+            Pickup::Repair => Vec3::new(0.3, 1.0, 0.4),
+            Pickup::Minerals => Vec3::new(1.0, 0.8, 0.2),
+        };
+
+        lasers.stage(&[
+            LaserInstance {
+                start: position.0,
+                end: position.0 + Vec3::unit_y() * BEACON_HEIGHT,
+                colour: colour * pulse,
+                width: 1.0,
+            },
+            // A brighter glow around the crate itself.
+            LaserInstance {
+                start: position.0 - Vec3::unit_y() * 2.0,
+                end: position.0 + Vec3::unit_y() * 2.0,
+                colour: colour * pulse * 2.0,
+                width: 4.0,
+            },
+        ]);
+    });
+}
@@ -0,0 +1,174 @@
+use crate::rendering::to_wgpu;
+use crate::strategic_map::stage_quad;
+use crate::SelectedFriendly;
+use bevy_ecs::prelude::*;
+use components_and_resources::components::*;
+use components_and_resources::resources::*;
+use ultraviolet::{Vec2, Vec3};
+
+fn panel_position(queue_length: usize, hud_layout: &HudLayout, screen: Vec2, dpi: f32) -> Vec2 {
+    hud_layout
+        .production_panel
+        .anchor
+        .position(ProductionPanel::size(queue_length, dpi), screen)
+}
+
+pub fn set_hovered_production_item(
+    mut production_panel: ResMut<ProductionPanel>,
+    carriers: Query<(Entity, &BuildQueue), SelectedFriendly>,
+    mouse_state: Res<MouseState>,
+    hud_layout: Res<HudLayout>,
+    dimensions: Res<Dimensions>,
+    dpi_factor: Res<DpiFactor>,
+) {
+    production_panel.carrier = None;
+    production_panel.hovered = None;
+
+    if !hud_layout.production_panel.visible {
+        return;
+    }
+
+    let mut selected = carriers.iter();
+
+    let (carrier, build_queue) = match (selected.next(), selected.next()) {
+        (Some(carrier), None) => carrier,
+        _ => return,
+    };
+
+    production_panel.carrier = Some(carrier);
+
+    let queue_length = build_queue.num_in_queue();
+    let top_left = panel_position(queue_length, &hud_layout, dimensions.to_vec(), dpi_factor.0);
+
+    production_panel.hovered =
+        ProductionPanel::item_at(mouse_state.position - top_left, queue_length, dpi_factor.0);
+}
+
+// Clicking moves a ship up the queue, Shift+clicking moves it down and Ctrl+clicking cancels it.
+pub fn handle_production_panel_clicks(
+    production_panel: Res<ProductionPanel>,
+    mouse_state: Res<MouseState>,
+    keyboard_state: Res<KeyboardState>,
+    build_queues: Query<&BuildQueue>,
+    mut player_commands: ResMut<PlayerCommands>,
+) {
+    if !mouse_state.left_state.was_clicked() {
+        return;
+    }
+
+    let (carrier, index) = match (production_panel.carrier, production_panel.hovered) {
+        (Some(carrier), Some(index)) => (carrier, index),
+        _ => return,
+    };
+
+    let ship = match build_queues
+        .get(carrier)
+        .ok()
+        .and_then(|build_queue| build_queue.get(index))
+    {
+        Some(ship) => ship,
+        None => return,
+    };
+
+    let command = if keyboard_state.control {
+        PlayerCommand::CancelBuild {
+            carrier,
+            index,
+            ship,
+        }
+    } else {
+        PlayerCommand::ReorderBuild {
+            carrier,
+            index,
+            ship,
+            earlier: !keyboard_state.shift,
+        }
+    };
+
+    player_commands.local.push(command);
+}
+
+pub fn render_production_panel(
+    production_panel: Res<ProductionPanel>,
+    build_queues: Query<&BuildQueue>,
+    clock: Res<SimClock>,
+    ship_catalog: Res<ShipCatalog>,
+    hud_layout: Res<HudLayout>,
+    mut glyph_layout_cache: ResMut<GlyphLayoutCache>,
+    mut triangles_2d: ResMut<Triangles2DBuffer>,
+    dimensions: Res<Dimensions>,
+    dpi_factor: Res<DpiFactor>,
+) {
+    let build_queue = match production_panel
+        .carrier
+        .and_then(|carrier| build_queues.get(carrier).ok())
+    {
+        Some(build_queue) => build_queue,
+        None => return,
+    };
+
+    let queue_length = build_queue.num_in_queue();
+    let size = ProductionPanel::size(queue_length, dpi_factor.0);
+    let top_left = panel_position(queue_length, &hud_layout, dimensions.to_vec(), dpi_factor.0);
+    let line_height = UnitButtons::LINE_HEIGHT * dpi_factor.0;
+
+    // Min and max are flipped vertically going from pixels to wgpu coordinates.
+    let mut stage_box = |left: f32, top: f32, right: f32, bottom: f32, colour: Vec3| {
+        stage_quad(
+            &mut triangles_2d.0,
+            to_wgpu(Vec2::new(left, bottom), &dimensions),
+            to_wgpu(Vec2::new(right, top), &dimensions),
+            colour,
+        );
+    };
+
+    stage_box(
+        top_left.x,
+        top_left.y,
+        top_left.x + size.x,
+        top_left.y + size.y,
+        Vec3::broadcast(0.02),
+    );
+
+    let progress = build_queue
+        .progress_time(&clock, &ship_catalog)
+        .unwrap_or(0.0)
+        .max(0.0)
+        .min(1.0);
+
+    let bar_left = top_left.x + size.x * 0.6;
+    let bar_right = top_left.x + size.x - 8.0 * dpi_factor.0;
+
+    for index in 0..queue_length {
+        let top = top_left.y + (index + 1) as f32 * line_height + line_height * 0.25;
+        let bottom = top + line_height * 0.5;
+
+        stage_box(bar_left, top, bar_right, bottom, Vec3::broadcast(0.15));
+
+        // Only the front of the queue is being built.
+        if index == 0 {
+            let filled = bar_left + (bar_right - bar_left) * progress;
+            stage_box(bar_left, top, filled, bottom, Vec3::new(0.3, 0.6, 1.0));
+        }
+    }
+
+    let mut section = glyph_layout_cache.start_section(top_left, dpi_factor.0);
+
+    match queue_length {
+        0 => section.push(format_args!("Production: nothing queued\n"), [1.0; 4]),
+        _ => section.push(
+            format_args!("Production (click: earlier, Shift: later, Ctrl: cancel)\n"),
+            [1.0; 4],
+        ),
+    }
+
+    for (index, ship_type) in build_queue.iter().enumerate() {
+        let colour = if production_panel.hovered == Some(index) {
+            [0.5, 0.75, 1.0, 1.0]
+        } else {
+            [1.0; 4]
+        };
+
+        section.push(format_args!("{}. {:?}\n", index + 1, ship_type), colour);
+    }
+}
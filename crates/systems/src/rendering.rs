@@ -2,12 +2,15 @@ use super::get_scale;
 use bevy_ecs::prelude::*;
 use components_and_resources::components::*;
 use components_and_resources::gpu_structs::{
-    CircleInstance, ColouredVertex, Instance, LaserVertex, RangeInstance, Vertex2D,
+    CircleInstance, ColouredVertex, Instance, LaserInstance, RangeInstance, TranslucentVertex,
+    Vertex2D,
 };
 use components_and_resources::resources::*;
-use components_and_resources::utils::compare_floats;
+use components_and_resources::utils::{
+    compare_floats, estimated_arrival, rotation_from_facing, MissionTime,
+};
 use std::array::IntoIter;
-use ultraviolet::{Vec2, Vec3, Vec4};
+use ultraviolet::{Mat3, Rotor3, Vec2, Vec3, Vec4};
 
 #[profiling::function]
 pub fn render_model_instances(
@@ -15,34 +18,48 @@ pub fn render_model_instances(
         Entity,
         Option<&Selected>,
         &Position,
-        &RotationMatrix,
+        &Rotation,
         &ModelId,
         Option<&Scale>,
-        Option<&Friendly>,
-        Option<&Enemy>,
+        Option<&Ours>,
+        Option<&Theirs>,
         Option<&CanBeMined>,
+        Option<&LastHit>,
+        Option<&Hidden>,
+        Option<&Cloaked>,
     )>,
     ship_under_cursor: Res<ShipUnderCursor>,
     mut ship_buffer: ResMut<ShipBuffer>,
+    mut cloaked_ship_buffer: ResMut<CloakedShipBuffer>,
     models: Res<Models>,
     misc_textures: Res<MiscTextures>,
+    players: Res<Players>,
 ) {
     query.for_each(
         |(
             entity,
             selected,
             position,
-            rotation_matrix,
+            rotation,
             model_id,
             scale,
             friendly,
             enemy,
             can_be_mined,
+            last_hit,
+            hidden,
+            cloaked,
         )| {
+            if (hidden.is_some() || cloaked.is_some()) && enemy.is_some() {
+                return;
+            }
+
             let base_colour = if friendly.is_some() {
-                Vec3::unit_y()
+                players.local_colour().rgb()
             } else if enemy.is_some() {
-                Vec3::unit_x()
+                players
+                    .opponent_colour()
+                    .map_or(Vec3::unit_x(), TeamColour::rgb)
             } else {
                 Vec3::unit_z()
             };
@@ -57,20 +74,33 @@ pub fn render_model_instances(
 
             let model = models.get(*model_id);
 
-            ship_buffer.stage(
+            let buffer = if cloaked.is_some() {
+                &mut cloaked_ship_buffer.0
+            } else {
+                &mut *ship_buffer
+            };
+
+            buffer.stage(
+                entity,
                 Instance {
+                    rotation: rotation.0.into_quaternion_array().into(),
                     translation: position.0,
-                    rotation: rotation_matrix.matrix,
-                    colour,
                     scale: get_scale(scale),
-                    diffuse_texture: if *model_id == ModelId::Asteroid && can_be_mined.is_none() {
-                        misc_textures.mined_out_asteroid
-                    } else {
-                        model.diffuse_texture
-                    },
-                    emissive_texture: model.emissive_texture,
+                    colour: Instance::pack_colour(colour, selected.is_some()),
+                    textures: [
+                        if *model_id == ModelId::Asteroid && can_be_mined.is_none() {
+                            misc_textures.mined_out_asteroid
+                        } else {
+                            model.diffuse_texture
+                        },
+                        model.emissive_texture,
+                        model.normal_texture,
+                        model.metallic_roughness_texture,
+                    ]
+                    .map(|texture| texture as u16),
+                    last_hit_time: last_hit.map(|last_hit| last_hit.0).unwrap_or(f32::MIN),
+                    model_id: *model_id as u32,
                 },
-                *model_id as usize,
             );
         },
     );
@@ -147,33 +177,153 @@ pub fn debug_render_find_ship_under_cursor(
     }
 }
 
-pub fn render_projectiles(query: Query<&Projectile>, mut lasers: ResMut<GpuBuffer<LaserVertex>>) {
+pub fn render_projectiles(query: Query<&Projectile>, mut lasers: ResMut<GpuBuffer<LaserInstance>>) {
     query.for_each(|projectile| {
         let (start, end) = projectile.line_points(-0.1);
 
-        let colour = Vec3::new(0.75, 0.0, 1.0) * 0.75;
+        lasers.stage(&[LaserInstance {
+            start,
+            end,
+            colour: Vec3::new(0.75, 0.0, 1.0) * 0.75,
+            width: 0.15,
+        }]);
+    })
+}
+
+// A bright core inside a wider glow that ripples along its length, like air shimmering over
+// something hot.
+pub fn render_beams(
+    beams: Query<(&Position, &BeamWeapon)>,
+    positions: Query<&Position>,
+    clock: Res<SimClock>,
+    mut lasers: ResMut<GpuBuffer<LaserInstance>>,
+) {
+    const SEGMENTS: usize = 12;
+
+    beams.for_each(|(position, beam)| {
+        let start = position.0;
+
+        let end = match beam.target.and_then(|target| positions.get(target).ok()) {
+            Some(target_pos) => target_pos.0,
+            None => return,
+        };
+
+        lasers.stage(&[LaserInstance {
+            start,
+            end,
+            colour: Vec3::new(1.0, 0.8, 0.6) * 2.0,
+            width: 0.15,
+        }]);
+
+        let point = |i: usize| start + (end - start) * (i as f32 / SEGMENTS as f32);
+
+        for i in 0..SEGMENTS {
+            let shimmer = (clock.time * 20.0 - i as f32 * 1.3).sin() * 0.5 + 0.5;
+
+            lasers.stage(&[LaserInstance {
+                start: point(i),
+                end: point(i + 1),
+                colour: Vec3::new(1.0, 0.3, 0.05) * (0.3 + shimmer * 0.3),
+                width: 0.5 + shimmer * 0.3,
+            }]);
+        }
+    })
+}
+
+// Missiles leave a fading exhaust trail behind a bright head.
+pub fn render_missiles(
+    query: Query<(&Position, &Velocity), With<Missile>>,
+    mut lasers: ResMut<GpuBuffer<LaserInstance>>,
+) {
+    query.for_each(|(position, velocity)| {
+        let head = position.0 - velocity.0 * 0.02;
+        let tail = position.0 - velocity.0 * 0.25;
 
         lasers.stage(&[
-            LaserVertex {
-                position: start,
-                colour,
+            LaserInstance {
+                start: position.0,
+                end: head,
+                colour: Vec3::new(1.0, 0.9, 0.6) * 3.0,
+                width: 0.3,
             },
-            LaserVertex {
-                position: end,
-                colour,
+            LaserInstance {
+                start: head,
+                end: tail,
+                colour: Vec3::new(1.0, 0.4, 0.1) * 0.5,
+                width: 0.2,
             },
         ]);
     })
 }
 
+pub fn render_impact_effects(
+    query: Query<(&Position, &ImpactEffect)>,
+    clock: Res<SimClock>,
+    mut lasers: ResMut<GpuBuffer<LaserInstance>>,
+) {
+    query.for_each(|(position, effect)| {
+        let progress = ((clock.time - effect.spawned_at) / ImpactEffect::DURATION).min(1.0);
+        let fade = 1.0 - progress;
+
+        match &effect.ty {
+            ImpactEffectType::Sparks { directions } => {
+                for &direction in directions {
+                    let start = position.0 + direction * progress * 2.0;
+
+                    lasers.stage(&[LaserInstance {
+                        start,
+                        end: start + direction * fade * 0.5,
+                        colour: Vec3::new(1.0, 0.6, 0.2) * fade * 2.0,
+                        width: 0.05,
+                    }]);
+                }
+            }
+            ImpactEffectType::ShieldRipple => {
+                let normal = effect.normal;
+
+                let tangent = if normal.x.abs() < 0.9 {
+                    normal.cross(Vec3::unit_x())
+                } else {
+                    normal.cross(Vec3::unit_y())
+                }
+                .normalized();
+                let bitangent = normal.cross(tangent);
+
+                let radius = progress * 3.0;
+
+                let corner = |i: usize| {
+                    let angle = i as f32 / 6.0 * std::f32::consts::TAU;
+                    position.0 + (tangent * angle.cos() + bitangent * angle.sin()) * radius
+                };
+
+                for i in 0..6 {
+                    lasers.stage(&[LaserInstance {
+                        start: corner(i),
+                        end: corner(i + 1),
+                        colour: Vec3::new(0.25, 0.75, 1.0) * fade,
+                        width: 0.1,
+                    }]);
+                }
+            }
+        }
+    })
+}
+
 pub fn render_movement_circle(
     mut circle_instances: ResMut<GpuBuffer<CircleInstance>>,
     mut lines_buffer: ResMut<GpuBuffer<ColouredVertex>>,
     average_selected_position: Res<AverageSelectedPosition>,
     mouse_mode: Res<MouseMode>,
 ) {
-    if let (Some(avg), &MouseMode::Movement { point_on_plane, ty }) =
-        (average_selected_position.0, &*mouse_mode)
+    if let (
+        Some(avg),
+        &MouseMode::Movement {
+            point_on_plane,
+            ty,
+            match_slowest,
+            ..
+        },
+    ) = (average_selected_position.0, &*mouse_mode)
     {
         let circle_center = Vec3::new(avg.x, point_on_plane.y, avg.z);
 
@@ -183,6 +333,13 @@ pub fn render_movement_circle(
             MoveType::Normal => Vec3::unit_y(),
             MoveType::Attack => Vec3::unit_x(),
         };
+
+        // Lighten the colour to show that the group will move together.
+        let colour = if match_slowest {
+            (colour + Vec3::one()) / 2.0
+        } else {
+            colour
+        };
         let colour_with_alpha = ultraviolet::Vec4::new(colour.x, colour.y, colour.z, 0.15);
 
         circle_instances.stage(&[CircleInstance {
@@ -220,6 +377,380 @@ pub fn render_movement_circle(
     }
 }
 
+// Shows how long the selected ships would take to reach the point under the cursor, along with
+// marks along the way at regular intervals.
+pub fn render_arrival_estimate(
+    selected: Query<
+        (&Position, &MaxSpeed, Option<&Throttle>),
+        (With<Selected>, With<Ours>, With<CommandQueue>),
+    >,
+    average_selected_position: Res<AverageSelectedPosition>,
+    mouse_mode: Res<MouseMode>,
+    mouse_state: Res<MouseState>,
+    perspective_view: Res<PerspectiveView>,
+    dimensions: Res<Dimensions>,
+    dpi_factor: Res<DpiFactor>,
+    mut lines_buffer: ResMut<GpuBuffer<ColouredVertex>>,
+    mut glyph_layout_cache: ResMut<GlyphLayoutCache>,
+) {
+    let (avg, destination, match_slowest) = match (average_selected_position.0, &*mouse_mode) {
+        (
+            Some(avg),
+            &MouseMode::Movement {
+                point_on_plane,
+                match_slowest,
+                ..
+            },
+        ) => (avg, point_on_plane, match_slowest),
+        _ => return,
+    };
+
+    let ships: Vec<_> = selected
+        .iter()
+        .map(|(position, max_speed, throttle)| {
+            (position.0, max_speed.0 * Throttle::get(throttle).fraction())
+        })
+        .collect();
+
+    let eta = match estimated_arrival(&ships, destination, match_slowest) {
+        Some(eta) if eta > 0.0 => eta,
+        _ => return,
+    };
+
+    let offset = Vec2::new(15.0, 5.0) * dpi_factor.0;
+    let mut section = glyph_layout_cache.start_section(mouse_state.position + offset, dpi_factor.0);
+    section.push(format_args!("ETA {}", MissionTime(eta)), [1.0; 4]);
+    drop(section);
+
+    // Keep the number of marks down on long trips.
+    let interval = IntoIter::new([5.0, 10.0, 15.0, 30.0, 60.0, 120.0, 300.0])
+        .find(|&interval| eta / interval <= 8.0)
+        .unwrap_or(600.0);
+
+    let colour = Vec3::broadcast(0.75);
+    let mut time = interval;
+
+    while time < eta {
+        let point = avg + (destination - avg) * (time / eta);
+
+        lines_buffer.stage(&[
+            ColouredVertex {
+                position: point - Vec3::unit_y(),
+                colour,
+            },
+            ColouredVertex {
+                position: point + Vec3::unit_y(),
+                colour,
+            },
+        ]);
+
+        if let Some(pixel) = perspective_view.project_to_pixels(point, &dimensions) {
+            let mut section = glyph_layout_cache.start_centered_section(pixel, dpi_factor.0);
+            section.push(
+                format_args!("{}", MissionTime(time)),
+                [0.75, 0.75, 0.75, 1.0],
+            );
+        }
+
+        time += interval;
+    }
+}
+
+pub fn render_weapon_arcs(
+    selected: Query<(&Position, &RotationMatrix, &WeaponArcs), (With<Selected>, With<Ours>)>,
+    mouse_mode: Res<MouseMode>,
+    mut translucent_buffer: ResMut<GpuBuffer<TranslucentVertex>>,
+    mut lines_buffer: ResMut<GpuBuffer<ColouredVertex>>,
+) {
+    const SEGMENTS: usize = 8;
+
+    let colour = Vec3::new(1.0, 0.25, 0.0);
+
+    let mut render_arcs = |position: Vec3, rotation: Mat3, weapon_arcs: &WeaponArcs| {
+        let vertex = |position| TranslucentVertex {
+            position,
+            colour: Vec4::new(colour.x, colour.y, colour.z, 0.15),
+        };
+
+        for arc in &weapon_arcs.0 {
+            let edge = |i: usize| {
+                let angle = (i as f32 / SEGMENTS as f32 * 2.0 - 1.0) * arc.half_angle;
+                let direction = Rotor3::from_rotation_xz(angle) * arc.direction;
+                position + rotation * direction * arc.range
+            };
+
+            for i in 0..SEGMENTS {
+                translucent_buffer.stage(&[vertex(position), vertex(edge(i)), vertex(edge(i + 1))]);
+            }
+        }
+    };
+
+    selected.for_each(|(position, rotation, weapon_arcs)| {
+        render_arcs(position.0, rotation.matrix, weapon_arcs)
+    });
+
+    // Show where the arcs will point once the ships have arrived and turned.
+    if let MouseMode::Movement {
+        point_on_plane,
+        facing: Some(facing),
+        ..
+    } = *mouse_mode
+    {
+        let rotation = rotation_from_facing(facing).into_matrix();
+        let mut any_arcs = false;
+
+        selected.for_each(|(.., weapon_arcs)| {
+            any_arcs = true;
+            render_arcs(point_on_plane, rotation, weapon_arcs)
+        });
+
+        if any_arcs {
+            lines_buffer.stage(&[
+                ColouredVertex {
+                    position: point_on_plane,
+                    colour,
+                },
+                ColouredVertex {
+                    position: point_on_plane + facing * 20.0,
+                    colour,
+                },
+            ]);
+        }
+    }
+}
+
+pub fn render_area_targeting(
+    mut circle_instances: ResMut<GpuBuffer<CircleInstance>>,
+    mut lines_buffer: ResMut<GpuBuffer<ColouredVertex>>,
+    mouse_mode: Res<MouseMode>,
+) {
+    if let &MouseMode::AreaTargeting { area, point } = &*mouse_mode {
+        let colour = Vec3::new(1.0, 0.5, 0.0);
+
+        // The bands overlap, so the preview gets more opaque towards the center where the
+        // effect is strongest.
+        for radius in area.band_radii() {
+            circle_instances.stage(&[CircleInstance {
+                translation: point,
+                scale: radius,
+                colour: Vec4::new(colour.x, colour.y, colour.z, 0.1),
+            }]);
+        }
+
+        const SEGMENTS: usize = 32;
+
+        let great_circles: [fn(f32, f32) -> Vec3; 3] = [
+            |x, y| Vec3::new(x, y, 0.0),
+            |x, z| Vec3::new(x, 0.0, z),
+            |y, z| Vec3::new(0.0, y, z),
+        ];
+
+        for great_circle in IntoIter::new(great_circles) {
+            for i in 0..SEGMENTS {
+                for j in IntoIter::new([i, i + 1]) {
+                    let angle = j as f32 / SEGMENTS as f32 * std::f32::consts::TAU;
+
+                    lines_buffer.stage(&[ColouredVertex {
+                        position: point + great_circle(angle.cos(), angle.sin()) * area.radius,
+                        colour,
+                    }]);
+                }
+            }
+        }
+    }
+}
+
+// Puffs of gas drifting slowly around each cloud, thinning out as it's harvested.
+pub fn render_gas_clouds(
+    clouds: Query<(&Position, &CanBeHarvested)>,
+    clock: Res<SimClock>,
+    mut lasers: ResMut<GpuBuffer<LaserInstance>>,
+) {
+    const PUFFS: usize = 32;
+
+    clouds.for_each(|(position, cloud)| {
+        let remaining = cloud.gas / cloud.total;
+
+        for i in 0..PUFFS {
+            let angle = i as f32 * 2.4 + clock.time * 0.05;
+            let height = (i as f32 * 0.618).fract() * 2.0 - 1.0;
+            let distance = (0.3 + (i as f32 * 0.37).fract() * 0.7) * CanBeHarvested::RADIUS;
+            let ring = (1.0 - height * height).sqrt();
+
+            let direction = Vec3::new(angle.cos() * ring, height * 0.5, angle.sin() * ring);
+            let drift = Vec3::new(-angle.sin(), 0.0, angle.cos());
+            let start = position.0 + direction * distance;
+
+            lasers.stage(&[LaserInstance {
+                start,
+                end: start + drift * 2.0,
+                colour: Vec3::new(0.3, 0.9, 0.5) * remaining * 0.5,
+                width: 3.0,
+            }]);
+        }
+    });
+}
+
+pub fn render_mining_routes(
+    miners: Query<
+        (&Position, &CommandQueue, Option<&DropOff>),
+        (
+            With<Selected>,
+            With<Ours>,
+            Or<(With<CanMine>, With<CanHarvest>)>,
+        ),
+    >,
+    positions: Query<&Position>,
+    carriers: Query<(Entity, &Position), With<Carrying>>,
+    mut lines_buffer: ResMut<GpuBuffer<ColouredVertex>>,
+) {
+    miners.for_each(|(position, queue, drop_off)| {
+        let asteroid = queue.0.iter().find_map(|command| match command {
+            Command::Interact {
+                target,
+                ty: InteractionType::Mine | InteractionType::Salvage | InteractionType::Harvest,
+                ..
+            } => positions.get(*target).ok(),
+            _ => None,
+        });
+
+        let asteroid = match asteroid {
+            Some(asteroid) => asteroid.0,
+            None => return,
+        };
+
+        // Without an explicit drop-off, miners go back to the closest carrier.
+        let carrier = match drop_off {
+            Some(drop_off) => positions.get(drop_off.0).ok().map(|position| position.0),
+            None => carriers
+                .iter()
+                .map(|(_, carrier)| carrier.0)
+                .min_by(|&a, &b| {
+                    compare_floats((a - position.0).mag_sq(), (b - position.0).mag_sq())
+                }),
+        };
+
+        if let Some(carrier) = carrier {
+            let colour = if drop_off.is_some() {
+                Vec3::new(1.0, 0.5, 1.0)
+            } else {
+                Vec3::new(0.5, 0.0, 0.5)
+            };
+
+            stage_dashed_line(&mut lines_buffer, asteroid, carrier, colour);
+        }
+    })
+}
+
+fn stage_dashed_line(
+    lines_buffer: &mut GpuBuffer<ColouredVertex>,
+    start: Vec3,
+    end: Vec3,
+    colour: Vec3,
+) {
+    const DASH_LENGTH: f32 = 2.0;
+
+    let length = (end - start).mag();
+
+    if length == 0.0 {
+        return;
+    }
+
+    let direction = (end - start) / length;
+
+    let mut distance = 0.0;
+
+    while distance < length {
+        let dash_end = (distance + DASH_LENGTH).min(length);
+
+        lines_buffer.stage(&[
+            ColouredVertex {
+                position: start + direction * distance,
+                colour,
+            },
+            ColouredVertex {
+                position: start + direction * dash_end,
+                colour,
+            },
+        ]);
+
+        distance += DASH_LENGTH * 2.0;
+    }
+}
+
+pub fn render_rally_points(
+    carriers: Query<(&Position, &RallyPoint), (With<Selected>, With<Ours>)>,
+    mouse_mode: Res<MouseMode>,
+    mut lines_buffer: ResMut<GpuBuffer<ColouredVertex>>,
+    mut circle_instances: ResMut<GpuBuffer<CircleInstance>>,
+) {
+    let colour = Vec3::new(0.0, 0.5, 1.0);
+
+    let mut stage_marker = |point: Vec3| {
+        circle_instances.stage(&[CircleInstance {
+            translation: point,
+            scale: 2.0,
+            colour: Vec4::new(colour.x, colour.y, colour.z, 0.5),
+        }]);
+    };
+
+    if let MouseMode::SetRallyPoint { point_on_plane } = *mouse_mode {
+        stage_marker(point_on_plane);
+    }
+
+    carriers.for_each(|(position, rally_point)| {
+        stage_dashed_line(&mut lines_buffer, position.0, rally_point.0, colour);
+        stage_marker(rally_point.0);
+    });
+}
+
+pub fn render_construction_sites(
+    sites: Query<(&Position, &UnderConstruction)>,
+    mouse_mode: Res<MouseMode>,
+    perspective_view: Res<PerspectiveView>,
+    dimensions: Res<Dimensions>,
+    dpi_factor: Res<DpiFactor>,
+    mut lines_2d: ResMut<GpuBuffer<Vertex2D>>,
+    mut circle_instances: ResMut<GpuBuffer<CircleInstance>>,
+) {
+    if let MouseMode::PlaceStructure { point_on_plane, .. } = *mouse_mode {
+        circle_instances.stage(&[CircleInstance {
+            translation: point_on_plane,
+            scale: UnderConstruction::CONSTRUCTION_RANGE,
+            colour: Vec4::new(1.0, 0.75, 0.0, 0.5),
+        }]);
+    }
+
+    let width = 40.0 * dpi_factor.0;
+    let offset = Vec2::new(width / 2.0, 30.0 * dpi_factor.0);
+
+    sites.for_each(|(position, site)| {
+        let start = match perspective_view.project_to_pixels(position.0, &dimensions) {
+            Some(pixels) => pixels - offset,
+            None => return,
+        };
+
+        let filled = start + Vec2::new(width * site.fraction(), 0.0);
+        let end = start + Vec2::new(width, 0.0);
+
+        for &(from, to, colour) in &[
+            (start, filled, Vec3::new(1.0, 0.75, 0.0)),
+            (filled, end, Vec3::broadcast(0.25)),
+        ] {
+            lines_2d.stage(&[
+                Vertex2D {
+                    pos: to_wgpu(from, &dimensions),
+                    colour,
+                },
+                Vertex2D {
+                    pos: to_wgpu(to, &dimensions),
+                    colour,
+                },
+            ]);
+        }
+    });
+}
+
 pub fn debug_render_targets(
     query: Query<(&Position, &CommandQueue), With<Selected>>,
     positions: Query<&Position>,
@@ -231,7 +762,7 @@ pub fn debug_render_targets(
             Some(Command::Interact { target, .. }) => {
                 positions.get(*target).ok().map(|position| position.0)
             }
-            None => None,
+            Some(Command::Wait(_)) | Some(Command::Face { .. }) | None => None,
         };
 
         if let Some(target_pos) = target_pos {
@@ -249,8 +780,68 @@ pub fn debug_render_targets(
     })
 }
 
+// Draws the rest of the queue for ships with more than one order lined up, numbering each stop.
+pub fn render_waypoints(
+    query: Query<(&Position, &CommandQueue), (With<Selected>, With<Ours>)>,
+    positions: Query<&Position>,
+    mut lines_buffer: ResMut<GpuBuffer<ColouredVertex>>,
+    mut circle_instances: ResMut<GpuBuffer<CircleInstance>>,
+    mut glyph_layout_cache: ResMut<GlyphLayoutCache>,
+    perspective_view: Res<PerspectiveView>,
+    dimensions: Res<Dimensions>,
+    dpi_factor: Res<DpiFactor>,
+) {
+    let colour = Vec3::new(0.0, 1.0, 0.5);
+
+    query.for_each(|(position, queue)| {
+        let waypoints = queue
+            .0
+            .iter()
+            .filter_map(|command| match command {
+                Command::MoveTo { point, .. } => Some(*point),
+                Command::Interact { target, .. } => {
+                    positions.get(*target).ok().map(|position| position.0)
+                }
+                Command::Wait(_) | Command::Face { .. } => None,
+            })
+            .collect::<Vec<_>>();
+
+        if waypoints.len() < 2 {
+            return;
+        }
+
+        let mut previous = position.0;
+
+        for (i, &waypoint) in waypoints.iter().enumerate() {
+            lines_buffer.stage(&[
+                ColouredVertex {
+                    position: previous,
+                    colour,
+                },
+                ColouredVertex {
+                    position: waypoint,
+                    colour,
+                },
+            ]);
+
+            circle_instances.stage(&[CircleInstance {
+                translation: waypoint,
+                scale: 1.0,
+                colour: Vec4::new(colour.x, colour.y, colour.z, 0.5),
+            }]);
+
+            if let Some(pixel) = perspective_view.project_to_pixels(waypoint, &dimensions) {
+                let mut section = glyph_layout_cache.start_centered_section(pixel, dpi_factor.0);
+                section.push(format_args!("{}", i + 1), [1.0; 4]);
+            }
+
+            previous = waypoint;
+        }
+    })
+}
+
 pub fn render_agro_ranges(
-    query: Query<(&Position, &AgroRange), (With<Friendly>, With<Selected>)>,
+    query: Query<(&Position, &AgroRange), (With<Ours>, With<Selected>)>,
     mut ranges: ResMut<GpuBuffer<RangeInstance>>,
 ) {
     query.for_each(|(position, range)| {
@@ -262,6 +853,99 @@ pub fn render_agro_ranges(
     })
 }
 
+// Shields flash up as a bubble around the ship when they stop a projectile.
+pub fn render_shield_impacts(
+    query: Query<(&Position, &WorldSpaceBoundingBox, &Shield, &LastHit)>,
+    clock: Res<SimClock>,
+    mut ranges: ResMut<GpuBuffer<RangeInstance>>,
+) {
+    query.for_each(|(position, bounding_box, shield, last_hit)| {
+        let since_hit = clock.time - last_hit.0;
+
+        if shield.current == 0.0 || since_hit > Shield::IMPACT_FLASH_DURATION {
+            return;
+        }
+
+        let fade = 1.0 - since_hit / Shield::IMPACT_FLASH_DURATION;
+
+        ranges.stage(&[RangeInstance {
+            translation: position.0,
+            scale: bounding_box.0.size().mag() / 2.0,
+            colour: Vec4::new(0.25, 0.75, 1.0, fade),
+        }]);
+    })
+}
+
+pub fn render_sensor_relays(
+    query: Query<(&Position, &SensorRelay)>,
+    mut ranges: ResMut<GpuBuffer<RangeInstance>>,
+) {
+    query.for_each(|(position, relay)| {
+        ranges.stage(&[RangeInstance {
+            translation: position.0,
+            scale: relay.pickup_radius,
+            colour: Vec4::new(0.0, 1.0, 1.0, 1.0),
+        }]);
+    })
+}
+
+pub fn render_relics(
+    relics: Query<&Position, With<Relic>>,
+    holders: Query<(&Position, &HoldingItem)>,
+    is_relic: Query<&Relic>,
+    mut ranges: ResMut<GpuBuffer<RangeInstance>>,
+) {
+    let colour = Vec4::new(1.0, 0.8, 0.0, 1.0);
+
+    relics.for_each(|position| {
+        ranges.stage(&[RangeInstance {
+            translation: position.0,
+            scale: 3.0,
+            colour,
+        }]);
+    });
+
+    holders.for_each(|(position, holding_item)| {
+        if is_relic.get(holding_item.0).is_ok() {
+            ranges.stage(&[RangeInstance {
+                translation: position.0,
+                scale: 5.0,
+                colour,
+            }]);
+        }
+    });
+}
+
+pub fn render_dust(
+    dust: Res<AmbientDust>,
+    comets: Query<&Position, With<Comet>>,
+    camera: Res<Camera>,
+    orbit: Res<Orbit>,
+    settings: Res<Settings>,
+    mut lasers: ResMut<GpuBuffer<LaserInstance>>,
+) {
+    if settings.disable_dust {
+        return;
+    }
+
+    let camera_position = camera.center + orbit.as_vector();
+    let density = dust.density_at(camera_position, comets.iter().map(|position| position.0));
+    let count = (AmbientDust::MAX_MOTES as f32 * density) as usize;
+    let half_size = AmbientDust::BOX_SIZE / 2.0;
+
+    for position in dust.mote_positions(camera_position).take(count) {
+        // Fade out towards the edges of the box so that motes don't pop in as they wrap.
+        let fade = (1.0 - (position - camera_position).mag() / half_size).max(0.0);
+
+        lasers.stage(&[LaserInstance {
+            start: position,
+            end: position + Vec3::unit_y() * 0.05,
+            colour: Vec3::broadcast(0.2 * fade),
+            width: 0.05,
+        }]);
+    }
+}
+
 pub fn render_drag_box(
     mouse_state: Res<MouseState>,
     dimensions: Res<Dimensions>,
@@ -308,7 +992,266 @@ pub fn render_drag_box(
     }
 }
 
-fn to_wgpu(point: Vec2, dimensions: &Dimensions) -> Vec2 {
+pub fn render_order_confirmation(
+    order_confirmation: Res<OrderConfirmation>,
+    mouse_state: Res<MouseState>,
+    dimensions: Res<Dimensions>,
+    dpi_factor: Res<DpiFactor>,
+    mut lines_2d: ResMut<GpuBuffer<Vertex2D>>,
+) {
+    if let Some(progress) = order_confirmation.progress() {
+        const SEGMENTS: usize = 32;
+
+        let radius = 20.0 * dpi_factor.0;
+        let colour = Vec3::new(1.0, 0.25, 0.0);
+        let filled_segments = (progress * SEGMENTS as f32) as usize;
+
+        for i in 0..filled_segments {
+            for j in IntoIter::new([i, i + 1]) {
+                // Start at the top of the ring and go clockwise.
+                let angle = j as f32 / SEGMENTS as f32 * std::f32::consts::TAU;
+                let offset = Vec2::new(angle.sin(), -angle.cos()) * radius;
+
+                lines_2d.stage(&[Vertex2D {
+                    pos: to_wgpu(mouse_state.position + offset, &dimensions),
+                    colour,
+                }]);
+            }
+        }
+    }
+}
+
+pub fn render_order_rejection(
+    order_feedback: Res<OrderFeedback>,
+    real_time: Res<RealTime>,
+    dimensions: Res<Dimensions>,
+    dpi_factor: Res<DpiFactor>,
+    mut lines_2d: ResMut<GpuBuffer<Vertex2D>>,
+) {
+    let (position, flashed_at) = match order_feedback.flash {
+        Some(flash) => flash,
+        None => return,
+    };
+
+    let progress = (real_time.0 - flashed_at) / OrderFeedback::FLASH_DURATION;
+
+    if progress > 1.0 {
+        return;
+    }
+
+    const SEGMENTS: usize = 32;
+
+    let radius = (10.0 + progress * 10.0) * dpi_factor.0;
+    let colour = Vec3::new(1.0, 0.0, 0.0) * (1.0 - progress);
+
+    for i in 0..SEGMENTS {
+        for j in IntoIter::new([i, i + 1]) {
+            let angle = j as f32 / SEGMENTS as f32 * std::f32::consts::TAU;
+            let offset = Vec2::new(angle.sin(), -angle.cos()) * radius;
+
+            lines_2d.stage(&[Vertex2D {
+                pos: to_wgpu(position + offset, &dimensions),
+                colour,
+            }]);
+        }
+    }
+}
+
+pub fn render_mission_clock(
+    clock: Res<SimClock>,
+    hud_layout: Res<HudLayout>,
+    mut glyph_layout_cache: ResMut<GlyphLayoutCache>,
+    dimensions: Res<Dimensions>,
+    dpi_factor: Res<DpiFactor>,
+) {
+    if !hud_layout.mission_clock.visible {
+        return;
+    }
+
+    let mut section = glyph_layout_cache.start_anchored_section(
+        hud_layout.mission_clock.anchor,
+        UnitButtons::LINE_HEIGHT * dpi_factor.0,
+        dimensions.to_vec(),
+        dpi_factor.0,
+    );
+    section.push(format_args!("{}", MissionTime(clock.time)), [1.0; 4]);
+
+    if clock.speed() != GameSpeed::Normal {
+        section.push(
+            format_args!(" ({})", clock.speed().to_str()),
+            [1.0, 1.0, 0.0, 1.0],
+        );
+    }
+}
+
+// A frame around the edge of the screen and a line of text, so that a paused game can't be
+// mistaken for a quiet moment.
+pub fn render_paused(
+    paused: Res<Paused>,
+    mut glyph_layout_cache: ResMut<GlyphLayoutCache>,
+    mut lines_2d: ResMut<GpuBuffer<Vertex2D>>,
+    dimensions: Res<Dimensions>,
+    dpi_factor: Res<DpiFactor>,
+) {
+    if !paused.0 {
+        return;
+    }
+
+    let colour = Vec3::new(1.0, 1.0, 0.0);
+    let screen = dimensions.to_vec();
+    let inset = 4.0 * dpi_factor.0;
+
+    let corners = [
+        Vec2::broadcast(inset),
+        Vec2::new(screen.x - inset, inset),
+        screen - Vec2::broadcast(inset),
+        Vec2::new(inset, screen.y - inset),
+    ];
+
+    for i in 0..corners.len() {
+        let next = (i + 1) % corners.len();
+
+        lines_2d.stage(&[
+            Vertex2D {
+                pos: to_wgpu(corners[i], &dimensions),
+                colour,
+            },
+            Vertex2D {
+                pos: to_wgpu(corners[next], &dimensions),
+                colour,
+            },
+        ]);
+    }
+
+    let position = Vec2::new(screen.x / 2.0, screen.y * 0.2);
+    let mut section = glyph_layout_cache.start_centered_section(position, dpi_factor.0);
+    section.push(format_args!("PAUSED\n"), [1.0, 1.0, 0.0, 1.0]);
+    section.push(
+        format_args!("Orders given now are carried out when the game resumes"),
+        [1.0; 4],
+    );
+}
+
+// Only shown in multiplayer, under the mission clock.
+pub fn render_player_names(
+    players: Res<Players>,
+    mut glyph_layout_cache: ResMut<GlyphLayoutCache>,
+    dimensions: Res<Dimensions>,
+    dpi_factor: Res<DpiFactor>,
+) {
+    if players.players.len() < 2 {
+        return;
+    }
+
+    let position = Vec2::new(
+        dimensions.width as f32 / 2.0,
+        UnitButtons::LINE_HEIGHT * dpi_factor.0,
+    );
+    let mut section = glyph_layout_cache.start_centered_section(position, dpi_factor.0);
+
+    for (i, (name, colour)) in players.players.iter().enumerate() {
+        if i > 0 {
+            section.push(format_args!(" - "), [1.0; 4]);
+        }
+
+        section.push(format_args!("{}", name), colour.rgba());
+    }
+}
+
+pub fn render_render_stats(
+    render_stats: Res<RenderStats>,
+    settings: Res<Settings>,
+    mut glyph_layout_cache: ResMut<GlyphLayoutCache>,
+    dimensions: Res<Dimensions>,
+    dpi_factor: Res<DpiFactor>,
+) {
+    const WIDTH: f32 = 200.0;
+
+    if !settings.enable_render_stats {
+        return;
+    }
+
+    let position = Vec2::new(dimensions.width as f32 - WIDTH * dpi_factor.0, 0.0);
+    let mut section = glyph_layout_cache.start_section(position, dpi_factor.0);
+
+    section.push(
+        format_args!("Draw calls: {}\n", render_stats.draw_calls),
+        [1.0; 4],
+    );
+
+    for &(pipeline, instances) in &render_stats.instances_per_pipeline {
+        section.push(
+            format_args!("{}: {}\n", pipeline, instances),
+            [0.75, 0.75, 0.75, 1.0],
+        );
+    }
+}
+
+pub fn render_notifications(
+    mut notifications: ResMut<Notifications>,
+    real_time: Res<RealTime>,
+    attack_alert: Res<AttackAlert>,
+    hud_layout: Res<HudLayout>,
+    mut glyph_layout_cache: ResMut<GlyphLayoutCache>,
+    dimensions: Res<Dimensions>,
+    dpi_factor: Res<DpiFactor>,
+) {
+    notifications.remove_expired(real_time.0);
+
+    let alert_shown = attack_alert.is_shown(real_time.0);
+
+    if (notifications.is_empty() && !alert_shown) || !hud_layout.notifications.visible {
+        return;
+    }
+
+    let lines = notifications.len() + alert_shown as usize;
+    let height = lines as f32 * UnitButtons::LINE_HEIGHT * dpi_factor.0;
+
+    let mut section = glyph_layout_cache.start_anchored_section(
+        hud_layout.notifications.anchor,
+        height,
+        dimensions.to_vec(),
+        dpi_factor.0,
+    );
+
+    if alert_shown {
+        section.push(
+            format_args!("Under attack! Press Space to jump there\n"),
+            Severity::Danger.colour(),
+        );
+    }
+
+    for message in notifications.iter() {
+        section.push(format_args!("{}\n", message), [1.0, 0.5, 0.5, 1.0]);
+    }
+}
+
+pub fn render_pending_order_timing(
+    pending_timing: Res<PendingOrderTiming>,
+    mouse_state: Res<MouseState>,
+    mut glyph_layout_cache: ResMut<GlyphLayoutCache>,
+    dpi_factor: Res<DpiFactor>,
+) {
+    let timing = match pending_timing.0 {
+        Some(timing) => timing,
+        None => return,
+    };
+
+    let position = mouse_state.position + Vec2::new(16.0, 16.0) * dpi_factor.0;
+    let mut section = glyph_layout_cache.start_section(position, dpi_factor.0);
+
+    match timing {
+        OrderTiming::Delay(delay) => section.push(
+            format_args!("Next order in {}s", delay),
+            [1.0, 1.0, 0.0, 1.0],
+        ),
+        OrderTiming::OnMark => {
+            section.push(format_args!("Next order on mark"), [1.0, 1.0, 0.0, 1.0])
+        }
+    }
+}
+
+pub(crate) fn to_wgpu(point: Vec2, dimensions: &Dimensions) -> Vec2 {
     let dimensions = dimensions.to_vec();
 
     let scaled = point / dimensions * 2.0;
@@ -317,28 +1260,33 @@ fn to_wgpu(point: Vec2, dimensions: &Dimensions) -> Vec2 {
 
 pub fn render_buttons(
     selected_button: Res<SelectedButton>,
+    buttons: Res<UnitButtons>,
     mut lines_2d: ResMut<GpuBuffer<Vertex2D>>,
     dimensions: Res<Dimensions>,
     dpi_factor: Res<DpiFactor>,
 ) {
-    if let Some(i) = selected_button.0 {
-        let colour = Vec3::one();
+    // The throttle line is directly above the first button.
+    let line = match selected_button.0 {
+        Some(i) => Some(i + 1),
+        None if buttons.throttle_hovered => Some(0),
+        None => None,
+    };
 
-        let offset = i + 1 + UnitButtons::UI_LINES as usize;
+    if let Some(line) = line {
+        let colour = Vec3::one();
 
         let line_height = UnitButtons::LINE_HEIGHT * dpi_factor.0;
 
+        let start = buttons.origin + Vec2::new(0.0, line as f32 * line_height);
+
         lines_2d.stage(&[
             Vertex2D {
-                pos: to_wgpu(Vec2::new(0.0, offset as f32 * line_height), &dimensions),
+                pos: to_wgpu(start, &dimensions),
                 colour,
             },
             Vertex2D {
                 pos: to_wgpu(
-                    Vec2::new(
-                        UnitButtons::BUTTON_WIDTH * dpi_factor.0,
-                        offset as f32 * line_height,
-                    ),
+                    start + Vec2::new(UnitButtons::BUTTON_WIDTH * dpi_factor.0, 0.0),
                     &dimensions,
                 ),
                 colour,
@@ -353,51 +1301,173 @@ pub fn render_3d_ship_stats(
         (
             &Position,
             Option<&Health>,
+            Option<&Shield>,
             Option<&Selected>,
             Option<&Carrying>,
             Option<&OnBoard>,
             Option<&StoredMinerals>,
             Option<&CanBeMined>,
             Option<&BuildQueue>,
+            Option<&Scuttling>,
+            Option<&RulesOfEngagementOverride>,
+            Option<&CommandQueue>,
+            Option<&HoldPosition>,
+            Option<&MineLayer>,
+            // Grouped to stay within the limit on the number of query items.
+            (
+                Option<&Veterancy>,
+                Option<&PlayerTag>,
+                Option<&Heat>,
+                Option<&Autonomy>,
+                Option<&TargetingStance>,
+            ),
         ),
-        Without<Enemy>,
+        Without<Theirs>,
     >,
-    people: Query<(Option<&Engineer>, Option<&Researcher>)>,
+    people: Query<(
+        Option<&Engineer>,
+        Option<&Researcher>,
+        Option<&Gunner>,
+        Option<&Pilot>,
+    )>,
     carried_ships: Query<(&ModelId, &Health)>,
     mut glyph_layout_cache: ResMut<GlyphLayoutCache>,
     perspective_view: Res<PerspectiveView>,
     dimensions: Res<Dimensions>,
-    total_time: Res<TotalTime>,
+    clock: Res<SimClock>,
+    ship_catalog: Res<ShipCatalog>,
     dpi_factor: Res<DpiFactor>,
 ) {
     query.for_each(
-        |(pos, health, selected, carrying, on_board, minerals, can_be_mined, build_queue)| {
-            let projected =
-                perspective_view.perspective_view * Vec4::new(pos.0.x, pos.0.y, pos.0.z, 1.0);
+        |(
+            pos,
+            health,
+            shield,
+            selected,
+            carrying,
+            on_board,
+            minerals,
+            can_be_mined,
+            build_queue,
+            scuttling,
+            rules_override,
+            command_queue,
+            hold_position,
+            mine_layer,
+            (veterancy, player_tag, heat, autonomy, targeting_stance),
+        )| {
+            let unnormalised_pos = match perspective_view.project_to_pixels(pos.0, &dimensions) {
+                Some(pos) => pos,
+                // Ship is behind the camera.
+                None => return,
+            };
 
-            // Ship is behind the camera.
-            if projected.z < 0.0 {
-                return;
+            let selected = selected.is_some();
+
+            let mut section = glyph_layout_cache.start_section(unnormalised_pos, dpi_factor.0);
+
+            if let Some(&player_tag) = player_tag {
+                section.push(
+                    format_args!("[{}]\n", player_tag.name()),
+                    player_tag.colour(),
+                );
             }
 
-            let screen_space_pos = Vec2::new(projected.x, projected.y) / projected.w;
+            if let Some(health) = health {
+                let chevrons = veterancy.map_or("", Veterancy::chevrons);
 
-            let uv_space_pos = Vec2::new(
-                (screen_space_pos.x + 1.0) / 2.0,
-                (1.0 - screen_space_pos.y) / 2.0,
-            );
-            let unnormalised_pos = uv_space_pos * dimensions.to_vec();
+                if selected || health.current < health.max || !chevrons.is_empty() {
+                    section.push(
+                        format_args!("Health: {:.2} {}\n", health.current, chevrons),
+                        [1.0; 4],
+                    );
+                }
+            }
 
-            let selected = selected.is_some();
+            if let Some(shield) = shield {
+                if selected || shield.current < shield.max {
+                    section.push(
+                        format_args!("Shield: {:.2}\n", shield.current),
+                        [0.25, 0.75, 1.0, 1.0],
+                    );
+                }
+            }
 
-            let mut section = glyph_layout_cache.start_section(unnormalised_pos, dpi_factor.0);
+            if let Some(heat) = heat {
+                if selected || heat.current > 0.0 {
+                    const BAR_LENGTH: usize = 10;
+                    let filled = (heat.fraction() * BAR_LENGTH as f32).round() as usize;
 
-            if let Some(health) = health {
-                if selected || health.current < health.max {
-                    section.push(format_args!("Health: {:.2}\n", health.current), [1.0; 4]);
+                    let colour = if heat.overheated {
+                        [1.0, 0.0, 0.0, 1.0]
+                    } else {
+                        [1.0, 1.0 - heat.fraction() * 0.6, 1.0 - heat.fraction(), 1.0]
+                    };
+
+                    section.push(
+                        format_args!(
+                            "Heat: [{}{}]{}\n",
+                            "|".repeat(filled),
+                            ".".repeat(BAR_LENGTH - filled),
+                            if heat.overheated { " OVERHEATED" } else { "" }
+                        ),
+                        colour,
+                    );
                 }
             }
 
+            if let Some(rules_override) = rules_override {
+                if selected {
+                    section.push(
+                        format_args!("Rules of Engagement: {}\n", rules_override.0.to_str()),
+                        [1.0; 4],
+                    );
+                }
+            }
+
+            if let Some(&autonomy) = autonomy {
+                if selected && autonomy != Autonomy::Local {
+                    section.push(format_args!("{}\n", autonomy.to_str()), [1.0; 4]);
+                }
+            }
+
+            if let Some(&stance) = targeting_stance {
+                if selected && stance != TargetingStance::Nearest {
+                    section.push(format_args!("Targeting: {}\n", stance.to_str()), [1.0; 4]);
+                }
+            }
+
+            if hold_position.is_some() && selected {
+                section.push(format_args!("Holding position\n"), [1.0; 4]);
+            }
+
+            if let Some(mine_layer) = mine_layer {
+                if selected {
+                    section.push(format_args!("Mines: {}\n", mine_layer.mines), [1.0; 4]);
+                }
+            }
+
+            match command_queue.and_then(|queue| queue.0.front()) {
+                Some(Command::Wait(WaitFor::Time(timer))) => section.push(
+                    format_args!("T-{:.1}s\n", timer.remaining(&clock)),
+                    [1.0, 1.0, 0.0, 1.0],
+                ),
+                Some(Command::Wait(WaitFor::Mark)) => {
+                    section.push(format_args!("Waiting for mark\n"), [1.0, 1.0, 0.0, 1.0])
+                }
+                _ => {}
+            }
+
+            if let Some(scuttling) = scuttling {
+                section.push(
+                    format_args!(
+                        "Scuttling in {:.1}s\n",
+                        scuttling.detonates_at.remaining(&clock)
+                    ),
+                    [1.0, 0.0, 0.0, 1.0],
+                );
+            }
+
             if let Some(carrying) = carrying {
                 if selected || !carrying.is_empty() {
                     section.push(
@@ -456,9 +1526,13 @@ pub fn render_3d_ship_stats(
                     let mut counts = [0; PersonEnum::COUNT];
 
                     on_board.0.iter().for_each(|&entity| {
-                        if let Ok((engineer, researcher)) = people.get(entity) {
-                            let person_enum =
-                                PersonEnum::new(engineer.is_some(), researcher.is_some());
+                        if let Ok((engineer, researcher, gunner, pilot)) = people.get(entity) {
+                            let person_enum = PersonEnum::new(
+                                engineer.is_some(),
+                                researcher.is_some(),
+                                gunner.is_some(),
+                                pilot.is_some(),
+                            );
                             counts[person_enum as usize] += 1;
                         }
                     });
@@ -499,7 +1573,7 @@ pub fn render_3d_ship_stats(
             }
 
             if let Some(build_queue) = build_queue {
-                let progress = build_queue.progress_time(total_time.0);
+                let progress = build_queue.progress_time(&clock, &ship_catalog);
 
                 if selected || progress.is_some() {
                     section.push(
@@ -0,0 +1,154 @@
+use bevy_ecs::prelude::*;
+use components_and_resources::components::*;
+use components_and_resources::resources::*;
+use ultraviolet::Vec2;
+
+pub fn advance_research(
+    mut tech_tree: ResMut<PerSide<TechTree>>,
+    mut global_research: ResMut<PerSide<GlobalResearch>>,
+    players: Res<Players>,
+    mut notifications: ResMut<Notifications>,
+    real_time: Res<RealTime>,
+) {
+    for &side in &[Side::Friendly, Side::Enemy] {
+        if let Some(tech) = tech_tree[side].advance(&mut global_research[side].0) {
+            if side == players.local_side() {
+                notifications.push(format!("Research complete: {}", tech.name()), real_time.0);
+            }
+        }
+    }
+}
+
+pub fn apply_research_upgrades(
+    tech_tree: Res<PerSide<TechTree>>,
+    mut build_queues: Query<
+        (&mut BuildQueue, Option<&Friendly>),
+        Or<(With<Friendly>, With<Enemy>)>,
+    >,
+    without_labs: Query<
+        (Entity, Option<&Friendly>),
+        (
+            With<OnBoard>,
+            Or<(With<Friendly>, With<Enemy>)>,
+            Without<ResearchMultiplier>,
+        ),
+    >,
+    mut commands: Commands,
+) {
+    // Runs every tick rather than only when the tech tree changes, so that ships built or spawned
+    // after an upgrade is researched get it too.
+    build_queues.for_each_mut(|(mut build_queue, friendly)| {
+        let build_speed_bonus = tech_tree[Side::of(friendly)].build_speed_bonus();
+
+        // Avoids flagging every build queue as changed each tick.
+        if build_queue.speed_bonus != build_speed_bonus {
+            build_queue.speed_bonus = build_speed_bonus;
+        }
+    });
+
+    without_labs.for_each(|(entity, friendly)| {
+        if tech_tree[Side::of(friendly)].is_unlocked(Tech::ResearchLabs) {
+            commands.entity(entity).insert(ResearchMultiplier(1.5));
+        }
+    });
+}
+
+pub fn toggle_research_panel(
+    keyboard_state: Res<KeyboardState>,
+    mut research_panel: ResMut<ResearchPanel>,
+) {
+    if keyboard_state.research_panel.0 {
+        research_panel.open = !research_panel.open;
+    }
+}
+
+pub fn set_hovered_tech(
+    mut research_panel: ResMut<ResearchPanel>,
+    mouse_state: Res<MouseState>,
+    dimensions: Res<Dimensions>,
+    dpi_factor: Res<DpiFactor>,
+) {
+    let left_edge = dimensions.width as f32 - ResearchPanel::WIDTH * dpi_factor.0;
+
+    research_panel.hovered = if research_panel.open && mouse_state.position.x > left_edge {
+        // The first line is the heading.
+        let line = (mouse_state.position.y / (UnitButtons::LINE_HEIGHT * dpi_factor.0)) as usize;
+        line.checked_sub(1)
+            .and_then(|index| Tech::ARRAY.get(index))
+            .copied()
+    } else {
+        None
+    };
+}
+
+pub fn choose_research(
+    research_panel: Res<ResearchPanel>,
+    mouse_state: Res<MouseState>,
+    mut player_commands: ResMut<PlayerCommands>,
+) {
+    if !mouse_state.left_state.was_clicked() {
+        return;
+    }
+
+    if let Some(tech) = research_panel.hovered {
+        player_commands.local.push(PlayerCommand::Research(tech));
+    }
+}
+
+pub fn render_research_panel(
+    research_panel: Res<ResearchPanel>,
+    tech_tree: Res<PerSide<TechTree>>,
+    global_research: Res<PerSide<GlobalResearch>>,
+    players: Res<Players>,
+    key_bindings: Res<KeyBindings>,
+    mut glyph_layout_cache: ResMut<GlyphLayoutCache>,
+    dimensions: Res<Dimensions>,
+    dpi_factor: Res<DpiFactor>,
+) {
+    if !research_panel.open {
+        return;
+    }
+
+    let position = Vec2::new(
+        dimensions.width as f32 - ResearchPanel::WIDTH * dpi_factor.0,
+        0.0,
+    );
+
+    let mut section = glyph_layout_cache.start_section(position, dpi_factor.0);
+
+    section.push(
+        format_args!("Research ({:?} to close)\n", key_bindings.research_panel),
+        [1.0; 4],
+    );
+
+    let tech_tree = &tech_tree[players.local_side()];
+    let global_research = &global_research[players.local_side()];
+
+    for tech in std::array::IntoIter::new(Tech::ARRAY) {
+        let status = tech_tree.status(tech);
+
+        let (status_text, mut colour) = match status {
+            TechStatus::Unlocked => ("done".to_string(), [0.5, 1.0, 0.5, 1.0]),
+            TechStatus::Researching => (
+                format!("{:.0}%", (global_research.0 / tech.cost()).min(1.0) * 100.0),
+                [1.0, 1.0, 0.5, 1.0],
+            ),
+            TechStatus::Available => (format!("{}", tech.cost()), [1.0; 4]),
+            TechStatus::Locked => ("locked".to_string(), [0.5, 0.5, 0.5, 1.0]),
+        };
+
+        if research_panel.hovered == Some(tech) && status == TechStatus::Available {
+            colour = [0.5, 0.75, 1.0, 1.0];
+        }
+
+        section.push(
+            format_args!(
+                "{} ({}) - {}\n",
+                tech.name(),
+                tech.description(),
+                status_text
+            ),
+            colour,
+        );
+    }
+}
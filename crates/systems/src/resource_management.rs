@@ -1,8 +1,9 @@
 use crate::find_functions::*;
 use bevy_ecs::prelude::*;
 use components_and_resources::components::*;
-use components_and_resources::gpu_structs::LaserVertex;
+use components_and_resources::gpu_structs::LaserInstance;
 use components_and_resources::resources::*;
+use components_and_resources::utils::compare_floats;
 use ultraviolet::Vec3;
 
 pub fn mine(
@@ -12,16 +13,43 @@ pub fn mine(
         &mut CommandQueue,
         &mut StoredMinerals,
         &mut Rotation,
+        Option<&DropOff>,
+        Option<&MiningSlot>,
+        Option<&Friendly>,
+        Option<&Autonomy>,
+        Option<&Ours>,
     )>,
-    mut targets: Query<(&Position, &mut CanBeMined)>,
-    new_targets: Query<(Entity, &Position, &Scale), With<CanBeMined>>,
+    mut targets: Query<(&Position, &mut CanBeMined, Option<&Comet>)>,
+    mut new_targets: Query<(Entity, &Position, &Scale, &mut MiningClaims), With<CanBeMined>>,
     carriers: Query<(Entity, &Position), With<Carrying>>,
-    delta_time: Res<DeltaTime>,
+    clock: Res<SimClock>,
+    tech_tree: Res<PerSide<TechTree>>,
+    mut game_events: ResMut<GameEvents>,
     mut commands: Commands,
-    mut lasers: ResMut<GpuBuffer<LaserVertex>>,
+    mut lasers: ResMut<GpuBuffer<LaserInstance>>,
 ) {
     query.for_each_mut(
-        |(pos, max_speed, mut queue, mut stored_minerals, mut rotation)| {
+        |(
+            pos,
+            max_speed,
+            mut queue,
+            mut stored_minerals,
+            mut rotation,
+            drop_off,
+            mining_slot,
+            friendly,
+            autonomy,
+            ours,
+        )| {
+            let acts_alone = Autonomy::get(autonomy).acts_alone();
+
+            let return_to_carrier = |queue: &mut CommandQueue| match drop_off
+                .and_then(|drop_off| carriers.get(drop_off.0).ok())
+            {
+                Some(carrier) => find_next_carrier(pos.0, queue, std::iter::once(carrier)),
+                None => find_next_carrier(pos.0, queue, carriers.iter()),
+            };
+
             let (target, range_sq) = match queue.0.front() {
                 Some(Command::Interact {
                     target,
@@ -33,42 +61,51 @@ pub fn mine(
 
             if stored_minerals.stored >= stored_minerals.capacity {
                 queue.0.pop_front();
-                find_next_carrier(pos.0, &mut queue, carriers.iter());
-                find_next_asteroid(pos.0, &mut queue, &new_targets);
+
+                // Miners under full manual control wait to be told where to drop off.
+                if acts_alone {
+                    return_to_carrier(&mut queue);
+                    find_next_asteroid(pos.0, &mut queue, &mut new_targets);
+                }
+
                 return;
             }
 
-            if let Ok((target_pos, mut can_be_mined)) = targets.get_mut(*target) {
+            if let Ok((target_pos, mut can_be_mined, comet)) = targets.get_mut(*target) {
                 let max_force = max_speed.max_force();
                 let vector = target_pos.0 - pos.0;
                 let within_range = vector.mag_sq() < range_sq + max_force;
+                let has_slot = mining_slot.map_or(false, |slot| slot.asteroid == *target);
 
-                if within_range {
+                if within_range && has_slot {
                     rotation.0 = crate::rotation_from_facing(vector);
 
                     // This is not good in terms of 'seperation of concerns' but whatever
                     {
                         let laser_start = pos.0 + rotation.0 * Models::MINER_LASER_OFFSET;
 
-                        lasers.stage(&[
-                            LaserVertex {
-                                position: laser_start,
-                                colour: Vec3::unit_z(),
-                            },
-                            LaserVertex {
-                                position: target_pos.0,
-                                colour: Vec3::unit_x(),
-                            },
-                        ]);
+                        lasers.stage(&[LaserInstance {
+                            start: laser_start,
+                            end: target_pos.0,
+                            colour: Vec3::new(0.5, 0.0, 0.5),
+                            width: 0.1,
+                        }]);
                     }
 
-                    let to_mine = delta_time.0;
+                    let to_mine =
+                        clock.delta * tech_tree[Side::of(friendly)].mining_rate_multiplier();
+                    let richness = comet.map_or(1.0, |comet| comet.richness);
                     let to_mine = to_mine
                         .min(can_be_mined.minerals)
-                        .min(stored_minerals.capacity - stored_minerals.stored);
+                        .min((stored_minerals.capacity - stored_minerals.stored) / richness);
                     can_be_mined.minerals -= to_mine;
 
-                    stored_minerals.stored += to_mine;
+                    // Only the player's own miners are watched closely enough to notice.
+                    if ours.is_some() && to_mine > 0.0 && can_be_mined.minerals <= 0.0 {
+                        game_events.push(clock.time, Severity::Info, "Asteroid mined out");
+                    }
+
+                    stored_minerals.stored += to_mine * richness;
 
                     if to_mine == 0.0 {
                         commands.entity(*target).remove::<CanBeMined>();
@@ -77,69 +114,423 @@ pub fn mine(
             } else {
                 queue.0.pop_front();
 
-                if new_targets.iter().next().is_none() {
-                    find_next_carrier(pos.0, &mut queue, carriers.iter());
+                if !acts_alone {
+                    return;
+                }
+
+                if new_targets.iter_mut().next().is_none() {
+                    return_to_carrier(&mut queue);
                 } else {
-                    find_next_asteroid(pos.0, &mut queue, &new_targets);
+                    find_next_asteroid(pos.0, &mut queue, &mut new_targets);
+                }
+            }
+        },
+    )
+}
+
+pub fn count_mining_claims(
+    miners: Query<&CommandQueue, With<CanMine>>,
+    mut asteroids: Query<&mut MiningClaims>,
+) {
+    asteroids.for_each_mut(|mut claims| claims.0 = 0);
+
+    miners.for_each(|queue| {
+        let target = queue.0.iter().find_map(|command| match *command {
+            Command::Interact {
+                target,
+                ty: InteractionType::Mine,
+                ..
+            } => Some(target),
+            _ => None,
+        });
+
+        if let Some(mut claims) = target.and_then(|target| asteroids.get_mut(target).ok()) {
+            claims.0 += 1;
+        }
+    });
+}
+
+// Miners that have just been unloaded from a carrier head off to mine instead of waiting for
+// orders. Anything other than the short move away from the carrier counts as an order. Miners with
+// high autonomy also look for work whenever they run out of orders.
+pub fn auto_task_idle_miners(
+    mut miners: Query<
+        (
+            &Position,
+            &mut CommandQueue,
+            Option<&Unloading>,
+            Option<&Autonomy>,
+        ),
+        (With<CanMine>, Without<HoldPosition>),
+    >,
+    mut asteroids: Query<(Entity, &Position, &Scale, &mut MiningClaims), With<CanBeMined>>,
+) {
+    miners.for_each_mut(|(pos, mut queue, unloading, autonomy)| {
+        let idle = match (unloading, Autonomy::get(autonomy)) {
+            (_, Autonomy::FullManual) => false,
+            (Some(_), _) => {
+                queue.0.len() <= 1
+                    && queue
+                        .0
+                        .iter()
+                        .all(|command| matches!(command, Command::MoveTo { .. }))
+            }
+            (None, Autonomy::High) => queue.0.is_empty(),
+            (None, Autonomy::Local) => false,
+        };
+
+        if idle {
+            find_next_asteroid(pos.0, &mut queue, &mut asteroids);
+        }
+    });
+}
+
+pub fn salvage(
+    mut miners: Query<
+        (
+            &Position,
+            &MaxSpeed,
+            &mut CommandQueue,
+            &mut StoredMinerals,
+            &mut Rotation,
+            Option<&DropOff>,
+        ),
+        With<CanMine>,
+    >,
+    mut wrecks: Query<(&Position, &mut Wreck)>,
+    carriers: Query<(Entity, &Position), With<Carrying>>,
+    clock: Res<SimClock>,
+    mut commands: Commands,
+    mut lasers: ResMut<GpuBuffer<LaserInstance>>,
+) {
+    miners.for_each_mut(
+        |(pos, max_speed, mut queue, mut stored_minerals, mut rotation, drop_off)| {
+            let return_to_carrier = |queue: &mut CommandQueue| match drop_off
+                .and_then(|drop_off| carriers.get(drop_off.0).ok())
+            {
+                Some(carrier) => find_next_carrier(pos.0, queue, std::iter::once(carrier)),
+                None => find_next_carrier(pos.0, queue, carriers.iter()),
+            };
+
+            let (target, range_sq) = match queue.0.front() {
+                Some(&Command::Interact {
+                    target,
+                    ty: InteractionType::Salvage,
+                    range_sq,
+                }) => (target, range_sq),
+                _ => return,
+            };
+
+            let (wreck_pos, mut wreck) = match wrecks.get_mut(target) {
+                Ok(wreck) => wreck,
+                Err(_) => {
+                    queue.0.pop_front();
+
+                    if stored_minerals.stored > 0.0 {
+                        return_to_carrier(&mut queue);
+                    }
+
+                    return;
+                }
+            };
+
+            // Drop off what's been collected, then come back for the rest.
+            if stored_minerals.stored >= stored_minerals.capacity {
+                return_to_carrier(&mut queue);
+                return;
+            }
+
+            let vector = wreck_pos.0 - pos.0;
+
+            if vector.mag_sq() > range_sq + max_speed.max_force() {
+                return;
+            }
+
+            rotation.0 = crate::rotation_from_facing(vector);
+
+            lasers.stage(&[LaserInstance {
+                start: pos.0 + rotation.0 * Models::MINER_LASER_OFFSET,
+                end: wreck_pos.0,
+                colour: Vec3::new(1.0, 0.5, 0.0),
+                width: 0.1,
+            }]);
+
+            let to_salvage = (Wreck::SALVAGE_RATE * clock.delta)
+                .min(wreck.minerals)
+                .min(stored_minerals.capacity - stored_minerals.stored);
+            wreck.minerals -= to_salvage;
+            stored_minerals.stored += to_salvage;
+
+            // Stripped hulls are left to drift until they break up.
+            if wreck.minerals <= 0.0 {
+                commands.entity(target).remove::<Wreck>();
+            }
+        },
+    )
+}
+
+pub fn harvest(
+    mut harvesters: Query<
+        (
+            &Position,
+            &MaxSpeed,
+            &mut CommandQueue,
+            &mut StoredGas,
+            &mut Rotation,
+            Option<&DropOff>,
+        ),
+        With<CanHarvest>,
+    >,
+    mut clouds: Query<(&Position, &mut CanBeHarvested)>,
+    new_clouds: Query<(Entity, &Position), With<CanBeHarvested>>,
+    carriers: Query<(Entity, &Position), With<Carrying>>,
+    clock: Res<SimClock>,
+    mut commands: Commands,
+    mut lasers: ResMut<GpuBuffer<LaserInstance>>,
+) {
+    harvesters.for_each_mut(
+        |(pos, max_speed, mut queue, mut stored_gas, mut rotation, drop_off)| {
+            let return_to_carrier = |queue: &mut CommandQueue| match drop_off
+                .and_then(|drop_off| carriers.get(drop_off.0).ok())
+            {
+                Some(carrier) => find_next_carrier(pos.0, queue, std::iter::once(carrier)),
+                None => find_next_carrier(pos.0, queue, carriers.iter()),
+            };
+
+            let (target, range_sq) = match queue.0.front() {
+                Some(&Command::Interact {
+                    target,
+                    ty: InteractionType::Harvest,
+                    range_sq,
+                }) => (target, range_sq),
+                _ => return,
+            };
+
+            let (cloud_pos, mut cloud) = match clouds.get_mut(target) {
+                Ok(cloud) => cloud,
+                Err(_) => {
+                    queue.0.pop_front();
+
+                    if new_clouds.iter().next().is_none() {
+                        if stored_gas.stored > 0.0 {
+                            return_to_carrier(&mut queue);
+                        }
+                    } else {
+                        find_next_gas_cloud(pos.0, &mut queue, &new_clouds);
+                    }
+
+                    return;
                 }
+            };
+
+            if stored_gas.stored >= stored_gas.capacity {
+                queue.0.pop_front();
+                return_to_carrier(&mut queue);
+                find_next_gas_cloud(pos.0, &mut queue, &new_clouds);
+                return;
+            }
+
+            let vector = cloud_pos.0 - pos.0;
+
+            if vector.mag_sq() > range_sq + max_speed.max_force() {
+                return;
+            }
+
+            rotation.0 = crate::rotation_from_facing(vector);
+
+            lasers.stage(&[LaserInstance {
+                start: pos.0 + rotation.0 * Models::MINER_LASER_OFFSET,
+                end: cloud_pos.0,
+                colour: Vec3::new(0.2, 0.8, 0.4),
+                width: 0.1,
+            }]);
+
+            let to_harvest = (CanBeHarvested::HARVEST_RATE * clock.delta)
+                .min(cloud.gas)
+                .min(stored_gas.capacity - stored_gas.stored);
+            cloud.gas -= to_harvest;
+            stored_gas.stored += to_harvest;
+
+            if cloud.gas <= 0.0 {
+                commands.entity(target).remove::<CanBeHarvested>();
             }
         },
     )
 }
 
+pub fn reserve_mining_slots(
+    miners: Query<(Entity, &Position, &CommandQueue, Option<&MiningSlot>), With<CanMine>>,
+    asteroids: Query<(&Position, &Scale), With<CanBeMined>>,
+    mut commands: Commands,
+) {
+    // Slots claimed this frame, as the commands to insert them haven't been applied yet.
+    let mut claimed_this_frame = Vec::new();
+
+    miners.for_each(|(entity, pos, queue, mining_slot)| {
+        let target = match queue.0.front() {
+            Some(Command::Interact {
+                target,
+                ty: InteractionType::Mine,
+                ..
+            }) => Some(*target),
+            _ => None,
+        };
+
+        if let Some(mining_slot) = mining_slot {
+            let still_mining =
+                Some(mining_slot.asteroid) == target && asteroids.get(mining_slot.asteroid).is_ok();
+
+            if still_mining {
+                return;
+            }
+
+            commands.entity(entity).remove::<MiningSlot>();
+        }
+
+        let (target, asteroid_pos, scale) = match target.and_then(|target| {
+            asteroids
+                .get(target)
+                .ok()
+                .map(|(asteroid_pos, scale)| (target, asteroid_pos.0, scale))
+        }) {
+            Some(target) => target,
+            None => return,
+        };
+
+        if (asteroid_pos - pos.0).mag_sq() > scale.queue_range_sq() {
+            return;
+        }
+
+        let mut taken = vec![false; scale.mining_slots()];
+
+        let claimed_by_others = miners
+            .iter()
+            .filter_map(|(_, _, _, mining_slot)| mining_slot)
+            .map(|mining_slot| (mining_slot.asteroid, mining_slot.index))
+            .chain(claimed_this_frame.iter().copied());
+
+        for (asteroid, index) in claimed_by_others {
+            if asteroid == target && index < taken.len() {
+                taken[index] = true;
+            }
+        }
+
+        let free_slot = (0..taken.len())
+            .filter(|&index| !taken[index])
+            .min_by(|&a, &b| {
+                let distance_sq =
+                    |index| (asteroid_pos + scale.mining_slot_offset(index) - pos.0).mag_sq();
+                compare_floats(distance_sq(a), distance_sq(b))
+            });
+
+        if let Some(index) = free_slot {
+            claimed_this_frame.push((target, index));
+            commands.entity(entity).insert(MiningSlot {
+                asteroid: target,
+                index,
+            });
+        }
+    });
+}
+
 pub fn build_ships<Side: Default + Send + Sync + 'static>(
     mut query: Query<
         (
+            Entity,
             &Position,
             &mut BuildQueue,
             Option<&Selected>,
             Option<&mut Carrying>,
+            Option<&RallyPoint>,
+            Option<&Ours>,
         ),
         With<Side>,
     >,
-    total_time: Res<TotalTime>,
+    clock: Res<SimClock>,
+    ship_catalog: Res<ShipCatalog>,
+    mut control_groups: ResMut<ControlGroups>,
+    mut game_events: ResMut<GameEvents>,
     mut commands: Commands,
     mut rng: ResMut<SmallRng>,
 ) {
-    query.for_each_mut(|(pos, mut build_queue, selected, carrying)| {
-        if let Some(built_ship) = build_queue.advance(total_time.0) {
-            let entity = spawn_ship::<Side>(built_ship, pos.0, &mut commands);
-
-            if build_queue.stay_carried && built_ship != ShipType::Carrier {
-                if let Some(mut carrying) = carrying {
-                    if carrying.checked_push(entity, built_ship == ShipType::Fighter) {
-                        commands.entity(entity).remove::<Position>();
-                        return;
+    query.for_each_mut(
+        |(carrier, pos, mut build_queue, selected, carrying, rally_point, ours)| {
+            if let Some(built_ship) = build_queue.advance(&clock, &ship_catalog) {
+                let entity = spawn_ship::<Side>(built_ship, pos.0, &mut commands);
+                let is_drone = built_ship == ShipType::Drone;
+
+                if ours.is_some() && !is_drone {
+                    game_events.push(
+                        clock.time,
+                        Severity::Info,
+                        format!("{:?} built", built_ship),
+                    );
+                }
+
+                if is_drone {
+                    commands.entity(entity).insert_bundle((
+                        Drone {
+                            mothership: carrier,
+                        },
+                        Escorting {
+                            carrier,
+                            offset: Drone::orbit_offset(&mut rng),
+                        },
+                    ));
+                } else {
+                    control_groups.backfill(carrier, entity);
+                }
+
+                if build_queue.stay_carried && !built_ship.model_id().is_capital() && !is_drone {
+                    if let Some(mut carrying) = carrying {
+                        if carrying.checked_push(entity, built_ship == ShipType::Fighter) {
+                            commands.entity(entity).remove::<Position>();
+                            return;
+                        }
                     }
                 }
-            }
 
-            let mut velocity = Velocity(Vec3::zero());
-            let mut command_queue = CommandQueue::default();
+                let mut velocity = Velocity(Vec3::zero());
+                let mut command_queue = CommandQueue::default();
 
-            crate::unload_single(
-                pos.0,
-                entity,
-                &mut rng,
-                total_time.0,
-                Some((&mut velocity, &mut command_queue)),
-                &mut commands,
-                selected.is_some(),
-            );
+                crate::unload_single(
+                    pos.0,
+                    entity,
+                    &mut rng,
+                    &clock,
+                    Some((&mut velocity, &mut command_queue)),
+                    &mut commands,
+                    selected.is_some() && !is_drone,
+                );
 
-            commands
-                .entity(entity)
-                .insert_bundle((velocity, command_queue));
-        }
-    })
+                // Head straight for the rally point instead of milling around the carrier.
+                if let Some(rally_point) = rally_point.filter(|_| !is_drone) {
+                    command_queue.0.clear();
+                    command_queue.0.push_back(Command::MoveTo {
+                        point: rally_point.0,
+                        ty: MoveType::Attack,
+                        speed_cap: None,
+                    });
+                }
+
+                commands
+                    .entity(entity)
+                    .insert_bundle((velocity, command_queue));
+            }
+        },
+    )
 }
 
 pub fn redirect_ships_from_full_carriers(
-    mut query: Query<&mut CommandQueue>,
+    mut query: Query<(
+        &mut CommandQueue,
+        Option<&StoredMinerals>,
+        Option<&StoredGas>,
+    )>,
     full_carriers: Query<&Position, With<CarrierFull>>,
     carriers_with_room: Query<(Entity, &Position), (With<Carrying>, Without<CarrierFull>)>,
 ) {
-    query.for_each_mut(|mut queue| {
+    query.for_each_mut(|(mut queue, stored_minerals, stored_gas)| {
         let is_targetting_full_carrier_and_its_position = queue
             .0
             .front()
@@ -153,17 +544,24 @@ pub fn redirect_ships_from_full_carriers(
             })
             .and_then(|&target| full_carriers.get(target).ok());
 
+        // Miners and harvesters that head back out afterwards are only docking to drop off what
+        // they're carrying, so they don't need any room.
+        let is_dropping_off =
+            (stored_minerals.is_some() || stored_gas.is_some()) && queue.0.len() > 1;
+
         // Note: we redirect to the closest carrier _to the carrier being targetted_,
         // not the ship we're redirecting. This is so the ships go to carriers in the same
         // region of space as opposed to being scattered all over the place.
-        if let Some(target_pos) = is_targetting_full_carrier_and_its_position {
+        if let Some(target_pos) =
+            is_targetting_full_carrier_and_its_position.filter(|_| !is_dropping_off)
+        {
             queue.0.pop_front();
             find_next_carrier(target_pos.0, &mut queue, carriers_with_room.iter())
         }
     })
 }
 
-fn spawn_ship<Side: Default + Send + Sync + 'static>(
+pub fn spawn_ship<Side: Default + Send + Sync + 'static>(
     ship: ShipType,
     pos: Vec3,
     commands: &mut Commands,
@@ -184,7 +582,161 @@ fn spawn_ship<Side: Default + Send + Sync + 'static>(
         ShipType::Carrier => {
             spawner.insert_bundle(carrier_components(BuildQueue::default(), Vec::new()));
         }
+        ShipType::Minelayer => {
+            spawner.insert_bundle(minelayer_components());
+        }
+        ShipType::RepairShip => {
+            spawner.insert_bundle(repair_ship_components());
+        }
+        ShipType::GasHarvester => {
+            spawner.insert_bundle(gas_harvester_components());
+        }
+        ShipType::Freighter => {
+            spawner.insert_bundle(freighter_components());
+        }
+        ShipType::ConstructionShip => {
+            spawner.insert_bundle(construction_ship_components());
+        }
+        ShipType::Frigate => {
+            spawner.insert_bundle(frigate_components());
+        }
+        ShipType::Destroyer => {
+            spawner.insert_bundle(destroyer_components());
+        }
+        ShipType::Battleship => {
+            spawner.insert_bundle(battleship_components());
+        }
+        ShipType::Drone => {
+            // Drones fly themselves, so there's nothing to select them for.
+            spawner
+                .insert_bundle(drone_components())
+                .remove::<Selectable>();
+        }
     }
 
     spawner.id()
 }
+
+pub fn collect_sensor_relays(
+    relays: Query<(Entity, &Position, &SensorRelay)>,
+    mut ships: Query<(&Position, &mut AgroRange)>,
+    mut commands: Commands,
+) {
+    relays.for_each(|(entity, position, relay)| {
+        let collector = ships.iter_mut().find(|(ship_position, _)| {
+            (ship_position.0 - position.0).mag_sq() < relay.pickup_radius.powi(2)
+        });
+
+        if let Some((_, mut agro_range)) = collector {
+            agro_range.0 += relay.range_bonus;
+            commands.entity(entity).despawn();
+        }
+    });
+}
+
+pub fn pick_up_items(
+    mut ships: Query<(
+        Entity,
+        &Position,
+        &mut CommandQueue,
+        &mut MaxSpeed,
+        Option<&HoldingItem>,
+    )>,
+    items: Query<(&Position, &Carryable, Option<&TlasIndex>)>,
+    mut tlas: ResMut<TopLevelAccelerationStructure>,
+    mut commands: Commands,
+) {
+    // Items aren't removed from `items` until the commands are applied.
+    let mut picked_up = Vec::new();
+
+    ships.for_each_mut(
+        |(entity, position, mut queue, mut max_speed, holding_item)| {
+            let (target, range_sq) = match queue.0.front() {
+                Some(&Command::Interact {
+                    target,
+                    ty: InteractionType::PickUp,
+                    range_sq,
+                }) => (target, range_sq),
+                _ => return,
+            };
+
+            let (item_position, carryable, tlas_index) = match items.get(target) {
+                Ok(item) if !picked_up.contains(&target) => item,
+                // Someone else got there first.
+                _ => {
+                    queue.0.pop_front();
+                    return;
+                }
+            };
+
+            if (item_position.0 - position.0).mag_sq() > range_sq + max_speed.max_force() {
+                return;
+            }
+
+            queue.0.pop_front();
+
+            // Ships can only hold one item at a time.
+            if holding_item.is_some() {
+                return;
+            }
+
+            if let Some(tlas_index) = tlas_index {
+                tlas.remove(tlas_index.index);
+            }
+
+            commands
+                .entity(target)
+                .remove::<TlasIndex>()
+                .remove::<Position>()
+                .remove::<Selected>();
+
+            commands.entity(entity).insert(HoldingItem(target));
+            max_speed.0 *= carryable.speed_multiplier;
+            picked_up.push(target);
+        },
+    );
+}
+
+pub fn deliver_relics(
+    mut holders: Query<(
+        Entity,
+        &Position,
+        &HoldingItem,
+        &mut MaxSpeed,
+        Option<&Friendly>,
+    )>,
+    relics: Query<&Carryable, With<Relic>>,
+    carriers: Query<(&Position, Option<&Friendly>), With<Carrying>>,
+    mut objective_score: ResMut<ObjectiveScore>,
+    mut commands: Commands,
+) {
+    const DELIVERY_RANGE: f32 = 15.0;
+
+    holders.for_each_mut(
+        |(entity, position, holding_item, mut max_speed, friendly)| {
+            let carryable = match relics.get(holding_item.0) {
+                Ok(carryable) => carryable,
+                Err(_) => return,
+            };
+
+            let at_home_carrier = carriers.iter().any(|(carrier_position, carrier_friendly)| {
+                carrier_friendly.is_some() == friendly.is_some()
+                    && (carrier_position.0 - position.0).mag_sq() < DELIVERY_RANGE.powi(2)
+            });
+
+            if !at_home_carrier {
+                return;
+            }
+
+            if friendly.is_some() {
+                objective_score.friendly += 1;
+            } else {
+                objective_score.enemy += 1;
+            }
+
+            max_speed.0 /= carryable.speed_multiplier;
+            commands.entity(holding_item.0).despawn();
+            commands.entity(entity).remove::<HoldingItem>();
+        },
+    );
+}
@@ -0,0 +1,64 @@
+use crate::resource_management::spawn_ship;
+use bevy_ecs::prelude::*;
+use components_and_resources::components::*;
+use components_and_resources::resources::*;
+use components_and_resources::utils::random_point_in_sphere;
+
+pub fn run_mission_scripts(
+    friendly_ships: Query<&Position, (With<Friendly>, With<MaxSpeed>)>,
+    clock: Res<SimClock>,
+    real_time: Res<RealTime>,
+    mut script_host: ResMut<ScriptHost>,
+    mut global_minerals: ResMut<PerSide<GlobalMinerals>>,
+    mut notifications: ResMut<Notifications>,
+    mut rng: ResMut<SmallRng>,
+    mut lighting: ResMut<Lighting>,
+    mut cutscene_player: ResMut<CutscenePlayer>,
+    mut commands: Commands,
+) {
+    let destroyed = script_host.take_destroyed();
+
+    let script_commands = script_host.fire(|condition| match *condition {
+        TriggerCondition::Timer(time) => clock.time >= time,
+        TriggerCondition::UnitDestroyed { model, enemy } => destroyed.contains(&(model, enemy)),
+        TriggerCondition::AreaEntered { center, radius } => friendly_ships
+            .iter()
+            .any(|position| (position.0 - center).mag_sq() < radius * radius),
+    });
+
+    for command in script_commands {
+        match command {
+            ScriptCommand::SpawnWave {
+                ship,
+                count,
+                position,
+            } => {
+                for _ in 0..count {
+                    let offset = random_point_in_sphere(&mut *rng) * 25.0;
+                    spawn_ship::<Enemy>(ship, position + offset, &mut commands);
+                }
+            }
+            // Missions are played on the friendly side.
+            ScriptCommand::GrantMinerals(amount) => global_minerals.friendly.0 += amount,
+            ScriptCommand::Message(message) => notifications.push(message, real_time.0),
+            ScriptCommand::SetSun {
+                direction,
+                colour,
+                duration,
+            } => {
+                let mut target = lighting.target();
+                target.sun_dir = direction;
+                target.sun_colour = colour;
+                lighting.transition_to(target, duration, clock.time);
+            }
+            ScriptCommand::SetAmbient { colour, duration } => {
+                let mut target = lighting.target();
+                target.ambient_light = colour;
+                lighting.transition_to(target, duration, clock.time);
+            }
+            ScriptCommand::Flyover { center, subtitle } => {
+                cutscene_player.play(Cutscene::flyover(center, &subtitle));
+            }
+        }
+    }
+}
@@ -2,6 +2,7 @@ use crate::find_functions::*;
 use bevy_ecs::prelude::*;
 use components_and_resources::components::*;
 use components_and_resources::resources::*;
+use components_and_resources::utils::rotation_from_facing;
 use ultraviolet::Vec3;
 
 mod primitives;
@@ -15,20 +16,40 @@ pub fn run_persuit(
         &MaxSpeed,
         Option<&mut CommandQueue>,
         Option<&mut StoredMinerals>,
+        Option<&mut StoredGas>,
+        Option<&mut Cargo>,
         &mut StagingPersuitForce,
         &TlasIndex,
         Option<&CanAttack>,
+        Option<&FormationSlot>,
+        Option<&MiningSlot>,
+        Option<&HoldPosition>,
+        Option<&Escorting>,
     )>,
     to_transfer: Query<&mut OnBoard>,
-    boids: Query<(&Position, Option<&Velocity>, Option<&MaxSpeed>)>,
+    boids: Query<(
+        &Position,
+        Option<&Velocity>,
+        Option<&MaxSpeed>,
+        Option<&Scale>,
+    )>,
     mut commands: Commands,
     mut carrying: Query<&mut Carrying>,
-    total_time: Res<TotalTime>,
-    mut global_minerals: ResMut<GlobalMinerals>,
+    clock: Res<SimClock>,
+    mut global_minerals: ResMut<PerSide<GlobalMinerals>>,
+    mut global_gas: ResMut<PerSide<GlobalGas>>,
     mut tlas: ResMut<TopLevelAccelerationStructure>,
     carriers: Query<(Entity, &Position), (With<Carrying>, Without<CarrierFull>)>,
+    // Kept separate as the main query is at the limit on the number of items.
+    friendlies: Query<&Friendly>,
 ) {
-    query.for_each_mut(|(entity, pos, vel, max_speed, queue, stored_minerals, mut staging_persuit_force, tlas_index, can_attack)| {
+    query.for_each_mut(|(entity, pos, vel, max_speed, queue, stored_minerals, stored_gas, cargo, mut staging_persuit_force, tlas_index, can_attack, formation_slot, mining_slot, hold_position, escorting)| {
+        // Ships holding position still shoot at anything in range but don't chase it.
+        if hold_position.is_some() {
+            staging_persuit_force.0 = Vec3::zero();
+            return;
+        }
+
         let boid = to_boid(pos, vel, max_speed);
         let max_force = max_speed.max_force();
 
@@ -45,16 +66,24 @@ pub fn run_persuit(
         let command = match queue.0.front().copied() {
             Some(command) => command,
             None => {
-                staging_persuit_force.0 = Vec3::zero();
+                // Escorts fall back into their slot around the carrier once there's nothing to intercept.
+                let slot = escorting.and_then(|escorting| {
+                    boids.get(escorting.carrier).ok().map(|(carrier_pos, ..)| carrier_pos.0 + escorting.offset)
+                });
+
+                staging_persuit_force.0 = match slot {
+                    Some(slot) if (boid.pos - slot).mag_sq() > max_force => boid.seek(slot),
+                    _ => Vec3::zero(),
+                };
                 return;
             }
         };
 
         match command {
             Command::Interact { target, ty, range_sq } => {
-                let target_boid = match boids.get(target) {
-                    Ok((p, v, ms)) => {
-                        to_boid(p, &v.copied().unwrap_or_default(), &ms.copied().unwrap_or_default())
+                let (target_boid, target_scale) = match boids.get(target) {
+                    Ok((p, v, ms, scale)) => {
+                        (to_boid(p, &v.copied().unwrap_or_default(), &ms.copied().unwrap_or_default()), scale)
                     },
                     _ => {
                         queue.0.pop_front();
@@ -68,6 +97,30 @@ pub fn run_persuit(
                 // We should fix this someother how though.
                 let lead_factor = 0.0;
 
+                if let (InteractionType::Mine, Some(scale)) = (ty, target_scale) {
+                    match mining_slot.filter(|slot| slot.asteroid == target) {
+                        Some(slot) => {
+                            let slot_pos = target_boid.pos + scale.mining_slot_offset(slot.index);
+
+                            staging_persuit_force.0 = if (boid.pos - slot_pos).mag_sq() < max_force {
+                                Vec3::zero()
+                            } else {
+                                boid.seek(slot_pos)
+                            };
+                        }
+                        // Hang back until `reserve_mining_slots` finds a free slot.
+                        None => {
+                            staging_persuit_force.0 = if (boid.pos - target_boid.pos).mag_sq() < scale.queue_range_sq() {
+                                Vec3::zero()
+                            } else {
+                                boid.persue(target_boid, lead_factor)
+                            };
+                        }
+                    }
+
+                    return;
+                }
+
                 let within_range = (boid.pos - target_boid.pos).mag_sq() < range_sq + max_force;
 
                 if !within_range {
@@ -116,7 +169,7 @@ pub fn run_persuit(
                                 .remove::<Position>()
                                 .remove::<Selected>();
                         } else {
-                            entity_commands.insert(Unloading::new(total_time.0));
+                            entity_commands.insert(Unloading::new(&clock));
                         }
 
                         if carrying.is_full() {
@@ -135,26 +188,136 @@ pub fn run_persuit(
                             carrier_people.0.append(&mut ship_people.0);
                         }
 
+                        let side = Side::of(friendlies.get(entity).ok());
+                        let global_minerals = &mut global_minerals[side];
+
                         if let Some(mut stored_minerals) = stored_minerals {
                             global_minerals.0 += stored_minerals.stored;
                             stored_minerals.stored = 0.0;
                         }
+
+                        if let Some(mut stored_gas) = stored_gas {
+                            global_gas[side].0 += stored_gas.stored;
+                            stored_gas.stored = 0.0;
+                        }
+
+                        // Freighters load up on their way out to a trading station.
+                        if let Some(mut cargo) = cargo.filter(|_| !queue.0.is_empty()) {
+                            let loaded = (cargo.capacity - cargo.minerals).min(global_minerals.0).max(0.0);
+                            global_minerals.0 -= loaded;
+                            cargo.minerals += loaded;
+                        }
                     },
                     InteractionType::Mine => {}
                     InteractionType::Attack => {}
+                    // Handled by `pick_up_items`.
+                    InteractionType::PickUp => {}
+                    // Handled by `repair_with_beams`.
+                    InteractionType::Repair => {}
+                    // Handled by `salvage`.
+                    InteractionType::Salvage => {}
+                    // Handled by `harvest`.
+                    InteractionType::Harvest => {}
+                    // Handled by `trade`.
+                    InteractionType::Trade => {}
+                    // Handled by `construct`.
+                    InteractionType::Construct => {}
                 }
             }
-            Command::MoveTo { point, .. } => {
-                staging_persuit_force.0 = boid.seek(point);
+            Command::Wait(wait_for) => {
+                staging_persuit_force.0 = Vec3::zero();
+
+                if let WaitFor::Time(timer) = wait_for {
+                    if timer.finished(&clock) {
+                        queue.0.pop_front();
+                    }
+                }
+            }
+            // Turning is handled by `turn_to_face`.
+            Command::Face { .. } => {
+                staging_persuit_force.0 = Vec3::zero();
+            }
+            Command::MoveTo { point, speed_cap, .. } => {
+                let boid = match speed_cap {
+                    Some(speed_cap) => primitives::Boid {
+                        max_vel: boid.max_vel.min(speed_cap),
+                        ..boid
+                    },
+                    None => boid,
+                };
+
+                // Keep station on the leader until it arrives, at which point the slot is the
+                // same as the point we're heading to anyway.
+                let seek_point = formation_slot
+                    .and_then(|slot| {
+                        boids
+                            .get(slot.leader)
+                            .ok()
+                            .map(|(leader_pos, ..)| leader_pos.0 + slot.offset)
+                    })
+                    .unwrap_or(point);
+
+                staging_persuit_force.0 = boid.seek(seek_point);
 
                 if (boid.pos - point).mag_sq() < max_force {
                     queue.0.pop_front();
+
+                    if formation_slot.is_some() {
+                        commands.entity(entity).remove::<FormationSlot>();
+                    }
                 }
             }
         }
     })
 }
 
+// In radians per second.
+const TURN_RATE: f32 = 0.5;
+
+pub fn turn_to_face(
+    mut query: Query<(&mut CommandQueue, &mut Rotation, &mut Velocity)>,
+    clock: Res<SimClock>,
+) {
+    query.for_each_mut(|(mut queue, mut rotation, mut velocity)| {
+        let direction = match queue.0.front() {
+            Some(&Command::Face { direction }) => direction,
+            _ => return,
+        };
+
+        // Wait for the ship to come to a stop, otherwise `set_rotation_from_velocity` would just
+        // turn it back again.
+        if velocity.0.mag_sq() > 0.01 {
+            return;
+        }
+
+        velocity.0 = Vec3::zero();
+
+        let forward = rotation.0 * Vec3::unit_z();
+        let angle = forward.dot(direction).max(-1.0).min(1.0).acos();
+        let max_turn = TURN_RATE * clock.delta;
+
+        if angle <= max_turn {
+            rotation.0 = rotation_from_facing(direction);
+            queue.0.pop_front();
+            return;
+        }
+
+        // When facing the opposite way any axis will do, so turn around the vertical one.
+        let axis = forward.cross(direction);
+        let axis = if axis.mag_sq() > 0.0001 {
+            axis.normalized()
+        } else {
+            Vec3::unit_y()
+        };
+
+        let turned = forward * max_turn.cos()
+            + axis.cross(forward) * max_turn.sin()
+            + axis * axis.dot(forward) * (1.0 - max_turn.cos());
+
+        rotation.0 = rotation_from_facing(turned);
+    })
+}
+
 #[profiling::function]
 pub fn run_evasion(
     mut query: Query<(
@@ -165,12 +328,25 @@ pub fn run_evasion(
         Option<&Evading>,
         &CommandQueue,
         &mut StagingEvasionForce,
+        Option<&Jinking>,
     )>,
     boids: Query<(&Position, &Velocity, &MaxSpeed)>,
+    clock: Res<SimClock>,
     mut commands: Commands,
 ) {
     query.for_each_mut(
-        |(entity, pos, vel, max_speed, evading, queue, mut staging_evasion_force)| {
+        |(entity, pos, vel, max_speed, evading, queue, mut staging_evasion_force, jinking)| {
+            let jink_force = match jinking {
+                Some(jinking) if !jinking.until.finished(&clock) => {
+                    jinking.direction * jinking.strength * max_speed.max_force()
+                }
+                Some(_) => {
+                    commands.entity(entity).remove::<Jinking>();
+                    Vec3::zero()
+                }
+                None => Vec3::zero(),
+            };
+
             let should_evade = matches!(
                 queue.0.front(),
                 None | Some(Command::Interact {
@@ -180,14 +356,14 @@ pub fn run_evasion(
             );
 
             if !should_evade {
-                staging_evasion_force.0 = Vec3::zero();
+                staging_evasion_force.0 = jink_force;
                 return;
             }
 
             let entity_to_evade = match evading {
                 Some(&Evading(entity_to_evade)) => entity_to_evade,
                 _ => {
-                    staging_evasion_force.0 = Vec3::zero();
+                    staging_evasion_force.0 = jink_force;
                     return;
                 }
             };
@@ -195,7 +371,7 @@ pub fn run_evasion(
             let evading_boid = match boids.get(entity_to_evade) {
                 Ok((p, v, ms)) => to_boid(p, v, ms),
                 _ => {
-                    staging_evasion_force.0 = Vec3::zero();
+                    staging_evasion_force.0 = jink_force;
                     commands.entity(entity).remove::<Evading>();
                     return;
                 }
@@ -203,11 +379,122 @@ pub fn run_evasion(
 
             let boid = to_boid(pos, vel, max_speed);
 
-            staging_evasion_force.0 = boid.flee(evading_boid.pos) * 0.5;
+            staging_evasion_force.0 = boid.flee(evading_boid.pos) * 0.5 + jink_force;
         },
     )
 }
 
+// Ships with a retreat policy break off and head for the closest carrier once they're badly
+// damaged. The policy has to be turned off to keep a damaged ship fighting.
+pub fn retreat_damaged_ships<Side: Send + Sync + 'static>(
+    mut query: Query<
+        (
+            Entity,
+            &Position,
+            &Health,
+            &mut CommandQueue,
+            &mut RetreatPolicy,
+        ),
+        (With<Side>, With<CanBeCarried>),
+    >,
+    carriers: Query<(Entity, &Position), (With<Side>, With<Carrying>, Without<CarrierFull>)>,
+    mut commands: Commands,
+) {
+    query.for_each_mut(|(entity, pos, health, mut queue, mut policy)| {
+        let docking = matches!(
+            queue.0.front(),
+            Some(Command::Interact {
+                ty: InteractionType::BeCarriedBy,
+                ..
+            })
+        );
+
+        let retreating = policy.retreated_from.is_some();
+
+        // Keep heading for a carrier even if something else gets put in front of the order.
+        if !(policy.should_retreat(health) || retreating && !docking) {
+            return;
+        }
+
+        let mut docking_order = CommandQueue::default();
+        find_next_carrier(pos.0, &mut docking_order, carriers.iter());
+
+        // With nowhere to go, the ship carries on as it was until a carrier turns up, including
+        // when the carrier it was heading for is destroyed.
+        if docking_order.0.is_empty() {
+            if retreating {
+                policy.retreated_from = None;
+            }
+
+            return;
+        }
+
+        *queue = docking_order;
+
+        if !retreating {
+            policy.retreated_from = Some(pos.0);
+            commands
+                .entity(entity)
+                .remove::<FormationSlot>()
+                .remove::<Escorting>()
+                .remove::<HoldPosition>();
+        }
+    });
+}
+
+// Once a retreating ship has been fully repaired, its carrier launches it back to where it was.
+pub fn rejoin_after_repair(
+    mut carriers: Query<(Entity, &Position, &mut Carrying)>,
+    mut ships: Query<(&Health, &mut RetreatPolicy)>,
+    mut movement: Query<(&mut Velocity, &mut CommandQueue)>,
+    clock: Res<SimClock>,
+    mut rng: ResMut<SmallRng>,
+    mut commands: Commands,
+) {
+    carriers.for_each_mut(|(carrier, pos, mut carrying)| {
+        let repaired: Vec<Entity> = carrying
+            .iter()
+            .filter(|&entity| {
+                ships
+                    .get_mut(entity)
+                    .map_or(false, |(health, policy)| policy.ready_to_rejoin(health))
+            })
+            .collect();
+
+        for entity in repaired {
+            carrying.remove(entity);
+
+            crate::unload_single(
+                pos.0,
+                entity,
+                &mut rng,
+                &clock,
+                movement.get_mut(entity).ok(),
+                &mut commands,
+                false,
+            );
+
+            let retreated_from = match ships.get_mut(entity) {
+                Ok((_, mut policy)) => policy.retreated_from.take(),
+                Err(_) => None,
+            };
+
+            if let (Some(point), Ok((_, mut queue))) = (retreated_from, movement.get_mut(entity)) {
+                queue.0.push_back(Command::MoveTo {
+                    point,
+                    ty: MoveType::Attack,
+                    speed_cap: None,
+                });
+            }
+
+            commands.entity(carrier).remove::<CarrierFull>();
+        }
+    });
+}
+
+// Getting round obstacles matters more than keeping up with a target.
+const OBSTACLE_WEIGHT: f32 = 2.0;
+
 #[profiling::function]
 pub fn run_avoidance(
     mut query: Query<(
@@ -226,6 +513,7 @@ pub fn run_avoidance(
         &Velocity,
         &MaxSpeed,
     )>,
+    obstacles: Query<&WorldSpaceBoundingBox, With<Obstacle>>,
     task_pool: Res<bevy_tasks::TaskPool>,
     bvh: Res<TopLevelAccelerationStructure>,
 ) {
@@ -277,7 +565,39 @@ pub fn run_avoidance(
                 })
                 .map(|(_, (.., p, v, ms))| to_boid(p, v, ms));
 
-            steering_avoidance_force.0 = boid.avoidance(iter) * 0.1;
+            let boid_avoidance = boid.avoidance(iter) * 0.1;
+
+            // Look a couple of seconds ahead for anything too big to just nudge around.
+            let look_ahead = pos.0 + vel.0 * 2.0;
+            let path = BoundingBox::new(
+                pos.0.min_by_component(look_ahead),
+                pos.0.max_by_component(look_ahead),
+            )
+            .expand(2.0);
+
+            // Miners still need to get up close to whatever they're mining.
+            let interacting_with = match queue.and_then(|queue| queue.0.front()) {
+                Some(&Command::Interact { target, .. }) => Some(target),
+                _ => None,
+            };
+
+            let mut obstacle_find_stack = Vec::with_capacity(10);
+
+            let obstacle_avoidance = bvh
+                .find(
+                    |bounding_box| path.intersects(bounding_box),
+                    &mut obstacle_find_stack,
+                )
+                .filter(|&&entity| Some(entity) != interacting_with)
+                .filter_map(|&entity| obstacles.get(entity).ok())
+                .filter(|obstacle| obstacle.0.intersects(path))
+                .map(|obstacle| {
+                    let radius = (obstacle.0.size() / 2.0).component_max();
+                    boid.steer_around(obstacle.0.center(), radius)
+                })
+                .fold(Vec3::zero(), |sum, away| sum + away);
+
+            steering_avoidance_force.0 = boid_avoidance + obstacle_avoidance * OBSTACLE_WEIGHT;
         },
     )
 }
@@ -309,23 +629,26 @@ pub fn apply_staging_velocity(
         &StagingPersuitForce,
         &StagingEvasionForce,
         &StagingAvoidanceForce,
+        Option<&Throttle>,
     )>,
-    paused: Res<Paused>,
+    clock: Res<SimClock>,
 ) {
-    if paused.0 {
-        return;
-    }
-    query.for_each_mut(|(mut velocity, max_speed, persuit, evasion, avoidance)| {
-        let max_force = max_speed.max_force();
+    query.for_each_mut(
+        |(mut velocity, max_speed, persuit, evasion, avoidance, throttle)| {
+            // Forces are per tick, so they're scaled to turn just as tightly at any game speed.
+            let max_force = max_speed.max_force() * clock.delta / SimClock::TICK;
 
-        let mut steering = persuit.0 + evasion.0 + avoidance.0;
+            let mut steering = persuit.0 + evasion.0 + avoidance.0;
 
-        if steering == Vec3::zero() {
-            steering = -velocity.0;
-        }
+            if steering == Vec3::zero() {
+                steering = -velocity.0;
+            }
 
-        let steering = truncate(steering, max_force);
+            let steering = truncate(steering, max_force);
 
-        velocity.0 = truncate(velocity.0 + steering, max_speed.0);
-    });
+            let top_speed = max_speed.0 * Throttle::get(throttle).fraction();
+
+            velocity.0 = truncate(velocity.0 + steering, top_speed);
+        },
+    );
 }
@@ -44,6 +44,40 @@ impl Boid {
         desired_vel - self.vel
     }
 
+    // Steers sideways around a sphere in the boid's path, instead of braking in front of it.
+    pub fn steer_around(self, center: Vec3, radius: f32) -> Vec3 {
+        let to_center = center - self.pos;
+
+        if to_center.mag_sq() < radius * radius {
+            return self.flee(center);
+        }
+
+        let direction = normalize_to(self.vel, 1.0);
+        let ahead = to_center.dot(direction);
+
+        // Already flying away from it.
+        if ahead <= 0.0 {
+            return Vec3::zero();
+        }
+
+        let mut away = self.pos + direction * ahead - center;
+
+        // Heading straight for the middle, so any way round will do.
+        if away.mag_sq() < 0.0001 {
+            away = direction.cross(Vec3::unit_y());
+
+            if away.mag_sq() < 0.0001 {
+                away = Vec3::unit_x();
+            }
+        }
+
+        if away.mag_sq() > radius * radius {
+            return Vec3::zero();
+        }
+
+        self.seek(center + normalize_to(away, radius * 1.5))
+    }
+
     pub fn avoidance(self, other: impl Iterator<Item = Boid>) -> Vec3 {
         let mut sum = Vec3::zero();
 
@@ -73,3 +107,38 @@ fn normalize_to(vec: Vec3, new_mag: f32) -> Vec3 {
         vec / mag * new_mag
     }
 }
+
+#[test]
+fn test_steer_around() {
+    let boid = Boid {
+        pos: Vec3::zero(),
+        vel: Vec3::new(0.0, 0.0, 10.0),
+        max_vel: 10.0,
+        radius_sq: 1.0,
+    };
+
+    // Head on, it picks a side rather than braking.
+    let force = boid.steer_around(Vec3::new(0.0, 0.0, 50.0), 10.0);
+    assert!(force.x.abs() > 1.0, "{:?}", force);
+    assert!(force.z <= 0.0, "{:?}", force);
+
+    // A glancing approach off to the right of centre goes round to the left.
+    let force = boid.steer_around(Vec3::new(3.0, 0.0, 50.0), 10.0);
+    assert!(force.x < 0.0, "{:?}", force);
+
+    // Nothing to do when it'll clear the obstacle anyway.
+    let force = boid.steer_around(Vec3::new(20.0, 0.0, 50.0), 10.0);
+    assert_eq!(force, Vec3::zero());
+
+    // Or when the obstacle is behind it.
+    let force = boid.steer_around(Vec3::new(0.0, 0.0, -50.0), 10.0);
+    assert_eq!(force, Vec3::zero());
+
+    // Already inside, it gets out the quickest way, away from the centre.
+    let force = boid.steer_around(Vec3::new(1.0, 0.0, 0.0), 10.0);
+    assert_eq!(force, Vec3::new(-10.0, 0.0, -10.0));
+
+    // Right on top of the centre there's no way that's away, but it mustn't come out as NaN.
+    let force = boid.steer_around(Vec3::zero(), 10.0);
+    assert_eq!(force, Vec3::new(0.0, 0.0, -10.0));
+}
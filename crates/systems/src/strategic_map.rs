@@ -0,0 +1,416 @@
+use crate::rendering::to_wgpu;
+use crate::SelectedFriendly;
+use bevy_ecs::prelude::*;
+use bevy_ecs::schedule::ShouldRun;
+use components_and_resources::components::*;
+use components_and_resources::formations::Formation;
+use components_and_resources::gpu_structs::Vertex2D;
+use components_and_resources::resources::*;
+use components_and_resources::utils::compare_floats;
+use ultraviolet::{Vec2, Vec3};
+
+pub fn toggle_strategic_map(
+    keyboard_state: Res<KeyboardState>,
+    mut strategic_map: ResMut<StrategicMap>,
+) {
+    if keyboard_state.strategic_map.0 {
+        strategic_map.open = !strategic_map.open;
+    }
+}
+
+// Clicks on the 3D view are ignored while the map is covering it.
+pub fn strategic_map_closed(strategic_map: Res<StrategicMap>) -> ShouldRun {
+    if strategic_map.open {
+        ShouldRun::No
+    } else {
+        ShouldRun::Yes
+    }
+}
+
+fn aspect_ratio(dimensions: &Dimensions) -> f32 {
+    dimensions.width as f32 / dimensions.height as f32
+}
+
+// Enemies only show up on the map when they're within range of a friendly ship.
+fn in_sensor_range(point: Vec3, sensors: &[(Vec3, f32)]) -> bool {
+    sensors
+        .iter()
+        .any(|&(sensor, range)| (point - sensor).mag_sq() < range * range)
+}
+
+// Finds the icon closest to the cursor, if there's one close enough to click on.
+fn icon_under_cursor(
+    icons: impl Iterator<Item = (Entity, Vec3)>,
+    cursor: Vec2,
+    dimensions: &Dimensions,
+    dpi_factor: f32,
+) -> Option<Entity> {
+    let aspect_ratio = aspect_ratio(dimensions);
+    let max_distance = StrategicMap::CLICK_RADIUS * dpi_factor;
+
+    icons
+        .map(|(entity, point)| {
+            let offset =
+                (StrategicMap::to_wgpu(point, aspect_ratio) - cursor) / 2.0 * dimensions.to_vec();
+            (entity, offset.mag())
+        })
+        .filter(|&(_, distance)| distance < max_distance)
+        .min_by(|&(_, a), &(_, b)| compare_floats(a, b))
+        .map(|(entity, _)| entity)
+}
+
+pub fn handle_strategic_map_clicks(
+    strategic_map: Res<StrategicMap>,
+    mouse_state: Res<MouseState>,
+    keyboard_state: Res<KeyboardState>,
+    dimensions: Res<Dimensions>,
+    dpi_factor: Res<DpiFactor>,
+    friendly: Query<(Entity, &Position, Option<&AgroRange>), (With<Ours>, With<Selectable>)>,
+    enemies: Query<(Entity, &Position), (With<Theirs>, With<Selectable>, Without<Cloaked>)>,
+    selected: Query<(Entity, &Position), (SelectedFriendly, With<CommandQueue>)>,
+    all_selected: Query<Entity, With<Selected>>,
+    can_attack: Query<Entity, (SelectedFriendly, With<CommandQueue>, With<CanAttack>)>,
+    mut player_commands: ResMut<PlayerCommands>,
+    mut pending_timing: ResMut<PendingOrderTiming>,
+    mut commands: Commands,
+) {
+    if !strategic_map.open {
+        return;
+    }
+
+    let aspect_ratio = aspect_ratio(&dimensions);
+    let cursor = to_wgpu(mouse_state.position, &dimensions);
+
+    if !StrategicMap::contains(cursor, aspect_ratio) {
+        return;
+    }
+
+    if mouse_state.left_state.was_clicked() {
+        let clicked = icon_under_cursor(
+            friendly
+                .iter()
+                .map(|(entity, position, _)| (entity, position.0)),
+            cursor,
+            &dimensions,
+            dpi_factor.0,
+        );
+
+        if !keyboard_state.shift {
+            all_selected.for_each(|entity| {
+                commands.entity(entity).remove::<Selected>();
+            });
+        }
+
+        if let Some(entity) = clicked {
+            commands.entity(entity).insert(Selected);
+        }
+    }
+
+    if !mouse_state.right_state.was_clicked() {
+        return;
+    }
+
+    let sensors: Vec<_> = friendly
+        .iter()
+        .filter_map(|(_, position, agro_range)| Some((position.0, agro_range?.0)))
+        .collect();
+
+    let target = icon_under_cursor(
+        enemies
+            .iter()
+            .map(|(entity, position)| (entity, position.0))
+            .filter(|&(_, point)| in_sensor_range(point, &sensors)),
+        cursor,
+        &dimensions,
+        dpi_factor.0,
+    );
+
+    let orders: Vec<_> = match target {
+        Some(target) => can_attack
+            .iter()
+            .map(|entity| {
+                (
+                    entity,
+                    Command::Interact {
+                        target,
+                        ty: InteractionType::Attack,
+                        range_sq: 0.0,
+                    },
+                )
+            })
+            .collect(),
+        None => {
+            let count = selected.iter().count();
+
+            if count == 0 {
+                return;
+            }
+
+            // The map is flat, so ships are sent to the average height of the selection.
+            let height = selected
+                .iter()
+                .map(|(_, position)| position.0.y)
+                .sum::<f32>()
+                / count as f32;
+            let point = StrategicMap::to_world(cursor, aspect_ratio, height);
+            let mut formation = Formation::in_sphere(point, count);
+
+            selected
+                .iter()
+                .filter_map(|(entity, position)| {
+                    let point = formation.choose_position(position.0)?;
+
+                    Some((
+                        entity,
+                        Command::MoveTo {
+                            point,
+                            ty: MoveType::Normal,
+                            speed_cap: None,
+                        },
+                    ))
+                })
+                .collect()
+        }
+    };
+
+    if !orders.is_empty() {
+        player_commands.local.push(PlayerCommand::Orders {
+            orders,
+            queued: keyboard_state.shift,
+            timing: pending_timing.0.take(),
+        });
+    }
+}
+
+pub(crate) fn stage_quad(
+    triangles_2d: &mut GpuBuffer<Vertex2D>,
+    min: Vec2,
+    max: Vec2,
+    colour: Vec3,
+) {
+    let vertex = |x, y| Vertex2D {
+        pos: Vec2::new(x, y),
+        colour,
+    };
+
+    triangles_2d.stage(&[
+        vertex(min.x, min.y),
+        vertex(max.x, min.y),
+        vertex(max.x, max.y),
+        vertex(min.x, min.y),
+        vertex(max.x, max.y),
+        vertex(min.x, max.y),
+    ]);
+}
+
+// `radius` is separate for each axis as the screen isn't square.
+fn stage_disc(triangles_2d: &mut GpuBuffer<Vertex2D>, center: Vec2, radius: Vec2, colour: Vec3) {
+    const SEGMENTS: usize = 24;
+
+    let point = |i: usize| {
+        let angle = i as f32 / SEGMENTS as f32 * std::f32::consts::TAU;
+        Vertex2D {
+            pos: center + Vec2::new(angle.cos(), angle.sin()) * radius,
+            colour,
+        }
+    };
+
+    for i in 0..SEGMENTS {
+        triangles_2d.stage(&[
+            Vertex2D {
+                pos: center,
+                colour,
+            },
+            point(i),
+            point(i + 1),
+        ]);
+    }
+}
+
+fn stage_outline(lines_2d: &mut GpuBuffer<Vertex2D>, min: Vec2, max: Vec2, colour: Vec3) {
+    let corners = [min, Vec2::new(max.x, min.y), max, Vec2::new(min.x, max.y)];
+
+    for i in 0..corners.len() {
+        lines_2d.stage(&[
+            Vertex2D {
+                pos: corners[i],
+                colour,
+            },
+            Vertex2D {
+                pos: corners[(i + 1) % corners.len()],
+                colour,
+            },
+        ]);
+    }
+}
+
+pub fn render_strategic_map(
+    strategic_map: Res<StrategicMap>,
+    ships: Query<
+        (
+            &Position,
+            &ModelId,
+            Option<&Ours>,
+            Option<&Theirs>,
+            Option<&Selected>,
+            Option<&AgroRange>,
+            Option<&Cloaked>,
+        ),
+        With<Selectable>,
+    >,
+    dimensions: Res<Dimensions>,
+    dpi_factor: Res<DpiFactor>,
+    mut triangles_2d: ResMut<Triangles2DBuffer>,
+    mut lines_2d: ResMut<GpuBuffer<Vertex2D>>,
+    mut glyph_layout_cache: ResMut<GlyphLayoutCache>,
+    players: Res<Players>,
+    key_bindings: Res<KeyBindings>,
+) {
+    if !strategic_map.open {
+        return;
+    }
+
+    let aspect_ratio = aspect_ratio(&dimensions);
+    let (map_min, map_max) = StrategicMap::bounds(aspect_ratio);
+    let pixel = Vec2::one() * 2.0 * dpi_factor.0 / dimensions.to_vec();
+
+    let friendly_colour = players.local_colour().rgb();
+    let enemy_colour = players
+        .opponent_colour()
+        .map_or(Vec3::unit_x(), TeamColour::rgb);
+
+    stage_quad(
+        &mut triangles_2d.0,
+        -Vec2::one(),
+        Vec2::one(),
+        Vec3::broadcast(0.01),
+    );
+    stage_quad(
+        &mut triangles_2d.0,
+        map_min,
+        map_max,
+        Vec3::new(0.02, 0.025, 0.04),
+    );
+
+    let sensors: Vec<_> = ships
+        .iter()
+        .filter(|(.., friendly, _, _, agro_range, _)| friendly.is_some() && agro_range.is_some())
+        .map(|(position, .., agro_range, _)| (position.0, agro_range.unwrap().0))
+        .collect();
+
+    for &(point, range) in &sensors {
+        let center = StrategicMap::to_wgpu(point, aspect_ratio);
+        let radius_x =
+            StrategicMap::to_wgpu(point + Vec3::new(range, 0.0, 0.0), aspect_ratio).x - center.x;
+
+        stage_disc(
+            &mut triangles_2d.0,
+            center,
+            Vec2::new(radius_x, radius_x * aspect_ratio),
+            Vec3::new(0.04, 0.08, 0.06),
+        );
+    }
+
+    let is_visible = |point: Vec3, enemy: Option<&Theirs>, cloaked: Option<&Cloaked>| {
+        enemy.is_none() || (cloaked.is_none() && in_sensor_range(point, &sensors))
+    };
+
+    let control_zones = StrategicMap::control_zones(
+        ships
+            .iter()
+            .filter(|&(_, model, friendly, ..)| friendly.is_some() && *model != ModelId::Asteroid)
+            .map(|(position, ..)| position.0),
+        ships
+            .iter()
+            .filter(|&(position, _, _, enemy, _, _, cloaked)| {
+                enemy.is_some() && is_visible(position.0, enemy, cloaked)
+            })
+            .map(|(position, ..)| position.0),
+    );
+
+    for (cell, &balance) in control_zones.iter().enumerate() {
+        let colour = match balance {
+            0 => continue,
+            balance if balance > 0 => friendly_colour * 0.4,
+            _ => enemy_colour * 0.4,
+        };
+
+        let (min, max) = StrategicMap::cell_bounds(cell, aspect_ratio);
+        // Inset so that neighbouring cells don't share an edge.
+        stage_outline(&mut lines_2d, min + pixel, max - pixel, colour);
+    }
+
+    ships.for_each(|(position, model, friendly, enemy, selected, _, cloaked)| {
+        if !is_visible(position.0, enemy, cloaked) {
+            return;
+        }
+
+        let center = StrategicMap::to_wgpu(position.0, aspect_ratio);
+
+        if !StrategicMap::contains(center, aspect_ratio) {
+            return;
+        }
+
+        let colour = if friendly.is_some() {
+            friendly_colour
+        } else if enemy.is_some() {
+            enemy_colour
+        } else {
+            Vec3::broadcast(0.4)
+        };
+
+        let size = match model {
+            ModelId::Asteroid => 1.5,
+            model if model.is_capital() => 6.0,
+            ModelId::DefensePlatform
+            | ModelId::Shipyard
+            | ModelId::Refinery
+            | ModelId::TradingStation => 4.0,
+            _ => 2.5,
+        };
+
+        let half_extent = pixel * size;
+
+        if model.is_capital() {
+            // A diamond, so that carriers stand out from everything else.
+            let point = |x: f32, y: f32| Vertex2D {
+                pos: center + Vec2::new(x, y) * half_extent,
+                colour,
+            };
+
+            triangles_2d.0.stage(&[
+                point(0.0, 1.0),
+                point(-1.0, 0.0),
+                point(1.0, 0.0),
+                point(0.0, -1.0),
+                point(1.0, 0.0),
+                point(-1.0, 0.0),
+            ]);
+        } else {
+            stage_quad(
+                &mut triangles_2d.0,
+                center - half_extent,
+                center + half_extent,
+                colour,
+            );
+        }
+
+        if selected.is_some() {
+            let margin = half_extent + pixel * 2.0;
+            stage_outline(&mut lines_2d, center - margin, center + margin, Vec3::one());
+        }
+    });
+
+    let position = Vec2::new(
+        dimensions.width as f32 / 2.0,
+        dimensions.height as f32 * 0.955,
+    );
+    let mut section = glyph_layout_cache.start_centered_section(position, dpi_factor.0);
+    section.push(
+        format_args!(
+            "Strategic map ({:?} to close) - left click to select, right click to order",
+            key_bindings.strategic_map
+        ),
+        [1.0; 4],
+    );
+}
@@ -0,0 +1,146 @@
+use crate::find_functions::*;
+use crate::SelectedFriendly;
+use bevy_ecs::prelude::*;
+use components_and_resources::components::*;
+use components_and_resources::resources::*;
+
+pub fn fluctuate_trade_prices(
+    mut stations: Query<&mut TradingStation>,
+    clock: Res<SimClock>,
+    mut rng: ResMut<SmallRng>,
+) {
+    stations.for_each_mut(|mut station| {
+        station.fluctuate(&mut *rng, clock.delta);
+    });
+}
+
+// Freighters fill up at a carrier, sell everything at the station, then head back for more.
+pub fn trade(
+    mut freighters: Query<
+        (
+            &Position,
+            &MaxSpeed,
+            &mut CommandQueue,
+            &mut Cargo,
+            Option<&DropOff>,
+            Option<&Friendly>,
+        ),
+        With<CanTrade>,
+    >,
+    mut stations: Query<(&Position, &mut TradingStation)>,
+    carriers: Query<(Entity, &Position), With<Carrying>>,
+    global_minerals: Res<PerSide<GlobalMinerals>>,
+    mut global_credits: ResMut<PerSide<GlobalCredits>>,
+) {
+    freighters.for_each_mut(
+        |(pos, max_speed, mut queue, mut cargo, drop_off, friendly)| {
+            let side = Side::of(friendly);
+
+            let (target, range_sq) = match queue.0.front() {
+                Some(&Command::Interact {
+                    target,
+                    ty: InteractionType::Trade,
+                    range_sq,
+                }) => (target, range_sq),
+                _ => return,
+            };
+
+            let (station_pos, mut station) = match stations.get_mut(target) {
+                Ok(station) => station,
+                Err(_) => {
+                    queue.0.pop_front();
+                    return;
+                }
+            };
+
+            if cargo.minerals <= 0.0 {
+                // There's nothing to load, so stop instead of shuttling back and forth empty.
+                if global_minerals[side].0 <= 0.0 {
+                    queue.0.pop_front();
+                    return;
+                }
+
+                match drop_off.and_then(|drop_off| carriers.get(drop_off.0).ok()) {
+                    Some(carrier) => find_next_carrier(pos.0, &mut queue, std::iter::once(carrier)),
+                    None => find_next_carrier(pos.0, &mut queue, carriers.iter()),
+                }
+
+                // No carriers left to load from.
+                if let Some(Command::Interact {
+                    ty: InteractionType::Trade,
+                    ..
+                }) = queue.0.front()
+                {
+                    queue.0.pop_front();
+                }
+
+                return;
+            }
+
+            if (station_pos.0 - pos.0).mag_sq() > range_sq + max_speed.max_force() {
+                return;
+            }
+
+            global_credits[side].0 += station.sell(cargo.minerals);
+            cargo.minerals = 0.0;
+        },
+    );
+}
+
+pub fn handle_purchase_keys(
+    keyboard_state: Res<KeyboardState>,
+    carriers: Query<Entity, (SelectedFriendly, With<Carrying>)>,
+    global_credits: Res<PerSide<GlobalCredits>>,
+    players: Res<Players>,
+    mut player_commands: ResMut<PlayerCommands>,
+    mut order_feedback: ResMut<OrderFeedback>,
+) {
+    let purchase = if keyboard_state.hire_mercenaries.0 {
+        SpecialPurchase::Mercenaries
+    } else if keyboard_state.buy_gas_shipment.0 {
+        SpecialPurchase::GasShipment
+    } else {
+        return;
+    };
+
+    let carrier = match carriers.iter().next() {
+        Some(carrier) => carrier,
+        None => {
+            order_feedback.rejected = Some("Select a carrier to deliver the purchase to");
+            return;
+        }
+    };
+
+    if global_credits[players.local_side()].0 < purchase.cost() {
+        order_feedback.rejected = Some("Not enough credits");
+        return;
+    }
+
+    player_commands
+        .local
+        .push(PlayerCommand::Purchase { purchase, carrier });
+}
+
+pub fn render_trade_prices(
+    stations: Query<(&Position, &TradingStation)>,
+    mut glyph_layout_cache: ResMut<GlyphLayoutCache>,
+    perspective_view: Res<PerspectiveView>,
+    dimensions: Res<Dimensions>,
+    dpi_factor: Res<DpiFactor>,
+) {
+    stations.for_each(|(pos, station)| {
+        if let Some(pixels) = perspective_view.project_to_pixels(pos.0, &dimensions) {
+            let colour = if station.price >= station.base_price {
+                [0.25, 1.0, 0.25, 1.0]
+            } else {
+                [1.0, 0.5, 0.25, 1.0]
+            };
+
+            let mut section = glyph_layout_cache.start_section(pixels, dpi_factor.0);
+            section.push(
+                format_args!("Buying minerals: {:.2} credits\n", station.price),
+                colour,
+            );
+        }
+    });
+}
@@ -0,0 +1,153 @@
+use components_and_resources::resources::EngineHum;
+use rodio::{OutputStream, Sink, Source};
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+const SAMPLE_RATE: u32 = 44_100;
+const BASE_FREQUENCY: f32 = 55.0;
+const ERROR_BLIP_FREQUENCY: f32 = 220.0;
+const ERROR_BLIP_DURATION: f32 = 0.12;
+
+// The hum parameters are written on the main thread and read on the audio thread, so
+// they're stored as the bits of an f32.
+#[derive(Default)]
+struct SharedHum {
+    volume: AtomicU32,
+    pitch: AtomicU32,
+}
+
+pub struct EngineHumPlayer {
+    shared: Arc<SharedHum>,
+    _sink: Sink,
+    // One-off sounds go in their own sink, as the hum never finishes.
+    blip_sink: Sink,
+    _stream: OutputStream,
+}
+
+impl EngineHumPlayer {
+    pub fn new() -> Option<Self> {
+        let (stream, handle) = match OutputStream::try_default() {
+            Ok(output) => output,
+            Err(error) => {
+                log::warn!("Failed to open an audio output stream: {}", error);
+                return None;
+            }
+        };
+
+        let sink = Sink::try_new(&handle).ok()?;
+        let blip_sink = Sink::try_new(&handle).ok()?;
+
+        let shared = Arc::new(SharedHum::default());
+
+        sink.append(EngineHumSource {
+            shared: shared.clone(),
+            phase: 0.0,
+        });
+
+        Some(Self {
+            shared,
+            _sink: sink,
+            blip_sink,
+            _stream: stream,
+        })
+    }
+
+    pub fn update(&self, engine_hum: &EngineHum) {
+        self.shared
+            .volume
+            .store(engine_hum.volume.to_bits(), Ordering::Relaxed);
+        self.shared
+            .pitch
+            .store(engine_hum.pitch.to_bits(), Ordering::Relaxed);
+    }
+
+    pub fn play_error_blip(&self) {
+        self.blip_sink.append(ErrorBlipSource { sample: 0 });
+    }
+}
+
+struct EngineHumSource {
+    shared: Arc<SharedHum>,
+    phase: f32,
+}
+
+impl Iterator for EngineHumSource {
+    type Item = f32;
+
+    fn next(&mut self) -> Option<f32> {
+        use std::f32::consts::TAU;
+
+        let volume = f32::from_bits(self.shared.volume.load(Ordering::Relaxed));
+        let pitch = f32::from_bits(self.shared.pitch.load(Ordering::Relaxed));
+
+        self.phase = (self.phase + BASE_FREQUENCY * pitch / SAMPLE_RATE as f32).fract();
+
+        // A couple of harmonics make it sound less like a pure tone and more like an engine.
+        let sample = (self.phase * TAU).sin()
+            + (self.phase * 2.0 * TAU).sin() * 0.5
+            + (self.phase * 3.0 * TAU).sin() * 0.25;
+
+        Some(sample / 1.75 * volume)
+    }
+}
+
+impl Source for EngineHumSource {
+    fn current_frame_len(&self) -> Option<usize> {
+        None
+    }
+
+    fn channels(&self) -> u16 {
+        1
+    }
+
+    fn sample_rate(&self) -> u32 {
+        SAMPLE_RATE
+    }
+
+    fn total_duration(&self) -> Option<Duration> {
+        None
+    }
+}
+
+// A short, low square wave.
+struct ErrorBlipSource {
+    sample: u32,
+}
+
+impl Iterator for ErrorBlipSource {
+    type Item = f32;
+
+    fn next(&mut self) -> Option<f32> {
+        let time = self.sample as f32 / SAMPLE_RATE as f32;
+
+        if time > ERROR_BLIP_DURATION {
+            return None;
+        }
+
+        self.sample += 1;
+
+        let phase = (time * ERROR_BLIP_FREQUENCY).fract();
+        let fade = 1.0 - time / ERROR_BLIP_DURATION;
+
+        Some(if phase < 0.5 { 0.2 } else { -0.2 } * fade)
+    }
+}
+
+impl Source for ErrorBlipSource {
+    fn current_frame_len(&self) -> Option<usize> {
+        None
+    }
+
+    fn channels(&self) -> u16 {
+        1
+    }
+
+    fn sample_rate(&self) -> u32 {
+        SAMPLE_RATE
+    }
+
+    fn total_duration(&self) -> Option<Duration> {
+        Some(Duration::from_secs_f32(ERROR_BLIP_DURATION))
+    }
+}
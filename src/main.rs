@@ -5,27 +5,160 @@ use wgpu::util::DeviceExt;
 use winit::event::*;
 use winit::event_loop::*;
 
-use bevy_ecs::prelude::{IntoSystem, ParallelSystemDescriptorCoercion, Stage};
+use bevy_ecs::prelude::{IntoSystem, Mut, ParallelSystemDescriptorCoercion, Stage};
 use components_and_resources::{
-    components,
+    assets, balance, components,
     gpu_structs::*,
+    map_generation,
     model::{load_image_from_bytes, load_ship_model},
+    mods,
     resources::{self, StructOpt},
+    save,
     texture_manager::TextureManager,
-    utils::uniform_sphere_distribution,
+    utils::{uniform_sphere_distribution, MissionTime},
+    world_hash,
 };
+use std::io::Write;
+
+mod audio;
+mod golden_image;
+mod simulate;
+
+const QUICKSAVE_PATH: &str = "quicksave.ron";
 
 fn main() -> anyhow::Result<()> {
     env_logger::init();
 
     let settings = resources::Settings::from_args();
 
+    // Golden images need the built-in assets.
+    let mod_files = match &settings.golden_image {
+        Some(_) => mods::ModFiles::default(),
+        None => mods::ModFiles::scan(&settings.mods_directory)?,
+    };
+    for warning in &mod_files.warnings {
+        log::warn!("{}", warning);
+    }
+
+    let assets = assets::Assets::new(settings.assets_directory.clone());
+
+    if let Some(resources::Subcommand::Simulate {
+        matchup,
+        runs,
+        time_limit,
+    }) = &settings.subcommand
+    {
+        let matchup = balance::Matchup::parse(matchup)?;
+        return simulate::run(matchup, *runs, *time_limit, &mod_files, &assets);
+    }
+
+    let replay = match &settings.replay {
+        Some(path) => Some(resources::InputReplay::load(path)?),
+        None => None,
+    };
+
+    let scenario = match &settings.scenario {
+        Some(path) => resources::Scenario::load(path)?,
+        None => resources::Scenario::default(),
+    };
+
+    // Golden images need the same world every time.
+    let fixed_seed = settings
+        .seed
+        .or_else(|| settings.golden_image.as_ref().map(|_| 0));
+
+    // Golden images are compared against the default layout. A layout that can't be read or
+    // written out isn't worth refusing to start over.
+    let hud_layout_path = settings
+        .hud_layout
+        .clone()
+        .unwrap_or_else(|| resources::HudLayout::path_for_profile(&settings.player_name));
+    let mut hud_layout = match &settings.golden_image {
+        Some(_) => resources::HudLayout::default(),
+        None => resources::HudLayout::load_or_create(&hud_layout_path).unwrap_or_else(|error| {
+            log::warn!(
+                "Using the default HUD layout, as '{}' couldn't be loaded or created: {}",
+                hud_layout_path.display(),
+                error
+            );
+            resources::HudLayout::default()
+        }),
+    };
+    hud_layout.mission_clock.visible &= !settings.hide_mission_clock;
+
+    let ship_catalog = mod_files.ship_catalog();
+
+    let profile = networking::PlayerProfile {
+        name: settings.player_name.clone(),
+        colour: settings.team_colour as u8,
+    };
+
+    let mut pickups = settings.pickups;
+
+    // Both players need to generate the same world.
+    let (mut lockstep, seed) = if let Some(address) = &settings.host {
+        let match_settings = networking::MatchSettings {
+            seed: fixed_seed.unwrap_or_else(rand::random),
+            pickups,
+        };
+        (
+            Some(networking::Lockstep::host(
+                address,
+                match_settings,
+                profile,
+            )?),
+            match_settings.seed,
+        )
+    } else if let Some(address) = &settings.connect {
+        let (lockstep, match_settings) = networking::Lockstep::connect(address, profile)?;
+        pickups = match_settings.pickups;
+        (Some(lockstep), match_settings.seed)
+    } else if let Some(replay) = &replay {
+        (None, replay.seed)
+    } else {
+        (None, fixed_seed.unwrap_or_else(rand::random))
+    };
+
+    let mut recording = settings
+        .record_replay
+        .clone()
+        .map(|path| (path, resources::InputReplay::new(seed)));
+
+    let golden_image_check = settings.golden_image.clone().map(|path| {
+        let tick = replay
+            .as_ref()
+            .map(|replay| replay.last_tick())
+            .unwrap_or(0)
+            + settings.golden_image_ticks;
+        (path, tick, settings.bless)
+    });
+
+    let mut hash_log = match &settings.hash_log {
+        Some(path) => Some(std::fs::File::create(path)?),
+        None => None,
+    };
+    // Only the first divergence is worth reporting, as everything after it will differ too.
+    let mut divergence_reported = false;
+
+    let mut tick = 0;
+    let mut kill_cam_state: Option<KillCamState> = None;
+
     let backends = wgpu::Backends::VULKAN;
 
     let instance = wgpu::Instance::new(backends);
 
     let event_loop = winit::event_loop::EventLoop::new();
-    let window = winit::window::Window::new(&event_loop)?;
+    let window = if golden_image_check.is_some() {
+        winit::window::WindowBuilder::new()
+            .with_inner_size(winit::dpi::PhysicalSize::new(
+                golden_image::WIDTH,
+                golden_image::HEIGHT,
+            ))
+            .with_resizable(false)
+            .build(&event_loop)?
+    } else {
+        winit::window::Window::new(&event_loop)?
+    };
 
     let surface = unsafe { instance.create_surface(&window) };
 
@@ -48,6 +181,7 @@ fn main() -> anyhow::Result<()> {
             features: wgpu::Features::PUSH_CONSTANTS
                 | wgpu::Features::DEPTH_CLAMPING
                 | wgpu::Features::TEXTURE_BINDING_ARRAY
+                | wgpu::Features::UNSIZED_BINDING_ARRAY
                 | wgpu::Features::MULTI_DRAW_INDIRECT
                 | wgpu::Features::SPIRV_SHADER_PASSTHROUGH
                 | wgpu::Features::TEXTURE_ADAPTER_SPECIFIC_FORMAT_FEATURES,
@@ -74,33 +208,70 @@ fn main() -> anyhow::Result<()> {
         height: window_size.height,
     };
 
-    let mut rng = rand::thread_rng();
-    let (mut background, ambient_light) = background::make_background(&mut rng);
+    let mut rng = resources::SmallRng::seed_from_u64(seed);
+
+    let environment = match settings.lighting_preset.or(scenario.lighting_preset) {
+        Some(preset) => preset.environment(),
+        None => resources::LightingPreset::random_environment(),
+    };
+
+    // The lighting preset is purely cosmetic and can differ between players, so it gets its own
+    // rng to avoid changing the rest of the map.
+    let mut environment_rng = resources::SmallRng::seed_from_u64(seed ^ 0x5eed);
+
+    let (mut background, nebula_ambient) =
+        background::make_background(&mut environment_rng, environment.nebula_brightness);
 
-    let mut sun_dir = uniform_sphere_distribution(&mut rng);
+    let mut sun_dir = uniform_sphere_distribution(&mut environment_rng);
     sun_dir.y = sun_dir.y.abs();
 
-    let stars = background::create_stars(&mut rng)
-        .chain(background::star_points(
-            sun_dir,
-            250.0,
-            Vec3::broadcast(2.0) * Vec3::new(1.0, 0.8, 1.0 / 3.0),
-        ))
-        .collect::<Vec<_>>();
+    let (second_sun_dir, second_sun_colour) = match environment.second_sun {
+        Some(second_sun) => (
+            Rotor3::from_rotation_xz(second_sun.separation) * sun_dir,
+            second_sun.colour,
+        ),
+        None => (sun_dir, Vec3::zero()),
+    };
 
-    background.extend_from_slice(&stars);
+    // The player's lighting preset is ignored here, as it's only cosmetic.
+    let sim_environment = match scenario.lighting_preset {
+        Some(preset) => preset.environment(),
+        None => resources::LightingPreset::random_environment(),
+    };
 
-    let star_system = rendering::passes::StarSystem {
+    let heat_zones = std::iter::once((sun_dir, sim_environment.sun_heat))
+        .chain(sim_environment.second_sun.map(|second_sun| {
+            let second_sun_dir = Rotor3::from_rotation_xz(second_sun.separation) * sun_dir;
+            (second_sun_dir, second_sun.heat)
+        }))
+        .collect();
+
+    let lighting = resources::Lighting::new(resources::LightingState {
         sun_dir,
-        num_background_vertices: background.len() as u32,
-        background_vertices: device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
-            label: Some("background vertices"),
-            contents: bytemuck::cast_slice(&background),
-            usage: wgpu::BufferUsages::VERTEX,
-        }),
-        ambient_light,
+        sun_colour: environment.sun_colour,
+        ambient_light: nebula_ambient * environment.ambient_scale,
+        second_sun_dir,
+        second_sun_colour,
+    });
+
+    // This is relative to the nebula's own ambient light, so it doesn't need scaling.
+    let irradiance_cubemap = background::irradiance_cubemap(&background, nebula_ambient, 16);
+
+    let stars =
+        background::create_stars(&mut environment_rng, environment.star_count).collect::<Vec<_>>();
+
+    let godrays = resources::Godrays {
+        density: settings
+            .godray_density
+            .unwrap_or(environment.godrays.density),
+        decay: settings.godray_decay.unwrap_or(environment.godrays.decay),
+        weight: settings.godray_weight.unwrap_or(environment.godrays.weight),
     };
 
+    let ambient_dust = resources::AmbientDust::new(&mut environment_rng, environment.dust_density);
+
+    background.extend_from_slice(&stars);
+
     let constants = rendering::passes::Constants {
         bounding_box_indices: device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
             label: Some("bounding box vertices"),
@@ -126,12 +297,13 @@ fn main() -> anyhow::Result<()> {
 
     // ecs
     let mut world = bevy_ecs::world::World::default();
+    let clock = resources::SimClock::new(0.0);
 
     for _ in 0..500 {
         let side = rng.gen_range(0.0..1.0) > 0.5;
 
         let position = Vec3::new(
-            rng.gen_range(-100.0..100.0) + side as u8 as f32 * 500.0,
+            rng.gen_range(-100.0..100.0) + side as u8 as f32 * map_generation::ENEMY_OFFSET,
             rng.gen_range(-100.0..100.0),
             rng.gen_range(-100.0..100.0),
         );
@@ -146,6 +318,8 @@ fn main() -> anyhow::Result<()> {
                 world.spawn().insert(components::Engineer).id(),
                 world.spawn().id(),
                 world.spawn().insert(components::Researcher).id(),
+                world.spawn().insert(components::Gunner).id(),
+                world.spawn().insert(components::Pilot).id(),
             ])
         } else {
             None
@@ -159,7 +333,7 @@ fn main() -> anyhow::Result<()> {
             spawner.insert_bundle(components::fighter_components(rng.gen_range(0.0..1.0)));
         } else if let Some(carrier_crew) = carrier_crew {
             let mut queue = components::BuildQueue::default();
-            queue.push(components::ShipType::Fighter, 0.0);
+            queue.push(components::ShipType::Fighter, &clock, &ship_catalog);
             spawner.insert_bundle(components::carrier_components(queue, carrier_crew));
         } else {
             spawner.insert_bundle(components::miner_components());
@@ -173,35 +347,26 @@ fn main() -> anyhow::Result<()> {
     }
 
     for _ in 0..10 {
-        let position = Vec3::new(
-            rng.gen_range(-400.0..400.0),
-            rng.gen_range(-50.0..=10.0),
-            rng.gen_range(-400.0..400.0),
-        );
-        let facing = uniform_sphere_distribution(&mut rng);
-        let rotation = Rotor3::from_rotation_between(Vec3::unit_y(), facing);
-
-        world.spawn().insert_bundle((
-            components::Position(position),
-            components::Rotation(rotation),
-            components::RotationMatrix::default(),
-            components::ModelId::Asteroid,
-            components::WorldSpaceBoundingBox::default(),
-            components::Spin::new(uniform_sphere_distribution(&mut rng)),
-            components::Scale(rng.gen_range(1.0..5.0)),
-            components::Health::new(1000.0),
-            components::Selectable,
-            components::CanBeMined::new(100.0),
-        ));
+        let position = map_generation::random_point_in_asteroid_belt(&mut rng);
+
+        world
+            .spawn()
+            .insert_bundle(map_generation::asteroid_components(position, &mut rng));
     }
 
-    world.insert_resource(resources::ShipBuffer::new(&device));
+    map_generation::spawn_skirmish_features(&mut world, &mut rng);
+
+    world.insert_resource(resources::ShipBuffer::new(&device, "ship instance buffer"));
+    world.insert_resource(resources::CloakedShipBuffer(resources::ShipBuffer::new(
+        &device,
+        "cloaked ship instance buffer",
+    )));
     world.insert_resource(resources::GpuBuffer::<ColouredVertex>::new(
         &device,
         "lines",
         wgpu::BufferUsages::VERTEX,
     ));
-    world.insert_resource(resources::GpuBuffer::<LaserVertex>::new(
+    world.insert_resource(resources::GpuBuffer::<LaserInstance>::new(
         &device,
         "lasers",
         wgpu::BufferUsages::VERTEX,
@@ -211,6 +376,11 @@ fn main() -> anyhow::Result<()> {
         "circle instances",
         wgpu::BufferUsages::VERTEX,
     ));
+    world.insert_resource(resources::GpuBuffer::<TranslucentVertex>::new(
+        &device,
+        "translucent vertices",
+        wgpu::BufferUsages::VERTEX,
+    ));
     world.insert_resource(resources::GpuBuffer::<RangeInstance>::new(
         &device,
         "range instances",
@@ -221,71 +391,78 @@ fn main() -> anyhow::Result<()> {
         "lines 2d",
         wgpu::BufferUsages::VERTEX,
     ));
+    world.insert_resource(resources::Triangles2DBuffer(resources::GpuBuffer::new(
+        &device,
+        "triangles 2d",
+        wgpu::BufferUsages::VERTEX,
+    )));
 
     let mut vertices = Vec::new();
     let mut indices = Vec::new();
     let mut bounding_boxes = Vec::new();
     let mut texture_manager = TextureManager::default();
 
+    let mined_out_asteroid = match mod_files.texture("mined_out_asteroid") {
+        Some(path) => assets::read(path)?,
+        None => assets::read(&assets.texture("mined_out_asteroid"))?,
+    };
+
     world.insert_resource(resources::MiscTextures {
         mined_out_asteroid: texture_manager.add(load_image_from_bytes(
-            &include_bytes!("../textures/mined_out_asteroid.png")[..],
+            &mined_out_asteroid,
+            wgpu::TextureFormat::Rgba8UnormSrgb,
             &device,
             &queue,
         )?),
     });
 
-    let models = [
-        load_ship_model(
-            include_bytes!("../models/carrier.glb"),
-            &device,
-            &queue,
-            &mut vertices,
-            &mut indices,
-            &mut bounding_boxes,
-            &mut texture_manager,
-        )?,
-        load_ship_model(
-            include_bytes!("../models/fighter.glb"),
-            &device,
-            &queue,
-            &mut vertices,
-            &mut indices,
-            &mut bounding_boxes,
-            &mut texture_manager,
-        )?,
-        load_ship_model(
-            include_bytes!("../models/miner.glb"),
-            &device,
-            &queue,
-            &mut vertices,
-            &mut indices,
-            &mut bounding_boxes,
-            &mut texture_manager,
-        )?,
-        load_ship_model(
-            include_bytes!("../models/explosion.glb"),
+    let mut models = Vec::with_capacity(resources::Models::COUNT);
+
+    for &model_id in &resources::Models::ARRAY {
+        let mut model = load_ship_model(
+            &mod_files.read_model(model_id, &assets)?,
             &device,
             &queue,
             &mut vertices,
             &mut indices,
             &mut bounding_boxes,
             &mut texture_manager,
-        )?,
-        load_ship_model(
-            include_bytes!("../models/asteroid.glb"),
+        )?;
+        mod_files.apply_texture_overrides(
+            model_id,
+            &mut model,
             &device,
             &queue,
-            &mut vertices,
-            &mut indices,
-            &mut bounding_boxes,
             &mut texture_manager,
-        )?,
-    ];
+        )?;
+        models.push(model);
+    }
 
-    let resources = rendering::Resources::new(&device, texture_manager.count());
+    let resources =
+        rendering::Resources::new(&device, texture_manager.count(), settings.texture_filtering);
     let pipelines = unsafe { rendering::Pipelines::new(&device, &resources, display_format) };
 
+    let star_system = rendering::passes::StarSystem {
+        num_background_vertices: background.len() as u32,
+        background_vertices: device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("background vertices"),
+            contents: bytemuck::cast_slice(&background),
+            usage: wgpu::BufferUsages::VERTEX,
+        }),
+        sun_vertices: device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("sun vertices"),
+            contents: bytemuck::cast_slice(&sun_vertices(&lighting.current)),
+            usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
+        }),
+        irradiance_bind_group: rendering::create_irradiance_bind_group(
+            &device,
+            &queue,
+            &resources,
+            16,
+            &irradiance_cubemap,
+        ),
+    };
+
     let mut resizables = rendering::Resizables::new(
         dimensions.width,
         dimensions.height,
@@ -295,8 +472,8 @@ fn main() -> anyhow::Result<()> {
         &resources,
     );
 
-    world.insert_resource(resources::Models {
-        models,
+    world.insert_resource(resources::Models::new(models));
+    world.insert_resource(resources::ModelBuffers {
         vertices: device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
             label: Some("merged model vertices"),
             usage: wgpu::BufferUsages::VERTEX,
@@ -314,7 +491,7 @@ fn main() -> anyhow::Result<()> {
         }),
         bind_group: texture_manager.into_bind_group(
             &device,
-            &resources.nearest_sampler,
+            &resources.texture_sampler,
             &resources.merged_textures_bgl,
         ),
     });
@@ -326,96 +503,433 @@ fn main() -> anyhow::Result<()> {
     .build(&device, display_format);
 
     world.insert_resource(resources::GlyphLayoutCache::new(glyph_brush));
+    world.insert_resource(resources::RenderStats::default());
     world.insert_resource(resources::GpuInterface { device, queue });
     world.insert_resource(resources::MouseState::default());
     world.insert_resource(resources::Ray::default());
     world.insert_resource(resources::ShipUnderCursor::default());
     let orbit = resources::Orbit::default();
-    world.insert_resource(resources::PerspectiveView::new(
+    let perspective_view = resources::PerspectiveView::new(
         59.0_f32.to_radians(),
         dimensions.width as f32 / dimensions.height as f32,
         orbit.as_vector(),
         Vec3::zero(),
-    ));
+    );
+    world.insert_resource(resources::PictureInPicture::new(perspective_view.clone()));
+    world.insert_resource(perspective_view);
     world.insert_resource(orbit);
     world.insert_resource(dimensions);
     world.insert_resource(resources::KeyboardState::default());
+    world.insert_resource(resources::KeyBindings::default());
     world.insert_resource(resources::Camera::default());
-    world.insert_resource(resources::DeltaTime(1.0 / 60.0));
-    world.insert_resource(resources::TotalTime(0.0));
+    world.insert_resource(clock);
+    world.insert_resource(resources::RealTime(0.0));
+    world.insert_resource(resources::HeatZones(heat_zones));
     world.insert_resource(resources::AverageSelectedPosition::default());
     world.insert_resource(resources::MouseMode::Normal);
     world.insert_resource(resources::Paused(false));
+    world.insert_resource(resources::SaveLoadRequest::default());
+    world.insert_resource(resources::AutosaveTimer::new(&settings, 0.0));
+    world.insert_resource(resources::OrderConfirmation::default());
+    world.insert_resource(resources::OrderFeedback::default());
+    world.insert_resource(resources::Notifications::default());
+    world.insert_resource(resources::GameEvents::default());
+    world.insert_resource(resources::DamageEvents::default());
+    world.insert_resource(resources::AttackAlert::default());
+    world.insert_resource(resources::IdleShips::default());
+    world.insert_resource(resources::ProductionPanel::default());
+    world.insert_resource(resources::AsteroidRespawns::default());
+    world.insert_resource(resources::PickupSpawner::new(pickups));
+    world.insert_resource(resources::ScriptHost::new(scenario)?);
+    world.insert_resource(save::ComponentRegistry::default());
+    // Re-simulating can't be done without holding up the other player in multiplayer, and would
+    // run replayed or recorded ticks twice.
+    world.insert_resource(resources::KillCam::new(
+        lockstep.is_none() && replay.is_none() && recording.is_none(),
+    ));
+    world.insert_resource(match &lockstep {
+        Some(lockstep) => resources::Players::new(
+            lockstep
+                .profiles()
+                .iter()
+                .map(|profile| {
+                    (
+                        profile.name.clone(),
+                        resources::TeamColour::from_index(profile.colour),
+                    )
+                })
+                .collect(),
+            lockstep.local_player(),
+        ),
+        None => resources::Players::new(
+            vec![(settings.player_name.clone(), settings.team_colour)],
+            0,
+        ),
+    });
+    world.insert_resource(resources::PerSide::<resources::GlobalRulesOfEngagement>::default());
     world.insert_resource(bevy_tasks::TaskPool::new());
-    world.insert_resource(resources::SmallRng::from_entropy());
+    world.insert_resource(resources::SmallRng::from_rng(&mut rng)?);
+    world.insert_resource(resources::PlayerCommands::default());
+    world.insert_resource(resources::PendingOrderTiming::default());
+    world.insert_resource(resources::SelectedFormationShape::default());
+    world.insert_resource(resources::GroupSpeed::default());
+    world.insert_resource(resources::ControlGroups::default());
+    world.insert_resource(resources::FleetCycle::default());
+    world.insert_resource(resources::TargetCycle::default());
+    world.insert_resource(resources::PerSide::<resources::TechTree>::default());
+    world.insert_resource(resources::ResearchPanel::default());
+    world.insert_resource(resources::StrategicMap::default());
+    world.insert_resource(resources::CrewTransferPanel::default());
     world.insert_resource(resources::UnitButtons::default());
+    world.insert_resource(hud_layout);
+    world.insert_resource(ship_catalog);
     world.insert_resource(resources::SelectedButton::default());
     world.insert_resource(resources::TopLevelAccelerationStructure::default());
-    world.insert_resource(resources::GlobalMinerals::default());
-    world.insert_resource(resources::GlobalResearch::default());
+    world.insert_resource(resources::PerSide::<resources::GlobalMinerals>::default());
+    world.insert_resource(resources::PerSide::<resources::GlobalGas>::default());
+    world.insert_resource(resources::PerSide::<resources::GlobalCredits>::default());
+    world.insert_resource(resources::PerSide::<resources::GlobalResearch>::default());
+    world.insert_resource(resources::ObjectiveScore::default());
+    world.insert_resource(resources::EngineHum::default());
+    world.insert_resource(lighting);
+
+    let mut cutscene_player = resources::CutscenePlayer::default();
+
+    if !settings.skip_intro && golden_image_check.is_none() {
+        cutscene_player.play(intro_cutscene());
+    }
+
+    world.insert_resource(cutscene_player);
+
+    let engine_hum_player = if settings.disable_audio {
+        None
+    } else {
+        audio::EngineHumPlayer::new()
+    };
+
+    world.insert_resource(match &settings.screen_reader {
+        Some(backend) => resources::ScreenReader::new(backend),
+        None => resources::ScreenReader::default(),
+    });
     world.insert_resource(settings);
+    world.insert_resource(godrays);
+    world.insert_resource(ambient_dust);
     world.insert_resource(resources::DpiFactor(window.scale_factor() as f32));
 
     let stage_1 = bevy_ecs::schedule::SystemStage::parallel()
         // No dependencies.
-        .with_system(systems::spin.system())
-        .with_system(systems::kill_temporary.system())
-        .with_system(systems::expand_explosions.system())
-        .with_system(systems::spawn_projectiles.system())
-        .with_system(systems::update_projectiles.system())
+        .with_system(systems::mark_local_side.system())
         .with_system(systems::move_camera.system())
         .with_system(systems::set_camera_following.system())
+        .with_system(systems::cycle_fleets.system())
+        .with_system(systems::jump_to_attack.system())
+        .with_system(systems::cycle_idle_ships.system())
+        .with_system(systems::cycle_targets.system())
+        .with_system(systems::read_out_game_state.system())
+        .with_system(systems::cycle_player_tags.system())
+        .with_system(systems::request_kill_cam.system())
+        .with_system(systems::toggle_research_panel.system())
+        .with_system(systems::toggle_strategic_map.system())
+        .with_system(systems::handle_strategic_map_clicks.system())
+        .with_system(systems::set_hovered_tech.system())
+        .with_system(systems::enter_crew_transfer_mode.system())
+        .with_system(systems::set_hovered_crew_member.system())
+        .with_system(systems::set_hovered_production_item.system())
         .with_system(systems::handle_keys.system())
-        .with_system(systems::remove_unloading.system())
-        .with_system(systems::build_ships::<components::Friendly>.system())
-        .with_system(systems::build_ships::<components::Enemy>.system())
-        .with_system(systems::redirect_ships_from_full_carriers.system())
-        .with_system(systems::debug_watch.system())
+        .with_system(systems::handle_purchase_keys.system())
+        // Runs while paused so that the game can be resumed. Other orders are held back until then.
+        .with_system(
+            systems::apply_player_commands
+                .system()
+                .label("player_commands"),
+        )
+        .with_system(systems::handle_save_load_keys.system())
+        .with_system(systems::handle_order_timing_keys.system())
+        .with_system(systems::cycle_formation_shape.system())
+        .with_system(systems::change_game_speed.system())
         .with_system(
-            systems::apply_staging_velocity
+            systems::handle_control_group_keys
                 .system()
-                .label("staging vel"),
+                .after("control_group_losses"),
         )
         .with_system(
-            systems::apply_velocity
+            systems::track_control_group_losses
                 .system()
-                .label("vel")
-                .after("staging vel"),
+                .label("control_group_losses"),
         )
-        .with_system(systems::spawn_projectile_from_ships::<components::Friendly>.system())
-        .with_system(systems::spawn_projectile_from_ships::<components::Enemy>.system())
+        .with_system(systems::set_drop_off.system())
+        .with_system(systems::toggle_hold_position.system())
+        .with_system(systems::lay_mine.system())
+        .with_system(systems::toggle_cloak.system())
+        .with_system(systems::toggle_carrier_doctrine.system())
+        .with_system(systems::cycle_autonomy.system())
+        .with_system(systems::toggle_retreat.system())
+        .with_system(systems::cycle_targeting_stance.system())
+        .with_system(systems::cycle_stance.system())
+        .with_system(systems::cycle_throttle.system())
+        .with_system(systems::enter_rally_point_mode.system())
+        .with_system(systems::enter_minefield_targeting_mode.system())
+        .with_system(systems::enter_structure_placement_mode.system())
+        .with_system(systems::assign_escorts.system())
+        .with_system(systems::set_rules_of_engagement.system())
+        .with_system(systems::confirm_destructive_orders.system())
+        .with_system(systems::update_lighting.system())
+        .with_system(systems::debug_watch.system())
+        .with_system(systems::dump_flight_recorders.system())
         .with_system(systems::count_selected.system())
         .with_system(systems::set_selected_button.system())
-        .with_system(systems::repair_ships.system())
-        .with_system(systems::perform_research.system())
-        .with_system(systems::mine.system().label("mine").after("vel"))
         // Buffer clears
-        .with_system(systems::clear_ship_buffer.system())
-        .with_system(systems::clear_buffer::<LaserVertex>.system())
+        .with_system(systems::clear_buffer::<LaserInstance>.system())
         .with_system(systems::clear_buffer::<ColouredVertex>.system())
         .with_system(systems::clear_buffer::<RangeInstance>.system())
         .with_system(systems::clear_buffer::<Vertex2D>.system())
-        .with_system(systems::clear_buffer::<CircleInstance>.system());
+        .with_system(systems::clear_triangles_2d_buffer.system())
+        .with_system(systems::clear_buffer::<CircleInstance>.system())
+        .with_system(systems::clear_buffer::<TranslucentVertex>.system())
+        .with_system_set(
+            bevy_ecs::schedule::SystemSet::new()
+                .with_run_criteria(systems::simulation_running.system())
+                .with_system(systems::spin.system())
+                .with_system(systems::orbit.system().before("mine"))
+                .with_system(systems::drift.system().before("mine"))
+                // Systems that draw from the rng or change the same health and heat run in a
+                // fixed order, as peers have to get the same results.
+                .with_system(
+                    systems::respawn_asteroids
+                        .system()
+                        .label("respawn")
+                        .after("player_commands"),
+                )
+                .with_system(
+                    systems::spawn_pickups
+                        .system()
+                        .label("spawn_pickups")
+                        .after("respawn"),
+                )
+                .with_system(systems::expire_boosts.system())
+                // Runs in a different stage to `handle_destruction` so that units destroyed on
+                // one tick always set off triggers on the next.
+                .with_system(
+                    systems::run_mission_scripts
+                        .system()
+                        .label("scripts")
+                        .after("spawn_pickups"),
+                )
+                .with_system(systems::kill_temporary.system())
+                .with_system(systems::expand_explosions.system())
+                .with_system(systems::spawn_projectiles.system())
+                .with_system(systems::update_projectiles.system())
+                .with_system(systems::remove_unloading.system())
+                .with_system(
+                    systems::build_ships::<components::Friendly>
+                        .system()
+                        .label("build_friendly")
+                        .after("scripts"),
+                )
+                .with_system(
+                    systems::build_ships::<components::Enemy>
+                        .system()
+                        .label("build_enemy")
+                        .after("build_friendly"),
+                )
+                .with_system(systems::redirect_ships_from_full_carriers.system())
+                .with_system(systems::orbit_motherships.system())
+                .with_system(
+                    systems::dock_drones
+                        .system()
+                        .label("dock_drones")
+                        .after("build_enemy"),
+                )
+                .with_system(
+                    systems::launch_fighters::<components::Friendly, components::Enemy>
+                        .system()
+                        .label("launch_friendly")
+                        .after("dock_drones"),
+                )
+                .with_system(
+                    systems::launch_fighters::<components::Enemy, components::Friendly>
+                        .system()
+                        .label("launch_enemy")
+                        .after("launch_friendly"),
+                )
+                .with_system(systems::recall_damaged_fighters::<components::Friendly>.system())
+                .with_system(systems::recall_damaged_fighters::<components::Enemy>.system())
+                .with_system(
+                    systems::repair_carried_ships
+                        .system()
+                        .label("repair_carried")
+                        .after("repair"),
+                )
+                .with_system(
+                    systems::dock_idle_ships::<components::Friendly, components::Enemy>.system(),
+                )
+                .with_system(
+                    systems::dock_idle_ships::<components::Enemy, components::Friendly>.system(),
+                )
+                .with_system(systems::record_flights.system())
+                .with_system(
+                    systems::apply_staging_velocity
+                        .system()
+                        .label("staging vel"),
+                )
+                .with_system(
+                    systems::apply_velocity
+                        .system()
+                        .label("vel")
+                        .after("staging vel"),
+                )
+                .with_system(
+                    systems::spawn_projectile_from_ships::<components::Friendly>
+                        .system()
+                        .label("shoot_friendly"),
+                )
+                .with_system(
+                    systems::spawn_projectile_from_ships::<components::Enemy>
+                        .system()
+                        .label("shoot_enemy")
+                        .after("shoot_friendly"),
+                )
+                .with_system(
+                    systems::fire_broadsides::<components::Friendly, components::Enemy>
+                        .system()
+                        .label("broadsides_friendly")
+                        .after("shoot_enemy"),
+                )
+                .with_system(
+                    systems::fire_broadsides::<components::Enemy, components::Friendly>
+                        .system()
+                        .label("broadsides_enemy")
+                        .after("broadsides_friendly"),
+                )
+                .with_system(
+                    systems::fire_beams::<components::Friendly, components::Enemy>
+                        .system()
+                        .label("beams_friendly")
+                        .after("broadsides_enemy"),
+                )
+                .with_system(
+                    systems::fire_beams::<components::Enemy, components::Friendly>
+                        .system()
+                        .label("beams_enemy")
+                        .after("beams_friendly"),
+                )
+                .with_system(
+                    systems::launch_missiles::<components::Friendly, components::Enemy>.system(),
+                )
+                .with_system(
+                    systems::launch_missiles::<components::Enemy, components::Friendly>.system(),
+                )
+                .with_system(systems::guide_missiles.system().before("vel"))
+                .with_system(
+                    systems::repair_ships
+                        .system()
+                        .label("repair")
+                        .after("beams_enemy"),
+                )
+                .with_system(systems::aggregate_crew.system())
+                .with_system(
+                    systems::repair_with_beams::<components::Friendly>
+                        .system()
+                        .label("beam_repair_friendly")
+                        .after("vel")
+                        .after("repair_carried"),
+                )
+                .with_system(
+                    systems::repair_with_beams::<components::Enemy>
+                        .system()
+                        .after("vel")
+                        .after("beam_repair_friendly"),
+                )
+                .with_system(systems::recharge_shields.system())
+                .with_system(systems::vent_heat.system().after("beams_enemy"))
+                .with_system(systems::perform_research.system().label("research"))
+                .with_system(systems::advance_research.system().after("research"))
+                .with_system(systems::apply_research_upgrades.system())
+                .with_system(systems::move_comets.system().label("comets"))
+                .with_system(systems::count_mining_claims.system().label("mining_claims"))
+                .with_system(
+                    systems::mine
+                        .system()
+                        .label("mine")
+                        .after("vel")
+                        .after("comets")
+                        .after("mining_claims"),
+                )
+                .with_system(
+                    systems::auto_task_idle_miners
+                        .system()
+                        .after("mining_claims")
+                        .after("mine"),
+                )
+                .with_system(systems::salvage.system().after("vel"))
+                .with_system(systems::harvest.system().after("vel"))
+                .with_system(systems::trade.system().after("vel"))
+                .with_system(
+                    systems::fluctuate_trade_prices
+                        .system()
+                        .after("launch_enemy"),
+                )
+                .with_system(systems::construct.system().after("vel"))
+                .with_system(systems::refine.system())
+                .with_system(systems::pick_up_items.system().after("vel"))
+                .with_system(systems::deliver_relics.system().after("vel")),
+        );
 
     // Need to update what the camera is following.
     let stage_2 = bevy_ecs::schedule::SystemStage::parallel()
         // Dependent on updated projectiles
         .with_system(systems::render_projectiles.system())
+        .with_system(systems::render_beams.system())
+        .with_system(systems::render_impact_effects.system())
+        .with_system(systems::render_missiles.system())
+        .with_system(systems::render_comet_tails.system())
+        .with_system(systems::render_gas_clouds.system())
+        .with_system(systems::render_pickups.system())
         // Dependent on ship positions (`move_ships_system`).
         .with_system(systems::calculate_average_selected_position.system())
         //  Dependent on average ship position (`calculate_average_selected_position_system`).
-        .with_system(systems::handle_right_clicks.system());
+        .with_system_set(
+            bevy_ecs::schedule::SystemSet::new()
+                .with_run_criteria(systems::strategic_map_closed.system())
+                .with_system(systems::handle_right_clicks.system()),
+        )
+        .with_system(systems::carry_out_destructive_orders.system())
+        .with_system_set(
+            bevy_ecs::schedule::SystemSet::new()
+                .with_run_criteria(systems::simulation_running.system())
+                .with_system(systems::detonate_scuttled_ships.system().label("scuttle"))
+                .with_system(
+                    systems::detonate_proximity_mines
+                        .system()
+                        .label("mines")
+                        .after("scuttle"),
+                )
+                .with_system(systems::drop_minefields.system())
+                .with_system(
+                    systems::collide_comets
+                        .system()
+                        .label("comet_impacts")
+                        .after("mines"),
+                )
+                .with_system(systems::detect_mines.system())
+                .with_system(systems::drain_cloak_energy.system())
+                .with_system(systems::lose_track_of_cloaked_ships.system())
+                .with_system(systems::collect_sensor_relays.system())
+                .with_system(systems::collect_pickups.system().after("comet_impacts"))
+                .with_system(systems::reserve_mining_slots.system()),
+        );
 
     // Flush the command buffer adding `MovingTo`s to ships.
     let stage_3 = bevy_ecs::schedule::SystemStage::parallel()
         // Dependent on `handle_right_clicks_system`.
         .with_system(systems::set_rotation_from_velocity.system().label("rot"))
+        .with_system(systems::report_rejected_orders.system())
         // Dependent on updated rotations.
         .with_system(
             systems::update_ship_rotation_matrix
                 .system()
                 .label("rot_mat")
-                .after("rot"),
+                .after("face"),
         )
         // Dependent on updated rotation matrices.
         .with_system(
@@ -426,56 +940,61 @@ fn main() -> anyhow::Result<()> {
                 .after("rot_mat"),
         )
         .with_system(systems::update_tlas.system().label("tlas").after("bbox"))
+        .with_system(systems::run_cutscene.system().label("cutscene"))
         // Dependent on model movement.
         .with_system(
             systems::move_camera_around_following
                 .system()
                 .label("cam")
-                .after("pos"),
+                .after("pos")
+                .after("cutscene"),
         )
-        .with_system(
-            systems::choose_enemy_target::<components::Friendly, components::Enemy>
-                .system()
-                .after("pos"),
+        .with_system(systems::update_picture_in_picture.system().after("pos"))
+        .with_system(systems::debug_render_targets.system().after("pos"))
+        .with_system(systems::render_waypoints.system().after("cam"))
+        .with_system(systems::render_mining_routes.system().after("pos"))
+        .with_system(systems::render_sensor_relays.system().after("pos"))
+        .with_system(systems::render_shield_impacts.system().after("bbox"))
+        .with_system(systems::render_relics.system().after("pos"))
+        .with_system(systems::render_dust.system().after("cam"))
+        .with_system_set(
+            bevy_ecs::schedule::SystemSet::new()
+                .with_run_criteria(systems::strategic_map_closed.system())
+                .with_system(systems::handle_left_drag.system().after("pos")),
         )
+        // Dependent on camera movement.
+        .with_system(systems::update_ray.system().label("ray").after("cam"))
+        // Dependent on an updated ray
         .with_system(
-            systems::choose_enemy_target::<components::Enemy, components::Friendly>
+            systems::update_ray_plane_point
                 .system()
-                .after("pos"),
+                .label("ray_plane")
+                .after("ray"),
         )
-        //.flush()
-        // This has to go before persuit as both use the command queue.
         .with_system(
-            systems::run_avoidance
+            systems::update_rally_point_on_plane
                 .system()
-                .label("avoidance")
-                .after("tlas"),
-        )
-        .with_system(systems::run_persuit.system().after("avoidance"))
-        .with_system(systems::run_evasion.system().after("pos"))
-        .with_system(systems::debug_render_targets.system().after("pos"))
-        .with_system(systems::handle_left_drag.system().after("pos"))
-        // Dependent on model movement and updated matrices
-        .with_system(
-            systems::collide_projectiles::<components::Friendly>
-                .system()
-                .after("bbox"),
+                .label("rally_plane")
+                .after("ray"),
         )
+        .with_system(systems::place_rally_point.system().after("rally_plane"))
         .with_system(
-            systems::collide_projectiles::<components::Enemy>
+            systems::update_structure_placement_point
                 .system()
-                .after("bbox"),
+                .label("structure_plane")
+                .after("ray"),
         )
-        // Dependent on camera movement.
-        .with_system(systems::update_ray.system().label("ray").after("cam"))
-        // Dependent on an updated ray
+        .with_system(systems::place_structure.system().after("structure_plane"))
+        .with_system(systems::open_crew_transfer_panel.system().after("under"))
+        // Dependent on an updated ray, positions and matrices.
         .with_system(
-            systems::update_ray_plane_point
+            systems::update_area_targeting_point
                 .system()
-                .label("ray_plane")
-                .after("ray"),
+                .label("area_targeting")
+                .after("ray")
+                .after("tlas"),
         )
-        // Dependent on an updated ray, positions and matrices.
+        .with_system(systems::place_minefield.system().after("area_targeting"))
         .with_system(
             systems::find_ship_under_cursor
                 .system()
@@ -485,29 +1004,178 @@ fn main() -> anyhow::Result<()> {
         // .with_system(systems::debug_find_ship_under_cursor.system())
         // Dependent on `find_ship_under_cursor_system`.
         // TODO: should ideally happen BEFORE ships are moved as the player is reacting to their last seen position onsceen.
-        .with_system(systems::handle_left_click.system().after("under"))
+        .with_system_set(
+            bevy_ecs::schedule::SystemSet::new()
+                .with_run_criteria(systems::strategic_map_closed.system())
+                .with_system(systems::handle_left_click.system().after("under")),
+        )
+        .with_system(systems::choose_research.system())
+        .with_system(systems::choose_crew_member.system())
+        .with_system(systems::handle_production_panel_clicks.system())
         // Staging
         .with_system(systems::render_movement_circle.system().after("ray_plane"))
+        .with_system(systems::render_arrival_estimate.system().after("ray_plane"))
+        .with_system(systems::render_rally_points.system().after("rally_plane"))
+        .with_system(
+            systems::render_construction_sites
+                .system()
+                .after("structure_plane"),
+        )
+        .with_system(
+            systems::render_weapon_arcs
+                .system()
+                .after("ray_plane")
+                .after("rot_mat"),
+        )
+        .with_system(
+            systems::render_area_targeting
+                .system()
+                .after("area_targeting"),
+        )
         //.with_system(systems::draw_agro_ranges.system().after("pos"))
         .with_system(systems::render_drag_box.system())
-        .with_system(systems::render_model_instances.system().after("under"));
+        .with_system(systems::render_model_instances.system().after("under"))
+        .with_system_set(
+            bevy_ecs::schedule::SystemSet::new()
+                .with_run_criteria(systems::simulation_running.system())
+                // Dependent on updated rotations.
+                .with_system(systems::turn_to_face.system().label("face").after("rot"))
+                .with_system(
+                    systems::choose_enemy_target::<components::Friendly, components::Enemy>
+                        .system()
+                        .after("pos")
+                        .after("tlas"),
+                )
+                .with_system(
+                    systems::choose_enemy_target::<components::Enemy, components::Friendly>
+                        .system()
+                        .after("pos")
+                        .after("tlas"),
+                )
+                .with_system(systems::pull_back_leashed_ships.system().after("pos"))
+                .with_system(systems::ram_ships.system().label("ram").after("tlas"))
+                .with_system(
+                    systems::intercept_threats::<components::Friendly, components::Enemy>
+                        .system()
+                        .after("pos"),
+                )
+                .with_system(
+                    systems::intercept_threats::<components::Enemy, components::Friendly>
+                        .system()
+                        .after("pos"),
+                )
+                //.flush()
+                // This has to go before persuit as both use the command queue.
+                .with_system(
+                    systems::run_avoidance
+                        .system()
+                        .label("avoidance")
+                        .after("tlas"),
+                )
+                .with_system(systems::run_persuit.system().after("avoidance"))
+                .with_system(systems::run_evasion.system().after("pos"))
+                .with_system(systems::retreat_damaged_ships::<components::Friendly>.system())
+                .with_system(systems::retreat_damaged_ships::<components::Enemy>.system())
+                .with_system(
+                    systems::rejoin_after_repair
+                        .system()
+                        .after("incoming_enemy"),
+                )
+                // Dependent on model movement and updated matrices. Like the systems above, these
+                // share the rng and health so run one after another.
+                .with_system(
+                    systems::collide_projectiles::<components::Friendly>
+                        .system()
+                        .label("collide_friendly")
+                        .after("bbox")
+                        .after("ram"),
+                )
+                .with_system(
+                    systems::collide_projectiles::<components::Enemy>
+                        .system()
+                        .label("collide_enemy")
+                        .after("collide_friendly"),
+                )
+                .with_system(
+                    systems::detonate_missiles::<components::Friendly>
+                        .system()
+                        .label("detonate_friendly")
+                        .after("tlas")
+                        .after("collide_enemy"),
+                )
+                .with_system(
+                    systems::detonate_missiles::<components::Enemy>
+                        .system()
+                        .label("detonate_enemy")
+                        .after("detonate_friendly"),
+                )
+                .with_system(
+                    systems::detect_incoming_fire::<components::Friendly>
+                        .system()
+                        .label("incoming_friendly")
+                        .after("tlas")
+                        .after("detonate_enemy"),
+                )
+                .with_system(
+                    systems::detect_incoming_fire::<components::Enemy>
+                        .system()
+                        .label("incoming_enemy")
+                        .after("incoming_friendly"),
+                ),
+        );
 
     let final_stage = bevy_ecs::schedule::SystemStage::parallel()
-        .with_system(systems::handle_destruction.system())
-        .with_system(systems::update_mouse_state.system())
+        .with_system(systems::update_mouse_state.system().label("mouse_state"))
+        .with_system(
+            systems::control_cursor_with_keys
+                .system()
+                .after("mouse_state"),
+        )
         .with_system(systems::update_keyboard_state.system())
-        .with_system(systems::increase_total_time.system())
+        .with_system(systems::increase_real_time.system())
+        .with_system(systems::autosave.system().after("sim_clock"))
         .with_system(systems::upload_ship_buffer.system())
         .with_system(systems::render_3d_ship_stats.system())
         .with_system(systems::debug_render_tlas.system())
-        .with_system(systems::render_buttons.system());
+        .with_system(systems::render_buttons.system())
+        .with_system(systems::render_order_confirmation.system())
+        .with_system(systems::render_pending_order_timing.system())
+        .with_system(systems::render_order_rejection.system())
+        .with_system(systems::render_notifications.system())
+        .with_system(systems::render_event_log.system())
+        .with_system(systems::render_mission_clock.system())
+        .with_system(systems::render_paused.system())
+        .with_system(systems::render_player_names.system())
+        .with_system(systems::render_kill_cam_prompt.system())
+        .with_system(systems::render_trade_prices.system())
+        .with_system(systems::render_render_stats.system())
+        .with_system(systems::render_research_panel.system())
+        .with_system(systems::render_strategic_map.system())
+        .with_system(systems::render_crew_transfer_panel.system())
+        .with_system(systems::render_production_panel.system())
+        .with_system(systems::update_engine_hum.system())
+        .with_system(systems::render_subtitles.system())
+        .with_system_set(
+            bevy_ecs::schedule::SystemSet::new()
+                .with_run_criteria(systems::simulation_running.system())
+                .with_system(systems::handle_destruction.system())
+                .with_system(systems::report_attacks.system().label("report_attacks"))
+                .with_system(
+                    systems::clear_damage_events
+                        .system()
+                        .after("report_attacks"),
+                )
+                .with_system(systems::advance_sim_clock.system().label("sim_clock")),
+        );
 
     let upload_buffer_stage = bevy_ecs::schedule::SystemStage::parallel()
-        .with_system(systems::upload_buffer::<LaserVertex>.system())
+        .with_system(systems::upload_buffer::<LaserInstance>.system())
         .with_system(systems::upload_buffer::<ColouredVertex>.system())
         .with_system(systems::upload_buffer::<RangeInstance>.system())
         .with_system(systems::upload_buffer::<Vertex2D>.system())
-        .with_system(systems::upload_buffer::<CircleInstance>.system());
+        .with_system(systems::upload_triangles_2d_buffer.system())
+        .with_system(systems::upload_buffer::<CircleInstance>.system())
+        .with_system(systems::upload_buffer::<TranslucentVertex>.system());
 
     let mut schedule = bevy_ecs::schedule::Schedule::default()
         .with_stage("stage 1", stage_1)
@@ -518,25 +1186,33 @@ fn main() -> anyhow::Result<()> {
 
     event_loop.run(move |event, _, control_flow| match event {
         Event::WindowEvent { ref event, .. } => match event {
-            WindowEvent::CloseRequested => *control_flow = ControlFlow::Exit,
-            WindowEvent::Resized(size) => {
-                let mut dimensions = world.get_resource_mut::<resources::Dimensions>().unwrap();
+            WindowEvent::CloseRequested => {
+                if let Some((path, recording)) = &recording {
+                    match recording.save(path) {
+                        Ok(()) => log::info!("Recorded a replay to '{}'", path.display()),
+                        Err(error) => log::error!(
+                            "Recording a replay to '{}' failed: {}",
+                            path.display(),
+                            error
+                        ),
+                    }
+                }
 
-                let (width, height) = (size.width as u32, size.height as u32);
+                *control_flow = ControlFlow::Exit;
+            }
+            WindowEvent::Resized(size) => {
+                // The window has been minimized. Zero-sized textures aren't valid, so keep the
+                // old resizables around until it's restored.
+                if size.width == 0 || size.height == 0 {
+                    return;
+                }
 
-                dimensions.width = width as u32;
-                dimensions.height = height as u32;
+                let mut dimensions = world.get_resource_mut::<resources::Dimensions>().unwrap();
 
-                let gpu_interface = world.get_resource::<resources::GpuInterface>().unwrap();
+                dimensions.width = size.width as u32;
+                dimensions.height = size.height as u32;
 
-                resizables = rendering::Resizables::new(
-                    width,
-                    height,
-                    display_format,
-                    &gpu_interface.device,
-                    &surface,
-                    &resources,
-                );
+                resizables = recreate_resizables(&world, &surface, display_format, &resources);
 
                 let mut perspective_view = world
                     .get_resource_mut::<resources::PerspectiveView>()
@@ -545,7 +1221,16 @@ fn main() -> anyhow::Result<()> {
                 perspective_view.set_perspective(
                     59.0_f32.to_radians(),
                     size.width as f32 / size.height as f32,
-                )
+                );
+
+                let mut picture_in_picture = world
+                    .get_resource_mut::<resources::PictureInPicture>()
+                    .unwrap();
+
+                picture_in_picture.perspective_view.set_perspective(
+                    59.0_f32.to_radians(),
+                    size.width as f32 / size.height as f32,
+                );
             }
             WindowEvent::KeyboardInput {
                 input:
@@ -558,16 +1243,37 @@ fn main() -> anyhow::Result<()> {
             } => {
                 let pressed = *state == ElementState::Pressed;
 
-                let mut keyboard_state = world
-                    .get_resource_mut::<resources::KeyboardState>()
-                    .unwrap();
+                let cutscene_playing = world
+                    .get_resource::<resources::CutscenePlayer>()
+                    .unwrap()
+                    .is_playing();
 
-                keyboard_state.handle(*key, pressed, &window);
+                world.resource_scope(|world, bindings: Mut<resources::KeyBindings>| {
+                    let mut keyboard_state = world
+                        .get_resource_mut::<resources::KeyboardState>()
+                        .unwrap();
+
+                    if cutscene_playing {
+                        keyboard_state.handle_during_cutscene(*key, pressed, &bindings, &window);
+                    } else {
+                        keyboard_state.handle(*key, pressed, &bindings, &window);
+                    }
+                });
             }
             WindowEvent::MouseInput { state, button, .. } => {
+                let pressed = *state == ElementState::Pressed;
+
+                let cutscene_playing = world
+                    .get_resource::<resources::CutscenePlayer>()
+                    .unwrap()
+                    .is_playing();
+
+                if cutscene_playing && pressed {
+                    return;
+                }
+
                 let mut mouse_state = world.get_resource_mut::<resources::MouseState>().unwrap();
 
-                let pressed = *state == ElementState::Pressed;
                 let position = mouse_state.position;
 
                 match button {
@@ -578,6 +1284,14 @@ fn main() -> anyhow::Result<()> {
                 }
             }
             WindowEvent::MouseWheel { delta, .. } => {
+                if world
+                    .get_resource::<resources::CutscenePlayer>()
+                    .unwrap()
+                    .is_playing()
+                {
+                    return;
+                }
+
                 let delta = match delta {
                     MouseScrollDelta::LineDelta(_, y) => -*y,
                     MouseScrollDelta::PixelDelta(winit::dpi::PhysicalPosition { y, .. }) => {
@@ -585,6 +1299,16 @@ fn main() -> anyhow::Result<()> {
                     }
                 };
 
+                let mut game_events = world.get_resource_mut::<resources::GameEvents>().unwrap();
+
+                // Scrolling up goes back through the log.
+                if game_events.hovered {
+                    if delta != 0.0 {
+                        game_events.scroll_by(-delta.signum() as i32);
+                    }
+                    return;
+                }
+
                 let mut orbit = world.get_resource_mut::<resources::Orbit>().unwrap();
 
                 orbit.zoom(delta);
@@ -593,10 +1317,14 @@ fn main() -> anyhow::Result<()> {
                 let keyboard_state = world.get_resource::<resources::KeyboardState>().unwrap();
                 let mouse_state = world.get_resource::<resources::MouseState>().unwrap();
 
+                let cutscene_player = world.get_resource::<resources::CutscenePlayer>().unwrap();
+
                 let position = Vec2::new(position.x as f32, position.y as f32);
                 let delta = position - mouse_state.position;
 
-                if mouse_state.middle_state.is_being_dragged().is_some() {
+                if cutscene_player.is_playing() {
+                    // Keep tracking the cursor but don't let it move the camera.
+                } else if mouse_state.middle_state.is_being_dragged().is_some() {
                     let mut orbit = world.get_resource_mut::<resources::Orbit>().unwrap();
                     orbit.rotate(delta);
                 } else if keyboard_state.shift {
@@ -617,13 +1345,341 @@ fn main() -> anyhow::Result<()> {
             _ => {}
         },
         Event::MainEventsCleared => {
+            if let Some(state) = &mut kill_cam_state {
+                run_kill_cam_tick(&mut world, &mut schedule, &mut state.playback);
+
+                let cutscene_playing = world
+                    .get_resource::<resources::CutscenePlayer>()
+                    .unwrap()
+                    .is_playing();
+
+                if state.playback.is_finished() || !cutscene_playing {
+                    let state = kill_cam_state.take().unwrap();
+                    return_to_live_play(&mut world, state);
+                }
+
+                window.request_redraw();
+                return;
+            }
+
+            let ready = {
+                let local_side = world
+                    .get_resource::<resources::Players>()
+                    .unwrap()
+                    .local_side();
+                let mut player_commands = world
+                    .get_resource_mut::<resources::PlayerCommands>()
+                    .unwrap();
+                let player_commands = &mut *player_commands;
+
+                match &mut lockstep {
+                    Some(lockstep) => match lockstep.advance(&mut player_commands.local) {
+                        Ok(Some([host, joiner])) => {
+                            // The host plays the friendly side.
+                            let host = host
+                                .into_iter()
+                                .map(|command| (components::Side::Friendly, command));
+                            let joiner = joiner
+                                .into_iter()
+                                .map(|command| (components::Side::Enemy, command));
+                            player_commands.to_apply.extend(host.chain(joiner));
+                            true
+                        }
+                        // Still waiting on the other player.
+                        Ok(None) => false,
+                        Err(error) => {
+                            log::error!("Lost the connection to the other player: {}", error);
+                            *control_flow = ControlFlow::Exit;
+                            false
+                        }
+                    },
+                    None => match &replay {
+                        // Anything the player does would throw the replay off.
+                        Some(replay) => {
+                            player_commands.local.clear();
+                            player_commands
+                                .to_apply
+                                .extend_from_slice(replay.commands_for_tick(tick));
+                            true
+                        }
+                        None => {
+                            let local = player_commands.local.drain(..);
+                            let local = local.map(|command| (local_side, command));
+                            player_commands.to_apply.extend(local);
+                            true
+                        }
+                    },
+                }
+            };
+
+            if !ready {
+                window.request_redraw();
+                return;
+            }
+
+            if let Some((_, recording)) = &mut recording {
+                let player_commands = world.get_resource::<resources::PlayerCommands>().unwrap();
+                recording.record(tick, &player_commands.to_apply);
+            }
+
+            if world
+                .get_resource::<resources::KillCam>()
+                .unwrap()
+                .should_snapshot(tick)
+            {
+                match save::save_world_to_string(&mut world) {
+                    Ok(snapshot) => world
+                        .get_resource_mut::<resources::KillCam>()
+                        .unwrap()
+                        .add_snapshot(tick, snapshot),
+                    Err(error) => log::error!("Taking a kill-cam snapshot failed: {}", error),
+                }
+            }
+
+            {
+                let commands = world
+                    .get_resource::<resources::PlayerCommands>()
+                    .unwrap()
+                    .to_apply
+                    .clone();
+
+                world
+                    .get_resource_mut::<resources::KillCam>()
+                    .unwrap()
+                    .record(tick, &commands);
+            }
+
             schedule.run(&mut world);
+            tick += 1;
+
+            let check_hashes = tick % world_hash::CHECK_INTERVAL == 0
+                && (lockstep.is_some() || replay.is_some() || recording.is_some());
+
+            if hash_log.is_some() || check_hashes {
+                let hashes = world_hash::WorldHashes::compute(&mut world);
+
+                if let Some(hash_log) = &mut hash_log {
+                    if let Err(error) = writeln!(hash_log, "{} {}", tick, hashes) {
+                        log::error!("Writing to the hash log failed: {}", error);
+                    }
+                }
+
+                if check_hashes {
+                    if let Some(lockstep) = &mut lockstep {
+                        if let Err(error) = lockstep.send_hashes(tick, hashes.0.clone()) {
+                            log::error!("Lost the connection to the other player: {}", error);
+                            *control_flow = ControlFlow::Exit;
+                        }
+
+                        for (tick, local, remote) in lockstep.take_mismatched_hashes() {
+                            if !divergence_reported {
+                                report_divergence(
+                                    tick,
+                                    "the other player's game",
+                                    &world_hash::WorldHashes(local),
+                                    &world_hash::WorldHashes(remote),
+                                );
+                                divergence_reported = true;
+                            }
+                        }
+                    }
+
+                    if let Some(expected) = replay
+                        .as_ref()
+                        .and_then(|replay| replay.hashes_for_tick(tick))
+                    {
+                        if !divergence_reported && *expected != hashes {
+                            report_divergence(tick, "the replay", &hashes, expected);
+                            divergence_reported = true;
+                        }
+                    }
+
+                    if let Some((_, recording)) = &mut recording {
+                        recording.record_hashes(tick, hashes);
+                    }
+                }
+            }
+
+            let kill_cam_request = world
+                .get_resource_mut::<resources::KillCam>()
+                .unwrap()
+                .take_request(tick);
+
+            if let Some(playback) = kill_cam_request {
+                kill_cam_state = start_kill_cam(&mut world, &mut schedule, playback);
+            }
+
+            let save_load_request = world
+                .get_resource_mut::<resources::SaveLoadRequest>()
+                .unwrap()
+                .0
+                .take();
+
+            if let Some(save_load) = save_load_request {
+                let path = match save_load {
+                    resources::SaveLoad::Save | resources::SaveLoad::Load => {
+                        std::path::PathBuf::from(QUICKSAVE_PATH)
+                    }
+                    resources::SaveLoad::Autosave { slot } => {
+                        std::path::PathBuf::from(format!("autosave_{}.ron", slot))
+                    }
+                };
+
+                let result = match save_load {
+                    resources::SaveLoad::Save | resources::SaveLoad::Autosave { .. } => {
+                        save::save_world(&mut world, &path)
+                    }
+                    resources::SaveLoad::Load => save::load_world(&mut world, &path),
+                };
+
+                match result {
+                    Ok(()) => {
+                        if let resources::SaveLoad::Load = save_load {
+                            world
+                                .get_resource_mut::<resources::KillCam>()
+                                .unwrap()
+                                .clear();
+                        }
+
+                        if let resources::SaveLoad::Autosave { .. } = save_load {
+                            let clock = world.get_resource::<resources::SimClock>().unwrap();
+                            log::info!(
+                                "Autosaved to '{}' at {}",
+                                path.display(),
+                                MissionTime(clock.time)
+                            );
+                        }
+                    }
+                    Err(error) => {
+                        log::error!("{:?} to '{}' failed: {}", save_load, path.display(), error);
+
+                        // Otherwise a load that fails looks like the key did nothing.
+                        if let resources::SaveLoad::Load = save_load {
+                            let real_time = world.get_resource::<resources::RealTime>().unwrap().0;
+                            world
+                                .get_resource_mut::<resources::Notifications>()
+                                .unwrap()
+                                .push(format!("Loading failed: {}", error), real_time);
+                        }
+                    }
+                }
+            }
+
+            let play_error_blip = std::mem::take(
+                &mut world
+                    .get_resource_mut::<resources::OrderFeedback>()
+                    .unwrap()
+                    .play_error_blip,
+            );
+
+            let mut mouse_state = world.get_resource_mut::<resources::MouseState>().unwrap();
+
+            if std::mem::take(&mut mouse_state.moved_by_keyboard) {
+                let position = mouse_state.position;
+                let position = winit::dpi::PhysicalPosition::new(position.x, position.y);
+
+                if let Err(error) = window.set_cursor_position(position) {
+                    log::warn!("Moving the cursor failed: {}", error);
+                }
+            }
+
+            if let Some(engine_hum_player) = &engine_hum_player {
+                engine_hum_player.update(world.get_resource().unwrap());
+
+                if play_error_blip {
+                    engine_hum_player.play_error_blip();
+                }
+            }
 
             window.request_redraw();
         }
         Event::RedrawRequested(_) => {
-            if let Ok(frame) = surface.get_current_frame() {
+            let dimensions = world.get_resource::<resources::Dimensions>().unwrap();
+
+            if dimensions.width == 0 || dimensions.height == 0 {
+                return;
+            }
+
+            if let Some((path, golden_image_tick, bless)) = &golden_image_check {
+                if tick < *golden_image_tick {
+                    return;
+                }
+
+                let (width, height) = (dimensions.width, dimensions.height);
+                let gpu_interface = world.get_resource::<resources::GpuInterface>().unwrap();
+
+                let result = golden_image::capture_frame(
+                    &gpu_interface.device,
+                    &gpu_interface.queue,
+                    display_format,
+                    width,
+                    height,
+                    |frame, encoder| {
+                        rendering::passes::run_render_passes(
+                            frame,
+                            encoder,
+                            &resizables,
+                            &pipelines,
+                            &world,
+                            &star_system,
+                            &tonemapper,
+                            &constants,
+                        );
+                    },
+                )
+                .and_then(|pixels| {
+                    golden_image::check_against_golden_image(&pixels, width, height, path, *bless)
+                });
+
+                match result {
+                    Ok(()) if *bless => log::info!("Blessed '{}'", path.display()),
+                    Ok(()) => log::info!("Matched '{}'", path.display()),
+                    Err(error) => {
+                        log::error!(
+                            "Golden image check against '{}' failed: {}",
+                            path.display(),
+                            error
+                        );
+                        std::process::exit(1);
+                    }
+                }
+
+                *control_flow = ControlFlow::Exit;
+                return;
+            }
+
+            let frame = match surface.get_current_frame() {
+                Ok(frame) => Some(frame),
+                // Either the window was resized without us getting an event or the surface
+                // was lost, e.g. after minimizing and restoring on some platforms.
+                Err(wgpu::SurfaceError::Outdated) | Err(wgpu::SurfaceError::Lost) => {
+                    resizables = recreate_resizables(&world, &surface, display_format, &resources);
+                    None
+                }
+                Err(wgpu::SurfaceError::Timeout) => {
+                    log::warn!("Timed out while waiting for the next swapchain frame");
+                    None
+                }
+                Err(wgpu::SurfaceError::OutOfMemory) => {
+                    log::error!(
+                        "Ran out of memory while getting the next swapchain frame. \
+                        The GPU device has most likely been lost, so exiting."
+                    );
+                    *control_flow = ControlFlow::Exit;
+                    None
+                }
+            };
+
+            if let Some(frame) = frame {
                 let gpu_interface = world.get_resource::<resources::GpuInterface>().unwrap();
+                let lighting = world.get_resource::<resources::Lighting>().unwrap();
+
+                // It's only 6 vertices so it's not worth checking whether the sun moved.
+                gpu_interface.queue.write_buffer(
+                    &star_system.sun_vertices,
+                    0,
+                    bytemuck::cast_slice(&sun_vertices(&lighting.current)),
+                );
 
                 let mut encoder =
                     gpu_interface
@@ -637,7 +1693,7 @@ fn main() -> anyhow::Result<()> {
                     .texture
                     .create_view(&wgpu::TextureViewDescriptor::default());
 
-                rendering::passes::run_render_passes(
+                let render_stats = rendering::passes::run_render_passes(
                     &frame,
                     &mut encoder,
                     &resizables,
@@ -649,12 +1705,213 @@ fn main() -> anyhow::Result<()> {
                 );
 
                 gpu_interface.queue.submit(Some(encoder.finish()));
+                world.insert_resource(render_stats);
             }
         }
         _ => {}
     })
 }
 
+fn recreate_resizables(
+    world: &bevy_ecs::world::World,
+    surface: &wgpu::Surface,
+    display_format: wgpu::TextureFormat,
+    resources: &rendering::Resources,
+) -> rendering::Resizables {
+    let dimensions = world.get_resource::<resources::Dimensions>().unwrap();
+    let gpu_interface = world.get_resource::<resources::GpuInterface>().unwrap();
+
+    rendering::Resizables::new(
+        dimensions.width,
+        dimensions.height,
+        display_format,
+        &gpu_interface.device,
+        surface,
+        resources,
+    )
+}
+
+// Always contains both suns so that the buffer stays the same size. A missing second sun has no
+// size.
+fn sun_vertices(lighting: &resources::LightingState) -> Vec<ColouredVertex> {
+    let second_sun_scale = if lighting.second_sun_colour == Vec3::zero() {
+        0.0
+    } else {
+        150.0
+    };
+
+    background::star_points(lighting.sun_dir, 250.0, lighting.sun_colour)
+        .chain(background::star_points(
+            lighting.second_sun_dir,
+            second_sun_scale,
+            lighting.second_sun_colour,
+        ))
+        .collect()
+}
+
+// The live game is put aside while the kill-cam plays, along with where the camera was and the
+// messages that the replay would otherwise repeat.
+struct KillCamState {
+    playback: resources::KillCamPlayback,
+    live: String,
+    camera_center: Vec3,
+    orbit: resources::Orbit,
+    notifications: resources::Notifications,
+    game_events: resources::GameEvents,
+    attack_alert: resources::AttackAlert,
+}
+
+fn start_kill_cam(
+    world: &mut bevy_ecs::world::World,
+    schedule: &mut bevy_ecs::schedule::Schedule,
+    mut playback: resources::KillCamPlayback,
+) -> Option<KillCamState> {
+    let live = match save::save_world_to_string(world) {
+        Ok(live) => live,
+        Err(error) => {
+            log::error!("Saving the game before the kill-cam failed: {}", error);
+            return None;
+        }
+    };
+
+    let snapshot = std::mem::take(&mut playback.snapshot);
+
+    let mut state = KillCamState {
+        playback,
+        live,
+        camera_center: world.get_resource::<resources::Camera>().unwrap().center,
+        orbit: world.get_resource::<resources::Orbit>().unwrap().clone(),
+        notifications: world
+            .get_resource::<resources::Notifications>()
+            .unwrap()
+            .clone(),
+        game_events: world
+            .get_resource::<resources::GameEvents>()
+            .unwrap()
+            .clone(),
+        attack_alert: world
+            .get_resource::<resources::AttackAlert>()
+            .unwrap()
+            .clone(),
+    };
+
+    if let Err(error) = save::load_world_from_str(world, &snapshot) {
+        log::error!("Loading the kill-cam snapshot failed: {}", error);
+        return_to_live_play(world, state);
+        return None;
+    }
+
+    // Catch up to the start of the replay without showing anything.
+    while state.playback.tick < state.playback.start_tick {
+        run_kill_cam_tick(world, schedule, &mut state.playback);
+    }
+
+    world
+        .get_resource_mut::<resources::CutscenePlayer>()
+        .unwrap()
+        .play(state.playback.cutscene());
+
+    Some(state)
+}
+
+fn run_kill_cam_tick(
+    world: &mut bevy_ecs::world::World,
+    schedule: &mut bevy_ecs::schedule::Schedule,
+    playback: &mut resources::KillCamPlayback,
+) {
+    {
+        let mut player_commands = world
+            .get_resource_mut::<resources::PlayerCommands>()
+            .unwrap();
+
+        // Nothing the player does can change what already happened.
+        player_commands.local.clear();
+        player_commands
+            .to_apply
+            .extend_from_slice(playback.commands_for_tick(playback.tick));
+    }
+
+    schedule.run(world);
+    playback.tick += 1;
+
+    // Autosaves would save the past.
+    world
+        .get_resource_mut::<resources::SaveLoadRequest>()
+        .unwrap()
+        .0 = None;
+}
+
+fn return_to_live_play(world: &mut bevy_ecs::world::World, state: KillCamState) {
+    if let Err(error) = save::load_world_from_str(world, &state.live) {
+        log::error!("Returning to the game after the kill-cam failed: {}", error);
+    }
+
+    world
+        .get_resource_mut::<resources::CutscenePlayer>()
+        .unwrap()
+        .skip();
+    // The replay will have offered itself again.
+    world
+        .get_resource_mut::<resources::KillCam>()
+        .unwrap()
+        .dismiss_offer();
+    world
+        .get_resource_mut::<resources::Camera>()
+        .unwrap()
+        .center = state.camera_center;
+    world.insert_resource(state.orbit);
+    world
+        .get_resource_mut::<resources::Notifications>()
+        .unwrap()
+        .restore(state.notifications);
+    world.insert_resource(state.game_events);
+    world.insert_resource(state.attack_alert);
+}
+
+fn report_divergence(
+    tick: u32,
+    against: &str,
+    hashes: &world_hash::WorldHashes,
+    expected: &world_hash::WorldHashes,
+) {
+    log::error!(
+        "The game diverged from {} by tick {}, in: {}",
+        against,
+        tick,
+        hashes.diverged_subsystems(expected).join(", ")
+    );
+    log::error!("Ours:   {}", hashes);
+    log::error!("Theirs: {}", expected);
+}
+
+// Sweep from the enemy fleet back to the player's one.
+fn intro_cutscene() -> resources::Cutscene {
+    let keyframe = |time, center, longitude, latitude, distance| resources::CameraKeyframe {
+        time,
+        center,
+        longitude,
+        latitude,
+        distance,
+    };
+
+    let default_orbit = resources::Orbit::default();
+
+    resources::Cutscene::default()
+        .with_keyframe(keyframe(0.0, Vec3::new(500.0, 0.0, 0.0), 1.2, -1.0, 200.0))
+        .with_keyframe(keyframe(4.0, Vec3::new(250.0, 0.0, 0.0), 1.0, -0.3, 250.0))
+        .with_keyframe(keyframe(8.0, Vec3::zero(), 1.1, 0.5, 120.0))
+        .with_keyframe(keyframe(
+            11.0,
+            Vec3::zero(),
+            default_orbit.longitude,
+            default_orbit.latitude,
+            10.0,
+        ))
+        .with_subtitle(0.5, 4.0, "An enemy fleet has entered the system.")
+        .with_subtitle(4.5, 8.0, "Mine the asteroids, build up your forces")
+        .with_subtitle(8.0, 10.5, "and drive them out.")
+}
+
 fn circle_vertices<const VERTICES: usize>() -> [Vec2; VERTICES] {
     let mut verts = [Default::default(); VERTICES];
 
@@ -0,0 +1,264 @@
+use bevy_ecs::prelude::IntoSystem;
+use bevy_ecs::system::{BoxedSystem, CommandQueue, Commands};
+use bevy_ecs::world::World;
+use components_and_resources::{
+    assets::Assets,
+    balance::{BalanceReport, Matchup},
+    components::{self, Command, MoveType},
+    model::load_model_geometry,
+    mods::ModFiles,
+    resources::{self, Models, SimClock},
+};
+use rand::{Rng, SeedableRng};
+use ultraviolet::Vec3;
+
+// How far from the middle of the arena each fleet starts.
+const START_DISTANCE: f32 = 200.0;
+const START_SPREAD: f32 = 50.0;
+
+// Fights out a matchup once per seed with nothing but the simulation systems, then prints how
+// each side fared.
+pub fn run(
+    matchup: Matchup,
+    runs: u32,
+    time_limit: f32,
+    mod_files: &ModFiles,
+    assets: &Assets,
+) -> anyhow::Result<()> {
+    // Only the geometry is needed for collisions, so the textures are never loaded.
+    let models = resources::Models::ARRAY
+        .iter()
+        .map(|&model_id| {
+            load_model_geometry(
+                &mod_files.read_model(model_id, assets)?,
+                &mut Vec::new(),
+                &mut Vec::new(),
+                &mut Vec::new(),
+            )
+        })
+        .collect::<anyhow::Result<Vec<_>>>()?;
+
+    let mut models = Some(Models::new(models));
+    let task_pool = bevy_tasks::TaskPool::new();
+    let mut report = BalanceReport::new(matchup);
+
+    for seed in 0..runs as u64 {
+        let mut world = World::default();
+        world.insert_resource(models.take().unwrap());
+        world.insert_resource(task_pool.clone());
+
+        let survivors = run_engagement(&mut world, report.matchup(), seed, time_limit);
+        report.record(survivors);
+
+        models = world.remove_resource::<Models>();
+    }
+
+    println!("{}", report);
+
+    Ok(())
+}
+
+fn run_engagement(world: &mut World, matchup: &Matchup, seed: u64, time_limit: f32) -> [u32; 2] {
+    let mut rng = resources::SmallRng::seed_from_u64(seed);
+
+    let centers = [
+        Vec3::new(-START_DISTANCE, 0.0, 0.0),
+        Vec3::new(START_DISTANCE, 0.0, 0.0),
+    ];
+
+    let mut queue = CommandQueue::default();
+    let mut fleets = [Vec::new(), Vec::new()];
+
+    {
+        let mut commands = Commands::new(&mut queue, world);
+
+        for side in 0..2 {
+            for &(ship_type, count) in &matchup.sides[side] {
+                for _ in 0..count {
+                    let offset = Vec3::new(
+                        rng.gen_range(-START_SPREAD..START_SPREAD),
+                        rng.gen_range(-START_SPREAD..START_SPREAD),
+                        rng.gen_range(-START_SPREAD..START_SPREAD),
+                    );
+                    let position = centers[side] + offset;
+
+                    let entity = if side == 0 {
+                        systems::spawn_ship::<components::Friendly>(
+                            ship_type,
+                            position,
+                            &mut commands,
+                        )
+                    } else {
+                        systems::spawn_ship::<components::Enemy>(ship_type, position, &mut commands)
+                    };
+
+                    fleets[side].push(entity);
+                }
+            }
+        }
+    }
+
+    queue.apply(world);
+
+    // Each fleet attack-moves through the other's starting point.
+    for side in 0..2 {
+        for &entity in &fleets[side] {
+            if let Some(mut command_queue) = world.get_mut::<components::CommandQueue>(entity) {
+                command_queue.0.push_back(Command::MoveTo {
+                    point: centers[1 - side],
+                    ty: MoveType::Attack,
+                    speed_cap: None,
+                });
+            }
+        }
+    }
+
+    world.insert_resource(SimClock::new(0.0));
+    world.insert_resource(rng);
+    world.insert_resource(resources::TopLevelAccelerationStructure::default());
+    world.insert_resource(resources::PerSide::<resources::GlobalRulesOfEngagement>::default());
+    world.insert_resource(resources::PerSide::<resources::TechTree>::default());
+    world.insert_resource(resources::PerSide::<resources::GlobalMinerals>::default());
+    world.insert_resource(resources::PerSide::<resources::GlobalGas>::default());
+    world.insert_resource(resources::HeatZones(vec![(
+        Vec3::unit_y(),
+        components::Heat::SUN_HEAT_PER_SECOND,
+    )]));
+    world.insert_resource(resources::KillCam::default());
+    world.insert_resource(resources::ScriptHost::default());
+    world.insert_resource(resources::GameEvents::default());
+    world.insert_resource(resources::DamageEvents::default());
+
+    let mut schedule = FixedSchedule::new(world);
+
+    let survivors = |world: &World| {
+        let alive = |fleet: &Vec<_>| {
+            fleet
+                .iter()
+                .filter(|&&entity| world.get_entity(entity).is_some())
+                .count() as u32
+        };
+        [alive(&fleets[0]), alive(&fleets[1])]
+    };
+
+    loop {
+        schedule.run(world);
+
+        let survivors = survivors(world);
+        let time = world.get_resource::<SimClock>().unwrap().time;
+
+        if survivors.contains(&0) || time >= time_limit {
+            return survivors;
+        }
+    }
+}
+
+// Boxes each system so that a stage can be listed in one `vec!`.
+macro_rules! stage {
+    ($($system:expr),* $(,)?) => {
+        vec![$(Box::new($system.system()) as BoxedSystem),*]
+    };
+}
+
+// The parts of the main schedule that move ships and resolve combat. Bevy picks the order of
+// systems in a stage differently each time the game starts, so they're run in a fixed order
+// instead to keep every seed giving the same result.
+struct FixedSchedule {
+    stages: Vec<Vec<BoxedSystem>>,
+    // How many archetypes the systems have been told about.
+    archetypes_seen: usize,
+}
+
+impl FixedSchedule {
+    fn new(world: &mut World) -> Self {
+        let mut stages = vec![
+            stage![
+                systems::kill_temporary,
+                systems::update_projectiles,
+                systems::guide_missiles,
+                systems::apply_staging_velocity,
+                systems::apply_velocity,
+                systems::spawn_projectile_from_ships::<components::Friendly>,
+                systems::spawn_projectile_from_ships::<components::Enemy>,
+                systems::fire_broadsides::<components::Friendly, components::Enemy>,
+                systems::fire_broadsides::<components::Enemy, components::Friendly>,
+                systems::fire_beams::<components::Friendly, components::Enemy>,
+                systems::fire_beams::<components::Enemy, components::Friendly>,
+                systems::launch_missiles::<components::Friendly, components::Enemy>,
+                systems::launch_missiles::<components::Enemy, components::Friendly>,
+                systems::launch_fighters::<components::Friendly, components::Enemy>,
+                systems::launch_fighters::<components::Enemy, components::Friendly>,
+                systems::recall_damaged_fighters::<components::Friendly>,
+                systems::recall_damaged_fighters::<components::Enemy>,
+                systems::repair_carried_ships,
+                systems::dock_idle_ships::<components::Friendly, components::Enemy>,
+                systems::dock_idle_ships::<components::Enemy, components::Friendly>,
+                systems::recharge_shields,
+                systems::vent_heat,
+            ],
+            stage![
+                systems::set_rotation_from_velocity,
+                systems::turn_to_face,
+                systems::update_ship_rotation_matrix,
+                systems::set_world_space_bounding_box,
+                systems::update_tlas,
+                systems::choose_enemy_target::<components::Friendly, components::Enemy>,
+                systems::choose_enemy_target::<components::Enemy, components::Friendly>,
+                systems::pull_back_leashed_ships,
+                systems::ram_ships,
+                systems::intercept_threats::<components::Friendly, components::Enemy>,
+                systems::intercept_threats::<components::Enemy, components::Friendly>,
+                systems::run_avoidance,
+                systems::run_persuit,
+                systems::run_evasion,
+                systems::retreat_damaged_ships::<components::Friendly>,
+                systems::retreat_damaged_ships::<components::Enemy>,
+                systems::rejoin_after_repair,
+                systems::collide_projectiles::<components::Friendly>,
+                systems::collide_projectiles::<components::Enemy>,
+                systems::detonate_missiles::<components::Friendly>,
+                systems::detonate_missiles::<components::Enemy>,
+                systems::detect_incoming_fire::<components::Friendly>,
+                systems::detect_incoming_fire::<components::Enemy>,
+            ],
+            stage![
+                systems::handle_destruction,
+                systems::clear_damage_events,
+                systems::advance_sim_clock
+            ],
+        ];
+
+        for system in stages.iter_mut().flatten() {
+            system.initialize(world);
+        }
+
+        Self {
+            stages,
+            archetypes_seen: 0,
+        }
+    }
+
+    // Like a bevy stage, commands are only applied once every system in the stage has run.
+    fn run(&mut self, world: &mut World) {
+        for index in 0..self.stages.len() {
+            // Queries only match archetypes that they've been shown, which is normally up to the
+            // executor.
+            for archetype in world.archetypes().iter().skip(self.archetypes_seen) {
+                for system in self.stages.iter_mut().flatten() {
+                    system.new_archetype(archetype);
+                }
+            }
+            self.archetypes_seen = world.archetypes().len();
+
+            let stage = &mut self.stages[index];
+
+            for system in stage.iter_mut() {
+                system.run((), world);
+            }
+
+            for system in stage.iter_mut() {
+                system.apply_buffers(world);
+            }
+        }
+    }
+}